@@ -0,0 +1,73 @@
+// SPDX-FileCopyrightText: 2025 Jason Pena <jasonpena@awkless.com>
+// SPDX-License-Identifier: MIT
+
+//! Embed build information into OCD for `ocd --version --verbose`.
+//!
+//! Commit of OCD itself is only known when building from a Git checkout, and is left out of
+//! builds from crate tarballs. Build date honors `$SOURCE_DATE_EPOCH` for reproducible builds.
+
+use std::{
+    path::Path,
+    process::Command,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+fn main() {
+    println!("cargo:rerun-if-env-changed=SOURCE_DATE_EPOCH");
+    watch_git_head();
+
+    let commit = Command::new("git")
+        .args(["rev-parse", "--short=12", "HEAD"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string());
+    if let Some(commit) = commit {
+        println!("cargo:rustc-env=OCD_GIT_COMMIT={commit}");
+    }
+
+    let epoch = std::env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|epoch| epoch.parse::<u64>().ok())
+        .unwrap_or_else(|| {
+            SystemTime::now().duration_since(UNIX_EPOCH).map(|time| time.as_secs()).unwrap_or(0)
+        });
+    println!("cargo:rustc-env=OCD_BUILD_DATE={}", civil_date(epoch));
+}
+
+// INVARIANT: Only rebuild when the commit changes, not whenever any tracked file changes.
+fn watch_git_head() {
+    let head = Path::new(".git/HEAD");
+    if !head.exists() {
+        return;
+    }
+
+    let mut watched = vec![head.to_path_buf(), Path::new(".git/packed-refs").to_path_buf()];
+    if let Some(reference) = std::fs::read_to_string(head)
+        .ok()
+        .and_then(|data| data.strip_prefix("ref: ").map(|name| name.trim().to_string()))
+    {
+        watched.push(Path::new(".git").join(reference));
+    }
+
+    // INVARIANT: Watching missing files would rerun build script on every build.
+    for path in watched.into_iter().filter(|path| path.exists()) {
+        println!("cargo:rerun-if-changed={}", path.display());
+    }
+}
+
+/// Convert seconds since Unix epoch into "YYYY-MM-DD" of the proleptic Gregorian calendar.
+fn civil_date(epoch: u64) -> String {
+    let days = i64::try_from(epoch / 86_400).unwrap_or(0) + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    format!("{year:04}-{month:02}-{day:02}")
+}
@@ -0,0 +1,323 @@
+# SPDX-FileCopyrightText: 2025 Jason Pena <jasonpena@awkless.com>
+# SPDX-License-Identifier: MIT
+
+# English messages of the OCD command set.
+#
+# Every other catalog falls back to this one. Message IDs are grouped by the command that shows
+# them. Placeables like { $name } are filled in by OCD, and must be kept as they are.
+
+## Shared
+
+node-not-defined = Node { $name } not defined
+remove-failure = Failed to remove { $path }
+write-failure = Failed to write { $path }
+read-targets-failure = Failed to read targets
+read-targets-empty = No targets were given through standard input
+command-missing = No command was given, see "ocd --help" for the command set
+config-dir-label = Configuration directory
+data-dir-label = Data directory
+cluster-name = Cluster: { $name }
+cluster-description = Description: { $description }
+cluster-maintainer = Maintainer: { $maintainer }
+
+## Running as root
+
+root-refused = Refusing to run as root, because files would be deployed into and owned by the home directory of root. Run as a regular user, or pass "--allow-root" if this is really intended
+root-warning = Running as root! Every file written by OCD will be owned by root
+root-sudo-home = Invoked through sudo by { $user }, running as them with home directory { $home }
+root-drop-failure = Failed to drop privileges of root to { $user }
+
+## Store layout
+
+store-layout-migrated = Moved { $count } node repositories to the store layout of root
+store-layout-pending = Node repositories do not follow the store layout of root yet, run a command that modifies the cluster, e.g., "ocd deploy", to move them
+
+## Host profiles
+
+host-profile-picked = Use host profile { $profile }, which selects { $count } of { $total } nodes
+host-profile-skipped = Skip { $name }, which host profile { $profile } does not select
+
+## ocd clone
+
+clone-root-failure = Root clone failure, clearing broken cluster
+clone-resume = Root already cloned, resume cloning nodes
+clone-resume-hint = Run "ocd clone { $url }" again to resume cloning the remaining nodes
+clone-left-out = { $count } nodes were left out, run "ocd clone { $url }" again to clone them
+
+## ocd init
+
+init-no-root = No root entry to initialize! Define { $path } first!
+init-root-missing = Root may not have been properly initialized
+init-no-node = No node entry to initialize! Define { $path } first!
+
+## ocd add
+
+add-no-path = No repository to adopt at { $path }
+add-no-name = Cannot name node after { $path }, give it a name with --name
+add-exists = Node { $name } already defined in cluster
+add-no-origin = Repository at { $path } has no origin remote, node will have no URL to clone from
+add-invalid = Cluster definition would not load with new node, nothing was adopted
+add-done = Add node { $name } at { $path }
+
+## ocd deploy
+
+deploy-overwrite-modified = Deployment would overwrite files modified since their last deployment:
+deploy-overwrite-prompt = Do you want to overwrite them? [y/n]
+deploy-cancelled = Deployment cancelled, nothing was overwritten
+deploy-profile-undefined = No target node defines sparse profile { $profile }
+deploy-entry-failure = Failed to deploy { $name }
+deploy-summary = Deployment summary: { $changed } changed, { $unchanged } unchanged, { $skipped } skipped, { $failed } failed
+deploy-check-drift = { $name }: would change
+deploy-check-failure = Deployment would change { $count } entries
+deploy-preflight-failure = Deployment would not finish, nothing was deployed
+deploy-no-lockfile = No node is locked, run "ocd freeze" first
+deploy-unlocked = { $name } has no locked commit, deploy it as it is
+deploy-pick-prompt = Pick nodes to deploy:
+undeploy-pick-prompt = Pick nodes to undeploy:
+notify-failure = Failed to send notification: { $error }
+skip-normal = { $name }: skipped: normal repository
+skip-mirror = { $name }: skipped: mirror repository
+skip-normal-reason = Normal nodes only need to exist in the repository store, and are never deployed
+skip-normal-hint = Run "ocd convert --to bare-alias <node>" to deploy a normal node to a working directory alias
+
+## ocd rm
+
+remove-all-nodes = Removing every node will leave only root in repository store: { $targets }
+remove-all-nodes-prompt = Do you want to remove every node? [y/n]
+remove-root = Removing root will nuke your entire cluster
+remove-root-prompt = Do you want to send your cluster to the gallows? [y/n]
+remove-root-name-prompt = Type the name of the cluster, { $name }, to send it to the gallows:
+remove-root-name-mismatch = Cluster name does not match, nothing removed
+remove-required = Cannot remove { $name }, because it is still needed by { $dependents }, use "--cascade" to remove them too
+remove-cascade = Remove { $dependents } along with the nodes they depend on
+nuke-node-missing = Node { $name } not found in repository store
+nuke-config-dir = Configuration directory removed
+nuke-data-dir = Data directory removed
+nuke-transient-dir = Transient directory { $path } removed
+
+## ocd uninstall
+
+uninstall-warning = Uninstalling will undeploy every node, and remove the repository store, the configuration directory, and every other directory of OCD
+uninstall-keep = Deployed files of { $targets } stay in place
+uninstall-prompt = Do you want to uninstall OCD? [y/n]
+uninstall-aborted = Uninstall aborted, nothing removed
+uninstall-clean = OCD left nothing behind
+uninstall-remaining = OCD left { $count } deployed files in place:
+
+## ocd repair
+
+repair-origin-diverged = Origin { $local } of { $name } diverged from { $url }
+repair-origin-prompt = Repair origin of { $name }?
+repair-rewrite-origin = Rewrite origin to match cluster definition
+repair-adopt-origin = Adopt origin into cluster definition
+repair-skip-origin = Skip
+repair-origin-skipped = Skip origin repair of { $name }
+
+## ocd excludes
+
+excludes-root = Root has no exclusion rules to edit, skipping it
+excludes-edited = Edit exclusion rules of { $name }
+
+## ocd migrate-remote
+
+migrate-skip = Skip migration of { $url }: { $error }
+migrate-skip-to = Skip migration of { $url } to { $migrated }: { $error }
+
+## ocd convert
+
+convert-bad-kind = Cannot convert to { $kind }, use normal or bare-alias
+convert-bad-node = Node { $name } is { $kind }, only normal and bare-alias nodes can be converted
+convert-external = Node { $name } lives in an external repository, which OCD does not own and cannot convert
+
+## ocd rename
+
+rename-done = Renamed { $old } to { $new }, updating dependencies of { $count } nodes
+
+## ocd pick
+
+pick-git-needs-command = Git action needs a Git command, e.g., "ocd pick git -- status"
+pick-git-args-unused = Git arguments are only used by the git action
+pick-no-nodes = Cluster has no nodes to pick from
+pick-node-prompt = Pick node:
+pick-nodes-prompt = Pick nodes:
+pick-none = No nodes were picked
+pick-state-missing = not cloned
+
+## ocd status
+
+status-no-untracked = No untracked files next to tracked files of { $name }
+
+## ocd diff
+
+diff-header = ==> { $name } <==
+diff-not-bare-alias = Skip { $name }, because only root and bare-alias nodes can be diffed
+diff-clean = No deployed file drifted from its repository
+diff-summary = { $files } drifted files across { $drifted } of { $total } entries
+
+## ocd clean
+
+clean-nothing = No untracked files to clean from { $name }
+clean-dry-run = Nothing removed, run again with "--force" to remove the files listed above
+clean-prompt = Remove { $count } untracked files?
+clean-aborted = Clean aborted, nothing removed
+clean-removed = Removed { $count } untracked files from { $name }
+
+## ocd prune-artifacts
+
+prune-artifacts-nothing = No files left behind by removed nodes
+prune-artifacts-node = { $name } left { $count } files behind:
+prune-artifacts-dry-run = Nothing removed, run again with "--force" to remove the files listed above
+prune-artifacts-prompt = Remove { $count } files left behind by removed nodes?
+prune-artifacts-aborted = Pruning aborted, nothing removed
+prune-artifacts-removed = Removed { $count } files left behind by removed nodes
+
+## ocd gc
+
+gc-inline = Skip { $name }, because inline nodes live inside root
+gc-external = Skip { $name }, because external repositories are not managed by OCD
+gc-no-shallow-since = Only pack { $name }, because it has no "shallow_since" setting
+gc-pruned = Pruned history of { $names }. Git commands that need full history, e.g., blame, bisect, or log past the cut-off date, show partial results or fail, and pruned nodes cannot be converted until "ocd <node> fetch --unshallow" restores their history
+
+## ocd pull
+
+pull-inline = Skip { $name }, because inline nodes are pulled along with root
+pull-missing = Skip { $name }, because it is missing from the repository store
+pull-diverged-prompt = { $name } diverged from its upstream, how should it be pulled?
+pull-entry = { $name }: { $outcome }
+pull-entry-failure = Failed to pull { $name }
+pull-summary = Pull summary: { $updated } updated, { $unchanged } unchanged, { $skipped } skipped, { $diverged } diverged, { $failed } failed
+pull-diverged = Skipped { $names }, because they diverged from their upstream. Pull them again with "--strategy rebase" or "--strategy merge" to update them
+
+## ocd fetch-config
+
+fetch-config-no-upstream = Root has no upstream to fetch the cluster definition from
+fetch-config-invalid = Cluster definition at the upstream of root is invalid
+fetch-config-up-to-date = Cluster definition is up to date with the upstream of root
+fetch-config-diverged = Root diverged from its upstream, pull it with "ocd pull --strategy rebase root" first
+fetch-config-kind-changed = Skip { $name }, because its deployment kind changed, use "ocd convert" on it
+
+## ocd sync
+
+sync-daemon-running = Another sync daemon is running already, see "ocd status --sync"
+sync-daemon-start = Sync daemon { $pid } reconciles cluster every { $interval } seconds, and on configuration changes
+sync-reconcile = Reconcile cluster, triggered by { $trigger }
+sync-reconcile-failure = Failed to reconcile cluster, trying again on next trigger: { $error }
+sync-status-running = Sync daemon { $pid } is running, reconciling every { $interval } seconds
+sync-status-starting = Sync daemon is starting
+sync-status-stopped = No sync daemon is running
+sync-status-never = Sync daemon has not reconciled cluster yet
+sync-status-succeeded = Last reconcile { $ago } seconds ago, triggered by { $trigger }, succeeded
+sync-status-failed = Last reconcile { $ago } seconds ago, triggered by { $trigger }, failed: { $error }
+
+## ocd freeze
+
+freeze-inline = Skip { $name }, because inline nodes live inside root
+freeze-missing = Skip { $name }, because it is missing from the repository store
+freeze-unborn = Skip { $name }, because it has no commits yet
+freeze-committed = Locked { $names } to their current commits, and committed lockfile to root
+
+## ocd thaw
+
+thaw-committed = Unlocked { $names }, and committed lockfile to root
+
+## ocd rollback
+
+rollback-missing = Cannot roll back { $name }, because it is missing from the repository store
+rollback-done = Rolled back { $name } to { $commit }, detaching its HEAD. Run "ocd { $name } checkout <branch>" to put it back on a branch
+
+## ocd undo
+
+undo-nothing = Nothing to undo, because no deploy, undeploy, or removal was recorded
+undo-done = Undid "ocd { $command }", bringing back { $count } entries as they were deployed before
+undo-list = Last transaction is "ocd { $command }" from { $ago } seconds ago, undoing it would:
+transaction-record-failure = Failed to record transaction: { $error }
+
+## ocd why
+
+why-untargeted = { $name } is deployed when targeted itself, through "--all", or through any of these nodes:
+why-deployed = { $name } would be deployed, because it is:
+why-not-deployed = { $name } would not be deployed, because no target matches it or depends on it
+why-matched = matched by target pattern { $pattern }
+why-dependency = a dependency of { $target } through { $chain }
+why-no-dependents = no node depends on it
+why-only = Dependencies are left alone when deploying with "--only"
+why-constraints = Deploying it amounts to:
+why-kind-normal = cloning it into the repository store, because normal nodes have no working directory alias
+why-kind = { $kind } deployment to { $path }
+why-profile = sparse profile { $profile } replacing its exclusion rules
+why-profile-undefined = its own exclusion rules, because it does not define sparse profile { $profile }
+why-included = only including files matching { $rules }
+why-excluded = excluding files matching { $rules }
+why-no-excluded = excluding no files
+
+## ocd validate
+
+validate-valid = Cluster definition is valid
+validate-staged-valid = Staged cluster definition is valid
+validate-staged-no-root = Root configuration file "root.toml" is not staged in root
+
+## ocd doctor
+
+doctor-pass = pass
+doctor-warn = warn
+doctor-fail = fail
+doctor-fix = fix: { $fix }
+doctor-summary = { $passed } passed, { $warned } warned, { $failed } failed
+doctor-failure = Setup has { $count } problems that break other commands
+doctor-definition-valid = Cluster definition is valid, defining { $count } nodes
+doctor-definition-invalid = Cluster definition is invalid: { $error }
+doctor-definition-fix = Run "ocd validate", and fix every problem it reports
+doctor-hooks-valid = Hook configuration is valid
+doctor-hooks-invalid = Hook configuration is invalid: { $error }
+doctor-hooks-fix = Fix "hooks.toml" in the configuration directory
+doctor-node-files-valid = Every node configuration file ends in ".toml"
+doctor-node-file-ignored = { $path } is ignored, because node configuration files must end in ".toml"
+doctor-node-file-fix = Rename it to end in ".toml", or move it out of the nodes directory
+doctor-git-found = Git binary is reachable through PATH
+doctor-git-missing = Git binary is not reachable through PATH
+doctor-git-fix = Install Git, which deploying, pulling, and other commands need
+doctor-root-missing = Root is missing from the repository store
+doctor-root-fix = Run "ocd clone <url>" to clone the cluster, or "ocd init" to start a new one
+doctor-store-consistent = Repository store matches cluster definition
+doctor-store-failure = Repository store cannot be read: { $error }
+doctor-store-fix = Make sure the data directory of OCD is readable
+doctor-node-missing = { $name } is missing from the repository store
+doctor-node-missing-fix = Run "ocd deploy { $name }" to clone it
+doctor-orphan = { $path } is in the repository store, but no node is defined for it
+doctor-orphan-fix = Remove it, or define a node for it through "ocd add"
+doctor-node-failure = { $name } cannot be opened: { $error }
+doctor-node-failure-fix = Run "ocd repair { $name }", or remove it from the repository store to clone it again
+doctor-sparse-consistent = Sparse checkout files match exclusion rules of their nodes
+doctor-sparse-stale = Sparse checkout file of { $name } does not match its exclusion rules
+doctor-sparse-stale-fix = Run "ocd deploy { $name }" to bring it up to date
+doctor-sparse-foreign = Sparse checkout file of { $name } was not written by OCD, or was edited by hand since
+doctor-sparse-foreign-fix = Run "ocd deploy --force { $name }" to overwrite it
+doctor-aliases-exist = Working directory aliases of nodes exist
+doctor-alias-missing = Working directory alias { $path } of { $name } does not exist
+doctor-alias-fix = Create it, or fix the working directory alias of { $name }
+
+## ocd share
+
+share-written = Write redacted cluster definition to { $path }
+
+## ocd devcontainer
+
+devcontainer-no-url = Root has no origin remote to clone cluster from, use "--url" to give one
+devcontainer-written = Write container setup snippet to { $path }
+
+## ocd stats
+
+stats-empty = No usage statistics recorded yet, set "usage_stats = true" in the settings of root to record them
+stats-cleared = Forget every usage statistic recorded so far
+stats-record-failure = Cannot record usage statistics: { $error }
+
+## ocd bugreport
+
+bugreport-written = Write bug report bundle to { $path }
+
+## Git passthrough
+
+ssh-agent-failure = Cannot start SSH agent: { $error }
+redeploy-head-moved = HEAD of { $name } moved, but its deployment did not follow
+redeploy-hint = Run "ocd deploy { $name }" to bring its deployment up to date
+root-commit-invalid = Refusing to commit invalid cluster definition into root, fix it or commit with "--no-verify"
@@ -1,26 +1,39 @@
 // SPDX-FileCopyrightText: 2025 Jason Pena <jasonpena@awkless.com>
 // SPDX-License-Identifier: MIT
 
-use ocd::cmd::Ocd;
+use ocd::{cmd::Ocd, store::git_trail, timings::Timings};
 
-use anyhow::Result;
 use clap::Parser;
-use tracing_subscriber::{fmt, prelude::*, EnvFilter};
+use tracing_subscriber::{filter::LevelFilter, fmt, prelude::*, EnvFilter};
 
 #[tokio::main]
 async fn main() {
+    let ocd = Ocd::parse();
+
+    // INVARIANT: Timings need debug spans, so filter log output per layer instead of globally.
+    let timings = ocd.timings.then(Timings::new);
     let layer = fmt::layer().compact().with_target(false).with_timer(false).without_time();
     let filter = EnvFilter::try_from_default_env().or_else(|_| EnvFilter::try_new("info")).unwrap();
-    tracing_subscriber::registry().with(filter).with(layer).init();
+    tracing_subscriber::registry()
+        .with(layer.with_filter(filter))
+        .with(timings.as_ref().map(|timings| timings.layer().with_filter(LevelFilter::DEBUG)))
+        .init();
+
+    let result = ocd.run().await;
+    if let Some(timings) = timings {
+        eprint!("{}", timings.report());
+    }
 
-    if let Err(error) = run().await {
+    if let Err(error) = result {
         tracing::error!("{error:?}");
+
+        // INVARIANT: Trail is only ever filled when Git operations are traced.
+        let trail = git_trail();
+        if !trail.is_empty() {
+            tracing::error!("Last Git operations:\n  {}", trail.join("\n  "));
+        }
         std::process::exit(1);
     }
 
     std::process::exit(0);
 }
-
-async fn run() -> Result<()> {
-    Ocd::parse().run().await
-}
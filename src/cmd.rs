@@ -6,36 +6,183 @@
 //! This module is the forward facing API of internal library. It is meant to be used in `main` of
 //! the OCD binary. The entire OCD command set is implemented right there!.
 
+mod bugreport;
+mod completions;
+mod devcontainer;
+mod doctor;
+pub(crate) mod i18n;
+mod man;
+mod privilege;
+mod version;
+mod why;
+
+pub use completions::CompletionShell;
+pub use devcontainer::SnippetFormat;
+pub use version::VersionFormat;
+
+use bugreport::BugReport;
+use completions::CompletionIndex;
+use devcontainer::ContainerSetup;
+use doctor::{Diagnosis, Verdict};
+use i18n::tr;
+use privilege::guard_root;
+use version::BuildInfo;
+use why::Explanation;
+
 use crate::{
     model::{
-        cluster::{Cluster, NodeEntry, RootEntry},
-        config_dir, data_dir,
+        cache_dir, check_writable,
+        cluster::{
+            ClonePolicy, Cluster, ClusterChanges, DeploymentKind, NodeEntry, NodeEntryDeployment,
+            PullStrategy, RootEntry, StoreLayout, WorkDirAlias,
+        },
+        config_dir, data_dir, expand_path,
         hook::{HookAction, HookKind, HookRunner},
+        hostname,
+        schema::set_strict,
+        state_dir,
+        url::normalize_url,
+    },
+    notify::{Notification, NotifyEvent},
+    output::{print_structured, set_plain, wants_plain, OutputFormat},
+    stats::{note_deployed, UsageStats},
+    store::{
+        begin_transaction, check_remote, is_node_present, is_sync_daemon_running, last_transaction,
+        lock_sync_daemon, migrate_store_layout, origin_url, prompt_segment, prune_artifacts,
+        rename_in_store, require_git_binary, set_clone_policy, set_store_layout, set_trace_git,
+        stale_artifacts, store_layout_changed, undo_transaction, wants_trace_git,
+        write_deploy_report, ClusterLock, CollisionPolicy, ConfigWatcher, DeployAction,
+        DeployOutcome, DeployPreflight, DeployState, MultiEntryFetch, MultiNodeClone, Node,
+        PullOutcome, RemoteHealth, Root, SshAgent, SyncStatus, SyncTrigger, TablizeCluster,
+        Transaction,
     },
-    store::{DeployAction, MultiNodeClone, Node, Root, TablizeCluster},
 };
 
 use anyhow::{anyhow, Context, Result};
-use clap::{Parser, Subcommand};
-use inquire::prompt_confirmation;
-use std::{ffi::OsString, fs::remove_dir_all};
+use beau_collector::BeauCollector as _;
+use clap::{error::ErrorKind, Arg, ArgGroup, Args, CommandFactory, Parser, Subcommand, ValueEnum};
+use inquire::{prompt_confirmation, prompt_text, MultiSelect, Select};
+use std::{
+    collections::{BTreeMap, BTreeSet, HashSet},
+    ffi::OsString,
+    fs::{remove_dir_all, remove_file},
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
 use tracing::{info, instrument, warn};
 
 /// OCD public command set CLI.
+///
+/// All help text of the command set is defined right here through doc comments, such that the
+/// first paragraph of a given doc comment is used as short help for `-h`, and the full doc comment
+/// is used as long help for `--help`. Any documentation generated from the command set should be
+/// derived from this definition to stay consistent with the actual CLI.
 #[derive(Debug, Clone, Parser)]
 #[command(
     about,
+    long_about,
+    after_long_help = OCD_EXAMPLES,
     override_usage = "\n  ocd [options] <ocd-command>\n  ocd [options] [target]... <git-command>",
     subcommand_help_heading = "Commands",
-    version
+    version,
+    disable_version_flag = true
 )]
 pub struct Ocd {
+    /// Behavior of command hook execution.
+    ///
+    /// Command hooks are defined in "$XDG_CONFIG_HOME/ocd/hooks.toml", and node hooks in the
+    /// configuration file of each node. By default, each hook is shown through a pager that
+    /// prompts to accept or deny its execution. Use "always" to run hooks without prompting, or
+    /// "never" to skip hooks entirely.
     #[arg(default_value_t = HookAction::default(), long, short, value_enum, value_name = "action")]
     pub run_hook: HookAction,
 
+    /// Do not start an SSH agent for Git passthrough calls.
+    ///
+    /// Git passthrough calls over more than one target share an SSH agent, such that each SSH key
+    /// passphrase only needs to be entered once per run. An agent already reachable through
+    /// "$SSH_AUTH_SOCK" is reused, otherwise a new agent is started, and killed once the run is
+    /// done.
+    #[arg(long)]
+    pub no_ssh_agent: bool,
+
+    /// Allow running as root.
+    ///
+    /// OCD refuses to run as root by default, because files would be deployed into the home
+    /// directory of root, and be owned by root. When run through sudo, the home directory of the
+    /// user that invoked sudo is used instead of "$HOME".
+    #[arg(long)]
+    pub allow_root: bool,
+
+    /// Print plain status lines instead of progress bars.
+    ///
+    /// Progress bars redraw themselves many times per second, which is noisy for screen readers
+    /// and dumb terminals. Plain output prints one status line when an operation like a clone or
+    /// fetch starts, and one when it finishes. Used by default when standard error is not a
+    /// terminal, or "$TERM" is "dumb".
+    #[arg(long)]
+    pub plain: bool,
+
+    /// Behavior of cloning nodes missing from the repository store on demand.
+    ///
+    /// Commands like deploy clone target nodes that are missing from the repository store, e.g.,
+    /// because they were left out of "ocd clone --only". By default, they are cloned without
+    /// asking. Use "prompt" to confirm each clone along with its URL, or "never" to fail instead,
+    /// e.g., on metered connections. Defaults to the "auto_clone" setting of root.
+    #[arg(long, value_enum, value_name = "policy")]
+    pub auto_clone: Option<ClonePolicy>,
+
+    /// Report total time, and time spent per node in each phase of the command.
+    ///
+    /// Phases are configuration parsing, Git operations, command hooks, and checkout. Report is
+    /// printed to standard error once the command finishes, even if it failed, to help find out
+    /// what makes a cluster slow.
+    #[arg(long)]
+    pub timings: bool,
+
+    /// Log every Git operation along with its arguments and how long it took.
+    ///
+    /// Covers calls to the Git binary, and clones or fetches done through libgit2. Credentials are
+    /// redacted from URLs. The last few Git operations are added to the error report of a failed
+    /// command. Also turned on by setting "$OCD_TRACE_GIT".
+    #[arg(long)]
+    pub trace_git: bool,
+
+    /// Skip unknown keys in configuration files instead of failing.
+    ///
+    /// Unknown keys are usually typos, e.g., "exclude" instead of "excluded", that would otherwise
+    /// leave a setting silently unset. Thus, OCD fails on them by default, suggesting the closest
+    /// known key. Skipped keys are still warned about.
+    #[arg(long)]
+    pub no_strict: bool,
+
+    /// Print version.
+    #[arg(short = 'V', long)]
+    pub version: bool,
+
+    /// Print build information along with version.
+    ///
+    /// Adds the commit OCD was built from, its build date, enabled cargo features, the libgit2
+    /// version it was built against, and the version of the Git binary found through "$PATH".
+    /// Include it in bug reports.
+    #[arg(long, requires = "version")]
+    pub verbose: bool,
+
+    /// Format of version output.
+    ///
+    /// The JSON format always includes build information, and keeps its keys stable for scripts.
+    #[arg(
+        default_value_t = VersionFormat::default(),
+        long,
+        requires = "version",
+        value_enum,
+        value_name = "format"
+    )]
+    pub format: VersionFormat,
+
     /// Command-set interfaces.
     #[command(subcommand)]
-    pub command: Command,
+    pub command: Option<Command>,
 }
 
 impl Ocd {
@@ -49,45 +196,646 @@ impl Ocd {
     ///
     /// Will fail if given command implementation fails.
     pub async fn run(self) -> Result<()> {
-        match self.command {
+        if self.version {
+            print!("{}", BuildInfo::gather().render(self.format, self.verbose)?);
+            return Ok(());
+        }
+
+        // INVARIANT: Command is only optional such that version can be printed without one.
+        let Some(command) = self.command else {
+            Ocd::command().error(ErrorKind::MissingSubcommand, tr!("command-missing")).exit();
+        };
+
+        // INVARIANT: Read-only commands that never touch the cluster need no guarding. Everyone
+        // else is guarded before any configuration path gets resolved, because guarding may
+        // switch to the home directory and privileges of the invoking user.
+        if !matches!(command, Command::Man(_) | Command::PromptSegment(_) | Command::Completions(_))
+        {
+            guard_root(self.allow_root)?;
+        }
+
+        set_plain(wants_plain(self.plain));
+        set_clone_policy(resolve_clone_policy(self.auto_clone));
+        set_trace_git(wants_trace_git(self.trace_git));
+        set_strict(!self.no_strict);
+        set_store_layout(resolve_store_layout());
+
+        // INVARIANT: Report missing Git binary before anything gets cloned or modified.
+        if needs_git_binary(&command) {
+            require_git_binary()?;
+        }
+
+        // INVARIANT: Commands that only read the cluster still work on read-only filesystems,
+        // while commands that modify it fail before modifying anything.
+        if modifies_cluster(&command) {
+            check_writable(config_dir()?, &tr!("config-dir-label"))?;
+            check_writable(data_dir()?, &tr!("data-dir-label"))?;
+
+            // INVARIANT: Node repositories follow the store layout of root before anything
+            // uses them.
+            if store_layout_changed() {
+                let moved = migrate_store_layout(&Cluster::new()?)?;
+                info!("{}", tr!("store-layout-migrated", count = moved.len()));
+            }
+        } else if !matches!(
+            command,
+            Command::Man(_) | Command::PromptSegment(_) | Command::Completions(_)
+        ) && store_layout_changed()
+        {
+            // INVARIANT: Read-only commands never move node repositories around.
+            warn!("{}", tr!("store-layout-pending"));
+        }
+
+        let name = command_name(&command);
+        let start = Instant::now();
+        let result = match command {
             Command::Clone(opts) => run_clone(self.run_hook, opts).await,
             Command::Init(opts) => run_init(self.run_hook, opts),
+            Command::Add(opts) => run_add(self.run_hook, opts),
             Command::Deploy(opts) => run_deploy(self.run_hook, opts),
             Command::Undeploy(opts) => run_undeploy(self.run_hook, opts),
             Command::Remove(opts) => run_remove(self.run_hook, opts),
-            Command::List(opts) => run_list(self.run_hook, opts),
-            Command::Git(opts) => run_git(opts),
+            Command::Uninstall(opts) => run_uninstall(opts),
+            Command::List(opts) => run_list(self.run_hook, opts).await,
+            Command::Repair(opts) => run_repair(self.run_hook, opts),
+            Command::Excludes(opts) => run_excludes(self.run_hook, opts),
+            Command::MigrateRemote(opts) => run_migrate_remote(self.run_hook, opts),
+            Command::Convert(opts) => run_convert(self.run_hook, opts),
+            Command::Rename(opts) => run_rename(self.run_hook, opts),
+            Command::Pick(opts) => run_pick(self.run_hook, !self.no_ssh_agent, opts),
+            Command::Status(opts) => run_status(opts),
+            Command::Diff(opts) => run_diff(opts),
+            Command::Clean(opts) => run_clean(opts),
+            Command::PruneArtifacts(opts) => run_prune_artifacts(opts),
+            Command::Gc(opts) => run_gc(self.run_hook, opts),
+            Command::Pull(opts) => run_pull(self.run_hook, opts).await,
+            Command::FetchConfig(opts) => run_fetch_config(self.run_hook, opts).await,
+            Command::Sync(opts) => run_sync(self.run_hook, opts),
+            Command::Freeze(opts) => run_freeze(self.run_hook, opts),
+            Command::Thaw(opts) => run_thaw(self.run_hook, opts),
+            Command::Rollback(opts) => run_rollback(self.run_hook, opts),
+            Command::Undo(opts) => run_undo(self.run_hook, opts),
+            Command::Why(opts) => run_why(opts),
+            Command::Validate(opts) => run_validate(opts),
+            Command::Doctor(opts) => run_doctor(opts),
+            Command::Share(opts) => run_share(opts),
+            Command::PromptSegment(opts) => run_prompt_segment(opts),
+            Command::Devcontainer(opts) => run_devcontainer(opts),
+            Command::Man(opts) => run_man(opts),
+            Command::Completions(opts) => run_completions(opts),
+            Command::Stats(opts) => run_stats(opts),
+            Command::Bugreport(opts) => run_bugreport(opts),
+            Command::Git(opts) => run_git(opts, !self.no_ssh_agent),
+        };
+
+        // INVARIANT: Failing to record usage statistics never fails the command itself.
+        if !matches!(name, "prompt-segment" | "completions")
+            && peek_root_setting("usage_stats") == Some(true.into())
+        {
+            if let Err(error) = record_usage(name, start.elapsed()) {
+                warn!("{}", tr!("stats-record-failure", error = format!("{error:?}")));
+            }
         }
+
+        result
+    }
+}
+
+fn record_usage(name: &str, elapsed: Duration) -> Result<()> {
+    let mut stats = UsageStats::load()?;
+    stats.record_invocation(name, elapsed);
+    stats.save()
+}
+
+fn command_name(command: &Command) -> &'static str {
+    match command {
+        Command::Clone(_) => "clone",
+        Command::Init(_) => "init",
+        Command::Add(_) => "add",
+        Command::Deploy(_) => "deploy",
+        Command::Undeploy(_) => "undeploy",
+        Command::Remove(_) => "rm",
+        Command::Uninstall(_) => "uninstall",
+        Command::List(_) => "ls",
+        Command::Repair(_) => "repair",
+        Command::Excludes(_) => "excludes",
+        Command::MigrateRemote(_) => "migrate-remote",
+        Command::Convert(_) => "convert",
+        Command::Rename(_) => "rename",
+        Command::Pick(_) => "pick",
+        Command::Status(_) => "status",
+        Command::Diff(_) => "diff",
+        Command::Clean(_) => "clean",
+        Command::PruneArtifacts(_) => "prune-artifacts",
+        Command::Gc(_) => "gc",
+        Command::Pull(_) => "pull",
+        Command::FetchConfig(_) => "fetch-config",
+        Command::Sync(_) => "sync",
+        Command::Freeze(_) => "freeze",
+        Command::Thaw(_) => "thaw",
+        Command::Rollback(_) => "rollback",
+        Command::Undo(_) => "undo",
+        Command::Why(_) => "why",
+        Command::Validate(_) => "validate",
+        Command::Doctor(_) => "doctor",
+        Command::Share(_) => "share",
+        Command::PromptSegment(_) => "prompt-segment",
+        Command::Devcontainer(_) => "devcontainer",
+        Command::Man(_) => "man",
+        Command::Completions(_) => "completions",
+        Command::Stats(_) => "stats",
+        Command::Bugreport(_) => "bugreport",
+        Command::Git(_) => "git",
+    }
+}
+
+// Commands that write into the configuration directory or the repository store.
+fn modifies_cluster(command: &Command) -> bool {
+    match command {
+        Command::Clone(_)
+        | Command::Init(_)
+        | Command::Add(_)
+        | Command::Undeploy(_)
+        | Command::Remove(_)
+        | Command::Uninstall(_)
+        | Command::Repair(_)
+        | Command::Excludes(_)
+        | Command::MigrateRemote(_)
+        | Command::Convert(_)
+        | Command::Rename(_)
+        | Command::Gc(_)
+        | Command::Pull(_)
+        | Command::Sync(_)
+        | Command::Freeze(_)
+        | Command::Thaw(_) => true,
+        Command::Rollback(opts) => !opts.list,
+        Command::Undo(opts) => !opts.list,
+        Command::FetchConfig(opts) => !opts.check,
+        Command::Deploy(opts) => !opts.check,
+        Command::Pick(opts) => opts.action.is_some(),
+        _ => false,
     }
 }
 
+// Commands that call the Git binary, as opposed to commands that only need libgit2.
+fn needs_git_binary(command: &Command) -> bool {
+    match command {
+        Command::Deploy(opts) => !opts.check,
+        Command::Clone(_)
+        | Command::Undeploy(_)
+        | Command::Uninstall(_)
+        | Command::Convert(_)
+        | Command::Gc(_)
+        | Command::Pull(_)
+        | Command::Sync(_)
+        | Command::Freeze(_)
+        | Command::Thaw(_)
+        | Command::Diff(_)
+        | Command::Git(_) => true,
+        Command::Rollback(opts) => !opts.list,
+        Command::Undo(opts) => !opts.list,
+        Command::FetchConfig(opts) => !opts.check,
+        Command::Status(opts) => opts.entry.is_some() && !opts.untracked,
+        Command::Pick(opts) => opts.action.is_some(),
+        _ => false,
+    }
+}
+
+// INVARIANT: Root configuration file is peeked at on its own, such that commands never fail over
+// a broken cluster definition before they get to load it themselves.
+fn resolve_clone_policy(flag: Option<ClonePolicy>) -> ClonePolicy {
+    flag.or_else(|| peek_root_setting("auto_clone")?.try_into().ok()).unwrap_or_default()
+}
+
+fn resolve_store_layout() -> StoreLayout {
+    peek_root_setting("store_layout").and_then(|layout| layout.try_into().ok()).unwrap_or_default()
+}
+
+fn peek_root_setting(key: &str) -> Option<toml::Value> {
+    let data = std::fs::read_to_string(config_dir().ok()?.join("root.toml")).ok()?;
+    let table: toml::Table = data.parse().ok()?;
+    table.get("settings")?.get(key).cloned()
+}
+
 /// Full command-set of OCD.
 #[derive(Debug, Clone, Subcommand)]
 pub enum Command {
     /// Clone existing cluster from root repository.
-    #[command(override_usage = "ocd clone [options] <url>")]
+    ///
+    /// Clones root first, and deploys it such that the cluster definition becomes available. Every
+    /// node defined in the cluster definition is then cloned concurrently. If root cannot be cloned
+    /// or deployed, then the configuration and data directories are cleared to avoid leaving a
+    /// broken cluster behind.
+    #[command(override_usage = "ocd clone [options] <url>", after_long_help = CLONE_EXAMPLES)]
     Clone(CloneOptions),
 
     /// Initialize new entries.
-    #[command(override_usage = "ocd init [options] <node_name>")]
+    ///
+    /// Creates an empty repository in the repository store for an entry that already has a
+    /// configuration file in the cluster definition. Use "root" to initialize root itself, which
+    /// must be done before any node can be initialized.
+    #[command(override_usage = "ocd init [options] <node_name>", after_long_help = INIT_EXAMPLES)]
     Init(InitOptions),
 
+    /// Adopt existing local repository into cluster as new node.
+    ///
+    /// Moves repository into the repository store, and defines a node for it in the cluster
+    /// definition, such that repositories cloned by hand need not be cloned again. Repositories
+    /// adopted as bare-alias nodes are copied into a bare repository instead, and left where they
+    /// are. The cluster definition is validated before the repository is touched, such that
+    /// nothing is adopted into a cluster that would not load.
+    #[command(override_usage = "ocd add [options] <path>", after_long_help = ADD_EXAMPLES)]
+    Add(AddOptions),
+
     /// Deploy target entries in cluster.
-    #[command(override_usage = "ocd deploy [options] [target]...")]
+    ///
+    /// Deploys bare-alias and inline nodes to their working directory aliases. Normal nodes only
+    /// need to exist in the repository store, so deploying them just makes sure they were cloned,
+    /// and each normal target is reported as skipped. Dependencies of each target node are deployed
+    /// as well, unless told otherwise. Root is always deployed, and "--all" deploys root along with
+    /// every node in dependency order.
+    #[command(
+        override_usage = "ocd deploy [options] [target]...",
+        after_long_help = DEPLOY_EXAMPLES
+    )]
     Deploy(DeployOptions),
 
     /// Undeploy target entries in cluster.
-    #[command(override_usage = "ocd undeploy [options] [target]...")]
+    ///
+    /// Removes deployed files of target nodes from their working directory aliases, leaving their
+    /// repositories in the repository store alone. Root can never be undeployed, because it houses
+    /// the cluster definition. Thus "--all" undeploys nodes before their dependencies, and only
+    /// targets root along with "--excluded-only".
+    #[command(
+        override_usage = "ocd undeploy [options] [target]...",
+        after_long_help = UNDEPLOY_EXAMPLES
+    )]
     Undeploy(UndeployOptions),
 
     /// Remove target entries from cluster.
-    #[command(name = "rm", override_usage = "ocd rm [options] [target]...")]
+    ///
+    /// Undeploys target nodes, and removes their repositories from the repository store. Targeting
+    /// root removes the entire cluster, i.e., every node, the configuration directory, and the data
+    /// directory, after confirmation. Target nodes that other nodes in the repository store still
+    /// depend on are refused, unless those nodes are removed along with them through "--cascade".
+    /// Node configuration files are left alone. Removing nodes through "--all" asks for
+    /// confirmation, removes nodes before their dependencies, skips nodes missing from the
+    /// repository store, and leaves root and the cluster definition alone.
+    #[command(
+        name = "rm",
+        override_usage = "ocd rm [options] [target]...",
+        after_long_help = REMOVE_EXAMPLES
+    )]
     Remove(RemoveOptions),
 
+    /// Remove OCD and everything it manages from this machine.
+    ///
+    /// Undeploys every node, then removes the repository store, the configuration directory, the
+    /// state and cache directories, and the deployment report if root defines one. Nodes matched
+    /// by "--keep" stay deployed. Root stays deployed as well, because root is never undeployed,
+    /// unless "--undeploy-root" is given. Deployed files left in place are listed at the end, such
+    /// that nothing OCD leaves behind goes unnoticed. Asks for confirmation first. Command hooks
+    /// never run.
+    #[command(override_usage = "ocd uninstall [options]", after_long_help = UNINSTALL_EXAMPLES)]
+    Uninstall(UninstallOptions),
+
     /// List current entries in cluster.
-    #[command(name = "ls", override_usage = "ocd list [options]")]
+    ///
+    /// Shows the deployment kind, name, deployment state, current branch, and upstream state of
+    /// every entry in the cluster, or only of target entries if any are given. Use "--with-deps"
+    /// to also list the transitive dependencies of each target node, annotated with the targets
+    /// that depend on them, i.e., everything deploying the targets would touch. Use "--format" to
+    /// print JSON or YAML for scripts and status bars instead of a table.
+    #[command(
+        name = "ls",
+        override_usage = "ocd ls [options] [target]...",
+        after_long_help = LIST_EXAMPLES
+    )]
     List(ListOptions),
 
+    /// Repair entries whose repository store drifted from the cluster definition.
+    ///
+    /// Checks target nodes against their cluster definition, and repairs whatever repair kind was
+    /// requested. Inline nodes, and nodes without a URL in their cluster definition, are skipped.
+    #[command(
+        override_usage = "ocd repair [options] [target]...",
+        after_long_help = REPAIR_EXAMPLES
+    )]
+    Repair(RepairOptions),
+
+    /// Edit exclusion rules of many nodes at once.
+    ///
+    /// Adds or removes sparsity rules in the "excluded" setting of each target node, editing node
+    /// configuration files in place such that comments and formatting are kept. Target nodes that
+    /// are currently deployed get their new exclusion rules applied right away. Nodes deployed with
+    /// their excluded files are left as they are until they are redeployed.
+    #[command(
+        override_usage = "ocd excludes <add|remove> [options] --nodes <pattern> <rule>...",
+        after_long_help = EXCLUDES_EXAMPLES
+    )]
+    Excludes(ExcludesOptions),
+
+    /// Rewrite remote URLs of entries when moving to another Git host.
+    ///
+    /// Replaces the first occurrence of the "--from" text with the "--to" text in the URL of each
+    /// target node, editing node configuration files in place such that comments and formatting
+    /// are kept. The origin remote of each target entry already in the repository store is
+    /// rewritten to match, including the origin remote of root. Entries whose URL does not contain
+    /// the "--from" text are left alone.
+    #[command(
+        override_usage = "ocd migrate-remote [options] --from <text> --to <text> [target]...",
+        after_long_help = MIGRATE_REMOTE_EXAMPLES
+    )]
+    MigrateRemote(MigrateRemoteOptions),
+
+    /// Convert node between normal and bare-alias deployment.
+    ///
+    /// Normal nodes keep a normal repository in the repository store, while bare-alias nodes keep
+    /// a bare one. Re-creates the repository of the node with the bareness its new deployment
+    /// kind needs, keeping every branch, tag, remote, and upstream branch of the old repository.
+    /// The deployment setting of the node configuration file is edited in place, such that
+    /// comments and formatting are kept. Node is undeployed before conversion, and only deployed
+    /// again if asked for. Nodes missing from the repository store only get their configuration
+    /// file edited.
+    #[command(
+        override_usage = "ocd convert [options] --to <kind> <node>",
+        after_long_help = CONVERT_EXAMPLES
+    )]
+    Convert(ConvertOptions),
+
+    /// Rename node.
+    ///
+    /// Moves the node configuration file to its new name, and edits the dependencies of every
+    /// node that depends on it in place, along with host profiles that list it by its exact name,
+    /// such that comments and formatting are kept. The repository of the node moves to its new
+    /// name in the repository store, while its pin in "cluster.lock", its deployment history, and
+    /// its deployment artifacts follow along. Files that node deployed stay where they are, such
+    /// that it stays deployed as it was. Command hooks and glob patterns of host profiles that
+    /// refer to the old name are left alone.
+    #[command(override_usage = "ocd rename [options] <old> <new>", after_long_help = RENAME_EXAMPLES)]
+    Rename(RenameOptions),
+
+    /// Pick nodes of cluster through fuzzy finder.
+    ///
+    /// Fuzzy finds over node names and their descriptions, for users that do not have fzf(1)
+    /// installed. Picked nodes are printed one per line, such that they can be piped into the
+    /// "--stdin" flag of other commands, unless an action is given to run on them directly.
+    /// Command hooks of the chosen action run as usual.
+    #[command(override_usage = "ocd pick [options] [action] [-- <git-args>...]", after_long_help = PICK_EXAMPLES)]
+    Pick(PickOptions),
+
+    /// Show status of target entry, or of entire cluster.
+    ///
+    /// Runs "git status" on the target entry, the same as "ocd <entry> status" does. Bare-alias
+    /// entries hide untracked files from Git, because their working directory alias is usually the
+    /// entire home directory. Use "--untracked" to list untracked files that sit in the same
+    /// directories as tracked files instead, honoring gitignore rules, as suggestions of files
+    /// worth adding. Without a target entry, shows a table of every entry in the cluster instead,
+    /// telling whether it is clean, or has staged changes, unstaged changes, or untracked files,
+    /// and how far it is ahead/behind of its upstream. Nodes missing from the repository store are
+    /// never cloned. Use "--sync" to show the status of the sync daemon instead. Command hooks
+    /// never run.
+    #[command(override_usage = "ocd status [options] [entry]", after_long_help = STATUS_EXAMPLES)]
+    Status(StatusOptions),
+
+    /// Show how deployed files of bare-alias entries differ from their repositories.
+    ///
+    /// Runs "git diff HEAD" on each target entry whose deployed files drifted from HEAD, i.e.,
+    /// were modified or removed in its working directory alias since deployment, under a header
+    /// naming the entry. Entries that did not drift are left out. Use "--stat" to show a diffstat
+    /// of each entry instead, followed by a summary of the entire cluster. Only root and
+    /// bare-alias nodes are diffed, and nodes missing from the repository store are never cloned.
+    /// Command hooks never run.
+    #[command(override_usage = "ocd diff [options] [target]...", after_long_help = DIFF_EXAMPLES)]
+    Diff(DiffOptions),
+
+    /// Remove untracked files next to tracked files of target node.
+    ///
+    /// Only cleans the files "ocd status --untracked" would suggest, i.e., untracked files that
+    /// sit in the same directories as tracked files, honoring gitignore rules. Files sitting
+    /// directly in the home directory are never removed, so the home directory as a whole is left
+    /// alone. Nothing is removed unless "--force" is given, in which case confirmation is asked
+    /// for first. Command hooks never run.
+    #[command(override_usage = "ocd clean [options] <node>", after_long_help = CLEAN_EXAMPLES)]
+    Clean(CleanOptions),
+
+    /// Remove files left behind by nodes that were removed from the cluster definition.
+    ///
+    /// OCD remembers which files each node left in its working directory alias as of its last
+    /// deployment. Nodes dropped from the cluster definition without being undeployed first, e.g.,
+    /// because their configuration file got deleted, or their undeployment failed, leave those
+    /// files behind. Lists every such file that still exists, unless a node that is still defined
+    /// deployed it too. Nothing is removed unless "--force" is given, in which case confirmation is
+    /// asked for first. Command hooks never run.
+    #[command(
+        override_usage = "ocd prune-artifacts [options]",
+        after_long_help = PRUNE_ARTIFACTS_EXAMPLES
+    )]
+    PruneArtifacts(PruneArtifactsOptions),
+
+    /// Reclaim disk space taken up by repositories of target nodes.
+    ///
+    /// Packs the repository of each target node, dropping unreachable objects. Use
+    /// "--prune-history" to also turn each target node into a shallow clone whose history starts
+    /// at the date of its "shallow_since" setting, which helps on machines with little disk space.
+    /// Shallow clones cannot be converted, and Git commands that need full history, e.g., "git
+    /// blame", "git bisect", or "git log" past the cut-off date, show partial results or fail.
+    /// Use "ocd <node> fetch --unshallow" to restore full history. Inline nodes live inside root,
+    /// and are skipped.
+    #[command(override_usage = "ocd gc [options] [node]...", after_long_help = GC_EXAMPLES)]
+    Gc(GcOptions),
+
+    /// Pull target entries from their upstream.
+    ///
+    /// Fetches the origin remote of each target entry concurrently, and then fast-forwards its
+    /// current branch to its upstream one entry at a time. Entries that fail to fetch are reported
+    /// along with every other failure once everything else was pulled. Entries whose local branch
+    /// diverged from its upstream follow the "pull_strategy" setting of their node, or else of
+    /// root: "rebase" rebases local commits onto upstream, "merge" merges upstream into the local
+    /// branch, "skip" leaves them as they are, and "prompt" asks for each of them. Diverged
+    /// entries are skipped by default. Rebases and merges that run into conflicts are aborted,
+    /// leaving the entry as it was. The summary lists every entry skipped over divergence. Mirrors
+    /// are only fetched. Inline nodes are pulled along with root, while detached nodes, e.g.,
+    /// frozen ones, and nodes missing from the repository store are skipped.
+    #[command(override_usage = "ocd pull [options] [entry]...", after_long_help = PULL_EXAMPLES)]
+    Pull(PullOptions),
+
+    /// Bring this machine in line with the cluster definition at the upstream of root.
+    ///
+    /// Fetches root, and lists how the incoming cluster definition differs from the current one:
+    /// nodes added with "+", nodes removed with "-", and nodes or root defined differently with
+    /// "~". Then pulls root, and applies the incoming cluster definition: removed nodes are taken
+    /// out of the repository store, dependents first, added nodes that the host profile of this
+    /// machine selects are cloned and deployed, and changed nodes are deployed again if they were
+    /// deployed already. Nodes whose deployment kind changed are left alone, use "ocd convert" on
+    /// them. Root is deployed again as well. Fails if root diverged from its upstream.
+    #[command(
+        override_usage = "ocd fetch-config [options]",
+        after_long_help = FETCH_CONFIG_EXAMPLES
+    )]
+    FetchConfig(FetchConfigOptions),
+
+    /// Reconcile repository store with cluster definition.
+    ///
+    /// Clones entries missing from the repository store, and deploys the entire cluster, the same
+    /// as "ocd deploy --all" does. Use "--daemon" to stay resident instead, reconciling on a timer
+    /// as well as whenever files in the configuration directory change. Only one daemon runs at a
+    /// time. Deployments of the daemon and of any other OCD invocation never interleave, because
+    /// each deployment locks its repository. The daemon keeps running when a reconcile fails,
+    /// recording what it last did in the state directory, which "ocd status --sync" shows.
+    /// Command hooks of deployment run on every reconcile.
+    #[command(override_usage = "ocd sync [options]", after_long_help = SYNC_EXAMPLES)]
+    Sync(SyncOptions),
+
+    /// Pin target nodes to their current commits.
+    ///
+    /// Records the commit and branch of each target node into "cluster.lock" next to the cluster
+    /// definition, and commits the lockfile to root. "ocd deploy --locked" then checks out exactly
+    /// the locked commits, giving reproducible machine setups. Checking out an older commit of
+    /// root brings back the lockfile of that time, such that machines can be rolled back to a
+    /// known-good state. Root is never locked, because the lockfile lives inside it. Inline nodes
+    /// live inside root as well, and are skipped, and so are nodes missing from the repository
+    /// store.
+    #[command(override_usage = "ocd freeze [options] [node]...", after_long_help = FREEZE_EXAMPLES)]
+    Freeze(FreezeOptions),
+
+    /// Unpin target nodes from their locked commits.
+    ///
+    /// Removes each target node from "cluster.lock", removing the lockfile entirely once no node
+    /// is left in it, and commits the change to root. Target nodes whose HEAD was detached at a
+    /// locked commit are put back on the branch they were frozen from.
+    #[command(override_usage = "ocd thaw [options] [node]...", after_long_help = THAW_EXAMPLES)]
+    Thaw(ThawOptions),
+
+    /// Roll node back to the commit it was deployed at before.
+    ///
+    /// Every deployment of a node records the commit it was deployed at into a journal in
+    /// "$XDG_STATE_HOME/ocd/journal.toml", keeping the last 10 commits of each node. Rolling back
+    /// checks out the most recently deployed commit that HEAD does not point to, detaching HEAD
+    /// at it, and deploys the node again. Undoes a bad pull without digging through reflogs.
+    /// Commits deployed after the one rolled back to are forgotten, such that rolling back again
+    /// goes further back. Inline nodes belong to root, and cannot be rolled back on their own.
+    #[command(override_usage = "ocd rollback [options] <node>", after_long_help = ROLLBACK_EXAMPLES)]
+    Rollback(RollbackOptions),
+
+    /// Undo the last deploy, undeploy, or removal.
+    ///
+    /// Every run of "ocd deploy", "ocd undeploy", and "ocd rm" records each file it placed or
+    /// removed, along with how each entry it touched was deployed before, as one transaction in
+    /// "$XDG_STATE_HOME/ocd/transactions.toml", keeping the last 10 transactions. Undoing brings
+    /// each entry of the last transaction back to how it was deployed before, in reverse order,
+    /// cloning removed nodes again. Undone transactions are forgotten, such that undoing again
+    /// reverts the transaction before. Runs that changed nothing are never recorded.
+    #[command(override_usage = "ocd undo [options]", after_long_help = UNDO_EXAMPLES)]
+    Undo(UndoOptions),
+
+    /// Explain why node would be deployed.
+    ///
+    /// Traces node back to the target patterns that match it directly, and to the chains of
+    /// dependencies that pull it in through other targets, using the same patterns "ocd deploy"
+    /// takes. Without any patterns, every node that pulls it in as a dependency is shown instead.
+    /// Also shows what deploying the node amounts to, i.e., its deployment kind, and the exclusion
+    /// rules that apply under the selected sparse profile. Command hooks never run.
+    #[command(override_usage = "ocd why [options] <node> [pattern]...", after_long_help = WHY_EXAMPLES)]
+    Why(WhyOptions),
+
+    /// Validate cluster definition.
+    ///
+    /// Loads root, every node entry, and hooks the same way every other command does, checking
+    /// that dependencies exist and are acyclic, and that configuration files have no unknown keys.
+    /// Use "--staged" to validate the cluster definition staged in the index of root instead,
+    /// e.g., as the pre-commit hook of root. "ocd root commit" does the same before committing.
+    /// Command hooks never run.
+    #[command(override_usage = "ocd validate [options]", after_long_help = VALIDATE_EXAMPLES)]
+    Validate(ValidateOptions),
+
+    /// Diagnose the whole setup of this machine.
+    ///
+    /// Checks that the cluster definition and hook configuration are valid, that every file in the
+    /// nodes directory defines a node, that the repository store holds every node and nothing
+    /// else, that sparse checkout files match the exclusion rules of their nodes, that working
+    /// directory aliases exist, and that the Git binary is reachable. Each finding passes, warns,
+    /// or fails, along with a suggested fix. Fails if any finding fails. Nothing gets cloned or
+    /// modified, and command hooks never run.
+    #[command(override_usage = "ocd doctor [options]", after_long_help = DOCTOR_EXAMPLES)]
+    Doctor(DoctorOptions),
+
+    /// Generate redacted cluster definition for public sharing.
+    ///
+    /// Combines root and every node entry into one TOML document that can be published without
+    /// leaking private setups. Node entries marked with "private = true" are left out, along with
+    /// any dependency on them, and so are exclusion rules that look like they refer to secrets,
+    /// e.g., ".ssh/" or "*.key". Comments and formatting of configuration files are kept. Node
+    /// entries of nested clusters are left to their own cluster. Command hooks never run.
+    #[command(override_usage = "ocd share [options]", after_long_help = SHARE_EXAMPLES)]
+    Share(ShareOptions),
+
+    /// Print compact cluster status for shell prompts.
+    ///
+    /// Prints the number of entries ahead of their upstream branch, and the number of entries with
+    /// uncommitted modifications, e.g., "ocd:3↑ 1✗". Prints nothing if every entry is clean and up
+    /// to date. Status is cached in "$XDG_STATE_HOME/ocd" to keep prompts fast, and recomputed
+    /// whenever an entry commits, stages, or fetches, or its cached status is a few seconds old.
+    /// Command hooks never run, and missing entries are never cloned.
+    #[command(
+        override_usage = "ocd prompt-segment [options]",
+        after_long_help = PROMPT_SEGMENT_EXAMPLES
+    )]
+    PromptSegment(PromptSegmentOptions),
+
+    /// Generate snippets that set up cluster inside containers.
+    ///
+    /// Emits a snippet that installs OCD through cargo, clones the cluster from the origin remote
+    /// of root, and deploys target nodes into the home directory of the container user, such that
+    /// dev containers and codespaces get the cluster automatically. Every call to OCD in the
+    /// snippet allows running as root, and skips hooks, because nobody is around to accept them.
+    /// Command hooks never run.
+    #[command(
+        override_usage = "ocd devcontainer emit [options] [target]...",
+        after_long_help = DEVCONTAINER_EXAMPLES
+    )]
+    Devcontainer(DevcontainerOptions),
+
+    /// Generate man pages of OCD.
+    ///
+    /// Writes a section-1 man page for OCD itself, and one for each of its commands, into the
+    /// output directory. Pages are rendered from the live command-set definition, so they always
+    /// match the installed binary.
+    #[command(override_usage = "ocd man [options]", after_long_help = MAN_EXAMPLES)]
+    Man(ManOptions),
+
+    /// Generate shell completion functions.
+    ///
+    /// Prints a completion function for bash, zsh, fish, or PowerShell that completes the command
+    /// set of OCD, and the names of nodes if "--dynamic" is given. Dynamic completion reads node
+    /// names from a completion index in "$XDG_CACHE_HOME/ocd/completion.toml", which is only
+    /// rebuilt when a root or node configuration file changes, such that tab completion stays fast
+    /// on large clusters. Command hooks never run.
+    #[command(
+        override_usage = "ocd completions [options] [shell]",
+        after_long_help = COMPLETIONS_EXAMPLES
+    )]
+    Completions(CompletionsOptions),
+
+    /// Show usage statistics recorded on this machine.
+    ///
+    /// Usage statistics count how often each command runs along with how long it takes, and how
+    /// often each node gets deployed, to help find out which parts of the cluster are actually
+    /// used. They are only recorded if root sets "usage_stats = true", are kept in
+    /// "$XDG_STATE_HOME/ocd/stats.toml", and never leave the machine. Command hooks never run.
+    #[command(override_usage = "ocd stats [options]", after_long_help = STATS_EXAMPLES)]
+    Stats(StatsOptions),
+
+    /// Generate bug report bundle to attach to issues.
+    ///
+    /// Gathers build information, environment, health checks of the cluster definition and hook
+    /// configuration, the redacted cluster definition, and the last commits each node got deployed
+    /// at into one Markdown document. Cluster definition is redacted like through "ocd share",
+    /// credentials are stripped from URLs, and the home directory is shortened to "~". Works on
+    /// broken clusters too, reporting whatever cannot be gathered instead. Command hooks never run.
+    #[command(override_usage = "ocd bugreport [options]", after_long_help = BUGREPORT_EXAMPLES)]
+    Bugreport(BugreportOptions),
+
     /// Git binary shortcut.
     #[command(external_subcommand)]
     Git(Vec<OsString>),
@@ -95,339 +843,3589 @@ pub enum Command {
 
 /// Clone existing cluster.
 #[derive(Parser, Clone, Debug)]
-#[command(author, about, long_about)]
 pub struct CloneOptions {
     /// URL to root repository to clone from.
     #[arg(value_name = "url")]
     pub url: String,
 
-    /// Number of threads to use per node clone.
+    /// Number of nodes to clone concurrently.
+    ///
+    /// Defaults to the "jobs" setting of root, or the number of available CPU cores if root does
+    /// not define it.
     #[arg(short, long, value_name = "limit")]
     pub jobs: Option<usize>,
+
+    /// Only clone nodes matching these patterns, along with their dependencies.
+    ///
+    /// Nodes left out can be cloned later by running the clone command again, or are cloned once
+    /// they are deployed.
+    #[arg(long, value_delimiter = ',', value_name = "pattern", pattern_help())]
+    pub only: Vec<String>,
+
+    /// Do not clone nodes matching these patterns, unless other nodes depend on them.
+    #[arg(long, value_delimiter = ',', value_name = "pattern", pattern_help())]
+    pub skip: Vec<String>,
+
+    /// Only clone nodes selected by this host profile of root, along with their dependencies.
+    ///
+    /// Host profiles are defined in root through "[profile.<name>]" tables. Defaults to the first
+    /// host profile whose "hosts" patterns match the hostname of the machine, if any.
+    #[arg(long, value_name = "name")]
+    pub host_profile: Option<String>,
 }
 
 /// Initialize new entry in repository store, based on cluster configuration entry.
 #[derive(Parser, Clone, Debug)]
-#[command(author, about, long_about)]
 pub struct InitOptions {
     /// Name of new repository to initialize.
+    ///
+    /// Must match the name of a configuration file in the cluster definition, i.e., "root" for
+    /// "$XDG_CONFIG_HOME/ocd/root.toml", or "<name>" for "$XDG_CONFIG_HOME/ocd/nodes/<name>.toml".
     #[arg(value_name = "entry_name")]
     pub entry_name: String,
 }
 
+/// Adopt existing local repository as node.
+#[derive(Parser, Clone, Debug)]
+pub struct AddOptions {
+    /// Path to repository to adopt.
+    #[arg(value_name = "path")]
+    pub path: PathBuf,
+
+    /// Name of new node, defaults to the directory name of repository without ".git" suffix.
+    #[arg(short, long, value_name = "name")]
+    pub name: Option<String>,
+
+    /// Adopt repository as bare-alias node deployed to given working directory alias.
+    ///
+    /// Kept as given in the node configuration file, such that "~" and shell variables are
+    /// expanded whenever the cluster definition is loaded. Adopted repositories are normal nodes
+    /// otherwise.
+    #[arg(short, long, value_name = "dir")]
+    pub bare_alias: Option<WorkDirAlias>,
+
+    /// Leave repository where it is, and define node as external repository through its path.
+    #[arg(short, long, conflicts_with = "bare_alias")]
+    pub link: bool,
+}
+
+/// Target entries of deploy, undeploy, and rm commands.
+#[derive(Args, Clone, Debug)]
+pub struct TargetOptions {
+    /// List of nodes to target.
+    #[arg(value_parser, num_args = 1.., value_delimiter = ',', value_name = "pattern", pattern_help())]
+    pub patterns: Vec<String>,
+
+    /// Target every node by name.
+    ///
+    /// Bypasses glob matching entirely, such that there is no need to pass a "*" pattern that the
+    /// shell may expand. Nodes are targeted in dependency order.
+    #[arg(short, long, conflicts_with = "patterns")]
+    pub all: bool,
+
+    /// Read node names to target from standard input, one per line.
+    ///
+    /// Allows selections to come from other tools, e.g., fzf(1) or scripts. Names are matched the
+    /// same way patterns are, blank lines are ignored.
+    #[arg(long, conflicts_with_all = ["patterns", "all"])]
+    pub stdin: bool,
+}
+
 /// Deploy node of cluster.
 #[derive(Parser, Clone, Debug)]
-#[command(author, about, long_about)]
 pub struct DeployOptions {
-    /// List of nodes to deploy ("root" is always deployed).
-    #[arg(value_parser, num_args = 1.., value_delimiter = ',', value_name = "pattern")]
-    pub patterns: Vec<String>,
+    #[command(flatten)]
+    pub targets: TargetOptions,
+
+    /// Pick nodes to deploy from a checklist of every node.
+    ///
+    /// Each node is listed along with its current deployment state, such that there is no need to
+    /// remember node names. Picked nodes are deployed the same way named targets are.
+    #[arg(short, long, conflicts_with_all = ["patterns", "all", "stdin"])]
+    pub interactive: bool,
 
     /// Do not deploy dependencies of target nodes.
-    #[arg(short, long)]
+    ///
+    /// Only the nodes matched by the given patterns are deployed. Dependencies that are not
+    /// deployed already stay undeployed, which may leave target nodes incomplete.
+    #[arg(short, long, conflicts_with = "all")]
     pub only: bool,
 
     /// Deploy excluded files as well.
+    ///
+    /// Ignores the "excluded" sparsity rules of each target node, such that the full index is
+    /// deployed.
     #[arg(short, long)]
     pub with_excluded: bool,
+
+    /// Overwrite files modified since their last deployment without asking.
+    ///
+    /// By default, deployment asks for confirmation before it overwrites files that changed since
+    /// OCD last placed them, e.g., because they are still being edited. Also overwrites sparse
+    /// checkout files that OCD did not write, which are never touched otherwise. Files that
+    /// bare-alias nodes would take over from their working directory alias are overwritten as
+    /// well, instead of failing their deployment.
+    #[arg(short, long)]
+    pub force: bool,
+
+    /// Move files that bare-alias nodes would take over aside before deploying them.
+    ///
+    /// Bare-alias nodes refuse to deploy over files in their working directory alias that they do
+    /// not own, e.g., a ".bashrc" that was already there, listing each of them. Backups keep
+    /// those files next to where they were, with ".ocd-backup" appended to their names.
+    #[arg(short, long, conflicts_with = "force")]
+    pub backup: bool,
+
+    /// Deploy with named sparse profile of each node instead of its exclusion rules.
+    ///
+    /// Sparse profiles are defined per node through the "sparse_profiles" setting, e.g.,
+    /// "sparse_profiles.minimal = [\"plugins/\"]". Nodes that do not define the profile are
+    /// deployed with their exclusion rules as usual. Fails if no node defines the profile.
+    #[arg(short, long, conflicts_with = "with_excluded", value_name = "name")]
+    pub profile: Option<String>,
+
+    /// Only deploy nodes selected by this host profile of root, along with their dependencies.
+    ///
+    /// Host profiles are defined in root through "[profile.<name>]" tables. Defaults to the first
+    /// host profile whose "hosts" patterns match the hostname of the machine, if any. Target nodes
+    /// that the host profile does not select are skipped with a warning.
+    #[arg(long, value_name = "name")]
+    pub host_profile: Option<String>,
+
+    /// Check whether target entries are deployed, without deploying anything.
+    ///
+    /// Lists each target entry that deployment would change, and fails if there is any, such that
+    /// scripts and CI jobs can tell whether the state of the machine drifted from the cluster.
+    #[arg(short, long)]
+    pub check: bool,
+
+    /// Check out the commits that target nodes are locked to before deploying them.
+    ///
+    /// Commits come from "cluster.lock" written by "ocd freeze". Locked commits missing from a
+    /// node are fetched from its origin remote. HEAD of each locked node is detached at its
+    /// commit, use "ocd thaw" to put nodes back on their branches. Target nodes without a locked
+    /// commit are deployed as they are. Along with "--check", nodes not at their locked commit
+    /// count as changed. Fails if there is no lockfile.
+    #[arg(short, long)]
+    pub locked: bool,
 }
 
 /// Undeploy nodes of cluster.
 #[derive(Parser, Clone, Debug)]
-#[command(author, about, long_about)]
 pub struct UndeployOptions {
-    /// List of nodes to undeploy ("root" cannot be undeployed).
-    #[arg(value_parser, num_args = 1.., value_delimiter = ',', value_name = "pattern")]
-    pub patterns: Vec<String>,
+    #[command(flatten)]
+    pub targets: TargetOptions,
+
+    /// Pick nodes to undeploy from a checklist of every node.
+    ///
+    /// Each node is listed along with its current deployment state, such that there is no need to
+    /// remember node names. Picked nodes are undeployed the same way named targets are.
+    #[arg(short, long, conflicts_with_all = ["patterns", "all", "stdin"])]
+    pub interactive: bool,
 
     /// Do not undeploy dependencies of target nodes.
-    #[arg(short, long)]
+    ///
+    /// Only the nodes matched by the given patterns are undeployed, leaving their dependencies
+    /// deployed for other nodes that may still need them.
+    #[arg(short, long, conflicts_with = "all")]
     pub only: bool,
 
     /// Undeploy excluded files only.
+    ///
+    /// Reapplies the "excluded" sparsity rules of each target node, removing files that were
+    /// deployed through "ocd deploy --with-excluded" while keeping the rest deployed.
     #[arg(short, long)]
     pub excluded_only: bool,
+
+    /// Overwrite sparse checkout files that OCD did not write.
+    ///
+    /// Sparse checkout files without the marker header of OCD belong to the user, and are never
+    /// touched otherwise.
+    #[arg(short, long)]
+    pub force: bool,
 }
 
 /// Remove target node from cluster.
 #[derive(Parser, Clone, Debug)]
-#[command(author, about, long_about)]
 pub struct RemoveOptions {
-    /// List of nodes to remove ("root" will nuke cluster).
-    #[arg(value_parser, num_args = 1.., value_delimiter = ',', value_name = "pattern")]
-    pub patterns: Vec<String>,
+    #[command(flatten)]
+    pub targets: TargetOptions,
+
+    /// Remove nodes that depend on target nodes along with them.
+    ///
+    /// Without it, target nodes that other nodes in the repository store still depend on are
+    /// refused, such that no node is left with a missing dependency. Nodes that depend on target
+    /// nodes through other nodes are removed as well.
+    #[arg(short, long, conflicts_with = "all")]
+    pub cascade: bool,
+}
+
+/// Remove OCD from this machine.
+#[derive(Parser, Clone, Debug)]
+pub struct UninstallOptions {
+    /// Nodes whose deployed files stay in place.
+    ///
+    /// Repositories of kept nodes are still removed, leaving their deployed files as plain files.
+    #[arg(short, long, num_args = 1.., value_delimiter = ',', value_name = "pattern", pattern_help())]
+    pub keep: Vec<String>,
+
+    /// Undeploy root too, instead of leaving its deployed files in place.
+    #[arg(short, long)]
+    pub undeploy_root: bool,
 }
 
 /// List current entries in cluster.
 #[derive(Parser, Clone, Debug)]
-#[command(author, about, long_about)]
 pub struct ListOptions {
+    /// List of entries to list (all entries by default).
+    #[arg(value_parser, num_args = 1.., value_delimiter = ',', value_name = "pattern", pattern_help())]
+    pub patterns: Vec<String>,
+
+    /// List transitive dependencies of target nodes too.
+    ///
+    /// Dependencies that were not targeted themselves are annotated with the target nodes that
+    /// depend on them.
+    #[arg(short, long, requires = "patterns")]
+    pub with_deps: bool,
+
     /// Only list names of each entry only.
     #[arg(short, long)]
     pub names_only: bool,
-}
 
-#[instrument(skip(opts), level = "debug")]
-async fn run_clone(action: HookAction, opts: CloneOptions) -> Result<()> {
-    // INVARIANT: Wipe out cluster if root cannot be cloned or deployed.
-    if let Err(error) = Root::new_clone(&opts.url) {
-        warn!("Root clone failure, clearing broken cluster");
-        let config_dir = config_dir()?;
-        if config_dir.exists() {
-            remove_dir_all(&config_dir)
-                .with_context(|| format!("Failed to remove {config_dir:?}"))?;
-        }
+    /// Fetch remotes of each entry before listing.
+    ///
+    /// Without fetching, the upstream column compares against whatever remote-tracking branches
+    /// were last fetched. Entries without an "origin" remote are skipped. Nodes missing from the
+    /// repository store are only cloned when fetching, and listed as not cloned otherwise.
+    #[arg(short, long)]
+    pub fetch: bool,
 
-        let data_dir = data_dir()?;
-        if data_dir.exists() {
-            remove_dir_all(&data_dir).with_context(|| format!("Failed to remove {data_dir:?}"))?;
-        }
+    /// Number of nodes to gather information for concurrently.
+    ///
+    /// Defaults to the "jobs" setting of root, or the number of available CPU cores if root does
+    /// not define it.
+    #[arg(short, long, value_name = "limit")]
+    pub jobs: Option<usize>,
 
-        return Err(error);
-    }
+    /// Format of listing.
+    ///
+    /// JSON and YAML are printed to standard output, with root named "root", and dependencies
+    /// carrying the targets that depend on them in "required_by". Fields that cannot be known,
+    /// e.g., the branch of a node that was not cloned, are null.
+    #[arg(default_value_t = OutputFormat::default(), long, value_enum, value_name = "format")]
+    pub format: OutputFormat,
+}
 
-    let cluster = Cluster::new()?;
-    let mut hooks = HookRunner::new()?;
-    hooks.set_action(action);
+/// Repair entries of cluster.
+#[derive(Parser, Clone, Debug)]
+#[command(group(ArgGroup::new("repairs").required(true).multiple(true)))]
+pub struct RepairOptions {
+    /// List of nodes to repair (all nodes by default).
+    #[arg(value_parser, num_args = 1.., value_delimiter = ',', value_name = "pattern", pattern_help())]
+    pub patterns: Vec<String>,
 
-    hooks.run("clone", HookKind::Pre, None)?;
-    let multi_clone = MultiNodeClone::new(&cluster, opts.jobs)?;
-    multi_clone.clone_all().await?;
-    hooks.run("clone", HookKind::Post, None)?;
+    /// Repair origin remotes that diverged from the cluster definition.
+    ///
+    /// Points the origin remote of each target node to the URL of its cluster definition, adding
+    /// the origin remote if it is missing.
+    #[arg(short, long, group = "repairs")]
+    pub remotes: bool,
 
-    Ok(())
+    /// Ask how to repair each diverged origin remote.
+    ///
+    /// Offers to adopt the URL of the local origin remote into the cluster definition instead of
+    /// rewriting the origin remote.
+    #[arg(short, long)]
+    pub interactive: bool,
 }
 
-pub fn run_init(action: HookAction, opts: InitOptions) -> Result<()> {
-    let mut hooks = HookRunner::new()?;
-    hooks.set_action(action);
+/// Edit exclusion rules of nodes.
+#[derive(Parser, Clone, Debug)]
+pub struct ExcludesOptions {
+    /// Kind of edit to perform.
+    #[command(subcommand)]
+    pub action: ExcludesAction,
+}
 
-    hooks.run("init", HookKind::Pre, Some(&vec![opts.entry_name.clone()]))?;
+/// Edits available for exclusion rules.
+#[derive(Subcommand, Clone, Debug)]
+pub enum ExcludesAction {
+    /// Add exclusion rules to target nodes.
+    ///
+    /// Rules that a target node already excludes are not added twice.
+    Add(ExcludesEditOptions),
 
-    match opts.entry_name.as_str() {
-        "root" => {
-            let path = config_dir()?.join(format!("{}.toml", opts.entry_name));
-            if !path.exists() {
-                return Err(anyhow!("No root entry to initialize! Define {path:?} first!"));
-            }
+    /// Remove exclusion rules from target nodes.
+    ///
+    /// Rules must match existing exclusion rules exactly to be removed.
+    #[command(name = "remove", visible_alias = "rm")]
+    Remove(ExcludesEditOptions),
+}
 
-            let data = std::fs::read_to_string(path)?;
-            let root: RootEntry = toml::de::from_str(&data)?;
-            let _ = Root::new_init(&root)?;
-        }
-        &_ => {
-            let cluster = Cluster::new()?;
-            let _ = Root::new_open(&cluster.root)
-                .with_context(|| "Root may not have been properly initialized")?;
+/// Exclusion rules and target nodes to edit.
+#[derive(Parser, Clone, Debug)]
+pub struct ExcludesEditOptions {
+    /// List of sparsity rules to edit.
+    ///
+    /// Rules use the same gitignore-style syntax as the "excluded" setting of node configuration
+    /// files. Quote rules to keep the shell from expanding them.
+    #[arg(value_parser, num_args = 1.., required = true, value_name = "rule")]
+    pub rules: Vec<String>,
 
-            let path = config_dir()?.join("nodes").join(format!("{}.toml", opts.entry_name));
-            if !path.exists() {
-                return Err(anyhow!("No node entry to initialize! Define {path:?} first!"));
-            }
+    /// List of nodes to edit ("root" is skipped).
+    #[arg(
+        short,
+        long,
+        required = true,
+        value_delimiter = ',',
+        value_name = "pattern",
+        pattern_help()
+    )]
+    pub nodes: Vec<String>,
+}
 
-            let data = std::fs::read_to_string(path)?;
-            let node: NodeEntry = toml::de::from_str(&data)?;
-            let _ = Node::new_init(&opts.entry_name, &node)?;
-        }
-    }
+/// Migrate remote URLs of entries.
+#[derive(Parser, Clone, Debug)]
+pub struct MigrateRemoteOptions {
+    /// List of entries to migrate (all entries by default).
+    #[arg(value_parser, num_args = 1.., value_delimiter = ',', value_name = "pattern", pattern_help())]
+    pub patterns: Vec<String>,
 
-    hooks.run("init", HookKind::Post, Some(&vec![opts.entry_name.clone()]))?;
+    /// Text of URLs to replace, e.g., "git@old-host:".
+    #[arg(long, value_name = "text")]
+    pub from: String,
 
-    Ok(())
-}
+    /// Text to replace it with, e.g., "git@new-host:".
+    #[arg(long, value_name = "text")]
+    pub to: String,
+
+    /// Make sure each rewritten URL can be reached before using it.
+    ///
+    /// Entries whose rewritten URL cannot be reached are skipped with a warning, leaving both
+    /// their cluster definition and their origin remote untouched.
+    #[arg(short, long)]
+    pub verify: bool,
+}
+
+/// Convert node between deployment kinds.
+#[derive(Parser, Clone, Debug)]
+pub struct ConvertOptions {
+    /// Name of node to convert.
+    #[arg(value_name = "node")]
+    pub node: String,
+
+    /// Deployment kind to convert node to, either "normal" or "bare-alias".
+    #[arg(long, value_name = "kind")]
+    pub to: DeploymentKind,
+
+    /// Working directory alias of node converted to bare-alias.
+    ///
+    /// Kept as given in the node configuration file, such that "~" and shell variables are
+    /// expanded whenever the cluster definition is loaded. Defaults to the current working
+    /// directory alias of node.
+    #[arg(short, long, value_name = "path")]
+    pub worktree: Option<WorkDirAlias>,
+
+    /// Deploy node once it is converted.
+    #[arg(short, long)]
+    pub deploy: bool,
+}
+
+/// Rename node.
+#[derive(Parser, Clone, Debug)]
+pub struct RenameOptions {
+    /// Current name of node.
+    #[arg(value_name = "old")]
+    pub old: String,
+
+    /// New name of node.
+    #[arg(value_name = "new")]
+    pub new: String,
+}
+
+/// Pick nodes through fuzzy finder.
+#[derive(Parser, Clone, Debug)]
+pub struct PickOptions {
+    /// Action to run on picked nodes, printing them if none is given.
+    #[arg(value_enum, value_name = "action")]
+    pub action: Option<PickAction>,
+
+    /// Pick exactly one node.
+    #[arg(short, long)]
+    pub single: bool,
+
+    /// Arguments of Git command to run on picked nodes through the "git" action.
+    #[arg(last = true, value_name = "git-args")]
+    pub git_args: Vec<OsString>,
+}
+
+/// Actions to run on picked nodes.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum)]
+pub enum PickAction {
+    /// Deploy picked nodes along with their dependencies.
+    Deploy,
+
+    /// Undeploy picked nodes along with their dependencies.
+    Undeploy,
+
+    /// Run Git command on every picked node.
+    Git,
+}
+
+/// Show status of target entry.
+#[derive(Parser, Clone, Debug)]
+pub struct StatusOptions {
+    /// Name of node to show status of, or "root" (entire cluster by default).
+    #[arg(value_name = "entry")]
+    pub entry: Option<String>,
+
+    /// List untracked files next to tracked files.
+    ///
+    /// Only directories that directly contain tracked files are scanned, leaving their
+    /// subdirectories alone. Files ignored through gitignore rules are never listed.
+    #[arg(short, long, requires = "entry")]
+    pub untracked: bool,
+
+    /// Only list entries of cluster that are not clean, one per line.
+    ///
+    /// Each line starts with flags for staged changes "S", unstaged changes "M", and untracked
+    /// files "?", with "-" for each one that is absent, followed by commits ahead/behind of
+    /// upstream if there are any, and the name of the entry. Lists nothing if every entry is
+    /// clean.
+    #[arg(short, long, conflicts_with_all = ["entry", "untracked"])]
+    pub short: bool,
+
+    /// Show status of sync daemon instead.
+    ///
+    /// Tells whether a daemon is running, and when and why it last reconciled the cluster, along
+    /// with the error that reconcile failed with, if any.
+    #[arg(long, conflicts_with_all = ["entry", "untracked", "short"])]
+    pub sync: bool,
+
+    /// Format of status overview.
+    ///
+    /// JSON and YAML are printed to standard output, listing every entry along with flags for
+    /// staged changes, unstaged changes, and untracked files, which are null for entries without
+    /// a working tree of their own.
+    #[arg(
+        default_value_t = OutputFormat::default(),
+        long,
+        conflicts_with_all = ["entry", "untracked", "short", "sync"],
+        value_enum,
+        value_name = "format"
+    )]
+    pub format: OutputFormat,
+}
+
+/// Show how deployed files differ from repositories.
+#[derive(Parser, Clone, Debug)]
+pub struct DiffOptions {
+    /// List of entries to diff (root and every bare-alias node by default).
+    #[arg(value_parser, num_args = 1.., value_delimiter = ',', value_name = "pattern", pattern_help())]
+    pub patterns: Vec<String>,
+
+    /// Show diffstat of each entry, followed by a summary of the entire cluster.
+    #[arg(short, long)]
+    pub stat: bool,
+}
+
+/// Remove untracked files of target node.
+#[derive(Parser, Clone, Debug)]
+pub struct CleanOptions {
+    /// Name of node to clean.
+    #[arg(value_name = "node")]
+    pub node: String,
+
+    /// Remove untracked files instead of listing them.
+    ///
+    /// Confirmation is still asked for before anything is removed.
+    #[arg(short, long)]
+    pub force: bool,
+}
+
+/// Remove files left behind by removed nodes.
+#[derive(Parser, Clone, Debug)]
+pub struct PruneArtifactsOptions {
+    /// Remove files left behind instead of listing them.
+    ///
+    /// Confirmation is still asked for before anything is removed.
+    #[arg(short, long)]
+    pub force: bool,
+}
+
+/// Validate cluster definition.
+#[derive(Parser, Clone, Debug)]
+pub struct ValidateOptions {
+    /// Validate cluster definition staged in the index of root instead.
+    ///
+    /// Honors "$GIT_INDEX_FILE", such that it validates what is about to be committed when run as
+    /// a Git hook.
+    #[arg(long)]
+    pub staged: bool,
+}
+
+/// Diagnose the whole setup of this machine.
+#[derive(Parser, Clone, Debug)]
+pub struct DoctorOptions {
+    /// Format of diagnosis.
+    ///
+    /// JSON and YAML are printed to standard output, listing every finding along with its
+    /// verdict and suggested fix, followed by the number of findings of each verdict.
+    #[arg(default_value_t = OutputFormat::default(), long, value_enum, value_name = "format")]
+    pub format: OutputFormat,
+}
+
+/// Explain why node would be deployed.
+#[derive(Parser, Clone, Debug)]
+pub struct WhyOptions {
+    /// Name of node to explain.
+    #[arg(value_name = "node")]
+    pub node: String,
+
+    /// Target patterns that would be given to "ocd deploy".
+    #[arg(value_parser, num_args = 1.., value_delimiter = ',', value_name = "pattern", pattern_help())]
+    pub patterns: Vec<String>,
+
+    /// Sparse profile that would be given to "ocd deploy".
+    #[arg(short, long, value_name = "name")]
+    pub profile: Option<String>,
+}
+
+/// Reclaim disk space of nodes.
+#[derive(Parser, Clone, Debug)]
+pub struct GcOptions {
+    /// List of nodes to reclaim disk space of (all nodes by default).
+    #[arg(value_parser, num_args = 1.., value_delimiter = ',', value_name = "pattern", pattern_help())]
+    pub patterns: Vec<String>,
+
+    /// Turn target nodes into shallow clones.
+    ///
+    /// History older than the "shallow_since" setting of each target node is dropped. Target
+    /// nodes without the setting are only packed, unless "--since" is given.
+    #[arg(short, long)]
+    pub prune_history: bool,
+
+    /// Date to prune history back to, overriding the "shallow_since" setting of target nodes.
+    ///
+    /// Takes any date "git fetch --shallow-since" accepts, e.g., "2024-01-01" or "1 year ago".
+    #[arg(short, long, value_name = "date", requires = "prune_history")]
+    pub since: Option<String>,
+}
+
+/// Pull entries from their upstream.
+#[derive(Parser, Clone, Debug)]
+pub struct PullOptions {
+    /// List of entries to pull (all entries by default).
+    #[arg(value_parser, num_args = 1.., value_delimiter = ',', value_name = "pattern", pattern_help())]
+    pub patterns: Vec<String>,
+
+    /// Strategy for entries that diverged from their upstream, overriding "pull_strategy"
+    /// settings.
+    #[arg(short, long, value_enum, value_name = "strategy")]
+    pub strategy: Option<PullStrategy>,
+
+    /// Number of entries to fetch concurrently.
+    ///
+    /// Defaults to the "jobs" setting of root, or the number of available CPU cores if root does
+    /// not define it.
+    #[arg(short, long, value_name = "limit")]
+    pub jobs: Option<usize>,
+}
+
+/// Bring this machine in line with the cluster definition at the upstream of root.
+#[derive(Parser, Clone, Debug)]
+pub struct FetchConfigOptions {
+    /// Only list how the incoming cluster definition differs, without pulling or applying it.
+    #[arg(short, long)]
+    pub check: bool,
+}
+
+/// Reconcile repository store with cluster definition.
+#[derive(Parser, Clone, Debug)]
+pub struct SyncOptions {
+    /// Stay resident, reconciling on a timer and on changes to the configuration directory.
+    #[arg(short, long)]
+    pub daemon: bool,
+
+    /// Seconds between reconciles of daemon.
+    #[arg(default_value_t = 300, short, long, requires = "daemon", value_name = "seconds")]
+    pub interval: u64,
+}
+
+/// Pin nodes to their current commits.
+#[derive(Parser, Clone, Debug)]
+pub struct FreezeOptions {
+    /// List of nodes to freeze (all nodes by default).
+    #[arg(value_parser, num_args = 1.., value_delimiter = ',', value_name = "pattern", pattern_help())]
+    pub patterns: Vec<String>,
+}
+
+/// Unpin nodes from their locked commits.
+#[derive(Parser, Clone, Debug)]
+pub struct ThawOptions {
+    /// List of nodes to thaw (all locked nodes by default).
+    #[arg(value_parser, num_args = 1.., value_delimiter = ',', value_name = "pattern", pattern_help())]
+    pub patterns: Vec<String>,
+}
+
+/// Roll node back to its previous deployment.
+#[derive(Parser, Clone, Debug)]
+pub struct RollbackOptions {
+    /// Name of node to roll back.
+    #[arg(value_name = "node")]
+    pub node: String,
+
+    /// List commits that node was deployed at, most recently deployed first, without rolling
+    /// back.
+    #[arg(short, long)]
+    pub list: bool,
+}
+
+/// Undo the last deployment transaction.
+#[derive(Parser, Clone, Debug)]
+pub struct UndoOptions {
+    /// Show what the last transaction placed and removed, and what undoing it would do, without
+    /// undoing it.
+    #[arg(short, long)]
+    pub list: bool,
+}
+
+/// Generate container setup snippets.
+#[derive(Parser, Clone, Debug)]
+pub struct DevcontainerOptions {
+    /// Kind of snippet action to perform.
+    #[command(subcommand)]
+    pub action: DevcontainerAction,
+}
+
+/// Actions available for container setup snippets.
+#[derive(Subcommand, Clone, Debug)]
+pub enum DevcontainerAction {
+    /// Emit container setup snippet.
+    Emit(DevcontainerEmitOptions),
+}
+
+/// Container setup snippet to emit.
+#[derive(Parser, Clone, Debug)]
+pub struct DevcontainerEmitOptions {
+    /// List of nodes to deploy inside container (all nodes by default).
+    ///
+    /// Patterns are passed to "ocd deploy" inside the container as they are, so dependencies of
+    /// each target are deployed as well.
+    #[arg(value_parser, num_args = 1.., value_delimiter = ',', value_name = "pattern")]
+    pub patterns: Vec<String>,
+
+    /// Format of snippet.
+    #[arg(default_value_t = SnippetFormat::default(), short, long, value_enum, value_name = "format")]
+    pub format: SnippetFormat,
+
+    /// Sparse profile to deploy target nodes with.
+    #[arg(short, long, value_name = "name")]
+    pub profile: Option<String>,
+
+    /// URL to clone cluster from (origin remote of root by default).
+    #[arg(short, long, value_name = "url")]
+    pub url: Option<String>,
+
+    /// File to write snippet into.
+    ///
+    /// Overwritten if it exists already. Snippet is printed to standard output if no file is
+    /// given.
+    #[arg(short, long, value_name = "file")]
+    pub output: Option<PathBuf>,
+}
+
+/// Generate redacted cluster definition.
+#[derive(Parser, Clone, Debug)]
+pub struct ShareOptions {
+    /// File to write redacted cluster definition into.
+    ///
+    /// Overwritten if it exists already. Redacted cluster definition is printed to standard output
+    /// if no file is given.
+    #[arg(short, long, value_name = "file")]
+    pub output: Option<PathBuf>,
+}
+
+/// Print cluster status for shell prompts.
+#[derive(Parser, Clone, Debug)]
+pub struct PromptSegmentOptions {
+    /// Ignore cached status, recomputing status of every entry.
+    #[arg(short, long)]
+    pub refresh: bool,
+}
+
+/// Show usage statistics.
+#[derive(Parser, Clone, Debug)]
+pub struct StatsOptions {
+    /// Forget every usage statistic recorded so far.
+    #[arg(long)]
+    pub clear: bool,
+}
+
+/// Generate bug report bundle.
+#[derive(Parser, Clone, Debug)]
+pub struct BugreportOptions {
+    /// File to write bug report bundle into.
+    ///
+    /// Overwritten if it exists already. Bug report bundle is printed to standard output if no
+    /// file is given.
+    #[arg(short, long, value_name = "file")]
+    pub output: Option<PathBuf>,
+}
+
+/// Generate shell completion functions.
+#[derive(Parser, Clone, Debug)]
+pub struct CompletionsOptions {
+    /// Shell to generate completion function for.
+    #[arg(default_value_t = CompletionShell::default(), value_enum, value_name = "shell")]
+    pub shell: CompletionShell,
+
+    /// Complete node names through the cached completion index.
+    #[arg(short, long)]
+    pub dynamic: bool,
+
+    /// List node names from completion index, one per line.
+    ///
+    /// Called by dynamic completion functions on each tab press.
+    #[arg(long, hide = true)]
+    pub list_nodes: bool,
+}
+
+/// Generate man pages.
+#[derive(Parser, Clone, Debug)]
+pub struct ManOptions {
+    /// Directory to write man pages into.
+    ///
+    /// Created if it does not exist already. Existing man pages of the same name are overwritten.
+    #[arg(default_value = ".", short, long, value_name = "dir")]
+    pub output: PathBuf,
+}
+
+const PATTERN_HELP: &str = "\
+Each pattern is a shell-style glob matched against entry names, e.g., \"*sh\" matches both \"sh\" \
+and \"bash\". Quote patterns to keep the shell from expanding them. Multiple patterns can be given \
+as separate arguments, or as one comma-separated list.";
+
+/// Append [`PATTERN_HELP`] to long help of pattern arguments.
+trait PatternHelp {
+    fn pattern_help(self) -> Self;
+}
+
+impl PatternHelp for Arg {
+    fn pattern_help(self) -> Self {
+        // Single paragraph doc comments only set short help, with their trailing period stripped.
+        let help = match (self.get_long_help(), self.get_help()) {
+            (Some(long), _) => long.to_string(),
+            (None, Some(short)) => format!("{short}."),
+            (None, None) => String::new(),
+        };
+        self.long_help(format!("{help}\n\n{PATTERN_HELP}"))
+    }
+}
+
+const OCD_EXAMPLES: &str = "\
+Examples:
+  # Clone cluster, and deploy the vim node with its dependencies.
+  ocd clone https://github.com/user/dotfiles.git
+  ocd deploy vim
+
+  # Run git status on every node whose name ends in \"sh\".
+  ocd '*sh' status
+
+  # Run git status on nodes whose names are read from standard input.
+  printf 'vim\\nbash\\n' | ocd - status
+
+  # Never run command hooks.
+  ocd --run-hook never deploy vim
+
+  # Ask before cloning nodes that are missing from the repository store.
+  ocd --auto-clone prompt deploy vim";
+
+const CLONE_EXAMPLES: &str = "\
+Examples:
+  # Clone cluster using at most four concurrent node clones.
+  ocd clone --jobs 4 git@github.com:user/dotfiles.git
+
+  # Clone vim and its dependencies only, then clone the rest later on.
+  ocd clone --only vim git@github.com:user/dotfiles.git
+  ocd clone git@github.com:user/dotfiles.git
+
+  # Clone every node except work nodes.
+  ocd clone --skip 'work-*' git@github.com:user/dotfiles.git
+
+  # Clone the nodes that the \"server\" host profile of root selects.
+  ocd clone --host-profile server git@github.com:user/dotfiles.git";
+
+const INIT_EXAMPLES: &str = "\
+Examples:
+  # Initialize root, then a node defined at $XDG_CONFIG_HOME/ocd/nodes/vim.toml.
+  ocd init root
+  ocd init vim";
+
+const ADD_EXAMPLES: &str = "\
+Examples:
+  # Move ~/src/nvim into repository store as node \"nvim\".
+  ocd add ~/src/nvim
+
+  # Adopt ~/dotfiles/bash as node \"bash\", deployed to $HOME.
+  ocd add --bare-alias '$HOME' ~/dotfiles/bash
+
+  # Define node \"notes\" for ~/notes, leaving it where it is.
+  ocd add --link --name notes ~/notes";
+
+const DEPLOY_EXAMPLES: &str = "\
+Examples:
+  # Deploy vim and all of its dependencies.
+  ocd deploy vim
+
+  # Deploy every node ending in \"sh\" without their dependencies.
+  ocd deploy --only '*sh'
+
+  # Deploy excluded files of root as well.
+  ocd deploy --with-excluded root
+
+  # Deploy entire cluster.
+  ocd deploy --all
+
+  # Overwrite files of vim modified since its last deployment.
+  ocd deploy --force vim
+
+  # Deploy bash, moving the files it would take over from $HOME aside.
+  ocd deploy --backup bash
+
+  # Deploy vim without the files its \"minimal\" sparse profile excludes.
+  ocd deploy --profile minimal vim
+
+  # Deploy the nodes that the \"laptop\" host profile of root selects.
+  ocd deploy --all --host-profile laptop
+
+  # Fail if deploying the entire cluster would change anything.
+  ocd deploy --all --check
+
+  # Deploy entire cluster at the commits \"ocd freeze\" locked it to.
+  ocd deploy --all --locked
+
+  # Deploy nodes picked through fzf.
+  ls ~/.config/ocd/nodes | sed 's/\\.toml$//' | fzf --multi | ocd deploy --stdin
+
+  # Pick nodes to deploy from a checklist showing their deployment state.
+  ocd deploy --interactive";
+
+const UNDEPLOY_EXAMPLES: &str = "\
+Examples:
+  # Undeploy vim and all of its dependencies.
+  ocd undeploy vim
+
+  # Undeploy excluded files of vim, but keep the rest deployed.
+  ocd undeploy --excluded-only vim
+
+  # Undeploy every node.
+  ocd undeploy --all
+
+  # Pick nodes to undeploy from a checklist showing their deployment state.
+  ocd undeploy --interactive";
+
+const REMOVE_EXAMPLES: &str = "\
+Examples:
+  # Remove vim and bash from repository store.
+  ocd rm vim,bash
+
+  # Remove every node, but keep root.
+  ocd rm --all
+
+  # Remove bash along with every node that depends on it.
+  ocd rm --cascade bash
+
+  # Remove entire cluster.
+  ocd rm root";
+
+const UNINSTALL_EXAMPLES: &str = "\
+Examples:
+  # Undeploy every node, and remove everything OCD manages.
+  ocd uninstall
+
+  # Keep configurations of vim and sh deployed as plain files.
+  ocd uninstall --keep vim,sh
+
+  # Undeploy root as well.
+  ocd uninstall --undeploy-root";
+
+const LIST_EXAMPLES: &str = "\
+Examples:
+  # List every entry, fetching remotes first.
+  ocd ls --fetch
+
+  # List names of entries only.
+  ocd ls --names-only
+
+  # Show everything deploying bash would touch.
+  ocd ls --with-deps bash
+
+  # Print names of nodes that were not cloned yet.
+  ocd ls --format json | jq -r '.[] | select(.cloned == false) | .name'";
+
+const REPAIR_EXAMPLES: &str = "\
+Examples:
+  # Point origin of every node back to its cluster definition.
+  ocd repair --remotes
+
+  # Decide per node whether to rewrite origin, or adopt it into the cluster definition.
+  ocd repair --remotes --interactive vim";
+
+const EXCLUDES_EXAMPLES: &str = "\
+Examples:
+  # Stop deploying markdown files of every work node.
+  ocd excludes add '*.md' --nodes 'work-*'
+
+  # Deploy README files of vim and sh again.
+  ocd excludes remove README.md --nodes vim,sh";
+
+const MIGRATE_REMOTE_EXAMPLES: &str = "\
+Examples:
+  # Move every entry from one Git host to another.
+  ocd migrate-remote --from 'git@old-host:' --to 'git@new-host:'
+
+  # Move work nodes only, making sure the new host has them.
+  ocd migrate-remote --verify --from github.com --to codeberg.org 'work-*'";
+
+const RENAME_EXAMPLES: &str = "\
+Examples:
+  # Rename vim after switching to neovim.
+  ocd rename vim nvim";
+
+const CONVERT_EXAMPLES: &str = "\
+Examples:
+  # Deploy vim straight to home directory instead of keeping it in the repository store.
+  ocd convert --to bare-alias --worktree '~' --deploy vim
+
+  # Stop deploying sh, but keep it around in the repository store.
+  ocd convert --to normal sh";
+
+const PICK_EXAMPLES: &str = "\
+Examples:
+  # Print picked nodes.
+  ocd pick
+
+  # Deploy picked nodes.
+  ocd pick deploy
+
+  # Run git status on one picked node.
+  ocd pick --single git -- status";
+
+const STATUS_EXAMPLES: &str = "\
+Examples:
+  # Show which entries of the cluster are dirty, ahead, or behind.
+  ocd status
+
+  # List only entries that are not clean.
+  ocd status --short
+
+  # Print status of every entry as YAML.
+  ocd status --format yaml
+
+  # Show git status of vim.
+  ocd status vim
+
+  # Suggest files worth adding to vim.
+  ocd status --untracked vim
+
+  # Add every suggested file to vim, whose working directory alias is the home directory.
+  cd ~ && ocd status --untracked vim | xargs ocd vim add
+
+  # Check on sync daemon.
+  ocd status --sync";
+
+const DIFF_EXAMPLES: &str = "\
+Examples:
+  # Show how deployed files of the entire cluster drifted from their repositories.
+  ocd diff
+
+  # Show how deployed files of vim and bash drifted.
+  ocd diff vim,bash
+
+  # Summarize which entries drifted, and by how much.
+  ocd diff --stat";
+
+const CLEAN_EXAMPLES: &str = "\
+Examples:
+  # List untracked files that would be removed from vim.
+  ocd clean vim
+
+  # Remove them after confirmation.
+  ocd clean --force vim";
+
+const PRUNE_ARTIFACTS_EXAMPLES: &str = "\
+Examples:
+  # List files left behind by removed nodes.
+  ocd prune-artifacts
+
+  # Remove them after confirmation.
+  ocd prune-artifacts --force";
+
+const GC_EXAMPLES: &str = "\
+Examples:
+  # Pack repositories of every node.
+  ocd gc
+
+  # Drop history of vim older than its \"shallow_since\" setting.
+  ocd gc --prune-history vim
+
+  # Drop history older than a year of every node.
+  ocd gc --prune-history --since '1 year ago'";
+
+const PULL_EXAMPLES: &str = "\
+Examples:
+  # Pull every entry, following their \"pull_strategy\" settings.
+  ocd pull
+
+  # Pull root and vim, rebasing them if they diverged.
+  ocd pull --strategy rebase root vim
+
+  # Ask what to do with each diverged node.
+  ocd pull --strategy prompt '*'";
+
+const FETCH_CONFIG_EXAMPLES: &str = "\
+Examples:
+  # See what changed upstream before applying it.
+  ocd fetch-config --check
+
+  # Clone, remove, and redeploy nodes as the upstream cluster definition says.
+  ocd fetch-config";
+
+const SYNC_EXAMPLES: &str = "\
+Examples:
+  # Clone missing entries and deploy entire cluster once.
+  ocd sync
+
+  # Keep cluster in sync, reconciling every ten minutes and on configuration changes.
+  ocd sync --daemon --interval 600";
+
+const FREEZE_EXAMPLES: &str = "\
+Examples:
+  # Pin every node to its current commit.
+  ocd freeze
+
+  # Pin vim and every node ending in \"sh\".
+  ocd freeze vim '*sh'
+
+  # Deploy entire cluster at its locked commits.
+  ocd deploy --all --locked";
+
+const THAW_EXAMPLES: &str = "\
+Examples:
+  # Unpin every node, putting them back on their branches.
+  ocd thaw
+
+  # Unpin vim only.
+  ocd thaw vim";
+
+const ROLLBACK_EXAMPLES: &str = "\
+Examples:
+  # Undo a bad pull of vim.
+  ocd vim pull
+  ocd rollback vim
+
+  # Show which commits vim can be rolled back to.
+  ocd rollback --list vim";
+
+const UNDO_EXAMPLES: &str = "\
+Examples:
+  # Take back a deployment of the wrong nodes.
+  ocd deploy --all
+  ocd undo
+
+  # Show what undoing would bring back first.
+  ocd undo --list";
+
+const WHY_EXAMPLES: &str = "\
+Examples:
+  # Show every node that pulls in readline as a dependency.
+  ocd why readline
+
+  # Explain why deploying bash and vim touches readline.
+  ocd why readline bash vim
+
+  # Show which exclusion rules apply to vim under the minimal profile.
+  ocd why --profile minimal vim 'v*'";
+
+const VALIDATE_EXAMPLES: &str = "\
+Examples:
+  # Validate cluster definition in the configuration directory.
+  ocd validate
+
+  # Validate cluster definition about to be committed into root.
+  ocd validate --staged";
+
+const DOCTOR_EXAMPLES: &str = "\
+Examples:
+  # Check the whole setup, and show how to fix whatever is wrong with it.
+  ocd doctor
+
+  # Print findings as JSON for monitoring.
+  ocd doctor --format json";
+
+const SHARE_EXAMPLES: &str = "\
+Examples:
+  # Write shareable copy of cluster definition.
+  ocd share --output cluster-public.toml
+
+  # Check what would be published.
+  ocd share | less";
+
+const PROMPT_SEGMENT_EXAMPLES: &str = "\
+Examples:
+  # Show cluster status in bash prompt.
+  PS1='$(ocd prompt-segment) \\$ '
+
+  # Show cluster status in starship prompt through a custom module.
+  [custom.ocd]
+  command = \"ocd prompt-segment\"
+  when = true";
+
+const DEVCONTAINER_EXAMPLES: &str = "\
+Examples:
+  # Print devcontainer.json fragment that deploys every node.
+  ocd devcontainer emit
+
+  # Add Dockerfile instruction that deploys vim and sh with the server profile.
+  ocd devcontainer emit --format dockerfile --profile server vim sh >> Dockerfile";
+
+const MAN_EXAMPLES: &str = "\
+Examples:
+  # Generate man pages for packaging.
+  ocd man --output target/man";
+
+const COMPLETIONS_EXAMPLES: &str = "\
+Examples:
+  # Complete commands and node names in zsh.
+  ocd completions --dynamic zsh > \"${fpath[1]}/_ocd\"
+
+  # Load completion function into the current zsh session.
+  source <(ocd completions --dynamic zsh)
+
+  # Complete commands and node names in bash.
+  ocd completions --dynamic bash > ~/.local/share/bash-completion/completions/ocd
+
+  # Complete commands and node names in fish.
+  ocd completions --dynamic fish > ~/.config/fish/completions/ocd.fish
+
+  # Complete commands and node names in PowerShell.
+  ocd completions --dynamic powershell | Out-String | Invoke-Expression";
+
+const STATS_EXAMPLES: &str = "\
+Examples:
+  # Show which commands and nodes are used most.
+  ocd stats
+
+  # Forget usage statistics, and start over.
+  ocd stats --clear";
+
+const BUGREPORT_EXAMPLES: &str = "\
+Examples:
+  # Write bug report bundle to attach to an issue.
+  ocd bugreport --output ocd-bugreport.md
+
+  # Check what would be published.
+  ocd bugreport | less";
+
+#[instrument(skip(opts), level = "debug")]
+async fn run_clone(action: HookAction, opts: CloneOptions) -> Result<()> {
+    // INVARIANT: Reject malformed URLs before anything can be wiped out.
+    let url = normalize_url(&opts.url)?;
+
+    // INVARIANT: Resume clone that failed after root got cloned, keeping every node cloned so far.
+    // Otherwise, wipe out cluster if root cannot be cloned or deployed.
+    if Root::is_cloned_from(&url)? {
+        info!("{}", tr!("clone-resume"));
+    } else if let Err(error) = Root::new_clone(&url) {
+        warn!("{}", tr!("clone-root-failure"));
+        let config_dir = config_dir()?;
+        if config_dir.exists() {
+            remove_dir_all(&config_dir)
+                .with_context(|| tr!("remove-failure", path = format!("{config_dir:?}")))?;
+        }
+
+        let data_dir = data_dir()?;
+        if data_dir.exists() {
+            remove_dir_all(&data_dir)
+                .with_context(|| tr!("remove-failure", path = format!("{data_dir:?}")))?;
+        }
+        remove_transient_dirs()?;
+
+        return Err(error);
+    }
+
+    let mut cluster = Cluster::new()?;
+
+    // INVARIANT: Root only now got cloned, so its store layout was unknown until now.
+    set_store_layout(cluster.root.settings.store_layout.unwrap_or_default());
+    migrate_store_layout(&cluster)?;
+
+    // INVARIANT: Freshly cloned root must not deploy the subdirectories of inline nodes.
+    if cluster.nodes.values().any(|node| node.settings.deployment.kind.is_inline()) {
+        Root::new_open(&cluster.root)?.refresh()?;
+    }
+
+    let mut hooks = HookRunner::new()?;
+    hooks.set_action(action);
+
+    hooks.run("clone", HookKind::Pre, None)?;
+    let jobs = opts.jobs.or(cluster.root.settings.jobs);
+
+    // INVARIANT: Nodes of nested clusters are only known once their nested cluster got cloned.
+    let mut selected;
+    loop {
+        selected = cluster.select_nodes(opts.only.clone(), opts.skip.clone())?;
+        if let Some((_, members)) = host_profile(&cluster, opts.host_profile.as_deref())? {
+            selected.retain(|name| members.contains(name));
+        }
+        MultiNodeClone::new(&cluster, jobs)?
+            .retain(|name| selected.contains(name))
+            .clone_all()
+            .await
+            .inspect_err(|_| warn!("{}", tr!("clone-resume-hint", url = url)))?;
+        for (name, node) in &cluster.nodes {
+            if node.settings.deployment.kind.is_cluster() && selected.contains(name) {
+                let _ = Node::new_open(name, node)?;
+            }
+        }
+
+        let expanded = Cluster::new()?;
+        if expanded.nodes.len() == cluster.nodes.len() {
+            break;
+        }
+        cluster = expanded;
+    }
+
+    let left_out = cluster.nodes.len() - selected.len();
+    if left_out > 0 {
+        info!("{}", tr!("clone-left-out", count = left_out, url = url));
+    }
+    hooks.run("clone", HookKind::Post, None)?;
+
+    Ok(())
+}
+
+pub fn run_init(action: HookAction, opts: InitOptions) -> Result<()> {
+    let mut hooks = HookRunner::new()?;
+    hooks.set_action(action);
+
+    hooks.run("init", HookKind::Pre, Some(&vec![opts.entry_name.clone()]))?;
+
+    match opts.entry_name.as_str() {
+        "root" => {
+            let path = config_dir()?.join(format!("{}.toml", opts.entry_name));
+            if !path.exists() {
+                return Err(anyhow!(tr!("init-no-root", path = format!("{path:?}"))));
+            }
+
+            let data = std::fs::read_to_string(path)?;
+            let root: RootEntry = toml::de::from_str(&data)?;
+            let _ = Root::new_init(&root)?;
+        }
+        &_ => {
+            let cluster = Cluster::new()?;
+            let _ = Root::new_open(&cluster.root).with_context(|| tr!("init-root-missing"))?;
+
+            let path = config_dir()?.join("nodes").join(format!("{}.toml", opts.entry_name));
+            if !path.exists() {
+                return Err(anyhow!(tr!("init-no-node", path = format!("{path:?}"))));
+            }
+
+            let data = std::fs::read_to_string(path)?;
+            let node: NodeEntry = toml::de::from_str(&data)?;
+            let _ = Node::new_init(
+                &opts.entry_name,
+                &node,
+                cluster.root.settings.default_branch.as_deref(),
+            )?;
+        }
+    }
+
+    hooks.run("init", HookKind::Post, Some(&vec![opts.entry_name.clone()]))?;
+
+    Ok(())
+}
+
+#[instrument(skip(opts), level = "debug")]
+fn run_add(run_hook: HookAction, opts: AddOptions) -> Result<()> {
+    let source = opts
+        .path
+        .canonicalize()
+        .with_context(|| tr!("add-no-path", path = format!("{:?}", opts.path)))?;
+    let name = match opts.name {
+        Some(name) => name,
+        None => source
+            .file_name()
+            .map(|name| name.to_string_lossy().trim_end_matches(".git").to_string())
+            .filter(|name| !name.is_empty())
+            .ok_or_else(|| anyhow!(tr!("add-no-name", path = format!("{source:?}"))))?,
+    };
+
+    let mut cluster = Cluster::new()?;
+    if name == "root" || cluster.nodes.contains_key(&name) {
+        return Err(anyhow!(tr!("add-exists", name = format!("{name:?}"))));
+    }
+
+    let url = origin_url(&source)?.unwrap_or_default();
+    if url.is_empty() {
+        warn!("{}", tr!("add-no-origin", path = format!("{source:?}")));
+    }
+    let mut node = NodeEntry::builder()?.url(url);
+    if let Some(work_dir_alias) = &opts.bare_alias {
+        node = node.deployment(DeploymentKind::BareAlias, work_dir_alias.clone());
+    }
+    if opts.link {
+        node = node.path(&source);
+    }
+    let node = node.build();
+
+    let mut hooks = HookRunner::new()?;
+    hooks.set_action(run_hook);
+    let targets = vec![name.clone()];
+    hooks.run("add", HookKind::Pre, Some(&targets))?;
+
+    // INVARIANT: Define node before adopting repository, such that repositories are never moved
+    // into a cluster that does not load, and nothing is left defined if adoption fails.
+    let path = config_dir()?.join("nodes").join(format!("{name}.toml"));
+    cluster.add_node(&name, node.clone())?;
+    let adopted = Cluster::new().context(tr!("add-invalid")).and_then(|cluster| {
+        if opts.link {
+            return Ok(());
+        }
+        let _ = Node::new_adopt(&name, node_entry(&cluster, &name)?, &source)?;
+        Ok(())
+    });
+    if let Err(error) = adopted {
+        let _ = std::fs::remove_file(&path);
+        return Err(error);
+    }
+    info!("{}", tr!("add-done", name = format!("{name:?}"), path = format!("{path:?}")));
+
+    hooks.run("add", HookKind::Post, Some(&targets))?;
+
+    Ok(())
+}
+
+#[instrument(skip(opts), level = "debug")]
+pub fn run_deploy(run_hook: HookAction, opts: DeployOptions) -> Result<()> {
+    let cluster = Cluster::new()?;
+    let mut root = Root::new_open(&cluster.root)?;
+    root.set_force(opts.force);
+    let action = if opts.with_excluded { DeployAction::DeployAll } else { DeployAction::Deploy };
+
+    // INVARIANT: Dry runs never run hooks, because hooks may change the system or veto the run.
+    let mut hooks = HookRunner::new()?;
+    hooks.set_action(if opts.check { HookAction::Never } else { run_hook });
+    let profile = host_profile(&cluster, opts.host_profile.as_deref())?;
+    let targets = if opts.targets.all {
+        let mut targets = vec!["root".to_string()];
+        targets.extend(cluster.dependency_order().into_iter().map(String::from));
+        targets.retain(|target| in_host_profile(profile.as_ref(), target, false));
+        target_all(&cluster, &hooks, "deploy", targets)?
+    } else {
+        let patterns = if opts.interactive {
+            pick_with_state(&cluster, &tr!("deploy-pick-prompt"))?
+        } else if opts.targets.stdin {
+            read_targets(std::io::stdin().lock())?
+        } else {
+            opts.targets.patterns
+        };
+        if opts.interactive && patterns.is_empty() {
+            warn!("{}", tr!("pick-none"));
+            return Ok(());
+        }
+        let mut targets = cluster.match_targets(patterns)?;
+        targets.retain(|target| in_host_profile(profile.as_ref(), target, true));
+        cluster.match_targets(hooks.run_pre("deploy", targets)?)?
+    };
+
+    // INVARIANT: Dry runs never form a transaction, because they change nothing.
+    let _transaction = (!opts.check).then(|| begin_transaction("deploy"));
+    let mut summary = DeploySummary::default();
+    let mut entries = Vec::new();
+    let mut deploy_root = false;
+    for target in &targets {
+        if target == "root" {
+            if opts.check {
+                summary.record("root", root.plan(action));
+            } else {
+                deploy_root = true;
+            }
+        } else if opts.only || opts.targets.all {
+            entries.push((target.as_str(), node_entry(&cluster, target)?));
+        } else {
+            entries.extend(cluster.dependency_iter(target));
+        }
+    }
+
+    // INVARIANT: Nodes are deployed after their dependencies, and the nodes they are ordered after.
+    cluster.sort_dependency_order(&mut entries, |(name, _)| name);
+
+    if let Some(profile) = &opts.profile {
+        if !entries.iter().any(|(_, entry)| entry.with_sparse_profile(profile).is_some()) {
+            return Err(anyhow!(tr!("deploy-profile-undefined", profile = format!("{profile:?}"))));
+        }
+    }
+
+    let lock = if opts.locked { Some(ClusterLock::load()?) } else { None };
+    if lock.as_ref().is_some_and(ClusterLock::is_empty) {
+        return Err(anyhow!(tr!("deploy-no-lockfile")));
+    }
+
+    let collisions = match (opts.force, opts.backup) {
+        (true, _) => CollisionPolicy::Force,
+        (_, true) => CollisionPolicy::Backup,
+        _ => CollisionPolicy::Abort,
+    };
+    let vars = cluster.template_vars()?;
+    let mut nodes = Vec::new();
+    for (name, entry) in entries {
+        // INVARIANT: Checks never clone nodes missing from repository store.
+        if opts.check && !is_node_present(name, entry)? {
+            summary.record(name, Ok(DeployOutcome::Changed));
+            continue;
+        }
+
+        let profiled =
+            opts.profile.as_deref().and_then(|profile| entry.with_sparse_profile(profile));
+        let mut node = Node::new_open(name, profiled.as_ref().unwrap_or(entry))?;
+        node.set_force(opts.force);
+        node.set_collisions(collisions);
+        node.set_template_vars(vars.clone());
+        nodes.push(node);
+    }
+
+    if opts.check {
+        log_cluster_about(&cluster);
+        for node in &nodes {
+            let locked = lock.as_ref().and_then(|lock| lock.get(node.name()));
+            if locked.is_some_and(|locked| node.head_id().as_ref() != Some(&locked.commit)) {
+                summary.record(node.name(), Ok(DeployOutcome::Changed));
+                continue;
+            }
+            summary.record(node.name(), node.plan(action));
+        }
+        summary.notify(&cluster, "deploy", true);
+        return summary.check();
+    }
+
+    // INVARIANT: Deployments that cannot finish fail before anything gets deployed.
+    let mut preflight = DeployPreflight::new();
+    if deploy_root {
+        preflight.add_root(&root, action)?;
+    }
+    for node in &nodes {
+        preflight.add_node(node, action)?;
+    }
+    preflight.check().context(tr!("deploy-preflight-failure"))?;
+
+    // INVARIANT: Never clobber files modified since their last deployment without confirmation.
+    if !opts.force {
+        let mut modified = Vec::new();
+        for node in &nodes {
+            modified.extend(node.locally_modified(action)?);
+        }
+        modified.sort();
+        modified.dedup();
+
+        if !modified.is_empty() {
+            warn!("{}", tr!("deploy-overwrite-modified"));
+            for path in &modified {
+                warn!("  {path:?}");
+            }
+            if !prompt_confirmation(tr!("deploy-overwrite-prompt"))? {
+                warn!("{}", tr!("deploy-cancelled"));
+                return Ok(());
+            }
+        }
+    }
+
+    if deploy_root {
+        summary.record("root", root.deploy(action));
+    }
+
+    // INVARIANT: Nodes whose locked commit cannot be checked out are never deployed at all.
+    let mut moved_heads = HashSet::new();
+    if let Some(lock) = &lock {
+        let mut checked_out = Vec::new();
+        for node in nodes {
+            match lock.get(node.name()) {
+                Some(locked) if !node.is_inline() => match node.checkout_commit(&locked.commit) {
+                    Ok(moved) => {
+                        if moved {
+                            moved_heads.insert(node.name().to_string());
+                        }
+                        checked_out.push(node);
+                    }
+                    Err(error) => summary.record(node.name(), Err(error)),
+                },
+                _ => {
+                    warn!("{}", tr!("deploy-unlocked", name = format!("{:?}", node.name())));
+                    checked_out.push(node);
+                }
+            }
+        }
+        nodes = checked_out;
+    }
+
+    for node in nodes {
+        let node_hooks = cluster.nodes.get(node.name()).and_then(|e| e.settings.hooks.as_ref());
+        let read = |script: &Path| node.read_head_file(script);
+        let mut result = hooks
+            .run_node(HookKind::Pre, node.name(), node_hooks, read, node.work_tree())
+            .and_then(|()| node.deploy(action));
+        if moved_heads.contains(node.name()) {
+            if let Ok(DeployOutcome::AlreadyInDesiredState) = result {
+                result = Ok(DeployOutcome::Changed);
+            }
+        }
+        if let Ok(DeployOutcome::Changed | DeployOutcome::AlreadyInDesiredState) = result {
+            note_deployed(node.name());
+            if let Err(error) =
+                hooks.run_node(HookKind::Post, node.name(), node_hooks, read, node.work_tree())
+            {
+                result = Err(error);
+            }
+        }
+        summary.record(node.name(), result);
+    }
+    warn_skipped(&cluster, &targets);
+    report_deployment(&cluster)?;
+    summary.notify(&cluster, "deploy", false);
+    summary.finish()?;
+
+    hooks.run_post_ordered("deploy", &targets, &cluster)?;
+
+    Ok(())
+}
+
+fn run_undeploy(run_hook: HookAction, opts: UndeployOptions) -> Result<()> {
+    let cluster = Cluster::new()?;
+    let mut root = Root::new_open(&cluster.root)?;
+    root.set_force(opts.force);
+    let action =
+        if opts.excluded_only { DeployAction::UndeployExcludes } else { DeployAction::Undeploy };
+
+    let mut hooks = HookRunner::new()?;
+    hooks.set_action(run_hook);
+    let targets = if opts.targets.all {
+        let mut targets: Vec<String> =
+            cluster.dependency_order().into_iter().rev().map(String::from).collect();
+        if opts.excluded_only {
+            targets.push("root".into());
+        }
+        target_all(&cluster, &hooks, "undeploy", targets)?
+    } else {
+        let patterns = if opts.interactive {
+            pick_with_state(&cluster, &tr!("undeploy-pick-prompt"))?
+        } else if opts.targets.stdin {
+            read_targets(std::io::stdin().lock())?
+        } else {
+            opts.targets.patterns
+        };
+        if opts.interactive && patterns.is_empty() {
+            warn!("{}", tr!("pick-none"));
+            return Ok(());
+        }
+        let targets = cluster.match_targets(patterns)?;
+        cluster.match_targets(hooks.run_pre("undeploy", targets)?)?
+    };
+
+    let _transaction = begin_transaction("undeploy");
+    let mut summary = DeploySummary::default();
+    let mut nodes = Vec::new();
+    if opts.only || opts.targets.all {
+        for target in &targets {
+            if target == "root" {
+                summary.record("root", root.deploy(action));
+                continue;
+            }
+
+            let entry = node_entry(&cluster, target)?;
+            let mut node = Node::new_open(target, entry)?;
+            node.set_force(opts.force);
+            nodes.push(node);
+        }
+    } else {
+        for target in &targets {
+            if target == "root" {
+                summary.record("root", root.deploy(action));
+                continue;
+            }
+
+            for (name, entry) in cluster.dependency_iter(target) {
+                let mut node = Node::new_open(name, entry)?;
+                node.set_force(opts.force);
+                nodes.push(node);
+            }
+        }
+    }
+
+    // INVARIANT: Nodes are undeployed before their dependencies, and the nodes they are ordered
+    // after.
+    cluster.sort_dependency_order(&mut nodes, |node| node.name());
+    nodes.reverse();
+
+    for node in nodes {
+        summary.record(node.name(), node.deploy(action));
+    }
+    warn_skipped(&cluster, &targets);
+    report_deployment(&cluster)?;
+    summary.notify(&cluster, "undeploy", false);
+    summary.finish()?;
+
+    hooks.run("undeploy", HookKind::Post, Some(&targets))?;
+
+    Ok(())
+}
+
+#[instrument(skip(opts), level = "debug")]
+fn run_remove(run_hook: HookAction, opts: RemoveOptions) -> Result<()> {
+    let cluster = Cluster::new()?;
+
+    let mut hooks = HookRunner::new()?;
+    hooks.set_action(run_hook);
+    let targets = if opts.targets.all {
+        let targets: Vec<String> = cluster
+            .dependency_order()
+            .into_iter()
+            .rev()
+            .filter(|name| {
+                let node = &cluster.nodes[*name];
+                !node.settings.deployment.kind.is_inline()
+                    && is_node_present(name, node).unwrap_or(false)
+            })
+            .map(String::from)
+            .collect();
+        warn!("{}", tr!("remove-all-nodes", targets = format!("{targets:?}")));
+        if !prompt_confirmation(tr!("remove-all-nodes-prompt"))? {
+            return Ok(());
+        }
+        target_all(&cluster, &hooks, "rm", targets)?
+    } else {
+        let patterns = if opts.targets.stdin {
+            read_targets(std::io::stdin().lock())?
+        } else {
+            opts.targets.patterns
+        };
+        let targets = cluster.match_targets(patterns)?;
+        let targets = cluster.match_targets(hooks.run_pre("rm", targets)?)?;
+        guard_dependents(&cluster, targets, opts.cascade)?
+    };
+
+    let _transaction = begin_transaction("rm");
+
+    if targets.contains(&"root".into()) {
+        warn!("{}", tr!("remove-root"));
+        if confirm_nuke(&cluster)? {
+            nuke_cluster(&cluster)?;
+        }
+    } else {
+        let mut nuked = HashSet::new();
+        for target in &targets {
+            nuke_node(&cluster, target, &mut nuked)?;
+        }
+    }
+
+    report_deployment(&cluster)?;
+
+    hooks.run("rm", HookKind::Post, Some(&targets))?;
+
+    Ok(())
+}
+
+fn nuke_node(cluster: &Cluster, target: &str, nuked: &mut HashSet<String>) -> Result<()> {
+    if nuked.contains(target) {
+        return Ok(());
+    }
+    let node = node_entry(cluster, target)?;
+
+    // INVARIANT: Nodes of nested cluster go before the nested cluster defining them.
+    if node.settings.deployment.kind.is_cluster() {
+        let prefix = format!("{target}/");
+        let members: Vec<_> = cluster
+            .nodes
+            .range(prefix.clone()..)
+            .take_while(|(name, _)| name.starts_with(&prefix))
+            .collect();
+        for (name, member) in members.into_iter().rev() {
+            if is_node_present(name, member)? {
+                Node::new_open(name, member)?.nuke()?;
+            }
+            nuked.insert(name.clone());
+        }
+    }
+
+    let repo = Node::new_open(target, node)?;
+    repo.nuke()?;
+    nuked.insert(target.to_string());
+
+    Ok(())
+}
+
+// INVARIANT: Only dependents in the repository store hold on to their dependencies, because
+// dependents that were never cloned have nothing to lose. Nodes of nested clusters go along with
+// their nested cluster.
+fn guard_dependents(cluster: &Cluster, targets: Vec<String>, cascade: bool) -> Result<Vec<String>> {
+    if targets.iter().any(|target| target == "root") {
+        return Ok(targets);
+    }
+
+    let mut removed: BTreeSet<String> = targets.iter().cloned().collect();
+    for target in &targets {
+        let prefix = format!("{target}/");
+        removed.extend(cluster.nodes.keys().filter(|name| name.starts_with(&prefix)).cloned());
+    }
+
+    let mut pending: Vec<String> = removed.iter().cloned().collect();
+    let mut cascaded = Vec::new();
+    let mut results = Vec::new();
+    while let Some(name) = pending.pop() {
+        let mut required = Vec::new();
+        for dependent in cluster.dependents_of(&name) {
+            if removed.contains(dependent)
+                || !is_node_present(dependent, &cluster.nodes[dependent])?
+            {
+                continue;
+            }
+
+            if cascade {
+                removed.insert(dependent.to_string());
+                pending.push(dependent.to_string());
+                cascaded.push(dependent.to_string());
+            } else {
+                required.push(dependent);
+            }
+        }
+
+        if !required.is_empty() {
+            let (name, dependents) = (format!("{name:?}"), format!("{required:?}"));
+            results.push(Err(anyhow!(tr!(
+                "remove-required",
+                name = name,
+                dependents = dependents
+            ))));
+        }
+    }
+    results.into_iter().bcollect::<Vec<()>>()?;
+
+    if cascaded.is_empty() {
+        return Ok(targets);
+    }
+    cascaded.sort();
+    info!("{}", tr!("remove-cascade", dependents = format!("{cascaded:?}")));
+
+    // INVARIANT: Dependents go before their dependencies.
+    let wanted: HashSet<&str> = targets.iter().chain(&cascaded).map(String::as_str).collect();
+    let order = cluster.dependency_order().into_iter().rev();
+    Ok(order.filter(|name| wanted.contains(name)).map(String::from).collect())
+}
+
+// Outcome of every entry that deployment went through, such that entries failing to deploy never
+// keep the remaining entries from being deployed.
+#[derive(Debug, Default)]
+struct DeploySummary {
+    outcomes: Vec<(String, DeployOutcome)>,
+    errors: Vec<anyhow::Error>,
+}
+
+impl DeploySummary {
+    fn record(&mut self, name: &str, result: Result<DeployOutcome>) {
+        let outcome = result.unwrap_or_else(|error| {
+            self.errors
+                .push(error.context(tr!("deploy-entry-failure", name = format!("{name:?}"))));
+            DeployOutcome::Failed
+        });
+        self.outcomes.push((name.to_string(), outcome));
+    }
+
+    fn count(&self, outcome: DeployOutcome) -> usize {
+        self.outcomes.iter().filter(|(_, recorded)| *recorded == outcome).count()
+    }
+
+    fn show(&self) {
+        info!(
+            "{}",
+            tr!(
+                "deploy-summary",
+                changed = self.count(DeployOutcome::Changed),
+                unchanged = self.count(DeployOutcome::AlreadyInDesiredState),
+                skipped = self.count(DeployOutcome::Skipped),
+                failed = self.count(DeployOutcome::Failed),
+            )
+        );
+    }
+
+    // INVARIANT: Every failure is reported in one-shot, after every entry got its turn.
+    fn finish(self) -> Result<()> {
+        self.show();
+        self.errors.into_iter().map(Err).bcollect::<Vec<()>>()?;
+        Ok(())
+    }
+
+    // INVARIANT: Failures and drift always reach the notification command of root, if any.
+    fn notify(&self, cluster: &Cluster, command: &str, check: bool) {
+        let Some(notify) = &cluster.root.settings.notify else {
+            return;
+        };
+
+        let drifted = |outcome: &DeployOutcome| check && *outcome == DeployOutcome::Changed;
+        let event = if !self.errors.is_empty() {
+            NotifyEvent::Failure
+        } else if self.outcomes.iter().any(|(_, outcome)| drifted(outcome)) {
+            NotifyEvent::Drift
+        } else {
+            return;
+        };
+
+        let mut notification =
+            Notification::new(event, command).cluster(cluster.root.settings.name.as_deref());
+        for (name, outcome) in &self.outcomes {
+            if *outcome == DeployOutcome::Failed || drifted(outcome) {
+                notification = notification.entry(name, outcome);
+            }
+        }
+        for error in &self.errors {
+            notification = notification.error(format!("{error:#}"));
+        }
+
+        if let Err(error) = notification.send(notify) {
+            warn!("{}", tr!("notify-failure", error = format!("{error:#}")));
+        }
+    }
+
+    fn check(self) -> Result<()> {
+        for (name, outcome) in &self.outcomes {
+            if *outcome == DeployOutcome::Changed {
+                warn!("{}", tr!("deploy-check-drift", name = name));
+            }
+        }
+
+        let drifted = self.count(DeployOutcome::Changed);
+        self.finish()?;
+        if drifted > 0 {
+            return Err(anyhow!(tr!("deploy-check-failure", count = drifted)));
+        }
+
+        Ok(())
+    }
+}
+
+// Show metadata of cluster, so users know which cluster they are about to act upon.
+fn log_cluster_about(cluster: &Cluster) {
+    let settings = &cluster.root.settings;
+    if let Some(name) = &settings.name {
+        info!("{}", tr!("cluster-name", name = name));
+    }
+    if let Some(description) = &settings.description {
+        info!("{}", tr!("cluster-description", description = description));
+    }
+    if let Some(maintainer) = &settings.maintainer {
+        info!("{}", tr!("cluster-maintainer", maintainer = maintainer));
+    }
+}
+
+// INVARIANT: Targets that deployment leaves alone are never skipped silently.
+fn warn_skipped(cluster: &Cluster, targets: &[String]) {
+    let mut normal = false;
+    for target in targets {
+        match cluster.nodes.get(target).map(|node| &node.settings.deployment.kind) {
+            Some(DeploymentKind::Normal) => {
+                warn!("{}", tr!("skip-normal", name = target));
+                normal = true;
+            }
+            Some(DeploymentKind::Mirror) => warn!("{}", tr!("skip-mirror", name = target)),
+            _ => {}
+        }
+    }
+
+    if normal {
+        warn!("{}", tr!("skip-normal-reason"));
+        warn!("{}", tr!("skip-normal-hint"));
+    }
+}
+
+// Read newline-separated target names, e.g., from standard input.
+fn read_targets(reader: impl std::io::BufRead) -> Result<Vec<String>> {
+    let mut targets = Vec::new();
+    for line in reader.lines() {
+        let line = line.with_context(|| tr!("read-targets-failure"))?;
+        let line = line.trim();
+        if !line.is_empty() {
+            targets.push(line.to_string());
+        }
+    }
+
+    if targets.is_empty() {
+        warn!("{}", tr!("read-targets-empty"));
+    }
+
+    Ok(targets)
+}
+
+fn node_entry<'cluster>(cluster: &'cluster Cluster, name: &str) -> Result<&'cluster NodeEntry> {
+    cluster
+        .nodes
+        .get(name)
+        .ok_or_else(|| anyhow!(tr!("node-not-defined", name = format!("{name:?}"))))
+}
+
+// Pick host profile for this machine, along with the nodes it selects.
+fn host_profile(
+    cluster: &Cluster,
+    name: Option<&str>,
+) -> Result<Option<(String, HashSet<String>)>> {
+    let Some(profile) = cluster.pick_profile(name, hostname().as_deref())? else {
+        return Ok(None);
+    };
+    let members = cluster.profile_nodes(profile)?;
+    info!(
+        "{}",
+        tr!(
+            "host-profile-picked",
+            profile = format!("{profile:?}"),
+            count = members.len(),
+            total = cluster.nodes.len()
+        )
+    );
+
+    Ok(Some((profile.to_string(), members)))
+}
+
+// INVARIANT: Root is relevant to every machine.
+fn in_host_profile(profile: Option<&(String, HashSet<String>)>, target: &str, warn: bool) -> bool {
+    let Some((profile, members)) = profile else {
+        return true;
+    };
+    if target == "root" || members.contains(target) {
+        return true;
+    }
+
+    if warn {
+        warn!(
+            "{}",
+            tr!(
+                "host-profile-skipped",
+                name = format!("{target:?}"),
+                profile = format!("{profile:?}")
+            )
+        );
+    }
+    false
+}
+
+// Regenerate deployment report if root asks for one, unless root itself is gone.
+fn report_deployment(cluster: &Cluster) -> Result<()> {
+    match &cluster.root.settings.deploy_report {
+        Some(path) if data_dir()?.join("root").exists() => write_deploy_report(cluster, path),
+        _ => Ok(()),
+    }
+}
+
+// INVARIANT: Targets are taken by name, such that glob matching is bypassed entirely.
+fn target_all(
+    cluster: &Cluster,
+    hooks: &HookRunner,
+    cmd: &str,
+    targets: Vec<String>,
+) -> Result<Vec<String>> {
+    let targets = hooks.run_pre(cmd, targets)?;
+    Ok(targets.into_iter().filter(|t| t == "root" || cluster.nodes.contains_key(t)).collect())
+}
+
+// INVARIANT: Named clusters are only nuked once the user types their name.
+fn confirm_nuke(cluster: &Cluster) -> Result<bool> {
+    log_cluster_about(cluster);
+    let Some(name) = &cluster.root.settings.name else {
+        return Ok(prompt_confirmation(tr!("remove-root-prompt"))?);
+    };
+
+    let answer = prompt_text(tr!("remove-root-name-prompt", name = format!("{name:?}")))?;
+    if answer.trim() != name {
+        warn!("{}", tr!("remove-root-name-mismatch"));
+        return Ok(false);
+    }
+
+    Ok(true)
+}
+
+fn nuke_cluster(cluster: &Cluster) -> Result<()> {
+    let root = Root::new_open(&cluster.root)?;
+    root.nuke()?;
+
+    for (name, node) in &cluster.nodes {
+        // INVARIANT: Inline nodes were nuked along with root.
+        if node.settings.deployment.kind.is_inline() {
+            continue;
+        }
+
+        if !is_node_present(name, node)? {
+            warn!("{}", tr!("nuke-node-missing", name = format!("{name:?}")));
+            continue;
+        }
+
+        let repo = Node::new_open(name, node)?;
+        repo.nuke()?;
+    }
+
+    remove_dir_all(config_dir()?)?;
+    info!("{}", tr!("nuke-config-dir"));
+
+    remove_dir_all(data_dir()?)?;
+    info!("{}", tr!("nuke-data-dir"));
+
+    remove_transient_dirs()?;
+
+    Ok(())
+}
+
+#[instrument(skip(opts), level = "debug")]
+fn run_uninstall(opts: UninstallOptions) -> Result<()> {
+    let cluster = Cluster::new()?;
+    let root = Root::new_open(&cluster.root)?;
+    let kept = if opts.keep.is_empty() { Vec::new() } else { cluster.match_targets(opts.keep)? };
+
+    warn!("{}", tr!("uninstall-warning"));
+    if !kept.is_empty() {
+        warn!("{}", tr!("uninstall-keep", targets = format!("{kept:?}")));
+    }
+    if !prompt_confirmation(tr!("uninstall-prompt"))? {
+        info!("{}", tr!("uninstall-aborted"));
+        return Ok(());
+    }
+
+    // INVARIANT: Nodes go before their dependencies, and nothing missing gets cloned.
+    let mut remaining = Vec::new();
+    for name in cluster.dependency_order().into_iter().rev() {
+        let entry = node_entry(&cluster, name)?;
+        if !is_node_present(name, entry)? {
+            continue;
+        }
+
+        let node = Node::new_open(name, entry)?;
+        if kept.iter().any(|target| target == name) {
+            remaining.extend(node.deployed_files()?);
+        } else {
+            node.deploy(DeployAction::Undeploy)?;
+        }
+    }
+
+    if opts.undeploy_root {
+        root.force_undeploy()?;
+    } else {
+        remaining.extend(root.deployed_files()?);
+    }
+
+    if let Some(path) = &cluster.root.settings.deploy_report {
+        let path = PathBuf::from(expand_path(&path.to_string_lossy())?);
+        if path.exists() {
+            remove_file(&path)
+                .with_context(|| tr!("remove-failure", path = format!("{path:?}")))?;
+        }
+    }
+
+    let (config_dir, data_dir) = (config_dir()?, data_dir()?);
+    for dir in [&config_dir, &data_dir] {
+        if dir.exists() {
+            remove_dir_all(dir)
+                .with_context(|| tr!("remove-failure", path = format!("{dir:?}")))?;
+        }
+    }
+    info!("{}", tr!("nuke-config-dir"));
+    info!("{}", tr!("nuke-data-dir"));
+    remove_transient_dirs()?;
+
+    // INVARIANT: Files inside removed directories are gone no matter what was kept.
+    remaining.retain(|path| !path.starts_with(&config_dir) && !path.starts_with(&data_dir));
+    remaining.sort();
+    remaining.dedup();
+    if remaining.is_empty() {
+        info!("{}", tr!("uninstall-clean"));
+    } else {
+        info!("{}", tr!("uninstall-remaining", count = remaining.len()));
+        for path in &remaining {
+            println!("{}", path.display());
+        }
+    }
+
+    Ok(())
+}
+
+fn remove_transient_dirs() -> Result<()> {
+    for dir in [state_dir()?, cache_dir()?] {
+        if dir.exists() {
+            remove_dir_all(&dir)
+                .with_context(|| tr!("remove-failure", path = format!("{dir:?}")))?;
+            info!("{}", tr!("nuke-transient-dir", path = format!("{dir:?}")));
+        }
+    }
+
+    Ok(())
+}
+
+async fn run_list(run_hook: HookAction, opts: ListOptions) -> Result<()> {
+    let cluster = Cluster::new()?;
+    let root = Root::new_open(&cluster.root)?;
+
+    let mut hooks = HookRunner::new()?;
+    hooks.set_action(run_hook);
+
+    hooks.run("ls", HookKind::Pre, None)?;
+
+    let mut tablize = TablizeCluster::new(&root, &cluster).format(opts.format);
+    if !opts.patterns.is_empty() {
+        tablize = tablize.select(&cluster.match_targets(opts.patterns)?, opts.with_deps);
+    }
+
+    if opts.names_only {
+        tablize.names_only()?;
+    } else {
+        if opts.format == OutputFormat::Table {
+            log_cluster_about(&cluster);
+        }
+        tablize.fancy(opts.jobs.or(cluster.root.settings.jobs), opts.fetch).await?;
+    }
+
+    hooks.run("ls", HookKind::Post, None)?;
+
+    Ok(())
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum OriginRepair {
+    Rewrite,
+    Adopt,
+    Skip,
+}
+
+const ORIGIN_REPAIRS: [OriginRepair; 3] =
+    [OriginRepair::Rewrite, OriginRepair::Adopt, OriginRepair::Skip];
+
+impl std::fmt::Display for OriginRepair {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let label = match self {
+            OriginRepair::Rewrite => tr!("repair-rewrite-origin"),
+            OriginRepair::Adopt => tr!("repair-adopt-origin"),
+            OriginRepair::Skip => tr!("repair-skip-origin"),
+        };
+        fmt.write_str(&label)
+    }
+}
 
 #[instrument(skip(opts), level = "debug")]
-pub fn run_deploy(run_hook: HookAction, opts: DeployOptions) -> Result<()> {
+fn run_repair(run_hook: HookAction, opts: RepairOptions) -> Result<()> {
+    let mut cluster = Cluster::new()?;
+    let patterns = if opts.patterns.is_empty() { vec!["*".into()] } else { opts.patterns };
+    let targets = cluster.match_targets(patterns)?;
+
+    let mut hooks = HookRunner::new()?;
+    hooks.set_action(run_hook);
+    let targets = cluster.match_targets(hooks.run_pre("repair", targets)?)?;
+
+    if opts.remotes {
+        for target in targets.iter().filter(|target| *target != "root") {
+            let entry = node_entry(&cluster, target)?;
+            let node = Node::new_open(target, entry)?;
+            match node.remote_health() {
+                RemoteHealth::Matches | RemoteHealth::Unchecked => continue,
+                RemoteHealth::Missing => node.repair_origin()?,
+                RemoteHealth::Diverged(local) => {
+                    warn!(
+                        "{}",
+                        tr!(
+                            "repair-origin-diverged",
+                            local = format!("{local:?}"),
+                            name = format!("{target:?}"),
+                            url = format!("{:?}", entry.settings.url),
+                        )
+                    );
+                    let choice = if opts.interactive {
+                        let prompt = tr!("repair-origin-prompt", name = format!("{target:?}"));
+                        Select::new(&prompt, ORIGIN_REPAIRS.into()).prompt()?
+                    } else {
+                        ORIGIN_REPAIRS[0]
+                    };
+
+                    match choice {
+                        OriginRepair::Rewrite => node.repair_origin()?,
+                        OriginRepair::Adopt => cluster.set_node_url(target, local)?,
+                        OriginRepair::Skip => {
+                            info!("{}", tr!("repair-origin-skipped", name = format!("{target:?}")))
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    hooks.run("repair", HookKind::Post, Some(&targets))?;
+
+    Ok(())
+}
+
+#[instrument(skip(opts), level = "debug")]
+fn run_excludes(run_hook: HookAction, opts: ExcludesOptions) -> Result<()> {
+    let (rules, patterns, add) = match opts.action {
+        ExcludesAction::Add(opts) => (opts.rules, opts.nodes, true),
+        ExcludesAction::Remove(opts) => (opts.rules, opts.nodes, false),
+    };
+    let (added, removed) = if add { (rules, Vec::new()) } else { (Vec::new(), rules) };
+
+    let mut cluster = Cluster::new()?;
+    let targets = cluster.match_targets(patterns)?;
+
+    let mut hooks = HookRunner::new()?;
+    hooks.set_action(run_hook);
+    let targets = cluster.match_targets(hooks.run_pre("excludes", targets)?)?;
+
+    for target in &targets {
+        if target == "root" {
+            warn!("{}", tr!("excludes-root"));
+            continue;
+        }
+
+        // INVARIANT: Only refresh nodes whose deployment currently follows their exclusion rules,
+        // leaving nodes deployed with their excluded files alone.
+        let entry = node_entry(&cluster, target)?;
+        let node = Node::new_open(target, entry)?;
+        let has_excluded = entry
+            .settings
+            .excluded
+            .iter()
+            .chain(entry.settings.included.iter())
+            .any(|rules| !rules.is_empty());
+        let refresh = node.is_deployed(DeployState::WithoutExcluded)?
+            && !(has_excluded && node.is_deployed(DeployState::WithExcluded)?);
+
+        if !cluster.edit_node_excluded(target, &added, &removed)? {
+            continue;
+        }
+        info!("{}", tr!("excludes-edited", name = format!("{target:?}")));
+
+        if refresh {
+            let entry = node_entry(&cluster, target)?;
+            Node::new_open(target, entry)?.refresh()?;
+        }
+    }
+
+    hooks.run("excludes", HookKind::Post, Some(&targets))?;
+
+    Ok(())
+}
+
+#[instrument(skip(opts), level = "debug")]
+fn run_migrate_remote(run_hook: HookAction, opts: MigrateRemoteOptions) -> Result<()> {
+    let mut cluster = Cluster::new()?;
+    let patterns = if opts.patterns.is_empty() { vec!["*".into()] } else { opts.patterns };
+    let targets = cluster.match_targets(patterns)?;
+
+    let mut hooks = HookRunner::new()?;
+    hooks.set_action(run_hook);
+    let targets = cluster.match_targets(hooks.run_pre("migrate-remote", targets)?)?;
+
+    let migrate = |url: &str| -> Option<String> {
+        if opts.from.is_empty() || !url.contains(&opts.from) {
+            return None;
+        }
+
+        let migrated = match normalize_url(url.replacen(&opts.from, &opts.to, 1)) {
+            Ok(migrated) => migrated,
+            Err(error) => {
+                let (url, error) = (format!("{url:?}"), format!("{error:?}"));
+                warn!("{}", tr!("migrate-skip", url = url, error = error));
+                return None;
+            }
+        };
+        if opts.verify {
+            if let Err(error) = check_remote(&migrated) {
+                let (url, error) = (format!("{url:?}"), format!("{error:?}"));
+                let migrated = format!("{migrated:?}");
+                warn!("{}", tr!("migrate-skip-to", url = url, migrated = migrated, error = error));
+                return None;
+            }
+        }
+
+        Some(migrated)
+    };
+
+    for target in &targets {
+        if target == "root" {
+            let root = Root::new_open(&cluster.root)?;
+            if let Some(url) = root.origin_url().as_deref().and_then(migrate) {
+                root.set_origin(url)?;
+            }
+            continue;
+        }
+
+        let entry = node_entry(&cluster, target)?;
+        let Some(url) = migrate(&entry.settings.url) else {
+            continue;
+        };
+        cluster.set_node_url(target, url)?;
+
+        // INVARIANT: Never clone nodes that are missing from the repository store.
+        let entry = node_entry(&cluster, target)?;
+        if entry.settings.subdir.is_none() && is_node_present(target, entry)? {
+            Node::new_open(target, entry)?.repair_origin()?;
+        }
+    }
+
+    hooks.run("migrate-remote", HookKind::Post, Some(&targets))?;
+
+    Ok(())
+}
+
+#[instrument(skip(opts), level = "debug")]
+fn run_convert(run_hook: HookAction, opts: ConvertOptions) -> Result<()> {
+    if !matches!(opts.to, DeploymentKind::Normal | DeploymentKind::BareAlias) {
+        return Err(anyhow!(tr!("convert-bad-kind", kind = opts.to)));
+    }
+
+    let mut cluster = Cluster::new()?;
+    let entry = node_entry(&cluster, &opts.node)?;
+    if !matches!(entry.settings.deployment.kind, DeploymentKind::Normal | DeploymentKind::BareAlias)
+    {
+        return Err(anyhow!(tr!(
+            "convert-bad-node",
+            name = format!("{:?}", opts.node),
+            kind = entry.settings.deployment.kind,
+        )));
+    }
+    if entry.is_external() {
+        return Err(anyhow!(tr!("convert-external", name = format!("{:?}", opts.node))));
+    }
+    let work_dir_alias = opts.worktree.unwrap_or(entry.settings.deployment.work_dir_alias.clone());
+    let deployment = NodeEntryDeployment::new(opts.to, work_dir_alias);
+
+    let mut hooks = HookRunner::new()?;
+    hooks.set_action(run_hook);
+    let targets = vec![opts.node.clone()];
+    hooks.run("convert", HookKind::Pre, Some(&targets))?;
+
+    // INVARIANT: Convert repository before cluster definition, such that a failed conversion
+    // leaves both the way they were.
+    let mut converted = entry.clone();
+    converted.settings.deployment =
+        NodeEntryDeployment::new(deployment.kind.clone(), deployment.work_dir_alias.expand()?);
+    let node = if is_node_present(&opts.node, entry)? {
+        Some(Node::new_open(&opts.node, entry)?.convert(&converted)?)
+    } else {
+        None
+    };
+    cluster.set_node_deployment(&opts.node, &deployment)?;
+
+    if opts.deploy {
+        let mut node = match node {
+            Some(node) => node,
+            None => Node::new_open(&opts.node, &converted)?,
+        };
+        node.set_template_vars(cluster.template_vars()?);
+        node.deploy(DeployAction::Deploy)?;
+        warn_skipped(&cluster, &targets);
+    }
+    report_deployment(&cluster)?;
+
+    hooks.run("convert", HookKind::Post, Some(&targets))?;
+
+    Ok(())
+}
+
+#[instrument(skip(opts), level = "debug")]
+fn run_rename(run_hook: HookAction, opts: RenameOptions) -> Result<()> {
+    let mut cluster = Cluster::new()?;
+    let entry = node_entry(&cluster, &opts.old)?.clone();
+    cluster.check_rename(&opts.old, &opts.new)?;
+
+    let mut hooks = HookRunner::new()?;
+    hooks.set_action(run_hook);
+    hooks.run("rename", HookKind::Pre, Some(&vec![opts.old.clone()]))?;
+
+    // INVARIANT: Carry node over in repository store before cluster definition, like "ocd
+    // convert" does, such that a failed move leaves the cluster definition the way it was.
+    rename_in_store(&entry, &opts.old, &opts.new)?;
+    let dependents = cluster.rename_node(&opts.old, &opts.new)?;
+    info!(
+        "{}",
+        tr!(
+            "rename-done",
+            old = format!("{:?}", opts.old),
+            new = format!("{:?}", opts.new),
+            count = dependents.len()
+        )
+    );
+    report_deployment(&cluster)?;
+
+    hooks.run("rename", HookKind::Post, Some(&vec![opts.new.clone()]))?;
+
+    Ok(())
+}
+
+#[instrument(skip(opts), level = "debug")]
+fn run_pick(run_hook: HookAction, ssh_agent: bool, opts: PickOptions) -> Result<()> {
+    match (opts.action, opts.git_args.is_empty()) {
+        (Some(PickAction::Git), true) => return Err(anyhow!(tr!("pick-git-needs-command"))),
+        (action, false) if action != Some(PickAction::Git) => {
+            return Err(anyhow!(tr!("pick-git-args-unused")))
+        }
+        _ => {}
+    }
+
+    let cluster = Cluster::new()?;
+    let items = pick_items(&cluster);
+    if items.is_empty() {
+        warn!("{}", tr!("pick-no-nodes"));
+        return Ok(());
+    }
+
+    let picked: Vec<String> = if opts.single {
+        vec![Select::new(&tr!("pick-node-prompt"), items).prompt()?.name]
+    } else {
+        let prompt = tr!("pick-nodes-prompt");
+        MultiSelect::new(&prompt, items).prompt()?.into_iter().map(|item| item.name).collect()
+    };
+    if picked.is_empty() {
+        warn!("{}", tr!("pick-none"));
+        return Ok(());
+    }
+
+    match opts.action {
+        None => picked.iter().for_each(|name| println!("{name}")),
+        Some(PickAction::Deploy) => {
+            let opts = DeployOptions {
+                targets: TargetOptions { patterns: picked, all: false, stdin: false },
+                interactive: false,
+                only: false,
+                with_excluded: false,
+                force: false,
+                backup: false,
+                profile: None,
+                host_profile: None,
+                check: false,
+                locked: false,
+            };
+            run_deploy(run_hook, opts)?;
+        }
+        Some(PickAction::Undeploy) => {
+            let opts = UndeployOptions {
+                targets: TargetOptions { patterns: picked, all: false, stdin: false },
+                interactive: false,
+                only: false,
+                excluded_only: false,
+                force: false,
+            };
+            run_undeploy(run_hook, opts)?;
+        }
+        Some(PickAction::Git) => {
+            let mut args = vec![OsString::from(picked.join(","))];
+            args.extend(opts.git_args);
+            run_git(args, ssh_agent)?;
+        }
+    }
+
+    Ok(())
+}
+
+// Node entry choice of fuzzy finder, which filters through both name and metadata.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct PickItem {
+    name: String,
+    about: Option<String>,
+    state: Option<String>,
+}
+
+impl std::fmt::Display for PickItem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.name)?;
+        if let Some(state) = &self.state {
+            write!(f, " ({state})")?;
+        }
+        if let Some(about) = &self.about {
+            write!(f, " - {about}")?;
+        }
+
+        Ok(())
+    }
+}
+
+fn pick_items(cluster: &Cluster) -> Vec<PickItem> {
+    let mut items: Vec<PickItem> = cluster
+        .nodes
+        .iter()
+        .map(|(name, node)| PickItem { name: name.clone(), about: node.about(), state: None })
+        .collect();
+    items.sort_by(|a, b| a.name.cmp(&b.name));
+    items
+}
+
+// INVARIANT: Nodes missing from the repository store are never cloned just to show their state,
+// and nested clusters are never opened, because opening them deploys them.
+fn pick_with_state(cluster: &Cluster, prompt: &str) -> Result<Vec<String>> {
+    let mut items = pick_items(cluster);
+    if items.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    for item in &mut items {
+        let entry = &cluster.nodes[&item.name];
+        item.state = if !is_node_present(&item.name, entry).unwrap_or(false) {
+            Some(tr!("pick-state-missing"))
+        } else if entry.settings.deployment.kind.is_cluster() {
+            None
+        } else {
+            Node::new_open(&item.name, entry)
+                .and_then(|node| node.deployment_status(DeployState::WithoutExcluded))
+                .map(|status| status.state.to_string())
+                .ok()
+        };
+    }
+
+    Ok(MultiSelect::new(prompt, items).prompt()?.into_iter().map(|item| item.name).collect())
+}
+
+fn run_status(opts: StatusOptions) -> Result<()> {
+    if opts.sync {
+        return show_sync_status();
+    }
+
+    let cluster = Cluster::new()?;
+    let Some(entry) = opts.entry else {
+        let root = Root::new_open(&cluster.root)?;
+        return TablizeCluster::new(&root, &cluster).format(opts.format).status(opts.short);
+    };
+
+    let untracked = if entry == "root" {
+        let root = Root::new_open(&cluster.root)?;
+        if !opts.untracked {
+            return root.gitcall(["status"]);
+        }
+        root.untracked_near_tracked()?
+    } else {
+        let node = Node::new_open(&entry, node_entry(&cluster, &entry)?)?;
+        if !opts.untracked {
+            return node.gitcall(["status"]);
+        }
+        node.untracked_near_tracked()?
+    };
+
+    if untracked.is_empty() {
+        info!("{}", tr!("status-no-untracked", name = format!("{entry:?}")));
+    }
+    for path in untracked {
+        println!("{}", path.display());
+    }
+
+    Ok(())
+}
+
+// INVARIANT: Drifted files are given as pathspecs relative to the top of the working directory
+// alias, whatever directory OCD runs in.
+fn run_diff(opts: DiffOptions) -> Result<()> {
+    let cluster = Cluster::new()?;
+    let targeted = !opts.patterns.is_empty();
+    let targets = if targeted {
+        cluster.match_targets(opts.patterns)?
+    } else {
+        std::iter::once("root".to_string()).chain(cluster.nodes.keys().cloned()).collect()
+    };
+
+    let mut args = vec![OsString::from("--no-pager"), OsString::from("diff")];
+    if opts.stat {
+        args.push("--stat".into());
+    }
+    args.extend(["HEAD".into(), "--".into()]);
+    let diff_args = |files: &[PathBuf]| -> Vec<OsString> {
+        let pathspecs = files.iter().map(|file| {
+            let mut pathspec = OsString::from(":(top,literal)");
+            pathspec.push(file);
+            pathspec
+        });
+        args.iter().cloned().chain(pathspecs).collect()
+    };
+
+    let (mut diffed, mut drifted, mut files) = (0, 0, 0);
+    for target in &targets {
+        let drift = if target == "root" {
+            let root = Root::new_open(&cluster.root)?;
+            diffed += 1;
+            let drift = root.drifted_files()?;
+            if !drift.is_empty() {
+                println!("{}", tr!("diff-header", name = target));
+                root.gitcall(diff_args(&drift))?;
+            }
+            drift
+        } else {
+            let entry = node_entry(&cluster, target)?;
+            if !entry.settings.deployment.kind.is_bare_alias() {
+                if targeted {
+                    warn!("{}", tr!("diff-not-bare-alias", name = format!("{target:?}")));
+                }
+                continue;
+            }
+            if !is_node_present(target, entry)? {
+                continue;
+            }
+
+            let node = Node::new_open(target, entry)?;
+            diffed += 1;
+            let drift = node.drifted_files()?;
+            if !drift.is_empty() {
+                println!("{}", tr!("diff-header", name = target));
+                node.gitcall(diff_args(&drift))?;
+            }
+            drift
+        };
+
+        if !drift.is_empty() {
+            drifted += 1;
+            files += drift.len();
+        }
+    }
+
+    if drifted == 0 {
+        info!("{}", tr!("diff-clean"));
+    } else if opts.stat {
+        println!("{}", tr!("diff-summary", files = files, drifted = drifted, total = diffed));
+    }
+
+    Ok(())
+}
+
+fn run_clean(opts: CleanOptions) -> Result<()> {
+    let cluster = Cluster::new()?;
+    let entry = node_entry(&cluster, &opts.node)?;
+    let node = Node::new_open(&opts.node, entry)?;
+    let untracked = node.cleanable_untracked()?;
+    if untracked.is_empty() {
+        info!("{}", tr!("clean-nothing", name = format!("{:?}", opts.node)));
+        return Ok(());
+    }
+
+    for path in &untracked {
+        println!("{}", path.display());
+    }
+
+    if !opts.force {
+        info!("{}", tr!("clean-dry-run"));
+        return Ok(());
+    }
+
+    if !prompt_confirmation(tr!("clean-prompt", count = untracked.len()))? {
+        info!("{}", tr!("clean-aborted"));
+        return Ok(());
+    }
+
+    let removed = node.clean_untracked()?;
+    info!("{}", tr!("clean-removed", count = removed.len(), name = format!("{:?}", opts.node)));
+
+    Ok(())
+}
+
+fn run_prune_artifacts(opts: PruneArtifactsOptions) -> Result<()> {
+    let cluster = Cluster::new()?;
+    let stale = stale_artifacts(&cluster)?;
+    let count: usize = stale.values().map(Vec::len).sum();
+    if count == 0 {
+        // INVARIANT: Removed nodes whose files are all gone are forgotten anyway.
+        if !stale.is_empty() {
+            prune_artifacts(&cluster)?;
+        }
+        info!("{}", tr!("prune-artifacts-nothing"));
+        return Ok(());
+    }
+
+    for (name, files) in stale.iter().filter(|(_, files)| !files.is_empty()) {
+        info!("{}", tr!("prune-artifacts-node", name = format!("{name:?}"), count = files.len()));
+        for path in files {
+            println!("{}", path.display());
+        }
+    }
+
+    if !opts.force {
+        info!("{}", tr!("prune-artifacts-dry-run"));
+        return Ok(());
+    }
+
+    if !prompt_confirmation(tr!("prune-artifacts-prompt", count = count))? {
+        info!("{}", tr!("prune-artifacts-aborted"));
+        return Ok(());
+    }
+
+    let removed = prune_artifacts(&cluster)?;
+    info!("{}", tr!("prune-artifacts-removed", count = removed));
+
+    Ok(())
+}
+
+#[instrument(skip(opts), level = "debug")]
+fn run_gc(run_hook: HookAction, opts: GcOptions) -> Result<()> {
+    let cluster = Cluster::new()?;
+    let patterns = if opts.patterns.is_empty() { vec!["*".into()] } else { opts.patterns };
+    let targets = cluster.match_targets(patterns)?;
+
+    let mut hooks = HookRunner::new()?;
+    hooks.set_action(run_hook);
+    let targets = cluster.match_targets(hooks.run_pre("gc", targets)?)?;
+
+    let mut pruned = Vec::new();
+    for target in targets.iter().filter(|target| *target != "root") {
+        let entry = node_entry(&cluster, target)?;
+        let node = Node::new_open(target, entry)?;
+        if node.is_inline() {
+            info!("{}", tr!("gc-inline", name = format!("{target:?}")));
+            continue;
+        }
+        if node.is_external() {
+            info!("{}", tr!("gc-external", name = format!("{target:?}")));
+            continue;
+        }
+
+        let since = opts.since.as_ref().or(entry.settings.shallow_since.as_ref());
+        match since {
+            Some(since) if opts.prune_history => {
+                node.prune_history(since)?;
+                pruned.push(target.clone());
+            }
+            _ => {
+                if opts.prune_history {
+                    warn!("{}", tr!("gc-no-shallow-since", name = format!("{target:?}")));
+                }
+                node.gc()?;
+            }
+        }
+    }
+
+    if !pruned.is_empty() {
+        warn!("{}", tr!("gc-pruned", names = pruned.join(", ")));
+    }
+
+    hooks.run("gc", HookKind::Post, Some(&targets))?;
+
+    Ok(())
+}
+
+async fn run_pull(run_hook: HookAction, opts: PullOptions) -> Result<()> {
+    let cluster = Cluster::new()?;
+    let patterns = if opts.patterns.is_empty() { vec!["*".into()] } else { opts.patterns };
+    let targets = cluster.match_targets(patterns)?;
+
+    let mut hooks = HookRunner::new()?;
+    hooks.set_action(run_hook);
+    let targets = cluster.match_targets(hooks.run_pre("pull", targets)?)?;
+
+    let mut fetch = MultiEntryFetch::new(opts.jobs.or(cluster.root.settings.jobs));
+    let mut pulled = Vec::new();
+    for target in &targets {
+        if target == "root" {
+            fetch = fetch.add_root(&cluster.root);
+            pulled.push(target);
+            continue;
+        }
+
+        let entry = node_entry(&cluster, target)?;
+        if entry.settings.subdir.is_some() {
+            info!("{}", tr!("pull-inline", name = format!("{target:?}")));
+            continue;
+        }
+
+        // INVARIANT: Pulling never clones nodes missing from repository store.
+        if !is_node_present(target, entry)? {
+            warn!("{}", tr!("pull-missing", name = format!("{target:?}")));
+            continue;
+        }
+        fetch = fetch.add_node(target, entry);
+        pulled.push(target);
+    }
+
+    // INVARIANT: Entries that failed to fetch are never integrated with stale upstreams.
+    let mut unfetched: BTreeMap<String, anyhow::Error> = fetch
+        .fetch_all()
+        .await
+        .into_iter()
+        .filter_map(|(name, result)| result.err().map(|error| (name, error)))
+        .collect();
+
+    let root_strategy = opts.strategy.or(cluster.root.settings.pull_strategy);
+    let mut summary = PullSummary::default();
+    for target in pulled {
+        if let Some(error) = unfetched.remove(target) {
+            summary.record(target, Err(error));
+            continue;
+        }
+
+        // INVARIANT: Collect and report _all_ failures encountered, like failed fetches.
+        if target == "root" {
+            let pull = || -> Result<PullOutcome> {
+                let root = Root::new_open(&cluster.root)?;
+                let strategy = root_strategy.unwrap_or_default();
+                let outcome = root.pull_fetched(|| pick_pull_strategy(target, strategy))?;
+                if outcome.is_updated() && !root.is_deployed(DeployState::WithoutExcluded)? {
+                    warn_redeploy(target);
+                }
+                Ok(outcome)
+            };
+            summary.record(target, pull());
+            continue;
+        }
+
+        let pull = || -> Result<PullOutcome> {
+            let entry = node_entry(&cluster, target)?;
+            let node = Node::new_open(target, entry)?;
+            let strategy = opts
+                .strategy
+                .or(entry.settings.pull_strategy)
+                .or(root_strategy)
+                .unwrap_or_default();
+            let was_deployed = node.is_deployed(DeployState::WithoutExcluded)?;
+            let outcome = node.pull_fetched(|| pick_pull_strategy(target, strategy))?;
+            if outcome.is_updated() && node.needs_redeploy(was_deployed)? {
+                warn_redeploy(target);
+            }
+            Ok(outcome)
+        };
+        summary.record(target, pull());
+    }
+    summary.finish()?;
+
+    hooks.run("pull", HookKind::Post, Some(&targets))?;
+
+    Ok(())
+}
+
+async fn run_fetch_config(run_hook: HookAction, opts: FetchConfigOptions) -> Result<()> {
     let cluster = Cluster::new()?;
     let root = Root::new_open(&cluster.root)?;
-    let action = if opts.with_excluded { DeployAction::DeployAll } else { DeployAction::Deploy };
+    MultiEntryFetch::new(Some(1))
+        .add_root(&cluster.root)
+        .fetch_all()
+        .await
+        .into_iter()
+        .map(|(_, result)| result)
+        .bcollect::<Vec<()>>()?;
+
+    let files =
+        root.upstream_config_files()?.ok_or_else(|| anyhow!(tr!("fetch-config-no-upstream")))?;
+    let incoming = cluster_from_files(&files).context(tr!("fetch-config-invalid"))?;
+    let changes = cluster.changes_to(&incoming);
+    if changes.is_empty() {
+        info!("{}", tr!("fetch-config-up-to-date"));
+        return Ok(());
+    }
+    print!("{}", render_changes(&changes));
+    if opts.check {
+        return Ok(());
+    }
 
-    let targets = cluster.match_targets(opts.patterns)?;
     let mut hooks = HookRunner::new()?;
     hooks.set_action(run_hook);
-    hooks.run("deploy", HookKind::Pre, Some(&targets))?;
+    let mut targets: Vec<String> =
+        changes.added.iter().chain(&changes.removed).chain(&changes.changed).cloned().collect();
+    targets.sort();
+    hooks.run("fetch-config", HookKind::Pre, Some(&targets))?;
 
-    let mut nodes = Vec::new();
-    if opts.only {
-        for target in &targets {
-            if target == "root" {
-                root.deploy(action)?;
+    let strategy = cluster.root.settings.pull_strategy.unwrap_or_default();
+    if let PullOutcome::Diverged = root.pull_fetched(|| pick_pull_strategy("root", strategy))? {
+        return Err(anyhow!(tr!("fetch-config-diverged")));
+    }
+    let incoming = Cluster::new()?;
+
+    // INVARIANT: Removals form a transaction of their own, because deploying opens another one.
+    {
+        let _transaction = begin_transaction("fetch-config");
+        let mut nuked = HashSet::new();
+        for name in cluster.dependency_order().into_iter().rev() {
+            let node = &cluster.nodes[name];
+            if !changes.removed.iter().any(|removed| removed == name)
+                || node.settings.deployment.kind.is_inline()
+                || !is_node_present(name, node)?
+            {
                 continue;
             }
+            nuke_node(&cluster, name, &mut nuked)?;
+        }
+    }
 
-            let entry = cluster.nodes.get(target).ok_or(anyhow!("Node {target:?} not defined"))?;
-            let node = Node::new_open(target, entry)?;
-            nodes.push(node);
+    let profile = match incoming.pick_profile(None, hostname().as_deref())? {
+        Some(profile) => Some((profile.to_string(), incoming.profile_nodes(profile)?)),
+        None => None,
+    };
+    let mut patterns = vec!["root".to_string()];
+    patterns.extend(
+        changes.added.iter().filter(|name| in_host_profile(profile.as_ref(), name, false)).cloned(),
+    );
+    for name in &changes.changed {
+        let (old, new) = (&cluster.nodes[name], &incoming.nodes[name]);
+        if !is_node_present(name, old)?
+            || !Node::new_open(name, old)?.is_deployed(DeployState::WithoutExcluded)?
+        {
+            continue;
         }
-    } else {
-        for target in &targets {
-            if target == "root" {
-                root.deploy(action)?;
-                continue;
+        if old.settings.deployment.kind != new.settings.deployment.kind {
+            warn!("{}", tr!("fetch-config-kind-changed", name = format!("{name:?}")));
+            continue;
+        }
+        patterns.push(name.clone());
+    }
+
+    let opts = DeployOptions {
+        targets: TargetOptions { patterns, all: false, stdin: false },
+        interactive: false,
+        only: false,
+        with_excluded: false,
+        force: false,
+        backup: false,
+        profile: None,
+        host_profile: None,
+        check: false,
+        locked: false,
+    };
+    run_deploy(run_hook, opts)?;
+
+    hooks.run("fetch-config", HookKind::Post, Some(&targets))?;
+
+    Ok(())
+}
+
+fn render_changes(changes: &ClusterChanges) -> String {
+    let mut text = String::new();
+    if changes.root {
+        text.push_str("~ root\n");
+    }
+    for (mark, names) in [("+", &changes.added), ("-", &changes.removed), ("~", &changes.changed)] {
+        for name in names {
+            text.push_str(&format!("{mark} {name}\n"));
+        }
+    }
+
+    text
+}
+
+const PULL_CHOICES: [PullStrategy; 3] =
+    [PullStrategy::Rebase, PullStrategy::Merge, PullStrategy::Skip];
+
+fn pick_pull_strategy(name: &str, strategy: PullStrategy) -> Result<PullStrategy> {
+    match strategy {
+        PullStrategy::Prompt => {
+            let prompt = tr!("pull-diverged-prompt", name = format!("{name:?}"));
+            Ok(Select::new(&prompt, PULL_CHOICES.into()).prompt()?)
+        }
+        strategy => Ok(strategy),
+    }
+}
+
+// Outcome of every entry that got pulled, such that entries failing to pull never keep the
+// remaining entries from being pulled.
+#[derive(Debug, Default)]
+struct PullSummary {
+    outcomes: Vec<(String, Option<PullOutcome>)>,
+    errors: Vec<anyhow::Error>,
+}
+
+impl PullSummary {
+    fn record(&mut self, name: &str, result: Result<PullOutcome>) {
+        let outcome = match result {
+            Ok(outcome) => Some(outcome),
+            Err(error) => {
+                self.errors
+                    .push(error.context(tr!("pull-entry-failure", name = format!("{name:?}"))));
+                None
             }
+        };
+        self.outcomes.push((name.to_string(), outcome));
+    }
 
-            for (name, entry) in cluster.dependency_iter(target) {
-                let node = Node::new_open(name, entry)?;
-                nodes.push(node);
+    fn count(&self, wanted: impl Fn(Option<PullOutcome>) -> bool) -> usize {
+        self.outcomes.iter().filter(|(_, outcome)| wanted(*outcome)).count()
+    }
+
+    fn show(&self) {
+        for (name, outcome) in &self.outcomes {
+            if let Some(outcome) = outcome {
+                info!("{}", tr!("pull-entry", name = name.as_str(), outcome = outcome.to_string()));
             }
         }
+
+        info!(
+            "{}",
+            tr!(
+                "pull-summary",
+                updated = self.count(|outcome| outcome.is_some_and(|o| o.is_updated())),
+                unchanged = self.count(|outcome| matches!(
+                    outcome,
+                    Some(PullOutcome::UpToDate | PullOutcome::Fetched)
+                )),
+                skipped = self.count(|outcome| matches!(
+                    outcome,
+                    Some(PullOutcome::NoUpstream | PullOutcome::Detached)
+                )),
+                diverged = self.count(|outcome| outcome == Some(PullOutcome::Diverged)),
+                failed = self.count(|outcome| outcome.is_none()),
+            )
+        );
+
+        let diverged: Vec<&str> = self
+            .outcomes
+            .iter()
+            .filter(|(_, outcome)| *outcome == Some(PullOutcome::Diverged))
+            .map(|(name, _)| name.as_str())
+            .collect();
+        if !diverged.is_empty() {
+            warn!("{}", tr!("pull-diverged", names = diverged.join(", ")));
+        }
     }
 
-    for node in nodes {
-        node.deploy(action)?;
+    // INVARIANT: Every failure is reported in one-shot, after every entry got its turn.
+    fn finish(self) -> Result<()> {
+        self.show();
+        self.errors.into_iter().map(Err).bcollect::<Vec<()>>()?;
+        Ok(())
+    }
+}
+
+fn run_sync(run_hook: HookAction, opts: SyncOptions) -> Result<()> {
+    if !opts.daemon {
+        return reconcile(run_hook);
+    }
+
+    let Some(_lock) = lock_sync_daemon()? else {
+        return Err(anyhow!(tr!("sync-daemon-running")));
+    };
+    let interval = Duration::from_secs(opts.interval.max(1));
+    let config_dir = config_dir()?;
+    let mut status = SyncStatus::new(interval);
+    status.save()?;
+    info!("{}", tr!("sync-daemon-start", pid = status.pid, interval = status.interval));
+
+    let mut trigger = SyncTrigger::Start;
+    loop {
+        info!("{}", tr!("sync-reconcile", trigger = trigger.to_string()));
+        let result = reconcile(run_hook);
+        if let Err(error) = &result {
+            warn!("{}", tr!("sync-reconcile-failure", error = format!("{error:?}")));
+        }
+        status.record(trigger, &result);
+        status.save()?;
+
+        // INVARIANT: Changes made by reconciling itself, e.g., deploying root into the
+        // configuration directory, never trigger yet another reconcile.
+        let watcher = ConfigWatcher::new(&config_dir)?;
+        trigger = if watcher.wait(interval)? { SyncTrigger::Config } else { SyncTrigger::Timer };
+    }
+}
+
+// INVARIANT: Root may have picked another store layout since the last reconcile.
+fn reconcile(run_hook: HookAction) -> Result<()> {
+    set_store_layout(resolve_store_layout());
+    if store_layout_changed() {
+        let moved = migrate_store_layout(&Cluster::new()?)?;
+        info!("{}", tr!("store-layout-migrated", count = moved.len()));
+    }
+
+    let opts = DeployOptions {
+        targets: TargetOptions { patterns: Vec::new(), all: true, stdin: false },
+        interactive: false,
+        only: false,
+        with_excluded: false,
+        force: false,
+        backup: false,
+        profile: None,
+        host_profile: None,
+        check: false,
+        locked: false,
+    };
+    run_deploy(run_hook, opts)
+}
+
+fn show_sync_status() -> Result<()> {
+    let status = SyncStatus::load()?;
+    match (&status, is_sync_daemon_running()?) {
+        (Some(status), true) => {
+            println!("{}", tr!("sync-status-running", pid = status.pid, interval = status.interval))
+        }
+        (None, true) => println!("{}", tr!("sync-status-starting")),
+        (_, false) => println!("{}", tr!("sync-status-stopped")),
     }
 
-    hooks.run("deploy", HookKind::Post, Some(&targets))?;
+    let Some(last) = status.and_then(|status| status.last) else {
+        println!("{}", tr!("sync-status-never"));
+        return Ok(());
+    };
+    let (ago, trigger) = (last.seconds_ago(), last.trigger.to_string());
+    match last.error {
+        Some(error) => {
+            println!("{}", tr!("sync-status-failed", ago = ago, trigger = trigger, error = error))
+        }
+        None => println!("{}", tr!("sync-status-succeeded", ago = ago, trigger = trigger)),
+    }
 
     Ok(())
 }
 
-fn run_undeploy(run_hook: HookAction, opts: UndeployOptions) -> Result<()> {
+fn run_freeze(run_hook: HookAction, opts: FreezeOptions) -> Result<()> {
     let cluster = Cluster::new()?;
     let root = Root::new_open(&cluster.root)?;
-    let action =
-        if opts.excluded_only { DeployAction::UndeployExcludes } else { DeployAction::Undeploy };
+    let patterns = if opts.patterns.is_empty() { vec!["*".into()] } else { opts.patterns };
+    let targets = cluster.match_targets(patterns)?;
 
-    let targets = cluster.match_targets(opts.patterns)?;
     let mut hooks = HookRunner::new()?;
     hooks.set_action(run_hook);
-    hooks.run("undeploy", HookKind::Pre, Some(&targets))?;
-
-    let mut nodes = Vec::new();
-    if opts.only {
-        for target in &targets {
-            if target == "root" {
-                root.deploy(action)?;
-                continue;
-            }
+    let targets = cluster.match_targets(hooks.run_pre("freeze", targets)?)?;
 
-            let entry = cluster.nodes.get(target).ok_or(anyhow!("Node {target:?} not defined"))?;
-            let node = Node::new_open(target, entry)?;
-            nodes.push(node);
+    let mut lock = ClusterLock::load()?;
+    let mut frozen = Vec::new();
+    for target in targets.iter().filter(|target| *target != "root") {
+        let entry = node_entry(&cluster, target)?;
+        if entry.settings.subdir.is_some() {
+            info!("{}", tr!("freeze-inline", name = format!("{target:?}")));
+            continue;
         }
-    } else {
-        for target in &targets {
-            if target == "root" {
-                root.deploy(action)?;
-                continue;
-            }
 
-            for (name, entry) in cluster.dependency_iter(target) {
-                let node = Node::new_open(name, entry)?;
-                nodes.push(node);
-            }
+        // INVARIANT: Freezing never clones nodes missing from repository store.
+        if !is_node_present(target, entry)? {
+            warn!("{}", tr!("freeze-missing", name = format!("{target:?}")));
+            continue;
         }
+
+        let node = Node::new_open(target, entry)?;
+        let Some(commit) = node.head_id() else {
+            warn!("{}", tr!("freeze-unborn", name = format!("{target:?}")));
+            continue;
+        };
+
+        // INVARIANT: Nodes detached by locked deployment keep the branch they were frozen from.
+        let branch = match lock.get(target) {
+            Some(locked) if node.is_detached() => locked.branch.clone(),
+            _ => node.current_branch()?,
+        };
+        lock.freeze(target, commit, branch);
+        frozen.push(target.clone());
     }
 
-    for node in nodes {
-        node.deploy(action)?;
+    lock.save()?;
+    if !frozen.is_empty()
+        && root.commit_file(lock.path(), &format!("Freeze {}", frozen.join(", ")))?
+    {
+        info!("{}", tr!("freeze-committed", names = frozen.join(", ")));
     }
 
-    hooks.run("undeploy", HookKind::Post, Some(&targets))?;
+    hooks.run("freeze", HookKind::Post, Some(&targets))?;
 
     Ok(())
 }
 
-#[instrument(skip(opts), level = "debug")]
-fn run_remove(run_hook: HookAction, opts: RemoveOptions) -> Result<()> {
+fn run_thaw(run_hook: HookAction, opts: ThawOptions) -> Result<()> {
     let cluster = Cluster::new()?;
+    let root = Root::new_open(&cluster.root)?;
+    let patterns = if opts.patterns.is_empty() { vec!["*".into()] } else { opts.patterns };
+    let targets = cluster.match_targets(patterns)?;
 
-    let targets = cluster.match_targets(opts.patterns)?;
     let mut hooks = HookRunner::new()?;
     hooks.set_action(run_hook);
-    hooks.run("rm", HookKind::Pre, Some(&targets))?;
+    let targets = cluster.match_targets(hooks.run_pre("thaw", targets)?)?;
 
-    if targets.contains(&"root".into()) {
-        warn!("Removing root will nuke your entire cluster");
-        if prompt_confirmation("Do you want to send your cluster to the gallows? [y/n]")? {
-            nuke_cluster(&cluster)?;
+    let mut lock = ClusterLock::load()?;
+    let mut thawed = Vec::new();
+    for target in targets.iter().filter(|target| *target != "root") {
+        let Some(locked) = lock.thaw(target) else {
+            continue;
+        };
+        thawed.push(target.clone());
+        let entry = node_entry(&cluster, target)?;
+        if !is_node_present(target, entry)? {
+            continue;
         }
-    } else {
-        for target in &targets {
-            let node = cluster.nodes.get(target).ok_or(anyhow!("Node {target:?} not defined"))?;
-            let repo = Node::new_open(target, node)?;
-            repo.nuke()?;
+
+        // INVARIANT: Nodes only go back to their branch if locked deployment detached them.
+        let node = Node::new_open(target, entry)?;
+        if node.is_detached() && node.head_id().as_ref() == Some(&locked.commit) {
+            let was_deployed = node.is_deployed(DeployState::WithoutExcluded)?;
+            node.checkout_branch(&locked.branch)?;
+            if node.needs_redeploy(was_deployed)? {
+                warn_redeploy(target);
+            }
         }
     }
 
-    hooks.run("rm", HookKind::Post, Some(&targets))?;
+    lock.save()?;
+    if !thawed.is_empty()
+        && root.commit_file(lock.path(), &format!("Thaw {}", thawed.join(", ")))?
+    {
+        info!("{}", tr!("thaw-committed", names = thawed.join(", ")));
+    }
+
+    hooks.run("thaw", HookKind::Post, Some(&targets))?;
 
     Ok(())
 }
 
-fn nuke_cluster(cluster: &Cluster) -> Result<()> {
-    let root = Root::new_open(&cluster.root)?;
-    root.nuke()?;
+fn run_rollback(run_hook: HookAction, opts: RollbackOptions) -> Result<()> {
+    let cluster = Cluster::new()?;
+    let entry = node_entry(&cluster, &opts.node)?;
+    if !is_node_present(&opts.node, entry)? {
+        return Err(anyhow!(tr!("rollback-missing", name = format!("{:?}", opts.node))));
+    }
+    let node = Node::new_open(&opts.node, entry)?;
 
-    for (name, node) in &cluster.nodes {
-        if !data_dir()?.join(name).exists() {
-            warn!("Node {name:?} not found in repository store");
-            continue;
+    if opts.list {
+        let head = node.head_id();
+        for commit in node.deployment_history()? {
+            let marker = if Some(&commit) == head.as_ref() { "*" } else { " " };
+            println!("{marker} {commit}");
         }
-
-        let repo = Node::new_open(name, node)?;
-        repo.nuke()?;
+        return Ok(());
     }
 
-    remove_dir_all(config_dir()?)?;
-    info!("Configuration directory removed");
+    let mut hooks = HookRunner::new()?;
+    hooks.set_action(run_hook);
+    let targets = vec![opts.node.clone()];
+    hooks.run("rollback", HookKind::Pre, Some(&targets))?;
 
-    remove_dir_all(data_dir()?)?;
-    info!("Data directory removed");
+    let commit = node.rollback()?;
+    note_deployed(node.name());
+    info!("{}", tr!("rollback-done", name = format!("{:?}", opts.node), commit = commit));
+    report_deployment(&cluster)?;
+
+    hooks.run("rollback", HookKind::Post, Some(&targets))?;
 
     Ok(())
 }
 
-fn run_list(run_hook: HookAction, opts: ListOptions) -> Result<()> {
+fn run_undo(run_hook: HookAction, opts: UndoOptions) -> Result<()> {
     let cluster = Cluster::new()?;
-    let root = Root::new_open(&cluster.root)?;
+    if opts.list {
+        match last_transaction()? {
+            Some(transaction) => print!("{}", render_transaction(&transaction)),
+            None => info!("{}", tr!("undo-nothing")),
+        }
+        return Ok(());
+    }
 
     let mut hooks = HookRunner::new()?;
     hooks.set_action(run_hook);
+    let targets = last_transaction()?
+        .map(|transaction| transaction.steps.into_iter().map(|step| step.entry).collect())
+        .unwrap_or_default();
+    hooks.run("undo", HookKind::Pre, Some(&targets))?;
 
-    hooks.run("ls", HookKind::Pre, None)?;
+    match undo_transaction(&cluster)? {
+        Some(transaction) => info!(
+            "{}",
+            tr!("undo-done", command = transaction.command, count = transaction.steps.len())
+        ),
+        None => info!("{}", tr!("undo-nothing")),
+    }
+    report_deployment(&cluster)?;
 
-    let tablize = TablizeCluster::new(&root, &cluster);
-    if opts.names_only {
-        tablize.names_only()?;
-    } else {
-        tablize.fancy()?;
+    hooks.run("undo", HookKind::Post, Some(&targets))?;
+
+    Ok(())
+}
+
+fn render_transaction(transaction: &Transaction) -> String {
+    let ago = transaction.seconds_ago();
+    let mut text =
+        format!("{}\n", tr!("undo-list", command = transaction.command.as_str(), ago = ago));
+    for step in &transaction.steps {
+        let inverse = match step.inverse() {
+            DeployAction::Deploy => "deploy",
+            DeployAction::DeployAll => "deploy with excluded",
+            DeployAction::Undeploy => "undeploy",
+            DeployAction::UndeployExcludes => "undeploy excluded",
+        };
+        text.push_str(&format!("  {inverse} {:?}\n", step.entry));
+        for path in &step.placed {
+            text.push_str(&format!("    + {}\n", path.display()));
+        }
+        for path in &step.removed {
+            text.push_str(&format!("    - {}\n", path.display()));
+        }
     }
 
-    hooks.run("ls", HookKind::Post, None)?;
+    text
+}
+
+fn run_validate(opts: ValidateOptions) -> Result<()> {
+    // INVARIANT: Staged cluster definitions are validated even if the one in the configuration
+    // directory is broken.
+    if opts.staged {
+        let mut entry = RootEntry::builder()?;
+        if peek_root_setting("work_dir_alias") == Some("home_dir".into()) {
+            entry = entry.deploy_to_home_dir()?;
+        }
+        let root = Root::new_open(&entry.build())?;
+        validate_staged(&root)?;
+        info!("{}", tr!("validate-staged-valid"));
+        return Ok(());
+    }
+
+    let _ = Cluster::new()?;
+    let _ = HookRunner::new()?;
+    info!("{}", tr!("validate-valid"));
+
+    Ok(())
+}
+
+fn run_doctor(opts: DoctorOptions) -> Result<()> {
+    let diagnosis = Diagnosis::gather();
+    if !print_structured(opts.format, || diagnosis.structured())? {
+        print!("{}", diagnosis.render());
+    }
+
+    let failed = diagnosis.count(Verdict::Fail);
+    if failed > 0 {
+        return Err(anyhow!(tr!("doctor-failure", count = failed)));
+    }
+
+    Ok(())
+}
+
+fn validate_staged(root: &Root) -> Result<()> {
+    let files = root.staged_config_files()?;
+    let _ = cluster_from_files(&files)?;
+    if let Some(hooks) = files.get(Path::new("hooks.toml")) {
+        let _ = HookRunner::from_definition(hooks)?;
+    }
+
+    Ok(())
+}
+
+// INVARIANT: Only "root.toml" and files directly inside "nodes" make up the cluster definition,
+// like they do in the configuration directory.
+fn cluster_from_files(files: &BTreeMap<PathBuf, String>) -> Result<Cluster> {
+    let definition = files
+        .get(Path::new("root.toml"))
+        .ok_or_else(|| anyhow!("{}", tr!("validate-staged-no-root")))?;
+    let nodes: BTreeMap<String, String> = files
+        .iter()
+        .filter(|(path, _)| path.parent() == Some(Path::new("nodes")))
+        .filter_map(|(path, data)| {
+            Some((path.file_stem()?.to_string_lossy().into_owned(), data.clone()))
+        })
+        .collect();
+
+    Cluster::from_definition(definition, &nodes)
+}
+
+fn run_why(opts: WhyOptions) -> Result<()> {
+    let cluster = Cluster::new()?;
+    let explanation = Explanation::new(&cluster, &opts.node, &opts.patterns, opts.profile)?;
+    print!("{}", explanation.render());
+
+    Ok(())
+}
+
+fn run_share(opts: ShareOptions) -> Result<()> {
+    let cluster = Cluster::new()?;
+    let definition = cluster.share()?;
+    match opts.output {
+        Some(path) => {
+            std::fs::write(&path, definition)
+                .with_context(|| tr!("write-failure", path = format!("{path:?}")))?;
+            info!("{}", tr!("share-written", path = format!("{path:?}")));
+        }
+        None => print!("{definition}"),
+    }
+
+    Ok(())
+}
+
+fn run_prompt_segment(opts: PromptSegmentOptions) -> Result<()> {
+    let cluster = Cluster::new()?;
+    let segment = prompt_segment(&cluster, opts.refresh)?;
+    if !segment.is_empty() {
+        println!("{segment}");
+    }
+
+    Ok(())
+}
+
+fn run_devcontainer(opts: DevcontainerOptions) -> Result<()> {
+    let DevcontainerAction::Emit(opts) = opts.action;
+    let url = match opts.url {
+        Some(url) => url,
+        None => {
+            let cluster = Cluster::new()?;
+            let root = Root::new_open(&cluster.root)?;
+            root.origin_url().ok_or_else(|| anyhow!(tr!("devcontainer-no-url")))?
+        }
+    };
+
+    let snippet = ContainerSetup::new(url, opts.patterns, opts.profile).render(opts.format)?;
+    match opts.output {
+        Some(path) => {
+            std::fs::write(&path, snippet)
+                .with_context(|| tr!("write-failure", path = format!("{path:?}")))?;
+            info!("{}", tr!("devcontainer-written", path = format!("{path:?}")));
+        }
+        None => print!("{snippet}"),
+    }
+
+    Ok(())
+}
+
+fn run_man(opts: ManOptions) -> Result<()> {
+    let _ = man::write_man_pages(Ocd::command(), &opts.output)?;
+
+    Ok(())
+}
+
+fn run_completions(opts: CompletionsOptions) -> Result<()> {
+    if opts.list_nodes {
+        print!("{}", CompletionIndex::load()?.list()?);
+        return Ok(());
+    }
+
+    print!("{}", completions::render(Ocd::command(), opts.shell, opts.dynamic));
+
+    Ok(())
+}
+
+fn run_stats(opts: StatsOptions) -> Result<()> {
+    let mut stats = UsageStats::load()?;
+    if opts.clear {
+        stats.clear();
+        stats.save()?;
+        info!("{}", tr!("stats-cleared"));
+        return Ok(());
+    }
+
+    if stats.is_empty() {
+        info!("{}", tr!("stats-empty"));
+        return Ok(());
+    }
+    print!("{}", stats.render());
+
+    Ok(())
+}
+
+fn run_bugreport(opts: BugreportOptions) -> Result<()> {
+    let report = BugReport::gather().render();
+    match opts.output {
+        Some(path) => {
+            std::fs::write(&path, report)
+                .with_context(|| tr!("write-failure", path = format!("{path:?}")))?;
+            info!("{}", tr!("bugreport-written", path = format!("{path:?}")));
+        }
+        None => print!("{report}"),
+    }
 
     Ok(())
 }
 
-fn run_git(opts: Vec<OsString>) -> Result<()> {
+fn run_git(opts: Vec<OsString>, ssh_agent: bool) -> Result<()> {
     let cluster = Cluster::new()?;
     let root = Root::new_open(&cluster.root)?;
     let patterns = opts[0].to_string_lossy().into_owned();
-    let patterns: Vec<String> = patterns.split(',').map(Into::into).collect();
+    let patterns: Vec<String> = if patterns == "-" {
+        read_targets(std::io::stdin().lock())?
+    } else {
+        patterns.split(',').map(Into::into).collect()
+    };
     let targets = cluster.match_targets(patterns)?;
 
+    // INVARIANT: Agent must outlive every Git call of this run.
+    let _agent = if ssh_agent && targets.len() > 1 {
+        SshAgent::start()
+            .inspect_err(|error| {
+                warn!("{}", tr!("ssh-agent-failure", error = format!("{error:?}")))
+            })
+            .ok()
+    } else {
+        None
+    };
+
     for target in &targets {
         if target == "root" {
+            // INVARIANT: Broken cluster definitions never get committed into root, unless Git is
+            // told to skip verification.
+            if opts.get(1).is_some_and(|arg| arg == "commit")
+                && !opts[2..].iter().any(|arg| arg == "--no-verify" || arg == "-n")
+            {
+                validate_staged(&root).context(tr!("root-commit-invalid"))?;
+            }
+
+            let before = root.head_id();
             root.gitcall(opts[1..].to_vec())?;
+            if root.head_id() != before && !root.is_deployed(DeployState::WithoutExcluded)? {
+                warn_redeploy(target);
+            }
             continue;
         }
 
-        let node = cluster.nodes.get(target).ok_or(anyhow!("{target} not found"))?;
+        let node = node_entry(&cluster, target)?;
         let node = Node::new_open(target, node)?;
+
+        // INVARIANT: Passthrough Git calls may move HEAD without deploying the result.
+        let before = node.head_id();
+        let was_deployed = node.is_deployed(DeployState::WithoutExcluded)?;
         node.gitcall(opts[1..].to_vec())?;
+        if node.head_id() != before && node.needs_redeploy(was_deployed)? {
+            warn_redeploy(target);
+        }
     }
 
     Ok(())
 }
 
+fn warn_redeploy(target: &str) {
+    warn!("{}", tr!("redeploy-head-moved", name = format!("{target:?}")));
+    warn!("{}", tr!("redeploy-hint", name = target));
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    use clap::CommandFactory;
+    #[test]
+    fn smoke_read_targets() -> Result<()> {
+        let input = "vim\n\n  bash \nroot\n";
+        assert_eq!(read_targets(input.as_bytes())?, vec!["vim", "bash", "root"]);
+        assert!(read_targets("\n".as_bytes())?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn smoke_pick_item_display() {
+        let item =
+            PickItem { name: "vim".into(), about: Some("Editor of choice".into()), state: None };
+        assert_eq!(item.to_string(), "vim - Editor of choice");
+        let item = PickItem { name: "sh".into(), about: None, state: None };
+        assert_eq!(item.to_string(), "sh");
+        let item = PickItem {
+            name: "vim".into(),
+            about: Some("Editor of choice".into()),
+            state: Some("partially deployed".into()),
+        };
+        assert_eq!(item.to_string(), "vim (partially deployed) - Editor of choice");
+    }
+
+    #[test]
+    fn smoke_needs_git_binary() {
+        let command = |args: &[&str]| Ocd::parse_from(args).command.unwrap();
+        assert!(needs_git_binary(&command(&["ocd", "clone", "https://host/dots.git"])));
+        assert!(needs_git_binary(&command(&["ocd", "deploy", "vim"])));
+        assert!(!needs_git_binary(&command(&["ocd", "deploy", "--check", "vim"])));
+        assert!(!needs_git_binary(&command(&["ocd", "ls"])));
+    }
 
     #[test]
     fn cli_verify_structure() {
         Ocd::command().debug_assert();
     }
+
+    #[test]
+    fn cli_verify_help() {
+        let ocd = Ocd::command();
+        for cmd in std::iter::once(&ocd).chain(ocd.get_subcommands()) {
+            assert!(cmd.get_about().is_some(), "{} has no about", cmd.get_name());
+            for arg in cmd.get_arguments() {
+                assert!(
+                    arg.get_help().is_some(),
+                    "{} {} has no help",
+                    cmd.get_name(),
+                    arg.get_id()
+                );
+            }
+        }
+    }
 }
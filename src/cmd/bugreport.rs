@@ -0,0 +1,197 @@
+// SPDX-FileCopyrightText: 2025 Jason Pena <jasonpena@awkless.com>
+// SPDX-License-Identifier: MIT
+
+//! Bug report bundles.
+//!
+//! Bug reports are hard to act on without knowing which build of OCD ran, what the cluster looked
+//! like, and what got deployed last. A bug report bundle gathers all of it into one Markdown
+//! document that can be pasted into an issue. The cluster definition is redacted the same way as
+//! through `ocd share`, credentials are stripped from URLs, and the home directory is shortened to
+//! `~`, such that bundles can be published as they are.
+//!
+//! OCD keeps no log files, so bundles ask for the output of the failing command rerun with
+//! `--trace-git` and debug logging instead.
+
+use super::version::{BuildInfo, VersionFormat};
+
+use crate::{
+    model::{cluster::Cluster, config_dir, data_dir, home_dir, hook::HookRunner, state_dir},
+    store::{has_git_binary, redact_credentials, DeployJournal},
+};
+
+use anyhow::Result;
+use std::{fmt::Write as _, path::PathBuf};
+
+/// Number of deployed commits shown per node.
+const JOURNAL_TAIL: usize = 3;
+
+/// Bug report bundle.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct BugReport {
+    sections: Vec<Section>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Section {
+    title: &'static str,
+    lang: &'static str,
+    body: String,
+}
+
+impl BugReport {
+    /// Gather bug report bundle of this machine.
+    ///
+    /// # Invariants
+    ///
+    /// - Never fails, because broken clusters are exactly what bug reports are about. Whatever
+    ///   cannot be gathered is reported in place of its section.
+    pub(crate) fn gather() -> Self {
+        let cluster = Cluster::new();
+        let definition = match &cluster {
+            Ok(cluster) => cluster.share().unwrap_or_else(|error| format!("{error:#}\n")),
+            Err(error) => format!("{error:#}\n"),
+        };
+
+        let sections = vec![
+            Section {
+                title: "Version",
+                lang: "text",
+                body: BuildInfo::gather()
+                    .render(VersionFormat::Text, true)
+                    .unwrap_or_else(|error| format!("{error:#}\n")),
+            },
+            Section { title: "Environment", lang: "text", body: environment() },
+            Section { title: "Checks", lang: "text", body: checks(cluster.as_ref().err()) },
+            Section { title: "Cluster definition", lang: "toml", body: definition },
+            Section { title: "Deployment journal", lang: "text", body: journal() },
+        ];
+
+        Self { sections }
+    }
+
+    /// Render bug report bundle as Markdown, with credentials and home directory redacted.
+    pub(crate) fn render(&self) -> String {
+        let mut report = String::from(
+            "## OCD bug report\n\n<!-- Describe what you did, what you expected, and what \
+             happened instead. -->\n",
+        );
+        for section in &self.sections {
+            let _ = write!(
+                report,
+                "\n### {}\n\n```{}\n{}\n```\n",
+                section.title,
+                section.lang,
+                section.body.trim_end()
+            );
+        }
+        report.push_str(
+            "\n<!-- Rerun the failing command as \"RUST_LOG=debug ocd --trace-git ...\", and \
+             attach its output. -->\n",
+        );
+
+        redact_home(&redact_credentials(&report))
+    }
+}
+
+fn environment() -> String {
+    let var = |name: &str| std::env::var(name).unwrap_or_else(|_| "unset".into());
+    let path = |dir: Result<PathBuf>| match dir {
+        Ok(dir) => dir.display().to_string(),
+        Err(error) => format!("{error:#}"),
+    };
+
+    let mut env = format!("os: {} {}\n", std::env::consts::OS, std::env::consts::ARCH);
+    let _ = writeln!(env, "shell: {}", var("SHELL"));
+    let _ = writeln!(env, "term: {}", var("TERM"));
+    let _ = writeln!(env, "config dir: {}", path(config_dir()));
+    let _ = writeln!(env, "data dir: {}", path(data_dir()));
+    let _ = writeln!(env, "state dir: {}", path(state_dir()));
+    env
+}
+
+fn checks(cluster: Option<&anyhow::Error>) -> String {
+    let outcome = |error: Option<String>| error.unwrap_or_else(|| "ok".into());
+    let root = data_dir().is_ok_and(|dir| dir.join("root").exists());
+
+    let mut checks =
+        format!("cluster definition: {}\n", outcome(cluster.map(|error| format!("{error:#}"))));
+    let hooks = HookRunner::new().err().map(|error| format!("{error:#}"));
+    let _ = writeln!(checks, "hooks: {}", outcome(hooks));
+    let _ = writeln!(checks, "root repository: {}", if root { "present" } else { "missing" });
+    let _ =
+        writeln!(checks, "git binary: {}", if has_git_binary() { "found" } else { "not found" });
+    checks
+}
+
+fn journal() -> String {
+    let journal = match DeployJournal::load() {
+        Ok(journal) => journal,
+        Err(error) => return format!("{error:#}\n"),
+    };
+
+    let mut text = String::new();
+    for (name, commits) in journal.nodes() {
+        let tail = &commits[commits.len().saturating_sub(JOURNAL_TAIL)..];
+        let tail: Vec<&str> = tail.iter().map(|commit| &commit[..commit.len().min(12)]).collect();
+        let _ = writeln!(text, "{name}: {}", tail.join(" "));
+    }
+
+    if text.is_empty() {
+        text.push_str("nothing deployed yet\n");
+    }
+
+    text
+}
+
+// INVARIANT: Root directory as home directory would turn every absolute path into "~".
+fn redact_home(text: &str) -> String {
+    match home_dir() {
+        Ok(home) if home.parent().is_some() => text.replace(&home.display().to_string(), "~"),
+        _ => text.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use sealed_test::prelude::*;
+    use std::fs::{create_dir_all, write};
+
+    #[sealed_test(env = [
+        ("XDG_CONFIG_HOME", ".config"),
+        ("XDG_DATA_HOME", ".local/share"),
+        ("XDG_STATE_HOME", ".local/state"),
+    ])]
+    fn smoke_bug_report_render() -> Result<()> {
+        let pwd = std::env::current_dir()?;
+        std::env::set_var("HOME", &pwd);
+
+        // INVARIANT: Broken clusters still produce a report.
+        let report = BugReport::gather().render();
+        assert!(report.contains("cluster definition: "), "{report}");
+        assert!(!report.contains("cluster definition: ok"), "{report}");
+
+        create_dir_all(".config/ocd/nodes")?;
+        write(".config/ocd/root.toml", "[settings]\nwork_dir_alias = \"home_dir\"\n")?;
+        write(
+            ".config/ocd/nodes/vim.toml",
+            "[settings]\ndeployment = \"bare_alias\"\nurl = \"https://token@host/vim.git\"\n",
+        )?;
+        let mut journal = DeployJournal::load()?;
+        for commit in ["0123456789abcdef", "fedcba9876543210", "00112233", "44556677"] {
+            journal.record("vim", commit);
+        }
+        journal.save()?;
+
+        let report = BugReport::gather().render();
+        assert!(report.starts_with("## OCD bug report\n"), "{report}");
+        assert!(report.contains("cluster definition: ok\n"), "{report}");
+        assert!(report.contains("url = \"https://***@host/vim.git\""), "{report}");
+        assert!(report.contains("vim: fedcba987654 00112233 44556677\n"), "{report}");
+        assert!(report.contains("config dir: ~/.config/ocd\n"), "{report}");
+        assert!(!report.contains(&pwd.display().to_string()), "{report}");
+
+        Ok(())
+    }
+}
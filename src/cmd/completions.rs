@@ -0,0 +1,445 @@
+// SPDX-FileCopyrightText: 2025 Jason Pena <jasonpena@awkless.com>
+// SPDX-License-Identifier: MIT
+
+//! Shell completion functions.
+//!
+//! Completion functions for bash, zsh, fish, and PowerShell are generated by [`clap_complete`]
+//! straight from the clap definition of the OCD command set, such that they always track the live
+//! CLI. Dynamic completion functions also complete node names by calling back into OCD on each
+//! tab press. Parsing the whole cluster on each tab press is too slow for large clusters, so node
+//! names are kept in a completion index in OCD's cache directory. The index is rebuilt whenever a
+//! file of the cluster definition changes, i.e., whenever the modification time of a root or node
+//! configuration file changes, or such a file comes or goes.
+
+use crate::model::{cache_dir, cluster::Cluster, config_dir};
+
+use anyhow::Result;
+use clap::ValueEnum;
+use clap_complete::{generate, Shell};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::hash_map::DefaultHasher,
+    fmt::Write as _,
+    fs::{create_dir_all, read_dir, read_to_string, write},
+    hash::{Hash, Hasher},
+    path::{Path, PathBuf},
+    time::UNIX_EPOCH,
+};
+use tracing::{debug, instrument};
+
+/// Shells that completion functions can be generated for.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum CompletionShell {
+    /// Bourne again shell.
+    Bash,
+
+    /// Z shell.
+    #[default]
+    Zsh,
+
+    /// Friendly interactive shell.
+    Fish,
+
+    /// PowerShell.
+    Powershell,
+}
+
+impl From<CompletionShell> for Shell {
+    fn from(shell: CompletionShell) -> Self {
+        match shell {
+            CompletionShell::Bash => Shell::Bash,
+            CompletionShell::Zsh => Shell::Zsh,
+            CompletionShell::Fish => Shell::Fish,
+            CompletionShell::Powershell => Shell::PowerShell,
+        }
+    }
+}
+
+/// Positional arguments that take node names.
+const NODE_ARGS: [&str; 6] = ["patterns", "node", "nodes", "entry", "entry_name", "old"];
+
+/// Render completion function of command for shell.
+///
+/// Node names are only completed if `dynamic` is set, through calls to `ocd completions
+/// --list-nodes` hooked onto the completion function generated by [`clap_complete`].
+pub(crate) fn render(cmd: clap::Command, shell: CompletionShell, dynamic: bool) -> String {
+    let mut cmd = cmd;
+    let name = cmd.get_name().to_string();
+    let mut script = Vec::new();
+    generate(Shell::from(shell), &mut cmd, &name, &mut script);
+    let script = String::from_utf8_lossy(&script).into_owned();
+    if !dynamic {
+        return script;
+    }
+
+    let subcmds: Vec<&clap::Command> =
+        cmd.get_subcommands().filter(|subcmd| !subcmd.is_hide_set()).collect();
+    let targeted = targeted(&subcmds);
+    match shell {
+        CompletionShell::Bash => hook_bash(script, &name, &targeted),
+        CompletionShell::Zsh => hook_zsh(script, &name, &targeted),
+        CompletionShell::Fish => hook_fish(script, &name, &targeted),
+        CompletionShell::Powershell => hook_powershell(script, &name, &targeted),
+    }
+}
+
+// Names of subcommands that take node names as positional arguments.
+fn targeted<'a>(subcmds: &[&'a clap::Command]) -> Vec<&'a str> {
+    subcmds
+        .iter()
+        .filter(|subcmd| {
+            subcmd.get_positionals().any(|arg| NODE_ARGS.contains(&arg.get_id().as_str()))
+        })
+        .map(|subcmd| subcmd.get_name())
+        .collect()
+}
+
+// INVARIANT: Node names are offered on top of whatever the generated function offers, except for
+// options.
+fn hook_bash(mut script: String, name: &str, targeted: &[&str]) -> String {
+    let _ = write!(
+        script,
+        "\n_{name}_nodes() {{\n    {name} completions --list-nodes 2>/dev/null | cut -d: -f1\n\
+         }}\n\n\
+         _{name}_dynamic() {{\n    _{name} \"$@\"\n    local cur=${{COMP_WORDS[COMP_CWORD]}}\n    \
+         [[ $cur == -* ]] && return 0\n    if (( COMP_CWORD == 1 )); then\n        \
+         COMPREPLY+=($(compgen -W \"$(_{name}_nodes)\" -- \"$cur\"))\n        return 0\n    \
+         fi\n"
+    );
+    if !targeted.is_empty() {
+        let _ = write!(
+            script,
+            "    case ${{COMP_WORDS[1]}} in\n        {}) COMPREPLY+=($(compgen -W \
+             \"$(_{name}_nodes)\" -- \"$cur\")) ;;\n    esac\n",
+            targeted.join("|")
+        );
+    }
+    let _ = write!(script, "}}\n\ncomplete -F _{name}_dynamic -o bashdefault -o default {name}\n");
+
+    script
+}
+
+// INVARIANT: Dynamic completion function takes over both when sourced and when autoloaded through
+// "$fpath", such that node names are completed on every tab press after the first one.
+fn hook_zsh(script: String, name: &str, targeted: &[&str]) -> String {
+    let dispatch = format!("\nif [ \"$funcstack[1]\" = \"_{name}\" ]; then");
+    let mut script = match script.find(&dispatch) {
+        Some(idx) => script[..idx].to_string(),
+        None => script,
+    };
+
+    let _ = write!(
+        script,
+        "\n_{name}_nodes() {{\n    local -a nodes\n    \
+         nodes=(${{(f)\"$({name} completions --list-nodes 2>/dev/null)\"}})\n    \
+         _describe -t nodes '{name} node' nodes\n}}\n\n\
+         _{name}_dynamic() {{\n    if [[ $PREFIX != -* ]] && (( CURRENT == 2 )); then\n        \
+         _{name}_nodes\n"
+    );
+    if !targeted.is_empty() {
+        let _ = write!(
+            script,
+            "    elif [[ $PREFIX != -* ]] && [[ ${{words[2]}} == ({}) ]]; then\n        \
+             _{name}_nodes\n",
+            targeted.join("|")
+        );
+    }
+    let _ = write!(
+        script,
+        "    fi\n    _{name} \"$@\"\n}}\n\ncompdef _{name}_dynamic {name}\n\
+         if [ \"$funcstack[1]\" = \"_{name}\" ]; then\n    _{name}_dynamic \"$@\"\nfi\n"
+    );
+
+    script
+}
+
+fn hook_fish(mut script: String, name: &str, targeted: &[&str]) -> String {
+    let _ = write!(
+        script,
+        "\nfunction __{name}_nodes\n    {name} completions --list-nodes 2>/dev/null | \
+         string replace : \\t\nend\n\n\
+         complete -c {name} -n __fish_{name}_needs_command -f -a '(__{name}_nodes)'\n"
+    );
+    if !targeted.is_empty() {
+        let _ = writeln!(
+            script,
+            "complete -c {name} -n '__fish_{name}_using_subcommand {}' -f -a '(__{name}_nodes)'",
+            targeted.join(" ")
+        );
+    }
+
+    script
+}
+
+// INVARIANT: Positional arguments end up in "$command" of the generated function, so targeted
+// commands are matched against the first command element instead.
+fn hook_powershell(script: String, name: &str, targeted: &[&str]) -> String {
+    let targeted: Vec<String> = targeted.iter().map(|name| format!("'{name}'")).collect();
+    let hook = format!(
+        "    $target = if ($commandElements.Count -gt 1) {{ $commandElements[1].ToString() }} \
+         else {{ '' }}\n    \
+         if ($command -eq '{name}' -or @({}) -contains $target) {{\n        \
+         foreach ($line in @({name} completions --list-nodes 2>$null)) {{\n            \
+         $node, $about = $line -split ':', 2\n            \
+         $completions += [CompletionResult]::new($node, $node, \
+         [CompletionResultType]::ParameterValue, $(if ($about) {{ $about }} else {{ $node }}))\n\
+         \x20       }}\n    }}\n\n",
+        targeted.join(", ")
+    );
+
+    script.replacen("    $completions.Where{", &format!("{hook}    $completions.Where{{"), 1)
+}
+
+/// Index of node names for completion functions.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Deserialize, Serialize)]
+pub(crate) struct CompletionIndex {
+    #[serde(skip)]
+    path: PathBuf,
+
+    /// Fingerprint of cluster definition files that index was built from.
+    #[serde(default)]
+    fingerprint: String,
+
+    /// Directories housing cluster definition files, i.e., of the cluster and its nested clusters.
+    #[serde(default)]
+    dirs: Vec<PathBuf>,
+
+    /// Node names along with their human-readable description, if any.
+    #[serde(default)]
+    nodes: Vec<IndexedNode>,
+}
+
+#[derive(Debug, Default, PartialEq, Eq, Clone, Deserialize, Serialize)]
+struct IndexedNode {
+    name: String,
+    about: String,
+}
+
+impl CompletionIndex {
+    /// Load completion index from cache directory.
+    ///
+    /// # Errors
+    ///
+    /// - Will fail if cache directory cannot be determined.
+    #[instrument(level = "debug")]
+    pub(crate) fn load() -> Result<Self> {
+        Ok(Self::load_from(cache_dir()?.join("completion.toml")))
+    }
+
+    /// Load completion index from specific path.
+    ///
+    /// # Invariants
+    ///
+    /// - Missing or unreadable index files just start an empty index, because everything in it
+    ///   can be rebuilt.
+    pub(crate) fn load_from(path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        let index = read_to_string(&path)
+            .ok()
+            .and_then(|data| toml::de::from_str::<CompletionIndex>(&data).ok())
+            .unwrap_or_else(|| {
+                debug!("No usable completion index at {path:?}, start with empty index");
+                CompletionIndex::default()
+            });
+
+        Self { path, ..index }
+    }
+
+    /// List node names for completion, rebuilding index first if cluster definition changed.
+    ///
+    /// Each line is a node name, followed by its description after a colon if it has one. Colons
+    /// inside node names are escaped.
+    ///
+    /// # Errors
+    ///
+    /// - Will fail if index needs to be rebuilt, but cluster definition cannot be loaded.
+    pub(crate) fn list(&mut self) -> Result<String> {
+        let dirs = if self.dirs.is_empty() { vec![config_dir()?] } else { self.dirs.clone() };
+        if self.fingerprint.is_empty() || self.fingerprint != fingerprint(&dirs) {
+            self.rebuild(&Cluster::new()?)?;
+
+            // INVARIANT: Shells keep completing on read-only cache directories, just slower.
+            if let Err(error) = self.save() {
+                debug!("Cannot save completion index: {error:?}");
+            }
+        }
+
+        let mut listing = String::new();
+        for node in &self.nodes {
+            let name = node.name.replace(':', "\\:");
+            match node.about.as_str() {
+                "" => {
+                    let _ = writeln!(listing, "{name}");
+                }
+                about => {
+                    let _ = writeln!(listing, "{name}:{about}");
+                }
+            }
+        }
+
+        Ok(listing)
+    }
+
+    fn rebuild(&mut self, cluster: &Cluster) -> Result<()> {
+        debug!("Rebuild completion index");
+        let mut dirs = vec![config_dir()?];
+        dirs.extend(
+            cluster
+                .nodes
+                .values()
+                .filter(|node| node.settings.deployment.kind.is_cluster())
+                .map(|node| node.settings.deployment.work_dir_alias.as_path().to_path_buf()),
+        );
+
+        self.fingerprint = fingerprint(&dirs);
+        self.dirs = dirs;
+        self.nodes = std::iter::once(IndexedNode { name: "root".into(), about: String::new() })
+            .chain(cluster.nodes.iter().map(|(name, node)| IndexedNode {
+                name: name.clone(),
+                about: node.about().unwrap_or_default(),
+            }))
+            .collect();
+
+        Ok(())
+    }
+
+    /// Save completion index to its cache file.
+    ///
+    /// # Errors
+    ///
+    /// - Will fail if cache file or its parent directory cannot be written.
+    pub(crate) fn save(&self) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            create_dir_all(parent)?;
+        }
+        write(&self.path, toml::ser::to_string(self)?)?;
+
+        Ok(())
+    }
+}
+
+// INVARIANT: Only file metadata is read, such that fingerprints stay cheap on large clusters.
+fn fingerprint(dirs: &[PathBuf]) -> String {
+    let mut hasher = DefaultHasher::new();
+    for dir in dirs {
+        let nodes = dir.join("nodes");
+        let mut files = vec![dir.join("root.toml"), nodes.clone()];
+        if let Ok(entries) = read_dir(&nodes) {
+            let mut entries: Vec<PathBuf> = entries.flatten().map(|entry| entry.path()).collect();
+            entries.sort();
+            files.extend(entries);
+        }
+
+        for file in files {
+            file.hash(&mut hasher);
+            modified(&file).hash(&mut hasher);
+        }
+    }
+
+    format!("{:016x}", hasher.finish())
+}
+
+fn modified(path: &Path) -> Option<u128> {
+    let modified = path.metadata().ok()?.modified().ok()?;
+    Some(modified.duration_since(UNIX_EPOCH).ok()?.as_nanos())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::cmd::Ocd;
+
+    use clap::CommandFactory;
+    use pretty_assertions::assert_eq as pretty_assert_eq;
+    use sealed_test::prelude::*;
+
+    #[test]
+    fn smoke_render_zsh() {
+        let script = render(Ocd::command(), CompletionShell::Zsh, false);
+        assert!(script.starts_with("#compdef ocd\n"), "{script}");
+        assert!(script.contains("'deploy:Deploy target entries in cluster'"), "{script}");
+        assert!(!script.contains("_ocd_nodes"), "{script}");
+
+        let script = render(Ocd::command(), CompletionShell::Zsh, true);
+        assert!(script.contains("[[ ${words[2]} == (init|deploy|undeploy|"), "{script}");
+        assert!(script.contains("$(ocd completions --list-nodes 2>/dev/null)"), "{script}");
+        assert!(
+            script.ends_with(
+                "compdef _ocd_dynamic ocd\nif [ \"$funcstack[1]\" = \"_ocd\" ]; then\n    \
+                 _ocd_dynamic \"$@\"\nfi\n"
+            ),
+            "{script}"
+        );
+        assert!(!script.contains("compdef _ocd ocd"), "{script}");
+    }
+
+    #[test]
+    fn smoke_render_bash() {
+        let script = render(Ocd::command(), CompletionShell::Bash, false);
+        assert!(script.starts_with("_ocd() {\n"), "{script}");
+        assert!(script.contains("ocd,deploy)"), "{script}");
+        assert!(!script.contains("_ocd_nodes"), "{script}");
+
+        let script = render(Ocd::command(), CompletionShell::Bash, true);
+        assert!(script.contains("        init|deploy|undeploy|"), "{script}");
+        assert!(script.contains("ocd completions --list-nodes 2>/dev/null | cut -d: -f1"));
+        assert!(script.ends_with("complete -F _ocd_dynamic -o bashdefault -o default ocd\n"));
+    }
+
+    #[test]
+    fn smoke_render_fish() {
+        let script = render(Ocd::command(), CompletionShell::Fish, false);
+        assert!(
+            script.contains(
+                "complete -c ocd -n \"__fish_ocd_needs_command\" -f -a \"deploy\" -d 'Deploy \
+                 target entries in cluster'\n"
+            ),
+            "{script}"
+        );
+        assert!(!script.contains("__ocd_nodes"), "{script}");
+
+        let script = render(Ocd::command(), CompletionShell::Fish, true);
+        assert!(script.contains("-n '__fish_ocd_using_subcommand init deploy "), "{script}");
+        assert!(script.contains("ocd completions --list-nodes 2>/dev/null | string replace"));
+    }
+
+    #[test]
+    fn smoke_render_powershell() {
+        let script = render(Ocd::command(), CompletionShell::Powershell, false);
+        assert!(script.contains("Register-ArgumentCompleter -Native -CommandName 'ocd'"));
+        assert!(script.contains("'ocd;deploy' {"), "{script}");
+        assert!(!script.contains("ocd completions --list-nodes"), "{script}");
+
+        let script = render(Ocd::command(), CompletionShell::Powershell, true);
+        assert!(script.contains("-or @('init', 'deploy', 'undeploy', "), "{script}");
+        assert!(script.contains("ocd completions --list-nodes 2>$null"), "{script}");
+        assert!(script.contains("    }\n\n    $completions.Where{"), "{script}");
+    }
+
+    #[sealed_test]
+    fn smoke_completion_index_list() -> Result<()> {
+        let pwd = std::env::current_dir()?;
+        std::env::set_var("HOME", &pwd);
+        create_dir_all(".config/ocd/nodes")?;
+        write(".config/ocd/root.toml", "[settings]\nwork_dir_alias = \"home_dir\"\n")?;
+        write(
+            ".config/ocd/nodes/vim.toml",
+            "[settings]\ndeployment = \"bare_alias\"\ndescription = \"Editor\"\n",
+        )?;
+
+        let path = pwd.join(".cache/ocd/completion.toml");
+        let mut index = CompletionIndex::load_from(&path);
+        pretty_assert_eq!(index.list()?, "root\nvim:Editor\n");
+
+        // INVARIANT: Index is used as it is as long as cluster definition stays the same.
+        let mut index = CompletionIndex::load_from(&path);
+        index.nodes.pop();
+        pretty_assert_eq!(index.list()?, "root\n");
+
+        write(".config/ocd/nodes/sh.toml", "[settings]\ndeployment = \"bare_alias\"\n")?;
+        pretty_assert_eq!(index.list()?, "root\nsh\nvim:Editor\n");
+
+        Ok(())
+    }
+}
@@ -0,0 +1,156 @@
+// SPDX-FileCopyrightText: 2025 Jason Pena <jasonpena@awkless.com>
+// SPDX-License-Identifier: MIT
+
+//! Container setup snippets.
+//!
+//! Renders snippets that install OCD inside a container, clone the cluster of the user, and deploy
+//! it into the home directory of the container user. Snippets come as a Dockerfile instruction, or
+//! as a fragment of "devcontainer.json" for dev containers and codespaces.
+//!
+//! Containers tend to run as root, and nobody is around to accept command hooks, so every call to
+//! OCD in a snippet allows running as root, never runs hooks, and prints plain output.
+
+use anyhow::Result;
+use clap::ValueEnum;
+use serde_json::json;
+use std::fmt::Write as _;
+
+/// Formats of container setup snippets.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum SnippetFormat {
+    /// Fragment of "devcontainer.json" that runs setup after container creation.
+    #[default]
+    Devcontainer,
+
+    /// Dockerfile instruction that runs setup while building image.
+    Dockerfile,
+}
+
+/// Commands that set up cluster inside container.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct ContainerSetup {
+    url: String,
+    targets: Vec<String>,
+    profile: Option<String>,
+}
+
+impl ContainerSetup {
+    /// Construct new container setup that clones cluster from URL.
+    ///
+    /// Every node is deployed if no targets are given.
+    pub(crate) fn new(
+        url: impl Into<String>,
+        targets: Vec<String>,
+        profile: Option<String>,
+    ) -> Self {
+        Self { url: url.into(), targets, profile }
+    }
+
+    /// List shell commands of container setup, in the order they must run.
+    pub(crate) fn commands(&self) -> Vec<String> {
+        const OCD: &str = "ocd --plain --allow-root --run-hook never";
+
+        let mut deploy = format!("{OCD} deploy");
+        if let Some(profile) = &self.profile {
+            let _ = write!(deploy, " --profile {}", shell_quote(profile));
+        }
+        if self.targets.is_empty() {
+            deploy.push_str(" --all");
+        }
+        for target in &self.targets {
+            let _ = write!(deploy, " {}", shell_quote(target));
+        }
+
+        vec![
+            "cargo install ocd --locked".into(),
+            format!("{OCD} clone {}", shell_quote(&self.url)),
+            deploy,
+        ]
+    }
+
+    /// Render container setup in given snippet format.
+    ///
+    /// # Errors
+    ///
+    /// - Will fail if "devcontainer.json" fragment cannot be serialized.
+    pub(crate) fn render(&self, format: SnippetFormat) -> Result<String> {
+        match format {
+            SnippetFormat::Dockerfile => Ok(self.render_dockerfile()),
+            SnippetFormat::Devcontainer => self.render_devcontainer(),
+        }
+    }
+
+    fn render_dockerfile(&self) -> String {
+        let mut snippet =
+            String::from("# Install OCD, then clone and deploy cluster into home directory.\n");
+        snippet.push_str("RUN ");
+        snippet.push_str(&self.commands().join(" \\\n    && "));
+        snippet.push('\n');
+        snippet
+    }
+
+    // INVARIANT: Rust feature is pulled in, because base images rarely ship with cargo.
+    fn render_devcontainer(&self) -> Result<String> {
+        let fragment = json!({
+            "features": { "ghcr.io/devcontainers/features/rust:1": {} },
+            "postCreateCommand": self.commands().join(" && "),
+        });
+
+        Ok(format!("{}\n", serde_json::to_string_pretty(&fragment)?))
+    }
+}
+
+/// Quote text for POSIX shells, leaving text that needs no quoting alone.
+fn shell_quote(text: &str) -> String {
+    let safe = |c: char| c.is_ascii_alphanumeric() || "@%+=:,./_-~".contains(c);
+    if !text.is_empty() && text.chars().all(safe) {
+        return text.to_string();
+    }
+
+    format!("'{}'", text.replace('\'', "'\\''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use pretty_assertions::assert_eq as pretty_assert_eq;
+    use simple_test_case::test_case;
+
+    #[test_case("vim", "vim"; "plain")]
+    #[test_case("https://host/dots.git", "https://host/dots.git"; "url")]
+    #[test_case("sh*", "'sh*'"; "glob")]
+    #[test_case("it's", "'it'\\''s'"; "single quote")]
+    #[test_case("", "''"; "empty")]
+    #[test]
+    fn smoke_shell_quote(input: &str, expect: &str) {
+        pretty_assert_eq!(shell_quote(input), expect);
+    }
+
+    #[test]
+    fn smoke_container_setup_render() -> Result<()> {
+        let setup = ContainerSetup::new("https://host/dots.git", vec![], None);
+        pretty_assert_eq!(
+            setup.render(SnippetFormat::Dockerfile)?,
+            "# Install OCD, then clone and deploy cluster into home directory.\n\
+             RUN cargo install ocd --locked \\\n    \
+             && ocd --plain --allow-root --run-hook never clone https://host/dots.git \\\n    \
+             && ocd --plain --allow-root --run-hook never deploy --all\n"
+        );
+
+        let setup = ContainerSetup::new(
+            "https://host/dots.git",
+            vec!["vim".into(), "sh*".into()],
+            Some("server".into()),
+        );
+        let snippet = setup.render(SnippetFormat::Devcontainer)?;
+        assert!(snippet.contains("\"ghcr.io/devcontainers/features/rust:1\": {}"));
+        assert!(snippet.contains(
+            "\"postCreateCommand\": \"cargo install ocd --locked && ocd --plain --allow-root \
+             --run-hook never clone https://host/dots.git && ocd --plain --allow-root --run-hook \
+             never deploy --profile server vim 'sh*'\""
+        ));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,353 @@
+// SPDX-FileCopyrightText: 2025 Jason Pena <jasonpena@awkless.com>
+// SPDX-License-Identifier: MIT
+
+//! Diagnoses of the whole setup.
+//!
+//! Problems with a setup tend to surface one at a time, each through whatever command trips over
+//! it first. A diagnosis checks everything at once instead: the cluster definition and hook
+//! configuration, the node configuration files, the repository store against the cluster
+//! definition, the sparse checkout files against the exclusion rules of their nodes, the working
+//! directory aliases, and the Git binary. Each finding either passes, warns about something that
+//! merely deserves attention, or fails over something that breaks other commands, along with a
+//! suggested fix.
+
+use super::i18n::tr;
+
+use crate::{
+    model::{
+        cluster::{Cluster, DeploymentKind},
+        config_dir, data_dir,
+        hook::HookRunner,
+    },
+    store::{has_git_binary, is_node_present, orphaned_repos, Node, SparseCheckoutState},
+};
+
+use serde::Serialize;
+use std::fmt::Write as _;
+
+/// How bad finding of diagnosis is.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Verdict {
+    /// Nothing to worry about.
+    Pass,
+
+    /// Something deserves attention, but does not break anything.
+    Warn,
+
+    /// Something breaks other commands.
+    Fail,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+struct Finding {
+    verdict: Verdict,
+    message: String,
+    fix: Option<String>,
+}
+
+/// Diagnosis of the whole setup of this machine.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub(crate) struct Diagnosis {
+    findings: Vec<Finding>,
+}
+
+impl Diagnosis {
+    /// Diagnose the setup of this machine.
+    ///
+    /// # Invariants
+    ///
+    /// - Never fails, because broken setups are exactly what diagnoses are about. Checks that
+    ///   need a valid cluster definition are skipped if it is invalid.
+    pub(crate) fn gather() -> Self {
+        let mut diagnosis = Self::default();
+        diagnosis.check_hooks();
+        diagnosis.check_node_files();
+        diagnosis.check_git_binary();
+
+        match Cluster::new() {
+            Ok(cluster) => {
+                diagnosis.pass(tr!("doctor-definition-valid", count = cluster.nodes.len()));
+                diagnosis.check_store(&cluster);
+                diagnosis.check_nodes(&cluster);
+            }
+            Err(error) => diagnosis.fail(
+                tr!("doctor-definition-invalid", error = format!("{error:#}")),
+                tr!("doctor-definition-fix"),
+            ),
+        }
+
+        diagnosis
+    }
+
+    /// Number of findings with given verdict.
+    pub(crate) fn count(&self, verdict: Verdict) -> usize {
+        self.findings.iter().filter(|finding| finding.verdict == verdict).count()
+    }
+
+    /// Render diagnosis as human-readable report, failures first.
+    pub(crate) fn render(&self) -> String {
+        let mut findings = self.findings.clone();
+        findings.sort_by_key(|finding| match finding.verdict {
+            Verdict::Fail => 0,
+            Verdict::Warn => 1,
+            Verdict::Pass => 2,
+        });
+
+        let mut text = String::new();
+        for finding in &findings {
+            let label = match finding.verdict {
+                Verdict::Pass => tr!("doctor-pass"),
+                Verdict::Warn => tr!("doctor-warn"),
+                Verdict::Fail => tr!("doctor-fail"),
+            };
+            let _ = writeln!(text, "{label:<4}  {}", finding.message);
+            if let Some(fix) = &finding.fix {
+                let _ = writeln!(text, "      {}", tr!("doctor-fix", fix = fix));
+            }
+        }
+        let _ = writeln!(
+            text,
+            "\n{}",
+            tr!(
+                "doctor-summary",
+                passed = self.count(Verdict::Pass),
+                warned = self.count(Verdict::Warn),
+                failed = self.count(Verdict::Fail)
+            )
+        );
+
+        text
+    }
+
+    /// Machine-readable diagnosis, in the order findings were made.
+    pub(crate) fn structured(&self) -> impl Serialize + '_ {
+        #[derive(Serialize)]
+        struct Report<'a> {
+            findings: &'a [Finding],
+            passed: usize,
+            warned: usize,
+            failed: usize,
+        }
+
+        Report {
+            findings: &self.findings,
+            passed: self.count(Verdict::Pass),
+            warned: self.count(Verdict::Warn),
+            failed: self.count(Verdict::Fail),
+        }
+    }
+
+    fn pass(&mut self, message: String) {
+        self.findings.push(Finding { verdict: Verdict::Pass, message, fix: None });
+    }
+
+    fn warn(&mut self, message: String, fix: String) {
+        self.findings.push(Finding { verdict: Verdict::Warn, message, fix: Some(fix) });
+    }
+
+    fn fail(&mut self, message: String, fix: String) {
+        self.findings.push(Finding { verdict: Verdict::Fail, message, fix: Some(fix) });
+    }
+
+    fn check_hooks(&mut self) {
+        match HookRunner::new() {
+            Ok(_) => self.pass(tr!("doctor-hooks-valid")),
+            Err(error) => self.fail(
+                tr!("doctor-hooks-invalid", error = format!("{error:#}")),
+                tr!("doctor-hooks-fix"),
+            ),
+        }
+    }
+
+    // INVARIANT: Only files ending in ".toml" define nodes, everything else is silently ignored by
+    // the cluster definition.
+    fn check_node_files(&mut self) {
+        let Ok(entries) = config_dir().and_then(|dir| Ok(std::fs::read_dir(dir.join("nodes"))?))
+        else {
+            self.pass(tr!("doctor-node-files-valid"));
+            return;
+        };
+
+        let mut ignored: Vec<_> = entries
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_none_or(|extension| extension != "toml"))
+            .collect();
+        ignored.sort();
+        if ignored.is_empty() {
+            self.pass(tr!("doctor-node-files-valid"));
+        }
+        for path in ignored {
+            self.warn(
+                tr!("doctor-node-file-ignored", path = format!("{path:?}")),
+                tr!("doctor-node-file-fix"),
+            );
+        }
+    }
+
+    fn check_git_binary(&mut self) {
+        if has_git_binary() {
+            self.pass(tr!("doctor-git-found"));
+        } else {
+            self.fail(tr!("doctor-git-missing"), tr!("doctor-git-fix"));
+        }
+    }
+
+    fn check_store(&mut self, cluster: &Cluster) {
+        let mut consistent = true;
+        if !data_dir().is_ok_and(|dir| dir.join("root").exists()) {
+            self.fail(tr!("doctor-root-missing"), tr!("doctor-root-fix"));
+            return;
+        }
+
+        for (name, node) in &cluster.nodes {
+            if !is_node_present(name, node).unwrap_or(false) {
+                consistent = false;
+                self.warn(
+                    tr!("doctor-node-missing", name = format!("{name:?}")),
+                    tr!("doctor-node-missing-fix", name = name),
+                );
+            }
+        }
+
+        match orphaned_repos(cluster) {
+            Ok(orphans) => {
+                for path in orphans {
+                    consistent = false;
+                    self.warn(
+                        tr!("doctor-orphan", path = format!("{path:?}")),
+                        tr!("doctor-orphan-fix"),
+                    );
+                }
+            }
+            Err(error) => {
+                consistent = false;
+                self.fail(
+                    tr!("doctor-store-failure", error = format!("{error:#}")),
+                    tr!("doctor-store-fix"),
+                );
+            }
+        }
+
+        if consistent {
+            self.pass(tr!("doctor-store-consistent"));
+        }
+    }
+
+    // INVARIANT: Nodes are only opened if present, such that diagnoses never clone anything, and
+    // nested clusters are never opened, because opening them deploys them.
+    fn check_nodes(&mut self, cluster: &Cluster) {
+        let (mut sparse, mut aliases) = (true, true);
+        for (name, entry) in &cluster.nodes {
+            let kind = &entry.settings.deployment.kind;
+            if !matches!(
+                kind,
+                DeploymentKind::BareAlias
+                    | DeploymentKind::HardLink
+                    | DeploymentKind::Symlink
+                    | DeploymentKind::Copy
+            ) {
+                continue;
+            }
+            let quoted = format!("{name:?}");
+
+            let alias = entry.settings.deployment.work_dir_alias.as_path();
+            if !alias.is_dir() {
+                aliases = false;
+                self.warn(
+                    tr!("doctor-alias-missing", path = format!("{alias:?}"), name = quoted),
+                    tr!("doctor-alias-fix", name = name),
+                );
+            }
+
+            if !is_node_present(name, entry).unwrap_or(false) {
+                continue;
+            }
+            let state = Node::new_open(name, entry).and_then(|node| {
+                Ok((node.sparse_checkout_state()?, node.is_sparse_checkout_current()?))
+            });
+            match state {
+                Ok((SparseCheckoutState::Drifted | SparseCheckoutState::Foreign, _)) => {
+                    sparse = false;
+                    self.warn(
+                        tr!("doctor-sparse-foreign", name = quoted),
+                        tr!("doctor-sparse-foreign-fix", name = name),
+                    );
+                }
+                Ok((_, false)) => {
+                    sparse = false;
+                    self.warn(
+                        tr!("doctor-sparse-stale", name = quoted),
+                        tr!("doctor-sparse-stale-fix", name = name),
+                    );
+                }
+                Ok(_) => {}
+                Err(error) => {
+                    sparse = false;
+                    self.fail(
+                        tr!("doctor-node-failure", name = quoted, error = format!("{error:#}")),
+                        tr!("doctor-node-failure-fix", name = name),
+                    );
+                }
+            }
+        }
+
+        if sparse {
+            self.pass(tr!("doctor-sparse-consistent"));
+        }
+        if aliases {
+            self.pass(tr!("doctor-aliases-exist"));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use anyhow::Result;
+    use sealed_test::prelude::*;
+    use std::fs::{create_dir_all, write};
+
+    #[sealed_test(env = [
+        ("XDG_CONFIG_HOME", ".config"),
+        ("XDG_DATA_HOME", ".local/share"),
+        ("XDG_STATE_HOME", ".local/state"),
+    ])]
+    fn smoke_diagnosis_render() -> Result<()> {
+        let pwd = std::env::current_dir()?;
+        std::env::set_var("HOME", &pwd);
+
+        // INVARIANT: Broken clusters still get diagnosed.
+        let diagnosis = Diagnosis::gather();
+        assert_eq!(diagnosis.count(Verdict::Fail), 1, "{}", diagnosis.render());
+
+        create_dir_all(".config/ocd/nodes")?;
+        create_dir_all(".local/share/ocd/root")?;
+        create_dir_all(".local/share/ocd/old/refs")?;
+        write(".local/share/ocd/old/HEAD", "ref: refs/heads/main\n")?;
+        write(".config/ocd/root.toml", "[settings]\nwork_dir_alias = \"home_dir\"\n")?;
+        write(
+            ".config/ocd/nodes/vim.toml",
+            "[settings]\ndeployment = \"bare_alias:~/nope\"\nurl = \"https://host/vim.git\"\n",
+        )?;
+        write(".config/ocd/nodes/sh.tml", "")?;
+
+        let diagnosis = Diagnosis::gather();
+        let report = diagnosis.render();
+        assert_eq!(diagnosis.count(Verdict::Fail), usize::from(!has_git_binary()), "{report}");
+        assert!(report.contains("pass  Cluster definition is valid"), "{report}");
+        assert!(report.contains("warn  \"vim\" is missing"), "{report}");
+        assert!(report.contains("fix: Run \"ocd deploy vim\" to clone it"), "{report}");
+        assert!(report.contains("ocd/old\" is in the repository store"), "{report}");
+        assert!(report.contains("sh.tml\" is ignored"), "{report}");
+        assert!(report.contains("/nope\" of \"vim\" does not exist"), "{report}");
+
+        let json = serde_json::to_string(&diagnosis.structured())?;
+        assert!(json.starts_with("{\"findings\":[{\"verdict\":"), "{json}");
+        assert!(json.contains("\"fix\":\"Run \\\"ocd deploy vim\\\" to clone it\""), "{json}");
+
+        Ok(())
+    }
+}
@@ -0,0 +1,242 @@
+// SPDX-FileCopyrightText: 2025 Jason Pena <jasonpena@awkless.com>
+// SPDX-License-Identifier: MIT
+
+//! Message catalog of user-facing text.
+//!
+//! Errors, prompts, and summaries of the command set are looked up by message ID in a catalog of
+//! the user's language, instead of being written inline. Catalogs are plain text files in the
+//! `locales` directory, using a small subset of the [Fluent][fluent] syntax:
+//!
+//! ```text
+//! # Comments start with a hash.
+//! deploy-cancelled = Deployment cancelled, nothing was overwritten
+//! node-not-defined = Node { $name } not defined
+//! ```
+//!
+//! Each message is one `id = text` line, where `{ $var }` placeables get replaced by arguments
+//! given at the call site. Indented lines continue the text of the message above them on a new
+//! line. Every catalog is compiled into the binary, thus translations are added by writing a new
+//! catalog, and registering it in [`CATALOGS`].
+//!
+//! The language is detected from `$LC_ALL`, `$LC_MESSAGES`, and `$LANG`, in that order, the same
+//! way gettext does it. Messages missing from the catalog of the user's language fall back to the
+//! English catalog, such that partial translations are fine.
+//!
+//! [fluent]: https://projectfluent.org/
+
+use std::{collections::HashMap, fmt::Display, sync::LazyLock};
+
+/// Catalog that every other catalog falls back to.
+const DEFAULT_LANGUAGE: &str = "en";
+
+/// Message catalogs of every supported language, keyed by language tag.
+const CATALOGS: &[(&str, &str)] = &[("en", include_str!("../../locales/en.ftl"))];
+
+static FALLBACK: LazyLock<Catalog> = LazyLock::new(|| Catalog::for_language(DEFAULT_LANGUAGE));
+static CATALOG: LazyLock<Catalog> = LazyLock::new(|| {
+    let language = detect_language(|key| std::env::var(key).ok());
+    Catalog::for_language(&language.unwrap_or(DEFAULT_LANGUAGE.into()))
+});
+
+/// Look up translated message, filling in its placeables with given arguments.
+///
+/// Prefer the [`tr!`] macro, which names arguments after their placeables.
+///
+/// # Invariants
+///
+/// - Messages missing from catalog of user's language fall back to English.
+/// - Messages missing from every catalog are shown as their message ID.
+pub(crate) fn translate(id: &str, args: &[(&str, &dyn Display)]) -> String {
+    let text = CATALOG.get(id).or_else(|| FALLBACK.get(id)).unwrap_or(id);
+    format_message(text, args)
+}
+
+/// Look up translated message by ID, naming arguments after placeables of message.
+///
+/// ```ignore
+/// warn!("{}", tr!("node-not-defined", name = format!("{target:?}")));
+/// ```
+macro_rules! tr {
+    ($id:literal) => {
+        $crate::cmd::i18n::translate($id, &[])
+    };
+    ($id:literal, $($name:ident = $value:expr),+ $(,)?) => {
+        $crate::cmd::i18n::translate(
+            $id,
+            &[$((stringify!($name), &$value as &dyn ::std::fmt::Display)),+],
+        )
+    };
+}
+pub(crate) use tr;
+
+#[derive(Debug, Default)]
+struct Catalog {
+    messages: HashMap<String, String>,
+}
+
+impl Catalog {
+    fn parse(source: &str) -> Self {
+        let mut messages = HashMap::new();
+        let mut current: Option<(String, String)> = None;
+        for line in source.lines() {
+            if line.trim_start().starts_with('#') || line.trim().is_empty() {
+                continue;
+            }
+
+            // Indented lines continue text of current message.
+            if line.starts_with([' ', '\t']) {
+                if let Some((_, text)) = current.as_mut() {
+                    text.push('\n');
+                    text.push_str(line.trim());
+                }
+                continue;
+            }
+
+            if let Some((id, text)) = line.split_once('=') {
+                if let Some((id, text)) = current.replace((id.trim().into(), text.trim().into())) {
+                    messages.insert(id, text);
+                }
+            }
+        }
+
+        if let Some((id, text)) = current {
+            messages.insert(id, text);
+        }
+
+        Self { messages }
+    }
+
+    fn for_language(language: &str) -> Self {
+        CATALOGS
+            .iter()
+            .find(|(tag, _)| *tag == language)
+            .map(|(_, source)| Self::parse(source))
+            .unwrap_or_default()
+    }
+
+    fn get(&self, id: &str) -> Option<&str> {
+        self.messages.get(id).map(String::as_str)
+    }
+}
+
+// Replace "{ $var }" placeables with their arguments, leaving unknown placeables as they are.
+fn format_message(text: &str, args: &[(&str, &dyn Display)]) -> String {
+    let mut message = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('{') {
+        let Some(end) = rest[start..].find('}').map(|end| start + end) else {
+            break;
+        };
+
+        message.push_str(&rest[..start]);
+        let placeable = &rest[start..=end];
+        let name = placeable[1..placeable.len() - 1].trim().trim_start_matches('$');
+        match args.iter().find(|(arg, _)| *arg == name) {
+            Some((_, value)) => message.push_str(&value.to_string()),
+            None => message.push_str(placeable),
+        }
+        rest = &rest[end + 1..];
+    }
+    message.push_str(rest);
+
+    message
+}
+
+// Pick supported language from locale environment variables, e.g., "pt_BR.UTF-8" can use "pt_BR"
+// or "pt".
+fn detect_language(var: impl Fn(&str) -> Option<String>) -> Option<String> {
+    let locale = ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .into_iter()
+        .find_map(|key| var(key).filter(|value| !value.is_empty()))?;
+    let locale = locale.split(['.', '@']).next().unwrap_or_default();
+    if locale == "C" || locale == "POSIX" {
+        return None;
+    }
+
+    let language = locale.split(['_', '-']).next().unwrap_or_default();
+    let supported = [locale, language]
+        .into_iter()
+        .find(|candidate| CATALOGS.iter().any(|(tag, _)| tag == candidate))
+        .map(String::from);
+
+    supported
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use pretty_assertions::assert_eq as pretty_assert_eq;
+    use simple_test_case::test_case;
+
+    // Sources whose message IDs must exist in English catalog.
+    const SOURCES: &[&str] = &[
+        include_str!("../cmd.rs"),
+        include_str!("privilege.rs"),
+        include_str!("why.rs"),
+        include_str!("../store/transaction.rs"),
+    ];
+
+    fn placeables(text: &str) -> Vec<&str> {
+        let mut names: Vec<&str> = text
+            .split('{')
+            .skip(1)
+            .filter_map(|part| part.split_once('}'))
+            .map(|(name, _)| name.trim().trim_start_matches('$'))
+            .collect();
+        names.sort_unstable();
+        names
+    }
+
+    #[test]
+    fn smoke_catalog_parse() {
+        let catalog = Catalog::parse("# comment\n\nfoo = Foo { $bar }\nbaz = Baz\n  qux\n");
+        pretty_assert_eq!(catalog.get("foo"), Some("Foo { $bar }"));
+        pretty_assert_eq!(catalog.get("baz"), Some("Baz\nqux"));
+        pretty_assert_eq!(catalog.get("comment"), None);
+    }
+
+    #[test]
+    fn smoke_format_message() {
+        let name = "\"vim\"";
+        pretty_assert_eq!(tr!("node-not-defined", name = name), "Node \"vim\" not defined");
+        pretty_assert_eq!(format_message("{ $a } { $b }", &[("a", &1)]), "1 { $b }");
+        pretty_assert_eq!(translate("no-such-message", &[]), "no-such-message");
+    }
+
+    #[test_case(&[("LANG", "en_US.UTF-8")], Some("en"); "lang")]
+    #[test_case(&[("LC_ALL", "en_GB"), ("LANG", "xx_XX")], Some("en"); "lc_all wins")]
+    #[test_case(&[("LC_MESSAGES", "xx_XX.UTF-8@euro")], None; "unsupported")]
+    #[test_case(&[("LANG", "C.UTF-8")], None; "c locale")]
+    #[test_case(&[], None; "unset")]
+    #[test]
+    fn smoke_detect_language(vars: &[(&str, &str)], expect: Option<&str>) {
+        let var = |key: &str| {
+            vars.iter().find(|(name, _)| *name == key).map(|(_, value)| value.to_string())
+        };
+        pretty_assert_eq!(detect_language(var).as_deref(), expect);
+    }
+
+    #[test]
+    fn catalogs_match_english() {
+        let english = Catalog::for_language(DEFAULT_LANGUAGE);
+        for (tag, source) in CATALOGS {
+            for (id, text) in &Catalog::parse(source).messages {
+                let default = english.get(id);
+                assert!(default.is_some(), "{tag}: {id:?} not in English catalog");
+                pretty_assert_eq!(placeables(text), placeables(default.unwrap()), "{tag}: {id}");
+            }
+        }
+    }
+
+    #[test]
+    fn catalog_covers_command_layer() {
+        let english = Catalog::for_language(DEFAULT_LANGUAGE);
+        for source in SOURCES {
+            for part in source.split("tr!(\"").skip(1) {
+                let id = part.split('"').next().unwrap_or_default();
+                assert!(english.get(id).is_some(), "{id:?} not in English catalog");
+            }
+        }
+    }
+}
@@ -0,0 +1,89 @@
+// SPDX-FileCopyrightText: 2025 Jason Pena <jasonpena@awkless.com>
+// SPDX-License-Identifier: MIT
+
+//! Man page generation.
+//!
+//! Renders section-1 man pages in roff straight from the clap definition of the OCD command set
+//! through [`clap_mangen`], such that generated documentation always tracks the live CLI. One page
+//! is rendered for the main command, and one page for each of its subcommands.
+
+use anyhow::{Context, Result};
+use clap_mangen::Man;
+use std::{
+    fs::{create_dir_all, write},
+    path::{Path, PathBuf},
+};
+use tracing::{info, instrument};
+
+/// Write man pages of command and all of its subcommands into target directory.
+///
+/// The main command is written as `<name>.1`, and each subcommand as `<name>-<subcommand>.1`.
+/// Target directory is created if it does not exist already. Returns paths of written man pages.
+///
+/// # Invariants
+///
+/// - Hidden subcommands and the help subcommand are never documented.
+///
+/// # Errors
+///
+/// - Will fail if target directory cannot be created.
+/// - Will fail if any man page cannot be rendered or written.
+#[instrument(skip(cmd), level = "debug")]
+pub(crate) fn write_man_pages(cmd: clap::Command, dir: &Path) -> Result<Vec<PathBuf>> {
+    create_dir_all(dir).with_context(|| format!("Failed to create {dir:?}"))?;
+
+    let mut cmd = cmd.disable_help_subcommand(true);
+    cmd.build();
+
+    // INVARIANT: Subcommands do not carry the version of their parent.
+    let source = format!("{} {}", cmd.get_name(), cmd.get_version().unwrap_or_default());
+    let mut pages = vec![cmd.clone()];
+    pages.extend(cmd.get_subcommands().filter(|subcmd| !subcmd.is_hide_set()).cloned());
+
+    let mut written = Vec::new();
+    for page in pages {
+        let man = Man::new(page).source(source.trim_end());
+        let path = dir.join(man.get_filename());
+        let mut roff = Vec::new();
+        man.render(&mut roff).with_context(|| format!("Failed to render {path:?}"))?;
+        write(&path, roff).with_context(|| format!("Failed to write {path:?}"))?;
+        info!("Wrote man page {path:?}");
+        written.push(path);
+    }
+
+    Ok(written)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::cmd::Ocd;
+
+    use clap::CommandFactory;
+    use sealed_test::prelude::*;
+    use std::fs::read_to_string;
+
+    #[sealed_test]
+    fn smoke_write_man_pages() -> Result<()> {
+        let dir = std::env::current_dir()?.join("man");
+        let written = write_man_pages(Ocd::command(), &dir)?;
+
+        let expect: Vec<PathBuf> = ["ocd", "ocd-clone", "ocd-deploy", "ocd-man"]
+            .iter()
+            .map(|name| dir.join(format!("{name}.1")))
+            .collect();
+        for path in &expect {
+            assert!(written.contains(path), "{path:?} not written");
+            assert!(path.exists());
+        }
+        assert!(!dir.join("ocd-help.1").exists());
+
+        let page = read_to_string(dir.join("ocd-deploy.1"))?;
+        assert!(page.contains(".TH ocd-deploy 1"));
+        assert!(page.contains("ocd\\-deploy \\- Deploy target entries in cluster"));
+        assert!(page.contains("# Deploy vim and all of its dependencies."));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,223 @@
+// SPDX-FileCopyrightText: 2025 Jason Pena <jasonpena@awkless.com>
+// SPDX-License-Identifier: MIT
+
+//! Guard rails for running OCD as root.
+//!
+//! Running OCD as root deploys files into whatever `$HOME` happens to be, which rarely is the home
+//! directory the user meant, and leaves every file it writes owned by root. Thus, OCD refuses to
+//! run with an effective user ID of zero unless explicitly allowed to.
+//!
+//! When allowed to run as root through `sudo`, the home directory of the invoking user is looked
+//! up in the user database, and every standard directory of OCD is resolved inside of it rather
+//! than inside whatever `$HOME` sudo left behind. Privileges are then dropped to the user and group
+//! IDs of the invoking user, such that their cluster is operated on as them, and every file written
+//! stays owned by them. The process environment is never modified along the way.
+
+use super::i18n::tr;
+use crate::model::{config_dir, set_home_dir};
+
+use anyhow::{anyhow, Result};
+use std::path::{Path, PathBuf};
+use tracing::{instrument, warn};
+
+/// User that invoked OCD through sudo.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct SudoUser {
+    name: String,
+    uid: u32,
+    gid: u32,
+}
+
+impl SudoUser {
+    // INVARIANT: Root invoking sudo is no different from root itself.
+    fn from_env(var: impl Fn(&str) -> Option<String>) -> Option<Self> {
+        let name = var("SUDO_USER").filter(|user| !user.is_empty() && user != "root")?;
+        let uid = var("SUDO_UID")?.parse().ok().filter(|uid| *uid != 0)?;
+        let gid = var("SUDO_GID")?.parse().ok()?;
+
+        Some(Self { name, uid, gid })
+    }
+}
+
+/// Guard against accidentally running OCD as root.
+///
+/// # Invariants
+///
+/// - Standard directories of OCD are resolved inside home directory of user that invoked sudo, if
+///   any.
+/// - Privileges are dropped to user that invoked sudo, if any.
+///
+/// # Errors
+///
+/// - Will fail if effective user is root, but running as root was not allowed.
+/// - Will fail if home directory of user that invoked sudo cannot be determined.
+/// - Will fail if privileges cannot be dropped to user that invoked sudo.
+#[instrument(level = "debug")]
+pub(crate) fn guard_root(allow_root: bool) -> Result<()> {
+    if !is_root() {
+        return Ok(());
+    }
+
+    let user = SudoUser::from_env(|key| std::env::var(key).ok());
+    let home = resolve_root_home(allow_root, user.as_ref(), user_home)?;
+    if let (Some(user), Some(home)) = (user, home) {
+        set_home_dir(&home);
+        use_git_home(&home)?;
+        drop_privileges(&user)?;
+    }
+
+    Ok(())
+}
+
+// Determine home directory to use when running as root, if it differs from the current one.
+fn resolve_root_home(
+    allow_root: bool,
+    sudo_user: Option<&SudoUser>,
+    lookup: impl FnOnce(&str) -> Result<PathBuf>,
+) -> Result<Option<PathBuf>> {
+    if !allow_root {
+        return Err(anyhow!(tr!("root-refused")));
+    }
+
+    let Some(user) = sudo_user else {
+        warn!("{}", tr!("root-warning"));
+        return Ok(None);
+    };
+
+    let home = lookup(&user.name)?;
+    warn!(
+        "{}",
+        tr!("root-sudo-home", user = format!("{:?}", user.name), home = format!("{home:?}"))
+    );
+
+    Ok(Some(home))
+}
+
+// Point libgit2 at Git configuration inside given home directory, instead of through `$HOME`.
+fn use_git_home(home: &Path) -> Result<()> {
+    let xdg = config_dir()?.with_file_name("git");
+    // SAFETY: No Git operation ran yet, thus global state of libgit2 is not accessed concurrently.
+    unsafe {
+        git2::opts::set_search_path(git2::ConfigLevel::Global, home)?;
+        git2::opts::set_search_path(git2::ConfigLevel::XDG, &xdg)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(unix)]
+fn is_root() -> bool {
+    // SAFETY: geteuid(2) cannot fail, and has no side effects.
+    unsafe { libc::geteuid() == 0 }
+}
+
+#[cfg(not(unix))]
+fn is_root() -> bool {
+    false
+}
+
+#[cfg(unix)]
+fn drop_privileges(user: &SudoUser) -> Result<()> {
+    use anyhow::Context;
+    use std::{ffi::CString, io};
+
+    let name = CString::new(user.name.as_str())?;
+    let failure = || tr!("root-drop-failure", user = format!("{:?}", user.name));
+
+    // INVARIANT: Groups are dropped before user ID, because only root may change them.
+    // SAFETY: Name is nul-terminated string that outlives the call.
+    if unsafe { libc::initgroups(name.as_ptr(), user.gid as _) } != 0 {
+        return Err(io::Error::last_os_error()).with_context(failure);
+    }
+    // SAFETY: setgid(2) and setuid(2) only take plain IDs, and apply to every thread.
+    if unsafe { libc::setgid(user.gid) } != 0 || unsafe { libc::setuid(user.uid) } != 0 {
+        return Err(io::Error::last_os_error()).with_context(failure);
+    }
+
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn drop_privileges(_: &SudoUser) -> Result<()> {
+    Ok(())
+}
+
+#[cfg(unix)]
+fn user_home(user: &str) -> Result<PathBuf> {
+    use std::{
+        ffi::{CStr, CString, OsStr},
+        os::unix::ffi::OsStrExt,
+    };
+
+    let name = CString::new(user)?;
+    let mut buffer = vec![0; 16 * 1024];
+    // SAFETY: passwd is plain old data, which getpwnam_r(3) fills in.
+    let mut entry: libc::passwd = unsafe { std::mem::zeroed() };
+    let mut result = std::ptr::null_mut();
+
+    // SAFETY: All pointers are valid for the duration of the call, and buffer length is exact.
+    let code = unsafe {
+        libc::getpwnam_r(name.as_ptr(), &mut entry, buffer.as_mut_ptr(), buffer.len(), &mut result)
+    };
+    if code != 0 || result.is_null() || entry.pw_dir.is_null() {
+        return Err(anyhow!("Cannot determine home directory of user {user:?}"));
+    }
+
+    // SAFETY: getpwnam_r(3) succeeded, so pw_dir points to nul-terminated string inside buffer.
+    let home = unsafe { CStr::from_ptr(entry.pw_dir) };
+
+    Ok(PathBuf::from(OsStr::from_bytes(home.to_bytes())))
+}
+
+#[cfg(not(unix))]
+fn user_home(user: &str) -> Result<PathBuf> {
+    Err(anyhow!("Cannot determine home directory of user {user:?}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use pretty_assertions::assert_eq as pretty_assert_eq;
+
+    #[test]
+    fn smoke_sudo_user_from_env() {
+        let env = |vars: &'static [(&str, &str)]| {
+            move |key: &str| {
+                vars.iter().find(|(name, _)| *name == key).map(|(_, value)| value.to_string())
+            }
+        };
+        let user = SudoUser { name: "awkless".into(), uid: 1000, gid: 100 };
+        let vars = &[("SUDO_USER", "awkless"), ("SUDO_UID", "1000"), ("SUDO_GID", "100")];
+        pretty_assert_eq!(SudoUser::from_env(env(vars)), Some(user));
+        let vars = &[("SUDO_USER", "root"), ("SUDO_UID", "0"), ("SUDO_GID", "0")];
+        pretty_assert_eq!(SudoUser::from_env(env(vars)), None);
+        let vars = &[("SUDO_USER", "awkless"), ("SUDO_UID", "0"), ("SUDO_GID", "0")];
+        pretty_assert_eq!(SudoUser::from_env(env(vars)), None);
+        pretty_assert_eq!(SudoUser::from_env(env(&[("SUDO_USER", "awkless")])), None);
+        pretty_assert_eq!(SudoUser::from_env(env(&[])), None);
+    }
+
+    #[test]
+    fn smoke_resolve_root_home() -> Result<()> {
+        let lookup = |user: &str| Ok(PathBuf::from(format!("/home/{user}")));
+        let user = SudoUser { name: "awkless".into(), uid: 1000, gid: 100 };
+        assert!(resolve_root_home(false, None, lookup).is_err());
+        assert!(resolve_root_home(false, Some(&user), lookup).is_err());
+        pretty_assert_eq!(resolve_root_home(true, None, lookup)?, None);
+        pretty_assert_eq!(
+            resolve_root_home(true, Some(&user), lookup)?,
+            Some(PathBuf::from("/home/awkless"))
+        );
+
+        Ok(())
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn smoke_user_home() -> Result<()> {
+        pretty_assert_eq!(user_home("root")?, PathBuf::from("/root"));
+        assert!(user_home("no-such-user-for-ocd").is_err());
+        Ok(())
+    }
+}
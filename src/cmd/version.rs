@@ -0,0 +1,126 @@
+// SPDX-FileCopyrightText: 2025 Jason Pena <jasonpena@awkless.com>
+// SPDX-License-Identifier: MIT
+
+//! Build information for version output.
+//!
+//! Bug reports need more than the version number of OCD to be reproduced. Build information adds
+//! the commit OCD was built from, its build date, enabled cargo features, the libgit2 version it
+//! was built against, and the version of the Git binary it finds at runtime. The JSON format is
+//! stable, such that scripts can rely on its keys.
+
+use crate::store::{git_binary_version, libgit2_version};
+
+use anyhow::Result;
+use clap::ValueEnum;
+use serde::Serialize;
+use std::fmt::Write as _;
+
+/// Formats of version output.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum VersionFormat {
+    /// Human-readable lines.
+    #[default]
+    Text,
+
+    /// Single JSON object.
+    Json,
+}
+
+/// Information about the build of OCD and its environment.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub(crate) struct BuildInfo {
+    version: String,
+    commit: Option<String>,
+    build_date: Option<String>,
+    features: Vec<String>,
+    libgit2: String,
+    git: Option<String>,
+}
+
+impl BuildInfo {
+    /// Gather build information of running binary.
+    pub(crate) fn gather() -> Self {
+        let features = [
+            ("pager", cfg!(feature = "pager")),
+            ("https", cfg!(feature = "https")),
+            ("ssh", cfg!(feature = "ssh")),
+            ("vendored-libgit2", cfg!(feature = "vendored-libgit2")),
+        ];
+
+        Self {
+            version: env!("CARGO_PKG_VERSION").into(),
+            commit: option_env!("OCD_GIT_COMMIT").map(Into::into),
+            build_date: option_env!("OCD_BUILD_DATE").map(Into::into),
+            features: features
+                .into_iter()
+                .filter(|(_, enabled)| *enabled)
+                .map(|(name, _)| name.to_string())
+                .collect(),
+            libgit2: libgit2_version(),
+            git: git_binary_version(),
+        }
+    }
+
+    /// Render build information in given format.
+    ///
+    /// Text format only shows the version number unless `verbose` is set. JSON format always
+    /// shows everything.
+    ///
+    /// # Errors
+    ///
+    /// - Will fail if build information cannot be serialized into JSON.
+    pub(crate) fn render(&self, format: VersionFormat, verbose: bool) -> Result<String> {
+        match format {
+            VersionFormat::Text if verbose => Ok(self.render_verbose_text()),
+            VersionFormat::Text => Ok(format!("ocd {}\n", self.version)),
+            VersionFormat::Json => Ok(format!("{}\n", serde_json::to_string(self)?)),
+        }
+    }
+
+    fn render_verbose_text(&self) -> String {
+        let unknown = || "unknown".to_string();
+        let mut text = format!("ocd {}\n", self.version);
+        let _ = writeln!(text, "commit: {}", self.commit.clone().unwrap_or_else(unknown));
+        let _ = writeln!(text, "build date: {}", self.build_date.clone().unwrap_or_else(unknown));
+        let _ = writeln!(text, "features: {}", self.features.join(", "));
+        let _ = writeln!(text, "libgit2: {}", self.libgit2);
+        let _ = writeln!(text, "git: {}", self.git.clone().unwrap_or_else(|| "not found".into()));
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use pretty_assertions::assert_eq as pretty_assert_eq;
+
+    fn build_info() -> BuildInfo {
+        BuildInfo {
+            version: "1.0.0".into(),
+            commit: Some("0123456789ab".into()),
+            build_date: Some("2025-06-02".into()),
+            features: vec!["pager".into(), "ssh".into()],
+            libgit2: "1.9.0".into(),
+            git: None,
+        }
+    }
+
+    #[test]
+    fn smoke_build_info_render() -> Result<()> {
+        let info = build_info();
+        pretty_assert_eq!(info.render(VersionFormat::Text, false)?, "ocd 1.0.0\n");
+        pretty_assert_eq!(
+            info.render(VersionFormat::Text, true)?,
+            "ocd 1.0.0\ncommit: 0123456789ab\nbuild date: 2025-06-02\nfeatures: pager, ssh\n\
+             libgit2: 1.9.0\ngit: not found\n"
+        );
+        pretty_assert_eq!(
+            info.render(VersionFormat::Json, false)?,
+            "{\"version\":\"1.0.0\",\"commit\":\"0123456789ab\",\"build_date\":\"2025-06-02\",\
+             \"features\":[\"pager\",\"ssh\"],\"libgit2\":\"1.9.0\",\"git\":null}\n"
+        );
+
+        Ok(())
+    }
+}
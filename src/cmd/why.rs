@@ -0,0 +1,225 @@
+// SPDX-FileCopyrightText: 2025 Jason Pena <jasonpena@awkless.com>
+// SPDX-License-Identifier: MIT
+
+//! Explanations of why nodes get deployed.
+//!
+//! Large clusters make it hard to tell why deploying some targets touches a given node. An
+//! explanation traces the node back to the target patterns that match it directly, and to the
+//! chains of dependencies that pull it in through other targets. It also lists the constraints
+//! that shape what deploying the node actually does, i.e., its deployment kind, its inclusion and
+//! exclusion rules, and the sparse profile selected for deployment.
+
+use super::i18n::tr;
+use crate::model::cluster::{Cluster, DeploymentKind, NodeEntry};
+
+use anyhow::{anyhow, Result};
+use std::fmt::Write as _;
+
+/// Explanation of why node would be deployed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct Explanation {
+    name: String,
+    entry: NodeEntry,
+    targeted: bool,
+    matched_by: Vec<String>,
+    pulled_in_by: Vec<Vec<String>>,
+    profile: Option<String>,
+}
+
+impl Explanation {
+    /// Explain why node would be deployed by given target patterns.
+    ///
+    /// Without any patterns, every other node that pulls in the node as a dependency is traced
+    /// instead, i.e., everything that deploys the node when targeted.
+    ///
+    /// # Errors
+    ///
+    /// - Will fail if node is not defined in cluster.
+    /// - Will fail if target patterns cannot be matched.
+    pub(crate) fn new(
+        cluster: &Cluster,
+        name: &str,
+        patterns: &[String],
+        profile: Option<String>,
+    ) -> Result<Self> {
+        let entry = cluster
+            .nodes
+            .get(name)
+            .ok_or_else(|| anyhow!(tr!("node-not-defined", name = format!("{name:?}"))))?
+            .clone();
+
+        // INVARIANT: Patterns that miss the node are fine, so only invalid ones get reported.
+        let matched_by = patterns
+            .iter()
+            .filter(|pattern| glob::Pattern::new(pattern).is_ok_and(|glob| glob.matches(name)))
+            .cloned();
+        let targets = if patterns.is_empty() {
+            cluster.nodes.keys().cloned().collect()
+        } else {
+            cluster.match_targets(patterns.to_vec())?
+        };
+        let mut targets: Vec<String> =
+            targets.into_iter().filter(|target| target != name && target != "root").collect();
+        targets.sort();
+        targets.dedup();
+        let pulled_in_by =
+            targets.iter().filter_map(|target| cluster.dependency_path(target, name)).collect();
+
+        Ok(Self {
+            name: name.into(),
+            entry,
+            targeted: !patterns.is_empty(),
+            matched_by: matched_by.collect(),
+            pulled_in_by,
+            profile,
+        })
+    }
+
+    /// Check if node would be deployed at all by given target patterns.
+    pub(crate) fn is_deployed(&self) -> bool {
+        !self.targeted || !self.matched_by.is_empty() || !self.pulled_in_by.is_empty()
+    }
+
+    /// Render explanation as human-readable lines.
+    pub(crate) fn render(&self) -> String {
+        let name = format!("{:?}", self.name);
+        let mut text = if !self.targeted {
+            tr!("why-untargeted", name = name)
+        } else if self.is_deployed() {
+            tr!("why-deployed", name = name)
+        } else {
+            tr!("why-not-deployed", name = name)
+        };
+        text.push('\n');
+
+        for pattern in &self.matched_by {
+            let _ = writeln!(text, "  {}", tr!("why-matched", pattern = format!("{pattern:?}")));
+        }
+        for chain in &self.pulled_in_by {
+            let _ = writeln!(
+                text,
+                "  {}",
+                tr!(
+                    "why-dependency",
+                    target = format!("{:?}", chain[0]),
+                    chain = chain.join(" -> ")
+                )
+            );
+        }
+        if !self.targeted && self.pulled_in_by.is_empty() {
+            let _ = writeln!(text, "  {}", tr!("why-no-dependents"));
+        }
+        if !self.pulled_in_by.is_empty() {
+            let _ = writeln!(text, "  {}", tr!("why-only"));
+        }
+
+        let _ = writeln!(text, "{}", tr!("why-constraints"));
+        if self.entry.settings.deployment.kind == DeploymentKind::Normal {
+            let _ = writeln!(text, "  {}", tr!("why-kind-normal"));
+        } else {
+            let kind = &self.entry.settings.deployment.kind;
+            let alias = self.entry.settings.deployment.work_dir_alias.to_os_string();
+            let _ = writeln!(
+                text,
+                "  {}",
+                tr!("why-kind", kind = kind, path = format!("{:?}", alias.to_string_lossy()))
+            );
+        }
+
+        let profiled =
+            self.profile.as_deref().and_then(|profile| self.entry.with_sparse_profile(profile));
+        if let Some(profile) = &self.profile {
+            let profile = format!("{profile:?}");
+            let line = match &profiled {
+                Some(_) => tr!("why-profile", profile = profile),
+                None => tr!("why-profile-undefined", profile = profile),
+            };
+            let _ = writeln!(text, "  {line}");
+        }
+        if let Some(rules) = self.entry.settings.included.as_ref().filter(|rules| !rules.is_empty())
+        {
+            let _ = writeln!(text, "  {}", tr!("why-included", rules = rules.join(", ")));
+        }
+        let excluded = profiled.as_ref().unwrap_or(&self.entry).settings.excluded.clone();
+        match excluded.unwrap_or_default() {
+            rules if rules.is_empty() => {
+                let _ = writeln!(text, "  {}", tr!("why-no-excluded"));
+            }
+            rules => {
+                let _ = writeln!(text, "  {}", tr!("why-excluded", rules = rules.join(", ")));
+            }
+        }
+
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::model::cluster::{RootEntry, WorkDirAlias};
+
+    use pretty_assertions::assert_eq as pretty_assert_eq;
+    use sealed_test::prelude::*;
+    use std::collections::BTreeMap;
+
+    fn cluster() -> Result<Cluster> {
+        let node = |deps: &[&str]| -> Result<NodeEntry> {
+            Ok(NodeEntry::builder()?
+                .deployment(DeploymentKind::BareAlias, WorkDirAlias::new("/home/awkless"))
+                .dependencies(deps.iter().copied())
+                .excluded(["README*"])
+                .sparse_profile("minimal", ["README*", "plugins/"])
+                .build())
+        };
+
+        Ok(Cluster {
+            root: RootEntry::builder()?.build(),
+            nodes: BTreeMap::from([
+                ("bash".into(), node(&["sh"])?),
+                ("sh".into(), node(&["readline"])?),
+                ("readline".into(), node(&[])?),
+                ("vim".into(), NodeEntry::builder()?.included(["vimrc", "colors/"]).build()),
+            ]),
+        })
+    }
+
+    #[sealed_test(env = [("HOME", "/home/awkless")])]
+    fn smoke_explanation_new() -> Result<()> {
+        let cluster = cluster()?;
+
+        let why = Explanation::new(&cluster, "readline", &[], None)?;
+        assert!(why.is_deployed());
+        pretty_assert_eq!(
+            why.pulled_in_by,
+            vec![vec!["bash", "sh", "readline"], vec!["sh", "readline"]]
+        );
+
+        let patterns = vec!["b*".to_string(), "read*".to_string(), "root".to_string()];
+        let why = Explanation::new(&cluster, "readline", &patterns, Some("minimal".into()))?;
+        assert!(why.is_deployed());
+        pretty_assert_eq!(why.matched_by, vec!["read*"]);
+        pretty_assert_eq!(why.pulled_in_by, vec![vec!["bash", "sh", "readline"]]);
+        let text = why.render();
+        assert!(text.contains("matched by target pattern \"read*\""), "{text}");
+        assert!(text.contains("bash -> sh -> readline"), "{text}");
+        assert!(text.contains("excluding files matching README*, plugins/"), "{text}");
+
+        let why = Explanation::new(&cluster, "readline", &["vim".into()], None)?;
+        assert!(!why.is_deployed());
+        let text = why.render();
+        assert!(text.contains("would not be deployed"), "{text}");
+        assert!(text.contains("excluding files matching README*\n"), "{text}");
+
+        let text = Explanation::new(&cluster, "vim", &[], Some("minimal".into()))?.render();
+        assert!(text.contains("no node depends on it"), "{text}");
+        assert!(text.contains("normal nodes have no working directory alias"), "{text}");
+        assert!(text.contains("does not define sparse profile \"minimal\""), "{text}");
+        assert!(text.contains("only including files matching vimrc, colors/"), "{text}");
+
+        assert!(Explanation::new(&cluster, "zsh", &[], None).is_err());
+
+        Ok(())
+    }
+}
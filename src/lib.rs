@@ -50,4 +50,8 @@
 
 pub mod cmd;
 pub mod model;
+pub mod notify;
+pub mod output;
+pub mod stats;
 pub mod store;
+pub mod timings;
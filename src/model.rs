@@ -8,18 +8,51 @@
 
 pub mod cluster;
 pub mod hook;
+pub mod schema;
+pub mod secret;
+pub mod url;
 
 use anyhow::{anyhow, Result};
-use std::path::PathBuf;
+use std::{
+    fs::{remove_file, OpenOptions},
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
 use tracing::{instrument, warn};
 
+static HOME_DIR: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+/// Use given home directory in place of `$HOME` for the rest of the invocation.
+///
+/// Every standard directory of OCD that lives inside the home directory of the current user is
+/// resolved inside given home directory instead. The process environment is left alone, see
+/// [`home_env`] for passing given home directory on to child processes.
+///
+/// # Panics
+///
+/// - Will panic if mutex guard fails to lock.
+pub fn set_home_dir(home: impl Into<PathBuf>) {
+    *HOME_DIR.lock().unwrap() = Some(home.into());
+}
+
+/// Get `$HOME` environment variable that child processes need to use home directory picked
+/// through [`set_home_dir`], if any.
+///
+/// # Panics
+///
+/// - Will panic if mutex guard fails to lock.
+pub fn home_env() -> Option<(&'static str, PathBuf)> {
+    HOME_DIR.lock().unwrap().clone().map(|home| ("HOME", home))
+}
+
 /// Get absolute path to user's home directory.
 ///
 /// # Errors
 ///
 /// - Will fail if user's home directory cannot be determined.
 pub fn home_dir() -> Result<PathBuf> {
-    dirs::home_dir().ok_or(anyhow!("Cannot determine path to home directory"))
+    let home = HOME_DIR.lock().unwrap().clone();
+    home.or_else(dirs::home_dir).ok_or(anyhow!("Cannot determine path to home directory"))
 }
 
 /// Get absolute path to OCD's standard configuration directory.
@@ -32,7 +65,7 @@ pub fn home_dir() -> Result<PathBuf> {
 ///
 /// - Will fail if user's home directory cannot be determined.
 pub fn config_dir() -> Result<PathBuf> {
-    dirs::config_dir()
+    in_home(dirs::config_dir())
         .map(|path| path.join("ocd"))
         .ok_or(anyhow!("Cannot determine path to configuration directory"))
 }
@@ -47,11 +80,145 @@ pub fn config_dir() -> Result<PathBuf> {
 ///
 /// - Will fail if user's home directory cannot be determined.
 pub fn data_dir() -> Result<PathBuf> {
-    dirs::data_dir()
+    in_home(dirs::data_dir())
         .map(|path| path.join("ocd"))
         .ok_or(anyhow!("Cannot determine path to data directory"))
 }
 
+/// Get absolute path to OCD's state directory.
+///
+/// Houses transient artifacts that should persist between invocations, but are not worth backing
+/// up, e.g., lock files, logs, and journals. Follows `$XDG_STATE_HOME`, falling back to the local
+/// data directory of the platform when it has no notion of a state directory.
+///
+/// # Invariants
+///
+/// - OCD's standard state directory is always relative to user's home directory.
+///
+/// # Errors
+///
+/// - Will fail if user's home directory cannot be determined.
+pub fn state_dir() -> Result<PathBuf> {
+    in_home(dirs::state_dir().or_else(dirs::data_local_dir))
+        .map(|path| path.join("ocd"))
+        .ok_or(anyhow!("Cannot determine path to state directory"))
+}
+
+/// Get absolute path to OCD's cache directory.
+///
+/// Houses artifacts that can always be regenerated, and thus can be removed at any time. Follows
+/// `$XDG_CACHE_HOME`.
+///
+/// # Invariants
+///
+/// - OCD's standard cache directory is always relative to user's home directory.
+///
+/// # Errors
+///
+/// - Will fail if user's home directory cannot be determined.
+pub fn cache_dir() -> Result<PathBuf> {
+    in_home(dirs::cache_dir())
+        .map(|path| path.join("ocd"))
+        .ok_or(anyhow!("Cannot determine path to cache directory"))
+}
+
+// INVARIANT: Directories outside of the current home directory are used as they are.
+// Move standard directory into home directory picked through `set_home_dir`, if any.
+fn in_home(dir: Option<PathBuf>) -> Option<PathBuf> {
+    let dir = dir?;
+    let Some(home) = HOME_DIR.lock().unwrap().clone() else {
+        return Some(dir);
+    };
+
+    Some(rebase(dir, dirs::home_dir().as_deref(), &home))
+}
+
+fn rebase(dir: PathBuf, from: Option<&Path>, to: &Path) -> PathBuf {
+    match from.and_then(|from| dir.strip_prefix(from).ok()) {
+        Some(rest) if rest.as_os_str().is_empty() => to.to_path_buf(),
+        Some(rest) => to.join(rest),
+        None => dir,
+    }
+}
+
+/// Expand leading tilde and environment variables of given path.
+///
+/// # Invariants
+///
+/// - Tilde and `$HOME` expand to [`home_dir`], such that home directory picked through
+///   [`set_home_dir`] is honored.
+///
+/// # Errors
+///
+/// - Will fail if path references undefined environment variable.
+pub fn expand_path(path: &str) -> Result<String> {
+    let home = home_dir().ok().map(|home| home.to_string_lossy().into_owned());
+    let context = |var: &str| match (var, &home) {
+        ("HOME", Some(home)) => Ok(Some(home.clone())),
+        _ => std::env::var(var).map(Some),
+    };
+
+    Ok(shellexpand::full_with_context(path, || home.clone(), context)?.into_owned())
+}
+
+/// Determine hostname of machine.
+///
+/// Returns [`None`] if hostname cannot be determined.
+#[cfg(unix)]
+pub fn hostname() -> Option<String> {
+    let mut buffer = [0u8; 256];
+    // SAFETY: Buffer is valid for writes of its full length for the duration of the call.
+    if unsafe { libc::gethostname(buffer.as_mut_ptr().cast(), buffer.len()) } != 0 {
+        return None;
+    }
+    let len = buffer.iter().position(|byte| *byte == 0).unwrap_or(buffer.len());
+    let name = String::from_utf8_lossy(&buffer[..len]).into_owned();
+
+    (!name.is_empty()).then_some(name)
+}
+
+/// Determine hostname of machine.
+///
+/// Returns [`None`] if hostname cannot be determined.
+#[cfg(not(unix))]
+pub fn hostname() -> Option<String> {
+    std::env::var("COMPUTERNAME").ok().filter(|name| !name.is_empty())
+}
+
+/// Make sure that directory can be written to.
+///
+/// Directories that do not exist yet are checked through their nearest existing ancestor, because
+/// that is where they would be created. Writability is probed by creating and removing a file,
+/// because permission bits say nothing about read-only mounts.
+///
+/// # Errors
+///
+/// - Will fail if directory, or its nearest existing ancestor, cannot be written to.
+pub fn check_writable(dir: impl AsRef<Path>, what: &str) -> Result<()> {
+    let dir = dir.as_ref();
+    let existing = dir
+        .ancestors()
+        .map(|path| if path.as_os_str().is_empty() { Path::new(".") } else { path })
+        .find(|path| path.exists())
+        .unwrap_or(dir);
+    let probe = existing.join(format!(".ocd-write-probe-{}", std::process::id()));
+    OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&probe)
+        .map_err(|error| anyhow!("{what} {dir:?} is not writable: {error}"))?;
+    let _ = remove_file(&probe);
+
+    Ok(())
+}
+
+/// Check if directory can be written to.
+///
+/// See [`check_writable`] for how writability is determined.
+pub fn is_writable(dir: impl AsRef<Path>) -> bool {
+    check_writable(dir, "Directory").is_ok()
+}
+
 /// Use Unix-like glob pattern matching.
 ///
 /// Will match a set of patterns to a given set of entries. Whatever is matched is returned as a
@@ -101,6 +268,7 @@ mod tests {
     use super::*;
 
     use pretty_assertions::assert_eq as pretty_assert_eq;
+    use sealed_test::prelude::*;
     use simple_test_case::test_case;
 
     #[test_case(
@@ -128,4 +296,29 @@ fn smoke_glob_match(patterns: Vec<String>, entries: Vec<String>, mut expect: Vec
         result.sort();
         pretty_assert_eq!(result, expect);
     }
+
+    #[test_case("/root/.config", Some("/root"), "/home/awkless/.config"; "inside home")]
+    #[test_case("/root", Some("/root"), "/home/awkless"; "home itself")]
+    #[test_case("/etc/xdg", Some("/root"), "/etc/xdg"; "outside home")]
+    #[test_case("/root/.config", None, "/root/.config"; "no home")]
+    #[test]
+    fn smoke_rebase(dir: &str, from: Option<&str>, expect: &str) {
+        let result = rebase(dir.into(), from.map(Path::new), Path::new("/home/awkless"));
+        pretty_assert_eq!(result, PathBuf::from(expect));
+    }
+
+    #[sealed_test]
+    fn smoke_check_writable() -> Result<()> {
+        std::fs::write("file", "not a directory")?;
+        assert!(is_writable("config/ocd"));
+        assert!(!Path::new("config").exists());
+        pretty_assert_eq!(std::fs::read_dir(".")?.count(), 1, "probe file left behind");
+
+        let error = check_writable("file/ocd", "Configuration directory").unwrap_err();
+        assert!(error
+            .to_string()
+            .starts_with("Configuration directory \"file/ocd\" is not writable"));
+
+        Ok(())
+    }
 }
@@ -5,21 +5,28 @@
 //!
 //! Provides methods to parse, deserialize, and manipulate the cluster definition.
 
-use super::{config_dir, glob_match, home_dir};
+use super::{
+    config_dir, data_dir, expand_path, glob_match, home_dir, hostname,
+    schema::{check_schema, Schema},
+    secret::{decrypt_config, decrypted_path, encrypt, is_encrypted, reveal},
+    state_dir,
+    url::normalize_url,
+};
 
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, Context, Result};
 use beau_collector::BeauCollector as _;
-use config::{Config, File};
+use clap::ValueEnum;
+use config::{Config, File, FileFormat};
 use serde::{
     de::{MapAccess, Visitor},
-    Deserialize, Deserializer,
+    Deserialize, Deserializer, Serialize, Serializer,
 };
 use std::{
-    collections::{HashMap, HashSet, VecDeque},
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque},
     ffi::OsString,
     fmt,
     marker::PhantomData,
-    path::PathBuf,
+    path::{Component, Path, PathBuf},
     str::FromStr,
 };
 use tracing::{debug, instrument, trace, warn};
@@ -42,16 +49,18 @@
 /// # Invariants
 ///
 /// - Root always exists.
-/// - All node dependencies are acyclic.
+/// - All node dependencies and ordering constraints are acyclic.
 /// - Working directory aliases are expanded.
+/// - Working directory aliases never let deployment overwrite OCD's own files.
 /// - Node dependencies are defined.
+/// - Node sources are well-formed.
 #[derive(Debug, PartialEq, Eq)]
 pub struct Cluster {
     /// Root entry of cluster.
     pub root: RootEntry,
 
-    /// Node entries of cluster represented as DAG.
-    pub nodes: HashMap<String, NodeEntry>,
+    /// Node entries of cluster represented as DAG, sorted by name.
+    pub nodes: BTreeMap<String, NodeEntry>,
 }
 
 impl Cluster {
@@ -67,27 +76,58 @@ pub fn new() -> Result<Self> {
 
         let path = config_dir()?.join("root.toml");
         debug!("Load root at {path:?}");
-        let root: RootEntry =
-            Config::builder().add_source(File::from(path)).build()?.try_deserialize()?;
-        let pattern = config_dir()?.join("nodes").join("*.toml").to_string_lossy().into_owned();
-        let mut nodes = HashMap::new();
-        for entry in glob::glob(pattern.as_str())? {
-            // INVARIANT: The name of a node is the file name itself without the extension.
-            let path = entry?;
-            let name = path.file_stem().unwrap().to_string_lossy().into_owned();
-
-            debug!("Load node {name:?} at {path:?}");
-            let node: NodeEntry = Config::builder()
-                .add_source(File::from(path).required(false))
-                .build()?
-                .try_deserialize()?;
-            nodes.insert(name, node);
-        }
 
+        let root: RootEntry = match std::fs::read_to_string(&path) {
+            Ok(data) => parse_root(&data, &path)?,
+            Err(_) => Config::builder().add_source(File::from(path)).build()?.try_deserialize()?,
+        };
+        let nodes = load_nodes(&config_dir()?.join("nodes"))?;
+
+        Self::assemble(root, nodes)
+    }
+
+    /// Construct new cluster definition from contents of its configuration files.
+    ///
+    /// Works like [`Cluster::new`], but takes the contents of `root.toml` and of each node
+    /// configuration file by node name, instead of reading them from the configuration directory,
+    /// e.g., to validate a cluster definition before it gets committed.
+    ///
+    /// # Errors
+    ///
+    /// - Will fail if _any_ configuration file contains invalid TOML formatting.
+    /// - Will fail if cluster definition is invalid.
+    #[instrument(skip(root, nodes), level = "debug")]
+    pub fn from_definition(root: &str, nodes: &BTreeMap<String, String>) -> Result<Self> {
+        trace!("Load cluster configuration from definition");
+        let root = parse_root(root, Path::new("root.toml"))?;
+        let nodes = nodes
+            .iter()
+            .map(|(name, data)| {
+                let path = Path::new("nodes").join(format!("{name}.toml"));
+                Ok((name.clone(), parse_node(name, data, &path)?))
+            })
+            .collect::<Result<BTreeMap<_, _>>>()?;
+
+        Self::assemble(root, nodes)
+    }
+
+    fn assemble(root: RootEntry, nodes: BTreeMap<String, NodeEntry>) -> Result<Self> {
         let mut cluster = Self { root, nodes };
+        cluster.load_nested_clusters()?;
+        cluster.interpolate_vars()?;
         cluster.dependency_existence_check()?;
         cluster.acyclic_check()?;
+        cluster.inline_check()?;
+        cluster.source_check()?;
         cluster.expand_work_dir_aliases()?;
+        cluster.external_check()?;
+        cluster.pin_check()?;
+        cluster.profile_check()?;
+        cluster.encrypted_check()?;
+        cluster.template_check()?;
+        cluster.hook_check()?;
+        cluster.work_dir_alias_check()?;
+        cluster.exclude_inline_nodes()?;
 
         Ok(cluster)
     }
@@ -101,6 +141,432 @@ pub fn dependency_iter(&self, node: impl Into<String>) -> DependencyIter<'_> {
         DependencyIter { graph: &self.nodes, visited: HashSet::new(), stack }
     }
 
+    /// Find shortest chain of dependencies leading from one node entry to another.
+    ///
+    /// Chain starts with `from` and ends with `to`, with every node entry in between depending
+    /// on the one after it. Returns [`None`] if `to` is not a transitive dependency of `from`.
+    pub fn dependency_path(&self, from: &str, to: &str) -> Option<Vec<String>> {
+        let mut parents: BTreeMap<&str, &str> = BTreeMap::new();
+        let mut pending = VecDeque::from([from]);
+        while let Some(name) = pending.pop_front() {
+            if name == to {
+                let mut path = vec![to.to_string()];
+                let mut current = to;
+                while let Some(parent) = parents.get(current) {
+                    path.push(parent.to_string());
+                    current = parent;
+                }
+                path.reverse();
+                return Some(path);
+            }
+
+            for dependency in self.nodes.get(name)?.settings.dependencies.iter().flatten() {
+                if dependency != from && !parents.contains_key(dependency.as_str()) {
+                    parents.insert(dependency, name);
+                    pending.push_back(dependency);
+                }
+            }
+        }
+
+        None
+    }
+
+    /// List node entries that depend on given node entry directly, sorted by name.
+    ///
+    /// Reverse of the dependencies setting, e.g., to find out which node entries would be left
+    /// with a missing dependency if the given node entry went away.
+    pub fn dependents_of(&self, name: &str) -> Vec<&str> {
+        self.nodes
+            .iter()
+            .filter(|(_, node)| node.settings.dependencies.iter().flatten().any(|dep| dep == name))
+            .map(|(dependent, _)| dependent.as_str())
+            .collect()
+    }
+
+    /// Compare cluster definition against an incoming one, e.g., the one at the upstream of root.
+    ///
+    /// Node entries of nested clusters are left out, because they come and go along with the
+    /// nested cluster defining them.
+    pub fn changes_to(&self, incoming: &Cluster) -> ClusterChanges {
+        let top_level = |name: &&String| !name.contains('/');
+        let mut changes =
+            ClusterChanges { root: self.root != incoming.root, ..ClusterChanges::default() };
+        for (name, node) in self.nodes.iter().filter(|(name, _)| top_level(name)) {
+            match incoming.nodes.get(name) {
+                Some(incoming) if incoming != node => changes.changed.push(name.clone()),
+                Some(_) => {}
+                None => changes.removed.push(name.clone()),
+            }
+        }
+        changes.added = incoming
+            .nodes
+            .keys()
+            .filter(top_level)
+            .filter(|name| !self.nodes.contains_key(*name))
+            .cloned()
+            .collect();
+
+        changes
+    }
+
+    /// Gather target node entries along with their transitive dependencies.
+    ///
+    /// Maps each node entry to the targets that pull it in as a dependency. Targets themselves
+    /// map to an empty set, even if another target depends on them. Root is never part of the
+    /// closure, because nodes cannot depend on it.
+    pub fn dependency_closure(
+        &self,
+        targets: impl IntoIterator<Item = impl AsRef<str>>,
+    ) -> BTreeMap<String, BTreeSet<String>> {
+        let targets: Vec<String> = targets
+            .into_iter()
+            .map(|target| target.as_ref().to_string())
+            .filter(|target| self.nodes.contains_key(target))
+            .collect();
+
+        let mut closure: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+        for target in &targets {
+            for (name, _) in self.dependency_iter(target).skip(1) {
+                closure.entry(name.to_string()).or_default().insert(target.clone());
+            }
+        }
+        for target in targets {
+            closure.insert(target, BTreeSet::new());
+        }
+
+        closure
+    }
+
+    /// Add new node entry to cluster definition.
+    ///
+    /// Writes the node entry into its own configuration file at
+    /// `$XDG_CONFIG_HOME/ocd/nodes/<name>.toml`. The configuration file is always generated the
+    /// same way for the same node entry, i.e., settings are written in a fixed order with the
+    /// deployment method kept as an inline table, such that diffs of the cluster definition stay
+    /// clean.
+    ///
+    /// # Errors
+    ///
+    /// - Will fail if node entry already exists in cluster.
+    /// - Will fail if node entry cannot be serialized.
+    /// - Will fail if node configuration file cannot be written.
+    #[instrument(skip(self, node), level = "debug")]
+    pub fn add_node(
+        &mut self,
+        name: impl Into<String> + fmt::Debug,
+        node: NodeEntry,
+    ) -> Result<()> {
+        let name = name.into();
+        if name == "root" || self.nodes.contains_key(&name) {
+            return Err(anyhow!("Node {name:?} already defined in cluster"));
+        }
+
+        let dir = config_dir()?.join("nodes");
+        std::fs::create_dir_all(&dir)?;
+        let path = dir.join(format!("{name}.toml"));
+        std::fs::write(&path, serialize_node(&node)?)?;
+        debug!("Add node {name:?} at {path:?}");
+        self.nodes.insert(name, node);
+
+        Ok(())
+    }
+
+    /// Set URL of existing node entry in cluster definition.
+    ///
+    /// Edits the URL of the node's configuration file in place, leaving the rest of the file,
+    /// e.g., comments and key ordering, untouched.
+    ///
+    /// # Errors
+    ///
+    /// - Will fail if node entry is not defined in cluster.
+    /// - Will fail if node configuration file cannot be read, parsed, or written.
+    #[instrument(skip(self), level = "debug")]
+    pub fn set_node_url(
+        &mut self,
+        name: impl AsRef<str> + fmt::Debug,
+        url: impl Into<String> + fmt::Debug,
+    ) -> Result<()> {
+        let name = name.as_ref();
+        let url = normalize_url(url.into())?;
+        self.edit_node(name, |settings| {
+            // INVARIANT: Encrypted URLs stay encrypted.
+            let encrypted =
+                settings.get("url").and_then(|url| url.as_str()).is_some_and(is_encrypted);
+            let url = if encrypted { encrypt(&url)? } else { url.clone() };
+            settings["url"] = toml_edit::value(url);
+            Ok(())
+        })?;
+        debug!("Set URL of node {name:?} to {url:?}");
+
+        Ok(())
+    }
+
+    /// Set deployment of existing node entry in cluster definition.
+    ///
+    /// Edits the node's configuration file in place, leaving the rest of the file untouched.
+    /// Deployment keeps the form, string or table, that it was written in. Working directory
+    /// alias is written as given, and expanded in the cluster itself.
+    ///
+    /// # Errors
+    ///
+    /// - Will fail if node entry is not defined in cluster.
+    /// - Will fail if node configuration file cannot be read, parsed, or written.
+    #[instrument(skip(self), level = "debug")]
+    pub fn set_node_deployment(
+        &mut self,
+        name: impl AsRef<str> + fmt::Debug,
+        deployment: &NodeEntryDeployment,
+    ) -> Result<()> {
+        let name = name.as_ref();
+        let kind = deployment.kind.to_string().replace('-', "_");
+        let alias = deployment.work_dir_alias.to_string();
+        self.edit_node(name, |settings| {
+            if let Some(table) = settings.get_mut("deployment").and_then(|d| d.as_table_like_mut())
+            {
+                let _ = table.insert("kind", toml_edit::value(kind));
+                let _ = table.insert("work_dir_alias", toml_edit::value(alias));
+                return Ok(());
+            }
+
+            // INVARIANT: Normal nodes have no use for a working directory alias.
+            settings["deployment"] = match deployment.kind {
+                DeploymentKind::Normal => toml_edit::value(kind),
+                _ => toml_edit::value(format!("{kind}:{alias}")),
+            };
+            Ok(())
+        })?;
+        debug!("Set deployment of node {name:?} to {deployment}");
+
+        Ok(())
+    }
+
+    /// Add and remove exclusion rules of existing node entry in cluster definition.
+    ///
+    /// Edits the node's configuration file in place, leaving the rest of the file untouched.
+    /// Rules that already exist are not added twice, and the excluded setting is dropped entirely
+    /// once no rules are left. Returns true if exclusion rules of node changed at all.
+    ///
+    /// # Errors
+    ///
+    /// - Will fail if node entry is not defined in cluster.
+    /// - Will fail if node configuration file cannot be read, parsed, or written.
+    #[instrument(skip(self), level = "debug")]
+    pub fn edit_node_excluded(
+        &mut self,
+        name: impl AsRef<str> + fmt::Debug,
+        add: &[String],
+        remove: &[String],
+    ) -> Result<bool> {
+        let name = name.as_ref();
+        let node = self.nodes.get(name).ok_or(anyhow!("Node {name:?} not defined"))?;
+        let old = node.settings.excluded.clone().unwrap_or_default();
+        let mut rules: Vec<String> = old.iter().filter(|r| !remove.contains(r)).cloned().collect();
+        for rule in add {
+            if !rules.contains(rule) {
+                rules.push(rule.clone());
+            }
+        }
+
+        if rules == old {
+            debug!("Exclusion rules of node {name:?} unchanged");
+            return Ok(false);
+        }
+
+        self.edit_node(name, |settings| {
+            if rules.is_empty() {
+                if let Some(table) = settings.as_table_like_mut() {
+                    let _ = table.remove("excluded");
+                }
+                return Ok(());
+            }
+
+            // INVARIANT: Keep formatting and encryption of rules that survived the edit.
+            let mut array = match settings.get("excluded").and_then(|item| item.as_array()) {
+                Some(array) => array.clone(),
+                None => toml_edit::Array::new(),
+            };
+            array
+                .retain(|rule| rule.as_str().map(reveal).is_some_and(|rule| rules.contains(&rule)));
+            let kept: Vec<String> = array.iter().filter_map(|r| r.as_str().map(reveal)).collect();
+            for rule in &rules {
+                if !kept.contains(rule) {
+                    array.push(rule.as_str());
+                }
+            }
+            settings["excluded"] = toml_edit::value(array);
+            Ok(())
+        })?;
+        debug!("Set exclusion rules of node {name:?} to {rules:?}");
+
+        Ok(true)
+    }
+
+    /// Check if existing node entry can be renamed.
+    ///
+    /// # Errors
+    ///
+    /// - Will fail if node entry is not defined in cluster, or new name is already taken.
+    /// - Will fail if new name is not a valid file name.
+    /// - Will fail if node belongs to nested cluster, or is a nested cluster itself.
+    pub fn check_rename(&self, old: &str, new: &str) -> Result<()> {
+        let node = self.nodes.get(old).ok_or(anyhow!("Node {old:?} not defined"))?;
+        if new == "root" || self.nodes.contains_key(new) {
+            return Err(anyhow!("Node {new:?} already defined in cluster"));
+        }
+        if new.is_empty() || new.starts_with('.') || new.contains(['/', '\\']) {
+            return Err(anyhow!("Node name {new:?} is not a valid file name"));
+        }
+        if old.contains('/') {
+            return Err(anyhow!(
+                "Node {old:?} belongs to nested cluster, rename it through its own cluster instead"
+            ));
+        }
+        if node.settings.deployment.kind.is_cluster() {
+            return Err(anyhow!(
+                "Node {old:?} is a nested cluster, whose nodes are named after it"
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Rename existing node entry in cluster definition.
+    ///
+    /// Moves the node's configuration file to `$XDG_CONFIG_HOME/ocd/nodes/<new>.toml`. Every
+    /// other node configuration file that lists the node as a dependency, or orders itself after
+    /// the node, is edited in place, as are host profiles of `root.toml` that select the node by
+    /// its exact name. Glob patterns of host profiles are left alone. Returns the names of the
+    /// nodes whose dependencies changed.
+    ///
+    /// # Errors
+    ///
+    /// - Will fail if node entry cannot be renamed, see [`Cluster::check_rename`].
+    /// - Will fail if any configuration file cannot be read, parsed, or written.
+    #[instrument(skip(self), level = "debug")]
+    pub fn rename_node(&mut self, old: &str, new: &str) -> Result<Vec<String>> {
+        self.check_rename(old, new)?;
+
+        // INVARIANT: Every edit is prepared before anything is written, such that unparsable
+        // configuration files leave the cluster definition untouched.
+        let dir = config_dir()?;
+        let mut edits = Vec::new();
+        let mut dependents = Vec::new();
+        for (name, node) in &self.nodes {
+            let settings = &node.settings;
+            let depends =
+                settings.dependencies.iter().chain(&settings.after).flatten().any(|dep| dep == old);
+            if name.contains('/') || !depends {
+                continue;
+            }
+
+            let path = dir.join("nodes").join(format!("{name}.toml"));
+            let data = std::fs::read_to_string(&path)
+                .with_context(|| format!("Failed to read {path:?}"))?;
+            let mut document: toml_edit::DocumentMut =
+                data.parse().with_context(|| format!("Failed to parse {path:?}"))?;
+            if let Some(settings) = document["settings"].as_table_like_mut() {
+                rename_strings(settings, "dependencies", old, new);
+                rename_strings(settings, "after", old, new);
+            }
+            edits.push((path, document.to_string()));
+            dependents.push(name.clone());
+        }
+
+        let path = dir.join("root.toml");
+        if self.root.profile.values().any(|profile| profile.nodes.iter().any(|n| n == old)) {
+            let data = std::fs::read_to_string(&path)
+                .with_context(|| format!("Failed to read {path:?}"))?;
+            let mut document: toml_edit::DocumentMut =
+                data.parse().with_context(|| format!("Failed to parse {path:?}"))?;
+            if let Some(profiles) = document.get_mut("profile").and_then(|p| p.as_table_like_mut())
+            {
+                for (_, profile) in profiles.iter_mut() {
+                    if let Some(profile) = profile.as_table_like_mut() {
+                        rename_strings(profile, "nodes", old, new);
+                    }
+                }
+            }
+            edits.push((path, document.to_string()));
+        }
+
+        let (from, to) = (
+            dir.join("nodes").join(format!("{old}.toml")),
+            dir.join("nodes").join(format!("{new}.toml")),
+        );
+        std::fs::rename(&from, &to)
+            .with_context(|| format!("Failed to move {from:?} to {to:?}"))?;
+        for (path, data) in edits {
+            std::fs::write(&path, data).with_context(|| format!("Failed to write {path:?}"))?;
+        }
+        debug!("Rename node {old:?} to {new:?}");
+
+        let node = self.nodes.remove(old).expect("node exists");
+        self.nodes.insert(new.to_string(), node);
+        let dependencies = self.nodes.values_mut().flat_map(|node| {
+            let settings = &mut node.settings;
+            settings.dependencies.iter_mut().chain(&mut settings.after)
+        });
+        let profiles = self.root.profile.values_mut().map(|profile| &mut profile.nodes);
+        for name in dependencies.chain(profiles).flatten().filter(|name| *name == old) {
+            *name = new.to_string();
+        }
+
+        Ok(dependents)
+    }
+
+    // INVARIANT: Node configuration files are edited in place, such that user formatting stays.
+    fn edit_node(
+        &mut self,
+        name: &str,
+        edit: impl FnOnce(&mut toml_edit::Item) -> Result<()>,
+    ) -> Result<()> {
+        if !self.nodes.contains_key(name) {
+            return Err(anyhow!("Node {name:?} not defined"));
+        }
+
+        if name.contains('/') {
+            return Err(anyhow!(
+                "Node {name:?} belongs to nested cluster, edit it through its own cluster instead"
+            ));
+        }
+
+        let path = config_dir()?.join("nodes").join(format!("{name}.toml"));
+        let data =
+            std::fs::read_to_string(&path).with_context(|| format!("Failed to read {path:?}"))?;
+        let mut document: toml_edit::DocumentMut = data.parse()?;
+        edit(&mut document["settings"])?;
+        let data = document.to_string();
+        let mut node: NodeEntry = check_schema(&decrypt_config(&data)?, NODE_SCHEMA)
+            .and_then(|data| Ok(toml::de::from_str(&data)?))
+            .with_context(|| format!("Edit of {path:?} produced invalid node entry"))?;
+        interpolate_node(&mut node.settings, &self.vars()?)
+            .with_context(|| format!("Edit of {path:?} produced invalid node entry"))?;
+        std::fs::write(&path, data).with_context(|| format!("Failed to write {path:?}"))?;
+
+        // INVARIANT: Cluster keeps interpolated and expanded working directory aliases.
+        let entry = self.nodes.get_mut(name).expect("node exists");
+        entry.settings.deployment = NodeEntryDeployment::new(
+            node.settings.deployment.kind,
+            node.settings.deployment.work_dir_alias.expand()?,
+        );
+        entry.settings.url = node.settings.url;
+        entry.settings.excluded = node.settings.excluded;
+        entry.settings.included = node.settings.included;
+        entry.settings.encrypted = node.settings.encrypted;
+        entry.settings.templates = node.settings.templates;
+        entry.settings.hooks = node.settings.hooks;
+        entry.settings.description = node.settings.description;
+        entry.settings.homepage = node.settings.homepage;
+        entry.settings.sparse_profiles = node.settings.sparse_profiles;
+        entry.settings.shallow_since = node.settings.shallow_since;
+        entry.settings.pull_strategy = node.settings.pull_strategy;
+        entry.settings.branch = node.settings.branch;
+        entry.settings.tag = node.settings.tag;
+        entry.settings.rev = node.settings.rev;
+
+        Ok(())
+    }
+
     /// Match list of targets to entries in cluster.
     ///
     /// # Errors
@@ -123,6 +589,148 @@ pub fn match_targets(&self, mut targets: Vec<String>) -> Result<Vec<String>> {
         Ok(results)
     }
 
+    /// Generate redacted copy of cluster definition that is safe to publish.
+    ///
+    /// Combines the configuration files of root and each node into one TOML document, with root
+    /// placed at `root.settings` and each node at `nodes.<name>.settings`. Configuration files are
+    /// copied over as they are, such that comments and formatting stay, except for the following:
+    ///
+    /// - Node entries marked private are dropped, along with any dependency on them.
+    /// - Exclusion rules that look like they refer to secrets are dropped, e.g., `.ssh/`.
+    ///
+    /// Node entries of nested clusters are left to the definition of their own cluster.
+    ///
+    /// # Errors
+    ///
+    /// - Will fail if any configuration file cannot be read or parsed.
+    #[instrument(skip(self), level = "debug")]
+    pub fn share(&self) -> Result<String> {
+        let dir = config_dir()?;
+        let private: Vec<&str> = self
+            .nodes
+            .iter()
+            .filter(|(_, node)| node.settings.private)
+            .map(|(name, _)| name.as_str())
+            .collect();
+
+        let mut entries = vec![(vec!["root".to_string()], dir.join("root.toml"))];
+        for name in self.nodes.keys().filter(|name| !name.contains('/')) {
+            if private.contains(&name.as_str()) {
+                debug!("Redact private node {name:?}");
+                continue;
+            }
+            let path = dir.join("nodes").join(format!("{name}.toml"));
+            entries.push((vec!["nodes".to_string(), name.clone()], path));
+        }
+
+        let mut document = toml_edit::DocumentMut::new();
+        let mut offset = 0;
+        for (keys, path) in entries {
+            let data = std::fs::read_to_string(&path)
+                .with_context(|| format!("Failed to read {path:?}"))?;
+            let mut entry: toml_edit::DocumentMut =
+                data.parse().with_context(|| format!("Failed to parse {path:?}"))?;
+            let Some(mut settings) = entry.remove("settings") else {
+                continue;
+            };
+            redact_settings(&mut settings, &private);
+
+            // INVARIANT: Tables keep their relative order, and follow tables of earlier entries.
+            offset = shift_positions(&mut settings, offset) + 1;
+
+            // INVARIANT: Entries are separated by a blank line.
+            if let Some(table) = settings.as_table_mut().filter(|_| !document.is_empty()) {
+                let prefix = table.decor().prefix().and_then(|p| p.as_str()).unwrap_or_default();
+                if !prefix.starts_with('\n') {
+                    let prefix = format!("\n{prefix}");
+                    table.decor_mut().set_prefix(prefix);
+                }
+            }
+
+            let mut table = document.as_table_mut();
+            for key in keys {
+                let item = table.entry(&key).or_insert_with(|| {
+                    let mut table = toml_edit::Table::new();
+                    table.set_implicit(true);
+                    toml_edit::Item::Table(table)
+                });
+                table = item.as_table_mut().ok_or(anyhow!("Expected {key:?} to be a table"))?;
+            }
+            table.insert("settings", settings);
+
+            // INVARIANT: Variables of root are shared, but never their machine-local overrides.
+            if let Some(mut vars) = entry.remove("vars") {
+                offset = shift_positions(&mut vars, offset) + 1;
+                table.insert("vars", vars);
+            }
+        }
+
+        Ok(document.to_string())
+    }
+
+    #[instrument(skip(self), level = "debug")]
+    fn load_nested_clusters(&mut self) -> Result<()> {
+        trace!("Load node entries of nested clusters");
+        let mut pending: VecDeque<(String, usize)> = self
+            .nodes
+            .iter()
+            .filter(|(_, node)| node.settings.deployment.kind.is_cluster())
+            .map(|(name, _)| (name.clone(), 1))
+            .collect();
+
+        while let Some((name, depth)) = pending.pop_front() {
+            if depth > MAX_CLUSTER_DEPTH {
+                return Err(anyhow!(
+                    "Nested cluster {name:?} is nested deeper than {MAX_CLUSTER_DEPTH} clusters"
+                ));
+            }
+
+            // INVARIANT: Definition of nested cluster is always deployed to its own directory.
+            let dir = nested_cluster_dir(&name)?;
+            let node = self.nodes.get_mut(&name).expect("nested cluster exists");
+            node.settings.deployment.work_dir_alias = WorkDirAlias::new(&dir);
+
+            if let Ok(data) = std::fs::read_to_string(dir.join("root.toml")) {
+                check_min_ocd_version(&data)
+                    .with_context(|| format!("Nested cluster {name:?} cannot be loaded"))?;
+            }
+
+            let mut members = Vec::new();
+            for (member, mut entry) in load_nodes(&dir.join("nodes"))? {
+                if entry.settings.deployment.kind.is_inline() {
+                    return Err(anyhow!(
+                        "Nested cluster {name:?} defines inline node {member:?}, which is not \
+                        supported"
+                    ));
+                }
+
+                // INVARIANT: Node names, dependencies, and ordering constraints of nested clusters
+                // are namespaced.
+                let member = format!("{name}/{member}");
+                let settings = &mut entry.settings;
+                for dependency in
+                    settings.dependencies.iter_mut().chain(&mut settings.after).flatten()
+                {
+                    *dependency = format!("{name}/{dependency}");
+                }
+
+                if entry.settings.deployment.kind.is_cluster() {
+                    pending.push_back((member.clone(), depth + 1));
+                }
+                debug!("Load node {member:?} of nested cluster {name:?}");
+                self.nodes.insert(member.clone(), entry);
+                members.push(member);
+            }
+
+            // INVARIANT: Nested cluster depends on all of its nodes, such that deploying the
+            // nested cluster deploys all of it.
+            let node = self.nodes.get_mut(&name).expect("nested cluster exists");
+            node.settings.dependencies.get_or_insert_with(Vec::new).extend(members);
+        }
+
+        Ok(())
+    }
+
     #[instrument(skip(self), level = "debug")]
     fn dependency_existence_check(&self) -> Result<()> {
         trace!("Perform dependency existence check on cluster");
@@ -142,18 +750,198 @@ fn dependency_existence_check(&self) -> Result<()> {
         results.into_iter().bcollect::<_>()
     }
 
+    /// List names of all node entries in dependency order.
+    ///
+    /// Dependencies of a node always come before the node itself, such that deploying nodes in
+    /// this order never deploys a node before its dependencies. Nodes listed in the `after`
+    /// setting of a node come before it as well. Reverse the order to undeploy or remove nodes
+    /// before their dependencies instead.
+    pub fn dependency_order(&self) -> Vec<&str> {
+        // INVARIANT: Cluster is acyclic, thus topological sort covers every node.
+        let mut order: Vec<&str> = self
+            .topological_sort()
+            .iter()
+            .filter_map(|name| self.nodes.get_key_value(name).map(|(name, _)| name.as_str()))
+            .collect();
+        order.reverse();
+        order
+    }
+
+    /// Sort items naming node entries into dependency order, see [`Cluster::dependency_order`].
+    ///
+    /// Items naming the same node entry are deduplicated, and items naming node entries not
+    /// defined in cluster go last.
+    pub fn sort_dependency_order<T>(&self, items: &mut Vec<T>, name: impl Fn(&T) -> &str) {
+        let ranks: HashMap<&str, usize> = self
+            .dependency_order()
+            .into_iter()
+            .enumerate()
+            .map(|(rank, name)| (name, rank))
+            .collect();
+        items.sort_by_key(|item| ranks.get(name(item)).copied().unwrap_or(usize::MAX));
+        items.dedup_by(|a, b| name(a) == name(b));
+    }
+
+    /// Map each node entry to its depth in the dependency graph.
+    ///
+    /// Node entries without dependencies sit at level zero, and every other node entry sits one
+    /// level above its deepest dependency, counting nodes it is ordered after as dependencies.
+    /// Node entries of the same level never depend on each other, so anything done per node
+    /// entry can be done for a whole level at once.
+    pub fn dependency_levels(&self) -> BTreeMap<String, usize> {
+        let mut levels: BTreeMap<String, usize> = BTreeMap::new();
+        for name in self.dependency_order() {
+            let level = self
+                .predecessors(&self.nodes[name])
+                .filter_map(|dependency| levels.get(dependency).map(|level| level + 1))
+                .max()
+                .unwrap_or_default();
+            levels.insert(name.to_string(), level);
+        }
+
+        levels
+    }
+
+    /// Select subset of node entries through patterns, along with everything they need.
+    ///
+    /// Selects every node matched by `only`, or every node if `only` is empty, leaving out nodes
+    /// matched by `skip`. Selected nested clusters bring their own nodes along, and selected nodes
+    /// of nested clusters bring their nested clusters along. Dependencies of selected nodes are
+    /// always selected, even if `skip` matched them. Root is never selected.
+    ///
+    /// # Errors
+    ///
+    /// - Will fail if patterns cannot be matched.
+    pub fn select_nodes(&self, only: Vec<String>, skip: Vec<String>) -> Result<HashSet<String>> {
+        let skipped: HashSet<String> =
+            if skip.is_empty() { Vec::new() } else { self.match_targets(skip)? }
+                .into_iter()
+                .collect();
+        let mut targets: HashSet<String> = if only.is_empty() {
+            self.nodes.keys().cloned().collect()
+        } else {
+            self.match_targets(only)?
+        }
+        .into_iter()
+        .filter(|name| name != "root" && !skipped.contains(name))
+        .collect();
+
+        // INVARIANT: Nested clusters and their nodes are only useful together.
+        for name in targets.clone() {
+            if self.nodes[&name].settings.deployment.kind.is_cluster() {
+                let prefix = format!("{name}/");
+                targets.extend(
+                    self.nodes
+                        .keys()
+                        .filter(|member| member.starts_with(&prefix) && !skipped.contains(*member))
+                        .cloned(),
+                );
+            }
+            for (index, _) in name.match_indices('/') {
+                targets.insert(name[..index].to_string());
+            }
+        }
+
+        let mut selected = HashSet::new();
+        for target in &targets {
+            for (name, _) in self.dependency_iter(target) {
+                if skipped.contains(name) && !selected.contains(name) {
+                    warn!("Node {name:?} is a dependency of {target:?}, select it anyway");
+                }
+                selected.insert(name.to_string());
+            }
+        }
+
+        Ok(selected)
+    }
+
+    /// Pick host profile to use on machine with given hostname.
+    ///
+    /// Picks host profile by name if given one. Otherwise, picks the first host profile whose host
+    /// patterns match hostname. Returns [`None`] if no host profile applies, in which case every
+    /// node is relevant.
+    ///
+    /// # Errors
+    ///
+    /// - Will fail if named host profile is not defined.
+    pub fn pick_profile(&self, name: Option<&str>, hostname: Option<&str>) -> Result<Option<&str>> {
+        if let Some(name) = name {
+            return match self.root.profile.get_key_value(name) {
+                Some((name, _)) => Ok(Some(name.as_str())),
+                None => Err(anyhow!("Host profile {name:?} is not defined in root")),
+            };
+        }
+
+        let Some(hostname) = hostname else {
+            return Ok(None);
+        };
+        let picked = self.root.profile.iter().find(|(_, profile)| {
+            profile
+                .hosts
+                .iter()
+                .any(|host| glob::Pattern::new(host).is_ok_and(|host| host.matches(hostname)))
+        });
+
+        Ok(picked.map(|(name, _)| name.as_str()))
+    }
+
+    /// Select node entries of host profile, along with everything they need, see
+    /// [`Cluster::select_nodes`].
+    ///
+    /// # Errors
+    ///
+    /// - Will fail if host profile is not defined.
+    /// - Will fail if node patterns of host profile cannot be matched.
+    pub fn profile_nodes(&self, name: &str) -> Result<HashSet<String>> {
+        let profile = self
+            .root
+            .profile
+            .get(name)
+            .ok_or_else(|| anyhow!("Host profile {name:?} is not defined in root"))?;
+        if profile.nodes.is_empty() {
+            return Ok(HashSet::new());
+        }
+
+        self.select_nodes(profile.nodes.clone(), Vec::new())
+    }
+
     #[instrument(skip(self), level = "debug")]
     fn acyclic_check(&self) -> Result<()> {
         trace!("Perform acyclic check on cluster");
-        let mut in_degree: HashMap<String, usize> = HashMap::new();
+        let order = self.topological_sort();
+
+        // INVARIANT: Topological sort does not cover the whole graph.
+        //   - There exists a cycle.
+        //   - The nodes left out represent this cycle.
+        if order.len() != self.nodes.len() {
+            let cycle: Vec<String> =
+                self.nodes.keys().filter(|key| !order.contains(*key)).cloned().collect();
+            return Err(anyhow!("Cluster contains cycle(s): {cycle:?}"));
+        }
+        debug!("Topological sort of cluster nodes: {order:?}");
+
+        Ok(())
+    }
+
+    // Dependencies of node entry, along with the nodes it is ordered after that are defined.
+    fn predecessors<'a>(&'a self, node: &'a NodeEntry) -> impl Iterator<Item = &'a String> + 'a {
+        let after =
+            node.settings.after.iter().flatten().filter(|name| self.nodes.contains_key(*name));
+        node.settings.dependencies.iter().flatten().chain(after)
+    }
+
+    // INVARIANT: Nodes come before their dependencies and the nodes they are ordered after,
+    // leaving out nodes that are part of a cycle.
+    fn topological_sort(&self) -> Vec<String> {
+        let mut in_degree: BTreeMap<String, usize> = BTreeMap::new();
         let mut queue: VecDeque<String> = VecDeque::new();
-        let mut visited: HashSet<String> = HashSet::new();
+        let mut order: Vec<String> = Vec::new();
 
         // INVARIANT: The in-degree of a node is the sum all all incoming edegs of each
         // destination node.
         for (name, node) in &self.nodes {
             in_degree.entry(name.clone()).or_insert(0);
-            for dependency in node.settings.dependencies.iter().flatten() {
+            for dependency in self.predecessors(node) {
                 *in_degree.entry(dependency.clone()).or_insert(0) += 1;
             }
         }
@@ -165,50 +953,726 @@ fn acyclic_check(&self) -> Result<()> {
             }
         }
 
-        while let Some(current) = queue.pop_front() {
-            for dependency in self.nodes[&current].settings.dependencies.iter().flatten() {
-                *in_degree.get_mut(dependency).unwrap() -= 1;
-                if *in_degree.get(dependency).unwrap() == 0 {
-                    queue.push_back(dependency.clone());
-                }
-            }
-            visited.insert(current);
-        }
+        while let Some(current) = queue.pop_front() {
+            for dependency in self.predecessors(&self.nodes[&current]) {
+                *in_degree.get_mut(dependency).unwrap() -= 1;
+                if *in_degree.get(dependency).unwrap() == 0 {
+                    queue.push_back(dependency.clone());
+                }
+            }
+            order.push(current);
+        }
+
+        order
+    }
+
+    #[instrument(skip(self), level = "debug")]
+    fn inline_check(&self) -> Result<()> {
+        trace!("Perform inline node check on cluster");
+        let mut results = Vec::new();
+        for (name, node) in &self.nodes {
+            let subdir = match (&node.settings.deployment.kind, &node.settings.subdir) {
+                (DeploymentKind::Inline, Some(subdir)) => subdir,
+                (DeploymentKind::Inline, None) => {
+                    results.push(Err(anyhow!("Inline node {name:?} does not define a subdir")));
+                    continue;
+                }
+                (_, Some(_)) => {
+                    results.push(Err(anyhow!("Node {name:?} defines subdir, but is not inline")));
+                    continue;
+                }
+                (_, None) => continue,
+            };
+
+            // INVARIANT: Inline subdirectories must stay within the root repository.
+            if subdir.as_os_str().is_empty()
+                || subdir.is_absolute()
+                || subdir.components().any(|c| !matches!(c, Component::Normal(_)))
+            {
+                results.push(Err(anyhow!(
+                    "Inline node {name:?} subdir {subdir:?} must be a relative path inside root"
+                )));
+            } else {
+                results.push(Ok(()));
+            }
+        }
+
+        results.into_iter().bcollect::<_>()
+    }
+
+    #[instrument(skip(self), level = "debug")]
+    fn source_check(&self) -> Result<()> {
+        trace!("Perform node source check on cluster");
+        let mut results = Vec::new();
+        for (name, node) in &self.nodes {
+            let Some(NodeSource::Archive { url, sha256 }) = &node.settings.source else {
+                continue;
+            };
+
+            if node.settings.deployment.kind.is_inline() {
+                results.push(Err(anyhow!("Inline node {name:?} cannot define a source")));
+            } else if node.settings.deployment.kind.is_mirror() {
+                results.push(Err(anyhow!("Mirror node {name:?} cannot define a source")));
+            } else if !node.settings.url.is_empty() {
+                results.push(Err(anyhow!("Node {name:?} defines both a URL and a source")));
+            } else if url.trim().is_empty() {
+                results.push(Err(anyhow!("Archive source of node {name:?} is missing its URL")));
+            } else if sha256.len() != 64 || !sha256.chars().all(|c| c.is_ascii_hexdigit()) {
+                results.push(Err(anyhow!(
+                    "Archive source of node {name:?} must give SHA-256 digest as 64 hex digits"
+                )));
+            } else {
+                results.push(Ok(()));
+            }
+        }
+
+        results.into_iter().bcollect::<_>()
+    }
+
+    #[instrument(skip(self), level = "debug")]
+    fn external_check(&self) -> Result<()> {
+        trace!("Perform external repository check on cluster");
+        let mut results = Vec::new();
+        for (name, node) in &self.nodes {
+            let Some(path) = &node.settings.path else {
+                continue;
+            };
+
+            if node.settings.deployment.kind != DeploymentKind::Normal {
+                results.push(Err(anyhow!(
+                    "Node {name:?} uses external repository, but only normal nodes can"
+                )));
+            } else if node.settings.source.is_some() {
+                results.push(Err(anyhow!("Node {name:?} defines both a path and a source")));
+            } else if !path.is_absolute() {
+                results.push(Err(anyhow!(
+                    "External repository path of node {name:?} must be absolute, got {path:?}"
+                )));
+            } else {
+                results.push(Ok(()));
+            }
+        }
+
+        results.into_iter().bcollect::<_>()
+    }
+
+    #[instrument(skip(self), level = "debug")]
+    fn pin_check(&self) -> Result<()> {
+        trace!("Perform pinned reference check on cluster");
+        let mut results = Vec::new();
+        for (name, node) in &self.nodes {
+            let settings = &node.settings;
+            let pins = [&settings.branch, &settings.tag, &settings.rev];
+            match pins.iter().filter(|pin| pin.is_some()).count() {
+                0 => continue,
+                1 => {}
+                _ => {
+                    results.push(Err(anyhow!(
+                        "Node {name:?} pins more than one of branch, tag, and rev"
+                    )));
+                    continue;
+                }
+            }
+
+            if settings.subdir.is_some()
+                || settings.source.is_some()
+                || settings.path.is_some()
+                || settings.deployment.kind.is_mirror()
+            {
+                results.push(Err(anyhow!(
+                    "Node {name:?} is pinned, but only nodes cloned from their URL can be"
+                )));
+            } else {
+                results.push(Ok(()));
+            }
+        }
+
+        results.into_iter().bcollect::<_>()
+    }
+
+    // INVARIANT: Patterns that name nodes of nested clusters are left alone, because those nodes
+    // are only known once their nested cluster got cloned.
+    #[instrument(skip(self), level = "debug")]
+    fn profile_check(&self) -> Result<()> {
+        trace!("Perform host profile check on cluster");
+        let mut results = Vec::new();
+        for (name, profile) in &self.root.profile {
+            for pattern in &profile.nodes {
+                match glob::Pattern::new(pattern) {
+                    Err(error) => results.push(Err(anyhow!(
+                        "Host profile {name:?} has invalid node pattern {pattern:?}: {error}"
+                    ))),
+                    Ok(glob)
+                        if !pattern.contains('/')
+                            && !self.nodes.keys().any(|node| glob.matches(node)) =>
+                    {
+                        results.push(Err(anyhow!(
+                            "Host profile {name:?} selects {pattern:?}, which is not defined in \
+                             cluster"
+                        )));
+                    }
+                    Ok(_) => results.push(Ok(())),
+                }
+            }
+
+            for pattern in &profile.hosts {
+                if let Err(error) = glob::Pattern::new(pattern) {
+                    results.push(Err(anyhow!(
+                        "Host profile {name:?} has invalid host pattern {pattern:?}: {error}"
+                    )));
+                }
+            }
+        }
+
+        results.into_iter().bcollect::<_>()
+    }
+
+    #[instrument(skip(self), level = "debug")]
+    fn encrypted_check(&self) -> Result<()> {
+        trace!("Perform encrypted file check on cluster");
+        let mut results = Vec::new();
+        for (name, node) in &self.nodes {
+            let Some(paths) = &node.settings.encrypted else {
+                continue;
+            };
+
+            if !matches!(
+                node.settings.deployment.kind,
+                DeploymentKind::Normal
+                    | DeploymentKind::BareAlias
+                    | DeploymentKind::HardLink
+                    | DeploymentKind::Symlink
+                    | DeploymentKind::Copy
+            ) {
+                results.push(Err(anyhow!(
+                    "Node {name:?} lists encrypted files, but only normal, bare-alias, hard-link, \
+                     symlink, and copy nodes can decrypt them"
+                )));
+                continue;
+            }
+
+            for path in paths {
+                let escapes = Path::new(path).is_absolute()
+                    || Path::new(path).components().any(|part| part == Component::ParentDir);
+                if escapes || decrypted_path(path).is_none() {
+                    results.push(Err(anyhow!(
+                        "Encrypted file {path:?} of node {name:?} must be a relative path ending \
+                         in \".age\", \".gpg\", or \".asc\""
+                    )));
+                } else {
+                    results.push(Ok(()));
+                }
+            }
+        }
+
+        results.into_iter().bcollect::<_>()
+    }
+
+    #[instrument(skip(self), level = "debug")]
+    fn template_check(&self) -> Result<()> {
+        trace!("Perform template check on cluster");
+        let mut results = Vec::new();
+        for (name, node) in &self.nodes {
+            if node.settings.templates.is_none() {
+                continue;
+            }
+
+            if matches!(
+                node.settings.deployment.kind,
+                DeploymentKind::Normal
+                    | DeploymentKind::BareAlias
+                    | DeploymentKind::HardLink
+                    | DeploymentKind::Symlink
+                    | DeploymentKind::Copy
+            ) {
+                results.push(Ok(()));
+            } else {
+                results.push(Err(anyhow!(
+                    "Node {name:?} lists templates, but only normal, bare-alias, hard-link, \
+                     symlink, and copy nodes can render them"
+                )));
+            }
+        }
+
+        results.into_iter().bcollect::<_>()
+    }
+
+    #[instrument(skip(self), level = "debug")]
+    fn hook_check(&self) -> Result<()> {
+        trace!("Perform node hook check on cluster");
+        let mut results = Vec::new();
+        for (name, node) in &self.nodes {
+            for script in node.settings.hooks.iter().flat_map(NodeHooks::scripts) {
+                // INVARIANT: Node hook scripts must live inside the repository of their node.
+                if script.as_os_str().is_empty()
+                    || script.components().any(|c| !matches!(c, Component::Normal(_)))
+                {
+                    results.push(Err(anyhow!(
+                        "Node {name:?} hook {script:?} must be a relative path inside its \
+                         repository"
+                    )));
+                } else {
+                    results.push(Ok(()));
+                }
+            }
+        }
+
+        results.into_iter().bcollect::<_>()
+    }
+
+    #[instrument(skip(self), level = "debug")]
+    fn work_dir_alias_check(&self) -> Result<()> {
+        trace!("Perform working directory alias check on cluster");
+        let home = home_dir()?;
+
+        // INVARIANT: Deployment must never overwrite OCD's own files. Aliases may still contain
+        // the configuration directory, because its parent, e.g., "~/.config", is a common
+        // deployment target. The same goes for the home directory nodes deploy to by default.
+        let dirs = [
+            ("configuration", config_dir()?, false),
+            ("data", data_dir()?, true),
+            ("state", state_dir()?, true),
+        ];
+
+        let mut results = Vec::new();
+        for (name, node) in &self.nodes {
+            // INVARIANT: Only bare-alias nodes and nodes placing files themselves deploy into
+            // their own working directory alias.
+            let kind = &node.settings.deployment.kind;
+            if !kind.is_bare_alias() && !kind.places_files() {
+                continue;
+            }
+
+            let alias = node.settings.deployment.work_dir_alias.as_path();
+            for (label, dir, guard_parents) in &dirs {
+                if alias.starts_with(dir) {
+                    results.push(Err(anyhow!(
+                        "Node {name:?} has working directory alias {alias:?} inside OCD's \
+                         {label} directory {dir:?}"
+                    )));
+                } else if *guard_parents && dir.starts_with(alias) && alias != home {
+                    results.push(Err(anyhow!(
+                        "Node {name:?} has working directory alias {alias:?} containing OCD's \
+                         {label} directory {dir:?}"
+                    )));
+                }
+            }
+        }
+
+        results.into_iter().bcollect::<_>()
+    }
+
+    #[instrument(skip(self), level = "debug")]
+    fn exclude_inline_nodes(&mut self) -> Result<()> {
+        trace!("Exclude inline node subdirectories from root deployment");
+        let mut inline: Vec<String> = self
+            .nodes
+            .values_mut()
+            .filter(|node| node.settings.deployment.kind.is_inline())
+            .map(|node| {
+                // INVARIANT: Inline nodes always share the working directory alias of root.
+                node.settings.deployment.work_dir_alias = self.root.settings.work_dir_alias.clone();
+                format!("{}/", node.settings.subdir.as_ref().unwrap().to_string_lossy())
+            })
+            .collect();
+
+        if !inline.is_empty() {
+            inline.sort();
+            self.root.settings.excluded.get_or_insert_with(Vec::new).extend(inline.clone());
+            self.root.settings.inline_subdirs = inline;
+        }
+
+        Ok(())
+    }
+
+    /// Variables that node entries can refer to through `{{ vars.NAME }}` placeholders.
+    ///
+    /// Variables come from the `[vars]` table of root, which is shared along with the rest of the
+    /// cluster. Each machine can override them through the `[vars]` table of `vars.local.toml` in
+    /// the configuration directory, e.g., to use another email address at work. The local file is
+    /// meant to stay untracked, and never shows up in redacted cluster definitions.
+    ///
+    /// # Errors
+    ///
+    /// - Will fail if configuration directory cannot be determined.
+    /// - Will fail if `vars.local.toml` exists, but cannot be read or parsed.
+    pub fn vars(&self) -> Result<BTreeMap<String, String>> {
+        let mut vars = self.root.vars.clone();
+        let path = config_dir()?.join(LOCAL_VARS);
+        if path.exists() {
+            let data = std::fs::read_to_string(&path)
+                .with_context(|| format!("Failed to read {path:?}"))?;
+            let local: LocalVars = check_schema(&data, LOCAL_VARS_SCHEMA)
+                .and_then(|data| Ok(toml::de::from_str(&data)?))
+                .with_context(|| format!("Failed to parse {path:?}"))?;
+            debug!("Override cluster variables {:?} through {path:?}", local.vars.keys());
+            vars.extend(local.vars);
+        }
+
+        Ok(vars)
+    }
+
+    /// Variables that templates of nodes can refer to through `{{ vars.NAME }}` placeholders.
+    ///
+    /// Besides the variables of the cluster, templates know the `hostname` of the machine, its
+    /// `os`, e.g., "linux", and the `user` running OCD. Cluster variables of the same name take
+    /// precedence, such that `vars.local.toml` can correct them.
+    ///
+    /// # Errors
+    ///
+    /// - Will fail if cluster variables cannot be determined, see [`Cluster::vars`].
+    pub fn template_vars(&self) -> Result<BTreeMap<String, String>> {
+        let mut vars = BTreeMap::from([("os".to_string(), std::env::consts::OS.to_string())]);
+        if let Some(hostname) = hostname() {
+            vars.insert("hostname".into(), hostname);
+        }
+        if let Some(user) = ["USER", "USERNAME"].iter().find_map(|key| std::env::var(key).ok()) {
+            vars.insert("user".into(), user);
+        }
+        vars.extend(self.vars()?);
+
+        Ok(vars)
+    }
+
+    #[instrument(skip(self), level = "debug")]
+    fn interpolate_vars(&mut self) -> Result<()> {
+        trace!("Interpolate cluster variables into node entries");
+        let vars = self.vars()?;
+        let mut errors = Vec::new();
+        for (name, node) in &mut self.nodes {
+            if let Err(error) = interpolate_node(&mut node.settings, &vars) {
+                errors.push(anyhow!("Node {name:?} cannot be loaded: {error:#}"));
+            }
+        }
+
+        errors.into_iter().map(Err).bcollect::<Vec<()>>()?;
+        Ok(())
+    }
+
+    #[instrument(skip(self), level = "debug")]
+    fn expand_work_dir_aliases(&mut self) -> Result<()> {
+        trace!("Expand working directory aliases of nodes");
+        for node in self.nodes.values_mut() {
+            node.settings.deployment.work_dir_alias =
+                node.settings.deployment.work_dir_alias.expand()?;
+            if let Some(path) = node.settings.path.as_mut() {
+                *path = PathBuf::from(expand_path(&path.to_string_lossy())?);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// File in configuration directory with machine-local overrides of cluster variables.
+const LOCAL_VARS: &str = "vars.local.toml";
+
+/// Opening delimiter of placeholders, see [`interpolate_vars`].
+const PLACEHOLDER_OPEN: &str = "{{";
+
+/// Closing delimiter of placeholders, see [`interpolate_vars`].
+const PLACEHOLDER_CLOSE: &str = "}}";
+
+/// Schema of `vars.local.toml`.
+const LOCAL_VARS_SCHEMA: Schema = Schema::Table(&[("vars", Schema::Map(&Schema::Value))]);
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct LocalVars {
+    #[serde(default)]
+    vars: BTreeMap<String, String>,
+}
+
+/// Replace `{{ vars.NAME }}` placeholders in text with the values of their variables.
+///
+/// Whitespace inside the braces is optional. Text without placeholders is returned as is.
+///
+/// # Errors
+///
+/// - Will fail if placeholder is never closed.
+/// - Will fail if placeholder does not refer to a variable.
+/// - Will fail if variable is not defined.
+pub fn interpolate_vars(text: &str, vars: &BTreeMap<String, String>) -> Result<String> {
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find(PLACEHOLDER_OPEN) {
+        let (head, tail) = rest.split_at(start);
+        result.push_str(head);
+
+        let tail = &tail[PLACEHOLDER_OPEN.len()..];
+        let end = tail
+            .find(PLACEHOLDER_CLOSE)
+            .ok_or_else(|| anyhow!("Placeholder in {text:?} is never closed"))?;
+        let expression = tail[..end].trim();
+        let name = expression
+            .strip_prefix("vars.")
+            .ok_or_else(|| anyhow!("Placeholder {expression:?} does not refer to a variable"))?;
+        let value = vars.get(name).ok_or_else(|| anyhow!("Variable {name:?} is not defined"))?;
+        result.push_str(value);
+        rest = &tail[end + PLACEHOLDER_CLOSE.len()..];
+    }
+    result.push_str(rest);
+
+    Ok(result)
+}
+
+// INVARIANT: URLs are validated and normalized once their placeholders are replaced.
+fn interpolate_node(
+    settings: &mut NodeEntrySettings,
+    vars: &BTreeMap<String, String>,
+) -> Result<()> {
+    if settings.url.contains(PLACEHOLDER_OPEN) {
+        settings.url = normalize_url(interpolate_vars(&settings.url, vars)?)?;
+    }
+
+    if let Some(NodeSource::Archive { url, .. }) = settings.source.as_mut() {
+        *url = interpolate_vars(url, vars)?;
+    }
+
+    let alias = settings.deployment.work_dir_alias.as_path().to_string_lossy().into_owned();
+    if alias.contains(PLACEHOLDER_OPEN) {
+        settings.deployment.work_dir_alias = WorkDirAlias::new(interpolate_vars(&alias, vars)?);
+    }
+
+    if let Some(path) = settings.path.as_mut() {
+        *path = PathBuf::from(interpolate_vars(&path.to_string_lossy(), vars)?);
+    }
+
+    Ok(())
+}
+
+/// Text that marks exclusion rules as referring to secrets.
+const SECRET_MARKERS: &[&str] = &[
+    "secret",
+    "private",
+    "password",
+    "passwd",
+    "token",
+    "credential",
+    "id_rsa",
+    "id_ed25519",
+    ".ssh",
+    ".gnupg",
+    ".netrc",
+    ".env",
+    ".pem",
+    ".key",
+];
+
+// INVARIANT: Settings that would be left empty are dropped entirely.
+fn redact_settings(settings: &mut toml_edit::Item, private: &[&str]) {
+    let Some(settings) = settings.as_table_like_mut() else {
+        return;
+    };
+
+    // INVARIANT: Paths to external repositories only make sense on this machine.
+    let _ = settings.remove("path");
+    retain_strings(settings, "excluded", |rule| !is_secret_rule(rule));
+    if let Some(profiles) =
+        settings.get_mut("sparse_profiles").and_then(|item| item.as_table_like_mut())
+    {
+        let names: Vec<String> = profiles.iter().map(|(name, _)| name.to_string()).collect();
+        for name in names {
+            retain_strings(profiles, &name, |rule| !is_secret_rule(rule));
+        }
+    }
+    for key in ["dependencies", "after"] {
+        retain_strings(settings, key, |dependency| {
+            !private
+                .iter()
+                .any(|name| dependency == *name || dependency.starts_with(&format!("{name}/")))
+        });
+    }
+}
+
+fn retain_strings(settings: &mut dyn toml_edit::TableLike, key: &str, keep: impl Fn(&str) -> bool) {
+    let Some(array) = settings.get_mut(key).and_then(|item| item.as_array_mut()) else {
+        return;
+    };
+
+    // INVARIANT: Remaining values keep the layout of the array, e.g., one value per line.
+    let prefix = array.get(0).and_then(|value| value.decor().prefix()).cloned();
+    array.retain(|value| value.as_str().is_none_or(&keep));
+    if let (Some(first), Some(prefix)) = (array.get_mut(0), prefix) {
+        first.decor_mut().set_prefix(prefix);
+    }
+
+    if array.is_empty() {
+        let _ = settings.remove(key);
+    }
+}
+
+// INVARIANT: Renamed values keep their decor, e.g., one value per line.
+fn rename_strings(settings: &mut dyn toml_edit::TableLike, key: &str, old: &str, new: &str) {
+    let Some(array) = settings.get_mut(key).and_then(|item| item.as_array_mut()) else {
+        return;
+    };
+
+    for value in array.iter_mut().filter(|value| value.as_str() == Some(old)) {
+        let decor = value.decor().clone();
+        *value = new.into();
+        *value.decor_mut() = decor;
+    }
+}
+
+fn is_secret_rule(rule: &str) -> bool {
+    let rule = rule.to_lowercase();
+    SECRET_MARKERS.iter().any(|marker| rule.contains(marker))
+}
+
+// Returns the largest position of any table in item after shifting.
+fn shift_positions(item: &mut toml_edit::Item, offset: usize) -> usize {
+    let Some(table) = item.as_table_mut() else {
+        return offset;
+    };
+
+    let mut max = offset;
+    if let Some(position) = table.position() {
+        table.set_position(offset + position);
+        max = max.max(offset + position);
+    }
+    for (_, item) in table.iter_mut() {
+        max = max.max(shift_positions(item, offset));
+    }
+
+    max
+}
 
-        // INVARIANT: Queue is empty, but graph has not been fully visited.
-        //   - There exists a cycle.
-        //   - The unvisited nodes represent this cycle.
-        if visited.len() != self.nodes.len() {
-            let cycle: Vec<String> =
-                self.nodes.keys().filter(|key| !visited.contains(*key)).cloned().collect();
-            return Err(anyhow!("Cluster contains cycle(s): {cycle:?}"));
-        }
-        debug!("Topological sort of cluster nodes: {visited:?}");
+/// Deepest level of nesting allowed for nested clusters.
+pub(crate) const MAX_CLUSTER_DEPTH: usize = 8;
 
-        Ok(())
+/// Get directory that definition of nested cluster is deployed to.
+///
+/// Definitions of nested clusters are deployed to `$XDG_CONFIG_HOME/ocd/clusters/<name>`, where
+/// the name of a nested cluster inside another nested cluster is namespaced, e.g., `work/base`.
+///
+/// # Errors
+///
+/// - Will fail if configuration directory cannot be determined.
+pub fn nested_cluster_dir(name: impl AsRef<str>) -> Result<PathBuf> {
+    Ok(config_dir()?.join("clusters").join(name.as_ref()))
+}
+
+// INVARIANT: The name of a node is the file name itself without the extension.
+fn load_nodes(dir: &Path) -> Result<BTreeMap<String, NodeEntry>> {
+    let pattern = dir.join("*.toml").to_string_lossy().into_owned();
+    let mut nodes = BTreeMap::new();
+    for entry in glob::glob(pattern.as_str())? {
+        let path = entry?;
+        let name = path.file_stem().unwrap().to_string_lossy().into_owned();
+
+        debug!("Load node {name:?} at {path:?}");
+        let data =
+            std::fs::read_to_string(&path).with_context(|| format!("Failed to read {path:?}"))?;
+        let node = parse_node(&name, &data, &path)?;
+        nodes.insert(name, node);
     }
 
-    #[instrument(skip(self), level = "debug")]
-    fn expand_work_dir_aliases(&mut self) -> Result<()> {
-        trace!("Expand working directory aliases of nodes");
-        for node in self.nodes.values_mut() {
-            let expand = shellexpand::full(
-                node.settings.deployment.work_dir_alias.0.to_string_lossy().as_ref(),
-            )?
-            .into_owned();
-            node.settings.deployment.work_dir_alias = WorkDirAlias::new(expand);
+    Ok(nodes)
+}
+
+fn parse_root(data: &str, path: &Path) -> Result<RootEntry> {
+    // INVARIANT: Version mismatch takes precedence over any other deserialization failure.
+    check_min_ocd_version(data)?;
+    let data = decrypt_config(data)?;
+    let data = check_schema(&data, ROOT_SCHEMA)
+        .with_context(|| format!("Failed to load root at {path:?}"))?;
+    Config::builder()
+        .add_source(File::from_str(&data, FileFormat::Toml))
+        .build()?
+        .try_deserialize()
+        .with_context(|| format!("Failed to load root at {path:?}"))
+}
+
+fn parse_node(name: &str, data: &str, path: &Path) -> Result<NodeEntry> {
+    let data = decrypt_config(data)?;
+    let data = check_schema(&data, NODE_SCHEMA)
+        .with_context(|| format!("Failed to load node {name:?} at {path:?}"))?;
+    Config::builder()
+        .add_source(File::from_str(&data, FileFormat::Toml))
+        .build()?
+        .try_deserialize()
+        .with_context(|| format!("Failed to load node {name:?} at {path:?}"))
+}
+
+/// Serialize node entry into stable TOML document.
+///
+/// # Errors
+///
+/// Will fail if node entry cannot be serialized.
+pub(crate) fn serialize_node(node: &NodeEntry) -> Result<String> {
+    let mut document: toml_edit::DocumentMut = toml::ser::to_string(node)?.parse()?;
+    if let Some(settings) = document.get_mut("settings").and_then(|item| item.as_table_mut()) {
+        if let Some(deployment) = settings.remove("deployment") {
+            let deployment = deployment
+                .into_table()
+                .map(toml_edit::Table::into_inline_table)
+                .map_err(|_| anyhow!("Node deployment method must be a table"))?;
+            settings.insert("deployment", toml_edit::value(deployment));
+            settings.sort_values_by(|a, _, b, _| setting_rank(a).cmp(&setting_rank(b)));
         }
-        Ok(())
     }
+
+    Ok(document.to_string())
+}
+
+// INVARIANT: Node settings are always written in the same order.
+fn setting_rank(key: &toml_edit::Key) -> usize {
+    const ORDER: &[&str] = &[
+        "deployment",
+        "url",
+        "branch",
+        "tag",
+        "rev",
+        "source",
+        "path",
+        "subdir",
+        "included",
+        "excluded",
+        "encrypted",
+        "templates",
+        "sparse_profiles",
+        "dependencies",
+        "after",
+        "hooks",
+        "shallow_since",
+        "pull_strategy",
+        "private",
+        "description",
+        "homepage",
+    ];
+    ORDER.iter().position(|k| *k == key.get()).unwrap_or(ORDER.len())
 }
 
 /// Iterator for node entry dependencies.
 ///
 /// Obtain a full listing of nodes defined as dependencies of a given target node that was
 /// initially pushed into stack.
+/// Differences between two cluster definitions, see [`Cluster::changes_to`].
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub struct ClusterChanges {
+    /// Whether root entry changed, e.g., its settings or host profiles.
+    pub root: bool,
+
+    /// Node entries only defined by the incoming cluster definition, sorted by name.
+    pub added: Vec<String>,
+
+    /// Node entries missing from the incoming cluster definition, sorted by name.
+    pub removed: Vec<String>,
+
+    /// Node entries defined differently by the incoming cluster definition, sorted by name.
+    pub changed: Vec<String>,
+}
+
+impl ClusterChanges {
+    /// Check if both cluster definitions are the same.
+    pub fn is_empty(&self) -> bool {
+        !self.root && self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
 #[derive(Debug)]
 pub struct DependencyIter<'cluster> {
-    graph: &'cluster HashMap<String, NodeEntry>,
+    graph: &'cluster BTreeMap<String, NodeEntry>,
     visited: HashSet<String>,
     stack: VecDeque<String>,
 }
@@ -250,10 +1714,37 @@ fn next(&mut self) -> Option<Self::Item> {
 ///
 /// Root also has access to the file exclusion feature. The user can specify a list of sparsity
 /// rules to exclude certain files and directories from deployment.
-#[derive(Debug, PartialEq, Eq, Clone, Deserialize)]
+#[derive(Debug, PartialEq, Eq, Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
 pub struct RootEntry {
     /// Deployment options.
     pub settings: RootEntrySettings,
+
+    /// Variables that node entries can refer to through `{{ vars.NAME }}` placeholders.
+    ///
+    /// Machine-local overrides belong in `vars.local.toml`, see [`Cluster::vars`].
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub vars: BTreeMap<String, String>,
+
+    /// Host profiles by name, each selecting the nodes relevant to some machines.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub profile: BTreeMap<String, HostProfile>,
+}
+
+/// Subset of the cluster that is relevant to some machines.
+///
+/// Clones and deployments that use a host profile only operate on the nodes it selects, along
+/// with their dependencies. Host profiles are either picked by name, or selected automatically
+/// for machines whose hostname matches one of their host patterns.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct HostProfile {
+    /// Glob patterns of nodes to select.
+    pub nodes: Vec<String>,
+
+    /// Glob patterns of hostnames that select host profile automatically.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub hosts: Vec<String>,
 }
 
 impl RootEntry {
@@ -276,15 +1767,57 @@ pub fn try_default() -> Result<Self> {
             settings: RootEntrySettings {
                 work_dir_alias: WorkDirAlias::new(config_dir()?),
                 excluded: None,
+                inline_subdirs: Vec::new(),
+                jobs: None,
+                default_branch: None,
+                min_ocd_version: None,
+                deploy_report: None,
+                auto_clone: None,
+                usage_stats: None,
+                name: None,
+                description: None,
+                maintainer: None,
+                notify: None,
+                store_layout: None,
+                pull_strategy: None,
             },
+            vars: BTreeMap::new(),
+            profile: BTreeMap::new(),
         })
     }
 }
 
+/// Schema of `root.toml`, see [`RootEntry`].
+pub(crate) const ROOT_SCHEMA: Schema = Schema::Table(&[
+    (
+        "settings",
+        Schema::Table(&[
+            ("work_dir_alias", Schema::Value),
+            ("excluded", Schema::Value),
+            ("jobs", Schema::Value),
+            ("default_branch", Schema::Value),
+            ("min_ocd_version", Schema::Value),
+            ("deploy_report", Schema::Value),
+            ("auto_clone", Schema::Value),
+            ("usage_stats", Schema::Value),
+            ("name", Schema::Value),
+            ("description", Schema::Value),
+            ("maintainer", Schema::Value),
+            ("notify", Schema::Value),
+            ("store_layout", Schema::Value),
+            ("pull_strategy", Schema::Value),
+        ]),
+    ),
+    ("vars", Schema::Map(&Schema::Value)),
+    ("profile", Schema::Map(&Schema::Table(&[("nodes", Schema::Value), ("hosts", Schema::Value)]))),
+]);
+
 /// Builder for [`RootEntry`].
 #[derive(Debug)]
 pub struct RootEntryBuilder {
     settings: RootEntrySettings,
+    vars: BTreeMap<String, String>,
+    profile: BTreeMap<String, HostProfile>,
 }
 
 impl RootEntryBuilder {
@@ -298,7 +1831,22 @@ pub fn new() -> Result<Self> {
             settings: RootEntrySettings {
                 work_dir_alias: WorkDirAlias::new(config_dir()?),
                 excluded: None,
+                inline_subdirs: Vec::new(),
+                jobs: None,
+                default_branch: None,
+                min_ocd_version: None,
+                deploy_report: None,
+                auto_clone: None,
+                usage_stats: None,
+                name: None,
+                description: None,
+                maintainer: None,
+                notify: None,
+                store_layout: None,
+                pull_strategy: None,
             },
+            vars: BTreeMap::new(),
+            profile: BTreeMap::new(),
         })
     }
 
@@ -328,21 +1876,312 @@ pub fn excluded(mut self, rules: impl IntoIterator<Item = impl Into<String>>) ->
         self
     }
 
+    /// Set number of jobs to use for concurrent node operations.
+    pub fn jobs(mut self, jobs: usize) -> Self {
+        self.settings.jobs = Some(jobs);
+        self
+    }
+
+    /// Set name of initial branch for newly initialized entries.
+    pub fn default_branch(mut self, branch: impl Into<String>) -> Self {
+        self.settings.default_branch = Some(branch.into());
+        self
+    }
+
+    /// Set file to write report of deployed entries into after each deployment.
+    pub fn deploy_report(mut self, path: impl Into<PathBuf>) -> Self {
+        self.settings.deploy_report = Some(path.into());
+        self
+    }
+
+    /// Set whether nodes missing from repository store are cloned on demand.
+    pub fn auto_clone(mut self, policy: ClonePolicy) -> Self {
+        self.settings.auto_clone = Some(policy);
+        self
+    }
+
+    /// Set whether usage statistics are recorded locally.
+    pub fn usage_stats(mut self, enabled: bool) -> Self {
+        self.settings.usage_stats = Some(enabled);
+        self
+    }
+
+    /// Name cluster, e.g., to tell clusters apart on machines that use more than one.
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.settings.name = Some(name.into());
+        self
+    }
+
+    /// Describe what cluster configures.
+    pub fn description(mut self, description: impl Into<String>) -> Self {
+        self.settings.description = Some(description.into());
+        self
+    }
+
+    /// Set who maintains cluster, e.g., "Jane Doe <jane@example.com>".
+    pub fn maintainer(mut self, maintainer: impl Into<String>) -> Self {
+        self.settings.maintainer = Some(maintainer.into());
+        self
+    }
+
+    /// Set command to run when deployment fails or a check detects drift.
+    pub fn notify(mut self, command: impl Into<String>) -> Self {
+        self.settings.notify = Some(command.into());
+        self
+    }
+
+    /// Set where node repositories are kept in the repository store.
+    pub fn store_layout(mut self, layout: StoreLayout) -> Self {
+        self.settings.store_layout = Some(layout);
+        self
+    }
+
+    /// Set what `ocd pull` does with entries that diverged from their upstream.
+    pub fn pull_strategy(mut self, strategy: PullStrategy) -> Self {
+        self.settings.pull_strategy = Some(strategy);
+        self
+    }
+
+    /// Set variable that node entries can refer to.
+    pub fn var(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.vars.insert(name.into(), value.into());
+        self
+    }
+
+    /// Define host profile.
+    pub fn profile(mut self, name: impl Into<String>, profile: HostProfile) -> Self {
+        self.profile.insert(name.into(), profile);
+        self
+    }
+
     /// Build new [`RootEntry`].
     pub fn build(self) -> RootEntry {
-        RootEntry { settings: self.settings }
+        RootEntry { settings: self.settings, vars: self.vars, profile: self.profile }
     }
 }
 
 /// Deployment options for root entry.
-#[derive(Debug, PartialEq, Eq, Clone, Deserialize)]
+#[derive(Debug, PartialEq, Eq, Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
 pub struct RootEntrySettings {
     /// Working directory alias option.
-    #[serde(deserialize_with = "deserialize_root_work_dir_alias")]
+    #[serde(
+        deserialize_with = "deserialize_root_work_dir_alias",
+        serialize_with = "serialize_root_work_dir_alias"
+    )]
     pub work_dir_alias: WorkDirAlias,
 
     /// List of sparsity rules to exclude files from deployment.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub excluded: Option<Vec<String>>,
+
+    /// Subdirectories of inline nodes, which root never deploys, not even with excluded files.
+    ///
+    /// Filled in whenever the cluster definition is loaded, rather than read from `root.toml`.
+    #[serde(skip)]
+    pub inline_subdirs: Vec<String>,
+
+    /// Number of jobs to use for commands that operate on nodes concurrently.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub jobs: Option<usize>,
+
+    /// Name of initial branch for newly initialized entries.
+    ///
+    /// Falls back to `init.defaultBranch` of the user's Git configuration if unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_branch: Option<String>,
+
+    /// Oldest version of OCD that understands the cluster definition.
+    ///
+    /// Checked before anything else in the cluster definition is deserialized, such that settings
+    /// introduced by newer versions of OCD are reported as a version mismatch rather than as
+    /// unknown settings.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_ocd_version: Option<String>,
+
+    /// File to write report of deployed entries into after each deployment.
+    ///
+    /// Tilde and shell variables are expanded upon use. Shell initialization scripts can read the
+    /// report to figure out which configurations are active.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deploy_report: Option<PathBuf>,
+
+    /// Whether nodes missing from repository store are cloned on demand, e.g., upon deployment.
+    ///
+    /// Clones them without asking by default.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auto_clone: Option<ClonePolicy>,
+
+    /// Whether usage statistics are recorded in OCD's state directory.
+    ///
+    /// Statistics never leave the machine, and are off by default.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage_stats: Option<bool>,
+
+    /// Human-readable name of cluster.
+    ///
+    /// Removing root of a named cluster asks for its name instead of a plain yes or no.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+
+    /// Short human-readable description of cluster.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+
+    /// Who maintains cluster, e.g., for clusters shared among several users.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub maintainer: Option<String>,
+
+    /// Command to run when deployment fails or a check detects drift.
+    ///
+    /// Receives a JSON payload describing what happened through `$OCD_EVENT`, see
+    /// [`crate::notify`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notify: Option<String>,
+
+    /// Where node repositories are kept in the repository store.
+    ///
+    /// Keeps each node at its name by default.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub store_layout: Option<StoreLayout>,
+
+    /// What `ocd pull` does with entries that diverged from their upstream.
+    ///
+    /// Node entries can override it through their own `pull_strategy` setting. Diverged entries
+    /// are skipped by default.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pull_strategy: Option<PullStrategy>,
+}
+
+/// Policies for cloning nodes that are missing from repository store on demand.
+#[derive(Default, Copy, Clone, Debug, PartialEq, Eq, Deserialize, Serialize, ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum ClonePolicy {
+    /// Clone missing nodes no questions asked.
+    #[default]
+    Always,
+
+    /// Ask before cloning each missing node, showing where it is cloned from.
+    Prompt,
+
+    /// Never clone missing nodes, failing instead.
+    Never,
+}
+
+/// Layouts of the repository store, i.e., where node repositories are kept in it.
+#[derive(Default, Copy, Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StoreLayout {
+    /// Keep each node at its name, e.g., "nvim".
+    #[default]
+    Name,
+
+    /// Keep each node at the host and path of its URL, e.g., "github.com/user/nvim".
+    ///
+    /// Nodes of the same name from different forges never collide this way. Nodes whose URL has
+    /// no host, e.g., local paths, are still kept at their name.
+    Forge,
+}
+
+impl fmt::Display for StoreLayout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Name => write!(f, "name"),
+            Self::Forge => write!(f, "forge"),
+        }
+    }
+}
+
+/// Strategies for pulling entries whose local branch diverged from its upstream, i.e., that
+/// cannot be fast-forwarded.
+#[derive(Default, Copy, Clone, Debug, PartialEq, Eq, Deserialize, Serialize, ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum PullStrategy {
+    /// Rebase local commits onto upstream.
+    Rebase,
+
+    /// Merge upstream into local branch.
+    Merge,
+
+    /// Leave diverged entries as they are.
+    #[default]
+    Skip,
+
+    /// Ask which of the other strategies to use for each diverged entry.
+    Prompt,
+}
+
+impl fmt::Display for PullStrategy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Rebase => write!(f, "rebase"),
+            Self::Merge => write!(f, "merge"),
+            Self::Skip => write!(f, "skip"),
+            Self::Prompt => write!(f, "prompt"),
+        }
+    }
+}
+
+/// Make sure that running version of OCD understands root configuration file.
+///
+/// Only looks at the `min_ocd_version` setting, ignoring everything else, such that root
+/// configuration files written for newer versions of OCD can still be checked.
+///
+/// # Errors
+///
+/// - Will fail if `min_ocd_version` is not a valid version.
+/// - Will fail if `min_ocd_version` is newer than running version of OCD.
+pub(crate) fn check_min_ocd_version(root: &str) -> Result<()> {
+    // INVARIANT: Malformed files are reported by regular deserialization instead.
+    let Ok(table) = root.parse::<toml::Table>() else {
+        return Ok(());
+    };
+    let Some(min) = table.get("settings").and_then(|settings| settings.get("min_ocd_version"))
+    else {
+        return Ok(());
+    };
+
+    let min = min.as_str().ok_or(anyhow!("Root setting min_ocd_version must be a string"))?;
+    let current = env!("CARGO_PKG_VERSION");
+    if parse_version(min)? > parse_version(current)? {
+        return Err(anyhow!(
+            "Cluster requires ocd >= {min}, but this is ocd {current}, upgrade ocd to use it"
+        ));
+    }
+
+    Ok(())
+}
+
+fn parse_version(version: &str) -> Result<(u64, u64, u64)> {
+    let invalid = || anyhow!("Invalid version {version:?}, expected <major>.<minor>.<patch>");
+
+    // INVARIANT: Pre-release and build metadata never make a version incompatible.
+    let core = version.trim().trim_start_matches('v');
+    let core = core.split(['-', '+']).next().unwrap_or_default();
+    let mut parts = core.split('.').map(|part| part.parse::<u64>().map_err(|_| invalid()));
+    let major = parts.next().ok_or_else(invalid)??;
+    let minor = parts.next().transpose()?.unwrap_or(0);
+    let patch = parts.next().transpose()?.unwrap_or(0);
+    if parts.next().is_some() {
+        return Err(invalid());
+    }
+
+    Ok((major, minor, patch))
+}
+
+fn deserialize_url<'de, D>(deserializer: D) -> Result<String, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let result: String = Deserialize::deserialize(deserializer)?;
+
+    // INVARIANT: Inline nodes have no URL of their own, encrypted URLs cannot be checked, and
+    // URLs with placeholders are checked once variables are interpolated.
+    if result.is_empty() || is_encrypted(&result) || result.contains(PLACEHOLDER_OPEN) {
+        return Ok(result);
+    }
+
+    normalize_url(&result).map_err(serde::de::Error::custom)
 }
 
 fn deserialize_root_work_dir_alias<'de, D>(deserializer: D) -> Result<WorkDirAlias, D::Error>
@@ -357,6 +2196,55 @@ fn deserialize_root_work_dir_alias<'de, D>(deserializer: D) -> Result<WorkDirAli
     }
 }
 
+fn serialize_root_work_dir_alias<S>(alias: &WorkDirAlias, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    if alias.0 == home_dir().map_err(serde::ser::Error::custom)? {
+        serializer.serialize_str("home_dir")
+    } else {
+        serializer.serialize_str("config_dir")
+    }
+}
+
+/// Schema of node configuration files, see [`NodeEntry`].
+pub(crate) const NODE_SCHEMA: Schema = Schema::Table(&[(
+    "settings",
+    Schema::Table(&[
+        (
+            "deployment",
+            Schema::Table(&[("kind", Schema::Value), ("work_dir_alias", Schema::Value)]),
+        ),
+        ("url", Schema::Value),
+        ("excluded", Schema::Value),
+        ("included", Schema::Value),
+        ("encrypted", Schema::Value),
+        ("templates", Schema::Value),
+        ("dependencies", Schema::Value),
+        ("after", Schema::Value),
+        ("hooks", Schema::Table(&[("pre_deploy", Schema::Value), ("post_deploy", Schema::Value)])),
+        ("subdir", Schema::Value),
+        (
+            "source",
+            Schema::Table(&[
+                ("type", Schema::Value),
+                ("url", Schema::Value),
+                ("sha256", Schema::Value),
+            ]),
+        ),
+        ("path", Schema::Value),
+        ("private", Schema::Value),
+        ("description", Schema::Value),
+        ("homepage", Schema::Value),
+        ("sparse_profiles", Schema::Map(&Schema::Value)),
+        ("shallow_since", Schema::Value),
+        ("pull_strategy", Schema::Value),
+        ("branch", Schema::Value),
+        ("tag", Schema::Value),
+        ("rev", Schema::Value),
+    ]),
+)]);
+
 /// Node entry of cluster.
 ///
 /// A cluster typically contains a series of nodes. A given node entry can either be normal or
@@ -369,7 +2257,13 @@ fn deserialize_root_work_dir_alias<'de, D>(deserializer: D) -> Result<WorkDirAli
 /// Thus, each node can have a listing of sparsity rules to exclude files and directories from
 /// deployment, and a listing of other nodes as dependencies that must be deployed with the node
 /// itself.
-#[derive(Debug, PartialEq, Eq, Clone, Deserialize)]
+///
+/// A node can also be _inline_, which means that its files live in a subdirectory of the root
+/// repository rather than a repository of its own. Inline nodes do not need a URL, and always
+/// share the working directory alias of root, such that their files are deployed to the subdirectory
+/// they occupy within root's working directory alias.
+#[derive(Debug, PartialEq, Eq, Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
 pub struct NodeEntry {
     pub settings: NodeEntrySettings,
 }
@@ -383,6 +2277,47 @@ impl NodeEntry {
     pub fn builder() -> Result<NodeEntryBuilder> {
         NodeEntryBuilder::new()
     }
+
+    /// Summarize human-readable metadata of node entry, e.g., "Editor of choice <https://...>".
+    ///
+    /// Returns [`None`] if node entry has neither a description nor a homepage.
+    pub fn about(&self) -> Option<String> {
+        match (&self.settings.description, &self.settings.homepage) {
+            (Some(description), Some(homepage)) => Some(format!("{description} <{homepage}>")),
+            (Some(description), None) => Some(description.clone()),
+            (None, Some(homepage)) => Some(format!("<{homepage}>")),
+            (None, None) => None,
+        }
+    }
+
+    /// Check if node entry lives in external repository outside the repository store.
+    pub fn is_external(&self) -> bool {
+        self.settings.path.is_some()
+    }
+
+    /// Reference that node entry is pinned to, if any.
+    ///
+    /// Cluster definitions never pin more than one reference per node entry, but if they do
+    /// anyway, branches win over tags, and tags win over revisions.
+    pub fn pin(&self) -> Option<NodePin> {
+        let settings = &self.settings;
+        settings
+            .branch
+            .clone()
+            .map(NodePin::Branch)
+            .or_else(|| settings.tag.clone().map(NodePin::Tag))
+            .or_else(|| settings.rev.clone().map(NodePin::Rev))
+    }
+
+    /// Copy node entry with its exclusion rules replaced by those of named sparse profile.
+    ///
+    /// Returns [`None`] if node entry does not define sparse profile.
+    pub fn with_sparse_profile(&self, profile: &str) -> Option<Self> {
+        let rules = self.settings.sparse_profiles.as_ref()?.get(profile)?;
+        let mut entry = self.clone();
+        entry.settings.excluded = Some(rules.clone());
+        Some(entry)
+    }
 }
 
 /// Builder for [`NodeEntry`]
@@ -406,7 +2341,24 @@ pub fn new() -> Result<Self> {
                 },
                 url: String::default(),
                 excluded: None,
+                included: None,
+                encrypted: None,
+                templates: None,
                 dependencies: None,
+                after: None,
+                hooks: None,
+                subdir: None,
+                source: None,
+                path: None,
+                private: false,
+                description: None,
+                homepage: None,
+                sparse_profiles: None,
+                shallow_since: None,
+                pull_strategy: None,
+                branch: None,
+                tag: None,
+                rev: None,
             },
         })
     }
@@ -429,46 +2381,315 @@ pub fn excluded(mut self, rules: impl IntoIterator<Item = impl Into<String>>) ->
         self
     }
 
+    /// Set inclusion rules that files must match to be deployed for node entry.
+    pub fn included(mut self, rules: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.settings.included = Some(rules.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Set files encrypted with age or GnuPG that deployment decrypts.
+    pub fn encrypted(mut self, paths: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.settings.encrypted = Some(paths.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Set sparsity rules matching templates that deployment renders.
+    pub fn templates(mut self, rules: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.settings.templates = Some(rules.into_iter().map(Into::into).collect());
+        self
+    }
+
     /// Set dependencies to be deployed with node entry.
     pub fn dependencies(mut self, nodes: impl IntoIterator<Item = impl Into<String>>) -> Self {
         self.settings.dependencies = Some(nodes.into_iter().map(Into::into).collect());
         self
     }
 
+    /// Set nodes that must be deployed before node entry whenever both are.
+    pub fn after(mut self, nodes: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.settings.after = Some(nodes.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Set hook scripts that run whenever node entry gets deployed.
+    pub fn hooks(mut self, hooks: NodeHooks) -> Self {
+        self.settings.hooks = Some(hooks);
+        self
+    }
+
+    /// Set subdirectory of root repository that houses inline node entry.
+    pub fn subdir(mut self, path: impl Into<PathBuf>) -> Self {
+        self.settings.subdir = Some(path.into());
+        self
+    }
+
+    /// Set source to populate node entry from instead of cloning its URL.
+    pub fn source(mut self, source: NodeSource) -> Self {
+        self.settings.source = Some(source);
+        self
+    }
+
+    /// Use existing repository outside the repository store for node entry.
+    pub fn path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.settings.path = Some(path.into());
+        self
+    }
+
+    /// Mark node entry as private, keeping it out of redacted cluster definitions.
+    pub fn private(mut self, private: bool) -> Self {
+        self.settings.private = private;
+        self
+    }
+
+    /// Describe node entry in human-readable terms.
+    pub fn description(mut self, description: impl Into<String>) -> Self {
+        self.settings.description = Some(description.into());
+        self
+    }
+
+    /// Point node entry to homepage of what it configures.
+    pub fn homepage(mut self, homepage: impl Into<String>) -> Self {
+        self.settings.homepage = Some(homepage.into());
+        self
+    }
+
+    /// Add named set of sparsity rules that can replace exclusion rules upon deployment.
+    pub fn sparse_profile(
+        mut self,
+        name: impl Into<String>,
+        rules: impl IntoIterator<Item = impl Into<String>>,
+    ) -> Self {
+        self.settings
+            .sparse_profiles
+            .get_or_insert_with(BTreeMap::new)
+            .insert(name.into(), rules.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Prune history of node entry back to date through `ocd gc --prune-history`.
+    pub fn shallow_since(mut self, date: impl Into<String>) -> Self {
+        self.settings.shallow_since = Some(date.into());
+        self
+    }
+
+    /// Set what `ocd pull` does with node entry once it diverged from its upstream.
+    pub fn pull_strategy(mut self, strategy: PullStrategy) -> Self {
+        self.settings.pull_strategy = Some(strategy);
+        self
+    }
+
+    /// Pin node entry to reference.
+    pub fn pin(mut self, pin: NodePin) -> Self {
+        let settings = &mut self.settings;
+        (settings.branch, settings.tag, settings.rev) = match pin {
+            NodePin::Branch(branch) => (Some(branch), None, None),
+            NodePin::Tag(tag) => (None, Some(tag), None),
+            NodePin::Rev(rev) => (None, None, Some(rev)),
+        };
+        self
+    }
+
     /// Build new [`NodeEntry`].
     pub fn build(self) -> NodeEntry {
         NodeEntry { settings: self.settings }
     }
 }
 
-/// Settings for node entry.
-#[derive(Debug, PartialEq, Eq, Clone, Deserialize)]
-pub struct NodeEntrySettings {
-    /// Deployment method for node entry.
-    #[serde(deserialize_with = "deserialize_node_deployment")]
-    pub deployment: NodeEntryDeployment,
-
-    /// URL to clone node entry from.
-    pub url: String,
+/// Settings for node entry.
+#[derive(Debug, PartialEq, Eq, Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct NodeEntrySettings {
+    /// Deployment method for node entry.
+    pub deployment: NodeEntryDeployment,
+
+    /// URL to clone node entry from.
+    ///
+    /// Validated and normalized on deserialization, see [`normalize_url`].
+    #[serde(
+        default,
+        deserialize_with = "deserialize_url",
+        skip_serializing_if = "String::is_empty"
+    )]
+    pub url: String,
+
+    /// List of sparisty rules to exclude files from deployment.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub excluded: Option<Vec<String>>,
+
+    /// List of sparsity rules that files must match to be deployed at all.
+    ///
+    /// Excluded files are left out even if they match.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub included: Option<Vec<String>>,
+
+    /// List of files encrypted with age or GnuPG, which deployment decrypts next to themselves.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encrypted: Option<Vec<String>>,
+
+    /// List of sparsity rules matching templates, which deployment renders next to themselves.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub templates: Option<Vec<String>>,
+
+    /// List of other nodes to be deployed as dependencies with this node entry.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dependencies: Option<Vec<String>>,
+
+    /// List of other nodes that must be deployed before this node entry whenever both are.
+    ///
+    /// Only orders deployment, without pulling listed nodes in as dependencies. Nodes not defined
+    /// in cluster are ignored.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub after: Option<Vec<String>>,
+
+    /// Hook scripts living inside the repository of node entry, run whenever it gets deployed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hooks: Option<NodeHooks>,
+
+    /// Subdirectory of root repository housing inline node entry.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub subdir: Option<PathBuf>,
+
+    /// Source to populate node entry from instead of cloning its URL.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<NodeSource>,
+
+    /// Existing repository outside the repository store that normal node entry lives in.
+    ///
+    /// OCD operates on external repositories in place, and never clones or removes them, such
+    /// that they can be developed on like any other project. Tilde and shell variables are
+    /// expanded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<PathBuf>,
+
+    /// Keep node entry out of redacted cluster definitions, see [`Cluster::share`].
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub private: bool,
+
+    /// Short human-readable description of node entry.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+
+    /// Homepage of project that node entry configures, or of node entry itself.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub homepage: Option<String>,
+
+    /// Named sets of sparsity rules that replace `excluded` when selected upon deployment.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sparse_profiles: Option<BTreeMap<String, Vec<String>>>,
+
+    /// Date that history of node entry gets pruned back to, in any format "git fetch
+    /// --shallow-since" accepts.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub shallow_since: Option<String>,
+
+    /// What `ocd pull` does with node entry once it diverged from its upstream, overriding the
+    /// `pull_strategy` setting of root.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pull_strategy: Option<PullStrategy>,
+
+    /// Branch to clone and pull node entry from, instead of the default branch of its remote.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub branch: Option<String>,
+
+    /// Tag to keep node entry at.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tag: Option<String>,
+
+    /// Revision, e.g., a commit ID, to keep node entry at.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rev: Option<String>,
+}
+
+/// Reference that node entry is pinned to.
+///
+/// Nodes pinned to a branch follow that branch like any other upstream. Nodes pinned to a tag or
+/// revision have their HEAD detached at its commit, where pulls keep them.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum NodePin {
+    /// Branch of remote.
+    Branch(String),
+
+    /// Tag of remote.
+    Tag(String),
+
+    /// Revision in any form "git rev-parse" accepts, typically a commit ID.
+    Rev(String),
+}
+
+impl fmt::Display for NodePin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Branch(branch) => write!(f, "branch {branch:?}"),
+            Self::Tag(tag) => write!(f, "tag {tag:?}"),
+            Self::Rev(rev) => write!(f, "revision {rev:?}"),
+        }
+    }
+}
+
+/// Hook scripts of node entry, run whenever node entry gets deployed.
+///
+/// Unlike command hooks, these scripts live inside the repository of the node entry itself, such
+/// that they travel along with the files they set up. Paths are relative to the top of the
+/// repository, or to the subdirectory of inline node entries. Scripts are read from HEAD, and run
+/// at the working tree of the node entry, i.e., its working directory alias if it has one.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct NodeHooks {
+    /// Script to execute _before_ node entry gets deployed, vetoing its deployment by failing.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pre_deploy: Option<PathBuf>,
+
+    /// Script to execute _after_ node entry got deployed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub post_deploy: Option<PathBuf>,
+}
 
-    /// List of sparisty rules to exclude files from deployment.
-    pub excluded: Option<Vec<String>>,
+impl NodeHooks {
+    /// Iterate through scripts of node hooks.
+    pub fn scripts(&self) -> impl Iterator<Item = &PathBuf> {
+        self.pre_deploy.iter().chain(&self.post_deploy)
+    }
+}
 
-    /// List of other nodes to be deployed as dependencies with this node entry.
-    pub dependencies: Option<Vec<String>>,
+/// Alternative source of node entry contents.
+///
+/// Some configurations are not distributed as Git repositories. A node entry with a source is not
+/// cloned, but populated from its source as a brand-new local repository in the repository store
+/// instead, such that its contents can still be deployed and excluded like any other node.
+#[derive(Debug, PartialEq, Eq, Clone, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum NodeSource {
+    /// Tarball, e.g., a release artifact, that is downloaded, verified, and unpacked.
+    Archive {
+        /// URL or local path to fetch archive from.
+        url: String,
+
+        /// Expected SHA-256 digest of archive as hexadecimal.
+        sha256: String,
+    },
 }
 
 /// Node deployment method.
 ///
-/// Currently, there are only two kinds of node deployment:
+/// Currently, there are five kinds of node deployment:
 ///
 /// 1. Normal deployment kind.
 /// 2. Bare-alias deployment kind.
+/// 3. Inline deployment kind.
+/// 4. Hard-link deployment kind.
+/// 5. Mirror deployment kind.
 ///
 /// Normal deployment simply ensures that the node entry has been cloned into repository store.
 /// Bare-alias deployment not only ensures that node entry has been cloned into repository store,
-/// but is also properly deployed to target working directory alias.
-#[derive(Debug, PartialEq, Eq, Clone, Deserialize)]
+/// but is also properly deployed to target working directory alias. Inline deployment deploys a
+/// subdirectory of the root repository through root's sparsity rules. Hard-link deployment links
+/// files of an internal checkout into the working directory alias. Mirror deployment keeps a
+/// bare mirror of all references of the remote, and is skipped by deployment entirely.
+///
+/// Can be deserialized from either a map of its fields, or a string of the form `<kind>` or
+/// `<kind>:<work_dir_alias>`, e.g., `"bare-alias:~/"`. When no working directory alias is given,
+/// the user's home directory is used.
+#[derive(Debug, PartialEq, Eq, Clone, Serialize)]
 pub struct NodeEntryDeployment {
     /// Deployment kind.
     pub kind: DeploymentKind,
@@ -477,20 +2698,38 @@ pub struct NodeEntryDeployment {
     pub work_dir_alias: WorkDirAlias,
 }
 
+impl NodeEntryDeployment {
+    /// Construct new node deployment method.
+    pub fn new(kind: DeploymentKind, work_dir_alias: WorkDirAlias) -> Self {
+        Self { kind, work_dir_alias }
+    }
+}
+
 impl FromStr for NodeEntryDeployment {
     type Err = anyhow::Error;
 
     fn from_str(data: &str) -> Result<Self, Self::Err> {
-        let (kind, work_dir_alias) = match data {
-            "normal" => (DeploymentKind::Normal, WorkDirAlias::try_default()?),
-            "bare_alias" => (DeploymentKind::BareAlias, WorkDirAlias::new(home_dir()?)),
-            _ => return Err(anyhow!("Invalid deployment kind")),
+        let (kind, work_dir_alias) = match data.split_once(':') {
+            Some((kind, path)) => (kind.parse()?, path.parse()?),
+            None => (data.parse()?, WorkDirAlias::try_default()?),
         };
 
         Ok(NodeEntryDeployment { kind, work_dir_alias })
     }
 }
 
+impl fmt::Display for NodeEntryDeployment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.kind, self.work_dir_alias)
+    }
+}
+
+#[derive(Deserialize)]
+struct NodeEntryDeploymentMap {
+    kind: DeploymentKind,
+    work_dir_alias: WorkDirAlias,
+}
+
 struct NodeEntryDeploymentVisitor(PhantomData<fn() -> NodeEntryDeployment>);
 
 impl<'de> Visitor<'de> for NodeEntryDeploymentVisitor {
@@ -511,19 +2750,26 @@ fn visit_map<M>(self, map: M) -> Result<NodeEntryDeployment, M::Error>
     where
         M: MapAccess<'de>,
     {
-        Deserialize::deserialize(serde::de::value::MapAccessDeserializer::new(map))
+        let NodeEntryDeploymentMap { kind, work_dir_alias } =
+            Deserialize::deserialize(serde::de::value::MapAccessDeserializer::new(map))?;
+        Ok(NodeEntryDeployment { kind, work_dir_alias })
     }
 }
 
-fn deserialize_node_deployment<'de, D>(deserializer: D) -> Result<NodeEntryDeployment, D::Error>
-where
-    D: Deserializer<'de>,
-{
-    deserializer.deserialize_any(NodeEntryDeploymentVisitor(PhantomData))
+impl<'de> Deserialize<'de> for NodeEntryDeployment {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(NodeEntryDeploymentVisitor(PhantomData))
+    }
 }
 
 /// Variants of node deployment.
-#[derive(Debug, PartialEq, Eq, Clone, Deserialize)]
+///
+/// Parses from, and displays as, the kebab-case name of a given variant, e.g., `"bare-alias"`.
+/// Parsing also accepts the snake_case names used in configuration files, e.g., `"bare_alias"`.
+#[derive(Debug, PartialEq, Eq, Clone, Deserialize, Serialize)]
 #[serde(deny_unknown_fields, rename_all = "snake_case")]
 pub enum DeploymentKind {
     /// Node is normal, so make sure it got cloned.
@@ -531,19 +2777,120 @@ pub enum DeploymentKind {
 
     /// Node is bare-alias, make sure it got cloned, and is deployed to working directory alias.
     BareAlias,
+
+    /// Node lives in subdirectory of root, and is deployed through root's sparsity rules.
+    Inline,
+
+    /// Node is normal, with files of its internal checkout hard-linked into working directory
+    /// alias, falling back to copies across filesystems.
+    HardLink,
+
+    /// Node is normal, with files of its internal checkout symlinked into working directory
+    /// alias, the way GNU Stow does.
+    Symlink,
+
+    /// Node is normal, with files of its internal checkout copied into working directory alias,
+    /// e.g., for filesystems that cannot link files.
+    Copy,
+
+    /// Node is a bare mirror of its remote, kept around for backup, and never deployed.
+    Mirror,
+
+    /// Node is the root of a nested cluster, whose node entries become part of this cluster.
+    Cluster,
 }
 
 impl DeploymentKind {
+    /// Determine if deployment kind is bare-alias.
     pub fn is_bare_alias(&self) -> bool {
         match self {
-            DeploymentKind::Normal => false,
+            DeploymentKind::Normal
+            | DeploymentKind::Inline
+            | DeploymentKind::HardLink
+            | DeploymentKind::Symlink
+            | DeploymentKind::Copy
+            | DeploymentKind::Mirror
+            | DeploymentKind::Cluster => false,
             DeploymentKind::BareAlias => true,
         }
     }
+
+    /// Determine if deployment kind is inline.
+    pub fn is_inline(&self) -> bool {
+        matches!(self, DeploymentKind::Inline)
+    }
+
+    /// Determine if deployment kind is hard-link.
+    pub fn is_hard_link(&self) -> bool {
+        matches!(self, DeploymentKind::HardLink)
+    }
+
+    /// Determine if deployment kind is symlink.
+    pub fn is_symlink(&self) -> bool {
+        matches!(self, DeploymentKind::Symlink)
+    }
+
+    /// Determine if deployment kind is copy.
+    pub fn is_copy(&self) -> bool {
+        matches!(self, DeploymentKind::Copy)
+    }
+
+    /// Determine if deployment kind places files of its internal checkout into working directory
+    /// alias itself, i.e., is hard-link, symlink, or copy.
+    pub fn places_files(&self) -> bool {
+        self.is_hard_link() || self.is_symlink() || self.is_copy()
+    }
+
+    /// Determine if deployment kind is mirror.
+    pub fn is_mirror(&self) -> bool {
+        matches!(self, DeploymentKind::Mirror)
+    }
+
+    /// Determine if deployment kind is nested cluster.
+    pub fn is_cluster(&self) -> bool {
+        matches!(self, DeploymentKind::Cluster)
+    }
+}
+
+impl FromStr for DeploymentKind {
+    type Err = anyhow::Error;
+
+    fn from_str(data: &str) -> Result<Self, Self::Err> {
+        match data {
+            "normal" => Ok(DeploymentKind::Normal),
+            "bare-alias" | "bare_alias" => Ok(DeploymentKind::BareAlias),
+            "inline" => Ok(DeploymentKind::Inline),
+            "hard-link" | "hard_link" => Ok(DeploymentKind::HardLink),
+            "symlink" => Ok(DeploymentKind::Symlink),
+            "copy" => Ok(DeploymentKind::Copy),
+            "mirror" => Ok(DeploymentKind::Mirror),
+            "cluster" => Ok(DeploymentKind::Cluster),
+            _ => Err(anyhow!("Invalid deployment kind {data:?}")),
+        }
+    }
+}
+
+impl fmt::Display for DeploymentKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = match self {
+            DeploymentKind::Normal => "normal",
+            DeploymentKind::BareAlias => "bare-alias",
+            DeploymentKind::Inline => "inline",
+            DeploymentKind::HardLink => "hard-link",
+            DeploymentKind::Symlink => "symlink",
+            DeploymentKind::Copy => "copy",
+            DeploymentKind::Mirror => "mirror",
+            DeploymentKind::Cluster => "cluster",
+        };
+        f.write_str(kind)
+    }
 }
 
 /// Working directory alias path.
-#[derive(Debug, PartialEq, Eq, Clone, Deserialize)]
+///
+/// Parses from, and displays as, a plain path. Paths are kept as-is until [`WorkDirAlias::expand`]
+/// is called, which the cluster definition does for every node upon construction.
+#[derive(Debug, PartialEq, Eq, Clone, Deserialize, Serialize)]
 pub struct WorkDirAlias(pub(crate) PathBuf);
 
 impl WorkDirAlias {
@@ -563,11 +2910,44 @@ pub fn try_default() -> Result<Self> {
         Ok(Self(home_dir()?))
     }
 
+    /// Expand shell variables and tilde of working directory alias path.
+    ///
+    /// # Errors
+    ///
+    /// - Will fail if a given shell variable is undefined.
+    pub fn expand(&self) -> Result<Self> {
+        let expand = expand_path(self.0.to_string_lossy().as_ref())?;
+        Ok(Self::new(expand))
+    }
+
+    /// Path of working directory alias.
+    pub fn as_path(&self) -> &Path {
+        self.0.as_path()
+    }
+
     pub fn to_os_string(&self) -> OsString {
         OsString::from(self.0.to_string_lossy().into_owned())
     }
 }
 
+impl FromStr for WorkDirAlias {
+    type Err = anyhow::Error;
+
+    fn from_str(data: &str) -> Result<Self, Self::Err> {
+        if data.is_empty() {
+            return Err(anyhow!("Working directory alias cannot be empty"));
+        }
+
+        Ok(Self::new(data))
+    }
+}
+
+impl fmt::Display for WorkDirAlias {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0.display())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -586,7 +2966,22 @@ mod tests {
             settings: RootEntrySettings {
                 work_dir_alias: WorkDirAlias::new("some/path"),
                 excluded: None,
-            }
+                inline_subdirs: Vec::new(),
+                jobs: None,
+                default_branch: None,
+                min_ocd_version: None,
+                deploy_report: None,
+                auto_clone: None,
+                usage_stats: None,
+                name: None,
+                description: None,
+                maintainer: None,
+                notify: None,
+                store_layout: None,
+                pull_strategy: None,
+            },
+            vars: BTreeMap::new(),
+            profile: BTreeMap::new(),
         };
         "home_dir"
     )]
@@ -599,7 +2994,22 @@ mod tests {
             settings: RootEntrySettings {
                 work_dir_alias: WorkDirAlias::new("some/path/.config/ocd"),
                 excluded: None,
-            }
+                inline_subdirs: Vec::new(),
+                jobs: None,
+                default_branch: None,
+                min_ocd_version: None,
+                deploy_report: None,
+                auto_clone: None,
+                usage_stats: None,
+                name: None,
+                description: None,
+                maintainer: None,
+                notify: None,
+                store_layout: None,
+                pull_strategy: None,
+            },
+            vars: BTreeMap::new(),
+            profile: BTreeMap::new(),
         };
         "config_dir"
     )]
@@ -610,6 +3020,26 @@ fn root_entry_valid_work_dir_alias(config: &str, expect: RootEntry) -> Result<()
         Ok(())
     }
 
+    #[test_case("https://host/repo", "https://host/repo"; "no placeholder")]
+    #[test_case("https://{{ vars.host }}/repo", "https://github.com/repo"; "spaced")]
+    #[test_case("{{vars.user}}@{{vars.host}}", "jdoe@github.com"; "several")]
+    #[test]
+    fn smoke_interpolate_vars(text: &str, expect: &str) -> Result<()> {
+        let vars =
+            BTreeMap::from([("host".into(), "github.com".into()), ("user".into(), "jdoe".into())]);
+        pretty_assert_eq!(interpolate_vars(text, &vars)?, expect);
+        Ok(())
+    }
+
+    #[test_case("{{ vars.nope }}"; "undefined")]
+    #[test_case("{{ env.HOME }}"; "not a variable")]
+    #[test_case("{{ vars.host"; "unclosed")]
+    #[test]
+    fn interpolate_vars_invalid(text: &str) {
+        let vars = BTreeMap::from([("host".into(), "github.com".into())]);
+        assert!(interpolate_vars(text, &vars).is_err());
+    }
+
     #[test]
     fn root_entry_invalid_work_dir_alias() {
         let config = r#"
@@ -634,7 +3064,24 @@ fn root_entry_invalid_work_dir_alias() {
                 },
                 url: "https://some/url".into(),
                 excluded: None,
+                included: None,
+                encrypted: None,
+                templates: None,
                 dependencies: None,
+                after: None,
+                hooks: None,
+                subdir: None,
+                source: None,
+                path: None,
+                private: false,
+                description: None,
+                homepage: None,
+                sparse_profiles: None,
+                shallow_since: None,
+                pull_strategy: None,
+                branch: None,
+                tag: None,
+                rev: None,
             }
         };
         "str_normal"
@@ -653,7 +3100,24 @@ fn root_entry_invalid_work_dir_alias() {
                 },
                 url: "https://some/url".into(),
                 excluded: None,
+                included: None,
+                encrypted: None,
+                templates: None,
                 dependencies: None,
+                after: None,
+                hooks: None,
+                subdir: None,
+                source: None,
+                path: None,
+                private: false,
+                description: None,
+                homepage: None,
+                sparse_profiles: None,
+                shallow_since: None,
+                pull_strategy: None,
+                branch: None,
+                tag: None,
+                rev: None,
             }
         };
         "str_bare_alias"
@@ -672,7 +3136,24 @@ fn root_entry_invalid_work_dir_alias() {
                 },
                 url: "https://some/url".into(),
                 excluded: None,
+                included: None,
+                encrypted: None,
+                templates: None,
                 dependencies: None,
+                after: None,
+                hooks: None,
+                subdir: None,
+                source: None,
+                path: None,
+                private: false,
+                description: None,
+                homepage: None,
+                sparse_profiles: None,
+                shallow_since: None,
+                pull_strategy: None,
+                branch: None,
+                tag: None,
+                rev: None,
             }
         };
         "map_normal"
@@ -691,11 +3172,293 @@ fn root_entry_invalid_work_dir_alias() {
                 },
                 url: "https://some/url".into(),
                 excluded: None,
+                included: None,
+                encrypted: None,
+                templates: None,
                 dependencies: None,
+                after: None,
+                hooks: None,
+                subdir: None,
+                source: None,
+                path: None,
+                private: false,
+                description: None,
+                homepage: None,
+                sparse_profiles: None,
+                shallow_since: None,
+                pull_strategy: None,
+                branch: None,
+                tag: None,
+                rev: None,
             }
         };
         "map_bare_alias"
     )]
+    #[test_case(
+        r#"
+            [settings]
+            deployment = "inline"
+            subdir = ".config/git"
+        "#,
+        NodeEntry  {
+            settings: NodeEntrySettings {
+                deployment: NodeEntryDeployment {
+                    kind: DeploymentKind::Inline,
+                    work_dir_alias: WorkDirAlias::new("some/path"),
+                },
+                url: String::default(),
+                excluded: None,
+                included: None,
+                encrypted: None,
+                templates: None,
+                dependencies: None,
+                after: None,
+                hooks: None,
+                subdir: Some(".config/git".into()),
+                source: None,
+                path: None,
+                private: false,
+                description: None,
+                homepage: None,
+                sparse_profiles: None,
+                shallow_since: None,
+                pull_strategy: None,
+                branch: None,
+                tag: None,
+                rev: None,
+            }
+        };
+        "str_inline"
+    )]
+    #[test_case(
+        r#"
+            [settings]
+            deployment = "bare_alias"
+            source = { type = "archive", url = "https://some/release.tar.gz", sha256 = "abc" }
+        "#,
+        NodeEntry  {
+            settings: NodeEntrySettings {
+                deployment: NodeEntryDeployment {
+                    kind: DeploymentKind::BareAlias,
+                    work_dir_alias: WorkDirAlias::new("some/path"),
+                },
+                url: String::default(),
+                excluded: None,
+                included: None,
+                encrypted: None,
+                templates: None,
+                dependencies: None,
+                after: None,
+                hooks: None,
+                subdir: None,
+                source: Some(NodeSource::Archive {
+                    url: "https://some/release.tar.gz".into(),
+                    sha256: "abc".into(),
+                }),
+                path: None,
+                private: false,
+                description: None,
+                homepage: None,
+                sparse_profiles: None,
+                shallow_since: None,
+                pull_strategy: None,
+                branch: None,
+                tag: None,
+                rev: None,
+            }
+        };
+        "archive_source"
+    )]
+    #[test_case(
+        r#"
+            [settings]
+            deployment = "normal"
+            url = "https://some/url"
+            description = "Editor of choice"
+            homepage = "https://www.vim.org"
+        "#,
+        NodeEntry  {
+            settings: NodeEntrySettings {
+                deployment: NodeEntryDeployment {
+                    kind: DeploymentKind::Normal,
+                    work_dir_alias: WorkDirAlias::try_default()?,
+                },
+                url: "https://some/url".into(),
+                excluded: None,
+                included: None,
+                encrypted: None,
+                templates: None,
+                dependencies: None,
+                after: None,
+                hooks: None,
+                subdir: None,
+                source: None,
+                path: None,
+                private: false,
+                description: Some("Editor of choice".into()),
+                homepage: Some("https://www.vim.org".into()),
+                sparse_profiles: None,
+                shallow_since: None,
+                pull_strategy: None,
+                branch: None,
+                tag: None,
+                rev: None,
+            }
+        };
+        "description"
+    )]
+    #[test_case(
+        r#"
+            [settings]
+            deployment = "normal"
+            url = "https://some/url"
+            excluded = ["README*"]
+            sparse_profiles.minimal = ["README*", "plugins/"]
+        "#,
+        NodeEntry  {
+            settings: NodeEntrySettings {
+                deployment: NodeEntryDeployment {
+                    kind: DeploymentKind::Normal,
+                    work_dir_alias: WorkDirAlias::try_default()?,
+                },
+                url: "https://some/url".into(),
+                excluded: Some(vec!["README*".into()]),
+                included: None,
+                encrypted: None,
+                templates: None,
+                dependencies: None,
+                after: None,
+                hooks: None,
+                subdir: None,
+                source: None,
+                path: None,
+                private: false,
+                description: None,
+                homepage: None,
+                sparse_profiles: Some(BTreeMap::from([(
+                    "minimal".into(),
+                    vec!["README*".into(), "plugins/".into()],
+                )])),
+                shallow_since: None,
+                pull_strategy: None,
+                branch: None,
+                tag: None,
+                rev: None,
+            }
+        };
+        "sparse_profiles"
+    )]
+    #[test_case(
+        r#"
+            [settings]
+            deployment = "normal"
+            url = "https://some/url"
+            shallow_since = "2024-01-01"
+        "#,
+        NodeEntry  {
+            settings: NodeEntrySettings {
+                deployment: NodeEntryDeployment {
+                    kind: DeploymentKind::Normal,
+                    work_dir_alias: WorkDirAlias::try_default()?,
+                },
+                url: "https://some/url".into(),
+                excluded: None,
+                included: None,
+                encrypted: None,
+                templates: None,
+                dependencies: None,
+                after: None,
+                hooks: None,
+                subdir: None,
+                source: None,
+                path: None,
+                private: false,
+                description: None,
+                homepage: None,
+                sparse_profiles: None,
+                shallow_since: Some("2024-01-01".into()),
+                pull_strategy: None,
+                branch: None,
+                tag: None,
+                rev: None,
+            }
+        };
+        "shallow_since"
+    )]
+    #[test_case(
+        r#"
+            [settings]
+            deployment = "normal"
+            url = "https://some/url"
+            pull_strategy = "rebase"
+        "#,
+        NodeEntry  {
+            settings: NodeEntrySettings {
+                deployment: NodeEntryDeployment {
+                    kind: DeploymentKind::Normal,
+                    work_dir_alias: WorkDirAlias::try_default()?,
+                },
+                url: "https://some/url".into(),
+                excluded: None,
+                included: None,
+                encrypted: None,
+                templates: None,
+                dependencies: None,
+                after: None,
+                hooks: None,
+                subdir: None,
+                source: None,
+                path: None,
+                private: false,
+                description: None,
+                homepage: None,
+                sparse_profiles: None,
+                shallow_since: None,
+                pull_strategy: Some(PullStrategy::Rebase),
+                branch: None,
+                tag: None,
+                rev: None,
+            }
+        };
+        "pull_strategy"
+    )]
+    #[test_case(
+        r#"
+            [settings]
+            deployment = "normal"
+            url = "https://some/url"
+            private = true
+        "#,
+        NodeEntry  {
+            settings: NodeEntrySettings {
+                deployment: NodeEntryDeployment {
+                    kind: DeploymentKind::Normal,
+                    work_dir_alias: WorkDirAlias::try_default()?,
+                },
+                url: "https://some/url".into(),
+                excluded: None,
+                included: None,
+                encrypted: None,
+                templates: None,
+                dependencies: None,
+                after: None,
+                hooks: None,
+                subdir: None,
+                source: None,
+                path: None,
+                private: true,
+                description: None,
+                homepage: None,
+                sparse_profiles: None,
+                shallow_since: None,
+                pull_strategy: None,
+                branch: None,
+                tag: None,
+                rev: None,
+            }
+        };
+        "private"
+    )]
     #[sealed_test(env = [("HOME", "some/path"), ("XDG_CONFIG_HOME", "some/path/.config")])]
     fn node_entry_valid_deployment(config: &str, expect: NodeEntry) -> Result<()> {
         let node: NodeEntry = toml::de::from_str(config)?;
@@ -723,4 +3486,148 @@ fn node_entry_invalid_deployment(config: &str) {
         let result: Result<NodeEntry> = toml::de::from_str(config).with_context(|| "should fail!");
         assert!(result.is_err());
     }
+
+    #[test_case("[settings]\nmin_ocd_version = \"0.1.0\"", true; "older")]
+    #[test_case(&format!("[settings]\nmin_ocd_version = \"{}\"", env!("CARGO_PKG_VERSION")), true; "same")]
+    #[test_case("[settings]\nmin_ocd_version = \"999.0\"\nsnafu = 1", false; "newer")]
+    #[test_case("[settings]\nmin_ocd_version = \"one\"", false; "invalid")]
+    #[test_case("[settings]\nwork_dir_alias = \"home_dir\"", true; "unset")]
+    #[test]
+    fn smoke_check_min_ocd_version(root: &str, expect: bool) {
+        pretty_assert_eq!(check_min_ocd_version(root).is_ok(), expect);
+    }
+
+    #[test_case("1.2.3", (1, 2, 3); "full")]
+    #[test_case("v1.2", (1, 2, 0); "short")]
+    #[test_case("1.2.3-rc.1+build", (1, 2, 3); "pre_release")]
+    #[test]
+    fn smoke_parse_version(version: &str, expect: (u64, u64, u64)) -> Result<()> {
+        pretty_assert_eq!(parse_version(version)?, expect);
+        Ok(())
+    }
+
+    #[test_case("normal", DeploymentKind::Normal; "normal")]
+    #[test_case("bare-alias", DeploymentKind::BareAlias; "bare_alias")]
+    #[test_case("inline", DeploymentKind::Inline; "inline")]
+    #[test_case("hard-link", DeploymentKind::HardLink; "hard_link")]
+    #[test_case("symlink", DeploymentKind::Symlink; "symlink")]
+    #[test_case("copy", DeploymentKind::Copy; "copy")]
+    #[test_case("mirror", DeploymentKind::Mirror; "mirror")]
+    #[test_case("cluster", DeploymentKind::Cluster; "cluster")]
+    #[test]
+    fn deployment_kind_round_trip(data: &str, expect: DeploymentKind) -> Result<()> {
+        let result: DeploymentKind = data.parse()?;
+        pretty_assert_eq!(result, expect);
+        pretty_assert_eq!(result.to_string(), data);
+        Ok(())
+    }
+
+    #[test_case("bare-alias:~/", DeploymentKind::BareAlias, "~/"; "bare_alias_tilde")]
+    #[test_case("bare_alias:/some/path", DeploymentKind::BareAlias, "/some/path"; "snake_case")]
+    #[test_case("normal:some/path", DeploymentKind::Normal, "some/path"; "normal")]
+    #[test]
+    fn node_entry_deployment_from_str(data: &str, kind: DeploymentKind, path: &str) -> Result<()> {
+        let result: NodeEntryDeployment = data.parse()?;
+        pretty_assert_eq!(result, NodeEntryDeployment::new(kind, WorkDirAlias::new(path)));
+        pretty_assert_eq!(result.to_string().parse::<NodeEntryDeployment>()?, result);
+        Ok(())
+    }
+
+    #[test_case("snafu:~/"; "invalid_kind")]
+    #[test_case("bare-alias:"; "empty_work_dir_alias")]
+    #[test]
+    fn node_entry_deployment_from_str_invalid(data: &str) {
+        assert!(data.parse::<NodeEntryDeployment>().is_err());
+    }
+
+    #[test]
+    fn node_entry_serialize_round_trip() -> Result<()> {
+        let expect = NodeEntry::builder()?
+            .deployment(DeploymentKind::BareAlias, WorkDirAlias::new("some/path"))
+            .url("https://some/url")
+            .excluded(["README*"])
+            .build();
+        let data = toml::ser::to_string(&expect)?;
+        let result: NodeEntry = toml::de::from_str(&data)?;
+        pretty_assert_eq!(result, expect);
+        Ok(())
+    }
+    // INVARIANT: Schemas know every setting, such that strict mode never rejects valid files.
+    #[test]
+    fn schemas_cover_every_setting() -> Result<()> {
+        let root = RootEntry {
+            settings: RootEntrySettings {
+                work_dir_alias: WorkDirAlias::new(home_dir()?),
+                excluded: Some(vec!["README*".into()]),
+                inline_subdirs: Vec::new(),
+                jobs: Some(4),
+                default_branch: Some("main".into()),
+                min_ocd_version: Some("0.8.0".into()),
+                deploy_report: Some("report.json".into()),
+                auto_clone: Some(ClonePolicy::Never),
+                usage_stats: Some(true),
+                name: Some("dotfiles".into()),
+                description: Some("Shared workstation setup".into()),
+                maintainer: Some("Jane Doe <jane@example.com>".into()),
+                notify: Some("notify-send".into()),
+                store_layout: Some(StoreLayout::Forge),
+                pull_strategy: Some(PullStrategy::Rebase),
+            },
+            vars: BTreeMap::from([("user".into(), "jdoe".into())]),
+            profile: BTreeMap::from([(
+                "laptop".into(),
+                HostProfile { nodes: vec!["vim".into()], hosts: vec!["laptop-*".into()] },
+            )]),
+        };
+        let data = toml::ser::to_string(&root)?;
+        pretty_assert_eq!(check_schema(&data, ROOT_SCHEMA)?, data);
+
+        let node = NodeEntry {
+            settings: NodeEntrySettings {
+                deployment: NodeEntryDeployment::new(
+                    DeploymentKind::Normal,
+                    WorkDirAlias::new("a"),
+                ),
+                url: "https://some/url".into(),
+                excluded: Some(vec!["README*".into()]),
+                included: None,
+                encrypted: Some(vec![".netrc.age".into()]),
+                templates: Some(vec!["*.tmpl".into()]),
+                dependencies: Some(vec!["sh".into()]),
+                after: None,
+                hooks: None,
+                subdir: Some("sub".into()),
+                source: Some(NodeSource::Archive { url: "a.tar.gz".into(), sha256: "0".into() }),
+                path: Some("/srv/dots".into()),
+                private: true,
+                description: Some("Editor".into()),
+                homepage: Some("https://some/page".into()),
+                sparse_profiles: Some(BTreeMap::from([("min".into(), vec!["big/".into()])])),
+                shallow_since: Some("2025-01-01".into()),
+                pull_strategy: Some(PullStrategy::Merge),
+                branch: Some("dev".into()),
+                tag: Some("v1".into()),
+                rev: Some("abc".into()),
+            },
+        };
+        let data = toml::ser::to_string(&node)?;
+        pretty_assert_eq!(check_schema(&data, NODE_SCHEMA)?, data);
+
+        Ok(())
+    }
+
+    #[test]
+    fn smoke_node_entry_with_sparse_profile() -> Result<()> {
+        let entry = NodeEntry::builder()?
+            .excluded(["README*"])
+            .sparse_profile("minimal", ["README*", "plugins/"])
+            .build();
+        let profiled = entry.with_sparse_profile("minimal").context("profile is defined")?;
+        pretty_assert_eq!(
+            profiled.settings.excluded,
+            Some(vec!["README*".to_string(), "plugins/".to_string()])
+        );
+        assert!(entry.with_sparse_profile("full").is_none());
+        Ok(())
+    }
 }
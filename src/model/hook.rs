@@ -5,26 +5,60 @@
 //!
 //! Provides methods to parse, deserialize, and execute command hooks.
 
-use super::config_dir;
+use super::{
+    cluster::{Cluster, NodeHooks},
+    config_dir, expand_path, home_env,
+    schema::{check_schema, Schema},
+    secret::{decrypt_config, is_encrypted},
+};
 
-use anyhow::{Context, Result};
+use anyhow::{anyhow, Context, Result};
+use beau_collector::BeauCollector as _;
 use clap::ValueEnum;
-use config::{Config, File};
+use config::{Config, File, FileFormat};
+#[cfg(feature = "pager")]
 use minus::{
     input::{HashedEventRegister, InputEvent},
     page_all, ExitStrategy, LineNumbers, Pager,
 };
 use run_script::{run_script, ScriptOptions};
 use serde::Deserialize;
+#[cfg(feature = "pager")]
+use std::hash::RandomState;
 use std::{
     collections::HashMap,
     fs::read_to_string,
-    hash::RandomState,
     path::{Path, PathBuf},
     sync::atomic::{AtomicBool, Ordering},
     sync::Arc,
 };
-use tracing::{debug, info, trace, warn};
+use tracing::{debug, info, instrument, trace, warn};
+
+/// Commands that run command hooks, along with whether they operate on targets.
+///
+/// Hook entries of any other command would never run, and neither would hook entries with a
+/// target for commands that do not operate on targets. Both are rejected when hooks are loaded.
+pub const HOOK_COMMANDS: [(&str, bool); 19] = [
+    ("clone", false),
+    ("init", false),
+    ("add", true),
+    ("deploy", true),
+    ("undeploy", true),
+    ("rm", true),
+    ("ls", false),
+    ("repair", true),
+    ("excludes", true),
+    ("migrate-remote", true),
+    ("convert", true),
+    ("rename", true),
+    ("gc", true),
+    ("pull", true),
+    ("fetch-config", true),
+    ("freeze", true),
+    ("thaw", true),
+    ("rollback", true),
+    ("undo", true),
+];
 
 /// Execute user defined command hooks.
 ///
@@ -32,6 +66,17 @@
 /// for a hook entry are stored at `$XDG_CONFIG_HOME/ocd/hooks/`. This type will not look anywhere
 /// else for hook scripts.
 ///
+/// # Hook Contract
+///
+/// Every hook script is given the name of the command it runs for through `OCD_COMMAND`, and the
+/// comma-separated list of current targets through `OCD_TARGETS`, along with every variable of
+/// the `env` table of the hook configuration file. Pre-command hooks can additionally:
+///
+/// - Veto the command by exiting with a non-zero exit code.
+/// - Replace the target list by printing a `OCD_TARGETS=<target>,<target>...` line to stdout,
+///   where the last such line wins. Printing `OCD_TARGETS=` with no targets leaves the command
+///   with nothing to operate on.
+///
 /// # Invariants
 ///
 /// Always expand working directory paths.
@@ -49,16 +94,43 @@ impl HookRunner {
     ///
     /// # Errors
     ///
-    /// Will fail if hook configuration file cannot be read, or contains invalid TOML formatting.
+    /// - Will fail if hook configuration file cannot be read, or contains invalid TOML formatting.
+    /// - Will fail if hook configuration file defines hooks for commands that do not run hooks, or
+    ///   targets for commands that do not operate on targets.
     pub fn new() -> Result<Self> {
         trace!("Load hook configurations");
 
         let path = config_dir()?.join("hooks.toml");
         debug!("Load hooks at {path:?}");
+        let data = read_to_string(&path).unwrap_or_default();
+
+        Self::load(&data, &path)
+    }
+
+    /// Construct new hook runner from contents of hook configuration file.
+    ///
+    /// Works like [`HookRunner::new`], but takes the contents of `hooks.toml` instead of reading
+    /// it from the configuration directory, e.g., to validate hooks before they get committed.
+    ///
+    /// # Errors
+    ///
+    /// - Will fail if hook configuration contains invalid TOML formatting.
+    /// - Will fail if hook configuration defines hooks for commands that do not run hooks, or
+    ///   targets for commands that do not operate on targets.
+    pub fn from_definition(data: &str) -> Result<Self> {
+        Self::load(data, Path::new("hooks.toml"))
+    }
+
+    fn load(data: &str, path: &Path) -> Result<Self> {
+        let data = decrypt_config(data)?;
+        let data = check_schema(&data, HOOKS_SCHEMA)
+            .with_context(|| format!("Failed to load hooks at {path:?}"))?;
         let entries: CommandHooks = Config::builder()
-            .add_source(File::from(path).required(false))
+            .add_source(File::from_str(&data, FileFormat::Toml))
             .build()?
-            .try_deserialize()?;
+            .try_deserialize()
+            .with_context(|| format!("Failed to load hooks at {path:?}"))?;
+        entries.validate().with_context(|| format!("Invalid hooks at {path:?}"))?;
 
         Ok(Self { entries, action: HookAction::default(), pager: HookPager::default() })
     }
@@ -75,86 +147,396 @@ pub fn set_action(&mut self, action: HookAction) {
     /// using the prompt hook action. Otherwise, it will either execute hooks with no prompting, or
     /// not execut any hooks based on hook action.
     ///
+    /// Target lists printed by pre-command hooks are ignored, use [`HookRunner::run_pre`] for
+    /// commands that respect them.
+    ///
     /// # Errors
     ///
     /// - Will fail for any pager failure.
     /// - Will fail if hook script cannot be read or executed for whatever reason.
     /// - Will fail if working directory path cannot be properly expanded.
+    /// - Will fail if pre-command hook vetoes command.
     pub fn run(
         &self,
         cmd: impl AsRef<str>,
         kind: HookKind,
         repos: Option<&Vec<String>>,
+    ) -> Result<()> {
+        let mut targets = repos.cloned();
+        self.execute(cmd.as_ref(), kind, &mut targets)
+    }
+
+    /// Run all pre-command hooks of a command operating on targets.
+    ///
+    /// Same as [`HookRunner::run`], except that the target list printed by a hook is respected.
+    /// Each hook sees the target list as modified by the hooks that ran before it. Returns final
+    /// target list to operate on.
+    ///
+    /// # Errors
+    ///
+    /// - Will fail for any pager failure.
+    /// - Will fail if hook script cannot be read or executed for whatever reason.
+    /// - Will fail if working directory path cannot be properly expanded.
+    /// - Will fail if pre-command hook vetoes command.
+    pub fn run_pre(&self, cmd: impl AsRef<str>, targets: Vec<String>) -> Result<Vec<String>> {
+        let mut targets = Some(targets);
+        self.execute(cmd.as_ref(), HookKind::Pre, &mut targets)?;
+        Ok(targets.unwrap_or_default())
+    }
+
+    /// Run all post-command hooks of a command in dependency order of their targets.
+    ///
+    /// Meant to run once the command is done with every target, such that no hook ever runs
+    /// before every target is in place. Hooks whose target is a dependency run before hooks whose
+    /// target depends on it, hooks without a target run last, and hooks of the same dependency
+    /// level keep their configuration order. Consecutive hooks of the same dependency level that
+    /// declare themselves independent through `parallel = true` form a group that runs
+    /// concurrently. Every hook of a group is prompted for before the group runs, and output of
+    /// each hook is reported once the whole group is done, such that it never interleaves.
+    ///
+    /// # Errors
+    ///
+    /// - Will fail for any pager failure.
+    /// - Will fail if hook script cannot be read or executed for whatever reason.
+    /// - Will fail if working directory path cannot be properly expanded.
+    #[instrument(skip(self, targets, cluster), level = "debug")]
+    pub fn run_post_ordered(
+        &self,
+        cmd: impl AsRef<str> + std::fmt::Debug,
+        targets: &[String],
+        cluster: &Cluster,
+    ) -> Result<()> {
+        let cmd = cmd.as_ref();
+        let Some(hooks) = self.entries.hooks.as_ref().and_then(|hooks| hooks.get(cmd)) else {
+            return Ok(());
+        };
+        if self.action == HookAction::Never {
+            return Ok(());
+        }
+
+        // INVARIANT: Stable sort keeps configuration order among hooks of the same level.
+        let levels = cluster.dependency_levels();
+        let mut ordered: Vec<(usize, &HookEntry)> = hooks
+            .iter()
+            .map(|hook| match &hook.target {
+                Some(target) => (levels.get(target).copied().unwrap_or_default(), hook),
+                None => (usize::MAX, hook),
+            })
+            .collect();
+        ordered.sort_by_key(|(level, _)| *level);
+
+        let targets = Some(targets.to_vec());
+        let mut planned = Vec::new();
+        for (level, hook) in ordered {
+            if let Some(script) = self.prepare(&HookKind::Post, hook, &targets)? {
+                planned.push((level, script));
+            }
+        }
+
+        let env_vars = self.env_vars(cmd, &targets);
+        let mut rest = planned.as_slice();
+        while let Some(((level, first), _)) = rest.split_first() {
+            let size = if first.parallel {
+                rest.iter().take_while(|(other, script)| other == level && script.parallel).count()
+            } else {
+                1
+            };
+            let (group, remaining) = rest.split_at(size);
+            rest = remaining;
+
+            if let [(_, script)] = group {
+                script.run(&env_vars)?;
+                continue;
+            }
+
+            debug!("Run {} independent hooks of {cmd:?} concurrently", group.len());
+            let results: Vec<Result<()>> = std::thread::scope(|scope| {
+                let handles: Vec<_> = group
+                    .iter()
+                    .map(|(_, script)| scope.spawn(|| script.execute(&env_vars)))
+                    .collect();
+                handles
+                    .into_iter()
+                    .zip(group)
+                    .map(|(handle, (_, script))| {
+                        let output = handle
+                            .join()
+                            .map_err(|_| anyhow!("Hook {:?} panicked", script.name))??;
+                        script.report(&output);
+                        Ok(())
+                    })
+                    .collect()
+            });
+            results.into_iter().collect::<Result<Vec<_>>>()?;
+        }
+
+        Ok(())
+    }
+
+    /// Run hook script that node declares for its deployment, see [`NodeHooks`].
+    ///
+    /// Node hooks are paged and prompted for like command hooks, and receive the same environment
+    /// as hooks of the deploy command with the node as its only target. Scripts are read through
+    /// `read`, and run at the working tree of the node, being skipped if it does not exist yet.
+    /// Pre-deployment hooks veto deployment of their node by exiting with a non-zero exit code.
+    ///
+    /// # Errors
+    ///
+    /// - Will fail for any pager failure.
+    /// - Will fail if hook script cannot be read or executed for whatever reason.
+    /// - Will fail if pre-deployment hook vetoes deployment of node.
+    #[instrument(skip(self, hooks, read), level = "debug")]
+    pub fn run_node(
+        &self,
+        kind: HookKind,
+        node: &str,
+        hooks: Option<&NodeHooks>,
+        read: impl Fn(&Path) -> Result<String>,
+        work_dir: PathBuf,
     ) -> Result<()> {
         if self.action == HookAction::Never {
             return Ok(());
         }
 
+        let script = hooks.and_then(|hooks| match kind {
+            HookKind::Pre => hooks.pre_deploy.as_ref(),
+            HookKind::Post => hooks.post_deploy.as_ref(),
+        });
+        let Some(script) = script else {
+            return Ok(());
+        };
+
+        let path = Path::new(node).join(script);
+        let data = read(script)?;
+        if !work_dir.exists() {
+            warn!("Work directory {work_dir:?} does not exist, skipping hook {path:?}");
+            return Ok(());
+        }
+
+        let work_dir = Some(work_dir);
+        if self.action == HookAction::Prompt {
+            self.pager.page_and_prompt(&path, &work_dir, &data)?;
+            if !self.pager.choice() {
+                return Ok(());
+            }
+        }
+
+        let name = path.to_string_lossy().into_owned();
+        let script = HookScript { name, data, work_dir, parallel: false };
+        let (code, _) = script.run(&self.env_vars("deploy", &Some(vec![node.into()])))?;
+        if kind == HookKind::Pre && code != 0 {
+            return Err(anyhow!("Hook {:?} vetoed deployment of {node:?} [{code}]", script.name));
+        }
+
+        Ok(())
+    }
+
+    #[instrument(skip(self, repos), level = "debug")]
+    fn execute(&self, cmd: &str, kind: HookKind, repos: &mut Option<Vec<String>>) -> Result<()> {
+        if self.action == HookAction::Never {
+            return Ok(());
+        }
+
         if self.entries.hooks.is_none() {
             return Ok(());
         }
 
-        if let Some(hooks) = self.entries.hooks.as_ref().unwrap().get(cmd.as_ref()) {
+        if let Some(hooks) = self.entries.hooks.as_ref().unwrap().get(cmd) {
             for hook in hooks {
-                let name = match kind {
-                    HookKind::Pre => hook.pre.as_ref(),
-                    HookKind::Post => hook.post.as_ref(),
-                };
-                let name = match name {
-                    Some(name) => name,
-                    None => continue,
-                };
-
-                if let Some(repos) = repos {
-                    if let Some(repo) = &hook.target {
-                        if !repos.contains(repo) {
-                            continue;
-                        }
-                    }
-                } else if hook.target.is_some() {
-                    warn!(
-                        "Command {:?} cannot operate on targets, skipping {hook:?}",
-                        cmd.as_ref()
-                    );
+                let Some(script) = self.prepare(&kind, hook, repos)? else {
                     continue;
-                }
+                };
 
-                let path = config_dir()?.join("hooks").join(name);
-                let data = read_to_string(&path).with_context(|| "Script {path:?} undefined")?;
-                let work_dir = if let Some(work_dir) = &hook.work_dir {
-                    let path: PathBuf =
-                        shellexpand::full(work_dir.to_string_lossy().as_ref())?.into_owned().into();
-                    if !path.exists() {
-                        warn!("Work directory {path:?} does not exist, skipping {hook:?}");
-                        continue;
+                let env_vars = self.env_vars(cmd, repos);
+                let (code, out) = script.run(&env_vars)?;
+                if kind == HookKind::Pre {
+                    let name = &script.name;
+                    if code != 0 {
+                        return Err(anyhow!("Hook {name:?} vetoed command {cmd:?} [{code}]"));
                     }
-                    Some(path)
-                } else {
-                    None
-                };
 
-                if self.action == HookAction::Prompt {
-                    self.pager.page_and_prompt(&path, &work_dir, &data)?;
-                    if !self.pager.choice() {
-                        continue;
+                    if let (Some(repos), Some(targets)) = (repos.as_mut(), parse_targets(&out)) {
+                        info!("Hook {name:?} changed targets of {cmd:?} to {targets:?}");
+                        *repos = targets;
                     }
                 }
-
-                let mut opts = ScriptOptions::new();
-                opts.working_directory = work_dir;
-                let (code, out, err) = run_script!(data, opts)?;
-                info!("[{code}] {name:?}\nstdout: {out}\nstderr: {err}");
             }
         }
 
         Ok(())
     }
+
+    // Pick script of hook to run, prompting for it if asked to. Hooks that do not apply to the
+    // command, or that the user denied, yield nothing.
+    fn prepare(
+        &self,
+        kind: &HookKind,
+        hook: &HookEntry,
+        repos: &Option<Vec<String>>,
+    ) -> Result<Option<HookScript>> {
+        let name = match kind {
+            HookKind::Pre => hook.pre.as_ref(),
+            HookKind::Post => hook.post.as_ref(),
+        };
+        let Some(name) = name else {
+            return Ok(None);
+        };
+
+        // INVARIANT: Targets of commands that do not operate on targets were rejected on load.
+        if let (Some(repos), Some(repo)) = (repos.as_ref(), &hook.target) {
+            if !repos.contains(repo) {
+                return Ok(None);
+            }
+        }
+
+        let path = config_dir()?.join("hooks").join(name);
+        let data = read_to_string(&path).with_context(|| "Script {path:?} undefined")?;
+        let work_dir = if let Some(work_dir) = &hook.work_dir {
+            let path: PathBuf = expand_path(work_dir.to_string_lossy().as_ref())?.into();
+            if !path.exists() {
+                warn!("Work directory {path:?} does not exist, skipping {hook:?}");
+                return Ok(None);
+            }
+            Some(path)
+        } else {
+            None
+        };
+
+        if self.action == HookAction::Prompt {
+            self.pager.page_and_prompt(&path, &work_dir, &data)?;
+            if !self.pager.choice() {
+                return Ok(None);
+            }
+        }
+
+        Ok(Some(HookScript { name: name.clone(), data, work_dir, parallel: hook.parallel }))
+    }
+
+    fn env_vars(&self, cmd: &str, repos: &Option<Vec<String>>) -> HashMap<String, String> {
+        let mut env_vars = self.entries.env_vars();
+        if let Some((key, home)) = home_env() {
+            env_vars.entry(key.into()).or_insert(home.to_string_lossy().into_owned());
+        }
+        env_vars.insert("OCD_COMMAND".into(), cmd.into());
+        env_vars.insert(
+            "OCD_TARGETS".into(),
+            repos.iter().flatten().cloned().collect::<Vec<_>>().join(","),
+        );
+        env_vars
+    }
+}
+
+/// Hook script ready to run.
+#[derive(Debug)]
+struct HookScript {
+    name: String,
+    data: String,
+    work_dir: Option<PathBuf>,
+    parallel: bool,
+}
+
+impl HookScript {
+    // Run script, reporting its output right away. Returns exit code and stdout of script.
+    fn run(&self, env_vars: &HashMap<String, String>) -> Result<(i32, String)> {
+        let output = self.execute(env_vars)?;
+        self.report(&output);
+        Ok((output.0, output.1))
+    }
+
+    fn execute(&self, env_vars: &HashMap<String, String>) -> Result<(i32, String, String)> {
+        let mut opts = ScriptOptions::new();
+        opts.working_directory = self.work_dir.clone();
+        opts.env_vars = Some(env_vars.clone());
+        Ok(run_script!(&self.data, opts)?)
+    }
+
+    fn report(&self, (code, out, err): &(i32, String, String)) {
+        info!("[{code}] {:?}\nstdout: {out}\nstderr: {err}", self.name);
+    }
+}
+
+/// Parse target list printed by hook.
+///
+/// Last `OCD_TARGETS=` line printed wins. Targets are comma-separated, with surrounding whitespace
+/// and empty targets ignored.
+fn parse_targets(stdout: &str) -> Option<Vec<String>> {
+    stdout.lines().rev().find_map(|line| line.trim().strip_prefix("OCD_TARGETS=")).map(|list| {
+        list.split(',').map(str::trim).filter(|t| !t.is_empty()).map(Into::into).collect()
+    })
 }
 
+/// Schema of `hooks.toml`, see [`CommandHooks`].
+const HOOKS_SCHEMA: Schema = Schema::Table(&[
+    (
+        "hooks",
+        Schema::Map(&Schema::Tables(&[
+            ("pre", Schema::Value),
+            ("post", Schema::Value),
+            ("work_dir", Schema::Value),
+            ("target", Schema::Value),
+            ("parallel", Schema::Value),
+        ])),
+    ),
+    ("env", Schema::Map(&Schema::Value)),
+]);
+
 /// Command hook representation.
 #[derive(Debug, Deserialize)]
 pub struct CommandHooks {
     hooks: Option<HashMap<String, Vec<HookEntry>>>,
+
+    /// Environment variables given to every hook script, e.g., tokens.
+    env: Option<HashMap<String, String>>,
+}
+
+impl CommandHooks {
+    // Reject hook entries that could never run, such that broken hook files do not silently skip
+    // their hooks.
+    fn validate(&self) -> Result<()> {
+        let mut commands: Vec<_> = self.hooks.iter().flatten().collect();
+        commands.sort_by_key(|(cmd, _)| *cmd);
+
+        let mut results = Vec::new();
+        for (cmd, entries) in commands {
+            let Some((_, targeted)) = HOOK_COMMANDS.iter().find(|(name, _)| name == cmd) else {
+                let known: Vec<&str> = HOOK_COMMANDS.iter().map(|(name, _)| *name).collect();
+                results.push(Err(anyhow!(
+                    "Command {cmd:?} does not run hooks, expected one of {}",
+                    known.join(", ")
+                )));
+                continue;
+            };
+
+            for (index, entry) in entries.iter().enumerate() {
+                match &entry.target {
+                    Some(target) if !targeted => results.push(Err(anyhow!(
+                        "Hook entry {} of {cmd:?} targets {target:?}, but {cmd:?} cannot operate \
+                         on targets",
+                        index + 1
+                    ))),
+                    _ => results.push(Ok(())),
+                }
+            }
+        }
+
+        results.into_iter().bcollect::<_>()
+    }
+
+    // INVARIANT: Values that could not be decrypted are never handed to hook scripts.
+    fn env_vars(&self) -> HashMap<String, String> {
+        self.env
+            .iter()
+            .flatten()
+            .filter(|(name, value)| {
+                let encrypted = is_encrypted(value);
+                if encrypted {
+                    warn!("Environment variable {name:?} of hooks is still encrypted, skipping it");
+                }
+                !encrypted
+            })
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect()
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -170,6 +552,12 @@ pub struct HookEntry {
 
     /// Only execute for target repository in cluster.
     pub target: Option<String>,
+
+    /// Script is independent of other hooks, and may run concurrently with them.
+    ///
+    /// Only honored by commands that run their post-command hooks in dependency order.
+    #[serde(default)]
+    pub parallel: bool,
 }
 
 /// Behavior variants for hook execution.
@@ -221,6 +609,7 @@ pub(crate) fn choice(&self) -> bool {
     /// # Errors
     ///
     /// Return errors issued by pager.
+    #[cfg(feature = "pager")]
     pub(crate) fn page_and_prompt(
         &self,
         name: impl AsRef<Path>,
@@ -245,6 +634,43 @@ pub(crate) fn page_and_prompt(
         Ok(())
     }
 
+    /// Print hook script and prompt about its execution through standard input.
+    ///
+    /// Fallback for builds without the pager. Answering "a" accepts, anything else denies.
+    ///
+    /// # Errors
+    ///
+    /// Will fail if standard error cannot be written to, or standard input cannot be read.
+    #[cfg(not(feature = "pager"))]
+    pub(crate) fn page_and_prompt(
+        &self,
+        name: impl AsRef<Path>,
+        work_dir: &Option<PathBuf>,
+        data: impl AsRef<str>,
+    ) -> Result<()> {
+        use std::io::{BufRead, Write};
+
+        let work_dir = match work_dir {
+            Some(path) => path.clone(),
+            None => PathBuf::from("./"),
+        };
+
+        let mut stderr = std::io::stderr().lock();
+        for (number, line) in data.as_ref().lines().enumerate() {
+            writeln!(stderr, "{:>4} {line}", number + 1)?;
+        }
+        write!(stderr, "Run {:?} at {:?}? [A]ccept/[D]eny ", name.as_ref(), work_dir)?;
+        stderr.flush()?;
+
+        let mut answer = String::new();
+        std::io::stdin().lock().read_line(&mut answer)?;
+        let accepted = answer.trim().eq_ignore_ascii_case("a");
+        self.choice.store(accepted, Ordering::Relaxed);
+
+        Ok(())
+    }
+
+    #[cfg(feature = "pager")]
     fn generate_key_bindings(&self) -> Box<HashedEventRegister<RandomState>> {
         let mut input = HashedEventRegister::default();
 
@@ -263,3 +689,53 @@ fn generate_key_bindings(&self) -> Box<HashedEventRegister<RandomState>> {
         Box::new(input)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::cmd::Ocd;
+
+    use clap::CommandFactory;
+    use pretty_assertions::assert_eq as pretty_assert_eq;
+    use simple_test_case::test_case;
+
+    #[test_case("", None; "no directive")]
+    #[test_case("checking display\n", None; "plain output")]
+    #[test_case("OCD_TARGETS=vim,sh\n", Some(vec!["vim".into(), "sh".into()]); "directive")]
+    #[test_case("OCD_TARGETS=\n", Some(vec![]); "empty directive")]
+    #[test_case(
+        "OCD_TARGETS=vim\n  OCD_TARGETS= dwm , ,st \n",
+        Some(vec!["dwm".into(), "st".into()]);
+        "last directive wins"
+    )]
+    #[test]
+    fn smoke_parse_targets(stdout: &str, expect: Option<Vec<String>>) {
+        pretty_assert_eq!(parse_targets(stdout), expect);
+    }
+
+    #[test_case("", true; "no hooks")]
+    #[test_case("[hooks]\nclone = [{ pre = \"a.sh\" }]\n", true; "untargeted")]
+    #[test_case("[hooks]\ndeploy = [{ pre = \"a.sh\", target = \"vim\" }]\n", true; "targeted")]
+    #[test_case("[hooks]\ndeplyo = [{ pre = \"a.sh\" }]\n", false; "unknown command")]
+    #[test_case("[hooks]\nstatus = [{ pre = \"a.sh\" }]\n", false; "command without hooks")]
+    #[test_case(
+        "[hooks]\nclone = [{ post = \"a.sh\" }, { pre = \"b.sh\", target = \"vim\" }]\n",
+        false;
+        "target on clone"
+    )]
+    #[test_case("[hooks]\ninit = [{ pre = \"a.sh\", target = \"vim\" }]\n", false; "target on init")]
+    #[test]
+    fn smoke_command_hooks_validate(config: &str, valid: bool) {
+        let hooks: CommandHooks = toml::de::from_str(config).unwrap();
+        pretty_assert_eq!(hooks.validate().is_ok(), valid);
+    }
+
+    #[test]
+    fn smoke_hook_commands_exist() {
+        let ocd = Ocd::command();
+        for (name, _) in HOOK_COMMANDS {
+            assert!(ocd.find_subcommand(name).is_some(), "{name}");
+        }
+    }
+}
@@ -0,0 +1,232 @@
+// SPDX-FileCopyrightText: 2025 Jason Pena <jasonpena@awkless.com>
+// SPDX-License-Identifier: MIT
+
+//! Schemas of configuration files.
+//!
+//! Typos in configuration files are easy to miss, e.g., `exclude = [...]` instead of
+//! `excluded = [...]`, and a parser that ignores unknown keys would go on to deploy everything.
+//! Thus, each configuration file is walked against the schema of the keys it may contain before
+//! it gets deserialized. Strict mode is on by default, where unknown keys are errors that suggest
+//! the closest known key. Strict mode can be turned off through `--no-strict`, where unknown keys
+//! are warned about and skipped instead.
+
+use anyhow::{anyhow, Context, Result};
+use beau_collector::BeauCollector as _;
+use std::sync::atomic::{AtomicBool, Ordering};
+use toml_edit::{DocumentMut, Item, TableLike, Value};
+use tracing::warn;
+
+static STRICT: AtomicBool = AtomicBool::new(true);
+
+/// Use strict mode for the rest of the invocation.
+pub fn set_strict(strict: bool) {
+    STRICT.store(strict, Ordering::Relaxed);
+}
+
+/// Check if strict mode is in use.
+pub fn is_strict() -> bool {
+    STRICT.load(Ordering::Relaxed)
+}
+
+/// Expected shape of configuration file.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Schema {
+    /// Table with known keys.
+    Table(&'static [(&'static str, Schema)]),
+
+    /// Table with keys of any name, whose values share a schema.
+    Map(&'static Schema),
+
+    /// Array of tables with known keys.
+    Tables(&'static [(&'static str, Schema)]),
+
+    /// Value that is not walked any further.
+    Value,
+}
+
+#[derive(Debug)]
+struct UnknownKey {
+    path: String,
+    suggestion: Option<&'static str>,
+}
+
+/// Check configuration data against its schema.
+///
+/// Returns data as is if it has no unknown keys. Outside of strict mode, unknown keys are warned
+/// about and removed from returned data, such that deserialization does not trip over them.
+/// Data that is not valid TOML is also returned as is, leaving syntax errors to deserialization.
+///
+/// # Errors
+///
+/// - Will fail with a diagnostic for each unknown key in strict mode.
+pub(crate) fn check_schema(data: &str, schema: Schema) -> Result<String> {
+    let Ok(mut document) = data.parse::<DocumentMut>() else {
+        return Ok(data.to_string());
+    };
+
+    let mut unknown = Vec::new();
+    walk_item(document.as_item_mut(), schema, "", &mut unknown);
+    if unknown.is_empty() {
+        return Ok(data.to_string());
+    }
+
+    if !is_strict() {
+        for key in &unknown {
+            warn!("Skip unknown key {:?}", key.path);
+        }
+        return Ok(document.to_string());
+    }
+
+    unknown
+        .into_iter()
+        .map(|key| match key.suggestion {
+            Some(suggestion) => {
+                Err(anyhow!("Unknown key {:?}, did you mean {suggestion:?}?", key.path))
+            }
+            None => Err(anyhow!("Unknown key {:?}", key.path)),
+        })
+        .bcollect::<Vec<()>>()
+        .context("Configuration has unknown keys, use --no-strict to skip them")?;
+
+    Ok(data.to_string())
+}
+
+fn walk_item(item: &mut Item, schema: Schema, path: &str, unknown: &mut Vec<UnknownKey>) {
+    match schema {
+        Schema::Table(keys) => {
+            if let Some(table) = item.as_table_like_mut() {
+                walk_table(table, keys, path, unknown);
+            }
+        }
+        Schema::Map(values) => {
+            if let Some(table) = item.as_table_like_mut() {
+                for (key, item) in table.iter_mut() {
+                    walk_item(item, *values, &join(path, key.get()), unknown);
+                }
+            }
+        }
+        Schema::Tables(keys) => match item {
+            Item::ArrayOfTables(tables) => {
+                for (index, table) in tables.iter_mut().enumerate() {
+                    walk_table(table, keys, &format!("{path}[{index}]"), unknown);
+                }
+            }
+            Item::Value(Value::Array(array)) => {
+                for (index, value) in array.iter_mut().enumerate() {
+                    if let Value::InlineTable(table) = value {
+                        walk_table(table, keys, &format!("{path}[{index}]"), unknown);
+                    }
+                }
+            }
+            _ => {}
+        },
+        Schema::Value => {}
+    }
+}
+
+// INVARIANT: Unknown keys are removed, such that only known keys get deserialized.
+fn walk_table(
+    table: &mut dyn TableLike,
+    keys: &'static [(&'static str, Schema)],
+    path: &str,
+    unknown: &mut Vec<UnknownKey>,
+) {
+    let names: Vec<String> = table.iter().map(|(key, _)| key.to_string()).collect();
+    for name in names {
+        match keys.iter().find(|(key, _)| *key == name) {
+            Some((_, schema)) => {
+                if let Some(item) = table.get_mut(&name) {
+                    walk_item(item, *schema, &join(path, &name), unknown);
+                }
+            }
+            None => {
+                let suggestion = suggest(&name, keys.iter().map(|(key, _)| *key));
+                unknown.push(UnknownKey { path: join(path, &name), suggestion });
+                let _ = table.remove(&name);
+            }
+        }
+    }
+}
+
+fn join(path: &str, key: &str) -> String {
+    if path.is_empty() {
+        key.to_string()
+    } else {
+        format!("{path}.{key}")
+    }
+}
+
+// INVARIANT: Only keys close enough to be plausible typos are suggested.
+fn suggest(name: &str, keys: impl Iterator<Item = &'static str>) -> Option<&'static str> {
+    let threshold = (name.chars().count() / 3).max(1);
+    keys.map(|key| (edit_distance(name, key), key))
+        .filter(|(distance, _)| *distance <= threshold)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, key)| key)
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let cost = usize::from(ca != *cb);
+            current[j + 1] = (previous[j] + cost).min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        previous = current;
+    }
+
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use pretty_assertions::assert_eq as pretty_assert_eq;
+    use sealed_test::prelude::*;
+    use simple_test_case::test_case;
+
+    const SCHEMA: Schema = Schema::Table(&[
+        (
+            "settings",
+            Schema::Table(&[
+                ("excluded", Schema::Value),
+                ("profiles", Schema::Map(&Schema::Value)),
+                ("source", Schema::Table(&[("url", Schema::Value)])),
+            ]),
+        ),
+        ("hooks", Schema::Map(&Schema::Tables(&[("pre", Schema::Value)]))),
+    ]);
+
+    #[test_case("[settings]\nexcluded = []\n"; "known keys")]
+    #[test_case("[settings.profiles]\nanything = []\n"; "map keys")]
+    #[test_case("[settings]\nsource = { url = \"x\" }\n"; "inline table")]
+    #[test_case("[[hooks.deploy]]\npre = \"x\"\n"; "array of tables")]
+    #[test_case("not = [valid"; "invalid toml")]
+    #[test]
+    fn smoke_check_schema(data: &str) -> Result<()> {
+        pretty_assert_eq!(check_schema(data, SCHEMA)?, data);
+        Ok(())
+    }
+
+    #[test_case("[settings]\nexclude = []\n", "\"settings.exclude\", did you mean \"excluded\"?"; "typo")]
+    #[test_case("[settings]\nsource = { urll = \"x\" }\n", "\"settings.source.urll\", did you mean \"url\"?"; "inline table")]
+    #[test_case("hooks.deploy = [{ pre = \"x\" }, { pree = \"x\" }]\n", "\"hooks.deploy[1].pree\", did you mean \"pre\"?"; "array")]
+    #[test_case("[bogus]\n", "\"bogus\""; "no suggestion")]
+    #[test]
+    fn check_schema_unknown_keys(data: &str, expect: &str) {
+        let error = format!("{:#}", check_schema(data, SCHEMA).unwrap_err());
+        assert!(error.contains(expect), "{error}");
+        assert!(error.contains("--no-strict"), "{error}");
+    }
+
+    #[sealed_test]
+    fn smoke_check_schema_lenient() -> Result<()> {
+        set_strict(false);
+        let result = check_schema("[settings]\nexclude = []\nexcluded = [\"a\"]\n", SCHEMA)?;
+        pretty_assert_eq!(result, "[settings]\nexcluded = [\"a\"]\n");
+        Ok(())
+    }
+}
@@ -0,0 +1,277 @@
+// SPDX-FileCopyrightText: 2025 Jason Pena <jasonpena@awkless.com>
+// SPDX-License-Identifier: MIT
+
+//! Encrypted configuration values.
+//!
+//! The cluster definition and hook configuration live in the root repository, which tends to be
+//! published. Any string value of these configuration files can be encrypted with [age] instead,
+//! such that secrets, e.g., tokens used by hooks or URLs of private repositories, never appear in
+//! plaintext. An encrypted value is simply an ASCII-armored age file. Encrypted values are
+//! decrypted in memory whenever a configuration file is parsed, as long as an age identity is
+//! available at `$OCD_AGE_IDENTITY`, or `$XDG_CONFIG_HOME/age/keys.txt` by default. Encrypted
+//! values that cannot be decrypted are left as they are.
+//!
+//! Encryption and decryption is left to the user's `age` binary, the same way downloading and
+//! unpacking archives is left to the user's `curl` and `tar` binaries.
+//!
+//! Nodes can also track whole files encrypted with age or GnuPG, which deployments decrypt into
+//! their working directory alias, see [`decrypt_file`].
+//!
+//! [age]: https://age-encryption.org
+
+use anyhow::{anyhow, Context, Result};
+use std::{
+    io::Write,
+    path::PathBuf,
+    process::{Command, Stdio},
+};
+use tracing::{debug, instrument, warn};
+
+/// First line of ASCII-armored age file, which marks a value as encrypted.
+pub const ARMOR_HEADER: &str = "-----BEGIN AGE ENCRYPTED FILE-----";
+
+/// Extensions of encrypted files, along with the binary that decrypts them.
+const FILE_EXTENSIONS: &[(&str, &str)] = &[(".age", "age"), (".gpg", "gpg"), (".asc", "gpg")];
+
+/// Determine if configuration value is encrypted.
+pub fn is_encrypted(value: impl AsRef<str>) -> bool {
+    value.as_ref().trim_start().starts_with(ARMOR_HEADER)
+}
+
+/// Get path to age identity used to decrypt configuration values.
+///
+/// Uses `$OCD_AGE_IDENTITY` if set, falling back to `$XDG_CONFIG_HOME/age/keys.txt`.
+///
+/// # Errors
+///
+/// - Will fail if configuration directory cannot be determined.
+pub fn identity_path() -> Result<PathBuf> {
+    if let Some(path) = std::env::var_os("OCD_AGE_IDENTITY").filter(|path| !path.is_empty()) {
+        let home = || super::home_dir().ok().map(|home| home.to_string_lossy().into_owned());
+        let path = shellexpand::tilde_with_context(&path.to_string_lossy(), home).into_owned();
+        return Ok(PathBuf::from(path));
+    }
+
+    super::in_home(dirs::config_dir())
+        .map(|path| path.join("age").join("keys.txt"))
+        .ok_or(anyhow!("Cannot determine path to configuration directory"))
+}
+
+/// Decrypt every encrypted string value of TOML configuration file.
+///
+/// Returns configuration file as is if it has no encrypted values, such that the `age` binary is
+/// only needed by users that actually encrypt something.
+///
+/// # Invariants
+///
+/// - Encrypted values are left encrypted if no age identity is available, or if they cannot be
+///   decrypted with it.
+/// - Malformed files are left for regular deserialization to report.
+///
+/// # Errors
+///
+/// - Will fail if path to age identity cannot be determined.
+#[instrument(skip(data), level = "debug")]
+pub(crate) fn decrypt_config(data: &str) -> Result<String> {
+    if !data.contains(ARMOR_HEADER) {
+        return Ok(data.into());
+    }
+
+    let Ok(mut document) = data.parse::<toml_edit::DocumentMut>() else {
+        return Ok(data.into());
+    };
+
+    let identity = identity_path()?;
+    if !identity.exists() {
+        warn!("No age identity at {identity:?}, keep encrypted values encrypted");
+        return Ok(data.into());
+    }
+
+    decrypt_item(document.as_item_mut());
+
+    Ok(document.to_string())
+}
+
+/// Decrypt configuration value if it is encrypted, leaving it as is otherwise.
+///
+/// Values that cannot be decrypted are left as they are.
+pub(crate) fn reveal(value: &str) -> String {
+    if !is_encrypted(value) {
+        return value.into();
+    }
+
+    decrypt(value).unwrap_or_else(|error| {
+        debug!("Keep value encrypted: {error:?}");
+        value.into()
+    })
+}
+
+/// Decrypt encrypted configuration value.
+///
+/// # Errors
+///
+/// - Will fail if no age identity is available.
+/// - Will fail if value cannot be decrypted with age identity.
+pub(crate) fn decrypt(value: &str) -> Result<String> {
+    let identity = identity_path()?;
+    if !identity.exists() {
+        return Err(anyhow!("No age identity at {identity:?} to decrypt value with"));
+    }
+
+    let identity = identity.into_os_string();
+    age(["--decrypt".as_ref(), "--identity".as_ref(), identity.as_os_str()], value)
+}
+
+/// Encrypt configuration value to recipients of age identity.
+///
+/// # Errors
+///
+/// - Will fail if no age identity is available.
+/// - Will fail if value cannot be encrypted.
+pub(crate) fn encrypt(value: &str) -> Result<String> {
+    let identity = identity_path()?;
+    if !identity.exists() {
+        return Err(anyhow!("No age identity at {identity:?} to keep value encrypted with"));
+    }
+
+    let identity = identity.into_os_string();
+    let args =
+        ["--encrypt".as_ref(), "--armor".as_ref(), "--identity".as_ref(), identity.as_os_str()];
+    let encrypted = age(args, value)?;
+
+    // INVARIANT: Armored age files always end with a newline.
+    Ok(format!("{}\n", encrypted.trim_end()))
+}
+
+/// Get path that encrypted file gets decrypted to, i.e., its path without its extension.
+///
+/// Returns [`None`] if path does not end in ".age", ".gpg", or ".asc".
+pub fn decrypted_path(path: &str) -> Option<&str> {
+    FILE_EXTENSIONS
+        .iter()
+        .find_map(|(extension, _)| path.strip_suffix(extension))
+        .filter(|path| !path.is_empty() && !path.ends_with('/'))
+}
+
+/// Decrypt contents of encrypted file, picking age or GnuPG by its extension.
+///
+/// Files ending in ".age" are decrypted with the age identity used for configuration values.
+/// Files ending in ".gpg" or ".asc" are decrypted with whatever keys GnuPG has, through
+/// gpg-agent(1) if they are protected by a passphrase.
+///
+/// # Errors
+///
+/// - Will fail if path has no extension of an encrypted file.
+/// - Will fail if no age identity is available for age files.
+/// - Will fail if contents cannot be decrypted.
+pub(crate) fn decrypt_file(path: &str, data: &[u8]) -> Result<Vec<u8>> {
+    let program = FILE_EXTENSIONS
+        .iter()
+        .find(|(extension, _)| path.ends_with(extension))
+        .map(|(_, program)| *program)
+        .ok_or_else(|| anyhow!("Cannot tell how {path:?} is encrypted from its extension"))?;
+
+    if program == "age" {
+        let identity = identity_path()?;
+        if !identity.exists() {
+            return Err(anyhow!("No age identity at {identity:?} to decrypt {path:?} with"));
+        }
+        let identity = identity.into_os_string();
+        return run(
+            program,
+            ["--decrypt".as_ref(), "--identity".as_ref(), identity.as_os_str()],
+            data,
+        );
+    }
+
+    run(program, ["--batch", "--quiet", "--decrypt"].map(std::ffi::OsStr::new), data)
+}
+
+fn decrypt_item(item: &mut toml_edit::Item) {
+    match item {
+        toml_edit::Item::Value(value) => decrypt_value(value),
+        toml_edit::Item::Table(table) => table.iter_mut().for_each(|(_, item)| decrypt_item(item)),
+        toml_edit::Item::ArrayOfTables(tables) => tables
+            .iter_mut()
+            .for_each(|table| table.iter_mut().for_each(|(_, item)| decrypt_item(item))),
+        toml_edit::Item::None => {}
+    }
+}
+
+fn decrypt_value(value: &mut toml_edit::Value) {
+    match value {
+        toml_edit::Value::String(string) if is_encrypted(string.value()) => {
+            match decrypt(string.value()) {
+                Ok(decrypted) => *value = decrypted.into(),
+                Err(error) => warn!("Keep value encrypted: {error:?}"),
+            }
+        }
+        toml_edit::Value::Array(array) => array.iter_mut().for_each(decrypt_value),
+        toml_edit::Value::InlineTable(table) => {
+            table.iter_mut().for_each(|(_, value)| decrypt_value(value))
+        }
+        _ => {}
+    }
+}
+
+fn age<'a>(args: impl IntoIterator<Item = &'a std::ffi::OsStr>, input: &str) -> Result<String> {
+    let output = run("age", args, input.as_bytes())?;
+    String::from_utf8(output).context("Output of age is not valid UTF-8")
+}
+
+fn run<'a>(
+    program: &str,
+    args: impl IntoIterator<Item = &'a std::ffi::OsStr>,
+    input: &[u8],
+) -> Result<Vec<u8>> {
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("Failed to run {program}, make sure it is installed"))?;
+
+    // INVARIANT: Close stdin once input is written, such that the program sees end of input.
+    child.stdin.take().expect("stdin is piped").write_all(input)?;
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow!("Command {program:?} failed:\nstderr: {}", stderr.trim_end()));
+    }
+
+    Ok(output.stdout)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use pretty_assertions::assert_eq as pretty_assert_eq;
+    use simple_test_case::test_case;
+
+    #[test_case("-----BEGIN AGE ENCRYPTED FILE-----\nabc\n", true; "armored")]
+    #[test_case("\n  -----BEGIN AGE ENCRYPTED FILE-----\nabc\n", true; "leading whitespace")]
+    #[test_case("https://some/url", false; "plaintext")]
+    #[test]
+    fn smoke_is_encrypted(value: &str, expect: bool) {
+        pretty_assert_eq!(is_encrypted(value), expect);
+    }
+
+    #[test_case(".ssh/id_ed25519.age", Some(".ssh/id_ed25519"); "age")]
+    #[test_case(".netrc.gpg", Some(".netrc"); "gpg")]
+    #[test_case("token.asc", Some("token"); "armored gpg")]
+    #[test_case(".age", None; "extension only")]
+    #[test_case(".ssh/config", None; "plaintext")]
+    #[test]
+    fn smoke_decrypted_path(path: &str, expect: Option<&str>) {
+        pretty_assert_eq!(decrypted_path(path), expect);
+    }
+
+    #[test]
+    fn smoke_decrypt_config_plaintext() -> Result<()> {
+        let data = "[settings]\nurl = \"https://some/url\" # comment\n";
+        pretty_assert_eq!(decrypt_config(data)?, data);
+        Ok(())
+    }
+}
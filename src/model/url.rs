@@ -0,0 +1,278 @@
+// SPDX-FileCopyrightText: 2025 Jason Pena <jasonpena@awkless.com>
+// SPDX-License-Identifier: MIT
+
+//! Git URL parser.
+//!
+//! Validates and normalizes the URLs that entries are cloned from, such that malformed URLs are
+//! caught when the cluster definition is loaded, rather than surfacing as cryptic libgit2 errors
+//! in the middle of a clone. Understands the same URL forms as Git itself:
+//!
+//! 1. URLs with a scheme, e.g., `https://host/user/repo.git` or `ssh://git@host:22/~user/repo`.
+//! 2. Scp-like shorthands, e.g., `git@host:user/repo.git` or `host:~user/repo`.
+//! 3. Local paths, e.g., `/srv/git/repo.git` or `~/repos/repo.git`.
+
+use super::home_dir;
+
+use anyhow::{anyhow, Result};
+use std::{fmt, str::FromStr};
+
+/// Schemes that Git can clone from.
+const SCHEMES: [&str; 5] = ["https", "http", "ssh", "git", "file"];
+
+/// Parsed Git URL.
+///
+/// # Invariants
+///
+/// - Scheme and host are always lowercase.
+/// - Path never ends with a slash.
+/// - Local paths never start with `~/`, because it is expanded to the user's home directory.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct GitUrl {
+    kind: GitUrlKind,
+    path: String,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+enum GitUrlKind {
+    Scheme { scheme: String, user: Option<String>, host: String, port: Option<u16> },
+    Scp { user: Option<String>, host: String },
+    Local,
+}
+
+impl GitUrl {
+    /// Parse and normalize Git URL.
+    ///
+    /// # Errors
+    ///
+    /// - Will fail if URL is empty or contains whitespace.
+    /// - Will fail if URL uses a scheme that Git cannot clone from.
+    /// - Will fail if URL is missing its host or repository path.
+    /// - Will fail if port is not a number.
+    /// - Will fail if local path refers to the home directory of another user.
+    pub fn parse(url: impl AsRef<str>) -> Result<Self> {
+        let url = url.as_ref().trim();
+        if url.is_empty() {
+            return Err(anyhow!("URL is empty"));
+        }
+
+        if url.chars().any(|c| c.is_whitespace() || c.is_control()) {
+            return Err(anyhow!("URL {url:?} contains whitespace"));
+        }
+
+        if let Some((scheme, rest)) = url.split_once("://") {
+            return Self::parse_scheme(url, scheme, rest);
+        }
+
+        // INVARIANT: Follow Git, a colon before the first slash makes it scp-like syntax.
+        match (url.find(':'), url.find('/')) {
+            (Some(colon), slash) if slash.is_none_or(|slash| colon < slash) => {
+                Self::parse_scp(url, &url[..colon], &url[colon + 1..])
+            }
+            _ => Self::parse_local(url),
+        }
+    }
+
+    /// Get host of URL, if it has one.
+    pub fn host(&self) -> Option<&str> {
+        match &self.kind {
+            GitUrlKind::Scheme { host, .. } | GitUrlKind::Scp { host, .. } => Some(host),
+            GitUrlKind::Local => None,
+        }
+    }
+
+    /// Get repository path of URL.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Determine if both URLs point to the same repository.
+    ///
+    /// Trailing `.git` suffixes are ignored, because hosts serve repositories either way.
+    pub fn is_same_repo(&self, other: &GitUrl) -> bool {
+        self.kind == other.kind && strip_git(&self.path) == strip_git(&other.path)
+    }
+
+    fn parse_scheme(url: &str, scheme: &str, rest: &str) -> Result<Self> {
+        let scheme = match scheme.to_lowercase().as_str() {
+            "git+ssh" | "ssh+git" => "ssh".to_string(),
+            scheme if SCHEMES.contains(&scheme) => scheme.to_string(),
+            _ => return Err(anyhow!("URL {url:?} uses unsupported scheme {scheme:?}")),
+        };
+
+        if scheme == "file" {
+            let path = trim_path(rest);
+            if !path.starts_with('/') {
+                return Err(anyhow!("URL {url:?} must use absolute path"));
+            }
+            return Ok(Self { kind: GitUrlKind::Local, path: format!("file://{path}") });
+        }
+
+        let (authority, path) = rest.split_once('/').unwrap_or((rest, ""));
+        let (user, host) = split_user(authority);
+        let (host, port) = match host.rsplit_once(':') {
+            // INVARIANT: Leave bracketed IPv6 addresses without a port alone.
+            Some((host, port)) if !port.ends_with(']') => {
+                let port =
+                    port.parse::<u16>().map_err(|_| anyhow!("URL {url:?} has invalid port"))?;
+                (host, Some(port))
+            }
+            _ => (host, None),
+        };
+
+        if host.is_empty() {
+            return Err(anyhow!("URL {url:?} is missing host"));
+        }
+
+        let path = trim_path(path);
+        if path.is_empty() {
+            return Err(anyhow!("URL {url:?} is missing repository path"));
+        }
+
+        Ok(Self {
+            kind: GitUrlKind::Scheme { scheme, user, host: host.to_lowercase(), port },
+            path: format!("/{path}"),
+        })
+    }
+
+    fn parse_scp(url: &str, authority: &str, path: &str) -> Result<Self> {
+        let (user, host) = split_user(authority);
+        if host.is_empty() {
+            return Err(anyhow!("URL {url:?} is missing host"));
+        }
+
+        let path = trim_path(path);
+        if path.is_empty() {
+            return Err(anyhow!("URL {url:?} is missing repository path"));
+        }
+
+        Ok(Self {
+            kind: GitUrlKind::Scp { user, host: host.to_lowercase() },
+            path: path.to_string(),
+        })
+    }
+
+    fn parse_local(url: &str) -> Result<Self> {
+        let path = match url.strip_prefix('~') {
+            Some("") => home_dir()?.to_string_lossy().into_owned(),
+            Some(rest) if rest.starts_with('/') => {
+                format!("{}{rest}", home_dir()?.to_string_lossy())
+            }
+            Some(_) => return Err(anyhow!("URL {url:?} refers to home directory of another user")),
+            None => url.to_string(),
+        };
+
+        let trimmed = trim_path(&path);
+        let path = if trimmed.is_empty() { "/".to_string() } else { trimmed.to_string() };
+
+        Ok(Self { kind: GitUrlKind::Local, path })
+    }
+}
+
+impl FromStr for GitUrl {
+    type Err = anyhow::Error;
+
+    fn from_str(url: &str) -> Result<Self> {
+        Self::parse(url)
+    }
+}
+
+impl fmt::Display for GitUrl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            GitUrlKind::Scheme { scheme, user, host, port } => {
+                write!(f, "{scheme}://")?;
+                if let Some(user) = user {
+                    write!(f, "{user}@")?;
+                }
+                write!(f, "{host}")?;
+                if let Some(port) = port {
+                    write!(f, ":{port}")?;
+                }
+                write!(f, "{}", self.path)
+            }
+            GitUrlKind::Scp { user, host } => {
+                if let Some(user) = user {
+                    write!(f, "{user}@")?;
+                }
+                write!(f, "{host}:{}", self.path)
+            }
+            GitUrlKind::Local => write!(f, "{}", self.path),
+        }
+    }
+}
+
+/// Validate and normalize Git URL into the form handed to Git.
+///
+/// # Errors
+///
+/// - Will fail if URL is invalid, see [`GitUrl::parse`].
+pub fn normalize_url(url: impl AsRef<str>) -> Result<String> {
+    Ok(GitUrl::parse(url)?.to_string())
+}
+
+fn split_user(authority: &str) -> (Option<String>, &str) {
+    match authority.rsplit_once('@') {
+        Some((user, host)) => (Some(user.to_string()), host),
+        None => (None, authority),
+    }
+}
+
+fn trim_path(path: &str) -> &str {
+    let trimmed = path.trim_end_matches('/');
+    if trimmed.is_empty() && path.starts_with('/') {
+        return "";
+    }
+    trimmed
+}
+
+fn strip_git(path: &str) -> &str {
+    path.strip_suffix(".git").unwrap_or(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use pretty_assertions::assert_eq as pretty_assert_eq;
+    use simple_test_case::test_case;
+
+    #[test_case("https://github.com/user/repo.git", "https://github.com/user/repo.git"; "https")]
+    #[test_case(" HTTPS://GitHub.com/user/repo/ ", "https://github.com/user/repo"; "case and slash")]
+    #[test_case("ssh://git@host:2222/~user/repo", "ssh://git@host:2222/~user/repo"; "ssh port")]
+    #[test_case("git+ssh://git@host/repo.git", "ssh://git@host/repo.git"; "ssh alias")]
+    #[test_case("git@GitHub.com:user/repo.git", "git@github.com:user/repo.git"; "scp")]
+    #[test_case("host:~user/repo/", "host:~user/repo"; "scp home")]
+    #[test_case("file:///srv/git/repo.git/", "file:///srv/git/repo.git"; "file")]
+    #[test_case("/srv/git/repo.git", "/srv/git/repo.git"; "absolute path")]
+    #[test_case("forge/repo.git", "forge/repo.git"; "relative path")]
+    #[test]
+    fn smoke_normalize_url(url: &str, expect: &str) -> Result<()> {
+        pretty_assert_eq!(normalize_url(url)?, expect);
+        Ok(())
+    }
+
+    #[test_case(""; "empty")]
+    #[test_case("https://host/some repo"; "whitespace")]
+    #[test_case("svn://host/repo"; "unsupported scheme")]
+    #[test_case("https:///repo"; "missing host")]
+    #[test_case("https://host"; "missing path")]
+    #[test_case("https://host:port/repo"; "bad port")]
+    #[test_case("git@:repo"; "scp missing host")]
+    #[test_case("git@host:"; "scp missing path")]
+    #[test_case("file://relative/repo"; "relative file")]
+    #[test_case("~user/repo"; "other home")]
+    #[test]
+    fn smoke_normalize_url_invalid(url: &str) {
+        assert!(normalize_url(url).is_err());
+    }
+
+    #[test_case("git@host:user/repo.git", "git@HOST:user/repo/", true; "same")]
+    #[test_case("https://host/user/repo", "https://host/user/repo.git", true; "git suffix")]
+    #[test_case("https://host/user/repo", "git@host:user/repo", false; "different syntax")]
+    #[test_case("https://host/user/repo", "https://host/user/other", false; "different path")]
+    #[test]
+    fn smoke_git_url_is_same_repo(left: &str, right: &str, expect: bool) -> Result<()> {
+        pretty_assert_eq!(GitUrl::parse(left)?.is_same_repo(&GitUrl::parse(right)?), expect);
+        Ok(())
+    }
+}
@@ -0,0 +1,164 @@
+// SPDX-FileCopyrightText: 2025 Jason Pena <jasonpena@awkless.com>
+// SPDX-License-Identifier: MIT
+
+//! Notifications about failures and drift.
+//!
+//! Nobody reads the output of deployments that run unattended, e.g., through cron(8) or a systemd
+//! timer. Root can name a notification command through its `notify` setting, which OCD runs
+//! whenever deployment fails or a check detects drift. The command receives a JSON payload
+//! describing what happened through `$OCD_EVENT`, such that it can mail the payload, or post it to
+//! a webhook through curl(1).
+
+use anyhow::{anyhow, Result};
+use run_script::{run_script, ScriptOptions};
+use serde::Serialize;
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    time::{SystemTime, UNIX_EPOCH},
+};
+use tracing::{debug, instrument};
+
+/// Events worth notifying about.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NotifyEvent {
+    /// Entries failed to deploy or undeploy.
+    Failure,
+
+    /// Check found entries that deployment would change.
+    Drift,
+}
+
+impl Display for NotifyEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Failure => write!(f, "failure"),
+            Self::Drift => write!(f, "drift"),
+        }
+    }
+}
+
+/// Notification about event of command.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Notification {
+    event: NotifyEvent,
+    command: String,
+    cluster: Option<String>,
+    time: u64,
+    entries: Vec<NotifiedEntry>,
+    errors: Vec<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+struct NotifiedEntry {
+    name: String,
+    outcome: String,
+}
+
+impl Notification {
+    /// Construct new notification about event of command.
+    pub fn new(event: NotifyEvent, command: impl Into<String>) -> Self {
+        let time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|time| time.as_secs())
+            .unwrap_or_default();
+
+        Self {
+            event,
+            command: command.into(),
+            cluster: None,
+            time,
+            entries: Vec::new(),
+            errors: Vec::new(),
+        }
+    }
+
+    /// Name cluster that event happened in.
+    pub fn cluster(mut self, name: Option<impl Into<String>>) -> Self {
+        self.cluster = name.map(Into::into);
+        self
+    }
+
+    /// Add entry affected by event, along with its outcome.
+    pub fn entry(mut self, name: impl Into<String>, outcome: impl Display) -> Self {
+        self.entries.push(NotifiedEntry { name: name.into(), outcome: outcome.to_string() });
+        self
+    }
+
+    /// Add error message that event produced.
+    pub fn error(mut self, message: impl Into<String>) -> Self {
+        self.errors.push(message.into());
+        self
+    }
+
+    /// Render notification as single JSON object.
+    ///
+    /// Keys are stable, such that notification commands can rely on them.
+    ///
+    /// # Errors
+    ///
+    /// - Will fail if notification cannot be serialized.
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(self)?)
+    }
+
+    /// Send notification through notification command.
+    ///
+    /// Command is run through the shell, with the JSON payload in `$OCD_EVENT`.
+    ///
+    /// # Errors
+    ///
+    /// - Will fail if notification cannot be serialized.
+    /// - Will fail if notification command cannot be run.
+    /// - Will fail if notification command exits with a non-zero exit code.
+    #[instrument(skip(self), level = "debug")]
+    pub fn send(&self, notify: &str) -> Result<()> {
+        let mut opts = ScriptOptions::new();
+        opts.env_vars = Some(HashMap::from([("OCD_EVENT".to_string(), self.to_json()?)]));
+        let (code, out, err) = run_script!(notify, opts)?;
+        debug!("Notification command exited with {code}\nstdout: {out}\nstderr: {err}");
+        if code != 0 {
+            return Err(anyhow!("Notification command exited with {code}: {}", err.trim()));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use pretty_assertions::assert_eq as pretty_assert_eq;
+    use sealed_test::prelude::*;
+
+    #[test]
+    fn smoke_notification_to_json() -> Result<()> {
+        let notification = Notification {
+            time: 1_700_000_000,
+            ..Notification::new(NotifyEvent::Failure, "deploy")
+        }
+        .cluster(Some("workstation"))
+        .entry("vim", "failed")
+        .error("Failed to deploy \"vim\"");
+        pretty_assert_eq!(
+            notification.to_json()?,
+            "{\"event\":\"failure\",\"command\":\"deploy\",\"cluster\":\"workstation\",\
+             \"time\":1700000000,\"entries\":[{\"name\":\"vim\",\"outcome\":\"failed\"}],\
+             \"errors\":[\"Failed to deploy \\\"vim\\\"\"]}"
+        );
+
+        Ok(())
+    }
+
+    #[sealed_test]
+    fn smoke_notification_send() -> Result<()> {
+        let notification = Notification::new(NotifyEvent::Drift, "deploy").entry("sh", "changed");
+        notification.send("printf '%s' \"$OCD_EVENT\" > event.json")?;
+        pretty_assert_eq!(std::fs::read_to_string("event.json")?, notification.to_json()?);
+        assert!(notification.send("exit 3").is_err());
+
+        Ok(())
+    }
+}
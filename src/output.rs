@@ -0,0 +1,212 @@
+// SPDX-FileCopyrightText: 2025 Jason Pena <jasonpena@awkless.com>
+// SPDX-License-Identifier: MIT
+
+//! Terminal output shared by the command set and the repository store.
+//!
+//! Long running operations like clones and fetches show their progress through progress bars
+//! that redraw themselves many times per second. Screen readers announce every redraw, and dumb
+//! terminals print every redraw on a line of its own. Thus, OCD can use plain output instead,
+//! where progress bars are never drawn, and each operation prints one status line when it starts
+//! and one when it finishes.
+//!
+//! Plain output is picked once per invocation, either by the user or because standard error is
+//! not a terminal that progress bars can be drawn on.
+//!
+//! Read-only commands like listings print human-readable tables by default. Scripts and status
+//! bars can ask for JSON or YAML instead, which commands serialize their rows into through serde,
+//! and print to standard output such that logging never gets in the way.
+
+use anyhow::Result;
+use clap::ValueEnum;
+use indicatif::{MultiProgress, ProgressBar, ProgressDrawTarget, ProgressStyle};
+use serde::Serialize;
+use std::{
+    io::IsTerminal,
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+};
+use tracing::info;
+
+static PLAIN: AtomicBool = AtomicBool::new(false);
+
+/// Use plain output for the rest of the invocation.
+pub fn set_plain(plain: bool) {
+    PLAIN.store(plain, Ordering::Relaxed);
+}
+
+/// Check if plain output is in use.
+pub fn is_plain() -> bool {
+    PLAIN.load(Ordering::Relaxed)
+}
+
+/// Determine if plain output should be used.
+///
+/// Plain output is used if asked for, if standard error is not a terminal, or if the terminal
+/// declares itself dumb through `$TERM`.
+pub fn wants_plain(asked: bool) -> bool {
+    asked
+        || !std::io::stderr().is_terminal()
+        || std::env::var("TERM").is_ok_and(|term| term == "dumb")
+}
+
+/// Formats of listings and other read-only command output.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// Human-readable table.
+    #[default]
+    Table,
+
+    /// Single JSON document.
+    Json,
+
+    /// Single YAML document.
+    Yaml,
+}
+
+/// Print data in given structured format to standard output.
+///
+/// Returns false without printing anything for table format, which callers render on their own.
+/// Data is only gathered if it gets printed.
+///
+/// # Errors
+///
+/// - Will fail if data cannot be serialized.
+pub(crate) fn print_structured<T: Serialize>(
+    format: OutputFormat,
+    data: impl FnOnce() -> T,
+) -> Result<bool> {
+    match render_structured(format, data)? {
+        Some(rendered) => print!("{rendered}"),
+        None => return Ok(false),
+    }
+
+    Ok(true)
+}
+
+// Render data as single JSON document on one line, or as YAML document in block style.
+fn render_structured<T: Serialize>(
+    format: OutputFormat,
+    data: impl FnOnce() -> T,
+) -> Result<Option<String>> {
+    let rendered = match format {
+        OutputFormat::Table => return Ok(None),
+        OutputFormat::Json => format!("{}\n", serde_json::to_string(&data())?),
+        OutputFormat::Yaml => serde_yaml::to_string(&data())?,
+    };
+
+    Ok(Some(rendered))
+}
+
+/// Construct new progress bar, which is never drawn under plain output.
+pub(crate) fn progress_bar() -> ProgressBar {
+    let bar = ProgressBar::no_length();
+    if is_plain() {
+        bar.set_draw_target(ProgressDrawTarget::hidden());
+    }
+
+    bar
+}
+
+/// Construct new group of progress bars, which is never drawn under plain output.
+pub(crate) fn multi_progress() -> MultiProgress {
+    if is_plain() {
+        return MultiProgress::with_draw_target(ProgressDrawTarget::hidden());
+    }
+
+    MultiProgress::new()
+}
+
+/// Start showing progress of operation described by message.
+///
+/// Prints a status line under plain output instead of animating the progress bar.
+///
+/// # Errors
+///
+/// - Will fail if progress bar template is invalid.
+pub(crate) fn start_progress(bar: &ProgressBar, msg: impl Into<String>) -> Result<()> {
+    let msg = msg.into();
+    if is_plain() {
+        info!("{msg}: started");
+        bar.set_message(msg);
+        return Ok(());
+    }
+
+    let style = ProgressStyle::with_template(
+        "{elapsed_precise:.green}  {msg:<50}  [{wide_bar:.yellow/blue}]",
+    )?
+    .progress_chars("-Cco.");
+    bar.set_style(style);
+    bar.set_message(msg);
+    bar.enable_steady_tick(Duration::from_millis(100));
+
+    Ok(())
+}
+
+/// Finish showing progress of operation that succeeded.
+///
+/// Prints a status line under plain output, and clears progress bar either way.
+pub(crate) fn finish_progress(bar: &ProgressBar) {
+    if is_plain() && !bar.message().is_empty() {
+        info!("{}: done", bar.message());
+    }
+    bar.finish_and_clear();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use pretty_assertions::assert_eq as pretty_assert_eq;
+    use sealed_test::prelude::*;
+
+    #[sealed_test]
+    fn smoke_plain_progress() -> Result<()> {
+        assert!(wants_plain(true));
+
+        set_plain(false);
+        let bar = progress_bar();
+        start_progress(&bar, "vim - https://example.com/vim.git")?;
+        finish_progress(&bar);
+
+        set_plain(true);
+        assert!(progress_bar().is_hidden());
+        assert!(multi_progress().add(ProgressBar::no_length()).is_hidden());
+        let bar = progress_bar();
+        start_progress(&bar, "vim - https://example.com/vim.git")?;
+        pretty_assert_eq!(bar.message(), "vim - https://example.com/vim.git");
+        finish_progress(&bar);
+
+        Ok(())
+    }
+
+    #[derive(Serialize)]
+    struct Row {
+        name: &'static str,
+        cloned: Option<bool>,
+        required_by: Vec<&'static str>,
+    }
+
+    #[test]
+    fn smoke_render_structured() -> Result<()> {
+        let rows = || {
+            vec![
+                Row { name: "vim", cloned: Some(true), required_by: vec!["sh", "x\"y"] },
+                Row { name: "root", cloned: None, required_by: Vec::new() },
+            ]
+        };
+
+        pretty_assert_eq!(render_structured(OutputFormat::Table, rows)?, None);
+        pretty_assert_eq!(
+            render_structured(OutputFormat::Json, rows)?.unwrap(),
+            "[{\"name\":\"vim\",\"cloned\":true,\"required_by\":[\"sh\",\"x\\\"y\"]},\
+             {\"name\":\"root\",\"cloned\":null,\"required_by\":[]}]\n"
+        );
+        pretty_assert_eq!(
+            render_structured(OutputFormat::Yaml, rows)?.unwrap(),
+            "- name: vim\n  cloned: true\n  required_by:\n  - sh\n  - x\"y\n- name: root\n  \
+             cloned: null\n  required_by: []\n"
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,211 @@
+// SPDX-FileCopyrightText: 2025 Jason Pena <jasonpena@awkless.com>
+// SPDX-License-Identifier: MIT
+
+//! Local usage statistics.
+//!
+//! Users that want to know which parts of their cluster they actually use can opt into recording
+//! usage statistics through the `usage_stats` setting of root. Statistics count how often each
+//! command runs along with how long it takes, and how often each node gets deployed. They are
+//! kept in OCD's state directory, and never leave the machine.
+//!
+//! Deployments are noted while a command runs, and written out along with the command itself once
+//! it finishes, such that statistics are only ever touched once per invocation.
+
+use crate::model::state_dir;
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fmt::Write as _,
+    fs::{create_dir_all, read_to_string, write},
+    path::PathBuf,
+    sync::Mutex,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use tracing::{debug, instrument};
+
+static DEPLOYED: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+/// Note that node was deployed during current invocation.
+pub fn note_deployed(name: impl Into<String>) {
+    DEPLOYED.lock().unwrap().push(name.into());
+}
+
+/// Usage statistics in OCD's state directory.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Deserialize, Serialize)]
+pub struct UsageStats {
+    #[serde(skip)]
+    path: PathBuf,
+
+    #[serde(default)]
+    commands: BTreeMap<String, CommandStats>,
+
+    #[serde(default)]
+    nodes: BTreeMap<String, NodeStats>,
+}
+
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy, Deserialize, Serialize)]
+struct CommandStats {
+    runs: u64,
+    total_ms: u64,
+}
+
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy, Deserialize, Serialize)]
+struct NodeStats {
+    deploys: u64,
+    last_deployed: u64,
+}
+
+impl UsageStats {
+    /// Load usage statistics from state directory.
+    ///
+    /// # Errors
+    ///
+    /// - Will fail if state directory cannot be determined.
+    #[instrument(level = "debug")]
+    pub fn load() -> Result<Self> {
+        Ok(Self::load_from(state_dir()?.join("stats.toml")))
+    }
+
+    /// Load usage statistics from specific path.
+    ///
+    /// # Invariants
+    ///
+    /// - Missing or unreadable statistics files just start empty statistics, because losing
+    ///   statistics is never worth failing a command over.
+    pub fn load_from(path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        let stats = read_to_string(&path)
+            .ok()
+            .and_then(|data| toml::de::from_str::<UsageStats>(&data).ok())
+            .unwrap_or_else(|| {
+                debug!("No usable usage statistics at {path:?}, start with empty statistics");
+                UsageStats::default()
+            });
+
+        Self { path, ..stats }
+    }
+
+    /// Record invocation of command, along with every node noted as deployed during it.
+    pub fn record_invocation(&mut self, command: &str, elapsed: Duration) {
+        let stats = self.commands.entry(command.into()).or_default();
+        stats.runs += 1;
+        stats.total_ms += u64::try_from(elapsed.as_millis()).unwrap_or(u64::MAX);
+
+        let now = unix_now();
+        for name in DEPLOYED.lock().unwrap().drain(..) {
+            let stats = self.nodes.entry(name).or_default();
+            stats.deploys += 1;
+            stats.last_deployed = now;
+        }
+    }
+
+    /// Check if nothing was recorded yet.
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty() && self.nodes.is_empty()
+    }
+
+    /// Forget everything recorded so far.
+    pub fn clear(&mut self) {
+        self.commands.clear();
+        self.nodes.clear();
+    }
+
+    /// Save usage statistics to their statistics file.
+    ///
+    /// # Errors
+    ///
+    /// - Will fail if statistics file or its parent directory cannot be written.
+    pub fn save(&self) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            create_dir_all(parent).with_context(|| format!("Failed to create {parent:?}"))?;
+        }
+        let data = toml::ser::to_string(self)?;
+        write(&self.path, data).with_context(|| format!("Failed to write {:?}", self.path))?;
+
+        Ok(())
+    }
+
+    /// Render usage statistics, most used commands and nodes first.
+    pub fn render(&self) -> String {
+        let mut commands: Vec<_> = self.commands.iter().collect();
+        commands.sort_by(|(a_name, a), (b_name, b)| b.runs.cmp(&a.runs).then(a_name.cmp(b_name)));
+        let mut nodes: Vec<_> = self.nodes.iter().collect();
+        nodes
+            .sort_by(|(a_name, a), (b_name, b)| b.deploys.cmp(&a.deploys).then(a_name.cmp(b_name)));
+
+        let width = commands
+            .iter()
+            .map(|(name, _)| name.len())
+            .chain(nodes.iter().map(|(name, _)| name.len()))
+            .max()
+            .unwrap_or_default();
+
+        let mut rendered = String::from("Commands:\n");
+        for (name, stats) in commands {
+            let total = Duration::from_millis(stats.total_ms);
+            let average = total / u32::try_from(stats.runs.max(1)).unwrap_or(u32::MAX);
+            let _ = writeln!(
+                rendered,
+                "  {name:<width$}  {:>6} runs  {total:>10.2?} total  {average:>10.2?} average",
+                stats.runs
+            );
+        }
+
+        rendered.push_str("Nodes:\n");
+        let now = unix_now();
+        for (name, stats) in nodes {
+            let days = now.saturating_sub(stats.last_deployed) / (60 * 60 * 24);
+            let _ = writeln!(
+                rendered,
+                "  {name:<width$}  {:>6} deploys  last deployed {days} days ago",
+                stats.deploys
+            );
+        }
+
+        rendered
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|time| time.as_secs()).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use pretty_assertions::assert_eq as pretty_assert_eq;
+    use sealed_test::prelude::*;
+
+    #[sealed_test]
+    fn smoke_usage_stats_record_invocation() -> Result<()> {
+        let path = std::env::current_dir()?.join("state/stats.toml");
+        let mut stats = UsageStats::load_from(&path);
+        assert!(stats.is_empty());
+
+        note_deployed("vim");
+        note_deployed("sh");
+        stats.record_invocation("deploy", Duration::from_millis(300));
+        note_deployed("vim");
+        stats.record_invocation("deploy", Duration::from_millis(100));
+        stats.record_invocation("ls", Duration::from_millis(20));
+        stats.save()?;
+
+        let stats = UsageStats::load_from(&path);
+        pretty_assert_eq!(stats.commands["deploy"], CommandStats { runs: 2, total_ms: 400 });
+        pretty_assert_eq!(stats.nodes["vim"].deploys, 2);
+        pretty_assert_eq!(stats.nodes["sh"].deploys, 1);
+
+        let rendered = stats.render();
+        let deploy = rendered.find("deploy").unwrap();
+        let ls = rendered.find("ls ").unwrap();
+        assert!(deploy < ls, "most used command should come first:\n{rendered}");
+        assert!(rendered.contains("200.00ms average"));
+        let vim = rendered.lines().find(|line| line.trim_start().starts_with("vim")).unwrap();
+        assert!(vim.ends_with("2 deploys  last deployed 0 days ago"));
+
+        Ok(())
+    }
+}
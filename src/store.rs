@@ -10,34 +10,101 @@
 //! such that a top-down heirarchy is followed, with the cluster definition at the top and
 //! repository store at the bottom.
 
-use crate::model::{
-    cluster::{Cluster, DeploymentKind, NodeEntry, RootEntry, WorkDirAlias},
-    config_dir, data_dir, glob_match,
+mod agent;
+mod archive;
+mod artifacts;
+mod daemon;
+mod journal;
+mod layout;
+mod lock;
+mod lockfile;
+mod manifest;
+mod preflight;
+mod render;
+mod report;
+mod secrets;
+mod status;
+mod trace;
+mod transaction;
+
+use crate::{
+    model::{
+        cluster::{
+            check_min_ocd_version, ClonePolicy, Cluster, DeploymentKind, NodeEntry, NodePin,
+            NodeSource, PullStrategy, RootEntry, WorkDirAlias,
+        },
+        config_dir, data_dir, expand_path, home_dir, home_env, is_writable,
+        secret::is_encrypted,
+        url::{normalize_url, GitUrl},
+    },
+    output::{
+        finish_progress, is_plain, multi_progress, print_structured, progress_bar, start_progress,
+        OutputFormat,
+    },
 };
+pub(crate) use agent::SshAgent;
+use archive::{sha256_hex, UnpackedArchive, DIGEST_KEY};
+use artifacts::DeployArtifacts;
+pub use artifacts::{prune_artifacts, stale_artifacts};
+pub(crate) use daemon::lock_sync_daemon;
+pub use daemon::{is_sync_daemon_running, ConfigWatcher, SyncReconcile, SyncStatus, SyncTrigger};
+pub(crate) use journal::DeployJournal;
+use layout::{layout_path, move_node_repo, prune_empty_parents, store_layout};
+pub use layout::{migrate_store_layout, orphaned_repos, set_store_layout, store_layout_changed};
+use lock::RepoLock;
+pub(crate) use lockfile::ClusterLock;
+use manifest::{DeployManifest, LinkMode};
+pub use preflight::DeployPreflight;
+use render::{remove_rendered, render_templates};
+use report::{DeployReport, DeployedEntry};
+use secrets::{reveal_secrets, shred_secrets};
+use status::{EntryStatus, StatusCache};
+pub(crate) use trace::redact_credentials;
+use trace::traced;
+pub use trace::{git_trail, set_trace_git, wants_trace_git};
+pub use transaction::{
+    begin_transaction, PriorDeployment, Transaction, TransactionGuard, TransactionStep,
+};
+use transaction::{is_transaction_open, record_step, TransactionLog};
 
 use anyhow::{anyhow, Context, Result};
 use auth_git2::{GitAuthenticator, Prompter};
 use beau_collector::BeauCollector as _;
 use futures::{stream, StreamExt};
 use git2::{
-    build::RepoBuilder, Config, FetchOptions, ObjectType, RemoteCallbacks, Repository,
-    RepositoryInitOptions,
+    build::{CheckoutBuilder, RepoBuilder},
+    AutotagOption, Config, ConfigLevel, Direction, FetchOptions, FetchPrune, Index,
+    IndexEntryExtendedFlag, ObjectType, Oid, Remote, RemoteCallbacks, Repository,
+    RepositoryInitOptions, Status, StatusOptions, TreeWalkMode, TreeWalkResult,
 };
-use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
-use inquire::{Password, Text};
+use indicatif::{HumanBytes, MultiProgress, ProgressBar};
+use inquire::{prompt_confirmation, Password, Text};
+use serde::{Deserialize, Serialize};
 use std::{
-    collections::VecDeque,
+    collections::{BTreeMap, BTreeSet, VecDeque},
     ffi::{OsStr, OsString},
     fmt::Write as FmtWrite,
-    fs::{remove_dir_all, File},
+    fs::{read_dir, remove_dir_all, File},
+    io,
     io::Write as IoWrite,
     path::{Path, PathBuf},
     process::Command,
     sync::{Arc, Mutex},
-    time::{Duration, Instant},
+    time::{Duration, Instant, UNIX_EPOCH},
 };
 use tracing::{debug, info, instrument, trace, warn};
 
+static CLONE_POLICY: Mutex<ClonePolicy> = Mutex::new(ClonePolicy::Always);
+
+/// Use policy for cloning nodes missing from repository store for the rest of the invocation.
+///
+/// # Panics
+///
+/// - Will panic if mutex guard fails to lock.
+pub fn set_clone_policy(policy: ClonePolicy) {
+    *CLONE_POLICY.lock().unwrap() = policy;
+}
+
 /// Root entry in repository store.
 #[derive(Debug)]
 pub struct Root {
@@ -52,22 +119,24 @@ impl Root {
     ///
     /// # Errors
     ///
+    /// - Will fail if URL is invalid.
     /// - Will fail if clone itself fails.
     /// - Will fail if root configuration file could not be extracted.
     /// - Will fail if deployment of root fails.
     #[instrument(skip(url), level = "debug")]
     pub fn new_clone(url: impl AsRef<str>) -> Result<Self> {
         trace!("Clone new root repository");
-        let bar = ProgressBar::no_length();
+        let url = normalize_url(url)?;
+        let bar = progress_bar();
         let entry = RepoEntry::builder("root")?
-            .url(url.as_ref())
+            .url(url)
             .deployment_kind(DeploymentKind::BareAlias)
             .work_dir_alias(WorkDirAlias::new(config_dir()?))
             .authentication_prompter(ProgressBarAuthenticator::new(ProgressBarKind::SingleBar(
                 bar.clone(),
             )))
             .clone(&bar)?;
-        bar.finish_and_clear();
+        finish_progress(&bar);
 
         let deployer = RepoEntryDeployer::new(&entry);
         let mut root = Self { entry, deployer };
@@ -76,11 +145,58 @@ pub fn new_clone(url: impl AsRef<str>) -> Result<Self> {
         std::fs::create_dir_all(config_dir()?)?;
         root.entry.set_deployment(DeploymentKind::BareAlias, config.settings.work_dir_alias);
         root.deployer.add_excluded(config.settings.excluded.iter().flatten());
-        root.deployer.deploy_with(BareAliasDeployment, &root.entry, DeployAction::Deploy)?;
+
+        // INVARIANT: Cloning never fails over files already in working directory alias, nor
+        // loses them.
+        root.deployer.set_collisions(CollisionPolicy::Backup);
+        root.deployer.deploy_with(root.deployer.bare_alias(), &root.entry, DeployAction::Deploy)?;
 
         Ok(root)
     }
 
+    /// Check if root was already cloned from remote URL.
+    ///
+    /// Clones of a cluster that failed after root was cloned, e.g., because some node could not be
+    /// cloned, can resume with the remaining nodes instead of cloning root all over again.
+    ///
+    /// # Invariants
+    ///
+    /// - Partial clones of root, i.e., root without commits, or root that was never deployed, are
+    ///   removed from repository store, such that root can be cloned again.
+    ///
+    /// # Errors
+    ///
+    /// - Will fail if URL is invalid.
+    /// - Will fail if root was cloned from another URL, or was initialized instead.
+    /// - Will fail if partial clone of root cannot be removed.
+    #[instrument(skip(url), level = "debug")]
+    pub fn is_cloned_from(url: impl AsRef<str>) -> Result<bool> {
+        let url = normalize_remote(url)?;
+        let path = data_dir()?.join("root");
+        if !path.exists() {
+            return Ok(false);
+        }
+
+        let entry = RepoEntry::builder("root")?.open();
+        let complete = entry.as_ref().is_ok_and(|entry| entry.is_empty().is_ok_and(|empty| !empty))
+            && config_dir()?.join("root.toml").exists();
+        if !complete {
+            warn!("Remove partial clone of root at {path:?}");
+            remove_dir_all(&path).with_context(|| format!("Failed to remove {path:?}"))?;
+            return Ok(false);
+        }
+
+        let origin = entry?.origin_url();
+        if origin.as_deref().and_then(|origin| normalize_remote(origin).ok()) != Some(url) {
+            return Err(anyhow!(
+                "Root of another cluster already exists at {path:?} with origin {origin:?}, \
+                 remove it with \"ocd rm root\" first"
+            ));
+        }
+
+        Ok(true)
+    }
+
     /// Open existing root in repository store.
     ///
     /// Will ensure that root is always deployed no matter what.
@@ -96,13 +212,27 @@ pub fn new_open(entry: &RootEntry) -> Result<Self> {
 
         root.entry.set_deployment(DeploymentKind::BareAlias, entry.settings.work_dir_alias.clone());
         root.deployer.add_excluded(entry.settings.excluded.iter().flatten());
-        root.deployer.deploy_with(RootDeployment, &root.entry, DeployAction::Deploy)?;
+        root.deployer.add_inline_subdirs(entry.settings.inline_subdirs.iter());
+
+        // INVARIANT: Read-only filesystems keep whatever root deployed last, such that commands
+        // that only read the cluster still work.
+        if let Err(error) =
+            root.deployer.deploy_with(RootDeployment, &root.entry, DeployAction::Deploy)
+        {
+            if is_writable(&entry.settings.work_dir_alias.0) && is_writable(root.entry.path()) {
+                return Err(error);
+            }
+            debug!("Keep last deployment of root on read-only filesystem: {error:?}");
+        }
 
         Ok(root)
     }
 
     /// Initialize new empty root in repository store.
     ///
+    /// Initial branch is named after the default branch setting of root, falling back to the
+    /// `init.defaultBranch` setting of the user's Git configuration.
+    ///
     /// # Errors
     ///
     /// Will fail if root could be initialized for whatever reason.
@@ -112,9 +242,11 @@ pub fn new_init(root: &RootEntry) -> Result<Self> {
         let entry = RepoEntry::builder("root")?
             .deployment_kind(DeploymentKind::BareAlias)
             .work_dir_alias(root.settings.work_dir_alias.clone())
+            .initial_branch(root.settings.default_branch.clone())
             .init()?;
         let mut deployer = RepoEntryDeployer::new(&entry);
         deployer.add_excluded(root.settings.excluded.iter().flatten());
+        deployer.add_inline_subdirs(root.settings.inline_subdirs.iter());
 
         Ok(Self { entry, deployer })
     }
@@ -126,8 +258,87 @@ pub fn new_init(root: &RootEntry) -> Result<Self> {
     /// # Errors
     ///
     /// Will fail if deployment for given action fails for whatever reason.
-    pub fn deploy(&self, action: DeployAction) -> Result<()> {
-        self.deployer.deploy_with(RootDeployment, &self.entry, action)
+    pub fn deploy(&self, action: DeployAction) -> Result<DeployOutcome> {
+        transacted("root", &self.entry, &self.deployer.excluded, action, || {
+            self.deployer.deploy_with(RootDeployment, &self.entry, action)
+        })
+    }
+
+    /// Determine outcome that deployment action would have, without deploying anything.
+    ///
+    /// # Errors
+    ///
+    /// Will fail if deployment state of root cannot be determined.
+    pub fn plan(&self, action: DeployAction) -> Result<DeployOutcome> {
+        if self.entry.is_empty()? || action == DeployAction::Undeploy {
+            return Ok(DeployOutcome::Skipped);
+        }
+
+        if action == DeployAction::Deploy && !self.deployer.excluded.exists() {
+            return Ok(DeployOutcome::Changed);
+        }
+
+        plan_action(&self.entry, &self.deployer.excluded, action)
+    }
+
+    /// Rewrite sparsity rules of root and checkout its index again.
+    ///
+    /// Useful when root exclusion rules changed after root got deployed, e.g., a freshly cloned
+    /// root that did not know about the inline nodes of its cluster yet.
+    ///
+    /// # Errors
+    ///
+    /// Will fail if sparsity rules cannot be written, or checkout fails.
+    pub fn refresh(&self) -> Result<()> {
+        if self.entry.is_empty()? {
+            return Ok(());
+        }
+
+        self.deployer.excluded.write_rules(ExcludeAction::ExcludeUnwanted)?;
+        let output = self.entry.gitcall_non_interactive(["checkout"])?;
+        info!("Refresh deployment of root\n{output}");
+
+        Ok(())
+    }
+
+    /// Pull root from its upstream.
+    ///
+    /// See [`Node::pull`].
+    ///
+    /// # Errors
+    ///
+    /// - Will fail if origin remote cannot be fetched.
+    /// - Will fail if root cannot be fast-forwarded, rebased, or merged.
+    pub fn pull(&self, diverged: impl FnOnce() -> Result<PullStrategy>) -> Result<PullOutcome> {
+        self.entry.pull(diverged)
+    }
+
+    /// Pull root from its upstream that was fetched already, e.g., through [`MultiEntryFetch`].
+    ///
+    /// See [`Node::pull_fetched`].
+    ///
+    /// # Errors
+    ///
+    /// - Will fail if root cannot be fast-forwarded, rebased, or merged.
+    pub fn pull_fetched(
+        &self,
+        diverged: impl FnOnce() -> Result<PullStrategy>,
+    ) -> Result<PullOutcome> {
+        self.entry.pull_fetched(diverged)
+    }
+
+    /// Allow deployment to overwrite sparse checkout file of root, even if OCD did not write it.
+    pub fn set_force(&mut self, force: bool) {
+        self.deployer.excluded.set_force(force);
+    }
+
+    /// Determine who owns sparse checkout file of root.
+    ///
+    /// # Errors
+    ///
+    /// Will fail if sparse checkout file exists, but cannot be read.
+    pub fn sparse_checkout_state(&self) -> Result<SparseCheckoutState> {
+        self.deployer.excluded.state()
     }
 
     /// Determine if root is currently deployed at specific state.
@@ -140,6 +351,26 @@ pub fn is_deployed(&self, state: DeployState) -> Result<bool> {
         is_deployed(&self.entry, &self.deployer.excluded, state)
     }
 
+    /// Determine deployment status of root at specific state, explaining which files are missing
+    /// or modified.
+    ///
+    /// # Errors
+    ///
+    /// Will fail if tracked files cannot be listed, or deployed files cannot be read.
+    pub fn deployment_status(&self, state: DeployState) -> Result<DeploymentStatus> {
+        scan_deployment(&self.entry, &self.deployer.excluded, state, true)
+    }
+
+    /// List deployed files of root that drifted from HEAD, i.e., were modified or removed in its
+    /// working directory alias since deployment.
+    ///
+    /// # Errors
+    ///
+    /// Will fail if tracked files cannot be listed, or deployed files cannot be read.
+    pub fn drifted_files(&self) -> Result<Vec<PathBuf>> {
+        drifted_files(&self.entry, &self.deployer.excluded)
+    }
+
     /// Nuke root entry from repository store.
     ///
     /// # Errors
@@ -148,13 +379,131 @@ pub fn is_deployed(&self, state: DeployState) -> Result<bool> {
     /// - Will fail if root repository cannot be removed from repository store.
     #[instrument(skip(self), level = "debug")]
     pub fn nuke(&self) -> Result<()> {
-        self.deployer.deploy_with(BareAliasDeployment, &self.entry, DeployAction::Undeploy)?;
+        self.deployer.deploy_with(
+            self.deployer.bare_alias(),
+            &self.entry,
+            DeployAction::Undeploy,
+        )?;
         remove_dir_all(self.path())?;
         info!("Nuke {:?} from cluster", self.entry.name());
 
         Ok(())
     }
 
+    /// Undeploy root, even though root is otherwise always deployed.
+    ///
+    /// Only meant for leaving OCD behind, because the cluster definition is gone from the working
+    /// directory alias of root afterwards.
+    ///
+    /// # Errors
+    ///
+    /// - Will fail if root entry cannot be undeployed.
+    #[instrument(skip(self), level = "debug")]
+    pub fn force_undeploy(&self) -> Result<DeployOutcome> {
+        self.deployer.deploy_with(self.deployer.bare_alias(), &self.entry, DeployAction::Undeploy)
+    }
+
+    /// List files of root present in its working directory alias, as absolute paths.
+    ///
+    /// # Errors
+    ///
+    /// - Will fail if files of root cannot be listed.
+    pub fn deployed_files(&self) -> Result<Vec<PathBuf>> {
+        deployed_files(&self.entry)
+    }
+
+    /// Read TOML files staged in index of root that belong to the configuration directory.
+    ///
+    /// Paths are relative to the configuration directory, e.g., `nodes/vim.toml`. Honors
+    /// `$GIT_INDEX_FILE`, such that Git hooks see the index that is about to be committed, e.g.,
+    /// the temporary index of `git commit --all`. Conflicted files are skipped.
+    ///
+    /// # Errors
+    ///
+    /// - Will fail if configuration directory cannot be determined.
+    /// - Will fail if index or staged blobs cannot be read.
+    pub fn staged_config_files(&self) -> Result<BTreeMap<PathBuf, String>> {
+        let repository = &self.entry.repository;
+        let prefix = self.config_prefix()?;
+        let index = match std::env::var_os("GIT_INDEX_FILE").map(PathBuf::from) {
+            Some(path) if path.is_relative() && !path.exists() => {
+                Index::open(&repository.path().join(path))?
+            }
+            Some(path) => Index::open(&path)?,
+            None => repository.index()?,
+        };
+
+        let mut files = BTreeMap::new();
+        for entry in index.iter().filter(|entry| (entry.flags >> 12) & 0x3 == 0) {
+            let path = PathBuf::from(String::from_utf8_lossy(&entry.path).into_owned());
+            let Ok(relative) = path.strip_prefix(&prefix) else {
+                continue;
+            };
+            if relative.extension().is_none_or(|extension| extension != "toml") {
+                continue;
+            }
+
+            let blob = repository.find_blob(entry.id)?;
+            let data = String::from_utf8_lossy(blob.content()).into_owned();
+            files.insert(relative.to_path_buf(), data);
+        }
+
+        Ok(files)
+    }
+
+    /// Read TOML files at the upstream of root that belong to the configuration directory.
+    ///
+    /// Works like [`Root::staged_config_files`], but reads the tree of the commit that the
+    /// upstream of root points to, e.g., once it got fetched. Gives nothing if root has no
+    /// upstream.
+    ///
+    /// # Errors
+    ///
+    /// - Will fail if configuration directory cannot be determined.
+    /// - Will fail if upstream commit or its blobs cannot be read.
+    pub fn upstream_config_files(&self) -> Result<Option<BTreeMap<PathBuf, String>>> {
+        let Some((_, upstream)) = self.entry.head_and_upstream() else {
+            return Ok(None);
+        };
+        let repository = &self.entry.repository;
+        let prefix = self.config_prefix()?;
+        let tree = repository.find_commit(upstream)?.tree()?;
+
+        let mut files = BTreeMap::new();
+        let mut results = Vec::new();
+        tree.walk(TreeWalkMode::PreOrder, |dir, entry| {
+            if entry.kind() != Some(ObjectType::Blob) {
+                return TreeWalkResult::Ok;
+            }
+            let path = Path::new(dir).join(bytes_to_path(entry.name_bytes()));
+            let Ok(relative) = path.strip_prefix(&prefix) else {
+                return TreeWalkResult::Ok;
+            };
+            if relative.extension().is_none_or(|extension| extension != "toml") {
+                return TreeWalkResult::Ok;
+            }
+
+            match repository.find_blob(entry.id()) {
+                Ok(blob) => {
+                    let data = String::from_utf8_lossy(blob.content()).into_owned();
+                    files.insert(relative.to_path_buf(), data);
+                }
+                Err(error) => results.push(Err(anyhow::Error::from(error))),
+            }
+            TreeWalkResult::Ok
+        })?;
+        results.into_iter().bcollect::<Vec<()>>()?;
+
+        Ok(Some(files))
+    }
+
+    fn config_prefix(&self) -> Result<PathBuf> {
+        Ok(config_dir()?
+            .strip_prefix(&self.entry.work_dir_alias.0)
+            .map(Path::to_path_buf)
+            .unwrap_or_default())
+    }
+
     /// Current branch of root repository.
     ///
     /// Uses lossy UTF-8 variation of branch pointed to by HEAD.
@@ -171,6 +520,82 @@ pub fn path(&self) -> &Path {
         self.entry.path()
     }
 
+    /// Get commit ID that HEAD of root currently points to.
+    pub fn head_id(&self) -> Option<String> {
+        self.entry.head_id()
+    }
+
+    /// List untracked files next to tracked files of root.
+    ///
+    /// # Errors
+    ///
+    /// Will fail if index cannot be read, or gitignore rules cannot be checked.
+    pub fn untracked_near_tracked(&self) -> Result<Vec<PathBuf>> {
+        self.entry.untracked_near_tracked()
+    }
+
+    /// Get working tree status of root.
+    ///
+    /// # Errors
+    ///
+    /// Will fail if status, gitignore rules, or commit graph cannot be obtained.
+    pub fn worktree_status(&self) -> Result<WorktreeStatus> {
+        self.entry.worktree_status()
+    }
+
+    /// Get URL of origin remote of root, if it has one.
+    pub fn origin_url(&self) -> Option<String> {
+        self.entry.origin_url()
+    }
+
+    /// Point origin remote of root to new URL, creating it if needed.
+    ///
+    /// Root has no URL in its cluster definition, so its origin remote is the only place its URL
+    /// is kept.
+    ///
+    /// # Errors
+    ///
+    /// Will fail if origin remote cannot be created or modified.
+    pub fn set_origin(&self, url: impl AsRef<str>) -> Result<()> {
+        self.entry.set_origin(url.as_ref())
+    }
+
+    /// Commit changes of file in working directory alias of root, including its removal.
+    ///
+    /// Only the file itself is committed, leaving anything else the user staged alone. Untracked
+    /// files count as changed, even though root hides them from "git status". Returns false
+    /// without committing anything if the file did not change.
+    ///
+    /// # Errors
+    ///
+    /// Will fail if Git binary fails to stage or commit the file.
+    #[instrument(skip(self), level = "debug")]
+    pub fn commit_file(&self, path: &Path, message: &str) -> Result<bool> {
+        let path = path.as_os_str();
+        let status = self.entry.gitcall_non_interactive([
+            OsStr::new("status"),
+            OsStr::new("--porcelain"),
+            OsStr::new("--untracked-files=all"),
+            OsStr::new("--"),
+            path,
+        ])?;
+        if status.trim().is_empty() {
+            return Ok(false);
+        }
+
+        self.entry.gitcall_non_interactive([OsStr::new("add"), OsStr::new("--"), path])?;
+        self.entry.gitcall_non_interactive([
+            OsStr::new("commit"),
+            OsStr::new("--quiet"),
+            OsStr::new("-m"),
+            OsStr::new(message),
+            OsStr::new("--"),
+            path,
+        ])?;
+
+        Ok(true)
+    }
+
     /// Make interactive system call to user's Git binary.
     ///
     /// # Errors
@@ -207,6 +632,7 @@ pub(crate) fn extract_root_config(&self) -> Result<RootEntry> {
         };
 
         let content = String::from_utf8_lossy(blob.content()).into_owned();
+        check_min_ocd_version(&content)?;
         let root: RootEntry = toml::de::from_str(&content)?;
         debug!("Extracted the following content from 'root.toml'\n{root:?}");
 
@@ -224,53 +650,215 @@ pub struct Node {
 impl Node {
     /// Initialize new node repository in repository store.
     ///
+    /// Initial branch is named after `default_branch`, which should be the default branch setting
+    /// of root, falling back to the `init.defaultBranch` setting of the user's Git configuration.
+    ///
     /// # Errors
     ///
     /// Will fail if repository could not be initialized for whatever reason.
     #[instrument(skip(name, node), level = "debug")]
-    pub fn new_init(name: impl AsRef<str>, node: &NodeEntry) -> Result<Self> {
+    pub fn new_init(
+        name: impl AsRef<str>,
+        node: &NodeEntry,
+        default_branch: Option<&str>,
+    ) -> Result<Self> {
+        if node.settings.deployment.kind.is_inline() {
+            return Err(anyhow!(
+                "Node {:?} is inline, initialize root and add files to its subdir instead",
+                name.as_ref()
+            ));
+        }
+
         info!("Initialize node repository {:?}", name.as_ref());
         let entry = RepoEntry::builder(name.as_ref())?
+            .url(&node.settings.url)
             .deployment_kind(node.settings.deployment.kind.clone())
             .work_dir_alias(node.settings.deployment.work_dir_alias.clone())
+            .initial_branch(default_branch.map(Into::into))
             .init()?;
         let mut deployer = RepoEntryDeployer::new(&entry);
-        deployer.add_excluded(node.settings.excluded.iter().flatten());
+        deployer.add_excluded(
+            node.settings.excluded.iter().chain(node.settings.templates.iter()).flatten(),
+        );
+        deployer.add_included(node.settings.included.iter().flatten());
+        deployer.add_secrets(node.settings.encrypted.iter().flatten());
+        deployer.add_templates(node.settings.templates.iter().flatten());
 
         Ok(Self { entry, deployer })
     }
 
+    /// Adopt existing repository outside the repository store as node repository.
+    ///
+    /// Normal nodes take the repository over as it is, by moving it into the repository store,
+    /// such that uncommitted work moves along. Bare-alias nodes need a bare repository, thus
+    /// every reference of the repository is copied into a brand-new one instead, leaving the
+    /// repository at `source` untouched. HEAD, remotes, and upstream branches are kept either
+    /// way.
+    ///
+    /// # Invariants
+    ///
+    /// - Nothing is left in the repository store if the repository cannot be copied.
+    ///
+    /// # Errors
+    ///
+    /// - Will fail if node is neither normal nor bare-alias, or is external.
+    /// - Will fail if node repository already exists in repository store.
+    /// - Will fail if `source` is not a repository.
+    /// - Will fail if repository cannot be moved or copied into repository store.
+    #[instrument(skip(name, node), level = "debug")]
+    pub fn new_adopt(name: impl AsRef<str>, node: &NodeEntry, source: &Path) -> Result<Self> {
+        let name = name.as_ref();
+        let kind = node.settings.deployment.kind.clone();
+        if !matches!(kind, DeploymentKind::Normal | DeploymentKind::BareAlias) || node.is_external()
+        {
+            return Err(anyhow!(
+                "Node {name:?} is {kind}, but only normal and bare-alias nodes can adopt \
+                 repositories into repository store"
+            ));
+        }
+
+        let builder = || -> Result<RepoEntryBuilder> {
+            Ok(RepoEntry::builder(name)?
+                .url(&node.settings.url)
+                .deployment_kind(kind.clone())
+                .work_dir_alias(node.settings.deployment.work_dir_alias.clone()))
+        };
+        let path = builder()?.path().to_path_buf();
+        if builder()?.exists() {
+            return Err(anyhow!("Node {name:?} already exists in repository store at {path:?}"));
+        }
+
+        let repository = Repository::open(source)
+            .with_context(|| format!("Failed to open repository at {source:?}"))?;
+        if !kind.is_bare_alias() && !repository.is_bare() {
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            drop(repository);
+            std::fs::rename(source, &path)
+                .with_context(|| format!("Failed to move {source:?} to {path:?}"))?;
+            info!("Move repository at {source:?} into repository store as {name:?}");
+            return Self::new_open(name, node);
+        }
+
+        let entry = builder()?.init()?;
+        if let Err(error) = copy_repository(&repository, &entry.repository) {
+            drop(entry);
+            let _ = remove_dir_all(&path);
+            return Err(error.context(format!("Failed to copy {source:?} into {name:?}")));
+        }
+        info!("Copy repository at {source:?} into repository store as {name:?}");
+
+        Self::new_open(name, node)
+    }
+
     /// Construct new node by opening existing node repository.
     ///
-    /// Will clone node repository if it does not already exist.
+    /// Will clone node repository if it does not already exist. Inline nodes open the root
+    /// repository instead, because that is where their files live. External nodes open their
+    /// repository outside the repository store, and are never cloned.
     ///
     /// # Errors
     ///
     /// - Will fail if clone itself fails when node is found to be missing.
     /// - Will fail if existing node cannot be opened for whatever reason.
     pub fn new_open(name: impl AsRef<str>, node: &NodeEntry) -> Result<Self> {
-        let entry = if data_dir()?.join(name.as_ref()).exists() {
+        let entry = if let Some(path) = &node.settings.path {
+            // INVARIANT: External repositories are never cloned, they live where the user put them.
+            if !path.exists() {
+                return Err(anyhow!(
+                    "External repository of node {:?} not found at {path:?}",
+                    name.as_ref()
+                ));
+            }
+
             RepoEntry::builder(name.as_ref())?
                 .url(&node.settings.url)
                 .deployment_kind(node.settings.deployment.kind.clone())
                 .work_dir_alias(node.settings.deployment.work_dir_alias.clone())
+                .external(Some(path.clone()))
                 .open()?
-        } else {
-            let bar = ProgressBar::no_length();
+        } else if let Some(subdir) = &node.settings.subdir {
+            RepoEntry::builder(name.as_ref())?
+                .deployment_kind(node.settings.deployment.kind.clone())
+                .work_dir_alias(node.settings.deployment.work_dir_alias.clone())
+                .inline(subdir)?
+                .open()?
+        } else if RepoEntry::builder(name.as_ref())?
+            .url(&node.settings.url)
+            .deployment_kind(node.settings.deployment.kind.clone())
+            .exists()
+        {
             let entry = RepoEntry::builder(name.as_ref())?
                 .url(&node.settings.url)
                 .deployment_kind(node.settings.deployment.kind.clone())
                 .work_dir_alias(node.settings.deployment.work_dir_alias.clone())
-                .authentication_prompter(ProgressBarAuthenticator::new(ProgressBarKind::SingleBar(
-                    bar.clone(),
-                )))
-                .clone(&bar)?;
+                .pin(node.pin())
+                .open()?;
+
+            // INVARIANT: Archive nodes are never repopulated behind the user's back.
+            if let Some(NodeSource::Archive { sha256, .. }) = &node.settings.source {
+                if !entry.archive_digest().is_some_and(|digest| digest.eq_ignore_ascii_case(sha256))
+                {
+                    warn!(
+                        "Archive source of {:?} changed, remove it to repopulate it",
+                        name.as_ref()
+                    );
+                }
+            }
+            entry
+        } else {
+            confirm_clone(name.as_ref(), node)?;
+            let bar = progress_bar();
+            let builder = || -> Result<RepoEntryBuilder> {
+                Ok(RepoEntry::builder(name.as_ref())?
+                    .url(&node.settings.url)
+                    .source(node.settings.source.clone())
+                    .deployment_kind(node.settings.deployment.kind.clone())
+                    .work_dir_alias(node.settings.deployment.work_dir_alias.clone())
+                    .pin(node.pin())
+                    .authentication_prompter(ProgressBarAuthenticator::new(
+                        ProgressBarKind::SingleBar(bar.clone()),
+                    )))
+            };
+
+            // INVARIANT: Brand-new nodes with nothing to clone yet must still be ready for their
+            // first push.
+            let entry = match builder()?.clone(&bar) {
+                Ok(entry) => {
+                    finish_progress(&bar);
+                    if entry.is_empty()? {
+                        entry.track_origin()?;
+                    }
+                    entry
+                }
+                Err(error) => {
+                    let builder = builder()?;
+                    if !builder.is_remote_empty().unwrap_or(false) {
+                        bar.finish_and_clear();
+                        return Err(error);
+                    }
+
+                    warn!("Remote of {:?} is empty, initialize it instead", name.as_ref());
+                    builder.init_with_remote()?
+                }
+            };
             bar.finish_and_clear();
             entry
         };
 
         let mut deployer = RepoEntryDeployer::new(&entry);
-        deployer.add_excluded(node.settings.excluded.iter().flatten());
+        deployer.add_excluded(
+            node.settings.excluded.iter().chain(node.settings.templates.iter()).flatten(),
+        );
+        deployer.add_included(node.settings.included.iter().flatten());
+        deployer.add_secrets(node.settings.encrypted.iter().flatten());
+        deployer.add_templates(node.settings.templates.iter().flatten());
+
+        // INVARIANT: Nested clusters must always be deployed, such that their nodes are known.
+        if entry.deployment_kind.is_cluster() {
+            deployer.deploy_with(ClusterDeployment, &entry, DeployAction::Deploy)?;
+        }
 
         Ok(Self { entry, deployer })
     }
@@ -284,7 +872,30 @@ pub fn new_open(name: impl AsRef<str>, node: &NodeEntry) -> Result<Self> {
     #[instrument(skip(self), level = "debug")]
     pub fn nuke(&self) -> Result<()> {
         self.deploy(DeployAction::Undeploy)?;
+
+        // INVARIANT: Inline nodes share root's repository, which must never be removed here.
+        if self.entry.is_inline() {
+            info!("Nuke inline node {:?} from cluster", self.entry.name());
+            return Ok(());
+        }
+
+        // INVARIANT: External repositories belong to the user, not to the repository store.
+        if self.entry.is_external() {
+            info!(
+                "Nuke external node {:?} from cluster, leaving its repository at {:?} in place",
+                self.entry.name(),
+                self.entry.repository.workdir().unwrap_or(self.path())
+            );
+            return Ok(());
+        }
+
         remove_dir_all(self.path())?;
+        prune_empty_parents(self.path(), &data_dir()?);
+
+        // INVARIANT: Definition of nested cluster goes along with it.
+        if self.entry.deployment_kind.is_cluster() && self.entry.work_dir_alias.0.exists() {
+            remove_dir_all(&self.entry.work_dir_alias.0)?;
+        }
         info!("Nuke node {:?} from cluster", self.entry.name());
 
         Ok(())
@@ -305,6 +916,16 @@ pub fn is_bare_alias(&self) -> bool {
         self.entry.is_bare_alias()
     }
 
+    /// Determine if node is inline.
+    pub fn is_inline(&self) -> bool {
+        self.entry.is_inline()
+    }
+
+    /// Determine if node lives in external repository outside the repository store.
+    pub fn is_external(&self) -> bool {
+        self.entry.is_external()
+    }
+
     /// Determine if node is currently deployed at specific state.
     ///
     /// # Errors
@@ -315,317 +936,2548 @@ pub fn is_deployed(&self, state: DeployState) -> Result<bool> {
         is_deployed(&self.entry, &self.deployer.excluded, state)
     }
 
-    /// Get current name of branch.
+    /// Determine deployment status of node at specific state, explaining which files are missing
+    /// or modified.
     ///
     /// # Errors
     ///
-    /// Will fail if there is no index to use.
-    pub fn current_branch(&self) -> Result<String> {
-        self.entry.current_branch()
+    /// Will fail if tracked files cannot be listed, or deployed files cannot be read.
+    pub fn deployment_status(&self, state: DeployState) -> Result<DeploymentStatus> {
+        scan_deployment(&self.entry, &self.deployer.excluded, state, true)
     }
 
-    /// Deploy node repository.
+    /// List deployed files of node that drifted from HEAD, i.e., were modified or removed in its
+    /// working directory alias since deployment.
     ///
     /// # Errors
     ///
-    /// Will fail if deployment action fails for whatever reason.
-    pub fn deploy(&self, action: DeployAction) -> Result<()> {
-        match self.entry.deployment_kind {
-            DeploymentKind::Normal => {
-                self.deployer.deploy_with(NormalDeployment, &self.entry, action)
-            }
-            DeploymentKind::BareAlias => {
-                self.deployer.deploy_with(BareAliasDeployment, &self.entry, action)
+    /// Will fail if tracked files cannot be listed, or deployed files cannot be read.
+    pub fn drifted_files(&self) -> Result<Vec<PathBuf>> {
+        drifted_files(&self.entry, &self.deployer.excluded)
+    }
+
+    /// Working tree of node, i.e., where its files end up.
+    ///
+    /// Normal and mirror nodes use the working tree of their repository, inline nodes use their
+    /// subdirectory within the working directory alias of root, and every other node uses its
+    /// working directory alias.
+    pub fn work_tree(&self) -> PathBuf {
+        let alias = &self.entry.work_dir_alias.0;
+        match (&self.entry.deployment_kind, &self.entry.subdir) {
+            (DeploymentKind::Normal | DeploymentKind::Mirror, _) => {
+                self.entry.repository.workdir().unwrap_or(self.path()).to_path_buf()
             }
+            (DeploymentKind::Inline, Some(subdir)) => alias.join(subdir),
+            _ => alias.clone(),
         }
     }
 
-    /// Make interactive call to Git binary.
+    /// Read file tracked by node at HEAD.
+    ///
+    /// Path is relative to top of node repository, or to subdirectory of inline node.
     ///
     /// # Errors
     ///
-    /// - Will fail if Git binary cannot be found.
-    /// - Will fail if provided arguments are invalid.
-    pub fn gitcall(&self, args: impl IntoIterator<Item = impl Into<OsString>>) -> Result<()> {
-        self.entry.gitcall_interactive(args)
-    }
-}
+    /// Will fail if node has no commits, or file is not tracked at HEAD.
+    pub fn read_head_file(&self, path: &Path) -> Result<String> {
+        let path = self.entry.subdir.as_deref().unwrap_or(Path::new("")).join(path);
+        let commit = self.entry.repository.head()?.peel_to_commit()?;
+        let entry = commit
+            .tree()?
+            .get_path(&path)
+            .map_err(|_| anyhow!("File {path:?} not tracked by {:?}", self.name()))?;
+        let blob = entry.to_object(&self.entry.repository)?.peel_to_blob()?;
 
-/// Clone all nodes in cluster definition asynchronously.
-#[derive(Debug)]
-pub struct MultiNodeClone {
-    nodes: Vec<RepoEntryBuilder>,
-    multi_bar: MultiProgress,
-    jobs: Option<usize>,
-}
+        Ok(String::from_utf8_lossy(blob.content()).into_owned())
+    }
 
-impl MultiNodeClone {
-    /// Construct new multi-node clone type from cluster definition.
-    ///
-    /// Extracts all node entries from cluster definition. Will set the number of threads/jobs that
-    /// will be used during the cloning of all nodes, with [`None`] resulting the saturation of all
-    /// CPU cores as much as possible.
+    /// Get current name of branch.
     ///
     /// # Errors
     ///
-    /// - Will fail if repository entry could not be constructed for a given node entry.
-    pub fn new(cluster: &Cluster, jobs: Option<usize>) -> Result<Self> {
-        let multi_bar = MultiProgress::new();
+    /// Will fail if there is no index to use.
+    pub fn current_branch(&self) -> Result<String> {
+        self.entry.current_branch()
+    }
+
+    /// Get commit ID that HEAD of node currently points to.
+    pub fn head_id(&self) -> Option<String> {
+        self.entry.head_id()
+    }
+
+    /// List untracked files next to tracked files of node.
+    ///
+    /// Suggests files worth adding to node, because bare-alias nodes hide untracked files from
+    /// "git status" of their working directory alias.
+    ///
+    /// # Errors
+    ///
+    /// Will fail if index cannot be read, or gitignore rules cannot be checked.
+    pub fn untracked_near_tracked(&self) -> Result<Vec<PathBuf>> {
+        self.entry.untracked_near_tracked()
+    }
+
+    /// Get working tree status of node.
+    ///
+    /// # Errors
+    ///
+    /// Will fail if status, gitignore rules, or commit graph cannot be obtained.
+    pub fn worktree_status(&self) -> Result<WorktreeStatus> {
+        self.entry.worktree_status()
+    }
+
+    /// List untracked files of node that are safe to clean.
+    ///
+    /// Same as [`Node::untracked_near_tracked`], except that files sitting directly in the home
+    /// directory are left out, because they usually belong to something else entirely.
+    ///
+    /// # Errors
+    ///
+    /// Will fail if index cannot be read, or gitignore rules cannot be checked.
+    pub fn cleanable_untracked(&self) -> Result<Vec<PathBuf>> {
+        self.entry.cleanable_untracked()
+    }
+
+    /// Remove untracked files of node that are safe to clean.
+    ///
+    /// Returns paths of removed files, relative to working directory alias of node.
+    ///
+    /// # Errors
+    ///
+    /// Will fail if untracked files cannot be listed or removed.
+    pub fn clean_untracked(&self) -> Result<Vec<PathBuf>> {
+        self.entry.clean_untracked()
+    }
+
+    /// Compare origin remote of node with URL of its cluster definition.
+    pub fn remote_health(&self) -> RemoteHealth {
+        self.entry.remote_health()
+    }
+
+    /// List files of node present in its working directory alias, as absolute paths.
+    ///
+    /// Normal and mirror nodes have no working directory alias, and never list anything.
+    ///
+    /// # Errors
+    ///
+    /// - Will fail if files of node cannot be listed.
+    pub fn deployed_files(&self) -> Result<Vec<PathBuf>> {
+        deployed_files(&self.entry)
+    }
+
+    /// Point origin remote of node to URL of its cluster definition, creating it if needed.
+    ///
+    /// # Errors
+    ///
+    /// Will fail if origin remote cannot be created or modified.
+    pub fn repair_origin(&self) -> Result<()> {
+        self.entry.repair_origin()
+    }
+
+    /// Check if node repository is a shallow clone, i.e., misses history.
+    pub fn is_shallow(&self) -> bool {
+        self.entry.repository.is_shallow()
+    }
+
+    /// Pack node repository, dropping unreachable objects.
+    ///
+    /// # Errors
+    ///
+    /// Will fail if Git binary fails to collect garbage.
+    #[instrument(skip(self), fields(node = %self.entry.name), level = "debug")]
+    pub fn gc(&self) -> Result<()> {
+        self.entry.gitcall_non_interactive(["gc", "--quiet"])?;
+        Ok(())
+    }
+
+    /// Turn node repository into shallow clone whose history starts at given date.
+    ///
+    /// Fetches origin remote again with its history cut off at `since`, then drops everything
+    /// older from the repository. Local commits missing from origin keep their full history.
+    ///
+    /// # Errors
+    ///
+    /// - Will fail if node has no origin remote to fetch from.
+    /// - Will fail if Git binary fails to fetch or collect garbage.
+    #[instrument(skip(self), fields(node = %self.entry.name), level = "debug")]
+    pub fn prune_history(&self, since: &str) -> Result<()> {
+        if self.entry.repository.find_remote("origin").is_err() {
+            return Err(anyhow!(
+                "Node {:?} has no origin remote to prune history with",
+                self.name()
+            ));
+        }
+
+        let since = format!("--shallow-since={since}");
+        self.entry.gitcall_non_interactive(["fetch", "--quiet", since.as_str(), "origin"])?;
+        self.entry.gitcall_non_interactive(["reflog", "expire", "--expire=now", "--all"])?;
+        self.entry.gitcall_non_interactive(["gc", "--quiet", "--prune=now"])?;
+        info!("Prune history of {:?}", self.name());
+
+        Ok(())
+    }
+
+    /// Check if HEAD of node is detached from any branch.
+    pub fn is_detached(&self) -> bool {
+        self.entry.repository.head_detached().unwrap_or(false)
+    }
+
+    /// Check out commit of node, detaching HEAD from its branch.
+    ///
+    /// Fetches origin remote first if the commit is missing from the node repository, e.g.,
+    /// because it was frozen on another machine. Returns false if HEAD already points to the
+    /// commit.
+    ///
+    /// # Errors
+    ///
+    /// - Will fail if commit cannot be found, even after fetching origin remote.
+    /// - Will fail if Git binary fails to check out the commit, e.g., because of local changes.
+    #[instrument(skip(self), fields(node = %self.entry.name), level = "debug")]
+    pub fn checkout_commit(&self, commit: &str) -> Result<bool> {
+        if self.head_id().as_deref() == Some(commit) {
+            return Ok(false);
+        }
+
+        let has_commit = |commit: &str| {
+            Oid::from_str(commit).is_ok_and(|oid| self.entry.repository.find_commit(oid).is_ok())
+        };
+        if !has_commit(commit) && self.entry.repository.find_remote("origin").is_ok() {
+            self.entry.gitcall_non_interactive(["fetch", "--quiet", "origin"])?;
+        }
+        if !has_commit(commit) {
+            return Err(anyhow!(
+                "Node {:?} does not have commit {commit}, even after fetching origin",
+                self.name()
+            ));
+        }
+
+        self.entry.gitcall_non_interactive(["checkout", "--quiet", "--detach", commit])?;
+        info!("Check out commit {commit} of {:?}", self.name());
+
+        Ok(true)
+    }
+
+    /// Check out branch of node, attaching HEAD to it again.
+    ///
+    /// # Errors
+    ///
+    /// Will fail if Git binary fails to check out the branch, e.g., because of local changes.
+    #[instrument(skip(self), fields(node = %self.entry.name), level = "debug")]
+    pub fn checkout_branch(&self, branch: &str) -> Result<()> {
+        self.entry.gitcall_non_interactive(["checkout", "--quiet", branch])?;
+        info!("Check out branch {branch:?} of {:?}", self.name());
+
+        Ok(())
+    }
+
+    /// Pull node from its upstream.
+    ///
+    /// Fetches origin remote, and fast-forwards current branch to its upstream whenever
+    /// possible. Once local branch and upstream diverged, `diverged` is called to pick a strategy
+    /// for it. Rebases and merges that run into conflicts are aborted, leaving node as it was.
+    /// Mirrors are only fetched. Detached nodes, e.g., frozen ones, are left alone.
+    ///
+    /// # Errors
+    ///
+    /// - Will fail if origin remote cannot be fetched.
+    /// - Will fail if node cannot be fast-forwarded, rebased, or merged.
+    pub fn pull(&self, diverged: impl FnOnce() -> Result<PullStrategy>) -> Result<PullOutcome> {
+        self.entry.pull(diverged)
+    }
+
+    /// Pull node from its upstream that was fetched already, e.g., through [`MultiEntryFetch`].
+    ///
+    /// Same as [`Node::pull`], just without fetching origin remote first, such that remotes of
+    /// many nodes can be fetched concurrently beforehand.
+    ///
+    /// # Errors
+    ///
+    /// - Will fail if node cannot be fast-forwarded, rebased, or merged.
+    pub fn pull_fetched(
+        &self,
+        diverged: impl FnOnce() -> Result<PullStrategy>,
+    ) -> Result<PullOutcome> {
+        self.entry.pull_fetched(diverged)
+    }
+
+    /// Determine if node needs to be redeployed after its HEAD moved.
+    ///
+    /// Bare-alias and inline nodes need it when they were deployed before, but some files of the
+    /// new HEAD are missing from the working directory alias. Hard-link nodes always need it when
+    /// they were deployed before, because Git replaces files of the internal checkout rather than
+    /// editing them in place, leaving the links behind on the old files.
+    ///
+    /// # Errors
+    ///
+    /// Will fail if deployment state cannot be determined.
+    pub fn needs_redeploy(&self, was_deployed: bool) -> Result<bool> {
+        if !was_deployed {
+            return Ok(false);
+        }
+
+        match self.entry.deployment_kind {
+            DeploymentKind::Normal | DeploymentKind::Mirror => Ok(false),
+            DeploymentKind::HardLink | DeploymentKind::Symlink | DeploymentKind::Copy => Ok(true),
+            DeploymentKind::BareAlias | DeploymentKind::Inline | DeploymentKind::Cluster => {
+                Ok(!self.is_deployed(DeployState::WithoutExcluded)?)
+            }
+        }
+    }
+
+    /// Deploy node repository.
+    ///
+    /// Deploying records the commit that node got deployed at into the deployment journal, such
+    /// that node can be rolled back to it later. Inline nodes are never recorded, because their
+    /// commits belong to root.
+    ///
+    /// Every deployment action also records the files that node leaves in its working directory
+    /// alias, such that they can be pruned once node is removed from the cluster definition
+    /// without being undeployed, see [`prune_artifacts`]. Files that it placed or removed are
+    /// recorded into the open transaction, if any, see [`begin_transaction`].
+    ///
+    /// # Errors
+    ///
+    /// Will fail if deployment action fails for whatever reason.
+    pub fn deploy(&self, action: DeployAction) -> Result<DeployOutcome> {
+        let outcome =
+            transacted(self.name(), &self.entry, &self.deployer.excluded, action, || {
+                self.deploy_without_journal(action)
+            })?;
+        let deployed =
+            matches!(outcome, DeployOutcome::Changed | DeployOutcome::AlreadyInDesiredState);
+        if deployed && matches!(action, DeployAction::Deploy | DeployAction::DeployAll) {
+            // INVARIANT: Failing to record deployment never fails the deployment itself.
+            if let Err(error) = self.record_deployment() {
+                warn!("Failed to record deployment of {:?} in journal: {error:?}", self.name());
+            }
+        }
+
+        if let Err(error) = self.record_artifacts() {
+            warn!("Failed to record deployed files of {:?}: {error:?}", self.name());
+        }
+
+        Ok(outcome)
+    }
+
+    // INVARIANT: Files of inline nodes belong to root, which deploys them once node is removed.
+    fn record_artifacts(&self) -> Result<()> {
+        if self.is_inline() {
+            return Ok(());
+        }
+
+        let mut artifacts = DeployArtifacts::load()?;
+        artifacts.record(self.name(), &self.entry.work_dir_alias.0, &self.deployed_files()?);
+        artifacts.save()
+    }
+
+    fn record_deployment(&self) -> Result<()> {
+        let Some(commit) = self.head_id().filter(|_| !self.is_inline()) else {
+            return Ok(());
+        };
+
+        let mut journal = DeployJournal::load()?;
+        journal.record(self.name(), commit);
+        journal.save()
+    }
+
+    /// Roll node back to the commit it was deployed at before, and deploy it again.
+    ///
+    /// Picks the most recently deployed commit that HEAD does not point to, such that a HEAD that
+    /// moved past its last deployment, e.g., through a pull, goes back to that deployment. HEAD
+    /// is detached at the commit. Commits deployed after it are forgotten, such that rolling back
+    /// again goes further back. Returns the commit rolled back to.
+    ///
+    /// # Errors
+    ///
+    /// - Will fail if node is inline, because its commits belong to root.
+    /// - Will fail if no earlier deployment of node was recorded.
+    /// - Will fail if commit cannot be checked out, or node cannot be deployed.
+    #[instrument(skip(self), fields(node = %self.entry.name), level = "debug")]
+    pub fn rollback(&self) -> Result<String> {
+        if self.is_inline() {
+            return Err(anyhow!(
+                "Inline node {:?} cannot be rolled back, roll back root instead",
+                self.name()
+            ));
+        }
+
+        let mut journal = DeployJournal::load()?;
+        let head = self.head_id();
+        let commit = journal
+            .previous(self.name(), head.as_deref())
+            .ok_or_else(|| anyhow!("No earlier deployment of {:?} was recorded", self.name()))?
+            .to_string();
+
+        self.checkout_commit(&commit)?;
+        self.deploy_without_journal(DeployAction::Deploy)?;
+        journal.rewind(self.name(), &commit);
+        journal.save()?;
+
+        Ok(commit)
+    }
+
+    /// List commits that node was deployed at, most recently deployed first.
+    ///
+    /// # Errors
+    ///
+    /// Will fail if state directory cannot be determined.
+    pub fn deployment_history(&self) -> Result<Vec<String>> {
+        let journal = DeployJournal::load()?;
+        Ok(journal.commits(self.name()).iter().rev().cloned().collect())
+    }
+
+    fn deploy_without_journal(&self, action: DeployAction) -> Result<DeployOutcome> {
+        match self.entry.deployment_kind {
+            DeploymentKind::Normal => {
+                self.deployer.deploy_with(NormalDeployment, &self.entry, action)
+            }
+            DeploymentKind::BareAlias => {
+                self.deployer.deploy_with(self.deployer.bare_alias(), &self.entry, action)
+            }
+            DeploymentKind::Inline => {
+                self.deployer.deploy_with(InlineDeployment, &self.entry, action)
+            }
+            DeploymentKind::HardLink => {
+                self.deployer.deploy_with(HardLinkDeployment, &self.entry, action)
+            }
+            DeploymentKind::Symlink => {
+                self.deployer.deploy_with(SymlinkDeployment, &self.entry, action)
+            }
+            DeploymentKind::Copy => self.deployer.deploy_with(CopyDeployment, &self.entry, action),
+            DeploymentKind::Mirror => {
+                self.deployer.deploy_with(MirrorDeployment, &self.entry, action)
+            }
+            DeploymentKind::Cluster => {
+                self.deployer.deploy_with(ClusterDeployment, &self.entry, action)
+            }
+        }
+    }
+
+    /// Determine outcome that deployment action would have, without deploying anything.
+    ///
+    /// # Errors
+    ///
+    /// Will fail if deployment state of node cannot be determined.
+    pub fn plan(&self, action: DeployAction) -> Result<DeployOutcome> {
+        if self.entry.is_empty()? {
+            return Ok(DeployOutcome::Skipped);
+        }
+
+        match (&self.entry.deployment_kind, action) {
+            (DeploymentKind::Normal | DeploymentKind::Mirror, _) => Ok(DeployOutcome::Skipped),
+            (DeploymentKind::Cluster, DeployAction::Undeploy | DeployAction::UndeployExcludes) => {
+                Ok(DeployOutcome::Skipped)
+            }
+            (DeploymentKind::Cluster, _) => {
+                plan_action(&self.entry, &self.deployer.excluded, DeployAction::DeployAll)
+            }
+            (_, action) => plan_action(&self.entry, &self.deployer.excluded, action),
+        }
+    }
+
+    /// List files that deployment action would overwrite, even though they changed since OCD last
+    /// placed them, e.g., because they are still being edited.
+    ///
+    /// Only hard-link, symlink, and copy nodes ever overwrite files in their working directory
+    /// alias. Git itself refuses to overwrite modified files of every other kind of node.
+    ///
+    /// # Errors
+    ///
+    /// Will fail if deployment manifest cannot be loaded, or placed files cannot be inspected.
+    pub fn locally_modified(&self, action: DeployAction) -> Result<Vec<PathBuf>> {
+        if !self.entry.deployment_kind.places_files() || self.entry.is_empty()? {
+            return Ok(Vec::new());
+        }
+
+        hard_link_modified(&self.entry, &self.deployer.excluded, action)
+    }
+
+    /// Allow deployment to overwrite sparse checkout file of node, even if OCD did not write it.
+    ///
+    /// Inline nodes share the sparse checkout file of root.
+    pub fn set_force(&mut self, force: bool) {
+        self.deployer.excluded.set_force(force);
+    }
+
+    /// Set how deployment treats files in working directory alias that it would take over.
+    ///
+    /// Only bare-alias nodes take over files, see [`CollisionPolicy`].
+    pub fn set_collisions(&mut self, collisions: CollisionPolicy) {
+        self.deployer.set_collisions(collisions);
+    }
+
+    /// Set variables that deployment renders templates of node with.
+    ///
+    /// Templates referring to variables fail to render unless their variables are set, see
+    /// [`Cluster::template_vars`].
+    pub fn set_template_vars(&mut self, vars: BTreeMap<String, String>) {
+        self.deployer.set_vars(vars);
+    }
+
+    /// Determine who owns sparse checkout file of node.
+    ///
+    /// # Errors
+    ///
+    /// Will fail if sparse checkout file exists, but cannot be read.
+    pub fn sparse_checkout_state(&self) -> Result<SparseCheckoutState> {
+        self.deployer.excluded.state()
+    }
+
+    /// Check if sparse checkout file of node agrees with its exclusion rules.
+    ///
+    /// Sparse checkout files agree if they hold the rules that deploying the node with or without
+    /// its excluded files, or undeploying it, would write. Inline nodes share the sparse checkout
+    /// file of root, and always agree.
+    ///
+    /// # Errors
+    ///
+    /// Will fail if sparse checkout file exists, but cannot be read.
+    pub fn is_sparse_checkout_current(&self) -> Result<bool> {
+        if self.is_inline() {
+            return Ok(true);
+        }
+
+        for action in
+            [ExcludeAction::ExcludeUnwanted, ExcludeAction::IncludeAll, ExcludeAction::ExcludeAll]
+        {
+            if self.deployer.excluded.has_rules(action)? {
+                return Ok(true);
+            }
+        }
+
+        Ok(false)
+    }
+
+    /// Rewrite sparsity rules of deployed node, such that current exclusion rules take effect.
+    ///
+    /// Useful when exclusion rules of node changed after node got deployed. Newly excluded files
+    /// are removed from the working directory alias, and files that are no longer excluded are
+    /// placed into it. Normal nodes have nothing to refresh.
+    ///
+    /// # Errors
+    ///
+    /// Will fail if sparsity rules cannot be written, or checkout fails.
+    pub fn refresh(&self) -> Result<()> {
+        if self.entry.is_empty()? {
+            return Ok(());
+        }
+
+        match (&self.entry.deployment_kind, &self.entry.subdir) {
+            (DeploymentKind::Normal | DeploymentKind::Mirror | DeploymentKind::Cluster, _) => {
+                return Ok(())
+            }
+            (DeploymentKind::HardLink | DeploymentKind::Symlink | DeploymentKind::Copy, _) => {
+                let _ = self.deploy(DeployAction::Deploy)?;
+                return Ok(());
+            }
+            (DeploymentKind::Inline, Some(subdir)) => self.deployer.excluded.write_inline_rules(
+                self.name(),
+                subdir,
+                ExcludeAction::ExcludeUnwanted,
+            )?,
+            _ => self.deployer.excluded.write_rules(ExcludeAction::ExcludeUnwanted)?,
+        }
+        let output = self.entry.gitcall_non_interactive(["checkout"])?;
+        info!("Refresh deployment of {:?}\n{output}", self.name());
+
+        Ok(())
+    }
+
+    /// Convert node repository to deployment kind of node entry.
+    ///
+    /// Normal nodes need a normal repository, while bare-alias nodes need a bare one. Thus, node
+    /// repository is re-created in repository store with the bareness that `node` expects, by
+    /// fetching every reference of the old repository into a brand-new one. HEAD, remotes, and
+    /// upstream branches of the old repository are kept. Node is undeployed first, and left
+    /// undeployed.
+    ///
+    /// # Invariants
+    ///
+    /// - Old repository is put back if the new one cannot be created.
+    ///
+    /// # Errors
+    ///
+    /// - Will fail if node or `node` is neither normal nor bare-alias.
+    /// - Will fail if node is a shallow clone, because conversion copies full history.
+    /// - Will fail if node cannot be undeployed.
+    /// - Will fail if node repository cannot be re-created.
+    #[instrument(skip(self, node), level = "debug")]
+    pub fn convert(self, node: &NodeEntry) -> Result<Self> {
+        let name = self.name().to_string();
+        let kind = node.settings.deployment.kind.clone();
+        let convertible = |kind: &DeploymentKind| {
+            matches!(kind, DeploymentKind::Normal | DeploymentKind::BareAlias)
+        };
+        if !convertible(&self.entry.deployment_kind) || !convertible(&kind) {
+            return Err(anyhow!(
+                "Node {name:?} is {}, but only normal and bare-alias nodes can be converted to \
+                 normal or bare-alias",
+                self.entry.deployment_kind
+            ));
+        }
+
+        if self.is_shallow() {
+            return Err(anyhow!(
+                "Node {name:?} is a shallow clone, restore its full history with \"ocd {name} \
+                 fetch --unshallow\" before converting it"
+            ));
+        }
+
+        self.deploy(DeployAction::Undeploy)?;
+        if self.entry.repository.is_bare() == kind.is_bare_alias() {
+            info!("Repository of {name:?} already fits {kind} deployment");
+            drop(self);
+            return Self::new_open(&name, node);
+        }
+
+        let path = RepoEntry::builder(&name)?.url(&node.settings.url).path().to_path_buf();
+        let mut backup = path.clone().into_os_string();
+        backup.push(".convert");
+        let backup = PathBuf::from(backup);
+        drop(self);
+        std::fs::rename(&path, &backup)
+            .with_context(|| format!("Failed to move {path:?} to {backup:?}"))?;
+
+        let result = || -> Result<()> {
+            let entry = RepoEntry::builder(&name)?
+                .url(&node.settings.url)
+                .deployment_kind(kind.clone())
+                .work_dir_alias(node.settings.deployment.work_dir_alias.clone())
+                .init()?;
+            copy_repository(&Repository::open(&backup)?, &entry.repository)
+        };
+        if let Err(error) = result() {
+            let _ = remove_dir_all(&path);
+            std::fs::rename(&backup, &path)
+                .with_context(|| format!("Failed to move {backup:?} back to {path:?}"))?;
+            return Err(error.context(format!("Failed to convert {name:?} to {kind}")));
+        }
+        remove_dir_all(&backup).with_context(|| format!("Failed to remove {backup:?}"))?;
+        info!("Convert {name:?} to {kind} node");
+
+        Self::new_open(&name, node)
+    }
+
+    /// Make interactive call to Git binary.
+    ///
+    /// # Errors
+    ///
+    /// - Will fail if Git binary cannot be found.
+    /// - Will fail if provided arguments are invalid.
+    pub fn gitcall(&self, args: impl IntoIterator<Item = impl Into<OsString>>) -> Result<()> {
+        self.entry.gitcall_interactive(args)
+    }
+}
+
+/// Clone all nodes in cluster definition asynchronously.
+#[derive(Debug)]
+pub struct MultiNodeClone {
+    nodes: Vec<RepoEntryBuilder>,
+    multi_bar: MultiProgress,
+    jobs: Option<usize>,
+}
+
+impl MultiNodeClone {
+    /// Construct new multi-node clone type from cluster definition.
+    ///
+    /// Extracts all node entries from cluster definition that are not in the repository store yet.
+    /// Will set the number of threads/jobs that will be used during the cloning of all nodes, with
+    /// [`None`] resulting the saturation of all CPU cores as much as possible.
+    ///
+    /// # Errors
+    ///
+    /// - Will fail if repository entry could not be constructed for a given node entry.
+    pub fn new(cluster: &Cluster, jobs: Option<usize>) -> Result<Self> {
+        let multi_bar = multi_progress();
         let mut nodes: Vec<RepoEntryBuilder> = Vec::new();
 
-        for (name, node) in &cluster.nodes {
-            let repo = RepoEntryBuilder::new(name)?
-                .url(&node.settings.url)
-                .deployment_kind(node.settings.deployment.kind.clone())
-                .work_dir_alias(node.settings.deployment.work_dir_alias.clone())
-                .authentication_prompter(ProgressBarAuthenticator::new(ProgressBarKind::MultiBar(
-                    multi_bar.clone(),
-                )));
+        for (name, node) in &cluster.nodes {
+            // INVARIANT: Inline nodes are cloned along with root, and external nodes never are.
+            if node.settings.deployment.kind.is_inline() || node.is_external() {
+                continue;
+            }
+
+            let repo = RepoEntryBuilder::new(name)?
+                .url(&node.settings.url)
+                .source(node.settings.source.clone())
+                .deployment_kind(node.settings.deployment.kind.clone())
+                .work_dir_alias(node.settings.deployment.work_dir_alias.clone())
+                .authentication_prompter(ProgressBarAuthenticator::new(ProgressBarKind::MultiBar(
+                    multi_bar.clone(),
+                )));
+
+            // INVARIANT: Nodes already in repository store are never cloned again.
+            if repo.exists() {
+                debug!("Node {name:?} already in repository store, skip clone");
+                continue;
+            }
+
+            nodes.push(repo);
+        }
+
+        Ok(Self { nodes, multi_bar, jobs })
+    }
+
+    /// Only clone node entries whose names are kept by predicate.
+    ///
+    /// Node entries left out stay missing from the repository store, such that they can be cloned
+    /// later on.
+    pub fn retain(mut self, keep: impl Fn(&str) -> bool) -> Self {
+        self.nodes.retain(|node| keep(&node.name));
+        self
+    }
+
+    /// Clone all node entries in cluster asynchronously.
+    ///
+    /// Shows clone progress for each clone tasks. Tasks may block if user needs to enter their
+    /// credentials.
+    ///
+    /// # Invariants
+    ///
+    /// - Progress bars are properly finished no matter what.
+    ///
+    /// # Panics
+    ///
+    /// - Will panic if mutex guard fails to lock.
+    /// - Will panic if mutex cannot be unwrapped to extract clone task result data.
+    ///
+    /// # Errors
+    ///
+    /// - Will fail for clone task failure.
+    ///     - Failed clone tasks will not cancel any active clone tasks that are not failing.
+    ///     - Results are only collected until _all_ clone tasks have finished.
+    ///     - All errors are reported in one-shot.
+    pub async fn clone_all(self) -> Result<()> {
+        let mut bars = Vec::new();
+        let results = Arc::new(Mutex::new(Vec::new()));
+
+        stream::iter(self.nodes)
+            .for_each_concurrent(self.jobs, |node| {
+                let results = results.clone();
+                let bar = self.multi_bar.add(progress_bar());
+                bars.push(bar.clone());
+
+                async move {
+                    let node_name = node.name.clone();
+                    let result = tokio::spawn(async move {
+                        let result = node.clone(&bar);
+                        if result.is_ok() {
+                            finish_progress(&bar);
+                        }
+                        result
+                    })
+                    .await;
+                    let mut guard = results.lock().unwrap();
+                    guard.push(
+                        result.map_err(|err| anyhow!("Failed to clone {node_name:?}: {err:?}")),
+                    );
+                    drop(guard);
+                }
+            })
+            .await;
+
+        // INVARIANT: All progress bars should be finished properly.
+        for bar in bars {
+            bar.finish_and_clear();
+        }
+
+        // INVARIANT: Collect and report _all_ failures encountered.
+        let results = Arc::try_unwrap(results).unwrap().into_inner().unwrap();
+        let _ = results.into_iter().flatten().bcollect::<Vec<_>>()?;
+
+        Ok(())
+    }
+}
+
+/// Fetch origin remotes of repository entries in cluster asynchronously.
+#[derive(Debug)]
+pub struct MultiEntryFetch {
+    entries: Vec<(String, FetchEntry)>,
+    multi_bar: MultiProgress,
+    jobs: Option<usize>,
+}
+
+#[derive(Debug)]
+enum FetchEntry {
+    Root(Box<RootEntry>),
+    Node(Box<NodeEntry>),
+}
+
+impl MultiEntryFetch {
+    /// Construct new empty multi-entry fetch.
+    ///
+    /// Will set the number of entries that are fetched at once, with [`None`] resulting in the
+    /// saturation of all CPU cores as much as possible.
+    pub fn new(jobs: Option<usize>) -> Self {
+        Self { entries: Vec::new(), multi_bar: multi_progress(), jobs }
+    }
+
+    /// Fetch root along with everything else.
+    pub fn add_root(mut self, root: &RootEntry) -> Self {
+        self.entries.push(("root".into(), FetchEntry::Root(Box::new(root.clone()))));
+        self
+    }
+
+    /// Fetch node along with everything else.
+    ///
+    /// # Invariants
+    ///
+    /// - Inline nodes are fetched along with root, so they are never fetched on their own.
+    pub fn add_node(mut self, name: impl Into<String>, node: &NodeEntry) -> Self {
+        if node.settings.subdir.is_none() {
+            self.entries.push((name.into(), FetchEntry::Node(Box::new(node.clone()))));
+        }
+        self
+    }
+
+    /// Fetch origin remote of every entry asynchronously.
+    ///
+    /// Shows fetch progress for each entry, pausing progress bars whenever the user needs to enter
+    /// their credentials. Entries without an origin remote are left alone. Returns the result of
+    /// each entry by name, with root named "root", such that entries failing to fetch can be told
+    /// apart from the rest.
+    ///
+    /// # Invariants
+    ///
+    /// - Failed fetches never cancel any other fetch.
+    /// - Entries missing from the repository store are never cloned, because callers are expected
+    ///   to only add entries that are present.
+    pub async fn fetch_all(self) -> Vec<(String, Result<()>)> {
+        let jobs = self.jobs.unwrap_or_else(default_jobs);
+        let mut fetches = stream::iter(self.entries)
+            .map(|(name, entry)| {
+                let multi_bar = self.multi_bar.clone();
+                let task = tokio::task::spawn_blocking({
+                    let name = name.clone();
+                    move || -> Result<()> {
+                        let mut entry = match entry {
+                            FetchEntry::Root(root) => Root::new_open(&root)?.entry,
+                            FetchEntry::Node(node) => Node::new_open(&name, &node)?.entry,
+                        };
+                        if entry.repository.find_remote("origin").is_err() {
+                            return Ok(());
+                        }
+                        entry.set_authentication_prompter(ProgressBarAuthenticator::new(
+                            ProgressBarKind::MultiBar(multi_bar.clone()),
+                        ));
+                        fetch_origin(&entry, &multi_bar)
+                    }
+                });
+                async move { (name, task.await) }
+            })
+            .buffer_unordered(jobs);
+
+        let mut results = Vec::new();
+        while let Some((name, result)) = fetches.next().await {
+            let result = match result {
+                Ok(result) => result,
+                Err(error) => Err(anyhow!("Failed to fetch {name:?}: {error:?}")),
+            };
+            results.push((name, result));
+        }
+
+        results
+    }
+}
+
+/// Tablize repository entry information in cluster.
+///
+/// Information is gathered into rows first, and rendered second, either as a table through the
+/// logger, or as JSON or YAML on standard output, see [`TablizeCluster::format`].
+#[derive(Debug)]
+pub struct TablizeCluster<'cluster> {
+    root: &'cluster Root,
+    cluster: &'cluster Cluster,
+    selection: Option<Selection>,
+    format: OutputFormat,
+}
+
+// Entries picked for listing, with the targets that pulled in each node as a dependency.
+#[derive(Debug)]
+struct Selection {
+    root: bool,
+    nodes: BTreeMap<String, BTreeSet<String>>,
+}
+
+impl<'cluster> TablizeCluster<'cluster> {
+    /// Construct new cluster tablizer.
+    pub fn new(root: &'cluster Root, cluster: &'cluster Cluster) -> Self {
+        Self { root, cluster, selection: None, format: OutputFormat::default() }
+    }
+
+    /// Render listings in given format instead of as tables.
+    ///
+    /// Root is named "root" rather than "<root>", and dependencies carry the targets that depend
+    /// on them in a "required_by" list rather than a name annotation.
+    pub fn format(mut self, format: OutputFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Only list given targets instead of every entry in cluster.
+    ///
+    /// Targets are expected to be matched through [`Cluster::match_targets`] already. Transitive
+    /// dependencies of each target are listed too if `with_deps` is set, annotated with the
+    /// targets that depend on them, i.e., exactly what deploying the targets would touch.
+    pub fn select(mut self, targets: &[String], with_deps: bool) -> Self {
+        let nodes = if with_deps {
+            self.cluster.dependency_closure(targets)
+        } else {
+            targets
+                .iter()
+                .filter(|target| self.cluster.nodes.contains_key(*target))
+                .map(|target| (target.clone(), BTreeSet::new()))
+                .collect()
+        };
+        self.selection = Some(Selection { root: targets.iter().any(|t| t == "root"), nodes });
+        self
+    }
+
+    fn lists_root(&self) -> bool {
+        self.selection.as_ref().is_none_or(|selection| selection.root)
+    }
+
+    // INVARIANT: Nodes come sorted by name, along with whatever depends on them.
+    fn listed_nodes(&self) -> Vec<(String, NodeEntry, Vec<String>)> {
+        self.cluster
+            .nodes
+            .iter()
+            .filter_map(|(name, entry)| {
+                let required_by = match &self.selection {
+                    None => Vec::new(),
+                    Some(selection) => selection.nodes.get(name)?.iter().cloned().collect(),
+                };
+                Some((name.clone(), entry.clone(), required_by))
+            })
+            .collect()
+    }
+
+    /// List only names of all entries in cluster.
+    ///
+    /// Names come straight from the cluster definition, so the repository store is never touched.
+    ///
+    /// # Errors
+    ///
+    /// - Will fail if listing cannot be serialized.
+    pub fn names_only(&self) -> Result<()> {
+        let mut rows = Vec::new();
+        if self.lists_root() {
+            rows.push(NameRow { name: "root".into(), is_root: true, required_by: Vec::new() });
+        }
+        rows.extend(self.listed_nodes().into_iter().map(|(name, _, required_by)| NameRow {
+            name,
+            is_root: false,
+            required_by,
+        }));
+
+        if print_structured(self.format, || &rows)? {
+            return Ok(());
+        }
+
+        let mut builder = tabled::builder::Builder::new();
+        for row in &rows {
+            builder.push_record([annotate_dependency(
+                table_name(&row.name, row.is_root),
+                &row.required_by,
+            )]);
+        }
+
+        let mut table = builder.build();
+        table.with(tabled::settings::Style::ascii_rounded());
+        info!("Name only listing:\n{table}");
+
+        Ok(())
+    }
+
+    /// List a wide range information about each entry in cluster.
+    ///
+    /// Will list the following information:
+    ///
+    /// - Deployment kind.
+    /// - Entry name.
+    /// - Deployment status.
+    /// - Currently active branch.
+    /// - Commits ahead/behind of upstream branch.
+    /// - Health of origin remote compared to cluster definition.
+    ///
+    /// Information about each node is gathered concurrently, with `jobs` limiting the number of
+    /// nodes being worked on at once ([`None`] uses every available CPU core). Rows are handed off
+    /// to the table renderer as soon as they arrive. The remotes of every entry are fetched
+    /// beforehand if `fetch` is set, so the upstream column reflects the actual state of each
+    /// remote.
+    ///
+    /// # Invariants
+    ///
+    /// - Nodes missing from the repository store are only cloned if `fetch` is set, otherwise
+    ///   they are listed as not cloned, such that listing never touches the network on its own.
+    ///
+    /// # Errors
+    ///
+    /// - Will fail if a given root or node entry does not exist.
+    /// - Will fail if deployment status cannot be obtained.
+    /// - Will fail if current branch cannot be obtained.
+    /// - Will fail if fetch fails for any entry.
+    ///     - All failures are reported in one-shot once every node has been processed.
+    #[instrument(skip(self), level = "debug")]
+    pub async fn fancy(&self, jobs: Option<usize>, fetch: bool) -> Result<()> {
+        let multi_bar = multi_progress();
+        if fetch {
+            fetch_origin(&self.root.entry, &multi_bar)?;
+        }
+
+        let nodes = self.listed_nodes();
+        let total = nodes.len() + usize::from(self.lists_root());
+        let stream_rows = self.format == OutputFormat::Table;
+        let mut renderer = FancyRenderer::new(multi_bar.add(progress_bar()), total, stream_rows)?;
+        if self.lists_root() {
+            renderer.push(ListingRow::root(&self.root.entry, &self.root.deployer.excluded)?);
+        }
+
+        let jobs = jobs.unwrap_or_else(default_jobs);
+        let mut rows = stream::iter(nodes)
+            .map(|(name, entry, required_by)| {
+                let multi_bar = multi_bar.clone();
+                tokio::task::spawn_blocking(move || -> Result<ListingRow> {
+                    let exists = RepoEntry::builder(&name)?
+                        .url(&entry.settings.url)
+                        .deployment_kind(entry.settings.deployment.kind.clone())
+                        .external(entry.settings.path.clone())
+                        .exists();
+                    if !fetch && entry.settings.subdir.is_none() && !exists {
+                        return Ok(ListingRow::missing(name, &entry).required_by(required_by));
+                    }
+
+                    let mut node = Node::new_open(&name, &entry)?;
+                    if fetch {
+                        node.entry.set_authentication_prompter(ProgressBarAuthenticator::new(
+                            ProgressBarKind::MultiBar(multi_bar.clone()),
+                        ));
+                        fetch_origin(&node.entry, &multi_bar)?;
+                    }
+                    let row = ListingRow::new(node.name(), &node.entry, &node.deployer.excluded)?;
+                    Ok(row.with_about(&entry).required_by(required_by))
+                })
+            })
+            .buffer_unordered(jobs);
+
+        // INVARIANT: Collect and report _all_ failures encountered.
+        let mut results = Vec::new();
+        while let Some(result) = rows.next().await {
+            match result.map_err(|err| anyhow!("Failed to list node: {err:?}")) {
+                Ok(Ok(row)) => renderer.push(row),
+                Ok(Err(error)) | Err(error) => results.push(Err(error)),
+            }
+        }
+        results.into_iter().bcollect::<Vec<()>>()?;
+
+        let rows = renderer.into_rows();
+        if !print_structured(self.format, || &rows)? {
+            info!("Fancy listing:\n{}", render_listing(&rows));
+        }
+
+        Ok(())
+    }
+
+    /// Show working tree status of each entry in cluster.
+    ///
+    /// Lists whether each entry is clean, or has staged changes, unstaged changes, or untracked
+    /// files, along with commits ahead/behind of its upstream branch. Lists only entries that are
+    /// not clean, one per line along with their [`WorktreeStatus::short`] flags, if `short` is set.
+    ///
+    /// # Invariants
+    ///
+    /// - Inline nodes are covered by root, and mirror nodes have no working tree.
+    /// - Entries missing from repository store are listed as not cloned, never cloned.
+    ///
+    /// # Errors
+    ///
+    /// - Will fail if status of any entry cannot be obtained.
+    ///     - All failures are reported in one-shot once every entry has been processed.
+    #[instrument(skip(self), level = "debug")]
+    pub fn status(&self, short: bool) -> Result<()> {
+        let mut rows = Vec::new();
+        let mut errors = Vec::new();
+        if self.lists_root() {
+            match self.root.worktree_status() {
+                Ok(status) => {
+                    rows.push(StatusRow::new("root".into(), true, Vec::new(), Ok(status)))
+                }
+                Err(error) => errors.push(Err(error.context("Failed to get status of root"))),
+            }
+        }
+
+        for (name, entry, required_by) in self.listed_nodes() {
+            let kind = &entry.settings.deployment.kind;
+            let status = if kind.is_inline() {
+                Err("part of root")
+            } else if kind.is_mirror() {
+                Err("mirrored")
+            } else {
+                let status = || -> Result<Option<WorktreeStatus>> {
+                    if !is_node_present(&name, &entry)? {
+                        return Ok(None);
+                    }
+                    Ok(Some(Node::new_open(&name, &entry)?.worktree_status()?))
+                };
+                match status() {
+                    Ok(Some(status)) => Ok(status),
+                    Ok(None) => Err("not cloned"),
+                    Err(error) => {
+                        errors
+                            .push(Err(error.context(format!("Failed to get status of {name:?}"))));
+                        continue;
+                    }
+                }
+            };
+            rows.push(StatusRow::new(name, false, required_by, status));
+        }
+        errors.into_iter().bcollect::<Vec<()>>()?;
+
+        if print_structured(self.format, || &rows)? {
+            return Ok(());
+        }
+
+        if short {
+            for row in rows {
+                let name =
+                    annotate_dependency(table_name(&row.name, row.is_root), &row.required_by);
+                match row.status {
+                    Some(status) if !status.is_clean() => println!("{} {name}", status.short()),
+                    _ => {}
+                }
+            }
+            return Ok(());
+        }
+
+        let mut builder = tabled::builder::Builder::new();
+        for row in rows {
+            let name = annotate_dependency(table_name(&row.name, row.is_root), &row.required_by);
+            let upstream = row.status.map_or_else(|| "-".to_string(), |status| status.upstream());
+            builder.push_record([name, row.state, upstream]);
+        }
+        let mut table = builder.build();
+        table.with(tabled::settings::Style::ascii_rounded());
+        info!("Status overview:\n{table}");
+
+        Ok(())
+    }
+}
+
+// INVARIANT: Nodes missing from repository store are only cloned on demand if policy allows it.
+fn confirm_clone(name: &str, node: &NodeEntry) -> Result<()> {
+    let url = match &node.settings.source {
+        Some(NodeSource::Archive { url, .. }) => url,
+        None => &node.settings.url,
+    };
+
+    match *CLONE_POLICY.lock().unwrap() {
+        ClonePolicy::Always => Ok(()),
+        ClonePolicy::Never => Err(anyhow!(
+            "Node {name:?} is missing from repository store, and cloning it on demand is \
+             disabled, clone it through \"ocd clone\", or pass \"--auto-clone always\""
+        )),
+        ClonePolicy::Prompt => {
+            let size = match local_remote_size(url) {
+                Some(size) => HumanBytes(size).to_string(),
+                None => "unknown size".into(),
+            };
+            let prompt = format!("Clone missing node {name:?} from {url} ({size})? [y/n]");
+            if !prompt_confirmation(prompt)? {
+                return Err(anyhow!("Clone of missing node {name:?} declined"));
+            }
+
+            Ok(())
+        }
+    }
+}
+
+// Size of remote on disk, which can only be known up front for remotes on the local filesystem.
+fn local_remote_size(url: &str) -> Option<u64> {
+    fn dir_size(path: &Path) -> io::Result<u64> {
+        let mut size = 0;
+        for entry in read_dir(path)? {
+            let entry = entry?;
+            let meta = entry.metadata()?;
+            size += if meta.is_dir() { dir_size(&entry.path())? } else { meta.len() };
+        }
+        Ok(size)
+    }
+
+    let path = Path::new(url.strip_prefix("file://").unwrap_or(url));
+    match path.metadata() {
+        Ok(meta) if meta.is_dir() => dir_size(path).ok(),
+        Ok(meta) => Some(meta.len()),
+        Err(_) => None,
+    }
+}
+
+// Normalize remote URL, where local remotes are compared by their absolute path, because that is
+// how git records them as origin of their clones.
+fn normalize_remote(url: impl AsRef<str>) -> Result<String> {
+    match std::fs::canonicalize(url.as_ref()) {
+        Ok(path) => normalize_url(path.to_string_lossy()),
+        Err(_) => normalize_url(url),
+    }
+}
+
+// Copy every reference of one repository into another, along with HEAD, remotes, and upstream
+// branches, checking out HEAD if the other repository is normal.
+fn copy_repository(src: &Repository, dest: &Repository) -> Result<()> {
+    let url = src.path().to_string_lossy().into_owned();
+    let mut remote = dest.remote_anonymous(&url)?;
+    traced("libgit2", ["fetch", url.as_str(), "+refs/*:refs/*"], || {
+        remote.fetch(&["+refs/*:refs/*"], None, None)
+    })?;
+
+    let src_config = src.config()?.open_level(ConfigLevel::Local)?;
+    let mut dest_config = dest.config()?.open_level(ConfigLevel::Local)?;
+    let mut entries = src_config.entries(Some("^(remote|branch|ocd)\\."))?;
+    while let Some(entry) = entries.next() {
+        let entry = entry?;
+        if let (Some(name), Some(value)) = (entry.name(), entry.value()) {
+            // INVARIANT: Keep every value of multivars like "remote.<name>.fetch".
+            dest_config.set_multivar(name, "^$", value)?;
+        }
+    }
+
+    let head = src.find_reference("HEAD")?;
+    match head.symbolic_target() {
+        Some(target) => dest.set_head(target)?,
+        None => dest.set_head_detached(head.target().ok_or(anyhow!("HEAD is invalid"))?)?,
+    }
+
+    if !dest.is_bare() && dest.head().is_ok() {
+        dest.checkout_head(Some(CheckoutBuilder::new().force()))?;
+    }
+
+    Ok(())
+}
+
+// Fetch "origin" remote of repository entry if it has one.
+fn fetch_origin(entry: &RepoEntry, multi_bar: &MultiProgress) -> Result<()> {
+    if entry.repository.find_remote("origin").is_err() {
+        warn!("Repository {:?} has no origin remote, skipping fetch", entry.name());
+        return Ok(());
+    }
+
+    let bar = multi_bar.add(progress_bar());
+    let result = entry.fetch(&bar);
+    match result {
+        Ok(_) => finish_progress(&bar),
+        Err(_) => bar.finish_and_clear(),
+    }
+    result
+}
+
+/// Row of name only cluster listing.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+struct NameRow {
+    name: String,
+    #[serde(skip)]
+    is_root: bool,
+    required_by: Vec<String>,
+}
+
+/// Row of working tree status overview.
+///
+/// Entries without working tree status, e.g., mirrors, carry a note saying why as their state,
+/// with everything else unknown.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+struct StatusRow {
+    name: String,
+    #[serde(skip)]
+    is_root: bool,
+    state: String,
+    staged: Option<bool>,
+    unstaged: Option<bool>,
+    untracked: Option<bool>,
+    #[serde(serialize_with = "serialize_upstream")]
+    upstream: Option<(usize, usize)>,
+    required_by: Vec<String>,
+    #[serde(skip)]
+    status: Option<WorktreeStatus>,
+}
+
+impl StatusRow {
+    fn new(
+        name: String,
+        is_root: bool,
+        required_by: Vec<String>,
+        status: Result<WorktreeStatus, &str>,
+    ) -> Self {
+        let (state, status) = match status {
+            Ok(status) => (status.state(), Some(status)),
+            Err(note) => (note.to_string(), None),
+        };
+
+        Self {
+            name,
+            is_root,
+            state,
+            staged: status.map(|status| status.staged),
+            unstaged: status.map(|status| status.unstaged),
+            untracked: status.map(|status| status.untracked),
+            upstream: status.and_then(|status| status.upstream),
+            required_by,
+            status,
+        }
+    }
+}
+
+/// Row of fancy cluster listing.
+///
+/// Whatever is unknown about entries missing from the repository store is left out.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub(crate) struct ListingRow {
+    name: String,
+    #[serde(skip)]
+    is_root: bool,
+    #[serde(rename = "deployment")]
+    deploy: String,
+    state: String,
+    cloned: bool,
+    branch: Option<String>,
+    #[serde(serialize_with = "serialize_upstream")]
+    upstream: Option<(usize, usize)>,
+    remote: Option<RemoteHealth>,
+    about: Option<String>,
+    required_by: Vec<String>,
+}
+
+impl ListingRow {
+    /// Gather listing information of node repository entry.
+    ///
+    /// # Errors
+    ///
+    /// - Will fail if deployment status cannot be obtained.
+    /// - Will fail if current branch cannot be obtained.
+    pub(crate) fn new(
+        name: impl Into<String>,
+        entry: &RepoEntry,
+        excluded: &SparseCheckout,
+    ) -> Result<Self> {
+        Self::gather(name.into(), false, entry, excluded)
+    }
+
+    /// Gather listing information of root repository entry.
+    ///
+    /// # Errors
+    ///
+    /// - Will fail if deployment status cannot be obtained.
+    /// - Will fail if current branch cannot be obtained.
+    pub(crate) fn root(entry: &RepoEntry, excluded: &SparseCheckout) -> Result<Self> {
+        Self::gather("root".into(), true, entry, excluded)
+    }
+
+    fn gather(
+        name: String,
+        is_root: bool,
+        entry: &RepoEntry,
+        excluded: &SparseCheckout,
+    ) -> Result<Self> {
+        let status = || -> Result<String> {
+            if is_deployed(entry, excluded, DeployState::WithExcluded)? {
+                return Ok("deployed fully".into());
+            }
+            Ok(scan_deployment(entry, excluded, DeployState::WithoutExcluded, true)?.to_string())
+        };
+        let (deploy, state) = if is_root {
+            ("bare-alias".to_string(), status()?)
+        } else if entry.deployment_kind.is_mirror() {
+            ("mirror".to_string(), "mirrored".to_string())
+        } else if entry.deployment_kind.is_cluster() {
+            let status = scan_deployment(entry, excluded, DeployState::WithExcluded, true)?;
+            ("cluster".to_string(), status.to_string())
+        } else if entry.is_bare_alias() || entry.is_inline() || entry.places_files() {
+            (entry.deployment_kind.to_string(), status()?)
+        } else {
+            ("normal".to_string(), "undeployable".to_string())
+        };
+
+        // INVARIANT: Root has no URL in cluster definition to compare origin with.
+        let remote = if is_root { RemoteHealth::Unchecked } else { entry.remote_health() };
+
+        let upstream = entry.ahead_behind()?;
+        let branch = entry.current_branch()?;
+        Ok(Self {
+            deploy,
+            name,
+            is_root,
+            state,
+            cloned: true,
+            branch: Some(branch),
+            upstream,
+            remote: Some(remote),
+            about: None,
+            required_by: Vec::new(),
+        })
+    }
+
+    /// Show human-readable metadata of node entry, see [`NodeEntry::about`].
+    pub(crate) fn with_about(mut self, node: &NodeEntry) -> Self {
+        self.about = node.about().filter(|about| !about.is_empty());
+        self
+    }
+
+    /// Annotate node entry as dependency of given targets.
+    pub(crate) fn required_by(mut self, targets: Vec<String>) -> Self {
+        self.required_by = targets;
+        self
+    }
+
+    /// Listing information of node entry missing from repository store.
+    pub(crate) fn missing(name: impl Into<String>, node: &NodeEntry) -> Self {
+        Self {
+            deploy: node.settings.deployment.kind.to_string(),
+            name: name.into(),
+            is_root: false,
+            state: "not cloned".into(),
+            cloned: false,
+            branch: None,
+            upstream: None,
+            remote: None,
+            about: None,
+            required_by: Vec::new(),
+        }
+        .with_about(node)
+    }
+}
+
+/// Health of origin remote compared to URL of cluster definition.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum RemoteHealth {
+    /// Origin matches cluster definition.
+    Matches,
+
+    /// Origin diverged from cluster definition, holding URL of origin.
+    Diverged(String),
+
+    /// Origin remote does not exist.
+    Missing,
+
+    /// Nothing to compare with, e.g., cluster definition has no URL.
+    Unchecked,
+}
+
+impl RemoteHealth {
+    /// Stable machine-readable name of health.
+    pub fn key(&self) -> &'static str {
+        match self {
+            RemoteHealth::Matches => "matches",
+            RemoteHealth::Diverged(_) => "diverged",
+            RemoteHealth::Missing => "missing",
+            RemoteHealth::Unchecked => "unchecked",
+        }
+    }
+}
+
+impl Serialize for RemoteHealth {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.key())
+    }
+}
+
+impl std::fmt::Display for RemoteHealth {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RemoteHealth::Matches => f.write_str("origin ok"),
+            RemoteHealth::Diverged(_) => f.write_str("origin diverged"),
+            RemoteHealth::Missing => f.write_str("no origin"),
+            RemoteHealth::Unchecked => f.write_str("-"),
+        }
+    }
+}
+
+/// Working tree status of repository entry.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub struct WorktreeStatus {
+    /// Index has changes that were not committed yet.
+    pub staged: bool,
+
+    /// Working directory alias has changes to tracked files that were not staged yet.
+    pub unstaged: bool,
+
+    /// Untracked files sit next to tracked files.
+    pub untracked: bool,
+
+    /// Commits ahead and behind of upstream branch, if there is one.
+    pub upstream: Option<(usize, usize)>,
+}
+
+impl WorktreeStatus {
+    /// Check if there is nothing to commit, and nothing to push or pull.
+    pub fn is_clean(&self) -> bool {
+        !self.staged && !self.unstaged && !self.untracked && self.is_up_to_date()
+    }
+
+    fn is_up_to_date(&self) -> bool {
+        self.upstream.is_none_or(|upstream| upstream == (0, 0))
+    }
+
+    fn state(&self) -> String {
+        let changes: Vec<&str> =
+            [(self.staged, "staged"), (self.unstaged, "unstaged"), (self.untracked, "untracked")]
+                .into_iter()
+                .filter_map(|(present, change)| present.then_some(change))
+                .collect();
+
+        if changes.is_empty() {
+            return "clean".into();
+        }
+
+        changes.join(", ")
+    }
+
+    fn upstream(&self) -> String {
+        upstream_label(self.upstream)
+    }
+
+    /// Condensed status in the spirit of "git status --short", e.g., "SM? ↑2 ↓0".
+    ///
+    /// Flags stand for staged changes, unstaged changes, and untracked files in that order, with
+    /// "-" for each one that is absent.
+    pub fn short(&self) -> String {
+        let flag = |present: bool, flag: char| if present { flag } else { '-' };
+        let flags: String =
+            [flag(self.staged, 'S'), flag(self.unstaged, 'M'), flag(self.untracked, '?')]
+                .into_iter()
+                .collect();
+
+        match self.upstream {
+            Some((ahead, behind)) if !self.is_up_to_date() => {
+                format!("{flags} ↑{ahead} ↓{behind}")
+            }
+            _ => flags,
+        }
+    }
+}
+
+/// Renderer of rows for fancy cluster listing.
+///
+/// Rows can be pushed in any order, i.e., whatever order concurrent tasks finish in. Each row is
+/// rendered as a progress line the moment it arrives, if streaming is enabled, while a progress
+/// bar counts rows still to come. Root always comes first, with nodes sorted by name once rows are
+/// taken for the final table.
+#[derive(Debug)]
+pub(crate) struct FancyRenderer {
+    rows: Vec<ListingRow>,
+    bar: ProgressBar,
+    stream: bool,
+}
+
+impl FancyRenderer {
+    /// Construct new empty renderer expecting given number of rows.
+    ///
+    /// # Errors
+    ///
+    /// - Will fail if progress bar cannot be started.
+    pub(crate) fn new(bar: ProgressBar, total: usize, stream: bool) -> Result<Self> {
+        bar.set_length(total as u64);
+        start_progress(&bar, "List cluster entries")?;
+        Ok(Self { rows: Vec::new(), bar, stream })
+    }
+
+    /// Push new row into renderer, rendering it as a progress line right away.
+    pub(crate) fn push(&mut self, row: ListingRow) {
+        debug!("Received listing row for {:?}", row.name);
+        if self.stream {
+            let line = format!("{}: {}", table_name(&row.name, row.is_root), row.state);
+            if is_plain() {
+                info!("{line}");
+            } else {
+                self.bar.println(line);
+            }
+        }
+        self.bar.inc(1);
+        self.rows.push(row);
+    }
+
+    /// Take all rows received, with root first, and nodes sorted by name.
+    pub(crate) fn into_rows(mut self) -> Vec<ListingRow> {
+        finish_progress(&self.bar);
+        self.rows.sort_by(|a, b| (!a.is_root, &a.name).cmp(&(!b.is_root, &b.name)));
+        self.rows
+    }
+}
+
+// Render table of fancy cluster listing, where normal nodes are marked as undeployable.
+fn render_listing(rows: &[ListingRow]) -> tabled::Table {
+    // INVARIANT: Metadata column only shows up if any entry has metadata to show.
+    let about = rows.iter().any(|row| row.about.is_some());
+    let mut builder = tabled::builder::Builder::new();
+    for row in rows {
+        let deploy = match row.deploy.as_str() {
+            "normal" => "[node:normal]".to_string(),
+            deploy => deploy.to_string(),
+        };
+        let name = annotate_dependency(table_name(&row.name, row.is_root), &row.required_by);
+        let (branch, upstream, remote) = if row.cloned {
+            (
+                row.branch.clone().unwrap_or_default(),
+                upstream_label(row.upstream),
+                row.remote.as_ref().map(ToString::to_string).unwrap_or_default(),
+            )
+        } else {
+            ("-".to_string(), "-".to_string(), "-".to_string())
+        };
+        let mut record = vec![deploy, name, row.state.clone(), branch, upstream, remote];
+        if about {
+            record.push(row.about.clone().unwrap_or_else(|| "-".into()));
+        }
+        builder.push_record(record);
+    }
+
+    let mut table = builder.build();
+    table.with(tabled::settings::Style::ascii_rounded());
+    table
+}
+
+// Root is listed as "<root>" in tables, but named like its target everywhere else.
+fn table_name(name: &str, is_root: bool) -> &str {
+    if is_root {
+        "<root>"
+    } else {
+        name
+    }
+}
+
+// Commits ahead and behind of upstream branch are keyed by name, rather than listed as a pair.
+fn serialize_upstream<S: serde::Serializer>(
+    upstream: &Option<(usize, usize)>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    #[derive(Serialize)]
+    struct Upstream {
+        ahead: usize,
+        behind: usize,
+    }
+
+    upstream.map(|(ahead, behind)| Upstream { ahead, behind }).serialize(serializer)
+}
+
+fn upstream_label(upstream: Option<(usize, usize)>) -> String {
+    match upstream {
+        Some((0, 0)) => "up to date".into(),
+        Some((ahead, behind)) => format!("↑{ahead} ↓{behind}"),
+        None => "no upstream".into(),
+    }
+}
+
+fn annotate_dependency(name: &str, required_by: &[String]) -> String {
+    if required_by.is_empty() {
+        return name.to_string();
+    }
+
+    format!("{name} (dependency of {})", required_by.join(", "))
+}
+
+/// Make sure that remote at URL can be reached.
+///
+/// Connects to the remote and lists its references without fetching anything, prompting for
+/// credentials if the remote asks for them.
+///
+/// # Errors
+///
+/// - Will fail if remote cannot be connected to, or refuses to list its references.
+#[instrument(level = "debug")]
+pub fn check_remote(url: &str) -> Result<()> {
+    let config = Config::open_default()?;
+    let authenticator = GitAuthenticator::default();
+    let mut rc = RemoteCallbacks::new();
+    rc.credentials(authenticator.credentials(&config));
+
+    let mut remote = Remote::create_detached(url)?;
+    let connection = remote
+        .connect_auth(Direction::Fetch, Some(rc), None)
+        .with_context(|| format!("Remote {url:?} is unreachable"))?;
+    let _ = connection.list()?;
+
+    Ok(())
+}
+
+/// Check if user's Git binary can be found through `$PATH`.
+pub fn has_git_binary() -> bool {
+    git_binary_version().is_some()
+}
+
+/// Get version of user's Git binary, e.g., "2.43.0", if it can be found through `$PATH`.
+pub fn git_binary_version() -> Option<String> {
+    let output = Command::new("git").arg("--version").output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+
+    let version = String::from_utf8_lossy(&output.stdout);
+    let version = version.trim();
+    Some(version.strip_prefix("git version ").unwrap_or(version).to_string())
+}
+
+/// Get version of libgit2 that OCD was built against, e.g., "1.9.0".
+pub fn libgit2_version() -> String {
+    let (major, minor, patch) = git2::Version::get().libgit2_version();
+    format!("{major}.{minor}.{patch}")
+}
+
+/// Make sure that user's Git binary can be found through `$PATH`.
+///
+/// Deployment, undeployment, and Git passthrough call the Git binary, while cloning, fetching,
+/// initializing, and listing entries go through libgit2, and work without it.
+///
+/// # Errors
+///
+/// - Will fail with installation guidance if Git binary is missing.
+pub fn require_git_binary() -> Result<()> {
+    if has_git_binary() {
+        return Ok(());
+    }
+
+    Err(anyhow!(MISSING_GIT_BINARY))
+}
+
+const MISSING_GIT_BINARY: &str = "\
+Cannot find Git binary \"git\" in $PATH. Cloning a cluster, deploying, undeploying, and Git \
+passthrough need it, while fetching into the repository store and listing entries go through \
+libgit2, as long as root is already deployed. Install Git through \
+the package manager of your system, e.g., \"apt install git\", \"dnf install git\", or \
+\"brew install git\", and try again";
+
+/// Render compact status of cluster for shell prompts.
+///
+/// Counts entries that are ahead of their upstream branch, and entries with uncommitted
+/// modifications, e.g., `ocd:3↑ 1✗`. Renders nothing at all if every entry is clean and up to
+/// date, such that prompts stay quiet. Status of each entry is taken from the status cache in
+/// OCD's state directory whenever possible, unless `refresh` is set.
+///
+/// # Invariants
+///
+/// - Entries missing from repository store are skipped, never cloned.
+/// - Inline nodes are covered by root, and mirror nodes have nothing to report.
+///
+/// # Errors
+///
+/// - Will fail if any entry cannot be opened, or its status cannot be obtained.
+/// - Will fail if status cache cannot be saved.
+#[instrument(skip(cluster), level = "debug")]
+pub fn prompt_segment(cluster: &Cluster, refresh: bool) -> Result<String> {
+    let mut cache = StatusCache::load()?;
+    if refresh {
+        cache.clear();
+    }
 
-            nodes.push(repo);
+    let mut entries = Vec::new();
+    if data_dir()?.join("root").exists() {
+        let entry = RepoEntry::builder("root")?
+            .deployment_kind(DeploymentKind::BareAlias)
+            .work_dir_alias(cluster.root.settings.work_dir_alias.clone())
+            .open()?;
+        entries.push(entry);
+    }
+
+    for (name, node) in &cluster.nodes {
+        let kind = &node.settings.deployment.kind;
+        let builder = RepoEntry::builder(name)?
+            .url(&node.settings.url)
+            .deployment_kind(kind.clone())
+            .work_dir_alias(node.settings.deployment.work_dir_alias.clone())
+            .external(node.settings.path.clone());
+        if kind.is_inline() || kind.is_mirror() || !builder.exists() {
+            continue;
         }
 
-        Ok(Self { nodes, multi_bar, jobs })
+        let entry = builder.open()?;
+        entries.push(entry);
     }
 
-    /// Clone all node entries in cluster asynchronously.
+    let (mut ahead, mut dirty) = (0, 0);
+    for entry in &entries {
+        let status =
+            cache.get_or_update(entry.name(), &entry.status_fingerprint(), || entry.status())?;
+        ahead += usize::from(status.ahead > 0);
+        dirty += usize::from(status.dirty);
+    }
+    cache.retain(entries.iter().map(RepoEntry::name));
+
+    // INVARIANT: Shell prompts keep working on read-only state directories, just without caching.
+    if let Err(error) = cache.save() {
+        debug!("Cannot save status cache: {error:?}");
+    }
+
+    let mut parts = Vec::new();
+    if ahead > 0 {
+        parts.push(format!("{ahead}↑"));
+    }
+    if dirty > 0 {
+        parts.push(format!("{dirty}✗"));
+    }
+
+    if parts.is_empty() {
+        return Ok(String::new());
+    }
+
+    Ok(format!("ocd:{}", parts.join(" ")))
+}
+
+/// Check if node repository can be opened without cloning it.
+///
+/// Inline nodes always can, external nodes can if their repository exists, and every other node
+/// can if it is in the repository store.
+///
+/// # Errors
+///
+/// - Will fail if data directory cannot be determined.
+pub fn is_node_present(name: impl AsRef<str>, node: &NodeEntry) -> Result<bool> {
+    if node.settings.subdir.is_some() {
+        return Ok(true);
+    }
+
+    Ok(RepoEntry::builder(name.as_ref())?
+        .url(&node.settings.url)
+        .deployment_kind(node.settings.deployment.kind.clone())
+        .external(node.settings.path.clone())
+        .exists())
+}
+
+/// Determine URL of origin remote of repository at path.
+///
+/// Returns nothing if repository has no origin remote, or its URL is not valid UTF-8.
+///
+/// # Errors
+///
+/// - Will fail if path is not a repository.
+pub fn origin_url(path: &Path) -> Result<Option<String>> {
+    let repository =
+        Repository::open(path).with_context(|| format!("Failed to open repository at {path:?}"))?;
+    let url = repository.find_remote("origin").ok().and_then(|remote| remote.url().map(Into::into));
+
+    Ok(url)
+}
+
+/// Write report of deployed entries into report file at path.
+///
+/// Lists root and every deployed node entry, see [`RootEntrySettings::deploy_report`]. Tilde and
+/// shell variables of path are expanded.
+///
+/// # Invariants
+///
+/// - Entries missing from repository store are skipped, never cloned.
+/// - Report file is kept out of `git add` of every root or bare-alias entry whose working
+///   directory alias houses it.
+///
+/// # Errors
+///
+/// - Will fail if path cannot be expanded.
+/// - Will fail if any entry cannot be opened, or its deployment cannot be checked.
+/// - Will fail if report file cannot be written.
+///
+/// [`RootEntrySettings::deploy_report`]: crate::model::cluster::RootEntrySettings::deploy_report
+#[instrument(skip(cluster, path), level = "debug")]
+pub fn write_deploy_report(cluster: &Cluster, path: impl AsRef<Path>) -> Result<()> {
+    let path = PathBuf::from(expand_path(&path.as_ref().to_string_lossy())?);
+    let root = Root::new_open(&cluster.root)?;
+    let mut entries = Vec::new();
+    let mut worktrees = vec![&root.entry];
+    if let Some(entry) = deployed_entry(&root.entry, &root.deployer.excluded)? {
+        entries.push(("root".to_string(), entry));
+    }
+
+    let mut nodes = Vec::new();
+    for (name, node) in &cluster.nodes {
+        let exists = RepoEntry::builder(name)?
+            .url(&node.settings.url)
+            .deployment_kind(node.settings.deployment.kind.clone())
+            .external(node.settings.path.clone())
+            .exists();
+        if node.settings.subdir.is_some() || exists {
+            nodes.push(Node::new_open(name, node)?);
+        }
+    }
+    for node in &nodes {
+        if let Some(entry) = deployed_entry(&node.entry, &node.deployer.excluded)? {
+            entries.push((node.name().to_string(), entry));
+        }
+        if node.is_bare_alias() {
+            worktrees.push(&node.entry);
+        }
+    }
+
+    let mut report = DeployReport::load_from(&path);
+    report.regenerate(entries);
+    debug!("Report {} deployed entries", report.entries().len());
+    for entry in worktrees {
+        entry.exclude_untracked(report.path())?;
+    }
+
+    report.save()
+}
+
+// Describe repository entry for deployment report, if it is deployed at all.
+fn deployed_entry(entry: &RepoEntry, excluded: &SparseCheckout) -> Result<Option<DeployedEntry>> {
+    if !is_deployed(entry, excluded, DeployState::WithoutExcluded)? {
+        return Ok(None);
+    }
+
+    Ok(Some(DeployedEntry {
+        deployment: entry.deployment_kind.to_string(),
+        branch: entry.current_branch()?,
+        commit: entry.head_id().unwrap_or_default(),
+        with_excluded: is_deployed(entry, excluded, DeployState::WithExcluded)?,
+        deployed_at: 0,
+    }))
+}
+
+/// Fetch refspec of mirror entries, mapping every reference of remote onto itself.
+const MIRROR_REFSPEC: &str = "+refs/*:refs/*";
+
+/// Default number of jobs to use for concurrent operations.
+///
+/// Uses the number of available CPU cores.
+pub(crate) fn default_jobs() -> usize {
+    std::thread::available_parallelism().map(Into::into).unwrap_or(1)
+}
+
+/// Entry representation of repository store.
+///
+/// Provides basic routines to create and manage repository entries in repository store of user's
+/// cluster.
+pub(crate) struct RepoEntry {
+    name: String,
+    repository: Repository,
+    url: String,
+    deployment_kind: DeploymentKind,
+    work_dir_alias: WorkDirAlias,
+    subdir: Option<PathBuf>,
+    pin: Option<NodePin>,
+    authenticator: GitAuthenticator,
+}
+
+impl RepoEntry {
+    /// Use builder to construct new repository entry.
+    pub(crate) fn builder(name: impl Into<String>) -> Result<RepoEntryBuilder> {
+        RepoEntryBuilder::new(name)
+    }
+
+    /// Set deployment type for repository entry.
+    pub(crate) fn set_deployment(
+        &mut self,
+        deployment_kind: DeploymentKind,
+        work_dir_alias: WorkDirAlias,
+    ) {
+        self.deployment_kind = deployment_kind;
+        self.work_dir_alias = work_dir_alias;
+    }
+
+    /// Check if repository entry is empty.
     ///
-    /// Shows clone progress for each clone tasks. Tasks may block if user needs to enter their
-    /// credentials.
+    /// A repository with no commits is considered to be empty.
     ///
-    /// # Invariants
+    /// # Errors
     ///
-    /// - Progress bars are properly finished no matter what.
+    /// - Will fail if revwalk can not be performed.
+    pub(crate) fn is_empty(&self) -> Result<bool> {
+        match self.repository.head() {
+            Ok(_) => {
+                let mut revwalk = self.repository.revwalk()?;
+                revwalk.push_head()?;
+                let mut no_commits = true;
+
+                if revwalk.flatten().next().is_some() {
+                    no_commits = false;
+                }
+
+                Ok(no_commits)
+            }
+            Err(_) => Ok(true),
+        }
+    }
+
+    /// Check if repository is bare-alias.
+    pub(crate) fn is_bare_alias(&self) -> bool {
+        self.repository.is_bare() && self.deployment_kind.is_bare_alias()
+    }
+
+    /// Check if repository entry lives outside the repository store.
+    pub(crate) fn is_external(&self) -> bool {
+        data_dir().is_ok_and(|dir| !self.repository.path().starts_with(dir))
+    }
+
+    /// Check if repository entry is inline node of root.
+    pub(crate) fn is_inline(&self) -> bool {
+        self.repository.is_bare() && self.deployment_kind.is_inline()
+    }
+
+    /// Check if repository entry is hard-link, symlink, or copy.
+    pub(crate) fn places_files(&self) -> bool {
+        !self.repository.is_bare() && self.deployment_kind.places_files()
+    }
+
+    /// Name of repository entry.
+    pub(crate) fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Absolute path to repository entry's gitdir.
+    pub(crate) fn path(&self) -> &Path {
+        self.repository.path()
+    }
+
+    /// Set authentication prompter.
+    pub(crate) fn set_authentication_prompter(
+        &mut self,
+        prompter: impl Prompter + Clone + 'static,
+    ) {
+        let authenticator = std::mem::take(&mut self.authenticator);
+        self.authenticator = authenticator.set_prompter(prompter);
+    }
+
+    /// Fetch from "origin" remote of repository entry.
     ///
-    /// # Panics
+    /// Shows fetch progress through given progress bar.
     ///
-    /// - Will panic if mutex guard fails to lock.
-    /// - Will panic if mutex cannot be unwrapped to extract clone task result data.
+    /// # Errors
+    ///
+    /// Will fail if there is no "origin" remote, or fetch fails for whatever reason.
+    #[instrument(skip(self, bar), fields(node = %self.name), level = "debug")]
+    pub(crate) fn fetch(&self, bar: &ProgressBar) -> Result<()> {
+        let mut remote = self.repository.find_remote("origin")?;
+        let url = remote.url().unwrap_or_default().to_string();
+        start_progress(bar, format!("{} - {url}", self.name))?;
+
+        let config = Config::open_default()?;
+        let mut fo = progress_fetch_options(&self.authenticator, &config, bar);
+
+        // INVARIANT: Mirrors drop references that their remote dropped.
+        if self.deployment_kind.is_mirror() {
+            let _ = fo.prune(FetchPrune::On);
+        }
+        if matches!(self.pin, Some(NodePin::Tag(_))) {
+            let _ = fo.download_tags(AutotagOption::All);
+        }
+        traced("libgit2", ["fetch", url.as_str()], || {
+            remote.fetch::<&str>(&[], Some(&mut fo), None)
+        })?;
+        debug!("Fetched {:?} from {url}", self.name);
+        self.check_pin_upstream(&remote)?;
+
+        Ok(())
+    }
+
+    // INVARIANT: Pinned references count as gone once remote no longer advertises them, even
+    // though their local copies stick around.
+    fn check_pin_upstream(&self, remote: &Remote<'_>) -> Result<()> {
+        let Some(pin) = &self.pin else {
+            return Ok(());
+        };
+
+        let heads = remote.list()?;
+        let exists = match pin {
+            NodePin::Branch(branch) => {
+                heads.iter().any(|head| head.name() == format!("refs/heads/{branch}"))
+            }
+            NodePin::Tag(tag) => heads.iter().any(|head| head.name() == format!("refs/tags/{tag}")),
+            NodePin::Rev(_) => resolve_pin(&self.repository, pin).is_some_and(|target| {
+                heads.iter().any(|head| {
+                    head.oid() == target
+                        || self.repository.graph_descendant_of(head.oid(), target).unwrap_or(false)
+                })
+            }),
+        };
+        if !exists {
+            return Err(anyhow!("Pinned {pin} of {:?} no longer exists upstream", self.name));
+        }
+
+        Ok(())
+    }
+
+    /// Count commits ahead and behind of upstream of current branch.
+    ///
+    /// Falls back to "origin" remote-tracking branch of the same name when no upstream has been
+    /// configured. Returns [`None`] if there is nothing to compare with.
     ///
     /// # Errors
     ///
-    /// - Will fail for clone task failure.
-    ///     - Failed clone tasks will not cancel any active clone tasks that are not failing.
-    ///     - Results are only collected until _all_ clone tasks have finished.
-    ///     - All errors are reported in one-shot.
-    pub async fn clone_all(self) -> Result<()> {
-        let mut bars = Vec::new();
-        let results = Arc::new(Mutex::new(Vec::new()));
+    /// Will fail if commit graph cannot be walked.
+    pub(crate) fn ahead_behind(&self) -> Result<Option<(usize, usize)>> {
+        match self.head_and_upstream() {
+            Some((local, upstream)) => {
+                Ok(Some(self.repository.graph_ahead_behind(local, upstream)?))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn head_and_upstream(&self) -> Option<(Oid, Oid)> {
+        let head = self.repository.head().ok()?;
+        let local = head.target()?;
+
+        let branch = String::from_utf8_lossy(head.shorthand_bytes()).into_owned();
+        let upstream = if head.is_branch() {
+            git2::Branch::wrap(head).upstream().ok().and_then(|upstream| upstream.get().target())
+        } else {
+            None
+        };
+        let upstream = upstream.or_else(|| {
+            self.repository.refname_to_id(&format!("refs/remotes/origin/{branch}")).ok()
+        })?;
+
+        Some((local, upstream))
+    }
+
+    /// Pull entry from its upstream, see [`Node::pull`].
+    ///
+    /// # Errors
+    ///
+    /// - Will fail if origin remote cannot be fetched.
+    /// - Will fail if entry cannot be fast-forwarded, rebased, or merged.
+    #[instrument(skip(self, diverged), fields(node = %self.name), level = "debug")]
+    pub(crate) fn pull(
+        &self,
+        diverged: impl FnOnce() -> Result<PullStrategy>,
+    ) -> Result<PullOutcome> {
+        if self.repository.find_remote("origin").is_err() {
+            return Ok(PullOutcome::NoUpstream);
+        }
+
+        // INVARIANT: Mirrors have no branch of their own to update, only references to fetch.
+        if self.deployment_kind.is_mirror() {
+            self.gitcall_non_interactive(["fetch", "--quiet", "--prune", "origin"])?;
+            return Ok(PullOutcome::Fetched);
+        }
+
+        // INVARIANT: Pinned entries fetch through libgit2, which tells whether their pinned
+        // reference still exists upstream.
+        if self.pin.is_some() {
+            self.fetch(&ProgressBar::hidden())?;
+            return self.pull_fetched(diverged);
+        }
+
+        // INVARIANT: Detached HEADs, e.g., of frozen nodes, stay where they were put.
+        if self.repository.head_detached().unwrap_or(false) {
+            return Ok(PullOutcome::Detached);
+        }
+
+        self.gitcall_non_interactive(["fetch", "--quiet", "origin"])?;
+        self.pull_fetched(diverged)
+    }
+
+    /// Pull entry from its upstream that was fetched already, see [`Node::pull_fetched`].
+    ///
+    /// # Errors
+    ///
+    /// - Will fail if entry cannot be fast-forwarded, rebased, or merged.
+    #[instrument(skip(self, diverged), fields(node = %self.name), level = "debug")]
+    pub(crate) fn pull_fetched(
+        &self,
+        diverged: impl FnOnce() -> Result<PullStrategy>,
+    ) -> Result<PullOutcome> {
+        if self.repository.find_remote("origin").is_err() {
+            return Ok(PullOutcome::NoUpstream);
+        }
+        if self.deployment_kind.is_mirror() {
+            return Ok(PullOutcome::Fetched);
+        }
+
+        // INVARIANT: Entries pinned to a tag or revision stay detached at its commit, and entries
+        // pinned to a branch get back onto it before pulling.
+        let mut repinned = false;
+        match &self.pin {
+            Some(pin @ (NodePin::Tag(_) | NodePin::Rev(_))) => return self.pull_pinned(pin),
+            Some(NodePin::Branch(branch))
+                if self.current_branch().ok().as_ref() != Some(branch) =>
+            {
+                self.gitcall_non_interactive(["checkout", "--quiet", branch.as_str()])?;
+                info!("Move {:?} back onto pinned branch {branch:?}", self.name);
+                repinned = true;
+            }
+            _ => {}
+        }
+
+        if self.repository.head_detached().unwrap_or(false) {
+            return Ok(PullOutcome::Detached);
+        }
+
+        let Some((local, upstream)) = self.head_and_upstream() else {
+            return Ok(PullOutcome::NoUpstream);
+        };
+        let (ahead, behind) = self.repository.graph_ahead_behind(local, upstream)?;
+        if behind == 0 {
+            return Ok(if repinned { PullOutcome::Pinned } else { PullOutcome::UpToDate });
+        }
+
+        let upstream = upstream.to_string();
+
+        if ahead == 0 {
+            self.gitcall_non_interactive(["merge", "--quiet", "--ff-only", upstream.as_str()])?;
+            info!("Fast-forward {:?} to {upstream}", self.name);
+            return Ok(PullOutcome::FastForwarded);
+        }
+
+        let (args, abort, outcome) = match diverged()? {
+            PullStrategy::Rebase => (["rebase", "--quiet"], "rebase", PullOutcome::Rebased),
+            PullStrategy::Merge => (["merge", "--no-edit"], "merge", PullOutcome::Merged),
+            PullStrategy::Skip | PullStrategy::Prompt => return Ok(PullOutcome::Diverged),
+        };
+
+        let result = self.gitcall_non_interactive(args.iter().copied().chain([upstream.as_str()]));
+        if let Err(error) = result {
+            // INVARIANT: Conflicts never leave entry halfway through a rebase or merge.
+            let _ = self.gitcall_non_interactive([abort, "--abort"]);
+            return Err(error.context(format!(
+                "Failed to {abort} {:?} with its upstream, left it as it was",
+                self.name
+            )));
+        }
+        info!("Pull {:?} through {abort} with {upstream}", self.name);
+
+        Ok(outcome)
+    }
+
+    fn pull_pinned(&self, pin: &NodePin) -> Result<PullOutcome> {
+        let target = resolve_pin(&self.repository, pin)
+            .ok_or_else(|| anyhow!("Pinned {pin} of {:?} not found", self.name))?;
+        let head = self.repository.head().ok().and_then(|head| head.target());
+        if self.repository.head_detached().unwrap_or(false) && head == Some(target) {
+            return Ok(PullOutcome::UpToDate);
+        }
+
+        let target = target.to_string();
+        self.gitcall_non_interactive(["checkout", "--quiet", "--detach", target.as_str()])?;
+        info!("Move {:?} to pinned {pin} at {target}", self.name);
+
+        Ok(PullOutcome::Pinned)
+    }
+
+    /// Get name of current branch pointed to by HEAD.
+    ///
+    /// Returns current branch in lossy UTF-8 form.
+    ///
+    /// # Errors
+    ///
+    /// - Will fail if HEAD connot be determined.
+    pub(crate) fn current_branch(&self) -> Result<String> {
+        let shorthand = self.repository.head()?.shorthand_bytes().to_vec();
+        Ok(String::from_utf8_lossy(shorthand.as_slice()).into_owned())
+    }
+
+    /// Get URL of origin remote, if any.
+    pub(crate) fn origin_url(&self) -> Option<String> {
+        self.repository.find_remote("origin").ok().and_then(|remote| remote.url().map(Into::into))
+    }
+
+    /// Get SHA-256 digest of archive that repository entry was populated from, if any.
+    pub(crate) fn archive_digest(&self) -> Option<String> {
+        self.repository.config().ok()?.get_string(DIGEST_KEY).ok()
+    }
 
-        stream::iter(self.nodes)
-            .for_each_concurrent(self.jobs, |node| {
-                let results = results.clone();
-                let bar = self.multi_bar.add(ProgressBar::no_length());
-                bars.push(bar.clone());
+    /// Compare origin remote with URL of cluster definition.
+    pub(crate) fn remote_health(&self) -> RemoteHealth {
+        if self.url.is_empty() || self.is_inline() {
+            return RemoteHealth::Unchecked;
+        }
 
-                async move {
-                    let node_name = node.name.clone();
-                    let result = tokio::spawn(async move { node.clone(&bar) }).await;
-                    let mut guard = results.lock().unwrap();
-                    guard.push(
-                        result.map_err(|err| anyhow!("Failed to clone {node_name:?}: {err:?}")),
-                    );
-                    drop(guard);
-                }
-            })
-            .await;
+        // INVARIANT: Spelling differences of the same URL never count as divergence.
+        let is_same_repo = |url: &str| match (GitUrl::parse(url), GitUrl::parse(&self.url)) {
+            (Ok(left), Ok(right)) => left.is_same_repo(&right),
+            _ => url == self.url,
+        };
 
-        // INVARIANT: All progress bars should be finished properly.
-        for bar in bars {
-            bar.finish_and_clear();
+        match self.origin_url() {
+            Some(url) if is_same_repo(&url) => RemoteHealth::Matches,
+            Some(url) => RemoteHealth::Diverged(url),
+            None => RemoteHealth::Missing,
         }
+    }
 
-        // INVARIANT: Collect and report _all_ failures encountered.
-        let results = Arc::try_unwrap(results).unwrap().into_inner().unwrap();
-        let _ = results.into_iter().flatten().bcollect::<Vec<_>>()?;
+    /// Point origin remote to URL of cluster definition, creating it if needed.
+    ///
+    /// # Errors
+    ///
+    /// - Will fail if origin remote cannot be created or modified.
+    pub(crate) fn repair_origin(&self) -> Result<()> {
+        self.set_origin(&self.url)
+    }
+
+    /// Point origin remote to given URL, creating it if needed.
+    ///
+    /// # Errors
+    ///
+    /// - Will fail if origin remote cannot be created or modified.
+    pub(crate) fn set_origin(&self, url: &str) -> Result<()> {
+        if self.repository.find_remote("origin").is_ok() {
+            self.repository.remote_set_url("origin", url)?;
+        } else {
+            let _ = self.repository.remote("origin", url)?;
+        }
+        info!("Point origin of {:?} to {url:?}", self.name);
 
         Ok(())
     }
-}
 
-/// Tablize repository entry information in cluster.
-#[derive(Debug)]
-pub struct TablizeCluster<'cluster> {
-    root: &'cluster Root,
-    cluster: &'cluster Cluster,
-}
+    /// Keep untracked file in working directory alias out of `git add` through `info/exclude`.
+    ///
+    /// Files outside of the working directory alias are left alone.
+    ///
+    /// # Errors
+    ///
+    /// - Will fail if `info/exclude` cannot be read or written.
+    pub(crate) fn exclude_untracked(&self, path: &Path) -> Result<()> {
+        let Ok(relative) = path.strip_prefix(&self.work_dir_alias.0) else {
+            return Ok(());
+        };
 
-impl<'cluster> TablizeCluster<'cluster> {
-    /// Construct new cluster tablizer.
-    pub fn new(root: &'cluster Root, cluster: &'cluster Cluster) -> Self {
-        Self { root, cluster }
+        self.exclude_relative(relative)
     }
 
-    /// List only names of all entries in cluster.
+    /// Keep untracked file at path relative to work tree out of `git add` through
+    /// `info/exclude`.
     ///
     /// # Errors
     ///
-    /// - Will fail if a given root or node entry does not exist.
-    pub fn names_only(&self) -> Result<()> {
-        let mut builder = tabled::builder::Builder::new();
-        builder.push_record(["<root>"]);
-
-        // INVARIANT: All node entries must be sorted by name.
-        let mut nodes: Vec<Node> = self
-            .cluster
-            .nodes
-            .iter()
-            .map(|(name, node)| Node::new_open(name, node))
-            .collect::<Result<Vec<_>>>()?;
-        nodes.sort_by(|a, b| a.name().cmp(b.name()));
+    /// - Will fail if `info/exclude` cannot be read or written.
+    pub(crate) fn exclude_relative(&self, relative: &Path) -> Result<()> {
+        if self.repository.index()?.get_path(relative, 0).is_some() {
+            warn!(
+                "{relative:?} is tracked by {:?}, untrack it to keep it out of commits",
+                self.name
+            );
+        }
 
-        for node in &nodes {
-            builder.push_record([node.name()]);
+        let rule = format!("/{}", relative.to_string_lossy());
+        let exclude = self.repository.path().join("info").join("exclude");
+        let mut data = std::fs::read_to_string(&exclude).unwrap_or_default();
+        if data.lines().any(|line| line == rule) {
+            return Ok(());
         }
 
-        let mut table = builder.build();
-        table.with(tabled::settings::Style::ascii_rounded());
-        info!("Name only listing:\n{table}");
+        if !data.is_empty() && !data.ends_with('\n') {
+            data.push('\n');
+        }
+        data.push_str(&rule);
+        data.push('\n');
+        if let Some(parent) = exclude.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create {parent:?}"))?;
+        }
+        std::fs::write(&exclude, data).with_context(|| format!("Failed to write {exclude:?}"))?;
+        debug!("Exclude {rule:?} from {:?}", self.name);
 
         Ok(())
     }
 
-    /// List a wide range information about each entry in cluster.
+    /// Set origin remote as upstream of current branch.
     ///
-    /// Will list the following information:
-    ///
-    /// - Deployment kind.
-    /// - Entry name.
-    /// - Deployment status.
-    /// - Currently active branch.
+    /// Works on unborn branches too, such that the first push of a brand-new repository entry
+    /// knows where to go.
     ///
     /// # Errors
     ///
-    /// - Will fail if a given root or node entry does not exist.
-    /// - Will fail if deployment status cannot be obtained.
-    /// - Will fail if current branch cannot be obtained.
-    #[instrument(skip(self), level = "debug")]
-    pub fn fancy(&self) -> Result<()> {
-        let mut builder = tabled::builder::Builder::new();
-        let state = if is_deployed(
-            &self.root.entry,
-            &self.root.deployer.excluded,
-            DeployState::WithExcluded,
-        )? {
-            "deployed fully"
-        } else {
-            "deployed"
-        };
-        builder.push_record(["bare-alias", "<root>", state, self.root.current_branch()?.as_str()]);
+    /// - Will fail if HEAD is detached.
+    /// - Will fail if branch configuration cannot be written.
+    pub(crate) fn track_origin(&self) -> Result<()> {
+        let head = self.repository.find_reference("HEAD")?;
+        let target = head
+            .symbolic_target()
+            .ok_or(anyhow!("HEAD of {:?} is detached", self.name))?
+            .to_string();
+        let branch = target.strip_prefix("refs/heads/").unwrap_or(&target);
+
+        let mut config = self.repository.config()?;
+        config.set_str(&format!("branch.{branch}.remote"), "origin")?;
+        config.set_str(&format!("branch.{branch}.merge"), &target)?;
+        debug!("Track origin with branch {branch:?} of {:?}", self.name);
 
-        // INVARIANT: All node entries must be sorted by name.
-        let mut nodes: Vec<Node> = self
-            .cluster
-            .nodes
-            .iter()
-            .map(|(name, node)| Node::new_open(name, node))
-            .collect::<Result<Vec<_>>>()?;
-        nodes.sort_by(|a, b| a.name().cmp(b.name()));
-
-        for node in &nodes {
-            let (deploy, state) = if node.entry.is_bare_alias() {
-                if is_deployed(&node.entry, &node.deployer.excluded, DeployState::WithExcluded)? {
-                    ("bare-alias", "deployed fully")
-                } else if is_deployed(
-                    &node.entry,
-                    &node.deployer.excluded,
-                    DeployState::WithoutExcluded,
-                )? {
-                    ("bare-alias", "deployed")
-                } else {
-                    ("bare-alias", "undeployed")
-                }
-            } else {
-                ("[node:normal]", "undeployable")
-            };
-            builder.push_record([deploy, node.name(), state, node.current_branch()?.as_str()]);
-        }
+        Ok(())
+    }
 
-        let mut table = builder.build();
-        table.with(tabled::settings::Style::ascii_rounded());
-        info!("Fancy listing:\n{table}");
+    /// Get commit ID that HEAD currently points to.
+    ///
+    /// Always reads HEAD fresh from disk, such that changes made by external Git calls are seen.
+    /// Will be none if HEAD is unborn.
+    pub(crate) fn head_id(&self) -> Option<String> {
+        self.repository.head().ok().and_then(|head| head.target()).map(|oid| oid.to_string())
+    }
 
-        Ok(())
+    /// Get fingerprint of repository state that status of entry depends on.
+    ///
+    /// Changes whenever HEAD moves, the index gets written, or a fetch happens.
+    pub(crate) fn status_fingerprint(&self) -> String {
+        let mtime = |name: &str| {
+            self.repository
+                .path()
+                .join(name)
+                .metadata()
+                .and_then(|metadata| metadata.modified())
+                .ok()
+                .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+                .map(|modified| modified.as_nanos())
+                .unwrap_or_default()
+        };
+
+        format!("{}:{}:{}", self.head_id().unwrap_or_default(), mtime("index"), mtime("FETCH_HEAD"))
     }
-}
 
-/// Entry representation of repository store.
-///
-/// Provides basic routines to create and manage repository entries in repository store of user's
-/// cluster.
-pub(crate) struct RepoEntry {
-    name: String,
-    repository: Repository,
-    deployment_kind: DeploymentKind,
-    work_dir_alias: WorkDirAlias,
-    authenticator: GitAuthenticator,
-}
+    /// Compute status of repository entry.
+    ///
+    /// Only files tracked by the index count as modifications. Files missing from the working
+    /// directory alias do not count, because undeployed and excluded files are missing on purpose.
+    ///
+    /// # Errors
+    ///
+    /// - Will fail if status of index or working directory alias cannot be obtained.
+    /// - Will fail if commit graph cannot be walked.
+    pub(crate) fn status(&self) -> Result<EntryStatus> {
+        let (staged, unstaged) = self.changes()?;
+        let ahead = self.ahead_behind()?.map_or(0, |(ahead, _)| ahead);
 
-impl RepoEntry {
-    /// Use builder to construct new repository entry.
-    pub(crate) fn builder(name: impl Into<String>) -> Result<RepoEntryBuilder> {
-        RepoEntryBuilder::new(name)
+        Ok(EntryStatus { dirty: staged || unstaged, ahead })
     }
 
-    /// Set deployment type for repository entry.
-    pub(crate) fn set_deployment(
-        &mut self,
-        deployment_kind: DeploymentKind,
-        work_dir_alias: WorkDirAlias,
-    ) {
-        self.deployment_kind = deployment_kind;
-        self.work_dir_alias = work_dir_alias;
+    /// Compute working tree status of repository entry.
+    ///
+    /// Modifications count the same way as they do for [`RepoEntry::status`]. Untracked files
+    /// only count if they sit next to tracked files, see [`RepoEntry::untracked_near_tracked`].
+    ///
+    /// # Errors
+    ///
+    /// - Will fail if status of index or working directory alias cannot be obtained.
+    /// - Will fail if gitignore rules cannot be checked.
+    /// - Will fail if commit graph cannot be walked.
+    pub(crate) fn worktree_status(&self) -> Result<WorktreeStatus> {
+        let (staged, unstaged) = self.changes()?;
+        let untracked = !self.untracked_near_tracked()?.is_empty();
+
+        Ok(WorktreeStatus { staged, unstaged, untracked, upstream: self.ahead_behind()? })
     }
 
-    /// Check if repository entry is empty.
+    // INVARIANT: Entries that were never deployed have no index to be dirty with.
+    fn changes(&self) -> Result<(bool, bool)> {
+        if self.deployment_kind.is_mirror() || self.repository.index()?.is_empty() {
+            return Ok((false, false));
+        }
+
+        // INVARIANT: Bare-alias entries compare their index against working directory alias.
+        if self.repository.is_bare() {
+            self.repository.set_workdir(&self.work_dir_alias.0, false)?;
+        }
+
+        let mut opts = StatusOptions::new();
+        opts.include_untracked(false).include_ignored(false).exclude_submodules(true);
+        let staged = Status::INDEX_NEW
+            | Status::INDEX_MODIFIED
+            | Status::INDEX_DELETED
+            | Status::INDEX_RENAMED
+            | Status::INDEX_TYPECHANGE;
+        let unstaged = Status::WT_MODIFIED | Status::WT_RENAMED | Status::WT_TYPECHANGE;
+        let statuses = self.repository.statuses(Some(&mut opts))?;
+
+        Ok((
+            statuses.iter().any(|entry| entry.status().intersects(staged)),
+            statuses.iter().any(|entry| entry.status().intersects(unstaged)),
+        ))
+    }
+
+    /// List untracked files that live in the same directories as tracked files.
     ///
-    /// A repository with no commits is considered to be empty.
+    /// Working directory aliases like the home directory are far too big to scan in full, and
+    /// mostly house files that have nothing to do with the entry. Only directories that directly
+    /// contain tracked files are scanned, without descending into their subdirectories. Paths are
+    /// relative to the working directory alias.
+    ///
+    /// # Invariants
+    ///
+    /// - Files ignored through gitignore rules, "info/exclude", or "core.excludesFile" are never
+    ///   listed.
+    /// - Directories of tracked files that are missing from working directory alias are skipped.
     ///
     /// # Errors
     ///
-    /// - Will fail if revwalk can not be performed.
-    pub(crate) fn is_empty(&self) -> Result<bool> {
-        match self.repository.head() {
-            Ok(_) => {
-                let mut revwalk = self.repository.revwalk()?;
-                revwalk.push_head()?;
-                let mut no_commits = true;
+    /// - Will fail if index cannot be read.
+    /// - Will fail if gitignore rules cannot be checked.
+    pub(crate) fn untracked_near_tracked(&self) -> Result<Vec<PathBuf>> {
+        if self.deployment_kind.is_mirror() {
+            return Ok(Vec::new());
+        }
 
-                if revwalk.flatten().next().is_some() {
-                    no_commits = false;
+        // INVARIANT: Bare-alias entries check gitignore rules against working directory alias.
+        if self.repository.is_bare() {
+            self.repository.set_workdir(&self.work_dir_alias.0, false)?;
+        }
+        let Some(work_dir) = self.repository.workdir() else {
+            return Ok(Vec::new());
+        };
+
+        let index = self.repository.index()?;
+        let dirs: BTreeSet<PathBuf> = index
+            .iter()
+            .filter_map(|entry| bytes_to_path(&entry.path).parent().map(Path::to_path_buf))
+            .collect();
+
+        let mut untracked = Vec::new();
+        for dir in &dirs {
+            let Ok(files) = read_dir(work_dir.join(dir)) else {
+                continue;
+            };
+
+            for file in files {
+                let file = file?;
+                if file.file_type()?.is_dir() {
+                    continue;
                 }
 
-                Ok(no_commits)
+                let path = dir.join(file.file_name());
+                if index.get_path(&path, 0).is_none() && !self.repository.is_path_ignored(&path)? {
+                    untracked.push(path);
+                }
             }
-            Err(_) => Ok(true),
         }
-    }
+        untracked.sort();
 
-    /// Check if repository is bare-alias.
-    pub(crate) fn is_bare_alias(&self) -> bool {
-        self.repository.is_bare() && self.deployment_kind.is_bare_alias()
+        Ok(untracked)
     }
 
-    /// Name of repository entry.
-    pub(crate) fn name(&self) -> &str {
-        &self.name
-    }
+    /// List untracked files near tracked files that are safe to clean.
+    ///
+    /// # Invariants
+    ///
+    /// - Files sitting directly in the home directory are never listed.
+    ///
+    /// # Errors
+    ///
+    /// - Will fail if untracked files cannot be listed.
+    /// - Will fail if home directory cannot be determined.
+    pub(crate) fn cleanable_untracked(&self) -> Result<Vec<PathBuf>> {
+        let untracked = self.untracked_near_tracked()?;
+        let Some(work_dir) = self.repository.workdir() else {
+            return Ok(Vec::new());
+        };
 
-    /// Absolute path to repository entry's gitdir.
-    pub(crate) fn path(&self) -> &Path {
-        self.repository.path()
+        let home = home_dir()?;
+        let untracked = untracked
+            .into_iter()
+            .filter(|path| work_dir.join(path).parent() != Some(home.as_path()))
+            .collect();
+
+        Ok(untracked)
     }
 
-    /// Get name of current branch pointed to by HEAD.
-    ///
-    /// Returns current branch in lossy UTF-8 form.
+    /// Remove untracked files near tracked files that are safe to clean.
     ///
     /// # Errors
     ///
-    /// - Will fail if HEAD connot be determined.
-    pub(crate) fn current_branch(&self) -> Result<String> {
-        let shorthand = self.repository.head()?.shorthand_bytes().to_vec();
-        Ok(String::from_utf8_lossy(shorthand.as_slice()).into_owned())
+    /// - Will fail if untracked files cannot be listed.
+    /// - Will fail if any untracked file cannot be removed.
+    pub(crate) fn clean_untracked(&self) -> Result<Vec<PathBuf>> {
+        let untracked = self.cleanable_untracked()?;
+        let Some(work_dir) = self.repository.workdir() else {
+            return Ok(untracked);
+        };
+
+        for path in &untracked {
+            let full = work_dir.join(path);
+            debug!("Remove untracked file {full:?}");
+            std::fs::remove_file(&full).with_context(|| format!("Failed to remove {full:?}"))?;
+        }
+
+        Ok(untracked)
     }
 
     /// Perform non-interactive call to user's Git binary.
@@ -635,14 +3487,14 @@ pub(crate) fn current_branch(&self) -> Result<String> {
     /// # Errors
     ///
     /// Will fail if call to Git binary fails, or Git binary was given invalid arguments.
-    #[instrument(skip(self, args), level = "debug")]
+    #[instrument(skip(self, args), fields(node = %self.name), level = "debug")]
     pub(crate) fn gitcall_non_interactive(
         &self,
         args: impl IntoIterator<Item = impl Into<OsString>>,
     ) -> Result<String> {
         let args = self.expand_bin_args(args);
         debug!("Run non interactive git with {args:?}");
-        syscall_non_interactive("git", args)
+        traced("git", &args, || syscall_non_interactive("git", &args))
     }
 
     /// Perform interactive call to user's Git binary.
@@ -653,7 +3505,7 @@ pub(crate) fn gitcall_non_interactive(
     /// # Errors
     ///
     /// Will fail if call to Git binary fails, or Git binary was given invalid arguments.
-    #[instrument(skip(self, args), level = "debug")]
+    #[instrument(skip(self, args), fields(node = %self.name), level = "debug")]
     pub(crate) fn gitcall_interactive(
         &self,
         args: impl IntoIterator<Item = impl Into<OsString>>,
@@ -661,7 +3513,7 @@ pub(crate) fn gitcall_interactive(
         info!("Interactive call to git for {:?}", self.name);
         let args = self.expand_bin_args(args);
         debug!("Run interactive git with {args:?}");
-        syscall_interactive("git", args)
+        traced("git", &args, || syscall_interactive("git", &args))
     }
 
     fn expand_bin_args(
@@ -670,8 +3522,14 @@ fn expand_bin_args(
     ) -> Vec<OsString> {
         let gitdir = self.repository.path().to_string_lossy().into_owned().into();
         let path_args: Vec<OsString> = match &self.deployment_kind {
-            DeploymentKind::Normal => vec!["--git-dir".into(), gitdir],
-            DeploymentKind::BareAlias => {
+            // INVARIANT: Normal repositories, external or not, are worked on in their own work
+            // tree, never in the current directory that Git falls back to without one.
+            DeploymentKind::Normal if !self.repository.is_bare() => {
+                let work_tree = self.repository.workdir().unwrap_or(self.repository.path());
+                vec!["--git-dir".into(), gitdir, "--work-tree".into(), work_tree.into()]
+            }
+            DeploymentKind::Normal | DeploymentKind::Mirror => vec!["--git-dir".into(), gitdir],
+            DeploymentKind::BareAlias | DeploymentKind::Inline | DeploymentKind::Cluster => {
                 vec![
                     "--git-dir".into(),
                     gitdir,
@@ -679,6 +3537,12 @@ fn expand_bin_args(
                     self.work_dir_alias.to_os_string(),
                 ]
             }
+            // INVARIANT: Entries placing files themselves operate on their internal checkout, never
+            // on the placed files.
+            DeploymentKind::HardLink | DeploymentKind::Symlink | DeploymentKind::Copy => {
+                let work_tree = self.repository.workdir().unwrap_or(self.repository.path());
+                vec!["--git-dir".into(), gitdir, "--work-tree".into(), work_tree.into()]
+            }
         };
 
         let mut bin_args: Vec<OsString> = Vec::new();
@@ -693,8 +3557,11 @@ impl std::fmt::Debug for RepoEntry {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "RepoEntry {{ name: {:?}, ", self.name)?;
         write!(f, "repository: (git2 stuff), ")?;
+        write!(f, "url: {:?}, ", self.url)?;
         write!(f, "deployment_kind: {:?} ", self.deployment_kind)?;
         write!(f, "work_dir_alias: {:?} ", self.work_dir_alias)?;
+        write!(f, "subdir: {:?} ", self.subdir)?;
+        write!(f, "pin: {:?} ", self.pin)?;
         writeln!(f, "authenticator: {:?} }}", self.authenticator)
     }
 }
@@ -704,9 +3571,15 @@ fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
 pub(crate) struct RepoEntryBuilder {
     name: String,
     path: PathBuf,
+    name_path: PathBuf,
+    store: PathBuf,
     url: String,
     deployment_kind: DeploymentKind,
     work_dir_alias: WorkDirAlias,
+    subdir: Option<PathBuf>,
+    initial_branch: Option<String>,
+    source: Option<NodeSource>,
+    pin: Option<NodePin>,
     authenticator: GitAuthenticator,
 }
 
@@ -714,32 +3587,92 @@ impl RepoEntryBuilder {
     /// Construct new builder.
     pub(crate) fn new(name: impl Into<String>) -> Result<Self> {
         let name = name.into();
-        let path = data_dir()?.join(&name);
+        let store = data_dir()?;
+        let path = store.join(&name);
         Ok(Self {
             name,
+            name_path: path.clone(),
+            store,
             path,
             url: String::default(),
             deployment_kind: DeploymentKind::BareAlias,
             work_dir_alias: WorkDirAlias::try_default()?,
+            subdir: None,
+            initial_branch: None,
+            source: None,
+            pin: None,
             authenticator: GitAuthenticator::default(),
         })
     }
 
     /// Set deployment settings for repository entry.
+    ///
+    /// Nested clusters keep their root at `<name>/root` in the repository store, right next to
+    /// their own nodes.
     pub(crate) fn deployment_kind(mut self, kind: DeploymentKind) -> Self {
+        if kind.is_cluster() && !self.deployment_kind.is_cluster() {
+            self.path = self.name_path.join("root");
+        }
         self.deployment_kind = kind;
         self
     }
 
+    /// Set source to populate repository entry from instead of cloning its URL.
+    pub(crate) fn source(mut self, source: Option<NodeSource>) -> Self {
+        self.source = source;
+        self
+    }
+
+    /// Set reference to clone and keep repository entry at.
+    pub(crate) fn pin(mut self, pin: Option<NodePin>) -> Self {
+        self.pin = pin;
+        self
+    }
+
     /// Set path to function as working directory alias.
     pub(crate) fn work_dir_alias(mut self, path: WorkDirAlias) -> Self {
         self.work_dir_alias = path;
         self
     }
 
+    /// Use existing repository outside the repository store for repository entry.
+    pub(crate) fn external(mut self, path: Option<PathBuf>) -> Self {
+        if let Some(path) = path {
+            self.path = path;
+        }
+        self
+    }
+
+    /// Use subdirectory of root repository as inline repository entry.
+    ///
+    /// # Errors
+    ///
+    /// Will fail if data directory cannot be determined.
+    pub(crate) fn inline(mut self, subdir: impl Into<PathBuf>) -> Result<Self> {
+        self.path = data_dir()?.join("root");
+        self.subdir = Some(subdir.into());
+        Ok(self)
+    }
+
+    /// Set name of initial branch for initialized repository entry.
+    ///
+    /// Falls back to `init.defaultBranch` of the user's Git configuration if unset.
+    pub(crate) fn initial_branch(mut self, branch: Option<String>) -> Self {
+        self.initial_branch = branch;
+        self
+    }
+
     /// Set URL to clone from for repository entry.
+    ///
+    /// Repository entry is kept wherever the store layout in use puts it, see
+    /// [`set_store_layout`].
     pub(crate) fn url(mut self, url: impl Into<String>) -> Self {
         self.url = url.into();
+
+        // INVARIANT: Inline, external, and nested cluster entries are never moved by layouts.
+        if self.path == self.name_path && !self.deployment_kind.is_cluster() {
+            self.path = layout_path(store_layout(), &self.store, &self.name, &self.url);
+        }
         self
     }
 
@@ -762,40 +3695,91 @@ pub(crate) fn authentication_prompter(
     ///
     /// Will fail if given invalid URL, invalid credentials, or any other reason that may cause the
     /// clone to fail.
+    #[instrument(skip(self, bar), fields(node = %self.name), level = "debug")]
     pub(crate) fn clone(self, bar: &ProgressBar) -> Result<RepoEntry> {
-        let style = ProgressStyle::with_template(
-            "{elapsed_precise:.green}  {msg:<50}  [{wide_bar:.yellow/blue}]",
-        )?
-        .progress_chars("-Cco.");
-        bar.set_style(style);
-        bar.set_message(format!("{} - {}", self.name, self.url));
-        bar.enable_steady_tick(std::time::Duration::from_millis(100));
-
-        let mut throttle = Instant::now();
+        if let Some(NodeSource::Archive { url, sha256 }) = self.source.clone() {
+            return self.unpack(&url, &sha256, bar);
+        }
+
+        // INVARIANT: Never hand encrypted URLs to Git.
+        if is_encrypted(&self.url) {
+            return Err(anyhow!(
+                "URL of {:?} is encrypted, but cannot be decrypted with an age identity",
+                self.name
+            ));
+        }
+
+        start_progress(bar, format!("{} - {}", self.name, self.url))?;
         let config = Config::open_default()?;
-        let mut rc = RemoteCallbacks::new();
-        rc.credentials(self.authenticator.credentials(&config));
-        rc.transfer_progress(|progress| {
-            let stats = progress.to_owned();
-            let bar_size = stats.total_objects() as u64;
-            let bar_pos = stats.received_objects() as u64;
-            if throttle.elapsed() > Duration::from_millis(50) {
-                throttle = Instant::now();
-                bar.set_length(bar_size);
-                bar.set_position(bar_pos);
-            }
-            true
-        });
+        let mut fo = progress_fetch_options(&self.authenticator, &config, bar);
+
+        // INVARIANT: Pinned tags are fetched even if no branch of remote reaches them.
+        if matches!(self.pin, Some(NodePin::Tag(_))) {
+            let _ = fo.download_tags(AutotagOption::All);
+        }
+
+        let repository = {
+            let mut builder = RepoBuilder::new();
+            let _ = builder.bare(self.is_bare()).fetch_options(fo);
+            if let Some(NodePin::Branch(branch)) = &self.pin {
+                let _ = builder.branch(branch);
+            }
+
+            // INVARIANT: Mirrors keep every reference of their remote as-is, like
+            // "git clone --mirror".
+            if self.deployment_kind.is_mirror() {
+                let _ = builder.remote_create(|repo, name, url| {
+                    let remote = repo.remote_with_fetch(name, url, MIRROR_REFSPEC)?;
+                    repo.config()?.set_bool(&format!("remote.{name}.mirror"), true)?;
+                    Ok(remote)
+                });
+            }
+            let result = traced(
+                "libgit2",
+                [OsStr::new("clone"), self.url.as_ref(), self.path.as_ref()],
+                || builder.clone(&self.url, &self.path),
+            );
+            match &self.pin {
+                Some(pin) => result
+                    .with_context(|| format!("Failed to clone {:?} at pinned {pin}", self.name))?,
+                None => result?,
+            }
+        };
 
-        let mut fo = FetchOptions::new();
-        fo.remote_callbacks(rc);
+        // INVARIANT: Never leave a repository entry behind that is not at its pinned reference.
+        if let Some(pin @ (NodePin::Tag(_) | NodePin::Rev(_))) = &self.pin {
+            let result = resolve_pin(&repository, pin)
+                .ok_or_else(|| anyhow!("Pinned {pin} of {:?} not found upstream", self.name))
+                .and_then(|target| {
+                    repository.set_head_detached(target)?;
+                    if !repository.is_bare() {
+                        repository.checkout_head(Some(CheckoutBuilder::new().force()))?;
+                    }
+                    Ok(())
+                });
+            if let Err(error) = result {
+                drop(repository);
+                let _ = std::fs::remove_dir_all(&self.path);
+                return Err(error);
+            }
+        }
 
-        let repository = RepoBuilder::new()
-            .bare(self.deployment_kind.is_bare_alias())
-            .fetch_options(fo)
-            .clone(&self.url, &self.path)?;
+        // INVARIANT: Mirrors have no remote-tracking references, with HEAD pointing to the
+        // default branch of their remote instead.
+        if self.deployment_kind.is_mirror() {
+            if let Ok(mut head) = repository.find_reference("refs/remotes/origin/HEAD") {
+                if let Some(target) = head.symbolic_target().map(ToString::to_string) {
+                    let branch = match target.strip_prefix("refs/remotes/origin/") {
+                        Some(branch) => format!("refs/heads/{branch}"),
+                        None => target,
+                    };
+                    repository.set_head(&branch)?;
+                }
+                head.delete()?;
+            }
+        }
 
-        if self.deployment_kind.is_bare_alias() {
+        if self.deployment_kind.is_bare_alias() || self.deployment_kind.is_cluster() {
             let mut config = repository.config()?;
             config.set_str("status.showUntrackedFiles", "no")?;
             config.set_str("core.sparseCheckout", "true")?;
@@ -804,23 +3788,84 @@ pub(crate) fn clone(self, bar: &ProgressBar) -> Result<RepoEntry> {
         Ok(RepoEntry {
             name: self.name,
             repository,
+            url: self.url,
             deployment_kind: self.deployment_kind,
             work_dir_alias: self.work_dir_alias,
+            subdir: self.subdir,
+            pin: self.pin,
             authenticator: self.authenticator,
         })
     }
 
+    /// Populate new repository entry from archive rather than cloning it.
+    ///
+    /// # Invariants
+    ///
+    /// - Never leave a half-populated repository entry behind.
+    ///
+    /// # Errors
+    ///
+    /// - Will fail if archive cannot be fetched, verified, or unpacked.
+    /// - Will fail if repository cannot be initialized, or populated.
+    fn unpack(self, url: &str, sha256: &str, bar: &ProgressBar) -> Result<RepoEntry> {
+        start_progress(bar, format!("{} - {url}", self.name))?;
+        let archive = UnpackedArchive::fetch(&self.name, url, sha256)?;
+
+        let path = self.path.clone();
+        let entry = self.init()?;
+        let result = archive.commit(&entry.repository, url, sha256).and_then(|_| {
+            if !entry.repository.is_bare() {
+                entry.repository.checkout_head(Some(CheckoutBuilder::new().force()))?;
+            }
+            Ok(())
+        });
+
+        if let Err(error) = result {
+            drop(entry);
+            let _ = std::fs::remove_dir_all(&path);
+            return Err(error);
+        }
+
+        Ok(entry)
+    }
+
+    /// Determine if repository entry already exists in repository store.
+    pub(crate) fn exists(&self) -> bool {
+        self.path.exists()
+    }
+
+    /// Path that repository entry is kept at.
+    pub(crate) fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn is_bare(&self) -> bool {
+        self.deployment_kind.is_bare_alias()
+            || self.deployment_kind.is_mirror()
+            || self.deployment_kind.is_cluster()
+    }
+
     /// Initialize new repository entry.
     ///
     /// # Errors
     ///
     /// Will fail if repository cannot be  initialized properly.
     pub(crate) fn init(self) -> Result<RepoEntry> {
+        // INVARIANT: Initial branch must match what the user expects to push later.
+        let initial_branch = match &self.initial_branch {
+            Some(branch) => Some(branch.clone()),
+            None => Config::open_default()?.get_string("init.defaultBranch").ok(),
+        };
+
         let mut opts = RepositoryInitOptions::new();
-        opts.bare(self.deployment_kind.is_bare_alias());
+        opts.bare(self.is_bare());
+        if let Some(branch) = &initial_branch {
+            debug!("Initialize {:?} on branch {branch:?}", self.name);
+            opts.initial_head(branch);
+        }
         let repository = Repository::init_opts(&self.path, &opts)?;
 
-        if self.deployment_kind.is_bare_alias() {
+        if self.deployment_kind.is_bare_alias() || self.deployment_kind.is_cluster() {
             let mut config = repository.config()?;
             config.set_str("status.showUntrackedFiles", "no")?;
             config.set_str("core.sparseCheckout", "true")?;
@@ -829,12 +3874,49 @@ pub(crate) fn init(self) -> Result<RepoEntry> {
         Ok(RepoEntry {
             name: self.name,
             repository,
+            url: self.url,
             deployment_kind: self.deployment_kind,
             work_dir_alias: self.work_dir_alias,
+            subdir: self.subdir,
+            pin: self.pin,
             authenticator: self.authenticator,
         })
     }
 
+    /// Initialize new repository entry tracking remote at URL, without cloning anything.
+    ///
+    /// Meant for brand-new entries whose remote has nothing to clone yet. Adds URL as origin
+    /// remote, and sets origin as upstream of the initial branch.
+    ///
+    /// # Errors
+    ///
+    /// - Will fail if repository cannot be initialized properly.
+    /// - Will fail if origin remote cannot be added, or tracked.
+    pub(crate) fn init_with_remote(self) -> Result<RepoEntry> {
+        let url = self.url.clone();
+        let entry = self.init()?;
+        let _ = entry.repository.remote("origin", &url)?;
+        entry.track_origin()?;
+
+        Ok(entry)
+    }
+
+    /// Determine if remote at URL has nothing to clone.
+    ///
+    /// # Errors
+    ///
+    /// Will fail if remote cannot be connected to.
+    pub(crate) fn is_remote_empty(&self) -> Result<bool> {
+        let config = Config::open_default()?;
+        let mut rc = RemoteCallbacks::new();
+        rc.credentials(self.authenticator.credentials(&config));
+
+        let mut remote = Remote::create_detached(self.url.as_str())?;
+        let connection = remote.connect_auth(Direction::Fetch, Some(rc), None)?;
+
+        Ok(connection.list()?.is_empty())
+    }
+
     /// Open existing repository entry.
     ///
     /// # Errors
@@ -846,13 +3928,58 @@ pub(crate) fn open(self) -> Result<RepoEntry> {
         Ok(RepoEntry {
             name: self.name,
             repository,
+            url: self.url,
             deployment_kind: self.deployment_kind,
             work_dir_alias: self.work_dir_alias,
+            subdir: self.subdir,
+            pin: self.pin,
             authenticator: self.authenticator,
         })
     }
 }
 
+/// Resolve commit that pinned reference points to in repository.
+///
+/// Pinned branches resolve through their remote-tracking branch of "origin".
+fn resolve_pin(repository: &Repository, pin: &NodePin) -> Option<Oid> {
+    let spec = match pin {
+        NodePin::Branch(branch) => format!("refs/remotes/origin/{branch}"),
+        NodePin::Tag(tag) => format!("refs/tags/{tag}"),
+        NodePin::Rev(rev) => rev.clone(),
+    };
+
+    repository
+        .revparse_single(&spec)
+        .and_then(|object| object.peel_to_commit())
+        .ok()
+        .map(|c| c.id())
+}
+
+fn progress_fetch_options<'a>(
+    authenticator: &'a GitAuthenticator,
+    config: &'a Config,
+    bar: &'a ProgressBar,
+) -> FetchOptions<'a> {
+    let mut throttle = Instant::now();
+    let mut rc = RemoteCallbacks::new();
+    rc.credentials(authenticator.credentials(config));
+    rc.transfer_progress(move |progress| {
+        let stats = progress.to_owned();
+        let bar_size = stats.total_objects() as u64;
+        let bar_pos = stats.received_objects() as u64;
+        if throttle.elapsed() > Duration::from_millis(50) {
+            throttle = Instant::now();
+            bar.set_length(bar_size);
+            bar.set_position(bar_pos);
+        }
+        true
+    });
+
+    let mut fo = FetchOptions::new();
+    fo.remote_callbacks(rc);
+    fo
+}
+
 /// Strategy for repository deployment.
 pub(crate) trait Deployment {
     fn deploy_action(
@@ -860,13 +3987,17 @@ fn deploy_action(
         entry: &RepoEntry,
         excluded: &SparseCheckout,
         action: DeployAction,
-    ) -> Result<()>;
+    ) -> Result<DeployOutcome>;
 }
 
 /// Handler for repository deployment strategies.
 #[derive(Debug)]
 pub(crate) struct RepoEntryDeployer {
     excluded: SparseCheckout,
+    collisions: CollisionPolicy,
+    secrets: Vec<String>,
+    templates: Vec<String>,
+    vars: BTreeMap<String, String>,
 }
 
 impl RepoEntryDeployer {
@@ -877,7 +4008,41 @@ pub(crate) fn new(entry: &RepoEntry) -> Self {
         let mut excluded = SparseCheckout::new();
         excluded.set_sparse_path(entry.path());
 
-        Self { excluded }
+        Self {
+            excluded,
+            collisions: CollisionPolicy::default(),
+            secrets: Vec::new(),
+            templates: Vec::new(),
+            vars: BTreeMap::new(),
+        }
+    }
+
+    /// Set how bare-alias deployment treats files it would take over.
+    pub(crate) fn set_collisions(&mut self, collisions: CollisionPolicy) {
+        self.collisions = collisions;
+    }
+
+    /// Bare-alias deployment strategy with collision policy of deployer.
+    pub(crate) fn bare_alias(&self) -> BareAliasDeployment {
+        BareAliasDeployment { collisions: self.collisions }
+    }
+
+    /// Add encrypted files that deployment decrypts, see [`reveal_secrets`].
+    pub(crate) fn add_secrets(&mut self, paths: impl IntoIterator<Item = impl Into<String>>) {
+        self.secrets.extend(paths.into_iter().map(Into::into));
+    }
+
+    /// Add sparsity rules matching templates that deployment renders, see [`render_templates`].
+    ///
+    /// Templates should also be excluded from deployment, such that they are never checked out
+    /// verbatim.
+    pub(crate) fn add_templates(&mut self, rules: impl IntoIterator<Item = impl Into<String>>) {
+        self.templates.extend(rules.into_iter().map(Into::into));
+    }
+
+    /// Set variables that templates are rendered with.
+    pub(crate) fn set_vars(&mut self, vars: BTreeMap<String, String>) {
+        self.vars = vars;
     }
 
     /// Add exclusion rules for deployment.
@@ -885,225 +4050,1168 @@ pub(crate) fn add_excluded(&mut self, rules: impl IntoIterator<Item = impl Into<
         self.excluded.add_exclusions(rules);
     }
 
+    /// Add subdirectories of inline nodes, which root never deploys itself.
+    pub(crate) fn add_inline_subdirs(
+        &mut self,
+        subdirs: impl IntoIterator<Item = impl Into<String>>,
+    ) {
+        self.excluded.add_inline_subdirs(subdirs);
+    }
+
+    /// Add inclusion rules for deployment, such that only files matching them get deployed.
+    pub(crate) fn add_included(&mut self, rules: impl IntoIterator<Item = impl Into<String>>) {
+        self.excluded.add_inclusions(rules);
+    }
+
     /// Deploy with given strategy.
     ///
+    /// # Invariants
+    ///
+    /// - Deployments of the same repository never interleave, even across OCD invocations.
+    ///   Inline nodes lock the repository of root, because they share its sparse checkout file.
+    /// - Decrypted copies of encrypted files and rendered templates are removed before their node
+    ///   gets undeployed, and are written once their node got deployed.
+    ///
     /// # Errors
     ///
-    /// Will fail if sparse-checkout fails with exclusion rules, or deployment strategy itself fails
-    /// for whatever reason.
+    /// Will fail if deployment lock cannot be acquired, sparse-checkout fails with exclusion
+    /// rules, encrypted files cannot be decrypted or shredded, templates cannot be rendered or
+    /// removed, or deployment strategy itself fails for whatever reason.
+    #[instrument(skip(self, deployer, entry), fields(node = %entry.name), level = "debug")]
     pub(crate) fn deploy_with(
         &self,
-        deployer: impl Deployment,
+        deployer: impl Deployment,
+        entry: &RepoEntry,
+        action: DeployAction,
+    ) -> Result<DeployOutcome> {
+        let _lock = RepoLock::acquire(entry.path())?;
+        let removed = match action {
+            DeployAction::Undeploy | DeployAction::UndeployExcludes => {
+                shred_secrets(entry, &self.secrets, &self.excluded, action)?
+                    + remove_rendered(entry, &self.templates, &self.excluded, action)?
+            }
+            DeployAction::Deploy | DeployAction::DeployAll => 0,
+        };
+
+        let outcome = deployer.deploy_action(entry, &self.excluded, action)?;
+        let written = match action {
+            DeployAction::Deploy | DeployAction::DeployAll => {
+                reveal_secrets(entry, &self.secrets, &self.excluded, action)?
+                    + render_templates(entry, &self.templates, &self.excluded, &self.vars, action)?
+            }
+            DeployAction::Undeploy | DeployAction::UndeployExcludes => 0,
+        };
+
+        if removed + written > 0 {
+            return Ok(DeployOutcome::Changed);
+        }
+        Ok(outcome)
+    }
+}
+
+/// Deployment strategy for root repository.
+///
+/// ## Rules
+///
+/// 1. Root must always be deployed.
+/// 2. Root cannot be undeployed.
+/// 3. Root is always bare-alias.
+/// 4. Excluded files can be either deployed or undeployed.
+///     1. Excluded files are not deployed by default.
+pub(crate) struct RootDeployment;
+
+impl Deployment for RootDeployment {
+    fn deploy_action(
+        &self,
+        entry: &RepoEntry,
+        excluded: &SparseCheckout,
+        action: DeployAction,
+    ) -> Result<DeployOutcome> {
+        if entry.is_empty()? {
+            warn!("Root repository is empty, nothing to deploy");
+            return Ok(DeployOutcome::Skipped);
+        }
+
+        if !entry.is_bare_alias() {
+            return Err(anyhow!(
+                "Root repository was somehow defined as normal when it should be bare-alias"
+            ));
+        }
+
+        let msg = match action {
+            DeployAction::Deploy => {
+                // INVARIANT: Root must own sparsity rules, because inline nodes depend on them.
+                if is_deployed(entry, excluded, DeployState::WithoutExcluded)? && excluded.exists()
+                {
+                    return Ok(DeployOutcome::AlreadyInDesiredState);
+                }
+
+                warn!("Root repository not deployed");
+                excluded.write_rules(ExcludeAction::ExcludeUnwanted)?;
+                "Deploy root, because it must always be deployed".to_string()
+            }
+            DeployAction::DeployAll => {
+                if is_deployed(entry, excluded, DeployState::WithExcluded)? {
+                    info!("Root repository is already deployed fully");
+                    return Ok(DeployOutcome::AlreadyInDesiredState);
+                }
+
+                excluded.write_rules(ExcludeAction::IncludeAll)?;
+                "Deploy all of root repository".to_string()
+            }
+            DeployAction::Undeploy => {
+                warn!("Root repository cannot be undeployed");
+                return Ok(DeployOutcome::Skipped);
+            }
+            DeployAction::UndeployExcludes => {
+                if !is_deployed(entry, excluded, DeployState::WithExcluded)? {
+                    info!("Root repository excluded files are already undeployed");
+                    return Ok(DeployOutcome::AlreadyInDesiredState);
+                }
+
+                excluded.write_rules(ExcludeAction::ExcludeUnwanted)?;
+                "Undeploy excluded files of root".to_string()
+            }
+        };
+
+        let output = entry.gitcall_non_interactive(["checkout"])?;
+        info!("{msg}\n{output}");
+
+        Ok(DeployOutcome::Changed)
+    }
+}
+
+/// Deployment strategy for normal repositories.
+///
+/// ## Rules
+///
+/// 1. Normal repositories cannot be deployed.
+/// 3. Make sure normal repository is actually defined to be normal.
+pub(crate) struct NormalDeployment;
+
+impl Deployment for NormalDeployment {
+    fn deploy_action(
+        &self,
+        entry: &RepoEntry,
+        _excluded: &SparseCheckout,
+        _action: DeployAction,
+    ) -> Result<DeployOutcome> {
+        if entry.is_bare_alias() {
+            return Err(anyhow!(
+                "Repository {:?} defined as normal, but is bare-alias",
+                entry.name
+            ));
+        }
+
+        info!("Repository {:?} is normal, no deployment needed", entry.name());
+
+        Ok(DeployOutcome::Skipped)
+    }
+}
+
+/// Deployment strategy for mirror repositories.
+///
+/// ## Rules
+///
+/// 1. Mirror repositories are never deployed, nor undeployed.
+/// 2. Make sure mirror repository is actually a bare repository.
+pub(crate) struct MirrorDeployment;
+
+impl Deployment for MirrorDeployment {
+    fn deploy_action(
+        &self,
+        entry: &RepoEntry,
+        _excluded: &SparseCheckout,
+        _action: DeployAction,
+    ) -> Result<DeployOutcome> {
+        if !entry.repository.is_bare() {
+            return Err(anyhow!("Repository {:?} defined as mirror, but is not bare", entry.name));
+        }
+
+        info!("Repository {:?} is a mirror, skipping deployment", entry.name());
+
+        Ok(DeployOutcome::Skipped)
+    }
+}
+
+/// Deployment strategy for nested cluster repositories.
+///
+/// ## Rules
+///
+/// 1. Nested clusters must always be deployed, because they contain their cluster definition.
+/// 2. Nested clusters cannot be undeployed, only removed.
+/// 3. Nested clusters are always deployed fully, their definition has nothing to exclude.
+/// 4. Make sure nested cluster repository is actually a bare repository.
+pub(crate) struct ClusterDeployment;
+
+impl Deployment for ClusterDeployment {
+    fn deploy_action(
+        &self,
+        entry: &RepoEntry,
+        excluded: &SparseCheckout,
+        action: DeployAction,
+    ) -> Result<DeployOutcome> {
+        if entry.is_empty()? {
+            warn!("Nested cluster {:?} is empty, nothing to deploy", entry.name());
+            return Ok(DeployOutcome::Skipped);
+        }
+
+        if !entry.repository.is_bare() {
+            return Err(anyhow!(
+                "Repository {:?} defined as nested cluster, but is not bare",
+                entry.name
+            ));
+        }
+
+        match action {
+            DeployAction::Deploy | DeployAction::DeployAll => {
+                if is_deployed(entry, excluded, DeployState::WithExcluded)? {
+                    return Ok(DeployOutcome::AlreadyInDesiredState);
+                }
+            }
+            DeployAction::Undeploy | DeployAction::UndeployExcludes => {
+                info!("Nested cluster {:?} cannot be undeployed, only removed", entry.name());
+                return Ok(DeployOutcome::Skipped);
+            }
+        }
+
+        std::fs::create_dir_all(&entry.work_dir_alias.0)?;
+        excluded.write_rules(ExcludeAction::IncludeAll)?;
+        let output = entry.gitcall_non_interactive(["checkout"])?;
+        info!("Deploy definition of nested cluster {:?}\n{output}", entry.name());
+
+        Ok(DeployOutcome::Changed)
+    }
+}
+
+/// Deployment strategy for bare-alias repositories.
+///
+/// ## Rules
+///
+/// 1. Bare-alias repositories can either be deployed or undeployed.
+///     1. Excluded files are not included unless specified with deployment by default.
+/// 2. Make sure bare-alias repository is actually defined to be bare-alias.
+/// 3. Skip deployment if bare-alias repository is already deployed.
+/// 4. Never take over files in working directory alias without collision policy saying so.
+///     1. Files whose content already matches the index are taken over as is.
+pub(crate) struct BareAliasDeployment {
+    collisions: CollisionPolicy,
+}
+
+impl Deployment for BareAliasDeployment {
+    fn deploy_action(
+        &self,
+        entry: &RepoEntry,
+        excluded: &SparseCheckout,
+        action: DeployAction,
+    ) -> Result<DeployOutcome> {
+        if entry.is_empty()? {
+            warn!("Repository {:?} is empty, nothing to deploy", entry.name());
+            return Ok(DeployOutcome::Skipped);
+        }
+
+        if !entry.is_bare_alias() {
+            return Err(anyhow!(
+                "Repository {:?} defined as bare-alias, but is normal",
+                entry.name
+            ));
+        }
+
+        let msg = match action {
+            DeployAction::Deploy => {
+                self.take_over(entry, excluded, DeployState::WithoutExcluded)?;
+                if is_deployed(entry, excluded, DeployState::WithoutExcluded)? {
+                    info!("Repository {:?} is already deployed", entry.name);
+                    return Ok(DeployOutcome::AlreadyInDesiredState);
+                }
+
+                excluded.write_rules(ExcludeAction::ExcludeUnwanted)?;
+                format!("Deploy {:?}", entry.name)
+            }
+            DeployAction::DeployAll => {
+                self.take_over(entry, excluded, DeployState::WithExcluded)?;
+                if is_deployed(entry, excluded, DeployState::WithExcluded)? {
+                    info!("Repository {:?} is already deployed fully", entry.name);
+                    return Ok(DeployOutcome::AlreadyInDesiredState);
+                }
+
+                excluded.write_rules(ExcludeAction::IncludeAll)?;
+                format!("Deploy all of {:?}", entry.name)
+            }
+            DeployAction::Undeploy => {
+                if !is_deployed(entry, excluded, DeployState::WithoutExcluded)? {
+                    info!("Repository {:?} is already undeployed fully", entry.name);
+                    return Ok(DeployOutcome::AlreadyInDesiredState);
+                }
+
+                excluded.write_rules(ExcludeAction::ExcludeAll)?;
+                format!("Undeploy {:?}", entry.name)
+            }
+            DeployAction::UndeployExcludes => {
+                if !is_deployed(entry, excluded, DeployState::WithExcluded)? {
+                    info!("Repository {:?} excluded files are already undeployed", entry.name);
+                    return Ok(DeployOutcome::AlreadyInDesiredState);
+                }
+
+                excluded.write_rules(ExcludeAction::ExcludeUnwanted)?;
+                format!("Undeploy excluded files of {:?}", entry.name)
+            }
+        };
+
+        let output = entry.gitcall_non_interactive(["checkout"])?;
+        info!("{msg}\n{output}");
+
+        Ok(DeployOutcome::Changed)
+    }
+}
+
+impl BareAliasDeployment {
+    // INVARIANT: Refuse to touch anything before every colliding file is dealt with.
+    fn take_over(
+        &self,
         entry: &RepoEntry,
-        action: DeployAction,
+        excluded: &SparseCheckout,
+        state: DeployState,
     ) -> Result<()> {
-        deployer.deploy_action(entry, &self.excluded, action)
+        let collisions = bare_alias_collisions(entry, excluded, state)?;
+        if collisions.is_empty() {
+            return Ok(());
+        }
+
+        match self.collisions {
+            CollisionPolicy::Abort => {
+                let report: String =
+                    collisions.iter().map(|path| format!("\n  {path:?}")).collect();
+                Err(anyhow!(
+                    "Repository {:?} would overwrite files it does not own, use --backup to move \
+                     them aside, or --force to overwrite them:{report}",
+                    entry.name
+                ))
+            }
+            CollisionPolicy::Backup => {
+                for path in collisions {
+                    let backup = backup_path(&path);
+                    std::fs::rename(&path, &backup)
+                        .with_context(|| format!("Failed to back up {path:?}"))?;
+                    info!("Back up {path:?} to {backup:?}");
+                }
+                Ok(())
+            }
+            CollisionPolicy::Force => {
+                for path in collisions {
+                    if path.symlink_metadata()?.is_dir() {
+                        remove_dir_all(&path)
+                    } else {
+                        std::fs::remove_file(&path)
+                    }
+                    .with_context(|| format!("Failed to overwrite {path:?}"))?;
+                    warn!("Overwrite {path:?}");
+                }
+                Ok(())
+            }
+        }
     }
 }
 
-/// Deployment strategy for root repository.
+/// Deployment strategy for inline nodes of root.
 ///
 /// ## Rules
 ///
-/// 1. Root must always be deployed.
-/// 2. Root cannot be undeployed.
-/// 3. Root is always bare-alias.
-/// 4. Excluded files can be either deployed or undeployed.
-///     1. Excluded files are not deployed by default.
-pub(crate) struct RootDeployment;
+/// 1. Inline nodes can either be deployed or undeployed.
+///     1. Excluded files are not included unless specified with deployment by default.
+/// 2. Make sure inline node is actually defined to be inline.
+/// 3. Only touch the sparsity rules of the inline node in root's sparse checkout file.
+/// 4. Skip deployment if inline node is already deployed.
+pub(crate) struct InlineDeployment;
 
-impl Deployment for RootDeployment {
+impl Deployment for InlineDeployment {
     fn deploy_action(
         &self,
         entry: &RepoEntry,
         excluded: &SparseCheckout,
         action: DeployAction,
-    ) -> Result<()> {
+    ) -> Result<DeployOutcome> {
         if entry.is_empty()? {
-            warn!("Root repository is empty, nothing to deploy");
-            return Ok(());
+            warn!("Root repository is empty, nothing to deploy for {:?}", entry.name());
+            return Ok(DeployOutcome::Skipped);
         }
 
-        if !entry.is_bare_alias() {
+        let subdir = match (&entry.subdir, entry.is_inline()) {
+            (Some(subdir), true) => subdir,
+            _ => return Err(anyhow!("Repository {:?} defined as inline, but is not", entry.name)),
+        };
+
+        if !excluded.exists() {
             return Err(anyhow!(
-                "Root repository was somehow defined as normal when it should be bare-alias"
+                "Root must be deployed before inline node {:?} can be deployed",
+                entry.name
             ));
         }
 
         let msg = match action {
             DeployAction::Deploy => {
                 if is_deployed(entry, excluded, DeployState::WithoutExcluded)? {
-                    return Ok(());
+                    info!("Inline node {:?} is already deployed", entry.name);
+                    return Ok(DeployOutcome::AlreadyInDesiredState);
                 }
 
-                warn!("Root repository not deployed");
-                excluded.write_rules(ExcludeAction::ExcludeUnwanted)?;
-                "Deploy root, because it must always be deployed".to_string()
+                excluded.write_inline_rules(&entry.name, subdir, ExcludeAction::ExcludeUnwanted)?;
+                format!("Deploy inline node {:?}", entry.name)
             }
             DeployAction::DeployAll => {
                 if is_deployed(entry, excluded, DeployState::WithExcluded)? {
-                    warn!("Root repository is already deployed fully");
-                    return Ok(());
+                    info!("Inline node {:?} is already deployed fully", entry.name);
+                    return Ok(DeployOutcome::AlreadyInDesiredState);
                 }
 
-                excluded.write_rules(ExcludeAction::IncludeAll)?;
-                "Deploy all of root repository".to_string()
+                excluded.write_inline_rules(&entry.name, subdir, ExcludeAction::IncludeAll)?;
+                format!("Deploy all of inline node {:?}", entry.name)
             }
             DeployAction::Undeploy => {
-                warn!("Root repository cannot be undeployed");
-                return Ok(());
+                if !is_deployed(entry, excluded, DeployState::WithoutExcluded)? {
+                    info!("Inline node {:?} is already undeployed fully", entry.name);
+                    return Ok(DeployOutcome::AlreadyInDesiredState);
+                }
+
+                excluded.write_inline_rules(&entry.name, subdir, ExcludeAction::ExcludeAll)?;
+                format!("Undeploy inline node {:?}", entry.name)
             }
             DeployAction::UndeployExcludes => {
                 if !is_deployed(entry, excluded, DeployState::WithExcluded)? {
-                    warn!("Root repository excluded files are undeployed");
-                    return Ok(());
+                    info!("Inline node {:?} excluded files are already undeployed", entry.name);
+                    return Ok(DeployOutcome::AlreadyInDesiredState);
                 }
 
-                excluded.write_rules(ExcludeAction::ExcludeUnwanted)?;
-                "Undeploy excluded files of root".to_string()
+                excluded.write_inline_rules(&entry.name, subdir, ExcludeAction::ExcludeUnwanted)?;
+                format!("Undeploy excluded files of inline node {:?}", entry.name)
             }
         };
 
         let output = entry.gitcall_non_interactive(["checkout"])?;
         info!("{msg}\n{output}");
 
-        Ok(())
+        Ok(DeployOutcome::Changed)
     }
 }
 
-/// Deployment strategy for normal repositories.
+/// Deployment strategy for hard-link repositories.
 ///
 /// ## Rules
 ///
-/// 1. Normal repositories cannot be deployed.
-/// 3. Make sure normal repository is actually defined to be normal.
-pub(crate) struct NormalDeployment;
-
-impl Deployment for NormalDeployment {
+/// 1. Hard-link repositories can either be deployed or undeployed.
+///     1. Excluded files are not included unless specified with deployment by default.
+/// 2. Make sure hard-link repository is actually defined to be hard-link.
+/// 3. Files of internal checkout are hard-linked into working directory alias.
+///     1. Fall back to copying files that cannot be hard-linked, e.g., across filesystems.
+/// 4. Every placed file is tracked in deployment manifest of repository.
+///     1. Never overwrite files that are not tracked by deployment manifest.
+///     2. Only remove tracked files that still match internal checkout.
+pub(crate) struct HardLinkDeployment;
+
+impl Deployment for HardLinkDeployment {
     fn deploy_action(
         &self,
         entry: &RepoEntry,
-        _excluded: &SparseCheckout,
-        _action: DeployAction,
-    ) -> Result<()> {
-        if entry.is_bare_alias() {
-            return Err(anyhow!(
-                "Repository {:?} defined as normal, but is bare-alias",
-                entry.name
-            ));
-        }
-
-        info!("Repository {:?} is normal, no deployment needed", entry.name());
-
-        Ok(())
+        excluded: &SparseCheckout,
+        action: DeployAction,
+    ) -> Result<DeployOutcome> {
+        deploy_placed_files(entry, excluded, action, LinkMode::HardLink)
     }
 }
 
-/// Deployment strategy for bare-alias repositories.
+/// Deployment strategy for symlink repositories.
 ///
 /// ## Rules
 ///
-/// 1. Bare-alias repositories can either be deployed or undeployed.
+/// 1. Symlink repositories can either be deployed or undeployed.
 ///     1. Excluded files are not included unless specified with deployment by default.
-/// 2. Make sure bare-alias repository is actually defined to be bare-alias.
-/// 3. Skip deployment if bare-alias repository is already deployed.
-pub(crate) struct BareAliasDeployment;
+/// 2. Make sure symlink repository is actually defined to be symlink.
+/// 3. Files of internal checkout are symlinked into working directory alias by absolute path.
+/// 4. Every placed symlink is tracked in deployment manifest of repository.
+///     1. Never overwrite files that are not tracked by deployment manifest.
+///     2. Only remove tracked symlinks that still point into internal checkout.
+pub(crate) struct SymlinkDeployment;
+
+impl Deployment for SymlinkDeployment {
+    fn deploy_action(
+        &self,
+        entry: &RepoEntry,
+        excluded: &SparseCheckout,
+        action: DeployAction,
+    ) -> Result<DeployOutcome> {
+        deploy_placed_files(entry, excluded, action, LinkMode::Symlink)
+    }
+}
 
-impl Deployment for BareAliasDeployment {
+/// Deployment strategy for copy repositories.
+///
+/// ## Rules
+///
+/// 1. Copy repositories can either be deployed or undeployed.
+///     1. Excluded files are not included unless specified with deployment by default.
+/// 2. Make sure copy repository is actually defined to be copy.
+/// 3. Files of internal checkout are copied into working directory alias.
+/// 4. Every placed copy is tracked in deployment manifest of repository, along with its digest.
+///     1. Never overwrite files that are not tracked by deployment manifest.
+///     2. Only remove tracked copies that were not edited since they were placed.
+pub(crate) struct CopyDeployment;
+
+impl Deployment for CopyDeployment {
     fn deploy_action(
         &self,
         entry: &RepoEntry,
         excluded: &SparseCheckout,
         action: DeployAction,
-    ) -> Result<()> {
-        if entry.is_empty()? {
-            warn!("Repository {:?} is empty, nothing to deploy", entry.name());
-            return Ok(());
-        }
+    ) -> Result<DeployOutcome> {
+        deploy_placed_files(entry, excluded, action, LinkMode::Copy)
+    }
+}
 
-        if !entry.is_bare_alias() {
+/// Place files of internal checkout into working directory alias the way link mode says.
+fn deploy_placed_files(
+    entry: &RepoEntry,
+    excluded: &SparseCheckout,
+    action: DeployAction,
+    link: LinkMode,
+) -> Result<DeployOutcome> {
+    if entry.is_empty()? {
+        warn!("Repository {:?} is empty, nothing to deploy", entry.name());
+        return Ok(DeployOutcome::Skipped);
+    }
+
+    let source = match (entry.repository.workdir(), entry.places_files()) {
+        (Some(workdir), true) => workdir,
+        _ => {
             return Err(anyhow!(
-                "Repository {:?} defined as bare-alias, but is normal",
-                entry.name
-            ));
+                "Repository {:?} defined as {}, but is bare",
+                entry.name,
+                entry.deployment_kind
+            ))
         }
+    };
+    let target = &entry.work_dir_alias.0;
 
-        let msg = match action {
-            DeployAction::Deploy => {
-                if is_deployed(entry, excluded, DeployState::WithoutExcluded)? {
-                    warn!("Repository {:?} is already deployed", entry.name);
-                    return Ok(());
-                }
+    let msg = match action {
+        DeployAction::Deploy => format!("Deploy {:?}", entry.name),
+        DeployAction::UndeployExcludes => {
+            format!("Undeploy excluded files of {:?}", entry.name)
+        }
+        DeployAction::DeployAll => format!("Deploy all of {:?}", entry.name),
+        DeployAction::Undeploy => format!("Undeploy {:?}", entry.name),
+    };
+    let wanted = hard_link_wanted(entry, excluded, action)?;
+
+    let mut manifest = DeployManifest::load(entry.name())?;
+
+    // INVARIANT: Refuse to touch anything before making sure nothing unowned gets overwritten.
+    let mut conflicts = Vec::new();
+    for path in &wanted {
+        let dest = target.join(path);
+        if dest.symlink_metadata().is_ok()
+            && manifest.get(path).is_none()
+            && !is_current(&source.join(path), &dest, link)?
+        {
+            conflicts.push(dest);
+        }
+    }
+    if !conflicts.is_empty() {
+        return Err(anyhow!(
+            "Repository {:?} would overwrite files it does not own: {conflicts:?}",
+            entry.name
+        ));
+    }
 
-                excluded.write_rules(ExcludeAction::ExcludeUnwanted)?;
-                format!("Deploy {:?}", entry.name)
+    let unwanted: Vec<(PathBuf, LinkMode)> = manifest
+        .iter()
+        .filter(|(path, _)| !wanted.iter().any(|want| want == path))
+        .map(|(path, mode)| (path.to_path_buf(), mode))
+        .collect();
+    let mut removed = 0;
+    for (path, mode) in unwanted {
+        let (src, dest) = (source.join(&path), target.join(&path));
+        // INVARIANT: Copies that were not edited since they were placed still belong to OCD, even
+        // if internal checkout moved on since.
+        let owned = is_current(&src, &dest, mode)?
+            || (mode == LinkMode::Copy
+                && dest.symlink_metadata().is_ok()
+                && !manifest.is_changed(&path, &dest)?);
+        if unlink_file(&dest, owned, target)? {
+            removed += 1;
+        }
+        manifest.remove(&path);
+    }
+
+    let mut placed = 0;
+    for path in wanted {
+        let (src, dest) = (source.join(&path), target.join(&path));
+        if let Some(mode) = manifest.get(&path) {
+            if is_current(&src, &dest, mode)? {
+                record_placed(&mut manifest, &path, &dest, mode)?;
+                continue;
             }
-            DeployAction::DeployAll => {
-                if is_deployed(entry, excluded, DeployState::WithExcluded)? {
-                    warn!("Repository {:?} is already deployed fully", entry.name);
-                    return Ok(());
-                }
+        } else if dest.symlink_metadata().is_ok() {
+            manifest.insert(&path, link);
+            record_placed(&mut manifest, &path, &dest, link)?;
+            continue;
+        }
+
+        let mode = link_file(&src, &dest, link)?;
+        manifest.insert(&path, mode);
+        record_placed(&mut manifest, &path, &dest, mode)?;
+        placed += 1;
+    }
+
+    manifest.save()?;
+
+    if placed == 0 && removed == 0 {
+        info!("Repository {:?} is already in requested deployment state", entry.name);
+        return Ok(DeployOutcome::AlreadyInDesiredState);
+    }
+
+    info!("{msg}\nPlaced {placed} file(s), removed {removed} file(s)");
+
+    Ok(DeployOutcome::Changed)
+}
+
+/// Remember how placed file looks right after it got placed, by digest for copies.
+fn record_placed(
+    manifest: &mut DeployManifest,
+    path: &Path,
+    dest: &Path,
+    mode: LinkMode,
+) -> Result<()> {
+    manifest.record_mtime(path, dest)?;
+    if mode == LinkMode::Copy {
+        manifest.record_hash(path, dest)?;
+    }
+
+    Ok(())
+}
+
+/// List files of repository placing files itself that deployment action wants placed.
+fn hard_link_wanted(
+    entry: &RepoEntry,
+    excluded: &SparseCheckout,
+    action: DeployAction,
+) -> Result<Vec<PathBuf>> {
+    let mut wanted: Vec<String> =
+        list_file_paths(entry)?.iter().map(|p| p.to_string_lossy().into_owned()).collect();
+    match action {
+        DeployAction::Deploy | DeployAction::UndeployExcludes => {
+            let rules = excluded.rules();
+            wanted.retain(|path| !rules.is_excluded(path));
+        }
+        DeployAction::DeployAll => {}
+        DeployAction::Undeploy => wanted.clear(),
+    }
+
+    Ok(wanted.into_iter().map(PathBuf::from).collect())
+}
+
+/// List placed files of repository placing files itself that deployment action would overwrite,
+/// even though they changed since they were last placed.
+///
+/// Files only get overwritten when they no longer match the internal checkout, e.g., because an
+/// editor replaced a link with a new file on save. Files that no longer match, but kept
+/// their modification time, are just stale after HEAD moved, and can be overwritten safely.
+fn hard_link_modified(
+    entry: &RepoEntry,
+    excluded: &SparseCheckout,
+    action: DeployAction,
+) -> Result<Vec<PathBuf>> {
+    let Some(source) = entry.repository.workdir() else {
+        return Ok(Vec::new());
+    };
+    let target = &entry.work_dir_alias.0;
+    let manifest = DeployManifest::load(entry.name())?;
+
+    let mut modified = Vec::new();
+    for path in hard_link_wanted(entry, excluded, action)? {
+        let (src, dest) = (source.join(&path), target.join(&path));
+        let Some(mode) = manifest.get(&path) else {
+            continue;
+        };
+
+        if dest.symlink_metadata().is_ok()
+            && !is_current(&src, &dest, mode)?
+            && manifest.is_changed(&path, &dest)?
+        {
+            modified.push(dest);
+        }
+    }
+
+    Ok(modified)
+}
+
+/// Link or copy file into place the way link mode says, falling back to copying files that
+/// cannot be hard-linked.
+fn link_file(src: &Path, dest: &Path, link: LinkMode) -> Result<LinkMode> {
+    if let Some(parent) = dest.parent() {
+        std::fs::create_dir_all(parent).with_context(|| format!("Failed to create {parent:?}"))?;
+    }
+
+    if dest.symlink_metadata().is_ok() {
+        std::fs::remove_file(dest).with_context(|| format!("Failed to remove stale {dest:?}"))?;
+    }
+
+    match link {
+        LinkMode::Symlink => {
+            symlink_file(src, dest)
+                .with_context(|| format!("Failed to symlink {src:?} to {dest:?}"))?;
+            trace!("Symlink {src:?} to {dest:?}");
+            return Ok(LinkMode::Symlink);
+        }
+        LinkMode::Copy => {
+            copy_file(src, dest)?;
+            trace!("Copy {src:?} to {dest:?}");
+            return Ok(LinkMode::Copy);
+        }
+        LinkMode::HardLink => {}
+    }
+
+    match std::fs::hard_link(src, dest) {
+        Ok(()) => {
+            trace!("Hard-link {src:?} to {dest:?}");
+            Ok(LinkMode::HardLink)
+        }
+        Err(error) => {
+            debug!("Cannot hard-link {src:?} to {dest:?}, copy instead: {error}");
+            copy_file(src, dest)?;
+            Ok(LinkMode::Copy)
+        }
+    }
+}
+
+#[cfg(unix)]
+fn symlink_file(src: &Path, dest: &Path) -> io::Result<()> {
+    std::os::unix::fs::symlink(src, dest)
+}
+#[cfg(windows)]
+fn symlink_file(src: &Path, dest: &Path) -> io::Result<()> {
+    std::os::windows::fs::symlink_file(src, dest)
+}
+
+#[cfg(unix)]
+fn copy_file(src: &Path, dest: &Path) -> Result<()> {
+    // INVARIANT: Symlinks tracked by Git are recreated as symlinks, not copies of their target.
+    if src.symlink_metadata()?.file_type().is_symlink() {
+        std::os::unix::fs::symlink(std::fs::read_link(src)?, dest)?;
+    } else {
+        std::fs::copy(src, dest).with_context(|| format!("Failed to copy {src:?} to {dest:?}"))?;
+    }
+
+    Ok(())
+}
+#[cfg(windows)]
+fn copy_file(src: &Path, dest: &Path) -> Result<()> {
+    std::fs::copy(src, dest).with_context(|| format!("Failed to copy {src:?} to {dest:?}"))?;
+    Ok(())
+}
+
+/// Remove placed file if it is still owned by OCD, along with empty parent directories up to the
+/// working directory alias.
+///
+/// Returns false if file was left alone.
+fn unlink_file(dest: &Path, owned: bool, root: &Path) -> Result<bool> {
+    if dest.symlink_metadata().is_err() {
+        return Ok(false);
+    }
+
+    if !owned {
+        warn!("Leave {dest:?} alone, because it no longer matches internal checkout");
+        return Ok(false);
+    }
+
+    std::fs::remove_file(dest).with_context(|| format!("Failed to remove {dest:?}"))?;
+    trace!("Remove {dest:?}");
+
+    let mut parent = dest.parent();
+    while let Some(dir) = parent {
+        if dir == root || std::fs::remove_dir(dir).is_err() {
+            break;
+        }
+        parent = dir.parent();
+    }
+
+    Ok(true)
+}
+
+/// Determine if placed file still matches internal checkout.
+fn is_current(src: &Path, dest: &Path, mode: LinkMode) -> Result<bool> {
+    if src.symlink_metadata().is_err() || dest.symlink_metadata().is_err() {
+        return Ok(false);
+    }
+
+    match mode {
+        LinkMode::HardLink => Ok(is_same_file(src, dest)?),
+        LinkMode::Symlink => Ok(std::fs::read_link(dest).is_ok_and(|target| target == src)),
+        LinkMode::Copy => {
+            let (src_meta, dest_meta) = (src.symlink_metadata()?, dest.symlink_metadata()?);
+            if src_meta.file_type().is_symlink() || dest_meta.file_type().is_symlink() {
+                return Ok(std::fs::read_link(src).ok() == std::fs::read_link(dest).ok());
+            }
+
+            Ok(std::fs::read(src)? == std::fs::read(dest)?)
+        }
+    }
+}
+
+#[cfg(unix)]
+fn is_same_file(src: &Path, dest: &Path) -> io::Result<bool> {
+    use std::os::unix::fs::MetadataExt;
+
+    match (src.symlink_metadata(), dest.symlink_metadata()) {
+        (Ok(src), Ok(dest)) => Ok(src.dev() == dest.dev() && src.ino() == dest.ino()),
+        (Err(error), _) | (_, Err(error)) if error.kind() != io::ErrorKind::NotFound => Err(error),
+        _ => Ok(false),
+    }
+}
+#[cfg(windows)]
+fn is_same_file(src: &Path, dest: &Path) -> io::Result<bool> {
+    match (std::fs::read(src), std::fs::read(dest)) {
+        (Ok(src), Ok(dest)) => Ok(src == dest),
+        _ => Ok(false),
+    }
+}
+
+// Outcome that deployment action would have on repository entry, going by the same deployment
+// state checks that deployment strategies use to skip work that is already done.
+fn plan_action(
+    entry: &RepoEntry,
+    excluded: &SparseCheckout,
+    action: DeployAction,
+) -> Result<DeployOutcome> {
+    let desired = match action {
+        DeployAction::Deploy => is_deployed(entry, excluded, DeployState::WithoutExcluded)?,
+        DeployAction::DeployAll => is_deployed(entry, excluded, DeployState::WithExcluded)?,
+        DeployAction::Undeploy => !is_deployed(entry, excluded, DeployState::WithoutExcluded)?,
+        DeployAction::UndeployExcludes => !is_deployed(entry, excluded, DeployState::WithExcluded)?,
+    };
+
+    Ok(if desired { DeployOutcome::AlreadyInDesiredState } else { DeployOutcome::Changed })
+}
+
+fn is_deployed(entry: &RepoEntry, excluded: &SparseCheckout, state: DeployState) -> Result<bool> {
+    Ok(scan_deployment(entry, excluded, state, false)?.state == DeploymentState::Deployed)
+}
+
+// INVARIANT: Nothing drifted from entries that were never deployed, and excluded files only count
+// once they got deployed along with the rest, i.e., once the sparse checkout of bare-alias entries
+// includes them, whatever else drifted since.
+fn drifted_files(entry: &RepoEntry, excluded: &SparseCheckout) -> Result<Vec<PathBuf>> {
+    let with_excluded = excluded.is_narrowed()
+        && if entry.deployment_kind.is_bare_alias() {
+            excluded.has_rules(ExcludeAction::IncludeAll)?
+        } else {
+            is_deployed(entry, excluded, DeployState::WithExcluded)?
+        };
+    let state =
+        if with_excluded { DeployState::WithExcluded } else { DeployState::WithoutExcluded };
+    let status = scan_deployment(entry, excluded, state, true)?;
+    if status.state == DeploymentState::Undeployed {
+        return Ok(Vec::new());
+    }
+
+    let mut drifted = status.missing;
+    drifted.extend(status.modified);
+    drifted.sort();
+
+    Ok(drifted)
+}
+
+// Compare tracked files of repository entry against its working directory alias, checking content
+// of each deployed file against HEAD if `check_modified` is set.
+fn scan_deployment(
+    entry: &RepoEntry,
+    excluded: &SparseCheckout,
+    state: DeployState,
+    check_modified: bool,
+) -> Result<DeploymentStatus> {
+    let undeployed = DeploymentStatus {
+        state: DeploymentState::Undeployed,
+        missing: Vec::new(),
+        modified: Vec::new(),
+    };
+    if entry.is_empty()? {
+        return Ok(undeployed);
+    }
+
+    let work_dir_alias = match &entry.deployment_kind {
+        DeploymentKind::Normal | DeploymentKind::Mirror => return Ok(undeployed),
+        DeploymentKind::BareAlias
+        | DeploymentKind::Inline
+        | DeploymentKind::HardLink
+        | DeploymentKind::Symlink
+        | DeploymentKind::Copy
+        | DeploymentKind::Cluster => &entry.work_dir_alias,
+    };
+
+    // INVARIANT: Inline entries only care about files in their subdirectory, with exclusion rules
+    // being relative to that subdirectory.
+    let prefix = entry.subdir.clone().unwrap_or_default();
+    let mut entries: Vec<(String, Oid)> = list_file_blobs(entry)?
+        .into_iter()
+        .filter_map(|(p, oid)| {
+            p.strip_prefix(&prefix).map(|p| (p.to_string_lossy().into_owned(), oid)).ok()
+        })
+        .collect();
+
+    let rules = excluded.rules_at(state);
+    entries.retain(|(path, _)| !rules.is_excluded(path));
+
+    // INVARIANT: Links placed by symlink entries stand for the files of their internal checkout.
+    let checkout = entry.repository.workdir().filter(|_| entry.deployment_kind.is_symlink());
+    let (mut missing, mut modified, mut present) = (Vec::new(), Vec::new(), 0);
+    for (entry, oid) in entries {
+        let mut path = work_dir_alias.0.join(&prefix).join(&entry);
+        if path.symlink_metadata().is_err() {
+            missing.push(prefix.join(entry));
+            continue;
+        }
+        if let Some(source) = checkout.map(|checkout| checkout.join(&entry)) {
+            if std::fs::read_link(&path).is_ok_and(|target| target == source) {
+                path = source;
+            }
+        }
+
+        present += 1;
+        if check_modified && hash_work_file(&path)? != oid {
+            modified.push(prefix.join(entry));
+        }
+    }
+
+    let state = match (missing.is_empty(), present) {
+        (true, _) => DeploymentState::Deployed,
+        (false, 0) => DeploymentState::Undeployed,
+        (false, _) => DeploymentState::PartiallyDeployed,
+    };
+
+    Ok(DeploymentStatus { state, missing, modified })
+}
+
+// INVARIANT: Paths that the index already checks out belong to the entry, even if modified.
+// Nothing belongs to entries that were never deployed, whatever their index says.
+fn bare_alias_collisions(
+    entry: &RepoEntry,
+    excluded: &SparseCheckout,
+    state: DeployState,
+) -> Result<Vec<PathBuf>> {
+    // INVARIANT: Index of repository is not cached, because deployment rewrites it through Git.
+    let index = Index::open(&entry.repository.path().join("index"))?;
+    let rules = excluded.rules_at(state);
+
+    let mut collisions = Vec::new();
+    for (path, oid) in list_file_blobs(entry)? {
+        if rules.is_excluded(path.to_string_lossy()) {
+            continue;
+        }
+
+        let owned = excluded.exists()
+            && index.get_path(&path, 0).is_some_and(|index_entry| {
+                !IndexEntryExtendedFlag::from_bits_truncate(index_entry.flags_extended)
+                    .is_skip_worktree()
+            });
+        let dest = entry.work_dir_alias.0.join(&path);
+        let Ok(metadata) = dest.symlink_metadata() else {
+            continue;
+        };
+        if !owned && (metadata.is_dir() || hash_work_file(&dest)? != oid) {
+            collisions.push(dest);
+        }
+    }
+
+    Ok(collisions)
+}
+
+// INVARIANT: Backups never overwrite earlier backups.
+fn backup_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_os_string();
+    name.push(".ocd-backup");
+    let mut backup = PathBuf::from(&name);
+    let mut count = 1;
+    while backup.symlink_metadata().is_ok() {
+        backup = PathBuf::from(format!("{}.{count}", name.to_string_lossy()));
+        count += 1;
+    }
+
+    backup
+}
+
+// Run deployment action of entry, recording files that it placed or removed into the open
+// transaction, if any.
+//
+// INVARIANT: Failed deployment actions are recorded too, because they may have changed files
+// before failing.
+// INVARIANT: Entries without exclusion rules are never deployed along with excluded files, such
+// that redeploying them changes nothing.
+fn transacted(
+    name: &str,
+    entry: &RepoEntry,
+    excluded: &SparseCheckout,
+    action: DeployAction,
+    deploy: impl FnOnce() -> Result<DeployOutcome>,
+) -> Result<DeployOutcome> {
+    if !is_transaction_open() {
+        return deploy();
+    }
+
+    let prior = deployed_files(entry).and_then(|before| {
+        let prior = if before.is_empty() {
+            PriorDeployment::Undeployed
+        } else if excluded.is_narrowed() && is_deployed(entry, excluded, DeployState::WithExcluded)?
+        {
+            PriorDeployment::DeployedAll
+        } else {
+            PriorDeployment::Deployed
+        };
+        Ok((prior, before))
+    });
+    let (prior, before) = match prior {
+        Ok(prior) => prior,
+        Err(error) => {
+            warn!("Failed to record deployment of {name:?} into transaction: {error:?}");
+            return deploy();
+        }
+    };
+
+    let outcome = deploy();
+    match deployed_files(entry) {
+        Ok(after) => record_step(TransactionStep::new(name, action, prior, &before, &after)),
+        Err(error) => warn!("Failed to record deployment of {name:?} into transaction: {error:?}"),
+    }
 
-                excluded.write_rules(ExcludeAction::IncludeAll)?;
-                format!("Deploy all of {:?}", entry.name)
-            }
-            DeployAction::Undeploy => {
-                if !is_deployed(entry, excluded, DeployState::WithoutExcluded)? {
-                    warn!("Repository {:?} is already undeployed fully", entry.name);
-                    return Ok(());
-                }
+    outcome
+}
 
-                excluded.write_rules(ExcludeAction::ExcludeAll)?;
-                format!("Undeploy {:?}", entry.name)
-            }
-            DeployAction::UndeployExcludes => {
-                if !is_deployed(entry, excluded, DeployState::WithExcluded)? {
-                    warn!("Repository {:?} excluded files are already undeployed", entry.name);
-                    return Ok(());
-                }
+/// Show last transaction recorded in state directory, if any.
+///
+/// # Errors
+///
+/// - Will fail if state directory cannot be determined.
+pub fn last_transaction() -> Result<Option<Transaction>> {
+    Ok(TransactionLog::load()?.last().cloned())
+}
 
-                excluded.write_rules(ExcludeAction::ExcludeUnwanted)?;
-                format!("Undeploy excluded files of {:?}", entry.name)
+/// Undo last transaction recorded in state directory.
+///
+/// Brings each entry that the transaction touched back to how it was deployed before, in reverse
+/// order, see [`TransactionStep::inverse`]. Nodes that the transaction removed from the
+/// repository store are cloned again. Returns the undone transaction, if any was recorded.
+///
+/// # Invariants
+///
+/// - Transaction is only forgotten once every entry was brought back, such that failed undos can
+///   be retried.
+/// - Undoing never records a transaction of its own.
+///
+/// # Errors
+///
+/// - Will fail if state directory cannot be determined.
+/// - Will fail if template variables of cluster cannot be determined.
+/// - Will fail if any entry is no longer defined, or cannot be deployed as it was before.
+///     - All failures are reported in one-shot once every entry has been processed.
+#[instrument(skip(cluster), level = "debug")]
+pub fn undo_transaction(cluster: &Cluster) -> Result<Option<Transaction>> {
+    let mut log = TransactionLog::load()?;
+    let Some(transaction) = log.last().cloned() else {
+        return Ok(None);
+    };
+
+    let vars = cluster.template_vars()?;
+    let mut results = Vec::new();
+    for step in transaction.steps.iter().rev() {
+        let action = step.inverse();
+        let result = if step.entry == "root" {
+            Root::new_open(&cluster.root).and_then(|root| root.deploy(action))
+        } else {
+            match cluster.nodes.get(&step.entry) {
+                Some(entry) => Node::new_open(&step.entry, entry).and_then(|mut node| {
+                    node.set_template_vars(vars.clone());
+                    node.deploy(action)
+                }),
+                None => Err(anyhow!("Node {:?} is no longer defined", step.entry)),
             }
         };
+        results.push(
+            result.map(|_| ()).with_context(|| format!("Failed to bring back {:?}", step.entry)),
+        );
+    }
+    results.into_iter().bcollect::<Vec<()>>()?;
 
-        let output = entry.gitcall_non_interactive(["checkout"])?;
-        info!("{msg}\n{output}");
+    log.pop();
+    log.save()?;
 
-        Ok(())
+    Ok(Some(transaction))
+}
+
+/// Carry node over to its new name in the repository store and OCD's state.
+///
+/// Moves the repository of node to wherever the layout in use keeps it under its new name, see
+/// [`migrate_store_layout`], and moves its pin in the lockfile, its deployment journal, its
+/// deployment artifacts, and its steps in the transaction log over to its new name. Files that
+/// node deployed stay where they are, such that it stays deployed as it was.
+///
+/// # Errors
+///
+/// - Will fail if data, state, or configuration directory cannot be determined.
+/// - Will fail if node repository cannot be moved.
+/// - Will fail if lockfile cannot be read or written.
+/// - Will fail if any state file cannot be written.
+#[instrument(skip(node), level = "debug")]
+pub fn rename_in_store(node: &NodeEntry, old: &str, new: &str) -> Result<()> {
+    move_node_repo(node, old, new)?;
+
+    let mut lock = ClusterLock::load()?;
+    if lock.rename(old, new) {
+        lock.save()?;
     }
+    let mut journal = DeployJournal::load()?;
+    journal.rename(old, new);
+    journal.save()?;
+    let mut artifacts = DeployArtifacts::load()?;
+    artifacts.rename(old, new);
+    artifacts.save()?;
+    let mut transactions = TransactionLog::load()?;
+    transactions.rename(old, new);
+    transactions.save()
 }
 
-fn is_deployed(entry: &RepoEntry, excluded: &SparseCheckout, state: DeployState) -> Result<bool> {
-    if entry.is_empty()? {
-        return Ok(false);
+// List files of entry present in its working directory alias, whether excluded or not.
+fn deployed_files(entry: &RepoEntry) -> Result<Vec<PathBuf>> {
+    if entry.is_empty()?
+        || matches!(entry.deployment_kind, DeploymentKind::Normal | DeploymentKind::Mirror)
+    {
+        return Ok(Vec::new());
     }
 
-    let work_dir_alias = match &entry.deployment_kind {
-        DeploymentKind::Normal => return Ok(false),
-        DeploymentKind::BareAlias => &entry.work_dir_alias,
-    };
+    let prefix = entry.subdir.clone().unwrap_or_default();
+    let mut files: Vec<PathBuf> = list_file_paths(entry)?
+        .into_iter()
+        .filter(|path| path.starts_with(&prefix))
+        .map(|path| entry.work_dir_alias.0.join(path))
+        .filter(|path| path.symlink_metadata().is_ok())
+        .collect();
+    files.sort();
 
-    let mut entries: Vec<String> =
-        list_file_paths(entry)?.into_iter().map(|p| p.to_string_lossy().into_owned()).collect();
+    Ok(files)
+}
 
-    if state == DeployState::WithoutExcluded {
-        let result = glob_match(excluded.iter(), entries.iter());
-        entries.retain(|x| !result.contains(x));
+// Hash deployed file the same way Git would hash it as a blob, without following symlinks.
+fn hash_work_file(path: &Path) -> Result<Oid> {
+    if path.symlink_metadata()?.is_symlink() {
+        let target = std::fs::read_link(path)?;
+        return Ok(Oid::hash_object(ObjectType::Blob, target.to_string_lossy().as_bytes())?);
     }
 
-    for entry in entries {
-        let path = work_dir_alias.0.join(entry);
-        if !path.exists() {
-            return Ok(false);
-        }
-    }
+    Ok(Oid::hash_file(ObjectType::Blob, path)?)
+}
 
-    Ok(true)
+fn list_file_paths(entry: &RepoEntry) -> Result<Vec<PathBuf>> {
+    Ok(list_file_blobs(entry)?.into_iter().map(|(path, _)| path).collect())
 }
 
 // Thank you Eric at https://www.hydrogen18.com/blog/list-all-files-git-repo-pygit2.html.
-fn list_file_paths(entry: &RepoEntry) -> Result<Vec<PathBuf>> {
+fn list_file_blobs(entry: &RepoEntry) -> Result<Vec<(PathBuf, Oid)>> {
     let mut entries = Vec::new();
     let commit = entry.repository.head()?.peel_to_commit()?;
     let tree = commit.tree()?;
@@ -1124,7 +5232,7 @@ fn list_file_paths(entry: &RepoEntry) -> Result<Vec<PathBuf>> {
                 // Insert filename with full path into path entry list...
                 Some(ObjectType::Blob) => {
                     let full_path = path.join(bytes_to_path(tree_entry.name_bytes()));
-                    entries.push(full_path);
+                    entries.push((full_path, tree_entry.id()));
                 }
                 _ => continue,
             }
@@ -1160,8 +5268,91 @@ pub enum DeployState {
     WithExcluded,
 }
 
+/// Deployment state of repository entry, with the files that explain it.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct DeploymentStatus {
+    /// Overall deployment state.
+    pub state: DeploymentState,
+
+    /// Tracked files missing from working directory alias.
+    pub missing: Vec<PathBuf>,
+
+    /// Deployed files whose content differs from HEAD.
+    pub modified: Vec<PathBuf>,
+}
+
+impl std::fmt::Display for DeploymentStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.state)?;
+        let mut reasons = Vec::new();
+        if !self.missing.is_empty() {
+            reasons.push(format!(
+                "{} missing ({})",
+                count_files(&self.missing),
+                preview(&self.missing)
+            ));
+        }
+        if !self.modified.is_empty() {
+            reasons.push(format!(
+                "{} modified ({})",
+                count_files(&self.modified),
+                preview(&self.modified)
+            ));
+        }
+
+        if !reasons.is_empty() {
+            write!(f, ": {}", reasons.join(", "))?;
+        }
+
+        Ok(())
+    }
+}
+
+fn count_files(files: &[PathBuf]) -> String {
+    match files.len() {
+        1 => "1 file".into(),
+        len => format!("{len} files"),
+    }
+}
+
+// Show the first few files only, such that listings stay readable.
+fn preview(files: &[PathBuf]) -> String {
+    const PREVIEW_LEN: usize = 3;
+    let mut shown: Vec<String> =
+        files.iter().take(PREVIEW_LEN).map(|file| file.display().to_string()).collect();
+    if files.len() > PREVIEW_LEN {
+        shown.push("...".into());
+    }
+
+    shown.join(", ")
+}
+
+/// Overall deployment state of repository entry.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum DeploymentState {
+    /// Every tracked file is deployed.
+    Deployed,
+
+    /// Some tracked files are deployed, but others are missing.
+    PartiallyDeployed,
+
+    /// No tracked file is deployed, or entry cannot be deployed at all.
+    Undeployed,
+}
+
+impl std::fmt::Display for DeploymentState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Deployed => write!(f, "deployed"),
+            Self::PartiallyDeployed => write!(f, "partially deployed"),
+            Self::Undeployed => write!(f, "undeployed"),
+        }
+    }
+}
+
 /// Variants of repository index deployment.
-#[derive(Default, Debug, PartialEq, Eq, Clone, Copy)]
+#[derive(Default, Debug, PartialEq, Eq, Clone, Copy, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
 pub enum DeployAction {
     /// Deploy to target worktree excluding unwanted files.
     #[default]
@@ -1177,6 +5368,105 @@ pub enum DeployAction {
     UndeployExcludes,
 }
 
+/// Outcome of deployment action on one entry.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum DeployOutcome {
+    /// Deployment of entry changed.
+    Changed,
+
+    /// Entry was already in requested deployment state, so nothing changed.
+    AlreadyInDesiredState,
+
+    /// Entry has no deployment to change, e.g., because it is normal or empty.
+    Skipped,
+
+    /// Deployment action failed.
+    Failed,
+}
+
+impl std::fmt::Display for DeployOutcome {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Changed => write!(f, "changed"),
+            Self::AlreadyInDesiredState => write!(f, "unchanged"),
+            Self::Skipped => write!(f, "skipped"),
+            Self::Failed => write!(f, "failed"),
+        }
+    }
+}
+
+/// How bare-alias deployment treats files in working directory alias that it would take over.
+///
+/// Files that exist at paths tracked by a bare-alias node, without being checked out by it, e.g.,
+/// a `.bashrc` that was already in `$HOME`, do not belong to the node. Git either overwrites them
+/// upon checkout, or quietly adopts them as local modifications of the node.
+#[derive(Default, Debug, PartialEq, Eq, Clone, Copy)]
+pub enum CollisionPolicy {
+    /// Refuse to deploy, listing every colliding file.
+    #[default]
+    Abort,
+
+    /// Move colliding files aside before deploying.
+    Backup,
+
+    /// Overwrite colliding files.
+    Force,
+}
+
+/// Outcome of pulling one entry from its upstream.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum PullOutcome {
+    /// Entry already had every commit of its upstream.
+    UpToDate,
+
+    /// Entry was fast-forwarded to its upstream.
+    FastForwarded,
+
+    /// Local commits of entry were rebased onto its upstream.
+    Rebased,
+
+    /// Upstream of entry was merged into it.
+    Merged,
+
+    /// Entry diverged from its upstream, and was left as it was.
+    Diverged,
+
+    /// Entry has no upstream to pull from.
+    NoUpstream,
+
+    /// HEAD of entry is detached from any branch, so there is nothing to pull into.
+    Detached,
+
+    /// Entry is a mirror, which only got fetched.
+    Fetched,
+
+    /// Entry was moved back to the reference it is pinned to.
+    Pinned,
+}
+
+impl PullOutcome {
+    /// Check if pull moved HEAD of entry.
+    pub fn is_updated(&self) -> bool {
+        matches!(self, Self::FastForwarded | Self::Rebased | Self::Merged | Self::Pinned)
+    }
+}
+
+impl std::fmt::Display for PullOutcome {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UpToDate => write!(f, "up to date"),
+            Self::FastForwarded => write!(f, "fast-forwarded"),
+            Self::Rebased => write!(f, "rebased"),
+            Self::Merged => write!(f, "merged"),
+            Self::Diverged => write!(f, "skipped, diverged from upstream"),
+            Self::NoUpstream => write!(f, "skipped, no upstream"),
+            Self::Detached => write!(f, "skipped, detached HEAD"),
+            Self::Fetched => write!(f, "fetched"),
+            Self::Pinned => write!(f, "moved to pinned reference"),
+        }
+    }
+}
+
 /// Manage authentication with progress bars.
 ///
 /// Can handle single and multi progress bars based on [`ProgressBarKind`]. For any prompt to the
@@ -1293,6 +5583,21 @@ pub(crate) enum ProgressBarKind {
 /// repositories. Thus, the performance penalty of non-cone mode is spread across multiple
 /// repositories that will hopefully reduce its impact.
 ///
+/// ## Inline Nodes
+///
+/// Inline nodes live in a subdirectory of the root repository, so they share root's sparse
+/// checkout file. Root always excludes the subdirectories of inline nodes, while each deployed
+/// inline node appends its own block of sparsity rules after root's rules. These blocks are
+/// delimited by marker comments, and are preserved whenever root rewrites its own rules.
+///
+/// ## Ownership
+///
+/// Every sparse checkout file that OCD writes starts with a marker header holding the SHA-256
+/// digest of the rules beneath it, e.g., `# managed by ocd (hash: 3f5a...)`. Files without marker
+/// header belong to the user, and are never overwritten unless forced to. Files whose rules no
+/// longer match their digest were edited by hand, and are said to have drifted. Files written by
+/// older versions of OCD are recognized by their shape, and are treated as managed by OCD.
+///
 /// ## See also
 ///
 /// - [git-sparse-checkout](https://git-scm.com/docs/git-sparse-checkout)
@@ -1300,6 +5605,9 @@ pub(crate) enum ProgressBarKind {
 pub(crate) struct SparseCheckout {
     sparse_path: PathBuf,
     exclusion_rules: Vec<String>,
+    inclusion_rules: Vec<String>,
+    inline_subdirs: Vec<String>,
+    force: bool,
 }
 
 impl SparseCheckout {
@@ -1313,6 +5621,46 @@ pub(crate) fn set_sparse_path(&mut self, gitdir: &Path) {
         self.sparse_path = gitdir.join("info/sparse-checkout");
     }
 
+    /// Determine if sparse checkout file exists.
+    pub(crate) fn exists(&self) -> bool {
+        self.sparse_path.exists()
+    }
+
+    /// Allow sparse checkout files that OCD did not write to be overwritten.
+    pub(crate) fn set_force(&mut self, force: bool) {
+        self.force = force;
+    }
+
+    /// Determine who owns sparse checkout file.
+    ///
+    /// # Errors
+    ///
+    /// - Will fail if sparse checkout file exists, but cannot be read.
+    pub(crate) fn state(&self) -> Result<SparseCheckoutState> {
+        if !self.sparse_path.exists() {
+            return Ok(SparseCheckoutState::Missing);
+        }
+
+        let content = std::fs::read_to_string(&self.sparse_path)
+            .with_context(|| "Failed to read sparse checkout file")?;
+        let Some((header, body)) =
+            content.split_once('\n').filter(|(header, _)| header.starts_with(MANAGED_HEADER))
+        else {
+            return Ok(if is_legacy_rules(&content) {
+                SparseCheckoutState::Managed
+            } else {
+                SparseCheckoutState::Foreign
+            });
+        };
+
+        let digest = header.trim_start_matches(MANAGED_HEADER).trim_end_matches(')');
+        Ok(if digest == sha256_hex(body.as_bytes()) {
+            SparseCheckoutState::Managed
+        } else {
+            SparseCheckoutState::Drifted
+        })
+    }
+
     /// Add list of sparsity rules to exclude files upon index checkout.
     pub(crate) fn add_exclusions(&mut self, rules: impl IntoIterator<Item = impl Into<String>>) {
         let mut vec = Vec::new();
@@ -1320,6 +5668,26 @@ pub(crate) fn add_exclusions(&mut self, rules: impl IntoIterator<Item = impl Int
         self.exclusion_rules = vec;
     }
 
+    /// Add list of sparsity rules that files must match to be checked out at all.
+    ///
+    /// Without inclusion rules, every file is included.
+    pub(crate) fn add_inclusions(&mut self, rules: impl IntoIterator<Item = impl Into<String>>) {
+        self.inclusion_rules = rules.into_iter().map(Into::into).collect();
+    }
+
+    /// Add list of inline node subdirectories that stay excluded, even when including every file.
+    pub(crate) fn add_inline_subdirs(
+        &mut self,
+        subdirs: impl IntoIterator<Item = impl Into<String>>,
+    ) {
+        self.inline_subdirs = subdirs.into_iter().map(Into::into).collect();
+    }
+
+    /// Determine if sparsity rules leave out any files at all.
+    pub(crate) fn is_narrowed(&self) -> bool {
+        !self.exclusion_rules.is_empty() || !self.inclusion_rules.is_empty()
+    }
+
     /// Write sparsity rules based on exclusion action.
     ///
     /// Will create sparse checkout file at expected path if it does not exist.
@@ -1329,33 +5697,239 @@ pub(crate) fn add_exclusions(&mut self, rules: impl IntoIterator<Item = impl Int
     /// - Will fail if sparse checkout file cannot be created when needed.
     /// - Will fail if sparsity rules cannot be written to sparse checkout file.
     pub(crate) fn write_rules(&self, action: ExcludeAction) -> Result<()> {
-        let rules: String = match action {
+        self.write_file(self.expected_rules(action)?)
+    }
+
+    /// Check if sparse checkout file holds the sparsity rules that exclusion action would write.
+    ///
+    /// Missing sparse checkout files hold no rules at all.
+    ///
+    /// # Errors
+    ///
+    /// - Will fail if sparse checkout file cannot be read.
+    pub(crate) fn has_rules(&self, action: ExcludeAction) -> Result<bool> {
+        Ok(self.read_rules()? == self.expected_rules(action)?)
+    }
+
+    fn expected_rules(&self, action: ExcludeAction) -> Result<String> {
+        let mut rules: String = match action {
+            // INVARIANT: Exclusion rules come after inclusion rules, such that they win over them,
+            // because the last matching sparsity rule decides.
             ExcludeAction::ExcludeUnwanted => {
-                let mut excluded = self.exclusion_rules.iter().fold(String::new(), |mut acc, u| {
-                    writeln!(&mut acc, "!{u}").unwrap();
-                    acc
-                });
-                excluded.insert_str(0, "/*\n");
-                excluded
+                let mut rules = if self.inclusion_rules.is_empty() {
+                    "/*\n".to_string()
+                } else {
+                    self.inclusion_rules.iter().fold(String::new(), |mut acc, u| {
+                        writeln!(&mut acc, "{u}").unwrap();
+                        acc
+                    })
+                };
+                for rule in &self.exclusion_rules {
+                    writeln!(&mut rules, "!{rule}").unwrap();
+                }
+                rules
+            }
+            // INVARIANT: Subdirectories of inline nodes are only checked out through their blocks.
+            ExcludeAction::IncludeAll => {
+                let mut rules = "/*\n".to_string();
+                for subdir in &self.inline_subdirs {
+                    writeln!(&mut rules, "!{subdir}").unwrap();
+                }
+                rules
             }
-            ExcludeAction::IncludeAll => "/*".into(),
             ExcludeAction::ExcludeAll => String::default(),
         };
 
+        // INVARIANT: Keep inline node rules unless the entire index is getting excluded.
+        if action != ExcludeAction::ExcludeAll {
+            for (_, block) in self.read_inline_blocks()? {
+                rules.push_str(&block);
+            }
+        }
+
+        Ok(rules)
+    }
+
+    /// Write sparsity rules of inline node based on exclusion action.
+    ///
+    /// Only replaces the block of rules owned by the inline node, leaving the rest of the sparse
+    /// checkout file alone. Inclusion and exclusion rules are treated as relative to the
+    /// subdirectory of the inline node.
+    ///
+    /// # Errors
+    ///
+    /// - Will fail if sparse checkout file cannot be read.
+    /// - Will fail if sparsity rules cannot be written to sparse checkout file.
+    pub(crate) fn write_inline_rules(
+        &self,
+        name: impl AsRef<str>,
+        subdir: impl AsRef<Path>,
+        action: ExcludeAction,
+    ) -> Result<()> {
+        let content = self.read_rules()?;
+        let begin = format!("{INLINE_BEGIN}{}", name.as_ref());
+        let mut rules = String::new();
+        let mut skip = false;
+        for line in content.lines() {
+            if line == begin {
+                skip = true;
+            } else if skip && line == INLINE_END {
+                skip = false;
+            } else if !skip {
+                writeln!(&mut rules, "{line}").unwrap();
+            }
+        }
+
+        let subdir = subdir.as_ref().to_string_lossy();
+        let subdir = subdir.trim_matches('/');
+        match action {
+            ExcludeAction::ExcludeUnwanted | ExcludeAction::IncludeAll => {
+                writeln!(&mut rules, "{begin}").unwrap();
+                if action == ExcludeAction::IncludeAll || self.inclusion_rules.is_empty() {
+                    writeln!(&mut rules, "/{subdir}/").unwrap();
+                } else {
+                    for rule in &self.inclusion_rules {
+                        writeln!(&mut rules, "{}", prefix_rule(subdir, rule)).unwrap();
+                    }
+                }
+                if action == ExcludeAction::ExcludeUnwanted {
+                    for rule in &self.exclusion_rules {
+                        writeln!(&mut rules, "!{}", prefix_rule(subdir, rule)).unwrap();
+                    }
+                }
+                writeln!(&mut rules, "{INLINE_END}").unwrap();
+            }
+            ExcludeAction::ExcludeAll => (),
+        }
+
+        self.write_file(rules)
+    }
+
+    fn read_inline_blocks(&self) -> Result<Vec<(String, String)>> {
+        let content = self.read_rules()?;
+        let mut blocks = Vec::new();
+        let mut current: Option<(String, String)> = None;
+        for line in content.lines() {
+            if let Some(name) = line.strip_prefix(INLINE_BEGIN) {
+                current = Some((name.to_string(), format!("{line}\n")));
+            } else if let Some((_, block)) = current.as_mut() {
+                writeln!(block, "{line}").unwrap();
+                if line == INLINE_END {
+                    blocks.push(current.take().unwrap());
+                }
+            }
+        }
+
+        Ok(blocks)
+    }
+
+    // INVARIANT: Marker header is never part of the rules themselves.
+    fn read_rules(&self) -> Result<String> {
+        if !self.sparse_path.exists() {
+            return Ok(String::default());
+        }
+
+        let content = std::fs::read_to_string(&self.sparse_path)
+            .with_context(|| "Failed to read sparse checkout file")?;
+        match content.split_once('\n') {
+            Some((header, body)) if header.starts_with(MANAGED_HEADER) => Ok(body.into()),
+            _ => Ok(content),
+        }
+    }
+
+    fn write_file(&self, rules: impl AsRef<str>) -> Result<()> {
+        match self.state()? {
+            SparseCheckoutState::Foreign if !self.force => {
+                return Err(anyhow!(
+                    "Sparse checkout file {:?} was not written by OCD, run \"ocd deploy --force\" \
+                     to overwrite it",
+                    self.sparse_path
+                ));
+            }
+            SparseCheckoutState::Foreign => {
+                warn!("Overwrite sparse checkout file {:?} not written by OCD", self.sparse_path)
+            }
+            SparseCheckoutState::Drifted => {
+                warn!("Overwrite sparse checkout file {:?} edited by hand", self.sparse_path)
+            }
+            SparseCheckoutState::Missing | SparseCheckoutState::Managed => {}
+        }
+
+        let rules = rules.as_ref();
         let mut file = File::create(&self.sparse_path)
             .with_context(|| "Failed to create sparse checkout file")?;
-        file.write_all(rules.as_bytes()).with_context(|| "Failed to write sparsity rules")?;
+        writeln!(file, "{MANAGED_HEADER}{})", sha256_hex(rules.as_bytes()))
+            .and_then(|_| file.write_all(rules.as_bytes()))
+            .with_context(|| "Failed to write sparsity rules")?;
 
         Ok(())
     }
 
-    /// Iterate through sparsity rules.
+    /// Compile inclusion and exclusion rules to match paths of index against.
     ///
-    /// Each pattern can be feed into [`glob_match`] if need be.
+    /// Invalid rules are skipped with a warning.
+    pub(crate) fn rules(&self) -> SparsityRules {
+        SparsityRules::new(self.exclusion_rules.iter()).including(self.inclusion_rules.iter())
+    }
+
+    /// Compile sparsity rules that leave out files at given deployment state.
     ///
-    /// [`glob_match`]: crate::glob_match
-    pub(crate) fn iter(&self) -> SparsityRuleIter<'_> {
-        SparsityRuleIter { exclusion_rules: &self.exclusion_rules, index: 0 }
+    /// Including excluded files still leaves out subdirectories of inline nodes.
+    pub(crate) fn rules_at(&self, state: DeployState) -> SparsityRules {
+        match state {
+            DeployState::WithoutExcluded => self.rules(),
+            DeployState::WithExcluded => SparsityRules::new(self.inline_subdirs.iter()),
+        }
+    }
+}
+
+const INLINE_BEGIN: &str = "# ocd inline node: ";
+const INLINE_END: &str = "# ocd inline node end";
+const MANAGED_HEADER: &str = "# managed by ocd (hash: ";
+
+/// Ownership of sparse checkout file.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum SparseCheckoutState {
+    /// Sparse checkout file does not exist yet.
+    Missing,
+
+    /// Sparse checkout file was written by OCD, and left as it is since.
+    Managed,
+
+    /// Sparse checkout file was written by OCD, but was edited by hand since.
+    Drifted,
+
+    /// Sparse checkout file was not written by OCD.
+    Foreign,
+}
+
+// INVARIANT: Older versions of OCD only ever wrote "/*" followed by negated rules, with blocks of
+// inline nodes appended, or nothing at all.
+fn is_legacy_rules(content: &str) -> bool {
+    let mut lines = Vec::new();
+    let mut inline = false;
+    for line in content.lines() {
+        if line.starts_with(INLINE_BEGIN) {
+            inline = true;
+        } else if inline {
+            inline = line != INLINE_END;
+        } else {
+            lines.push(line);
+        }
+    }
+
+    match lines.split_first() {
+        None => true,
+        Some((first, rest)) => *first == "/*" && rest.iter().all(|line| line.starts_with('!')),
+    }
+}
+
+// Anchor inclusion or exclusion rule of inline node to its subdirectory in root.
+fn prefix_rule(subdir: &str, rule: &str) -> String {
+    if rule.trim_end_matches('/').contains('/') {
+        format!("/{subdir}/{}", rule.trim_start_matches('/'))
+    } else {
+        format!("/{subdir}/**/{rule}")
     }
 }
 
@@ -1373,32 +5947,133 @@ pub(crate) enum ExcludeAction {
     ExcludeAll,
 }
 
-/// Iterator for sparsity rules.
+/// Compiled exclusion rules of sparse checkout.
+///
+/// Matches paths of the index the same way that the non-cone mode of Git's sparse checkout
+/// matches them against the sparsity rules that OCD writes, such that OCD agrees with Git on which
+/// files get deployed. Exclusion rules follow the pattern format of gitignore(5):
+///
+/// 1. Rules with a slash at their beginning or middle are anchored to the top of the index, while
+///    other rules match at any depth, e.g., `README*` matches both `README.md` and
+///    `docs/README.md`, but `/README*` and `docs/*.md` do not match nested files.
+/// 2. Rules with a trailing slash only match directories, e.g., `build/` matches `build/out.o`
+///    and `src/build/out.o`, but never a file named `build`.
+/// 3. Wildcards never match a slash, except for `**`, e.g., `**/notes.txt`, or `src/**`.
+/// 4. Files inside of a matched directory are matched as well.
+///
+/// Files that match none of the inclusion rules, if there are any, count as excluded too. Rules
+/// matching the file itself decide over rules matching its directories, and exclusion rules win
+/// over inclusion rules matching the same path, e.g., `*.md` included with `docs/` excluded still
+/// includes `docs/guide.md`, but `docs/` included with `*.md` excluded does not.
 ///
-/// Designed to make it easy to match glob data when walking through repository index.
+/// ## See also
+///
+/// - [gitignore](https://git-scm.com/docs/gitignore#_pattern_format)
 #[derive(Debug)]
-pub(crate) struct SparsityRuleIter<'rule> {
-    exclusion_rules: &'rule Vec<String>,
-    index: usize,
+pub(crate) struct SparsityRules {
+    rules: Vec<SparsityRule>,
+    inclusions: Vec<SparsityRule>,
+}
+
+impl SparsityRules {
+    /// Compile rules in the pattern format of gitignore(5).
+    ///
+    /// Invalid rules are skipped with a warning.
+    pub(crate) fn new(rules: impl IntoIterator<Item = impl AsRef<str>>) -> Self {
+        Self { rules: compile_rules(rules, "exclusion"), inclusions: Vec::new() }
+    }
+
+    /// Only include files matching given rules, on top of excluding files matching exclusion
+    /// rules.
+    ///
+    /// Invalid rules are skipped with a warning.
+    pub(crate) fn including(mut self, rules: impl IntoIterator<Item = impl AsRef<str>>) -> Self {
+        self.inclusions = compile_rules(rules, "inclusion");
+        self
+    }
+
+    /// Determine if file path of index is excluded.
+    ///
+    /// # Invariants
+    ///
+    /// - Directories are matched from the file itself up to the top of the index, because Git
+    ///   checks out everything inside of an excluded directory the same way.
+    pub(crate) fn is_excluded(&self, path: impl AsRef<str>) -> bool {
+        let mut current = Some(path.as_ref().trim_matches('/'));
+        let mut is_dir = false;
+        while let Some(path) = current.filter(|path| !path.is_empty()) {
+            if self.rules.iter().any(|rule| rule.matches(path, is_dir)) {
+                return true;
+            }
+            if self.inclusions.iter().any(|rule| rule.matches(path, is_dir)) {
+                return false;
+            }
+
+            current = path.rsplit_once('/').map(|(parent, _)| parent);
+            is_dir = true;
+        }
+
+        !self.inclusions.is_empty()
+    }
 }
 
-impl Iterator for SparsityRuleIter<'_> {
-    type Item = String;
+fn compile_rules(
+    rules: impl IntoIterator<Item = impl AsRef<str>>,
+    kind: &str,
+) -> Vec<SparsityRule> {
+    rules
+        .into_iter()
+        .filter_map(|rule| match SparsityRule::new(rule.as_ref()) {
+            Ok(rule) => rule,
+            Err(error) => {
+                warn!("Invalid {kind} rule {:?}: {error}", rule.as_ref());
+                None
+            }
+        })
+        .collect()
+}
+
+#[derive(Debug)]
+struct SparsityRule {
+    pattern: glob::Pattern,
+    anchored: bool,
+    dir_only: bool,
+}
 
-    fn next(&mut self) -> Option<Self::Item> {
-        if self.index >= self.exclusion_rules.len() {
-            return None;
+impl SparsityRule {
+    // INVARIANT: Blank rules and comments match nothing, like they do in gitignore(5).
+    fn new(rule: &str) -> Result<Option<Self>, glob::PatternError> {
+        let rule = rule.trim_end();
+        if rule.is_empty() || rule.starts_with('#') {
+            return Ok(None);
         }
 
-        let mut rule = self.exclusion_rules[self.index].clone();
-        self.index += 1;
+        let (rule, dir_only) = match rule.strip_suffix('/') {
+            Some(rule) => (rule, true),
+            None => (rule, false),
+        };
+        let anchored = rule.contains('/');
+        let pattern = glob::Pattern::new(rule.trim_start_matches('/'))?;
+
+        Ok(Some(Self { pattern, anchored, dir_only }))
+    }
 
-        // INVARIANT: Directory patterns match entire contents of directory itself through wildcard.
-        if rule.ends_with('/') {
-            rule.push('*');
+    fn matches(&self, path: &str, is_dir: bool) -> bool {
+        if self.dir_only && !is_dir {
+            return false;
         }
 
-        Some(rule)
+        let options = glob::MatchOptions {
+            case_sensitive: true,
+            require_literal_separator: true,
+            require_literal_leading_dot: false,
+        };
+        if self.anchored {
+            self.pattern.matches_with(path, options)
+        } else {
+            let name = path.rsplit_once('/').map_or(path, |(_, name)| name);
+            self.pattern.matches_with(name, options)
+        }
     }
 }
 
@@ -1406,7 +6081,11 @@ fn syscall_non_interactive(
     cmd: impl AsRef<OsStr>,
     args: impl IntoIterator<Item = impl AsRef<OsStr>>,
 ) -> Result<String> {
-    let output = Command::new(cmd.as_ref()).args(args).output()?;
+    let output = Command::new(cmd.as_ref())
+        .args(args)
+        .envs(home_env())
+        .output()
+        .map_err(|error| spawn_failure(cmd.as_ref(), error))?;
     let stdout = String::from_utf8_lossy(output.stdout.as_slice()).into_owned();
     let stderr = String::from_utf8_lossy(output.stderr.as_slice()).into_owned();
     let mut message = String::new();
@@ -1433,11 +6112,25 @@ fn syscall_non_interactive(
     Ok(message)
 }
 
+// INVARIANT: Missing Git binary is reported with installation guidance, not a raw "No such file".
+fn spawn_failure(cmd: &OsStr, error: io::Error) -> anyhow::Error {
+    if error.kind() == io::ErrorKind::NotFound && cmd == "git" {
+        return anyhow!(MISSING_GIT_BINARY);
+    }
+
+    anyhow::Error::from(error).context(format!("Failed to run {cmd:?}"))
+}
+
 fn syscall_interactive(
     cmd: impl AsRef<OsStr>,
     args: impl IntoIterator<Item = impl AsRef<OsStr>>,
 ) -> Result<()> {
-    let status = Command::new(cmd.as_ref()).args(args).spawn()?.wait()?;
+    let status = Command::new(cmd.as_ref())
+        .args(args)
+        .envs(home_env())
+        .spawn()
+        .map_err(|error| spawn_failure(cmd.as_ref(), error))?
+        .wait()?;
 
     if !status.success() {
         return Err(anyhow!("Command {:?} failed", cmd.as_ref()));
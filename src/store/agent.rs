@@ -0,0 +1,156 @@
+// SPDX-FileCopyrightText: 2025 Jason Pena <jasonpena@awkless.com>
+// SPDX-License-Identifier: MIT
+
+//! SSH agent scoped to OCD invocation.
+//!
+//! Git passthrough calls over many targets invoke the user's Git binary once per target, and each
+//! call may prompt for the passphrase of the same SSH key. An SSH agent that outlives a single Git
+//! call, but not the OCD invocation, allows passphrases to be entered once per run instead.
+//!
+//! An agent already reachable through `$SSH_AUTH_SOCK` is always reused as is. Otherwise, a new
+//! agent is started through the user's `ssh-agent` binary, and Git's SSH command is told to add
+//! keys to it upon first use. The new agent is killed once OCD is done with it, such that no
+//! passphrase outlives the run that entered it.
+
+use super::syscall_non_interactive;
+
+use anyhow::{anyhow, Result};
+use std::{
+    ffi::OsString,
+    path::Path,
+    process::{Command, Stdio},
+};
+use tracing::{debug, info, instrument, warn};
+
+const AUTH_SOCK: &str = "SSH_AUTH_SOCK";
+const AGENT_PID: &str = "SSH_AGENT_PID";
+const SSH_COMMAND: &str = "GIT_SSH_COMMAND";
+
+/// SSH agent available to Git calls for the rest of the OCD invocation.
+///
+/// # Invariants
+///
+/// - Agents started by OCD are killed once they go out of scope, restoring the environment of
+///   the OCD process to what it was beforehand.
+/// - Agents that OCD did not start are never killed.
+#[derive(Debug)]
+pub(crate) struct SshAgent {
+    started: bool,
+    saved: Vec<(&'static str, Option<OsString>)>,
+}
+
+impl SshAgent {
+    /// Reuse existing SSH agent, or start new one scoped to OCD invocation.
+    ///
+    /// # Errors
+    ///
+    /// - Will fail if `ssh-agent` cannot be run.
+    /// - Will fail if output of `ssh-agent` cannot be understood.
+    #[instrument(level = "debug")]
+    pub(crate) fn start() -> Result<Self> {
+        let saved = [AUTH_SOCK, AGENT_PID, SSH_COMMAND]
+            .into_iter()
+            .map(|key| (key, std::env::var_os(key)))
+            .collect();
+
+        if let Some(sock) = std::env::var_os(AUTH_SOCK).filter(|sock| Path::new(sock).exists()) {
+            debug!("Reuse SSH agent at {sock:?}");
+            if !has_identities() {
+                info!("SSH agent has no keys, run \"ssh-add\" to enter passphrases only once");
+            }
+            return Ok(Self { started: false, saved });
+        }
+
+        let output = syscall_non_interactive("ssh-agent", ["-s"])?;
+        let sock = agent_var(&output, AUTH_SOCK)?;
+        let pid = agent_var(&output, AGENT_PID)?;
+        debug!("Started SSH agent {pid} at {sock:?}");
+        std::env::set_var(AUTH_SOCK, sock);
+        std::env::set_var(AGENT_PID, pid);
+
+        // INVARIANT: Never override the SSH command that the user picked for Git.
+        if std::env::var_os(SSH_COMMAND).is_none() && !has_ssh_command() {
+            std::env::set_var(SSH_COMMAND, "ssh -o AddKeysToAgent=yes");
+        }
+
+        Ok(Self { started: true, saved })
+    }
+}
+
+impl Drop for SshAgent {
+    fn drop(&mut self) {
+        if self.started {
+            if let Err(error) = syscall_non_interactive("ssh-agent", ["-k"]) {
+                warn!("Failed to stop SSH agent: {error:?}");
+            }
+        }
+
+        for (key, value) in &self.saved {
+            match value {
+                Some(value) => std::env::set_var(key, value),
+                None => std::env::remove_var(key),
+            }
+        }
+    }
+}
+
+// Extract "KEY=value;" assignment from Bourne shell output of "ssh-agent -s".
+fn agent_var<'a>(output: &'a str, key: &str) -> Result<&'a str> {
+    let prefix = format!("{key}=");
+    output
+        .split([';', '\n', ' '])
+        .find_map(|part| part.strip_prefix(&prefix))
+        .filter(|value| !value.is_empty())
+        .ok_or(anyhow!("No {key} in output of ssh-agent:\n{output}"))
+}
+
+fn has_identities() -> bool {
+    Command::new("ssh-add")
+        .arg("-l")
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|status| status.success())
+}
+
+fn has_ssh_command() -> bool {
+    std::env::var_os("GIT_SSH").is_some()
+        || git2::Config::open_default()
+            .and_then(|config| config.get_string("core.sshCommand"))
+            .is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use pretty_assertions::assert_eq as pretty_assert_eq;
+    use sealed_test::prelude::*;
+
+    #[test]
+    fn smoke_agent_var() -> Result<()> {
+        let output = "stdout: SSH_AUTH_SOCK=/tmp/ssh-XXXX/agent.42; export SSH_AUTH_SOCK;\n\
+                      SSH_AGENT_PID=43; export SSH_AGENT_PID;\necho Agent pid 43;";
+        pretty_assert_eq!(agent_var(output, AUTH_SOCK)?, "/tmp/ssh-XXXX/agent.42");
+        pretty_assert_eq!(agent_var(output, AGENT_PID)?, "43");
+        assert!(agent_var("echo Agent pid 43;", AGENT_PID).is_err());
+        Ok(())
+    }
+
+    #[sealed_test]
+    fn smoke_ssh_agent_start() -> Result<()> {
+        std::env::remove_var(AUTH_SOCK);
+        std::env::remove_var(AGENT_PID);
+        let agent = SshAgent::start()?;
+        let sock = std::env::var_os(AUTH_SOCK).expect("agent socket is set");
+        assert!(Path::new(&sock).exists());
+
+        // Running agent is reused, and left alone once reuse goes out of scope.
+        drop(SshAgent::start()?);
+        pretty_assert_eq!(std::env::var_os(AUTH_SOCK), Some(sock));
+
+        drop(agent);
+        pretty_assert_eq!(std::env::var_os(AUTH_SOCK), None);
+        Ok(())
+    }
+}
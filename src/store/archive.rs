@@ -0,0 +1,217 @@
+// SPDX-FileCopyrightText: 2025 Jason Pena <jasonpena@awkless.com>
+// SPDX-License-Identifier: MIT
+
+//! Archive sources.
+//!
+//! Node entries can be populated from release archives instead of Git repositories. An archive is
+//! fetched into OCD's cache directory, verified against the SHA-256 digest of its node entry, and
+//! unpacked. The unpacked contents are then committed into a brand-new local repository, such that
+//! the rest of OCD can treat the node like any cloned node.
+//!
+//! Downloading and unpacking is left to the user's `curl` and `tar` binaries, the same way Git
+//! operations that libgit2 cannot handle are left to the user's Git binary.
+
+use super::syscall_non_interactive;
+use crate::model::{cache_dir, home_dir};
+
+use anyhow::{anyhow, Context, Result};
+use git2::{FileMode, Oid, Repository, Signature};
+use std::{
+    fs::{create_dir_all, read, read_dir, read_link, remove_dir_all},
+    path::{Path, PathBuf},
+};
+use tracing::{debug, info, instrument};
+
+/// Git configuration key recording the digest of the archive a repository was populated from.
+pub(crate) const DIGEST_KEY: &str = "ocd.archiveSha256";
+
+/// Archive that has been fetched, verified, and unpacked into OCD's cache directory.
+///
+/// # Invariants
+///
+/// - Unpacked contents are removed once archive goes out of scope.
+#[derive(Debug)]
+pub(crate) struct UnpackedArchive {
+    dir: PathBuf,
+    content: PathBuf,
+}
+
+impl UnpackedArchive {
+    /// Fetch, verify, and unpack archive for repository entry.
+    ///
+    /// Archives whose contents are wrapped in a single top-level directory, as is common for
+    /// release tarballs, are unwrapped.
+    ///
+    /// # Errors
+    ///
+    /// - Will fail if archive cannot be downloaded or read.
+    /// - Will fail if archive does not match expected SHA-256 digest.
+    /// - Will fail if archive cannot be unpacked.
+    #[instrument(level = "debug")]
+    pub(crate) fn fetch(name: &str, url: &str, sha256: &str) -> Result<Self> {
+        let dir = cache_dir()?.join("archives").join(name);
+        if dir.exists() {
+            remove_dir_all(&dir).with_context(|| format!("Failed to remove {dir:?}"))?;
+        }
+        let content = dir.join("content");
+        create_dir_all(&content).with_context(|| format!("Failed to create {content:?}"))?;
+        let archive = Self { dir, content };
+
+        let path = archive.download(url)?;
+        let digest = sha256_hex(&read(&path).with_context(|| format!("Failed to read {path:?}"))?);
+        if !digest.eq_ignore_ascii_case(sha256) {
+            return Err(anyhow!(
+                "Archive {url:?} of {name:?} has SHA-256 digest {digest}, but expected {sha256}"
+            ));
+        }
+
+        debug!("Unpack archive {path:?} into {:?}", archive.content);
+        let _ = syscall_non_interactive(
+            "tar",
+            [
+                "-xf".as_ref(),
+                path.as_os_str(),
+                "-C".as_ref(),
+                archive.content.as_os_str(),
+                "--no-same-owner".as_ref(),
+            ],
+        )?;
+
+        Ok(archive)
+    }
+
+    /// Commit unpacked contents as root commit of HEAD in repository.
+    ///
+    /// Records digest of archive in configuration of repository, such that later changes to the
+    /// archive source of its node entry can be detected.
+    ///
+    /// # Errors
+    ///
+    /// - Will fail if unpacked contents cannot be written into repository.
+    /// - Will fail if commit cannot be created.
+    #[instrument(skip(repository), level = "debug")]
+    pub(crate) fn commit(&self, repository: &Repository, url: &str, sha256: &str) -> Result<()> {
+        let root = self.root()?;
+        let tree = build_tree(repository, &root)?.ok_or(anyhow!("Archive {url:?} has no files"))?;
+        let tree = repository.find_tree(tree)?;
+
+        // INVARIANT: Committing must work even if the user never configured a Git identity.
+        let signature =
+            repository.signature().or_else(|_| Signature::now("ocd", "ocd@localhost"))?;
+        let message = format!("Unpack {url}\n\nSHA-256: {sha256}\n");
+        let _ = repository.commit(Some("HEAD"), &signature, &signature, &message, &tree, &[])?;
+        repository.config()?.set_str(DIGEST_KEY, &sha256.to_lowercase())?;
+        info!("Commit contents of archive {url:?}");
+
+        Ok(())
+    }
+
+    fn download(&self, url: &str) -> Result<PathBuf> {
+        if url.starts_with("https://") || url.starts_with("http://") {
+            let path = self.dir.join("archive");
+            debug!("Download archive {url:?} to {path:?}");
+            let _ = syscall_non_interactive(
+                "curl",
+                [
+                    "--fail".as_ref(),
+                    "--silent".as_ref(),
+                    "--show-error".as_ref(),
+                    "--location".as_ref(),
+                    "--output".as_ref(),
+                    path.as_os_str(),
+                    url.as_ref(),
+                ],
+            )
+            .with_context(|| format!("Failed to download archive {url:?}"))?;
+            return Ok(path);
+        }
+
+        let path = url.strip_prefix("file://").unwrap_or(url);
+        let home = || home_dir().ok().map(|home| home.to_string_lossy().into_owned());
+        Ok(PathBuf::from(shellexpand::tilde_with_context(path, home).into_owned()))
+    }
+
+    fn root(&self) -> Result<PathBuf> {
+        let entries: Vec<PathBuf> = read_dir(&self.content)?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<Result<_, _>>()?;
+
+        match entries.as_slice() {
+            [] => Err(anyhow!("Archive is empty")),
+            [single] if single.is_dir() && single.symlink_metadata()?.is_dir() => {
+                Ok(single.clone())
+            }
+            _ => Ok(self.content.clone()),
+        }
+    }
+}
+
+impl Drop for UnpackedArchive {
+    fn drop(&mut self) {
+        let _ = remove_dir_all(&self.dir);
+    }
+}
+
+fn build_tree(repository: &Repository, dir: &Path) -> Result<Option<Oid>> {
+    let mut builder = repository.treebuilder(None)?;
+    for entry in read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        let name = entry.file_name();
+        let file_type = entry.file_type()?;
+        if name == ".git" {
+            continue;
+        }
+
+        let (oid, mode) = if file_type.is_symlink() {
+            let target = read_link(&path)?;
+            (repository.blob(target.to_string_lossy().as_bytes())?, FileMode::Link)
+        } else if file_type.is_dir() {
+            // INVARIANT: Git cannot track empty directories.
+            match build_tree(repository, &path)? {
+                Some(oid) => (oid, FileMode::Tree),
+                None => continue,
+            }
+        } else {
+            (repository.blob_path(&path)?, blob_mode(&path)?)
+        };
+        let _ = builder.insert(name, oid, mode.into())?;
+    }
+
+    if builder.is_empty() {
+        return Ok(None);
+    }
+
+    Ok(Some(builder.write()?))
+}
+
+#[cfg(unix)]
+fn blob_mode(path: &Path) -> Result<FileMode> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let executable = path.metadata()?.permissions().mode() & 0o111 != 0;
+    Ok(if executable { FileMode::BlobExecutable } else { FileMode::Blob })
+}
+
+#[cfg(not(unix))]
+fn blob_mode(_: &Path) -> Result<FileMode> {
+    Ok(FileMode::Blob)
+}
+
+/// Render SHA-256 digest of data as lowercase hexadecimal.
+pub(crate) fn sha256_hex(data: &[u8]) -> String {
+    openssl::sha::sha256(data).iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use pretty_assertions::assert_eq as pretty_assert_eq;
+
+    #[test]
+    fn smoke_sha256_hex() {
+        let expect = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+        pretty_assert_eq!(sha256_hex(b""), expect);
+    }
+}
@@ -0,0 +1,237 @@
+// SPDX-FileCopyrightText: 2025 Jason Pena <jasonpena@awkless.com>
+// SPDX-License-Identifier: MIT
+
+//! Deployment artifacts.
+//!
+//! Files that a node deployed stay in its working directory alias until the node gets undeployed.
+//! Once a node is dropped from the cluster definition without being undeployed first, e.g.,
+//! because its configuration file got deleted, or because its undeployment failed, nothing knows
+//! about its files anymore. Thus, OCD remembers which files each node left in its working
+//! directory alias as of its last deployment, such that files of nodes that are no longer defined
+//! can be found and pruned later. Artifacts live in OCD's state directory.
+
+use super::layout::prune_empty_parents;
+
+use crate::model::{cluster::Cluster, state_dir};
+
+use anyhow::{Context, Result};
+use beau_collector::BeauCollector as _;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs::{create_dir_all, read_to_string, remove_file, write},
+    path::{Path, PathBuf},
+};
+use tracing::{debug, info, instrument};
+
+/// Files that nodes left in their working directory aliases as of their last deployment.
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub(crate) struct DeployArtifacts {
+    path: PathBuf,
+    file: ArtifactsFile,
+}
+
+impl DeployArtifacts {
+    /// Load deployment artifacts from state directory.
+    ///
+    /// # Errors
+    ///
+    /// - Will fail if state directory cannot be determined.
+    #[instrument(level = "debug")]
+    pub(crate) fn load() -> Result<Self> {
+        Ok(Self::load_from(state_dir()?.join("artifacts.toml")))
+    }
+
+    /// Load deployment artifacts from specific path.
+    ///
+    /// # Invariants
+    ///
+    /// - Missing or unreadable artifact files just start empty, because losing them only loses
+    ///   the ability to prune files of removed nodes.
+    pub(crate) fn load_from(path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        let file = read_to_string(&path)
+            .ok()
+            .and_then(|data| toml::de::from_str::<ArtifactsFile>(&data).ok())
+            .unwrap_or_else(|| {
+                debug!("No usable deployment artifacts at {path:?}, start with no artifacts");
+                ArtifactsFile::default()
+            });
+
+        Self { path, file }
+    }
+
+    /// Save deployment artifacts to their artifacts file.
+    ///
+    /// # Errors
+    ///
+    /// - Will fail if artifacts file or its parent directory cannot be written.
+    pub(crate) fn save(&self) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            create_dir_all(parent).with_context(|| format!("Failed to create {parent:?}"))?;
+        }
+        let data = toml::ser::to_string(&self.file)?;
+        write(&self.path, data).with_context(|| format!("Failed to write {:?}", self.path))?;
+
+        Ok(())
+    }
+
+    /// Record files that node left in its working directory alias, as absolute paths.
+    ///
+    /// # Invariants
+    ///
+    /// - Nodes that left no files behind are forgotten.
+    pub(crate) fn record(
+        &mut self,
+        name: impl Into<String>,
+        work_dir_alias: &Path,
+        files: &[PathBuf],
+    ) {
+        let name = name.into();
+        let files: Vec<PathBuf> = files
+            .iter()
+            .filter_map(|file| file.strip_prefix(work_dir_alias).ok())
+            .map(Path::to_path_buf)
+            .collect();
+        if files.is_empty() {
+            self.forget(&name);
+            return;
+        }
+
+        let artifacts = NodeArtifacts { work_dir_alias: work_dir_alias.to_path_buf(), files };
+        self.file.nodes.insert(name, artifacts);
+    }
+
+    /// Forget files of node.
+    pub(crate) fn forget(&mut self, name: impl AsRef<str>) {
+        let _ = self.file.nodes.remove(name.as_ref());
+    }
+
+    /// Move files of node over to its new name.
+    pub(crate) fn rename(&mut self, old: impl AsRef<str>, new: impl Into<String>) {
+        if let Some(artifacts) = self.file.nodes.remove(old.as_ref()) {
+            self.file.nodes.insert(new.into(), artifacts);
+        }
+    }
+
+    // INVARIANT: Files still claimed by nodes that are defined never count as left behind.
+    fn stale(&self, cluster: &Cluster) -> BTreeMap<String, Vec<PathBuf>> {
+        let (live, removed): (Vec<_>, Vec<_>) =
+            self.file.nodes.iter().partition(|(name, _)| cluster.nodes.contains_key(*name));
+        let claimed: BTreeSet<PathBuf> =
+            live.iter().flat_map(|(_, artifacts)| artifacts.paths()).collect();
+
+        removed
+            .into_iter()
+            .map(|(name, artifacts)| {
+                let files: Vec<PathBuf> = artifacts
+                    .paths()
+                    .filter(|path| !claimed.contains(path) && path.symlink_metadata().is_ok())
+                    .collect();
+                (name.clone(), files)
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Default, PartialEq, Eq, Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+struct ArtifactsFile {
+    #[serde(default)]
+    nodes: BTreeMap<String, NodeArtifacts>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+struct NodeArtifacts {
+    work_dir_alias: PathBuf,
+    files: Vec<PathBuf>,
+}
+
+impl NodeArtifacts {
+    fn paths(&self) -> impl Iterator<Item = PathBuf> + '_ {
+        self.files.iter().map(|file| self.work_dir_alias.join(file))
+    }
+}
+
+/// List files left behind by nodes that are no longer defined in cluster, by node name.
+///
+/// Only files that still exist are listed. Removed nodes whose files are all gone are listed with
+/// no files, such that pruning forgets them.
+///
+/// # Errors
+///
+/// - Will fail if state directory cannot be determined.
+pub fn stale_artifacts(cluster: &Cluster) -> Result<BTreeMap<String, Vec<PathBuf>>> {
+    Ok(DeployArtifacts::load()?.stale(cluster))
+}
+
+/// Remove files left behind by nodes that are no longer defined in cluster.
+///
+/// Directories left empty by removed files are removed as well, up to the working directory alias
+/// of their node. Nodes whose files were all removed are forgotten. Returns the number of files
+/// that were removed.
+///
+/// # Errors
+///
+/// - Will fail with a diagnostic for each file that cannot be removed.
+/// - Will fail if deployment artifacts cannot be saved.
+#[instrument(skip(cluster), level = "debug")]
+pub fn prune_artifacts(cluster: &Cluster) -> Result<usize> {
+    let mut artifacts = DeployArtifacts::load()?;
+    let mut removed = 0;
+    let mut errors = Vec::new();
+    for (name, files) in artifacts.stale(cluster) {
+        let alias = artifacts.file.nodes[&name].work_dir_alias.clone();
+        let mut pruned = true;
+        for file in files {
+            match remove_file(&file) {
+                Ok(()) => {
+                    info!("Prune {file:?} left behind by {name:?}");
+                    prune_empty_parents(&file, &alias);
+                    removed += 1;
+                }
+                Err(error) => {
+                    errors.push(Err(anyhow::Error::from(error)
+                        .context(format!("Failed to prune {file:?} left behind by {name:?}"))));
+                    pruned = false;
+                }
+            }
+        }
+
+        if pruned {
+            artifacts.forget(&name);
+        }
+    }
+    artifacts.save()?;
+    errors.into_iter().bcollect::<Vec<()>>()?;
+
+    Ok(removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use pretty_assertions::assert_eq as pretty_assert_eq;
+    use sealed_test::prelude::*;
+
+    #[sealed_test]
+    fn smoke_deploy_artifacts_round_trip() -> Result<()> {
+        let path = std::env::current_dir()?.join("artifacts.toml");
+        let alias = Path::new("/home/user");
+        let mut artifacts = DeployArtifacts::load_from(&path);
+        artifacts.record("vim", alias, &[alias.join(".vimrc"), PathBuf::from("/elsewhere")]);
+        artifacts.record("sh", alias, &[]);
+        artifacts.save()?;
+
+        let expect = "[nodes.vim]\nwork_dir_alias = \"/home/user\"\nfiles = [\".vimrc\"]\n";
+        pretty_assert_eq!(read_to_string(&path)?, expect);
+        pretty_assert_eq!(DeployArtifacts::load_from(&path), artifacts);
+
+        artifacts.record("vim", alias, &[]);
+        pretty_assert_eq!(artifacts.file, ArtifactsFile::default());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,339 @@
+// SPDX-FileCopyrightText: 2025 Jason Pena <jasonpena@awkless.com>
+// SPDX-License-Identifier: MIT
+
+//! Sync daemon state.
+//!
+//! Servers keep their cluster in shape through `ocd sync --daemon`, which stays resident, and
+//! reconciles the repository store with the cluster definition on a timer, as well as whenever
+//! files in the configuration directory change. Nobody reads the output of a daemon, so it
+//! records what it last did in OCD's state directory, which `ocd status --sync` reads back.
+//! Whether a daemon still runs is told by its lock rather than by its status file, such that a
+//! daemon that got killed never looks alive.
+
+use super::{lock::DaemonLock, status::unix_now};
+
+use crate::model::state_dir;
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt::Display,
+    fs::{create_dir_all, read_to_string, write},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+use tracing::debug;
+
+/// Name of status file of sync daemon inside state directory.
+const SYNC_STATUS: &str = "sync.toml";
+
+/// Name of lock file of sync daemon inside state directory.
+const SYNC_LOCK: &str = "sync.lock";
+
+/// Amount of quiet time that ends a burst of changes to the configuration directory.
+///
+/// Editors and Git tend to write several files in a row, which should only cause one reconcile.
+const DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// What made sync daemon reconcile the cluster.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncTrigger {
+    /// Daemon just started.
+    Start,
+
+    /// Reconcile interval elapsed.
+    Timer,
+
+    /// Files in the configuration directory changed.
+    Config,
+}
+
+impl Display for SyncTrigger {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Start => write!(f, "start"),
+            Self::Timer => write!(f, "timer"),
+            Self::Config => write!(f, "config"),
+        }
+    }
+}
+
+/// Status of sync daemon, as recorded in OCD's state directory.
+#[derive(Debug, PartialEq, Eq, Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct SyncStatus {
+    /// Process ID of daemon.
+    pub pid: u32,
+
+    /// Unix timestamp of daemon start.
+    pub started: u64,
+
+    /// Seconds between reconciles.
+    pub interval: u64,
+
+    /// Last reconcile of daemon, if any.
+    pub last: Option<SyncReconcile>,
+}
+
+/// Reconcile of cluster done by sync daemon.
+#[derive(Debug, PartialEq, Eq, Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct SyncReconcile {
+    /// Unix timestamp of reconcile.
+    pub at: u64,
+
+    /// What caused reconcile.
+    pub trigger: SyncTrigger,
+
+    /// Error that reconcile failed with, if any.
+    pub error: Option<String>,
+}
+
+impl SyncReconcile {
+    /// Number of seconds since reconcile.
+    pub fn seconds_ago(&self) -> u64 {
+        unix_now().saturating_sub(self.at)
+    }
+}
+
+impl SyncStatus {
+    /// Construct status of sync daemon running in current process.
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            pid: std::process::id(),
+            started: unix_now(),
+            interval: interval.as_secs(),
+            last: None,
+        }
+    }
+
+    /// Load status of last sync daemon from state directory.
+    ///
+    /// Returns nothing if no daemon ever recorded its status, or if its status file is unusable.
+    ///
+    /// # Errors
+    ///
+    /// - Will fail if state directory cannot be determined.
+    pub fn load() -> Result<Option<Self>> {
+        let path = state_dir()?.join(SYNC_STATUS);
+        let status =
+            read_to_string(&path).ok().and_then(|data| toml::de::from_str::<Self>(&data).ok());
+        if status.is_none() {
+            debug!("No usable sync daemon status at {path:?}");
+        }
+
+        Ok(status)
+    }
+
+    /// Record outcome of reconcile.
+    pub fn record(&mut self, trigger: SyncTrigger, result: &Result<()>) {
+        let error = result.as_ref().err().map(|error| format!("{error:#}"));
+        self.last = Some(SyncReconcile { at: unix_now(), trigger, error });
+    }
+
+    /// Save status to state directory.
+    ///
+    /// # Errors
+    ///
+    /// - Will fail if state directory cannot be determined.
+    /// - Will fail if status file or its parent directory cannot be written.
+    pub fn save(&self) -> Result<()> {
+        let path = state_dir()?.join(SYNC_STATUS);
+        if let Some(parent) = path.parent() {
+            create_dir_all(parent).with_context(|| format!("Failed to create {parent:?}"))?;
+        }
+        let data = toml::ser::to_string(self)?;
+        write(&path, data).with_context(|| format!("Failed to write {path:?}"))?;
+
+        Ok(())
+    }
+}
+
+/// Lock sync daemon of cluster for the lifetime of the returned lock.
+///
+/// Returns nothing if another sync daemon holds the lock already.
+///
+/// # Errors
+///
+/// - Will fail if state directory cannot be determined.
+/// - Will fail if lock file cannot be created.
+pub(crate) fn lock_sync_daemon() -> Result<Option<DaemonLock>> {
+    DaemonLock::try_acquire(state_dir()?.join(SYNC_LOCK))
+}
+
+/// Check if a sync daemon is running.
+///
+/// # Errors
+///
+/// - Will fail if state directory cannot be determined.
+pub fn is_sync_daemon_running() -> Result<bool> {
+    Ok(DaemonLock::is_held(&state_dir()?.join(SYNC_LOCK)))
+}
+
+/// Watcher of changes to files in a directory and its subdirectories.
+///
+/// Uses inotify(7) on Linux. Other platforms have no watcher, such that changes are only picked up
+/// once the timeout of [`ConfigWatcher::wait`] elapses.
+#[derive(Debug)]
+pub struct ConfigWatcher {
+    #[cfg(target_os = "linux")]
+    fd: std::os::fd::OwnedFd,
+}
+
+impl ConfigWatcher {
+    /// Start watching directory and every subdirectory that exists at this point.
+    ///
+    /// Missing directories are not watched at all.
+    ///
+    /// # Errors
+    ///
+    /// - Will fail if watcher cannot be set up.
+    #[cfg(target_os = "linux")]
+    pub fn new(dir: &Path) -> Result<Self> {
+        use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+
+        // SAFETY: inotify_init1 takes no pointers.
+        let fd = unsafe { libc::inotify_init1(libc::IN_CLOEXEC | libc::IN_NONBLOCK) };
+        if fd < 0 {
+            return Err(std::io::Error::last_os_error()).context("Failed to set up inotify");
+        }
+        // SAFETY: File descriptor was just opened, and nothing else owns it.
+        let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+
+        let mask = libc::IN_CREATE
+            | libc::IN_DELETE
+            | libc::IN_CLOSE_WRITE
+            | libc::IN_MOVED_FROM
+            | libc::IN_MOVED_TO
+            | libc::IN_DELETE_SELF
+            | libc::IN_MOVE_SELF;
+        for dir in watched_dirs(dir) {
+            let Ok(path) = std::ffi::CString::new(dir.as_os_str().as_encoded_bytes()) else {
+                continue;
+            };
+            // SAFETY: File descriptor stays open, and path is a valid C string for the duration
+            // of the call.
+            if unsafe { libc::inotify_add_watch(fd.as_raw_fd(), path.as_ptr(), mask) } < 0 {
+                let error = std::io::Error::last_os_error();
+                return Err(error).with_context(|| format!("Failed to watch {dir:?}"));
+            }
+            debug!("Watch {dir:?} for changes");
+        }
+
+        Ok(Self { fd })
+    }
+
+    /// Start watching directory.
+    ///
+    /// # Errors
+    ///
+    /// - Never fails on this platform.
+    #[cfg(not(target_os = "linux"))]
+    pub fn new(_: &Path) -> Result<Self> {
+        Ok(Self {})
+    }
+
+    /// Wait until files in watched directories change, or timeout elapses.
+    ///
+    /// Returns true if files changed. Bursts of changes are waited out, such that they only count
+    /// once.
+    ///
+    /// # Errors
+    ///
+    /// - Will fail if watcher cannot be waited on.
+    #[cfg(target_os = "linux")]
+    pub fn wait(&self, timeout: Duration) -> Result<bool> {
+        if !self.poll(timeout)? {
+            return Ok(false);
+        }
+        while self.poll(DEBOUNCE)? {}
+
+        Ok(true)
+    }
+
+    /// Wait until timeout elapses.
+    ///
+    /// # Errors
+    ///
+    /// - Never fails on this platform.
+    #[cfg(not(target_os = "linux"))]
+    pub fn wait(&self, timeout: Duration) -> Result<bool> {
+        std::thread::sleep(timeout);
+        Ok(false)
+    }
+
+    // INVARIANT: Pending events are drained, such that the next poll only sees new changes.
+    #[cfg(target_os = "linux")]
+    fn poll(&self, timeout: Duration) -> Result<bool> {
+        use std::os::fd::AsRawFd;
+
+        let mut pollfd = libc::pollfd { fd: self.fd.as_raw_fd(), events: libc::POLLIN, revents: 0 };
+        let timeout = libc::c_int::try_from(timeout.as_millis()).unwrap_or(libc::c_int::MAX);
+        // SAFETY: Poll file descriptor is valid for the duration of the call.
+        let ready = unsafe { libc::poll(&mut pollfd, 1, timeout) };
+        if ready < 0 {
+            let error = std::io::Error::last_os_error();
+            if error.kind() == std::io::ErrorKind::Interrupted {
+                return Ok(false);
+            }
+            return Err(error).context("Failed to wait for changes");
+        }
+        if ready == 0 {
+            return Ok(false);
+        }
+
+        let mut buffer = [0u8; 4096];
+        // SAFETY: Buffer is valid for writes of its full length for the duration of the call.
+        while unsafe { libc::read(self.fd.as_raw_fd(), buffer.as_mut_ptr().cast(), buffer.len()) }
+            > 0
+        {}
+
+        Ok(true)
+    }
+}
+
+// INVARIANT: Gitdirs are never watched, because Git writes into them all the time.
+#[cfg(target_os = "linux")]
+fn watched_dirs(dir: &Path) -> Vec<PathBuf> {
+    let mut dirs = Vec::new();
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        pending.extend(
+            entries
+                .flatten()
+                .filter(|entry| entry.file_type().is_ok_and(|kind| kind.is_dir()))
+                .filter(|entry| entry.file_name() != ".git")
+                .map(|entry| entry.path()),
+        );
+        dirs.push(dir);
+    }
+
+    dirs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use sealed_test::prelude::*;
+
+    #[cfg(target_os = "linux")]
+    #[sealed_test]
+    fn smoke_config_watcher_wait() -> Result<()> {
+        let dir = std::env::current_dir()?.join("config");
+        create_dir_all(dir.join("nodes"))?;
+
+        let watcher = ConfigWatcher::new(&dir)?;
+        assert!(!watcher.wait(Duration::from_millis(50))?);
+
+        write(dir.join("nodes").join("vim.toml"), "[settings]\n")?;
+        assert!(watcher.wait(Duration::from_secs(5))?);
+        assert!(!watcher.wait(Duration::from_millis(50))?);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,179 @@
+// SPDX-FileCopyrightText: 2025 Jason Pena <jasonpena@awkless.com>
+// SPDX-License-Identifier: MIT
+
+//! Deployment journal.
+//!
+//! Pulling a broken configuration into a node is easy, but finding the commit that worked before
+//! means digging through reflogs. The deployment journal remembers the last few commits each node
+//! got deployed at, such that a node can be rolled back to the commit it was deployed at before.
+//! The journal lives in OCD's state directory.
+//!
+//! Rolling back works like an undo stack. Commits recorded after the commit rolled back to are
+//! forgotten, such that rolling back again goes further back instead of undoing the rollback.
+
+use crate::model::state_dir;
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fs::{create_dir_all, read_to_string, write},
+    path::PathBuf,
+};
+use tracing::{debug, instrument};
+
+/// Number of deployed commits remembered per node.
+pub(crate) const JOURNAL_DEPTH: usize = 10;
+
+/// Journal of commits that nodes got deployed at.
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub(crate) struct DeployJournal {
+    path: PathBuf,
+    file: JournalFile,
+}
+
+impl DeployJournal {
+    /// Load deployment journal from state directory.
+    ///
+    /// # Errors
+    ///
+    /// - Will fail if state directory cannot be determined.
+    #[instrument(level = "debug")]
+    pub(crate) fn load() -> Result<Self> {
+        Ok(Self::load_from(state_dir()?.join("journal.toml")))
+    }
+
+    /// Load deployment journal from specific path.
+    ///
+    /// # Invariants
+    ///
+    /// - Missing or unreadable journal files just start an empty journal, because losing the
+    ///   journal only loses the ability to roll back.
+    pub(crate) fn load_from(path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        let file = read_to_string(&path)
+            .ok()
+            .and_then(|data| toml::de::from_str::<JournalFile>(&data).ok())
+            .unwrap_or_else(|| {
+                debug!("No usable deployment journal at {path:?}, start with empty journal");
+                JournalFile::default()
+            });
+
+        Self { path, file }
+    }
+
+    /// Save deployment journal to its journal file.
+    ///
+    /// # Errors
+    ///
+    /// - Will fail if journal file or its parent directory cannot be written.
+    pub(crate) fn save(&self) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            create_dir_all(parent).with_context(|| format!("Failed to create {parent:?}"))?;
+        }
+        let data = toml::ser::to_string(&self.file)?;
+        write(&self.path, data).with_context(|| format!("Failed to write {:?}", self.path))?;
+
+        Ok(())
+    }
+
+    /// Record that node got deployed at commit.
+    ///
+    /// # Invariants
+    ///
+    /// - Redeploying the most recently recorded commit records nothing.
+    /// - Only the last [`JOURNAL_DEPTH`] commits of each node are kept.
+    pub(crate) fn record(&mut self, name: impl Into<String>, commit: impl Into<String>) {
+        let commits = self.file.nodes.entry(name.into()).or_default();
+        let commit = commit.into();
+        if commits.last() == Some(&commit) {
+            return;
+        }
+
+        commits.push(commit);
+        if commits.len() > JOURNAL_DEPTH {
+            commits.drain(..commits.len() - JOURNAL_DEPTH);
+        }
+    }
+
+    /// Find the most recently deployed commit of node that HEAD does not point to.
+    pub(crate) fn previous(&self, name: impl AsRef<str>, head: Option<&str>) -> Option<&str> {
+        self.file
+            .nodes
+            .get(name.as_ref())?
+            .iter()
+            .rev()
+            .map(String::as_str)
+            .find(|commit| Some(*commit) != head)
+    }
+
+    /// Forget every commit of node recorded after the most recent record of given commit.
+    pub(crate) fn rewind(&mut self, name: impl AsRef<str>, commit: impl AsRef<str>) {
+        if let Some(commits) = self.file.nodes.get_mut(name.as_ref()) {
+            if let Some(index) = commits.iter().rposition(|entry| entry == commit.as_ref()) {
+                commits.truncate(index + 1);
+            }
+        }
+    }
+
+    /// List recorded commits of node, most recently deployed last.
+    pub(crate) fn commits(&self, name: impl AsRef<str>) -> &[String] {
+        self.file.nodes.get(name.as_ref()).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    /// Move recorded commits of node over to its new name.
+    pub(crate) fn rename(&mut self, old: impl AsRef<str>, new: impl Into<String>) {
+        if let Some(commits) = self.file.nodes.remove(old.as_ref()) {
+            self.file.nodes.insert(new.into(), commits);
+        }
+    }
+
+    /// Iterate over every node with recorded commits, along with those commits.
+    pub(crate) fn nodes(&self) -> impl Iterator<Item = (&str, &[String])> {
+        self.file.nodes.iter().map(|(name, commits)| (name.as_str(), commits.as_slice()))
+    }
+}
+
+#[derive(Debug, Default, PartialEq, Eq, Clone, Deserialize, Serialize)]
+struct JournalFile {
+    #[serde(default)]
+    nodes: BTreeMap<String, Vec<String>>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use pretty_assertions::assert_eq as pretty_assert_eq;
+    use sealed_test::prelude::*;
+
+    #[sealed_test]
+    fn smoke_deploy_journal_record() -> Result<()> {
+        let path = std::env::current_dir()?.join("state/journal.toml");
+        let mut journal = DeployJournal::load_from(&path);
+        assert!(journal.previous("vim", None).is_none());
+
+        for commit in ["a", "b", "b", "c"] {
+            journal.record("vim", commit);
+        }
+        journal.save()?;
+        let mut journal = DeployJournal::load_from(&path);
+        pretty_assert_eq!(journal.commits("vim"), ["a", "b", "c"]);
+
+        // HEAD moved past its last deployment, e.g., through "ocd vim pull".
+        pretty_assert_eq!(journal.previous("vim", Some("d")), Some("c"));
+        pretty_assert_eq!(journal.previous("vim", Some("c")), Some("b"));
+        journal.rewind("vim", "b");
+        pretty_assert_eq!(journal.commits("vim"), ["a", "b"]);
+        pretty_assert_eq!(journal.previous("vim", Some("b")), Some("a"));
+
+        for commit in 0..JOURNAL_DEPTH {
+            journal.record("sh", commit.to_string());
+        }
+        journal.record("sh", "last");
+        pretty_assert_eq!(journal.commits("sh").len(), JOURNAL_DEPTH);
+        pretty_assert_eq!(journal.commits("sh").first().map(String::as_str), Some("1"));
+
+        Ok(())
+    }
+}
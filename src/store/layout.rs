@@ -0,0 +1,283 @@
+// SPDX-FileCopyrightText: 2025 Jason Pena <jasonpena@awkless.com>
+// SPDX-License-Identifier: MIT
+
+//! Layout of the repository store.
+//!
+//! Each node is kept at its name in the repository store by default. Two nodes named "nvim" from
+//! different forges cannot coexist that way. Root can pick the forge layout through its
+//! `store_layout` setting instead, which keeps each node at the host and path of its URL, e.g.,
+//! "github.com/user/nvim".
+//!
+//! The layout in use is recorded in the repository store. Once root picks another layout, node
+//! repositories are moved to wherever the new layout puts them the next time OCD runs.
+
+use crate::model::{
+    cluster::{Cluster, NodeEntry, StoreLayout},
+    data_dir,
+    url::GitUrl,
+};
+
+use anyhow::{anyhow, Context, Result};
+use std::{
+    collections::HashSet,
+    fs::{create_dir_all, read_dir, read_to_string, remove_dir, rename, write},
+    path::{Component, Path, PathBuf},
+    sync::Mutex,
+};
+use tracing::{debug, info, instrument};
+
+/// File in repository store recording the layout in use.
+const LAYOUT_MARKER: &str = ".layout";
+
+static STORE_LAYOUT: Mutex<StoreLayout> = Mutex::new(StoreLayout::Name);
+
+/// Use layout of repository store for the rest of the invocation.
+///
+/// # Panics
+///
+/// - Will panic if mutex guard fails to lock.
+pub fn set_store_layout(layout: StoreLayout) {
+    *STORE_LAYOUT.lock().unwrap() = layout;
+}
+
+/// Get layout of repository store in use.
+///
+/// # Panics
+///
+/// - Will panic if mutex guard fails to lock.
+pub(crate) fn store_layout() -> StoreLayout {
+    *STORE_LAYOUT.lock().unwrap()
+}
+
+/// Determine where layout keeps node of given name and URL in repository store.
+///
+/// # Invariants
+///
+/// - Paths never leave the repository store, no matter what the URL looks like.
+pub(crate) fn layout_path(layout: StoreLayout, store: &Path, name: &str, url: &str) -> PathBuf {
+    let forge = match layout {
+        StoreLayout::Name => None,
+        StoreLayout::Forge => forge_path(url),
+    };
+
+    match forge {
+        Some(path) => store.join(path),
+        None => store.join(name),
+    }
+}
+
+fn forge_path(url: &str) -> Option<PathBuf> {
+    let url = GitUrl::parse(url).ok()?;
+    let mut path = PathBuf::from(url.host()?);
+    let repo = url.path().trim_start_matches(['/', '~']);
+    let repo = repo.strip_suffix(".git").unwrap_or(repo);
+    let components: Vec<_> = Path::new(repo)
+        .components()
+        .filter_map(|component| match component {
+            Component::Normal(part) => Some(part),
+            _ => None,
+        })
+        .collect();
+    if components.is_empty() {
+        return None;
+    }
+    path.extend(components);
+
+    Some(path)
+}
+
+/// Check if layout in use differs from the layout recorded in the repository store.
+///
+/// Repository stores that were never cloned have no layout to change.
+pub fn store_layout_changed() -> bool {
+    data_dir()
+        .is_ok_and(|store| store.join("root").exists() && recorded_layout(&store) != store_layout())
+}
+
+/// Move node repositories to wherever the layout in use puts them.
+///
+/// Only does anything if the layout in use differs from the layout recorded in the repository
+/// store. Inline nodes, nested clusters, and nodes living in external repositories stay where
+/// they are. Returns the names of the nodes that were moved.
+///
+/// # Errors
+///
+/// - Will fail if data directory cannot be determined.
+/// - Will fail if node repository cannot be moved.
+/// - Will fail if layout in use cannot be recorded.
+#[instrument(skip(cluster), level = "debug")]
+pub fn migrate_store_layout(cluster: &Cluster) -> Result<Vec<String>> {
+    let store = data_dir()?;
+    if !store.join("root").exists() {
+        return Ok(Vec::new());
+    }
+
+    let recorded = recorded_layout(&store);
+    let layout = store_layout();
+    if recorded == layout {
+        return Ok(Vec::new());
+    }
+
+    debug!("Migrate repository store from {recorded} layout to {layout} layout");
+    let mut moved = Vec::new();
+    for (name, node) in &cluster.nodes {
+        let settings = &node.settings;
+        if settings.subdir.is_some()
+            || settings.path.is_some()
+            || settings.deployment.kind.is_cluster()
+        {
+            continue;
+        }
+
+        let from = layout_path(recorded, &store, name, &settings.url);
+        let to = layout_path(layout, &store, name, &settings.url);
+        if from == to || !from.exists() || to.exists() {
+            continue;
+        }
+
+        if let Some(parent) = to.parent() {
+            create_dir_all(parent).with_context(|| format!("Failed to create {parent:?}"))?;
+        }
+        rename(&from, &to).with_context(|| format!("Failed to move {from:?} to {to:?}"))?;
+        prune_empty_parents(&from, &store);
+        info!("Move {name:?} from {from:?} to {to:?}");
+        moved.push(name.clone());
+    }
+
+    let marker = store.join(LAYOUT_MARKER);
+    write(&marker, format!("{layout}\n")).with_context(|| format!("Failed to write {marker:?}"))?;
+
+    Ok(moved)
+}
+
+/// Move repository of node to wherever the layout in use keeps it under its new name.
+///
+/// Inline nodes, nested clusters, nodes living in external repositories, and nodes that the
+/// layout in use keeps by URL stay where they are. Returns true if the repository was moved.
+///
+/// # Errors
+///
+/// - Will fail if data directory cannot be determined.
+/// - Will fail if something already exists where the new name would keep the repository.
+/// - Will fail if node repository cannot be moved.
+#[instrument(skip(node), level = "debug")]
+pub(crate) fn move_node_repo(node: &NodeEntry, old: &str, new: &str) -> Result<bool> {
+    let settings = &node.settings;
+    if settings.subdir.is_some() || settings.path.is_some() || settings.deployment.kind.is_cluster()
+    {
+        return Ok(false);
+    }
+
+    let store = data_dir()?;
+    let layout = store_layout();
+    let from = layout_path(layout, &store, old, &settings.url);
+    let to = layout_path(layout, &store, new, &settings.url);
+    if from == to || !from.exists() {
+        return Ok(false);
+    }
+    if to.exists() {
+        return Err(anyhow!(
+            "Cannot move {old:?} to {to:?}, because something already exists there"
+        ));
+    }
+
+    if let Some(parent) = to.parent() {
+        create_dir_all(parent).with_context(|| format!("Failed to create {parent:?}"))?;
+    }
+    rename(&from, &to).with_context(|| format!("Failed to move {from:?} to {to:?}"))?;
+    prune_empty_parents(&from, &store);
+    info!("Move {old:?} from {from:?} to {to:?}");
+
+    Ok(true)
+}
+
+/// List repositories in the repository store that no entry of cluster claims.
+///
+/// Inline nodes and nodes living in external repositories claim nothing in the repository store.
+/// Repositories are never searched for other repositories inside of them.
+///
+/// # Errors
+///
+/// - Will fail if data directory cannot be determined.
+pub fn orphaned_repos(cluster: &Cluster) -> Result<Vec<PathBuf>> {
+    let store = data_dir()?;
+    let layout = store_layout();
+    let mut claimed: HashSet<PathBuf> = cluster
+        .nodes
+        .iter()
+        .filter(|(_, node)| node.settings.subdir.is_none() && node.settings.path.is_none())
+        .map(|(name, node)| layout_path(layout, &store, name, &node.settings.url))
+        .collect();
+    claimed.insert(store.join("root"));
+
+    let mut orphans = Vec::new();
+    let mut pending = vec![store];
+    while let Some(dir) = pending.pop() {
+        let Ok(entries) = read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if !entry.file_type().is_ok_and(|kind| kind.is_dir()) || claimed.contains(&path) {
+                continue;
+            }
+
+            if path.join("HEAD").is_file() || path.join(".git").exists() {
+                orphans.push(path);
+            } else {
+                pending.push(path);
+            }
+        }
+    }
+    orphans.sort();
+
+    Ok(orphans)
+}
+
+// INVARIANT: Missing or unknown markers mean the default layout, i.e., stores that predate
+// layouts.
+fn recorded_layout(store: &Path) -> StoreLayout {
+    match read_to_string(store.join(LAYOUT_MARKER)).unwrap_or_default().trim() {
+        "forge" => StoreLayout::Forge,
+        _ => StoreLayout::Name,
+    }
+}
+
+/// Remove directories left empty between path and repository store, e.g., after its node was
+/// moved or removed.
+pub(crate) fn prune_empty_parents(path: &Path, store: &Path) {
+    for parent in path.ancestors().skip(1) {
+        if parent == store || !parent.starts_with(store) || remove_dir(parent).is_err() {
+            break;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use pretty_assertions::assert_eq as pretty_assert_eq;
+    use simple_test_case::test_case;
+
+    #[test_case(StoreLayout::Name, "https://github.com/user/nvim.git", "/store/nvim"; "name")]
+    #[test_case(
+        StoreLayout::Forge,
+        "https://github.com/user/nvim.git",
+        "/store/github.com/user/nvim";
+        "forge https"
+    )]
+    #[test_case(
+        StoreLayout::Forge,
+        "git@codeberg.org:user/nvim",
+        "/store/codeberg.org/user/nvim";
+        "forge scp-like"
+    )]
+    #[test_case(StoreLayout::Forge, "/srv/git/nvim.git", "/store/nvim"; "forge local path")]
+    #[test_case(StoreLayout::Forge, "https://host/../../etc", "/store/host/etc"; "forge escape")]
+    #[test_case(StoreLayout::Forge, "", "/store/nvim"; "forge no url")]
+    #[test]
+    fn smoke_layout_path(layout: StoreLayout, url: &str, expect: &str) {
+        pretty_assert_eq!(layout_path(layout, Path::new("/store"), "nvim", url), Path::new(expect));
+    }
+}
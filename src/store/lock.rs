@@ -0,0 +1,215 @@
+// SPDX-FileCopyrightText: 2025 Jason Pena <jasonpena@awkless.com>
+// SPDX-License-Identifier: MIT
+
+//! Per-repository deployment locks.
+//!
+//! Deploying an entry writes its sparse checkout file, and then checks out its index against that
+//! file. Two deployments of the same repository that interleave these steps can leave the working
+//! directory alias checked out against rules that neither of them wrote. Inline nodes make this
+//! worse, because they share the repository and sparse checkout file of root.
+//!
+//! Thus, every deployment holds an exclusive advisory lock on a lock file inside the gitdir of
+//! its repository, such that deployments of the same repository are serialized, no matter if
+//! they come from the same OCD invocation or from another one. Locks are released by the
+//! operating system once their holder exits, so a crashed deployment never leaves a stale lock
+//! behind.
+//!
+//! The sync daemon holds a lock of its own in OCD's state directory for as long as it runs, such
+//! that only one daemon reconciles a cluster at a time, and such that others can tell whether a
+//! daemon is running at all.
+
+use anyhow::{Context, Result};
+use std::{
+    fs::{create_dir_all, File, OpenOptions},
+    path::{Path, PathBuf},
+};
+use tracing::{debug, info};
+
+/// Name of lock file inside gitdir of repository.
+const LOCK_FILE: &str = "ocd-deploy.lock";
+
+/// Exclusive deployment lock on repository.
+///
+/// # Invariants
+///
+/// - Lock is held until it goes out of scope.
+#[derive(Debug)]
+pub(crate) struct RepoLock {
+    path: PathBuf,
+    _file: File,
+}
+
+impl RepoLock {
+    /// Acquire deployment lock of repository at gitdir, waiting for current holder if needed.
+    ///
+    /// # Errors
+    ///
+    /// - Will fail if lock file cannot be opened.
+    /// - Will fail if lock cannot be acquired.
+    pub(crate) fn acquire(gitdir: &Path) -> Result<Self> {
+        let path = gitdir.join(LOCK_FILE);
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&path)
+            .with_context(|| format!("Failed to open lock file {path:?}"))?;
+
+        if !try_lock(&file)? {
+            info!("Wait for another deployment of {gitdir:?} to finish");
+            lock(&file).with_context(|| format!("Failed to lock {path:?}"))?;
+        }
+        debug!("Acquire deployment lock {path:?}");
+
+        Ok(Self { path, _file: file })
+    }
+}
+
+impl Drop for RepoLock {
+    fn drop(&mut self) {
+        // INVARIANT: Closing the lock file releases the lock.
+        debug!("Release deployment lock {:?}", self.path);
+    }
+}
+
+/// Exclusive lock of sync daemon, such that only one daemon reconciles the cluster at a time.
+///
+/// # Invariants
+///
+/// - Lock is held until it goes out of scope.
+#[derive(Debug)]
+pub(crate) struct DaemonLock {
+    path: PathBuf,
+    _file: File,
+}
+
+impl DaemonLock {
+    /// Acquire daemon lock at path, unless another daemon holds it already.
+    ///
+    /// # Errors
+    ///
+    /// - Will fail if lock file or its parent directory cannot be created.
+    /// - Will fail if lock cannot be acquired for any other reason than being held.
+    pub(crate) fn try_acquire(path: impl Into<PathBuf>) -> Result<Option<Self>> {
+        let path = path.into();
+        if let Some(parent) = path.parent() {
+            create_dir_all(parent).with_context(|| format!("Failed to create {parent:?}"))?;
+        }
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&path)
+            .with_context(|| format!("Failed to open lock file {path:?}"))?;
+
+        if !try_lock(&file)? {
+            return Ok(None);
+        }
+        debug!("Acquire daemon lock {path:?}");
+
+        Ok(Some(Self { path, _file: file }))
+    }
+
+    /// Check if some daemon holds lock at path, without acquiring it for longer than the check.
+    pub(crate) fn is_held(path: &Path) -> bool {
+        path.exists() && matches!(Self::try_acquire(path), Ok(None))
+    }
+}
+
+impl Drop for DaemonLock {
+    fn drop(&mut self) {
+        // INVARIANT: Closing the lock file releases the lock.
+        debug!("Release daemon lock {:?}", self.path);
+    }
+}
+
+#[cfg(unix)]
+fn try_lock(file: &File) -> Result<bool> {
+    use std::os::unix::io::AsRawFd;
+
+    // SAFETY: File descriptor stays open for the duration of the call.
+    if unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX | libc::LOCK_NB) } == 0 {
+        return Ok(true);
+    }
+
+    let error = std::io::Error::last_os_error();
+    if error.kind() == std::io::ErrorKind::WouldBlock {
+        return Ok(false);
+    }
+
+    Err(error.into())
+}
+
+#[cfg(unix)]
+fn lock(file: &File) -> Result<()> {
+    use std::os::unix::io::AsRawFd;
+
+    // SAFETY: File descriptor stays open for the duration of the call.
+    if unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX) } == 0 {
+        return Ok(());
+    }
+
+    Err(std::io::Error::last_os_error().into())
+}
+
+// INVARIANT: Platforms without advisory locks deploy without serialization.
+#[cfg(not(unix))]
+fn try_lock(_: &File) -> Result<bool> {
+    Ok(true)
+}
+
+#[cfg(not(unix))]
+fn lock(_: &File) -> Result<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use sealed_test::prelude::*;
+    use std::{
+        sync::mpsc,
+        time::{Duration, Instant},
+    };
+
+    #[cfg(unix)]
+    #[sealed_test]
+    fn smoke_repo_lock_serializes() -> Result<()> {
+        let gitdir = std::env::current_dir()?;
+        let lock = RepoLock::acquire(&gitdir)?;
+
+        let (sender, receiver) = mpsc::channel();
+        let waiter = std::thread::spawn(move || -> Result<()> {
+            let _lock = RepoLock::acquire(&gitdir)?;
+            sender.send(Instant::now())?;
+            Ok(())
+        });
+
+        // Second lock must wait until first lock is released.
+        assert!(receiver.recv_timeout(Duration::from_millis(200)).is_err());
+        let released = Instant::now();
+        drop(lock);
+        assert!(receiver.recv_timeout(Duration::from_secs(5))? >= released);
+        waiter.join().expect("waiter does not panic")?;
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[sealed_test]
+    fn smoke_daemon_lock_excludes() -> Result<()> {
+        let path = std::env::current_dir()?.join("state").join("sync.lock");
+        assert!(!DaemonLock::is_held(&path));
+
+        let lock = DaemonLock::try_acquire(&path)?;
+        assert!(lock.is_some());
+        assert!(DaemonLock::try_acquire(&path)?.is_none());
+        assert!(DaemonLock::is_held(&path));
+
+        drop(lock);
+        assert!(!DaemonLock::is_held(&path));
+
+        Ok(())
+    }
+}
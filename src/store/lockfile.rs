@@ -0,0 +1,183 @@
+// SPDX-FileCopyrightText: 2025 Jason Pena <jasonpena@awkless.com>
+// SPDX-License-Identifier: MIT
+
+//! Cluster lockfiles.
+//!
+//! Branches of nodes keep moving, so deploying the same cluster twice rarely yields the same
+//! machine. A lockfile pins nodes to the exact commit they were at when they got frozen, along
+//! with the branch they were frozen from. It sits next to the cluster definition as
+//! "cluster.lock", and is committed to root, such that checking out an older commit of root also
+//! brings back the commits its nodes were pinned to at that time.
+
+use crate::model::config_dir;
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fs::{read_to_string, remove_file, write},
+    path::{Path, PathBuf},
+};
+use tracing::{debug, instrument};
+
+/// Comment leading every lockfile, such that users know not to bother editing it.
+const LOCKFILE_HEADER: &str = "# Generated by ocd freeze, do not edit.\n";
+
+/// Node pinned by lockfile.
+#[derive(Debug, PartialEq, Eq, Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct LockedNode {
+    /// Commit ID that node is pinned to.
+    pub(crate) commit: String,
+
+    /// Branch that node was frozen from.
+    pub(crate) branch: String,
+}
+
+/// Lockfile of cluster.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub(crate) struct ClusterLock {
+    path: PathBuf,
+    file: LockFile,
+}
+
+impl ClusterLock {
+    /// Load lockfile of cluster from configuration directory.
+    ///
+    /// Lockfile is empty if no lockfile exists yet.
+    ///
+    /// # Errors
+    ///
+    /// - Will fail if configuration directory cannot be determined.
+    /// - Will fail if existing lockfile cannot be read or parsed.
+    #[instrument(level = "debug")]
+    pub(crate) fn load() -> Result<Self> {
+        Self::load_from(config_dir()?.join("cluster.lock"))
+    }
+
+    /// Load lockfile from specific path.
+    ///
+    /// # Errors
+    ///
+    /// - Will fail if existing lockfile cannot be read or parsed.
+    pub(crate) fn load_from(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        if !path.exists() {
+            debug!("No lockfile at {path:?}, start with empty lockfile");
+            return Ok(Self { path, file: LockFile::default() });
+        }
+
+        let data = read_to_string(&path).with_context(|| format!("Failed to read {path:?}"))?;
+        let file =
+            toml::de::from_str(&data).with_context(|| format!("Failed to parse {path:?}"))?;
+
+        Ok(Self { path, file })
+    }
+
+    /// Save lockfile.
+    ///
+    /// # Invariants
+    ///
+    /// - Empty lockfiles remove their file instead, leaving nothing behind.
+    ///
+    /// # Errors
+    ///
+    /// - Will fail if lockfile cannot be written or removed.
+    pub(crate) fn save(&self) -> Result<()> {
+        if self.file.nodes.is_empty() {
+            if self.path.exists() {
+                remove_file(&self.path)
+                    .with_context(|| format!("Failed to remove {:?}", self.path))?;
+            }
+            return Ok(());
+        }
+
+        let data = format!("{LOCKFILE_HEADER}\n{}", toml::ser::to_string(&self.file)?);
+        write(&self.path, data).with_context(|| format!("Failed to write {:?}", self.path))?;
+
+        Ok(())
+    }
+
+    /// Pin node to commit, remembering the branch it was frozen from.
+    pub(crate) fn freeze(
+        &mut self,
+        name: impl Into<String>,
+        commit: impl Into<String>,
+        branch: impl Into<String>,
+    ) {
+        let node = LockedNode { commit: commit.into(), branch: branch.into() };
+        self.file.nodes.insert(name.into(), node);
+    }
+
+    /// Unpin node, returning what it was pinned to.
+    pub(crate) fn thaw(&mut self, name: impl AsRef<str>) -> Option<LockedNode> {
+        self.file.nodes.remove(name.as_ref())
+    }
+
+    /// Move pin of node over to its new name, returning true if node was pinned at all.
+    pub(crate) fn rename(&mut self, old: impl AsRef<str>, new: impl Into<String>) -> bool {
+        let Some(node) = self.file.nodes.remove(old.as_ref()) else {
+            return false;
+        };
+        self.file.nodes.insert(new.into(), node);
+        true
+    }
+
+    /// Get what node is pinned to, if it is pinned at all.
+    pub(crate) fn get(&self, name: impl AsRef<str>) -> Option<&LockedNode> {
+        self.file.nodes.get(name.as_ref())
+    }
+
+    /// Check if no node is pinned.
+    pub(crate) fn is_empty(&self) -> bool {
+        self.file.nodes.is_empty()
+    }
+
+    /// Get path to lockfile.
+    pub(crate) fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+#[derive(Debug, Default, PartialEq, Eq, Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+struct LockFile {
+    #[serde(default)]
+    nodes: BTreeMap<String, LockedNode>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use pretty_assertions::assert_eq as pretty_assert_eq;
+    use sealed_test::prelude::*;
+
+    #[sealed_test]
+    fn smoke_cluster_lock_round_trip() -> Result<()> {
+        let path = std::env::current_dir()?.join("cluster.lock");
+        let mut lock = ClusterLock::load_from(&path)?;
+        assert!(lock.is_empty());
+
+        lock.freeze("vim", "0123abcd", "main");
+        lock.freeze("sh", "4567ef01", "trunk");
+        lock.save()?;
+
+        let expect = "# Generated by ocd freeze, do not edit.\n\n[nodes.sh]\ncommit = \"4567ef01\"\n\
+                      branch = \"trunk\"\n\n[nodes.vim]\ncommit = \"0123abcd\"\nbranch = \"main\"\n";
+        pretty_assert_eq!(read_to_string(&path)?, expect);
+        let mut lock = ClusterLock::load_from(&path)?;
+        pretty_assert_eq!(lock.get("vim").map(|node| node.commit.as_str()), Some("0123abcd"));
+
+        pretty_assert_eq!(lock.thaw("vim").map(|node| node.branch), Some("main".to_string()));
+        assert!(lock.thaw("bash").is_none());
+        lock.thaw("sh");
+        lock.save()?;
+        assert!(!path.exists());
+
+        write(&path, "[nodes.vim]\nrev = \"0123abcd\"\n")?;
+        assert!(ClusterLock::load_from(&path).is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,261 @@
+// SPDX-FileCopyrightText: 2025 Jason Pena <jasonpena@awkless.com>
+// SPDX-License-Identifier: MIT
+
+//! Deployment manifests.
+//!
+//! Some deployment strategies place files into a working directory alias without Git knowing
+//! about it, e.g., hard-link or symlink farms. A deployment manifest keeps track of every file such a
+//! strategy placed, so they can be cleanly removed later without touching anything the user owns.
+//! Manifests live in OCD's state directory, one per repository entry.
+//!
+//! Manifests also remember the modification time of each file when it was last placed, such that
+//! files that changed since then, e.g., because they are still being edited, are never clobbered
+//! by redeployment without the user knowing about it. Copies get their SHA-256 digest remembered
+//! as well, because only their content tells whether they were edited.
+
+use super::archive::sha256_hex;
+
+use crate::model::state_dir;
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fs::{create_dir_all, read_to_string, remove_file, write},
+    path::{Path, PathBuf},
+    time::UNIX_EPOCH,
+};
+use tracing::{debug, instrument};
+
+/// Manifest of files deployed outside of Git for a repository entry.
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub(crate) struct DeployManifest {
+    path: PathBuf,
+    files: BTreeMap<PathBuf, LinkMode>,
+    mtimes: BTreeMap<PathBuf, u64>,
+    hashes: BTreeMap<PathBuf, String>,
+}
+
+impl DeployManifest {
+    /// Load manifest of repository entry.
+    ///
+    /// Manifest is empty if no manifest file exists yet.
+    ///
+    /// # Errors
+    ///
+    /// - Will fail if state directory cannot be determined.
+    /// - Will fail if existing manifest file cannot be read or parsed.
+    #[instrument(skip(name), level = "debug")]
+    pub(crate) fn load(name: impl AsRef<str>) -> Result<Self> {
+        let path = state_dir()?.join("manifests").join(format!("{}.toml", name.as_ref()));
+        Self::load_from(path)
+    }
+
+    /// Load manifest from specific path.
+    ///
+    /// # Errors
+    ///
+    /// - Will fail if existing manifest file cannot be read or parsed.
+    pub(crate) fn load_from(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        if !path.exists() {
+            debug!("No manifest at {path:?}, start with empty manifest");
+            return Ok(Self { path, ..Self::default() });
+        }
+
+        let data = read_to_string(&path).with_context(|| format!("Failed to read {path:?}"))?;
+        let manifest: ManifestFile =
+            toml::de::from_str(&data).with_context(|| format!("Failed to parse {path:?}"))?;
+
+        Ok(Self { path, files: manifest.files, mtimes: manifest.mtimes, hashes: manifest.hashes })
+    }
+
+    /// Save manifest to its manifest file.
+    ///
+    /// # Invariants
+    ///
+    /// - Empty manifests remove their manifest file instead, leaving nothing behind.
+    ///
+    /// # Errors
+    ///
+    /// - Will fail if manifest file cannot be written or removed.
+    pub(crate) fn save(&self) -> Result<()> {
+        if self.files.is_empty() {
+            if self.path.exists() {
+                remove_file(&self.path)
+                    .with_context(|| format!("Failed to remove {:?}", self.path))?;
+            }
+            return Ok(());
+        }
+
+        if let Some(parent) = self.path.parent() {
+            create_dir_all(parent).with_context(|| format!("Failed to create {parent:?}"))?;
+        }
+
+        let data = toml::ser::to_string(&ManifestFile {
+            files: self.files.clone(),
+            mtimes: self.mtimes.clone(),
+            hashes: self.hashes.clone(),
+        })?;
+        write(&self.path, data).with_context(|| format!("Failed to write {:?}", self.path))?;
+
+        Ok(())
+    }
+
+    /// Track file relative to working directory alias, with the way it got placed there.
+    pub(crate) fn insert(&mut self, path: impl Into<PathBuf>, mode: LinkMode) {
+        self.files.insert(path.into(), mode);
+    }
+
+    /// Stop tracking file.
+    pub(crate) fn remove(&mut self, path: impl AsRef<Path>) -> Option<LinkMode> {
+        let _ = self.mtimes.remove(path.as_ref());
+        let _ = self.hashes.remove(path.as_ref());
+        self.files.remove(path.as_ref())
+    }
+
+    /// Remember modification time of tracked file right after it got placed.
+    ///
+    /// # Errors
+    ///
+    /// - Will fail if modification time of placed file cannot be determined.
+    pub(crate) fn record_mtime(&mut self, path: impl Into<PathBuf>, placed: &Path) -> Result<()> {
+        let _ = self.mtimes.insert(path.into(), mtime(placed)?);
+        Ok(())
+    }
+
+    /// Remember SHA-256 digest of tracked file right after it got placed.
+    ///
+    /// # Errors
+    ///
+    /// - Will fail if placed file cannot be read.
+    pub(crate) fn record_hash(&mut self, path: impl Into<PathBuf>, placed: &Path) -> Result<()> {
+        let _ = self.hashes.insert(path.into(), content_hash(placed)?);
+        Ok(())
+    }
+
+    /// Determine if tracked file changed since it was last placed.
+    ///
+    /// Files with a recorded digest are compared by content, every other file by modification
+    /// time.
+    ///
+    /// # Invariants
+    ///
+    /// - Files without a recorded digest or modification time never count as changed, because
+    ///   nothing is known about them.
+    ///
+    /// # Errors
+    ///
+    /// - Will fail if placed file cannot be read, or its modification time cannot be determined.
+    pub(crate) fn is_changed(&self, path: impl AsRef<Path>, placed: &Path) -> Result<bool> {
+        if let Some(recorded) = self.hashes.get(path.as_ref()) {
+            return Ok(*recorded != content_hash(placed)?);
+        }
+
+        match self.mtimes.get(path.as_ref()) {
+            Some(recorded) => Ok(*recorded != mtime(placed)?),
+            None => Ok(false),
+        }
+    }
+
+    /// Get the way tracked file got placed, if it is tracked at all.
+    pub(crate) fn get(&self, path: impl AsRef<Path>) -> Option<LinkMode> {
+        self.files.get(path.as_ref()).copied()
+    }
+
+    /// Iterate through tracked files in sorted order.
+    pub(crate) fn iter(&self) -> impl Iterator<Item = (&Path, LinkMode)> {
+        self.files.iter().map(|(path, mode)| (path.as_path(), *mode))
+    }
+}
+
+/// The way a tracked file got placed into working directory alias.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum LinkMode {
+    /// File is hard-linked to internal checkout.
+    HardLink,
+
+    /// File is a copy, because it could not be hard-linked.
+    Copy,
+
+    /// File is a symlink to internal checkout.
+    Symlink,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+struct ManifestFile {
+    #[serde(default)]
+    files: BTreeMap<PathBuf, LinkMode>,
+
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    mtimes: BTreeMap<PathBuf, u64>,
+
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    hashes: BTreeMap<PathBuf, String>,
+}
+
+// INVARIANT: Symlinks are placed as they are, so their own modification time counts.
+fn mtime(path: &Path) -> Result<u64> {
+    let modified = path
+        .symlink_metadata()
+        .and_then(|meta| meta.modified())
+        .with_context(|| format!("Failed to get modification time of {path:?}"))?;
+    let nanos = modified.duration_since(UNIX_EPOCH).unwrap_or_default().as_nanos();
+
+    Ok(u64::try_from(nanos).unwrap_or(u64::MAX))
+}
+
+// INVARIANT: Symlinks are hashed by where they point, never by what they point to.
+fn content_hash(path: &Path) -> Result<String> {
+    let data = match std::fs::read_link(path) {
+        Ok(target) => target.to_string_lossy().into_owned().into_bytes(),
+        Err(_) => std::fs::read(path).with_context(|| format!("Failed to read {path:?}"))?,
+    };
+
+    Ok(sha256_hex(&data))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use pretty_assertions::assert_eq as pretty_assert_eq;
+    use sealed_test::prelude::*;
+
+    #[sealed_test]
+    fn smoke_deploy_manifest_round_trip() -> Result<()> {
+        let path = std::env::current_dir()?.join("manifests/vim.toml");
+        let mut manifest = DeployManifest::load_from(&path)?;
+        manifest.insert(".vimrc", LinkMode::HardLink);
+        manifest.insert(".vim/colors/theme.vim", LinkMode::Copy);
+        manifest.save()?;
+
+        let expect = "[files]\n\".vim/colors/theme.vim\" = \"copy\"\n\".vimrc\" = \"hard_link\"\n";
+        pretty_assert_eq!(read_to_string(&path)?, expect);
+        pretty_assert_eq!(DeployManifest::load_from(&path)?, manifest);
+
+        // Files only count as changed once their modification time moved.
+        write(".vimrc", "set number")?;
+        manifest.record_mtime(".vimrc", Path::new(".vimrc"))?;
+        assert!(!manifest.is_changed(".vimrc", Path::new(".vimrc"))?);
+        assert!(!manifest.is_changed(".vim/colors/theme.vim", Path::new(".vimrc"))?);
+        let file = std::fs::File::options().write(true).open(".vimrc")?;
+        file.set_modified(std::time::SystemTime::now() + std::time::Duration::from_secs(60))?;
+        assert!(manifest.is_changed(".vimrc", Path::new(".vimrc"))?);
+
+        // Copies only count as changed once their content moved, whatever their modification time.
+        manifest.record_hash(".vimrc", Path::new(".vimrc"))?;
+        assert!(!manifest.is_changed(".vimrc", Path::new(".vimrc"))?);
+        write(".vimrc", "set relativenumber")?;
+        assert!(manifest.is_changed(".vimrc", Path::new(".vimrc"))?);
+
+        manifest.remove(".vimrc");
+        manifest.remove(".vim/colors/theme.vim");
+        manifest.save()?;
+        assert!(!path.exists());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,196 @@
+// SPDX-FileCopyrightText: 2025 Jason Pena <jasonpena@awkless.com>
+// SPDX-License-Identifier: MIT
+
+//! Preflight checks of deployments.
+//!
+//! Deployments check files out into working directory aliases one entry at a time. Running out of
+//! permissions or disk space part way through leaves a cluster half deployed, with an error from
+//! the middle of a checkout that does not say which entry needed what. Preflight checks gather
+//! what each target entry needs up front, i.e., which directories get written to, and how many
+//! bytes of file blobs get checked out, such that deployments fail before touching anything, with
+//! a diagnostic per entry.
+
+use super::{
+    list_file_blobs, scan_deployment, DeployAction, DeployState, Node, RepoEntry, Root,
+    SparseCheckout,
+};
+
+use crate::model::check_writable;
+
+use anyhow::{anyhow, Result};
+use beau_collector::BeauCollector as _;
+use indicatif::HumanBytes;
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    path::{Path, PathBuf},
+};
+use tracing::debug;
+
+/// What deploying a set of entries needs from the filesystem.
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub struct DeployPreflight {
+    needs: Vec<EntryNeeds>,
+}
+
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+struct EntryNeeds {
+    name: String,
+    dirs: BTreeSet<PathBuf>,
+    bytes: u64,
+}
+
+impl DeployPreflight {
+    /// Construct new empty preflight check.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add what deploying root through action needs.
+    ///
+    /// # Errors
+    ///
+    /// Will fail if deployment state of root cannot be determined.
+    pub fn add_root(&mut self, root: &Root, action: DeployAction) -> Result<()> {
+        let needs = entry_needs(&root.entry, &root.deployer.excluded, action)?;
+        self.needs.push(needs);
+        Ok(())
+    }
+
+    /// Add what deploying node through action needs.
+    ///
+    /// # Errors
+    ///
+    /// Will fail if deployment state of node cannot be determined.
+    pub fn add_node(&mut self, node: &Node, action: DeployAction) -> Result<()> {
+        let needs = entry_needs(&node.entry, &node.deployer.excluded, action)?;
+        self.needs.push(needs);
+        Ok(())
+    }
+
+    /// Total size of file blobs that deployment checks out.
+    pub fn needed_bytes(&self) -> u64 {
+        self.needs.iter().map(|needs| needs.bytes).sum()
+    }
+
+    /// Check that every directory deployment writes to is writable, and that each filesystem has
+    /// enough free space for the file blobs checked out onto it.
+    ///
+    /// # Errors
+    ///
+    /// - Will fail with a diagnostic for each entry whose directories are not writable.
+    /// - Will fail with a diagnostic for each filesystem that lacks free space, naming the
+    ///   entries that need it.
+    pub fn check(&self) -> Result<()> {
+        let mut errors = Vec::new();
+        let mut filesystems: BTreeMap<u64, (PathBuf, Vec<&EntryNeeds>)> = BTreeMap::new();
+        for needs in self.needs.iter().filter(|needs| !needs.dirs.is_empty()) {
+            let probes: BTreeSet<&Path> =
+                needs.dirs.iter().map(|dir| existing_ancestor(dir)).collect();
+            for probe in &probes {
+                if let Err(error) = check_writable(probe, "Directory") {
+                    errors.push(anyhow!("Cannot deploy {:?}: {error}", needs.name));
+                }
+            }
+
+            if let Some(probe) = probes.first() {
+                let (_, entries) = filesystems
+                    .entry(device(probe))
+                    .or_insert_with(|| (probe.to_path_buf(), Vec::new()));
+                entries.push(needs);
+            }
+        }
+
+        for (probe, entries) in filesystems.values() {
+            let bytes: u64 = entries.iter().map(|needs| needs.bytes).sum();
+            let Some(free) = free_space(probe) else {
+                continue;
+            };
+            debug!("Deployment checks out {bytes} bytes onto {probe:?}, {free} bytes are free");
+
+            if bytes > free {
+                let wanted = entries
+                    .iter()
+                    .map(|needs| format!("{:?} needs {}", needs.name, HumanBytes(needs.bytes)))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                errors.push(anyhow!(
+                    "Not enough free space on filesystem of {probe:?}, only {} available: {wanted}",
+                    HumanBytes(free)
+                ));
+            }
+        }
+
+        errors.into_iter().map(Err).bcollect::<Vec<()>>()?;
+        Ok(())
+    }
+}
+
+// INVARIANT: Only files missing from the working directory alias get checked out.
+fn entry_needs(
+    entry: &RepoEntry,
+    excluded: &SparseCheckout,
+    action: DeployAction,
+) -> Result<EntryNeeds> {
+    let mut needs = EntryNeeds { name: entry.name.clone(), ..Default::default() };
+    let state = match action {
+        DeployAction::Deploy => DeployState::WithoutExcluded,
+        DeployAction::DeployAll => DeployState::WithExcluded,
+        DeployAction::Undeploy | DeployAction::UndeployExcludes => return Ok(needs),
+    };
+
+    let missing = scan_deployment(entry, excluded, state, false)?.missing;
+    if missing.is_empty() {
+        return Ok(needs);
+    }
+
+    let blobs: HashMap<PathBuf, _> = list_file_blobs(entry)?.into_iter().collect();
+    let odb = entry.repository.odb()?;
+    for path in missing {
+        if let Some(oid) = blobs.get(&path) {
+            needs.bytes += odb.read_header(*oid).map(|(size, _)| size as u64).unwrap_or_default();
+        }
+        if let Some(parent) = entry.work_dir_alias.0.join(&path).parent() {
+            needs.dirs.insert(parent.to_path_buf());
+        }
+    }
+
+    Ok(needs)
+}
+
+fn existing_ancestor(path: &Path) -> &Path {
+    path.ancestors().find(|ancestor| ancestor.exists()).unwrap_or(path)
+}
+
+#[cfg(unix)]
+fn device(path: &Path) -> u64 {
+    use std::os::unix::fs::MetadataExt;
+
+    path.metadata().map(|metadata| metadata.dev()).unwrap_or_default()
+}
+
+#[cfg(not(unix))]
+fn device(_: &Path) -> u64 {
+    0
+}
+
+#[cfg(unix)]
+fn free_space(path: &Path) -> Option<u64> {
+    use std::{ffi::CString, os::unix::ffi::OsStrExt};
+
+    let path = CString::new(path.as_os_str().as_bytes()).ok()?;
+    // SAFETY: statvfs is plain old data, which statvfs(3) fills in.
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    // SAFETY: Path is a valid C string, and stat is valid for the duration of the call.
+    if unsafe { libc::statvfs(path.as_ptr(), &mut stat) } != 0 {
+        return None;
+    }
+
+    #[allow(clippy::unnecessary_cast)]
+    Some(stat.f_bavail as u64 * stat.f_frsize as u64)
+}
+
+// INVARIANT: Filesystems whose free space cannot be determined are assumed to have enough.
+#[cfg(not(unix))]
+fn free_space(_: &Path) -> Option<u64> {
+    None
+}
@@ -0,0 +1,213 @@
+// SPDX-FileCopyrightText: 2025 Jason Pena <jasonpena@awkless.com>
+// SPDX-License-Identifier: MIT
+
+//! Templates.
+//!
+//! Some dotfiles differ slightly between machines, e.g., by the email address in `.gitconfig`,
+//! which would otherwise need a branch or node of their own per machine. Nodes can track such
+//! files as templates instead, matching them through their `templates` setting. Templates are
+//! never checked out verbatim. Deployments render each of them next to where it would have been
+//! deployed, minus its ".tmpl" extension, replacing `{{ vars.NAME }}` placeholders with the
+//! template variables of the cluster. Undeployments remove rendered files again. Rendered files
+//! are kept out of commits through `info/exclude` of their node.
+
+use super::{
+    layout::prune_empty_parents, list_file_blobs, secrets::deployed_dir, DeployAction, RepoEntry,
+    SparseCheckout, SparsityRules,
+};
+
+use crate::model::cluster::interpolate_vars;
+
+use anyhow::{anyhow, Context, Result};
+use beau_collector::BeauCollector as _;
+use std::{
+    collections::BTreeMap,
+    fs::{create_dir_all, remove_file},
+    path::{Path, PathBuf},
+};
+use tracing::{debug, info, instrument};
+
+/// Extension that marks files as templates.
+const TEMPLATE_EXTENSION: &str = ".tmpl";
+
+/// Render templates of repository entry that deployment action deploys.
+///
+/// Returns the number of rendered files that were written. Rendered files that are already up to
+/// date are left alone.
+///
+/// # Errors
+///
+/// - Will fail with a diagnostic for each template that lacks the ".tmpl" extension, refers to
+///   undefined variables, or cannot be written.
+#[instrument(skip(entry, templates, excluded, vars), fields(node = %entry.name), level = "debug")]
+pub(crate) fn render_templates(
+    entry: &RepoEntry,
+    templates: &[String],
+    excluded: &SparseCheckout,
+    vars: &BTreeMap<String, String>,
+    action: DeployAction,
+) -> Result<usize> {
+    let Some(dir) = deployed_dir(entry) else {
+        return Ok(0);
+    };
+    if templates.is_empty() || entry.is_empty()? {
+        return Ok(0);
+    }
+
+    let mut rendered = 0;
+    let mut results = Vec::new();
+    for (template, oid) in selected(entry, templates, excluded, action)? {
+        let result = rendered_path(&template).and_then(|plain| {
+            let blob = entry.repository.find_blob(oid)?;
+            render(entry, &dir, &template, &plain, blob.content(), vars)
+        });
+        match result {
+            Ok(written) => {
+                rendered += usize::from(written);
+                results.push(Ok(()));
+            }
+            Err(error) => results.push(Err(error)),
+        }
+    }
+    results.into_iter().bcollect::<Vec<()>>()?;
+
+    Ok(rendered)
+}
+
+/// Remove rendered files of repository entry that deployment action undeploys.
+///
+/// Returns the number of rendered files that were removed.
+///
+/// # Errors
+///
+/// - Will fail with a diagnostic for each rendered file that cannot be removed.
+#[instrument(skip(entry, templates, excluded), fields(node = %entry.name), level = "debug")]
+pub(crate) fn remove_rendered(
+    entry: &RepoEntry,
+    templates: &[String],
+    excluded: &SparseCheckout,
+    action: DeployAction,
+) -> Result<usize> {
+    let Some(dir) = deployed_dir(entry) else {
+        return Ok(0);
+    };
+    if templates.is_empty() || entry.is_empty()? {
+        return Ok(0);
+    }
+
+    let mut removed = 0;
+    let mut results = Vec::new();
+    for (template, _) in selected(entry, templates, excluded, action)? {
+        let Ok(plain) = rendered_path(&template) else {
+            continue;
+        };
+        let dest = dir.join(plain);
+        if dest.symlink_metadata().is_err() {
+            continue;
+        }
+
+        match remove_file(&dest) {
+            Ok(()) => {
+                info!("Remove rendered {dest:?} of {:?}", entry.name);
+                prune_empty_parents(&dest, &dir);
+                removed += 1;
+                results.push(Ok(()));
+            }
+            Err(error) => {
+                let error = anyhow::Error::from(error);
+                results.push(Err(error.context(format!("Failed to remove rendered {dest:?}"))));
+            }
+        }
+    }
+    results.into_iter().bcollect::<Vec<()>>()?;
+
+    Ok(removed)
+}
+
+// INVARIANT: Templates are excluded from sparse checkout on top of the exclusion rules of their
+// node, but only the latter decide which templates deploy and excluded-only undeploy actions
+// select, like the sparse checkout of their node would.
+fn selected(
+    entry: &RepoEntry,
+    templates: &[String],
+    excluded: &SparseCheckout,
+    action: DeployAction,
+) -> Result<Vec<(PathBuf, git2::Oid)>> {
+    let matches = SparsityRules::new(templates);
+    let rules = SparsityRules::new(
+        excluded.exclusion_rules.iter().filter(|rule| !templates.contains(rule)),
+    )
+    .including(excluded.inclusion_rules.iter());
+
+    let selected = list_file_blobs(entry)?
+        .into_iter()
+        .filter(|(path, _)| {
+            let path = path.to_string_lossy();
+            let wanted = match action {
+                DeployAction::Deploy => !rules.is_excluded(&path),
+                DeployAction::UndeployExcludes => rules.is_excluded(&path),
+                DeployAction::DeployAll | DeployAction::Undeploy => true,
+            };
+            wanted && matches.is_excluded(&path)
+        })
+        .collect();
+
+    Ok(selected)
+}
+
+fn rendered_path(template: &Path) -> Result<PathBuf> {
+    template
+        .to_str()
+        .and_then(|path| path.strip_suffix(TEMPLATE_EXTENSION))
+        .filter(|path| !path.is_empty() && !path.ends_with('/'))
+        .map(PathBuf::from)
+        .ok_or_else(|| anyhow!("Template {template:?} must end in {TEMPLATE_EXTENSION:?}"))
+}
+
+fn render(
+    entry: &RepoEntry,
+    dir: &Path,
+    template: &Path,
+    plain: &Path,
+    content: &[u8],
+    vars: &BTreeMap<String, String>,
+) -> Result<bool> {
+    let text = std::str::from_utf8(content)
+        .with_context(|| format!("Template {template:?} of {:?} is not UTF-8", entry.name))?;
+    let data = interpolate_vars(text, vars)
+        .with_context(|| format!("Failed to render {template:?} of {:?}", entry.name))?;
+
+    let dest = dir.join(plain);
+    if std::fs::read(&dest).is_ok_and(|current| current == data.as_bytes()) {
+        debug!("Rendered {dest:?} is up to date");
+        return Ok(false);
+    }
+
+    if let Some(parent) = dest.parent() {
+        create_dir_all(parent).with_context(|| format!("Failed to create {parent:?}"))?;
+    }
+    std::fs::write(&dest, data).with_context(|| format!("Failed to write {dest:?}"))?;
+    if !entry.deployment_kind.places_files() {
+        entry.exclude_relative(plain)?;
+    }
+    info!("Render {template:?} of {:?} into {dest:?}", entry.name);
+
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use simple_test_case::test_case;
+
+    #[test_case(".gitconfig.tmpl", Some(".gitconfig"); "dotfile")]
+    #[test_case("dir/config.toml.tmpl", Some("dir/config.toml"); "nested")]
+    #[test_case(".gitconfig", None; "no extension")]
+    #[test_case("dir/.tmpl", None; "extension only")]
+    #[test]
+    fn smoke_rendered_path(template: &str, expect: Option<&str>) {
+        let result = rendered_path(Path::new(template)).ok();
+        assert_eq!(result.as_deref(), expect.map(Path::new));
+    }
+}
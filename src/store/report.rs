@@ -0,0 +1,174 @@
+// SPDX-FileCopyrightText: 2025 Jason Pena <jasonpena@awkless.com>
+// SPDX-License-Identifier: MIT
+
+//! Report of deployed entries.
+//!
+//! Shell initialization scripts may want to branch on which configurations are currently active,
+//! e.g., only source plugin managers whose node is deployed. Root can name a report file through
+//! its `deploy_report` setting, which is regenerated after every command that deploys or undeploys
+//! entries. The report lists every deployed entry with its deployment kind, branch, and commit,
+//! along with the time that entry was last deployed at that commit.
+
+use super::status::unix_now;
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fs::{create_dir_all, read_to_string, write},
+    path::{Path, PathBuf},
+};
+use tracing::{debug, info};
+
+/// Comment leading every report file, such that users know not to bother editing it.
+const REPORT_HEADER: &str = "# Generated by ocd after each deployment, do not edit.\n";
+
+/// Deployed entry of deployment report.
+#[derive(Debug, PartialEq, Eq, Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct DeployedEntry {
+    /// Deployment kind of entry.
+    pub(crate) deployment: String,
+
+    /// Currently active branch of entry.
+    pub(crate) branch: String,
+
+    /// Commit ID that HEAD of entry points to.
+    pub(crate) commit: String,
+
+    /// Entry is deployed along with its excluded files.
+    pub(crate) with_excluded: bool,
+
+    /// Unix timestamp of deployment of entry at its current commit.
+    pub(crate) deployed_at: u64,
+}
+
+/// Deployment report file.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub(crate) struct DeployReport {
+    path: PathBuf,
+    file: ReportFile,
+}
+
+impl DeployReport {
+    /// Load deployment report from path.
+    ///
+    /// # Invariants
+    ///
+    /// - Missing or unreadable report files just start an empty report, because the report is
+    ///   regenerated from scratch anyway.
+    pub(crate) fn load_from(path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        let file = read_to_string(&path)
+            .ok()
+            .and_then(|data| toml::de::from_str::<ReportFile>(&data).ok())
+            .unwrap_or_else(|| {
+                debug!("No usable deployment report at {path:?}, start with empty report");
+                ReportFile::default()
+            });
+
+        Self { path, file }
+    }
+
+    /// Replace deployed entries of report.
+    ///
+    /// # Invariants
+    ///
+    /// - Entries deployed at the same commit as before keep their old deployment timestamp.
+    pub(crate) fn regenerate(
+        &mut self,
+        entries: impl IntoIterator<Item = (String, DeployedEntry)>,
+    ) {
+        let now = unix_now();
+        let previous = std::mem::take(&mut self.file.entries);
+        for (name, mut entry) in entries {
+            entry.deployed_at = match previous.get(&name) {
+                Some(old)
+                    if old.commit == entry.commit && old.with_excluded == entry.with_excluded =>
+                {
+                    old.deployed_at
+                }
+                _ => now,
+            };
+            self.file.entries.insert(name, entry);
+        }
+        self.file.generated_at = now;
+    }
+
+    /// Get deployed entries of report.
+    pub(crate) fn entries(&self) -> &BTreeMap<String, DeployedEntry> {
+        &self.file.entries
+    }
+
+    /// Get path of report file.
+    pub(crate) fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Save report to its report file.
+    ///
+    /// # Errors
+    ///
+    /// - Will fail if report file cannot be written.
+    pub(crate) fn save(&self) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            create_dir_all(parent).with_context(|| format!("Failed to create {parent:?}"))?;
+        }
+
+        let data = format!("{REPORT_HEADER}{}", toml::ser::to_string(&self.file)?);
+        write(&self.path, data).with_context(|| format!("Failed to write {:?}", self.path))?;
+        info!("Write deployment report {:?}", self.path);
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Default, PartialEq, Eq, Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+struct ReportFile {
+    #[serde(default)]
+    generated_at: u64,
+
+    #[serde(default)]
+    entries: BTreeMap<String, DeployedEntry>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use pretty_assertions::assert_eq as pretty_assert_eq;
+    use sealed_test::prelude::*;
+
+    fn entry(commit: &str) -> DeployedEntry {
+        DeployedEntry {
+            deployment: "bare-alias".into(),
+            branch: "main".into(),
+            commit: commit.into(),
+            with_excluded: false,
+            deployed_at: 0,
+        }
+    }
+
+    #[sealed_test]
+    fn smoke_deploy_report_regenerate() -> Result<()> {
+        let path = std::env::current_dir()?.join("report/deployed.toml");
+        let mut report = DeployReport::load_from(&path);
+        report.regenerate([("vim".to_string(), entry("abc")), ("sh".to_string(), entry("abc"))]);
+        report.save()?;
+        assert!(read_to_string(&path)?.starts_with(REPORT_HEADER));
+
+        // Same commit keeps its timestamp, undeployed entries are dropped.
+        let mut report = DeployReport::load_from(&path);
+        let deployed_at = report.entries()["vim"].deployed_at;
+        report.file.entries.get_mut("vim").expect("vim is deployed").deployed_at = 42;
+        report.regenerate([("vim".to_string(), entry("abc"))]);
+        pretty_assert_eq!(report.entries()["vim"].deployed_at, 42);
+        assert!(!report.entries().contains_key("sh"));
+
+        report.regenerate([("vim".to_string(), entry("def"))]);
+        assert!(report.entries()["vim"].deployed_at >= deployed_at);
+
+        Ok(())
+    }
+}
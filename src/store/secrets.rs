@@ -0,0 +1,238 @@
+// SPDX-FileCopyrightText: 2025 Jason Pena <jasonpena@awkless.com>
+// SPDX-License-Identifier: MIT
+
+//! Encrypted secret files.
+//!
+//! SSH keys, tokens, and the like do not belong into repositories in plaintext, but nodes can
+//! track them encrypted with age or GnuPG, listing them through their `encrypted` setting.
+//! Deployments decrypt each of them next to where it gets deployed, minus its ".age", ".gpg", or
+//! ".asc" extension, readable by the user only. Undeployments shred decrypted copies again, i.e.,
+//! overwrite them before removing them, such that no plaintext lingers in the working directory
+//! alias. Decrypted copies are kept out of commits through `info/exclude` of their node.
+
+use super::{
+    layout::prune_empty_parents, list_file_blobs, DeployAction, RepoEntry, SparseCheckout,
+};
+
+use crate::model::{
+    cluster::DeploymentKind,
+    secret::{decrypt_file, decrypted_path},
+};
+
+use anyhow::{anyhow, Context, Result};
+use beau_collector::BeauCollector as _;
+use git2::Oid;
+use std::{
+    collections::HashMap,
+    fs::{create_dir_all, remove_file, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+};
+use tracing::{debug, info, instrument};
+
+/// Decrypt encrypted files of repository entry that deployment action deploys.
+///
+/// Returns the number of decrypted copies that were written. Decrypted copies that are already
+/// up to date are left alone.
+///
+/// # Errors
+///
+/// - Will fail with a diagnostic for each encrypted file that is not tracked, or cannot be
+///   decrypted or written.
+#[instrument(skip(entry, secrets, excluded), fields(node = %entry.name), level = "debug")]
+pub(crate) fn reveal_secrets(
+    entry: &RepoEntry,
+    secrets: &[String],
+    excluded: &SparseCheckout,
+    action: DeployAction,
+) -> Result<usize> {
+    let Some(dir) = deployed_dir(entry) else {
+        return Ok(0);
+    };
+    if secrets.is_empty() || entry.is_empty()? {
+        return Ok(0);
+    }
+
+    let blobs: HashMap<PathBuf, Oid> = list_file_blobs(entry)?.into_iter().collect();
+    let mut revealed = 0;
+    let mut results = Vec::new();
+    for (secret, plain) in selected(secrets, excluded, action) {
+        let Some(oid) = blobs.get(Path::new(secret)) else {
+            results
+                .push(Err(anyhow!("Encrypted file {secret:?} of {:?} is not tracked", entry.name)));
+            continue;
+        };
+
+        match reveal(entry, &dir, secret, plain, *oid) {
+            Ok(written) => {
+                revealed += usize::from(written);
+                results.push(Ok(()));
+            }
+            Err(error) => results.push(Err(error)),
+        }
+    }
+    results.into_iter().bcollect::<Vec<()>>()?;
+
+    Ok(revealed)
+}
+
+/// Shred decrypted copies of encrypted files of repository entry that deployment action
+/// undeploys.
+///
+/// Returns the number of decrypted copies that were shredded.
+///
+/// # Errors
+///
+/// - Will fail with a diagnostic for each decrypted copy that cannot be shredded.
+#[instrument(skip(entry, secrets, excluded), fields(node = %entry.name), level = "debug")]
+pub(crate) fn shred_secrets(
+    entry: &RepoEntry,
+    secrets: &[String],
+    excluded: &SparseCheckout,
+    action: DeployAction,
+) -> Result<usize> {
+    let Some(dir) = deployed_dir(entry) else {
+        return Ok(0);
+    };
+
+    let mut shredded = 0;
+    let mut results = Vec::new();
+    for (_, plain) in selected(secrets, excluded, action) {
+        let dest = dir.join(plain);
+        if dest.symlink_metadata().is_err() {
+            continue;
+        }
+
+        match shred(&dest) {
+            Ok(()) => {
+                info!("Shred decrypted {dest:?} of {:?}", entry.name);
+                prune_empty_parents(&dest, &dir);
+                shredded += 1;
+                results.push(Ok(()));
+            }
+            Err(error) => results.push(Err(error.context(format!("Failed to shred {dest:?}")))),
+        }
+    }
+    results.into_iter().bcollect::<Vec<()>>()?;
+
+    Ok(shredded)
+}
+
+// INVARIANT: Deploy and excluded-only undeploy actions respect exclusion rules, like the sparse
+// checkout of their node does.
+fn selected<'a>(
+    secrets: &'a [String],
+    excluded: &SparseCheckout,
+    action: DeployAction,
+) -> impl Iterator<Item = (&'a str, &'a str)> {
+    let rules = excluded.rules();
+    secrets.iter().filter_map(move |secret| {
+        let wanted = match action {
+            DeployAction::Deploy => !rules.is_excluded(secret),
+            DeployAction::UndeployExcludes => rules.is_excluded(secret),
+            DeployAction::DeployAll | DeployAction::Undeploy => true,
+        };
+        let plain = decrypted_path(secret)?;
+        wanted.then_some((secret.as_str(), plain))
+    })
+}
+
+/// Directory that files of repository entry get deployed into, if any.
+///
+/// # Invariants
+///
+/// - Only directories that Git or the user sees are given, i.e., never the internal checkout of
+///   nodes placing files themselves.
+pub(super) fn deployed_dir(entry: &RepoEntry) -> Option<PathBuf> {
+    match entry.deployment_kind {
+        DeploymentKind::Normal => entry.repository.workdir().map(Path::to_path_buf),
+        DeploymentKind::BareAlias
+        | DeploymentKind::HardLink
+        | DeploymentKind::Symlink
+        | DeploymentKind::Copy => Some(entry.work_dir_alias.0.clone()),
+        DeploymentKind::Inline | DeploymentKind::Mirror | DeploymentKind::Cluster => None,
+    }
+}
+
+fn reveal(entry: &RepoEntry, dir: &Path, secret: &str, plain: &str, oid: Oid) -> Result<bool> {
+    let blob = entry.repository.find_blob(oid)?;
+    let data = decrypt_file(secret, blob.content())
+        .with_context(|| format!("Failed to decrypt {secret:?} of {:?}", entry.name))?;
+
+    let dest = dir.join(plain);
+    if std::fs::read(&dest).is_ok_and(|current| current == data) {
+        debug!("Decrypted {dest:?} is up to date");
+        return Ok(false);
+    }
+
+    if let Some(parent) = dest.parent() {
+        create_dir_all(parent).with_context(|| format!("Failed to create {parent:?}"))?;
+    }
+    write_private(&dest, &data).with_context(|| format!("Failed to write {dest:?}"))?;
+    if !entry.deployment_kind.places_files() {
+        entry.exclude_relative(Path::new(plain))?;
+    }
+    info!("Decrypt {secret:?} of {:?} into {dest:?}", entry.name);
+
+    Ok(true)
+}
+
+fn write_private(path: &Path, data: &[u8]) -> Result<()> {
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+
+        options.mode(0o600);
+        let mut file = options.open(path)?;
+        file.set_permissions(std::fs::Permissions::from_mode(0o600))?;
+        file.write_all(data)?;
+    }
+    #[cfg(not(unix))]
+    options.open(path)?.write_all(data)?;
+
+    Ok(())
+}
+
+// INVARIANT: Symlinks are removed without overwriting whatever they point to.
+fn shred(path: &Path) -> Result<()> {
+    let metadata = path.symlink_metadata()?;
+    if metadata.is_file() {
+        let mut file = OpenOptions::new().write(true).open(path)?;
+        let zeros = [0u8; 4096];
+        let mut left = metadata.len();
+        while left > 0 {
+            let chunk = left.min(zeros.len() as u64) as usize;
+            file.write_all(&zeros[..chunk])?;
+            left -= chunk as u64;
+        }
+        file.sync_all()?;
+    }
+    remove_file(path)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use sealed_test::prelude::*;
+
+    #[sealed_test]
+    fn smoke_shred() -> Result<()> {
+        let path = std::env::current_dir()?.join("token");
+        write_private(&path, b"hunter2")?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(path.metadata()?.permissions().mode() & 0o777, 0o600);
+        }
+
+        shred(&path)?;
+        assert!(!path.exists());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,184 @@
+// SPDX-FileCopyrightText: 2025 Jason Pena <jasonpena@awkless.com>
+// SPDX-License-Identifier: MIT
+
+//! Cached entry status.
+//!
+//! Shell prompts ask for the status of a cluster every time they are drawn, so that status needs
+//! to be cheap to obtain. Scanning the working directory alias of an entry for modifications is
+//! the expensive part. Thus, the status of each entry is cached in OCD's state directory along
+//! with a fingerprint of its repository. Cached status is reused as long as the fingerprint of an
+//! entry still matches, and the cached status is not older than [`STATUS_TTL`].
+
+use crate::model::state_dir;
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fs::{create_dir_all, read_to_string, write},
+    path::PathBuf,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use tracing::{debug, instrument};
+
+/// Longest amount of time cached status of an entry is trusted for.
+///
+/// Fingerprints catch commits, staging, and fetches right away, but not modifications made to the
+/// working directory alias itself. This puts an upper bound on how stale those can get.
+pub(crate) const STATUS_TTL: Duration = Duration::from_secs(10);
+
+/// Status of a repository entry.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy, Deserialize, Serialize)]
+pub(crate) struct EntryStatus {
+    /// Entry has modifications that were not committed yet.
+    pub(crate) dirty: bool,
+
+    /// Number of commits that entry is ahead of its upstream branch.
+    pub(crate) ahead: usize,
+}
+
+/// Cache of entry status in OCD's state directory.
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub(crate) struct StatusCache {
+    path: PathBuf,
+    entries: BTreeMap<String, CachedStatus>,
+}
+
+impl StatusCache {
+    /// Load status cache from state directory.
+    ///
+    /// # Errors
+    ///
+    /// - Will fail if state directory cannot be determined.
+    #[instrument(level = "debug")]
+    pub(crate) fn load() -> Result<Self> {
+        Ok(Self::load_from(state_dir()?.join("status.toml")))
+    }
+
+    /// Load status cache from specific path.
+    ///
+    /// # Invariants
+    ///
+    /// - Missing or unreadable cache files just start an empty cache, because everything in it
+    ///   can be recomputed.
+    pub(crate) fn load_from(path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        let entries = read_to_string(&path)
+            .ok()
+            .and_then(|data| toml::de::from_str::<StatusFile>(&data).ok())
+            .map(|file| file.entries)
+            .unwrap_or_else(|| {
+                debug!("No usable status cache at {path:?}, start with empty cache");
+                BTreeMap::new()
+            });
+
+        Self { path, entries }
+    }
+
+    /// Get status of entry, computing it through `status` if cached status is missing or stale.
+    ///
+    /// # Errors
+    ///
+    /// - Will fail if status needs to be computed, but cannot be.
+    pub(crate) fn get_or_update(
+        &mut self,
+        name: &str,
+        fingerprint: &str,
+        status: impl FnOnce() -> Result<EntryStatus>,
+    ) -> Result<EntryStatus> {
+        let now = unix_now();
+        if let Some(cached) = self.entries.get(name) {
+            if cached.fingerprint == fingerprint
+                && now.saturating_sub(cached.checked) < STATUS_TTL.as_secs()
+            {
+                return Ok(cached.status);
+            }
+        }
+
+        debug!("Recompute status of {name:?}");
+        let status = status()?;
+        let cached = CachedStatus { fingerprint: fingerprint.into(), checked: now, status };
+        self.entries.insert(name.into(), cached);
+
+        Ok(status)
+    }
+
+    /// Forget status of every entry not listed in `names`.
+    pub(crate) fn retain<'a>(&mut self, names: impl IntoIterator<Item = &'a str>) {
+        let names: Vec<&str> = names.into_iter().collect();
+        self.entries.retain(|name, _| names.contains(&name.as_str()));
+    }
+
+    /// Forget status of every entry.
+    pub(crate) fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    /// Save status cache to its cache file.
+    ///
+    /// # Errors
+    ///
+    /// - Will fail if cache file cannot be written.
+    pub(crate) fn save(&self) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            create_dir_all(parent).with_context(|| format!("Failed to create {parent:?}"))?;
+        }
+
+        let data = toml::ser::to_string(&StatusFile { entries: self.entries.clone() })?;
+        write(&self.path, data).with_context(|| format!("Failed to write {:?}", self.path))?;
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+struct CachedStatus {
+    fingerprint: String,
+    checked: u64,
+    status: EntryStatus,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+struct StatusFile {
+    #[serde(default)]
+    entries: BTreeMap<String, CachedStatus>,
+}
+
+/// Get current time as Unix timestamp in seconds.
+pub(crate) fn unix_now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|now| now.as_secs()).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use pretty_assertions::assert_eq as pretty_assert_eq;
+    use sealed_test::prelude::*;
+
+    #[sealed_test]
+    fn smoke_status_cache_get_or_update() -> Result<()> {
+        let path = std::env::current_dir()?.join("state/status.toml");
+        let dirty = EntryStatus { dirty: true, ahead: 2 };
+        let mut cache = StatusCache::load_from(&path);
+        pretty_assert_eq!(cache.get_or_update("vim", "abc", || Ok(dirty))?, dirty);
+        cache.save()?;
+
+        // Matching fingerprint reuses cached status, new fingerprint recomputes it.
+        let mut cache = StatusCache::load_from(&path);
+        pretty_assert_eq!(cache.get_or_update("vim", "abc", || unreachable!())?, dirty);
+        let clean = EntryStatus::default();
+        pretty_assert_eq!(cache.get_or_update("vim", "def", || Ok(clean))?, clean);
+
+        cache.retain(["sh"]);
+        pretty_assert_eq!(cache.get_or_update("vim", "def", || Ok(dirty))?, dirty);
+
+        // Corrupt cache files are just ignored.
+        write(&path, "entries = 42")?;
+        pretty_assert_eq!(StatusCache::load_from(&path).entries, BTreeMap::new());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,328 @@
+// SPDX-FileCopyrightText: 2025 Jason Pena <jasonpena@awkless.com>
+// SPDX-License-Identifier: MIT
+
+//! Deployment transactions.
+//!
+//! Deploying the wrong targets can scatter files all over a working directory alias, and
+//! undeploying or removing the wrong targets can take files away that were still in use. Thus,
+//! every run of a command that deploys, undeploys, or removes entries forms one transaction,
+//! which records each file it placed or removed, along with how each entry it touched was
+//! deployed before. Undoing a transaction brings each entry back to how it was deployed before,
+//! in reverse order. Transactions live in OCD's state directory.
+//!
+//! Undoing works like an undo stack. The last few transactions are kept, such that undoing again
+//! reverts the transaction before.
+
+use super::{status::unix_now, DeployAction};
+
+use crate::{cmd::i18n::tr, model::state_dir};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeSet,
+    fs::{create_dir_all, read_to_string, write},
+    path::PathBuf,
+    sync::Mutex,
+};
+use tracing::{debug, instrument, warn};
+
+/// Number of transactions remembered.
+pub(crate) const TRANSACTION_DEPTH: usize = 10;
+
+/// Transaction that deployment actions of this invocation get recorded into, if any.
+static OPEN: Mutex<Option<Transaction>> = Mutex::new(None);
+
+/// How entry was deployed before a step of a transaction.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PriorDeployment {
+    /// None of the files of entry were deployed.
+    Undeployed,
+
+    /// Entry was deployed without its excluded files.
+    Deployed,
+
+    /// Entry was deployed along with its excluded files.
+    DeployedAll,
+}
+
+/// Deployment action on one entry as part of a transaction.
+#[derive(Debug, PartialEq, Eq, Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct TransactionStep {
+    /// Name of entry, with root named "root".
+    pub entry: String,
+
+    /// Deployment action that was done.
+    pub action: DeployAction,
+
+    /// How entry was deployed before.
+    pub prior: PriorDeployment,
+
+    /// Files that deployment action placed, as absolute paths.
+    #[serde(default)]
+    pub placed: Vec<PathBuf>,
+
+    /// Files that deployment action removed, as absolute paths.
+    #[serde(default)]
+    pub removed: Vec<PathBuf>,
+}
+
+impl TransactionStep {
+    /// Construct step out of files of entry before and after deployment action.
+    pub(crate) fn new(
+        entry: impl Into<String>,
+        action: DeployAction,
+        prior: PriorDeployment,
+        before: &[PathBuf],
+        after: &[PathBuf],
+    ) -> Self {
+        let (before, after): (BTreeSet<_>, BTreeSet<_>) =
+            (before.iter().collect(), after.iter().collect());
+        Self {
+            entry: entry.into(),
+            action,
+            prior,
+            placed: after.difference(&before).map(|path| path.to_path_buf()).collect(),
+            removed: before.difference(&after).map(|path| path.to_path_buf()).collect(),
+        }
+    }
+
+    /// Deployment action that brings entry back to how it was deployed before.
+    pub fn inverse(&self) -> DeployAction {
+        match (self.prior, self.action) {
+            (PriorDeployment::Undeployed, _) => DeployAction::Undeploy,
+            (PriorDeployment::DeployedAll, _) => DeployAction::DeployAll,
+            (PriorDeployment::Deployed, DeployAction::DeployAll) => DeployAction::UndeployExcludes,
+            (PriorDeployment::Deployed, _) => DeployAction::Deploy,
+        }
+    }
+}
+
+/// Deployment actions of one run of a command.
+#[derive(Debug, PartialEq, Eq, Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Transaction {
+    /// Name of command that ran, e.g., "deploy".
+    pub command: String,
+
+    /// Unix timestamp of command start.
+    pub started: u64,
+
+    /// Deployment actions in the order they were done.
+    #[serde(default)]
+    pub steps: Vec<TransactionStep>,
+}
+
+impl Transaction {
+    /// Number of seconds since command started.
+    pub fn seconds_ago(&self) -> u64 {
+        unix_now().saturating_sub(self.started)
+    }
+}
+
+/// Guard of transaction that is open for the rest of the invocation.
+///
+/// Commits transaction once dropped, such that transactions of commands that fail halfway can
+/// still be undone.
+#[derive(Debug)]
+pub struct TransactionGuard {
+    _private: (),
+}
+
+impl Drop for TransactionGuard {
+    fn drop(&mut self) {
+        // INVARIANT: Failing to commit transaction never fails the command itself.
+        if let Err(error) = commit_transaction() {
+            warn!("{}", tr!("transaction-record-failure", error = format!("{error:#}")));
+        }
+    }
+}
+
+/// Open new transaction for deployment actions of given command.
+///
+/// Deployment actions of root and nodes get recorded into the transaction until the returned
+/// guard is dropped.
+///
+/// # Panics
+///
+/// - Will panic if mutex guard fails to lock.
+pub fn begin_transaction(command: impl Into<String>) -> TransactionGuard {
+    let transaction =
+        Transaction { command: command.into(), started: unix_now(), steps: Vec::new() };
+    debug!("Begin transaction of {:?}", transaction.command);
+    *OPEN.lock().unwrap() = Some(transaction);
+    TransactionGuard { _private: () }
+}
+
+/// Check if a transaction is open.
+pub(crate) fn is_transaction_open() -> bool {
+    OPEN.lock().is_ok_and(|open| open.is_some())
+}
+
+/// Record step into open transaction, if any.
+///
+/// # Invariants
+///
+/// - Steps that neither placed nor removed anything are dropped, unless they changed whether
+///   excluded files are deployed.
+pub(crate) fn record_step(step: TransactionStep) {
+    let changed_excludes = matches!(
+        (step.prior, step.action),
+        (PriorDeployment::Deployed, DeployAction::DeployAll)
+            | (PriorDeployment::DeployedAll, DeployAction::Deploy | DeployAction::UndeployExcludes)
+    );
+    if step.placed.is_empty() && step.removed.is_empty() && !changed_excludes {
+        return;
+    }
+
+    if let Ok(mut open) = OPEN.lock() {
+        if let Some(transaction) = open.as_mut() {
+            transaction.steps.push(step);
+        }
+    }
+}
+
+// INVARIANT: Transactions without steps are never recorded, such that undo skips over commands
+// that did not change anything.
+fn commit_transaction() -> Result<()> {
+    let Some(transaction) = OPEN.lock().ok().and_then(|mut open| open.take()) else {
+        return Ok(());
+    };
+    if transaction.steps.is_empty() {
+        debug!("Transaction of {:?} changed nothing", transaction.command);
+        return Ok(());
+    }
+
+    let mut log = TransactionLog::load()?;
+    log.push(transaction);
+    log.save()
+}
+
+/// Log of last transactions.
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub(crate) struct TransactionLog {
+    path: PathBuf,
+    file: TransactionFile,
+}
+
+impl TransactionLog {
+    /// Load transaction log from state directory.
+    ///
+    /// # Errors
+    ///
+    /// - Will fail if state directory cannot be determined.
+    #[instrument(level = "debug")]
+    pub(crate) fn load() -> Result<Self> {
+        Ok(Self::load_from(state_dir()?.join("transactions.toml")))
+    }
+
+    /// Load transaction log from specific path.
+    ///
+    /// # Invariants
+    ///
+    /// - Missing or unreadable log files just start an empty log, because losing the log only
+    ///   loses the ability to undo.
+    pub(crate) fn load_from(path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        let file = read_to_string(&path)
+            .ok()
+            .and_then(|data| toml::de::from_str::<TransactionFile>(&data).ok())
+            .unwrap_or_else(|| {
+                debug!("No usable transaction log at {path:?}, start with empty log");
+                TransactionFile::default()
+            });
+
+        Self { path, file }
+    }
+
+    /// Save transaction log to its log file.
+    ///
+    /// # Errors
+    ///
+    /// - Will fail if log file or its parent directory cannot be written.
+    pub(crate) fn save(&self) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            create_dir_all(parent).with_context(|| format!("Failed to create {parent:?}"))?;
+        }
+        let data = toml::ser::to_string(&self.file)?;
+        write(&self.path, data).with_context(|| format!("Failed to write {:?}", self.path))?;
+
+        Ok(())
+    }
+
+    /// Push transaction, keeping only the last [`TRANSACTION_DEPTH`] transactions.
+    pub(crate) fn push(&mut self, transaction: Transaction) {
+        let transactions = &mut self.file.transactions;
+        transactions.push(transaction);
+        if transactions.len() > TRANSACTION_DEPTH {
+            transactions.drain(..transactions.len() - TRANSACTION_DEPTH);
+        }
+    }
+
+    /// Move steps of every transaction on entry over to its new name.
+    pub(crate) fn rename(&mut self, old: impl AsRef<str>, new: impl AsRef<str>) {
+        let steps =
+            self.file.transactions.iter_mut().flat_map(|transaction| &mut transaction.steps);
+        for step in steps.filter(|step| step.entry == old.as_ref()) {
+            step.entry = new.as_ref().to_string();
+        }
+    }
+
+    /// Last transaction, if any.
+    pub(crate) fn last(&self) -> Option<&Transaction> {
+        self.file.transactions.last()
+    }
+
+    /// Forget last transaction, e.g., because it was undone.
+    pub(crate) fn pop(&mut self) -> Option<Transaction> {
+        self.file.transactions.pop()
+    }
+}
+
+#[derive(Debug, Default, PartialEq, Eq, Clone, Deserialize, Serialize)]
+struct TransactionFile {
+    #[serde(default)]
+    transactions: Vec<Transaction>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use pretty_assertions::assert_eq as pretty_assert_eq;
+    use sealed_test::prelude::*;
+
+    #[sealed_test]
+    fn smoke_transaction_log_push() -> Result<()> {
+        let path = std::env::current_dir()?.join("state/transactions.toml");
+        let mut log = TransactionLog::load_from(&path);
+        assert!(log.last().is_none());
+
+        let before = [PathBuf::from("/home/.vimrc"), PathBuf::from("/home/.bashrc")];
+        let after = [PathBuf::from("/home/.vimrc"), PathBuf::from("/home/.vim/colors.vim")];
+        let step = TransactionStep::new(
+            "vim",
+            DeployAction::Deploy,
+            PriorDeployment::Deployed,
+            &before,
+            &after,
+        );
+        pretty_assert_eq!(step.placed, [PathBuf::from("/home/.vim/colors.vim")]);
+        pretty_assert_eq!(step.removed, [PathBuf::from("/home/.bashrc")]);
+        pretty_assert_eq!(step.inverse(), DeployAction::Deploy);
+
+        for started in 0..=TRANSACTION_DEPTH as u64 {
+            log.push(Transaction { command: "deploy".into(), started, steps: vec![step.clone()] });
+        }
+        log.save()?;
+
+        let mut log = TransactionLog::load_from(&path);
+        pretty_assert_eq!(log.file.transactions.len(), TRANSACTION_DEPTH);
+        pretty_assert_eq!(log.pop().map(|transaction| transaction.started), Some(10));
+        pretty_assert_eq!(log.last().map(|transaction| transaction.started), Some(9));
+
+        Ok(())
+    }
+}
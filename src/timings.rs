@@ -0,0 +1,232 @@
+// SPDX-FileCopyrightText: 2025 Jason Pena <jasonpena@awkless.com>
+// SPDX-License-Identifier: MIT
+
+//! Timing report of where an invocation spent its time.
+//!
+//! OCD already wraps its interesting operations in `tracing` spans. Timings are gathered by a
+//! subscriber layer that measures how long those spans live, and sorts them into phases like
+//! configuration parsing or Git operations. Spans that carry a `node` field attribute their time,
+//! and the time of every span nested in them, to that node. Everything else is attributed to the
+//! cluster as a whole.
+//!
+//! Only the outermost span of each phase is measured, such that nested spans of the same phase
+//! are never counted twice. Phases can still overlap each other, e.g., a Git call made during
+//! checkout counts towards both.
+
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Write as _},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use tracing::{
+    field::{Field, Visit},
+    span::{Attributes, Id},
+    Metadata, Subscriber,
+};
+use tracing_subscriber::{layer::Context, registry::LookupSpan, Layer};
+
+/// Phase of an invocation that time can be spent in.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Phase {
+    /// Parsing and checking the cluster definition.
+    Config,
+
+    /// Cloning, fetching, or calling the Git binary.
+    Git,
+
+    /// Running command hooks.
+    Hooks,
+
+    /// Deploying or undeploying entries.
+    Checkout,
+}
+
+impl Phase {
+    const ALL: [Phase; 4] = [Phase::Config, Phase::Git, Phase::Hooks, Phase::Checkout];
+
+    fn of(metadata: &Metadata<'_>) -> Option<Self> {
+        let target = metadata.target();
+        if target.starts_with("ocd::model::cluster") {
+            return Some(Phase::Config);
+        }
+
+        if target.starts_with("ocd::model::hook") {
+            return Some(Phase::Hooks);
+        }
+
+        if target.starts_with("ocd::store") {
+            return match metadata.name() {
+                "clone" | "fetch" | "gitcall_interactive" | "gitcall_non_interactive" => {
+                    Some(Phase::Git)
+                }
+                "deploy_with" => Some(Phase::Checkout),
+                _ => None,
+            };
+        }
+
+        None
+    }
+}
+
+impl fmt::Display for Phase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Phase::Config => write!(f, "config"),
+            Phase::Git => write!(f, "git"),
+            Phase::Hooks => write!(f, "hooks"),
+            Phase::Checkout => write!(f, "checkout"),
+        }
+    }
+}
+
+/// Time spent per node and phase over the whole invocation.
+#[derive(Clone, Debug)]
+pub struct Timings {
+    start: Instant,
+    spent: Arc<Mutex<BTreeMap<String, BTreeMap<Phase, Duration>>>>,
+}
+
+impl Timings {
+    /// Start timing invocation.
+    pub fn new() -> Self {
+        Self { start: Instant::now(), spent: Arc::new(Mutex::new(BTreeMap::new())) }
+    }
+
+    /// Construct subscriber layer that gathers timings of spans.
+    pub fn layer(&self) -> TimingsLayer {
+        TimingsLayer { spent: self.spent.clone() }
+    }
+
+    /// Time spent in given phase by given node, or by the cluster as a whole for "cluster".
+    pub fn spent(&self, node: &str, phase: Phase) -> Duration {
+        let spent = self.spent.lock().unwrap();
+        spent.get(node).and_then(|phases| phases.get(&phase)).copied().unwrap_or_default()
+    }
+
+    /// Render report of total time, and time spent in each phase per node.
+    pub fn report(&self) -> String {
+        let spent = self.spent.lock().unwrap();
+        let width = spent.keys().map(String::len).max().unwrap_or_default();
+        let mut report = format!("Total time: {:.2?}\n", self.start.elapsed());
+        for (node, phases) in spent.iter() {
+            let _ = write!(report, "  {node:<width$}");
+            for phase in Phase::ALL {
+                let time = phases.get(&phase).copied().unwrap_or_default();
+                let _ = write!(report, "  {phase} {time:>9.2?}");
+            }
+            report.push('\n');
+        }
+
+        report
+    }
+}
+
+impl Default for Timings {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Subscriber layer that measures how long spans live.
+#[derive(Debug)]
+pub struct TimingsLayer {
+    spent: Arc<Mutex<BTreeMap<String, BTreeMap<Phase, Duration>>>>,
+}
+
+struct SpanTiming {
+    node: Option<String>,
+    phase: Option<Phase>,
+    start: Option<Instant>,
+}
+
+#[derive(Default)]
+struct NodeVisitor(Option<String>);
+
+impl Visit for NodeVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "node" {
+            self.0 = Some(value.to_string());
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == "node" {
+            self.0 = Some(format!("{value:?}").trim_matches('"').to_string());
+        }
+    }
+}
+
+impl<S> Layer<S> for TimingsLayer
+where
+    S: Subscriber + for<'lookup> LookupSpan<'lookup>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+
+        let mut visitor = NodeVisitor::default();
+        attrs.record(&mut visitor);
+
+        let (parent_node, parent_phase) = span
+            .parent()
+            .and_then(|parent| {
+                parent
+                    .extensions()
+                    .get::<SpanTiming>()
+                    .map(|timing| (timing.node.clone(), timing.phase))
+            })
+            .unwrap_or_default();
+
+        let phase = Phase::of(attrs.metadata()).or(parent_phase);
+
+        // INVARIANT: Only measure outermost span of each phase.
+        let start = (phase.is_some() && phase != parent_phase).then(Instant::now);
+        let node = visitor.0.or(parent_node);
+        span.extensions_mut().insert(SpanTiming { node, phase, start });
+    }
+
+    fn on_close(&self, id: Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(&id) else {
+            return;
+        };
+
+        let extensions = span.extensions();
+        let Some(SpanTiming { node, phase: Some(phase), start: Some(start) }) =
+            extensions.get::<SpanTiming>()
+        else {
+            return;
+        };
+
+        let node = node.clone().unwrap_or_else(|| "cluster".into());
+        let mut spent = self.spent.lock().unwrap();
+        *spent.entry(node).or_default().entry(*phase).or_default() += start.elapsed();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use tracing::{debug_span, subscriber::with_default};
+    use tracing_subscriber::prelude::*;
+
+    #[test]
+    fn smoke_timings_attribute_phase_to_node() {
+        let timings = Timings::new();
+        let subscriber = tracing_subscriber::registry().with(timings.layer());
+        with_default(subscriber, || {
+            let clone = debug_span!(target: "ocd::store", "clone", node = "vim");
+            let _clone = clone.enter();
+            let nested = debug_span!(target: "ocd::store", "gitcall_non_interactive");
+            let _nested = nested.enter();
+            std::thread::sleep(Duration::from_millis(5));
+        });
+
+        let git = timings.spent("vim", Phase::Git);
+        assert!(git >= Duration::from_millis(5));
+        assert_eq!(timings.spent("vim", Phase::Checkout), Duration::ZERO);
+        assert!(timings.report().contains("vim"));
+    }
+}
@@ -0,0 +1,39 @@
+// SPDX-FileCopyrightText: 2025 Jason Pena <jasonpena@awkless.com>
+// SPDX-License-Identifier: MIT
+
+use crate::{GitFixture, GitKind};
+
+use ocd::{
+    cmd::{run_deploy, DeployOptions},
+    model::hook::HookAction,
+};
+
+use anyhow::Result;
+use clap::Parser;
+use sealed_test::prelude::*;
+use std::{fs::write, path::Path};
+
+#[sealed_test(env = [
+    ("XDG_CONFIG_HOME", ".config/ocd"),
+    ("XDG_DATA_HOME", ".local/share/ocd"),
+])]
+fn run_deploy_check_skips_hooks() -> Result<()> {
+    std::env::set_var("HOME", std::env::current_dir()?);
+    std::fs::create_dir_all(".config/ocd/hooks")?;
+    let root = "[settings]\nwork_dir_alias = \"config_dir\"\n";
+    let git = GitFixture::new(".local/share/ocd/root", GitKind::Bare)?;
+    git.stage_and_commit("root.toml", root)?;
+    write(".config/ocd/root.toml", root)?;
+    write(".config/ocd/hooks.toml", "[[hooks.deploy]]\npre = \"pre.sh\"\n")?;
+    write(".config/ocd/hooks/pre.sh", "touch \"$HOME/pre.log\"\n")?;
+
+    let opts = DeployOptions::parse_from(["deploy", "--check", "root"]);
+    let _ = run_deploy(HookAction::Always, opts);
+    assert!(!Path::new("pre.log").exists());
+
+    let opts = DeployOptions::parse_from(["deploy", "root"]);
+    run_deploy(HookAction::Always, opts)?;
+    assert!(Path::new("pre.log").exists());
+
+    Ok(())
+}
@@ -1,5 +1,6 @@
 // SPDX-FileCopyrightText: 2025 Jason Pena <jasonpena@awkless.com>
 // SPDX-License-Identifier: MIT
 
+mod cmd;
 mod model;
 mod store;
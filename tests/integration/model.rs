@@ -2,8 +2,16 @@
 // SPDX-License-Identifier: MIT
 
 use ocd::model::{
-    cluster::{Cluster, DeploymentKind, NodeEntry, RootEntry, WorkDirAlias},
-    home_dir,
+    cache_dir,
+    cluster::{
+        nested_cluster_dir, Cluster, ClusterChanges, DeploymentKind, NodeEntry,
+        NodeEntryDeployment, NodeHooks, NodePin, RootEntry, WorkDirAlias,
+    },
+    config_dir, data_dir, expand_path, home_dir, home_env,
+    hook::{HookAction, HookKind, HookRunner},
+    schema::set_strict,
+    secret::{identity_path, is_encrypted},
+    set_home_dir, state_dir,
 };
 
 use anyhow::Result;
@@ -11,7 +19,11 @@
 use sealed_test::prelude::*;
 use simple_test_case::dir_cases;
 use simple_txtar::Archive;
-use std::{collections::HashMap, fs::write};
+use std::{
+    collections::BTreeMap,
+    fs::write,
+    path::{Path, PathBuf},
+};
 
 fn setup_cluster_env(content: &str) -> Result<()> {
     let pwd = std::env::current_dir()?;
@@ -20,6 +32,9 @@ fn setup_cluster_env(content: &str) -> Result<()> {
 
     let txtar = Archive::from(content);
     for file in txtar.iter() {
+        if let Some(parent) = std::path::Path::new(&file.name).parent() {
+            std::fs::create_dir_all(parent)?;
+        }
         write(&file.name, file.content.as_bytes())?;
     }
 
@@ -41,12 +56,12 @@ fn cluster_new_valid_setup(case: &str, content: &str) -> Result<()> {
         "tests/integration/fixtures/cluster_new_valid_setup/root_only.txtar" => {
             let expect = Cluster {
                 root: RootEntry::builder()?.deploy_to_home_dir()?.build(),
-                nodes: HashMap::default(),
+                nodes: BTreeMap::default(),
             };
             check_cluster_new(expect)?;
         }
         "tests/integration/fixtures/cluster_new_valid_setup/root_and_nodes.txtar" => {
-            let mut nodes = HashMap::new();
+            let mut nodes = BTreeMap::new();
             nodes.insert(
                 "vim".into(),
                 NodeEntry::builder()?
@@ -74,6 +89,332 @@ fn cluster_new_invalid_setup(_: &str, content: &str) -> Result<()> {
     Ok(())
 }
 
+#[sealed_test(env = [("XDG_CONFIG_HOME", ".config/ocd")])]
+fn cluster_new_min_ocd_version() -> Result<()> {
+    let config = "-- .config/ocd/root.toml --\n[settings]\nmin_ocd_version = \"999.0.0\"\n";
+    setup_cluster_env(config)?;
+    let error = Cluster::new().unwrap_err();
+    assert!(error.to_string().contains("Cluster requires ocd >= 999.0.0"));
+
+    let config = format!(
+        "-- .config/ocd/root.toml --\n[settings]\nwork_dir_alias = \"home_dir\"\nmin_ocd_version = \"{}\"\n",
+        env!("CARGO_PKG_VERSION")
+    );
+    setup_cluster_env(&config)?;
+    assert!(Cluster::new().is_ok());
+
+    Ok(())
+}
+
+#[sealed_test(env = [("XDG_CONFIG_HOME", ".config/ocd")])]
+fn cluster_new_strict() -> Result<()> {
+    let config = "-- .config/ocd/root.toml --\n[settings]\nwork_dir_alias = \"home_dir\"\n\
+                  -- .config/ocd/nodes/vim.toml --\n[settings]\ndeployment = \"bare_alias\"\n\
+                  url = \"https://some/vim\"\nexclude = [\"README*\"]\n\
+                  -- .config/ocd/hooks.toml --\n[[hooks.deploy]]\npost = \"true\"\ntargt = \"vim\"\n";
+    setup_cluster_env(config)?;
+    let error = format!("{:#}", Cluster::new().unwrap_err());
+    assert!(
+        error.contains("Unknown key \"settings.exclude\", did you mean \"excluded\"?"),
+        "{error}"
+    );
+    let error = format!("{:#}", HookRunner::new().unwrap_err());
+    assert!(
+        error.contains("Unknown key \"hooks.deploy[0].targt\", did you mean \"target\"?"),
+        "{error}"
+    );
+
+    // INVARIANT: Unknown keys are skipped outside of strict mode.
+    set_strict(false);
+    let cluster = Cluster::new()?;
+    pretty_assert_eq!(cluster.nodes["vim"].settings.excluded, None);
+    assert!(HookRunner::new().is_ok());
+
+    Ok(())
+}
+
+#[sealed_test(env = [("XDG_CONFIG_HOME", ".config/ocd")])]
+fn cluster_from_definition() -> Result<()> {
+    std::env::set_var("HOME", std::env::current_dir()?);
+    let root = "[settings]\nwork_dir_alias = \"home_dir\"\n";
+    let node = |dependency: &str| {
+        format!("[settings]\ndeployment = \"bare_alias\"\nurl = \"https://some/url\"\ndependencies = [\"{dependency}\"]\n")
+    };
+
+    let nodes = BTreeMap::from([("vim".to_string(), node("sh")), ("sh".to_string(), node("vim"))]);
+    let error = format!("{:#}", Cluster::from_definition(root, &nodes).unwrap_err());
+    assert!(error.contains("cycle"), "{error}");
+
+    let nodes = BTreeMap::from([("vim".to_string(), node("sh"))]);
+    assert!(Cluster::from_definition(root, &nodes).is_err());
+
+    let nodes =
+        BTreeMap::from([("vim".to_string(), node("sh").replace("dependencies", "depends"))]);
+    let cluster = Cluster::from_definition(root, &BTreeMap::new())?;
+    assert!(cluster.nodes.is_empty());
+    assert!(Cluster::from_definition(root, &nodes).is_err());
+
+    Ok(())
+}
+
+#[sealed_test(env = [("XDG_CONFIG_HOME", ".config/ocd")])]
+fn cluster_new_metadata() -> Result<()> {
+    let config = "-- .config/ocd/root.toml --\n[settings]\nwork_dir_alias = \"home_dir\"\n\
+                  name = \"dotfiles\"\ndescription = \"Shared workstation setup\"\n\
+                  maintainer = \"Jane Doe <jane@example.com>\"\n";
+    setup_cluster_env(config)?;
+    let expect = Cluster {
+        root: RootEntry::builder()?
+            .deploy_to_home_dir()?
+            .name("dotfiles")
+            .description("Shared workstation setup")
+            .maintainer("Jane Doe <jane@example.com>")
+            .build(),
+        nodes: BTreeMap::default(),
+    };
+    check_cluster_new(expect)?;
+
+    Ok(())
+}
+
+#[sealed_test(env = [("XDG_CONFIG_HOME", ".config/ocd")])]
+fn cluster_new_external_path() -> Result<()> {
+    let root = "-- .config/ocd/root.toml --\n[settings]\nwork_dir_alias = \"home_dir\"\n";
+    let node =
+        |settings: &str| format!("{root}-- .config/ocd/nodes/dots.toml --\n[settings]\n{settings}");
+
+    setup_cluster_env(&node("deployment = \"normal\"\npath = \"~/projects/dots\"\n"))?;
+    let cluster = Cluster::new()?;
+    let home = std::env::current_dir()?;
+    pretty_assert_eq!(cluster.nodes["dots"].settings.path, Some(home.join("projects/dots")));
+    assert!(cluster.nodes["dots"].is_external());
+
+    for settings in [
+        "deployment = \"bare_alias\"\npath = \"/srv/dots\"\n",
+        "deployment = \"normal\"\npath = \"projects/dots\"\n",
+        "deployment = \"normal\"\npath = \"/srv/dots\"\nsource = { type = \"archive\", url = \"/srv/dots.tar.gz\", sha256 = \"0000000000000000000000000000000000000000000000000000000000000000\" }\n",
+    ] {
+        setup_cluster_env(&node(settings))?;
+        assert!(Cluster::new().is_err(), "{settings}");
+    }
+
+    Ok(())
+}
+
+#[sealed_test(env = [("XDG_CONFIG_HOME", ".config/ocd")])]
+fn cluster_host_profiles() -> Result<()> {
+    let node = |name: &str, extra: &str| {
+        format!(
+            "-- .config/ocd/nodes/{name}.toml --\n[settings]\ndeployment = \"normal\"\nurl = \
+             \"https://some/url\"\n{extra}"
+        )
+    };
+    let cluster = |profiles: &str| {
+        format!(
+            "-- .config/ocd/root.toml --\n[settings]\nwork_dir_alias = \"home_dir\"\n{profiles}{}{}{}",
+            node("vim", "dependencies = [\"sh\"]\n"),
+            node("sh", ""),
+            node("work-git", ""),
+        )
+    };
+
+    setup_cluster_env(&cluster(
+        "[profile.laptop]\nnodes = [\"vim\"]\nhosts = [\"laptop-*\"]\n[profile.work]\nnodes = \
+         [\"work-*\"]\n",
+    ))?;
+    let cluster_def = Cluster::new()?;
+
+    // Host profiles are picked by name first, then by hostname.
+    pretty_assert_eq!(cluster_def.pick_profile(Some("work"), Some("laptop-1"))?, Some("work"));
+    pretty_assert_eq!(cluster_def.pick_profile(None, Some("laptop-1"))?, Some("laptop"));
+    pretty_assert_eq!(cluster_def.pick_profile(None, Some("desktop"))?, None);
+    assert!(cluster_def.pick_profile(Some("nope"), None).is_err());
+
+    // Host profiles select their nodes along with their dependencies.
+    let mut selected: Vec<String> = cluster_def.profile_nodes("laptop")?.into_iter().collect();
+    selected.sort();
+    pretty_assert_eq!(selected, vec!["sh".to_string(), "vim".to_string()]);
+
+    for profiles in [
+        "[profile.laptop]\nnodes = [\"emacs\"]\n",
+        "[profile.laptop]\nnodes = [\"[vim\"]\n",
+        "[profile.laptop]\nnodes = [\"vim\"]\nhosts = [\"[laptop\"]\n",
+        "[profile.laptop]\nnode = [\"vim\"]\n",
+    ] {
+        setup_cluster_env(&cluster(profiles))?;
+        assert!(Cluster::new().is_err(), "{profiles}");
+    }
+
+    Ok(())
+}
+
+#[sealed_test(env = [("XDG_CONFIG_HOME", ".config/ocd")])]
+fn cluster_new_pin_check() -> Result<()> {
+    let root = "-- .config/ocd/root.toml --\n[settings]\nwork_dir_alias = \"home_dir\"\n";
+    let node =
+        |settings: &str| format!("{root}-- .config/ocd/nodes/vim.toml --\n[settings]\n{settings}");
+
+    setup_cluster_env(&node(
+        "deployment = \"normal\"\nurl = \"https://some/url\"\ntag = \"v1\"\n",
+    ))?;
+    let cluster = Cluster::new()?;
+    pretty_assert_eq!(cluster.nodes["vim"].pin(), Some(NodePin::Tag("v1".into())));
+
+    for settings in [
+        "deployment = \"normal\"\nurl = \"https://some/url\"\ntag = \"v1\"\nrev = \"abc\"\n",
+        "deployment = \"normal\"\npath = \"/srv/vim\"\nbranch = \"dev\"\n",
+        "deployment = \"mirror\"\nurl = \"https://some/url\"\nrev = \"abc\"\n",
+    ] {
+        setup_cluster_env(&node(settings))?;
+        assert!(Cluster::new().is_err(), "{settings}");
+    }
+
+    Ok(())
+}
+
+#[sealed_test(env = [("XDG_CONFIG_HOME", ".config/ocd")])]
+fn cluster_new_vars() -> Result<()> {
+    let root = "-- .config/ocd/root.toml --\n[settings]\nwork_dir_alias = \"home_dir\"\n\
+                [vars]\nuser = \"jdoe\"\nhost = \"github.com\"\n";
+    let node =
+        |settings: &str| format!("{root}-- .config/ocd/nodes/dots.toml --\n[settings]\n{settings}");
+    let settings = "deployment = \"normal:~/{{ vars.user }}\"\n\
+                    url = \"https://{{vars.host}}/{{ vars.user }}/dots.git\"\n";
+
+    setup_cluster_env(&node(settings))?;
+    let cluster = Cluster::new()?;
+    let home = std::env::current_dir()?;
+    let dots = &cluster.nodes["dots"].settings;
+    pretty_assert_eq!(dots.url, "https://github.com/jdoe/dots.git");
+    pretty_assert_eq!(dots.deployment.work_dir_alias, WorkDirAlias::new(home.join("jdoe")));
+
+    // INVARIANT: Machine-local overrides take precedence over variables of root.
+    write(".config/ocd/vars.local.toml", "[vars]\nuser = \"jane\"\n")?;
+    let cluster = Cluster::new()?;
+    pretty_assert_eq!(cluster.nodes["dots"].settings.url, "https://github.com/jane/dots.git");
+    pretty_assert_eq!(cluster.vars()?["host"], "github.com");
+
+    setup_cluster_env(&node("deployment = \"normal\"\nurl = \"https://{{ vars.nope }}/dots\"\n"))?;
+    let error = Cluster::new().unwrap_err();
+    assert!(format!("{error:#}").contains("\"nope\" is not defined"), "{error:#}");
+
+    Ok(())
+}
+
+#[sealed_test(env = [("XDG_CONFIG_HOME", ".config/ocd")])]
+fn cluster_new_templates() -> Result<()> {
+    let root = "-- .config/ocd/root.toml --\n[settings]\nwork_dir_alias = \"home_dir\"\n\
+                [vars]\nos = \"plan9\"\nemail = \"jdoe@example.com\"\n";
+    let node =
+        |settings: &str| format!("{root}-- .config/ocd/nodes/git.toml --\n[settings]\n{settings}");
+
+    setup_cluster_env(&node(
+        "deployment = \"bare_alias\"\nurl = \"https://some/url\"\ntemplates = [\"*.tmpl\"]\n",
+    ))?;
+    let cluster = Cluster::new()?;
+    pretty_assert_eq!(cluster.nodes["git"].settings.templates, Some(vec!["*.tmpl".into()]));
+
+    // INVARIANT: Cluster variables take precedence over built-in template variables.
+    let vars = cluster.template_vars()?;
+    pretty_assert_eq!(vars["os"], "plan9");
+    pretty_assert_eq!(vars["email"], "jdoe@example.com");
+
+    setup_cluster_env(&node(
+        "deployment = \"mirror\"\nurl = \"https://some/url\"\ntemplates = [\"*.tmpl\"]\n",
+    ))?;
+    let error = Cluster::new().unwrap_err();
+    assert!(format!("{error:#}").contains("can render them"), "{error:#}");
+
+    Ok(())
+}
+
+#[dir_cases("tests/integration/fixtures/cluster_new_nested")]
+#[sealed_test(env = [("XDG_CONFIG_HOME", ".config/ocd")])]
+fn cluster_new_nested(_: &str, content: &str) -> Result<()> {
+    setup_cluster_env(content)?;
+    let cluster = Cluster::new()?;
+    let names: Vec<&str> = cluster.nodes.keys().map(String::as_str).collect();
+    pretty_assert_eq!(names, vec!["sh", "work", "work/lsp", "work/nvim"]);
+
+    let work = &cluster.nodes["work"];
+    pretty_assert_eq!(
+        work.settings.deployment.work_dir_alias,
+        WorkDirAlias::new(nested_cluster_dir("work")?)
+    );
+    pretty_assert_eq!(
+        work.settings.dependencies,
+        Some(vec!["work/lsp".to_string(), "work/nvim".to_string()])
+    );
+    pretty_assert_eq!(
+        cluster.nodes["work/nvim"].settings.dependencies,
+        Some(vec!["work/lsp".to_string()])
+    );
+
+    let mut deps: Vec<&str> = cluster.dependency_iter("sh").map(|(name, _)| name).collect();
+    deps.sort();
+    pretty_assert_eq!(deps, vec!["sh", "work", "work/lsp", "work/nvim"]);
+
+    let mut cluster = cluster;
+    assert!(cluster.set_node_url("work/nvim", "https://other/nvim").is_err());
+
+    Ok(())
+}
+
+#[dir_cases("tests/integration/fixtures/cluster_share")]
+#[sealed_test(env = [("XDG_CONFIG_HOME", ".config/ocd")])]
+fn cluster_share(_: &str, content: &str) -> Result<()> {
+    setup_cluster_env(content)?;
+    let cluster = Cluster::new()?;
+    let expect = Archive::from(content).get("expect.toml").unwrap().content.clone();
+    pretty_assert_eq!(cluster.share()?, expect);
+
+    // Redacted cluster definition must still be valid.
+    let _: toml::Table = toml::de::from_str(&cluster.share()?)?;
+
+    Ok(())
+}
+
+#[dir_cases("tests/integration/fixtures/cluster_new_encrypted")]
+#[sealed_test(env = [("XDG_CONFIG_HOME", ".config/ocd")])]
+fn cluster_new_encrypted(_: &str, content: &str) -> Result<()> {
+    setup_cluster_env(content)?;
+    let pwd = std::env::current_dir()?;
+    std::fs::set_permissions("bin/age", std::os::unix::fs::PermissionsExt::from_mode(0o755))?;
+    let path = std::env::var("PATH").unwrap_or_default();
+    std::env::set_var("PATH", format!("{}:{path}", pwd.join("bin").display()));
+
+    // Should leave encrypted values alone without age identity.
+    std::env::set_var("OCD_AGE_IDENTITY", pwd.join("missing.txt"));
+    let cluster = Cluster::new()?;
+    assert!(is_encrypted(&cluster.nodes["vim"].settings.url));
+
+    std::env::set_var("OCD_AGE_IDENTITY", pwd.join("identity.txt"));
+    let mut cluster = Cluster::new()?;
+    let vim = &cluster.nodes["vim"];
+    pretty_assert_eq!(vim.settings.url, "https://some/vim");
+    pretty_assert_eq!(
+        vim.settings.excluded,
+        Some(vec!["README.md".to_string(), "secrets.vim".to_string()])
+    );
+
+    // Should keep encrypted values encrypted when editing them.
+    cluster.set_node_url("vim", "https://other/vim")?;
+    let removed = cluster.edit_node_excluded("vim", &[], &["README.md".into()])?;
+    assert!(removed);
+    let data = std::fs::read_to_string(".config/ocd/nodes/vim.toml")?;
+    assert!(!data.contains("https://other/vim"), "{data}");
+    assert!(!data.contains("secrets.vim"), "{data}");
+    assert!(!data.contains("README.md"), "{data}");
+    pretty_assert_eq!(cluster.nodes["vim"].settings.url, "https://other/vim");
+
+    let cluster = Cluster::new()?;
+    let vim = &cluster.nodes["vim"];
+    pretty_assert_eq!(vim.settings.url, "https://other/vim");
+    pretty_assert_eq!(vim.settings.excluded, Some(vec!["secrets.vim".to_string()]));
+
+    Ok(())
+}
+
 #[dir_cases("tests/integration/fixtures/cluster_new_acyclic_check")]
 #[sealed_test(env = [("XDG_CONFIG_HOME", ".config/ocd")])]
 fn cluster_new_acyclic_check(case: &str, content: &str) -> Result<()> {
@@ -85,7 +426,8 @@ fn cluster_new_acyclic_check(case: &str, content: &str) -> Result<()> {
             assert!(result.is_ok());
         }
         "tests/integration/fixtures/cluster_new_acyclic_check/depend_self.txtar"
-        | "tests/integration/fixtures/cluster_new_acyclic_check/full_cycle.txtar" => {
+        | "tests/integration/fixtures/cluster_new_acyclic_check/full_cycle.txtar"
+        | "tests/integration/fixtures/cluster_new_acyclic_check/after_cycle.txtar" => {
             let result = Cluster::new();
             assert!(result.is_err());
         }
@@ -141,6 +483,239 @@ fn check_cluster_dependency_iter(target: &str, mut expect: Vec<(String, NodeEntr
     Ok(())
 }
 
+#[dir_cases("tests/integration/fixtures/cluster_dependency_order")]
+#[sealed_test(env = [("XDG_CONFIG_HOME", ".config/ocd")])]
+fn cluster_dependency_order(_: &str, content: &str) -> Result<()> {
+    setup_cluster_env(content)?;
+    let cluster = Cluster::new()?;
+    let order = cluster.dependency_order();
+    pretty_assert_eq!(order.len(), cluster.nodes.len());
+
+    let position = |name: &str| order.iter().position(|n| *n == name).unwrap();
+    for (name, node) in &cluster.nodes {
+        let settings = &node.settings;
+        for dependency in settings.dependencies.iter().chain(&settings.after).flatten() {
+            if cluster.nodes.contains_key(dependency) {
+                assert!(
+                    position(dependency) < position(name),
+                    "{dependency} after {name}: {order:?}"
+                );
+            }
+        }
+    }
+
+    let mut names: Vec<&str> =
+        cluster.nodes.keys().rev().map(String::as_str).chain(["gone"]).collect();
+    names.extend(order.iter().rev());
+    cluster.sort_dependency_order(&mut names, |name| *name);
+    let expect: Vec<&str> = order.iter().copied().chain(["gone"]).collect();
+    pretty_assert_eq!(names, expect);
+
+    Ok(())
+}
+
+#[dir_cases("tests/integration/fixtures/cluster_select_nodes")]
+#[sealed_test(env = [("XDG_CONFIG_HOME", ".config/ocd")])]
+fn cluster_select_nodes(_: &str, content: &str) -> Result<()> {
+    setup_cluster_env(content)?;
+    let cluster = Cluster::new()?;
+    let select = |only: &[&str], skip: &[&str]| -> Result<Vec<String>> {
+        let only = only.iter().map(ToString::to_string).collect();
+        let skip = skip.iter().map(ToString::to_string).collect();
+        let mut selected: Vec<String> = cluster.select_nodes(only, skip)?.into_iter().collect();
+        selected.sort();
+        Ok(selected)
+    };
+
+    pretty_assert_eq!(select(&[], &[])?, vec!["a_vim", "b_plugins", "m_dwm", "z_sh"]);
+    pretty_assert_eq!(select(&["a_*"], &[])?, vec!["a_vim", "b_plugins", "z_sh"]);
+    pretty_assert_eq!(select(&["root", "b_plugins"], &[])?, vec!["b_plugins", "z_sh"]);
+    pretty_assert_eq!(select(&[], &["a_vim"])?, vec!["b_plugins", "m_dwm", "z_sh"]);
+    pretty_assert_eq!(select(&[], &["z_sh", "m_dwm"])?, vec!["a_vim", "b_plugins", "z_sh"]);
+    pretty_assert_eq!(select(&["m_dwm"], &["m_dwm"])?, Vec::<String>::new());
+
+    Ok(())
+}
+
+#[dir_cases("tests/integration/fixtures/cluster_dependency_closure")]
+#[sealed_test(env = [("XDG_CONFIG_HOME", ".config/ocd")])]
+fn cluster_dependency_closure(_: &str, content: &str) -> Result<()> {
+    setup_cluster_env(content)?;
+    let cluster = Cluster::new()?;
+    let closure = |targets: &[&str]| -> Vec<(String, Vec<String>)> {
+        cluster
+            .dependency_closure(targets)
+            .into_iter()
+            .map(|(name, required_by)| (name, required_by.into_iter().collect()))
+            .collect()
+    };
+
+    pretty_assert_eq!(
+        closure(&["bash", "zsh"]),
+        vec![
+            ("bash".into(), vec![]),
+            ("readline".into(), vec!["bash".into(), "zsh".into()]),
+            ("sh".into(), vec!["bash".into()]),
+            ("zsh".into(), vec![]),
+        ]
+    );
+    pretty_assert_eq!(
+        closure(&["root", "sh", "bash"]),
+        vec![
+            ("bash".into(), vec![]),
+            ("readline".into(), vec!["bash".into(), "sh".into()]),
+            ("sh".into(), vec![]),
+        ]
+    );
+    pretty_assert_eq!(closure(&["vim"]), vec![("vim".into(), vec![])]);
+
+    Ok(())
+}
+
+#[dir_cases("tests/integration/fixtures/cluster_dependency_closure")]
+#[sealed_test(env = [("XDG_CONFIG_HOME", ".config/ocd")])]
+fn cluster_dependency_path(_: &str, content: &str) -> Result<()> {
+    setup_cluster_env(content)?;
+    let cluster = Cluster::new()?;
+    pretty_assert_eq!(
+        cluster.dependency_path("bash", "readline"),
+        Some(vec!["bash".into(), "sh".into(), "readline".into()])
+    );
+    pretty_assert_eq!(
+        cluster.dependency_path("zsh", "readline"),
+        Some(vec!["zsh".into(), "readline".into()])
+    );
+    pretty_assert_eq!(cluster.dependency_path("readline", "bash"), None);
+    pretty_assert_eq!(cluster.dependency_path("vim", "readline"), None);
+
+    Ok(())
+}
+
+#[dir_cases("tests/integration/fixtures/cluster_dependency_closure")]
+#[sealed_test(env = [("XDG_CONFIG_HOME", ".config/ocd")])]
+fn cluster_dependents_of(_: &str, content: &str) -> Result<()> {
+    setup_cluster_env(content)?;
+    let cluster = Cluster::new()?;
+    pretty_assert_eq!(cluster.dependents_of("readline"), vec!["sh", "zsh"]);
+    pretty_assert_eq!(cluster.dependents_of("sh"), vec!["bash"]);
+    assert!(cluster.dependents_of("vim").is_empty());
+    assert!(cluster.dependents_of("emacs").is_empty());
+
+    Ok(())
+}
+
+#[dir_cases("tests/integration/fixtures/cluster_dependency_closure")]
+#[sealed_test(env = [("XDG_CONFIG_HOME", ".config/ocd")])]
+fn cluster_changes_to(_: &str, content: &str) -> Result<()> {
+    setup_cluster_env(content)?;
+    let cluster = Cluster::new()?;
+    pretty_assert_eq!(cluster.changes_to(&Cluster::new()?), ClusterChanges::default());
+
+    let root = std::fs::read_to_string(".config/ocd/root.toml")?;
+    let node = |name: &str| std::fs::read_to_string(format!(".config/ocd/nodes/{name}.toml"));
+    let nodes = BTreeMap::from([
+        ("readline".to_string(), node("readline")?),
+        ("sh".to_string(), node("sh")?.replace("normal", "bare_alias")),
+        ("vim".to_string(), node("vim")?),
+        ("emacs".to_string(), node("vim")?),
+    ]);
+    let incoming = Cluster::from_definition(&root, &nodes)?;
+    let changes = cluster.changes_to(&incoming);
+    pretty_assert_eq!(
+        changes,
+        ClusterChanges {
+            root: false,
+            added: vec!["emacs".into()],
+            removed: vec!["bash".into(), "zsh".into()],
+            changed: vec!["sh".into()],
+        }
+    );
+    assert!(!changes.is_empty());
+
+    Ok(())
+}
+
+#[dir_cases("tests/integration/fixtures/cluster_dependency_closure")]
+#[sealed_test(env = [("XDG_CONFIG_HOME", ".config/ocd")])]
+fn cluster_dependency_levels(_: &str, content: &str) -> Result<()> {
+    setup_cluster_env(content)?;
+    let cluster = Cluster::new()?;
+    let expect = BTreeMap::from([
+        ("bash".into(), 2),
+        ("readline".into(), 0),
+        ("sh".into(), 1),
+        ("vim".into(), 0),
+        ("zsh".into(), 1),
+    ]);
+    pretty_assert_eq!(cluster.dependency_levels(), expect);
+
+    Ok(())
+}
+
+#[dir_cases("tests/integration/fixtures/hook_runner_run_post_ordered")]
+#[sealed_test(env = [("XDG_CONFIG_HOME", ".config/ocd")])]
+fn hook_runner_run_post_ordered(_: &str, content: &str) -> Result<()> {
+    setup_cluster_env(content)?;
+    let cluster = Cluster::new()?;
+    let mut hooks = HookRunner::new()?;
+    hooks.set_action(HookAction::Always);
+
+    let targets: Vec<String> = ["bash", "sh", "readline", "vim"].map(Into::into).to_vec();
+    hooks.run_post_ordered("deploy", &targets, &cluster)?;
+    let log = std::fs::read_to_string("order.log")?;
+    let mut order: Vec<&str> = log.lines().collect();
+    order[..2].sort_unstable();
+    pretty_assert_eq!(order, vec!["readline", "vim", "sh", "bash", "all"]);
+
+    Ok(())
+}
+
+#[sealed_test(env = [("XDG_CONFIG_HOME", ".config/ocd")])]
+fn hook_runner_run_node() -> Result<()> {
+    std::env::set_var("HOME", std::env::current_dir()?);
+    let root = "[settings]\nwork_dir_alias = \"home_dir\"\n";
+    let node = |script: &str| {
+        format!("[settings]\ndeployment = \"bare_alias\"\nurl = \"https://some/url\"\n\n[settings.hooks]\npre_deploy = \"{script}\"\n")
+    };
+    let nodes = BTreeMap::from([("vim".to_string(), node("hooks/pre.sh"))]);
+    Cluster::from_definition(root, &nodes)?;
+    for script in ["/etc/pre.sh", "../pre.sh", ""] {
+        let nodes = BTreeMap::from([("vim".to_string(), node(script))]);
+        assert!(Cluster::from_definition(root, &nodes).is_err(), "{script}");
+    }
+
+    std::fs::create_dir_all("alias")?;
+    let work_dir = std::env::current_dir()?.join("alias");
+    let node_hooks = NodeHooks {
+        pre_deploy: Some("hooks/pre.sh".into()),
+        post_deploy: Some("hooks/post.sh".into()),
+    };
+    let read = |script: &Path| -> Result<String> {
+        Ok(match script.to_str() {
+            Some("hooks/pre.sh") => "echo \"$OCD_COMMAND $OCD_TARGETS\" > pre.log\n".into(),
+            _ => "exit 1\n".into(),
+        })
+    };
+
+    let mut hooks = HookRunner::from_definition("")?;
+    hooks.set_action(HookAction::Always);
+    hooks.run_node(HookKind::Pre, "vim", Some(&node_hooks), read, work_dir.clone())?;
+    pretty_assert_eq!(std::fs::read_to_string("alias/pre.log")?, "deploy vim\n");
+
+    // INVARIANT: Only pre-deployment hooks veto deployment of their node.
+    hooks.run_node(HookKind::Post, "vim", Some(&node_hooks), read, work_dir.clone())?;
+    let vetoing = NodeHooks { pre_deploy: Some("hooks/post.sh".into()), post_deploy: None };
+    let error = hooks.run_node(HookKind::Pre, "vim", Some(&vetoing), read, work_dir.clone());
+    assert!(format!("{:#}", error.unwrap_err()).contains("vetoed"));
+
+    // INVARIANT: Hooks of nodes whose working tree is missing are skipped.
+    hooks.run_node(HookKind::Pre, "vim", Some(&vetoing), read, "missing".into())?;
+    hooks.set_action(HookAction::Never);
+    hooks.run_node(HookKind::Pre, "vim", Some(&vetoing), read, work_dir)?;
+
+    Ok(())
+}
+
 #[dir_cases("tests/integration/fixtures/cluster_dependency_iter")]
 #[sealed_test(env = [("XDG_CONFIG_HOME", ".config/ocd")])]
 fn cluster_dependency_iter(case: &str, content: &str) -> Result<()> {
@@ -170,3 +745,312 @@ fn cluster_dependency_iter(case: &str, content: &str) -> Result<()> {
     }
     Ok(())
 }
+
+#[dir_cases("tests/integration/fixtures/cluster_new_inline_check")]
+#[sealed_test(env = [("XDG_CONFIG_HOME", ".config/ocd")])]
+fn cluster_new_inline_check(case: &str, content: &str) -> Result<()> {
+    setup_cluster_env(content)?;
+    match case {
+        "tests/integration/fixtures/cluster_new_inline_check/valid_subdir.txtar" => {
+            let cluster = Cluster::new()?;
+            pretty_assert_eq!(
+                cluster.root.settings.excluded,
+                Some(vec!["README*".into(), ".config/git/".into()])
+            );
+            pretty_assert_eq!(
+                cluster.nodes["git"].settings.deployment.work_dir_alias,
+                cluster.root.settings.work_dir_alias
+            );
+        }
+        "tests/integration/fixtures/cluster_new_inline_check/no_subdir.txtar"
+        | "tests/integration/fixtures/cluster_new_inline_check/escape_root.txtar"
+        | "tests/integration/fixtures/cluster_new_inline_check/subdir_not_inline.txtar" => {
+            let result = Cluster::new();
+            assert!(result.is_err());
+        }
+        &_ => unreachable!("No code for this yet!"),
+    }
+    Ok(())
+}
+
+#[dir_cases("tests/integration/fixtures/cluster_new_work_dir_alias_check")]
+#[sealed_test(env = [("XDG_CONFIG_HOME", ".config/ocd")])]
+fn cluster_new_work_dir_alias_check(case: &str, content: &str) -> Result<()> {
+    setup_cluster_env(content)?;
+    match case {
+        "tests/integration/fixtures/cluster_new_work_dir_alias_check/common_targets.txtar" => {
+            let cluster = Cluster::new()?;
+            pretty_assert_eq!(
+                cluster.nodes["xdg"].settings.deployment.work_dir_alias,
+                WorkDirAlias::new(home_dir()?.join(".config"))
+            );
+        }
+        "tests/integration/fixtures/cluster_new_work_dir_alias_check/inside_data_dir.txtar"
+        | "tests/integration/fixtures/cluster_new_work_dir_alias_check/contains_data_dir.txtar"
+        | "tests/integration/fixtures/cluster_new_work_dir_alias_check/inside_config_dir.txtar" => {
+            let error = Cluster::new().unwrap_err();
+            assert!(format!("{error:?}").contains("OCD's"), "{error:?}");
+        }
+        &_ => unreachable!("No code for this yet!"),
+    }
+    Ok(())
+}
+
+#[sealed_test(env = [("XDG_CONFIG_HOME", ".config/ocd")])]
+fn cluster_add_node() -> Result<()> {
+    setup_cluster_env("-- .config/ocd/root.toml --\n[settings]\nwork_dir_alias = \"home_dir\"\n")?;
+    let mut cluster = Cluster::new()?;
+    let node = NodeEntry::builder()?
+        .deployment(DeploymentKind::BareAlias, WorkDirAlias::new(home_dir()?))
+        .url("https://some/url")
+        .dependencies(["sh"])
+        .build();
+    cluster.add_node("sh", NodeEntry::builder()?.url("https://some/url").build())?;
+    cluster.add_node("bash", node.clone())?;
+    assert!(cluster.add_node("bash", node.clone()).is_err());
+
+    let names: Vec<&String> = cluster.nodes.keys().collect();
+    pretty_assert_eq!(names, vec!["bash", "sh"]);
+    pretty_assert_eq!(Cluster::new()?, cluster);
+
+    let data = std::fs::read_to_string(".config/ocd/nodes/bash.toml")?;
+    let expect = format!(
+        "[settings]\ndeployment = {{ kind = \"bare_alias\", work_dir_alias = {:?} }}\nurl = \
+         \"https://some/url\"\ndependencies = [\"sh\"]\n",
+        home_dir()?.to_string_lossy()
+    );
+    pretty_assert_eq!(data, expect);
+
+    Ok(())
+}
+
+#[sealed_test(env = [("XDG_CONFIG_HOME", ".config/ocd")])]
+fn cluster_set_node_url() -> Result<()> {
+    setup_cluster_env(
+        r#"
+-- .config/ocd/root.toml --
+[settings]
+work_dir_alias = "home_dir"
+
+-- .config/ocd/nodes/vim.toml --
+# Editor of choice.
+[settings]
+deployment = "normal"
+url = "https://old/url"
+"#,
+    )?;
+    let mut cluster = Cluster::new()?;
+    cluster.set_node_url("vim", "https://new/url")?;
+    assert!(cluster.set_node_url("emacs", "https://new/url").is_err());
+    assert!(cluster.set_node_url("vim", "https://new url").is_err());
+
+    pretty_assert_eq!(cluster.nodes["vim"].settings.url, "https://new/url");
+    pretty_assert_eq!(Cluster::new()?, cluster);
+
+    let data = std::fs::read_to_string(".config/ocd/nodes/vim.toml")?;
+    let expect =
+        "# Editor of choice.\n[settings]\ndeployment = \"normal\"\nurl = \"https://new/url\"\n";
+    pretty_assert_eq!(data, expect);
+
+    Ok(())
+}
+
+#[sealed_test(env = [("XDG_CONFIG_HOME", ".config/ocd")])]
+fn cluster_node_metadata_round_trip() -> Result<()> {
+    setup_cluster_env(
+        r#"
+-- .config/ocd/root.toml --
+[settings]
+work_dir_alias = "home_dir"
+
+-- .config/ocd/nodes/vim.toml --
+[settings]
+description = "Editor of choice" # Shown by ocd ls.
+homepage = "https://www.vim.org"
+deployment = "normal"
+url = "https://old/url"
+"#,
+    )?;
+    let mut cluster = Cluster::new()?;
+    let about = Some("Editor of choice <https://www.vim.org>".to_string());
+    pretty_assert_eq!(cluster.nodes["vim"].about(), about);
+
+    cluster.edit_node_excluded("vim", &["README*".into()], &[])?;
+    pretty_assert_eq!(cluster.nodes["vim"].about(), about);
+    pretty_assert_eq!(Cluster::new()?, cluster);
+
+    let data = std::fs::read_to_string(".config/ocd/nodes/vim.toml")?;
+    assert!(data.contains("description = \"Editor of choice\" # Shown by ocd ls.\n"), "{data}");
+    assert!(data.contains("homepage = \"https://www.vim.org\"\n"), "{data}");
+
+    Ok(())
+}
+
+#[sealed_test(env = [("XDG_CONFIG_HOME", ".config/ocd")])]
+fn cluster_edit_node_excluded() -> Result<()> {
+    setup_cluster_env(
+        r#"
+-- .config/ocd/root.toml --
+[settings]
+work_dir_alias = "home_dir"
+
+-- .config/ocd/nodes/vim.toml --
+# Editor of choice.
+[settings]
+deployment = "bare_alias"
+url = "https://some/url"
+excluded = ["README*", "LICENSE"] # Keep home clean.
+"#,
+    )?;
+    let mut cluster = Cluster::new()?;
+    let add = vec!["*.md".to_string(), "LICENSE".to_string()];
+    let remove = vec!["README*".to_string()];
+    assert!(cluster.edit_node_excluded("vim", &add, &remove)?);
+    assert!(!cluster.edit_node_excluded("vim", &add, &remove)?);
+    assert!(cluster.edit_node_excluded("emacs", &add, &remove).is_err());
+
+    let expect = Some(vec!["LICENSE".to_string(), "*.md".to_string()]);
+    pretty_assert_eq!(cluster.nodes["vim"].settings.excluded, expect);
+    pretty_assert_eq!(Cluster::new()?, cluster);
+
+    let data = std::fs::read_to_string(".config/ocd/nodes/vim.toml")?;
+    assert!(data.starts_with("# Editor of choice.\n[settings]\n"));
+    assert!(data.contains("# Keep home clean."));
+
+    let rules = vec!["LICENSE".to_string(), "*.md".to_string()];
+    assert!(cluster.edit_node_excluded("vim", &[], &rules)?);
+    pretty_assert_eq!(cluster.nodes["vim"].settings.excluded, None);
+    assert!(!std::fs::read_to_string(".config/ocd/nodes/vim.toml")?.contains("excluded"));
+
+    Ok(())
+}
+
+#[sealed_test(env = [("XDG_CONFIG_HOME", ".config/ocd")])]
+fn cluster_rename_node() -> Result<()> {
+    setup_cluster_env(
+        r#"
+-- .config/ocd/root.toml --
+[settings]
+work_dir_alias = "home_dir"
+
+[profile.laptop]
+nodes = ["sh", "vim*"] # Bare minimum.
+
+-- .config/ocd/nodes/sh.toml --
+[settings]
+deployment = "normal"
+url = "https://some/url"
+
+-- .config/ocd/nodes/vim.toml --
+[settings]
+deployment = "normal"
+url = "https://some/url"
+dependencies = [
+    "sh", # Needed for plugins.
+]
+"#,
+    )?;
+    let mut cluster = Cluster::new()?;
+    assert!(cluster.rename_node("emacs", "zsh").is_err());
+    assert!(cluster.rename_node("sh", "vim").is_err());
+    assert!(cluster.rename_node("sh", "root").is_err());
+    assert!(cluster.rename_node("sh", "a/b").is_err());
+
+    pretty_assert_eq!(cluster.rename_node("sh", "zsh")?, vec!["vim".to_string()]);
+    assert!(!cluster.nodes.contains_key("sh"));
+    pretty_assert_eq!(cluster.nodes["vim"].settings.dependencies, Some(vec!["zsh".into()]));
+    pretty_assert_eq!(cluster.root.profile["laptop"].nodes, vec!["zsh", "vim*"]);
+    pretty_assert_eq!(Cluster::new()?, cluster);
+
+    assert!(!std::path::Path::new(".config/ocd/nodes/sh.toml").exists());
+    let data = std::fs::read_to_string(".config/ocd/nodes/vim.toml")?;
+    assert!(data.contains("    \"zsh\", # Needed for plugins.\n"), "{data}");
+    let data = std::fs::read_to_string(".config/ocd/root.toml")?;
+    assert!(data.contains("nodes = [\"zsh\", \"vim*\"] # Bare minimum.\n"), "{data}");
+
+    Ok(())
+}
+
+#[sealed_test(env = [("XDG_CONFIG_HOME", ".config/ocd")])]
+fn cluster_set_node_deployment() -> Result<()> {
+    setup_cluster_env(
+        r#"
+-- .config/ocd/root.toml --
+[settings]
+work_dir_alias = "home_dir"
+
+-- .config/ocd/nodes/sh.toml --
+[settings]
+deployment = { kind = "bare_alias", work_dir_alias = "~" } # Shell of choice.
+url = "https://some/url"
+
+-- .config/ocd/nodes/vim.toml --
+# Editor of choice.
+[settings]
+deployment = "normal"
+url = "https://some/url"
+"#,
+    )?;
+    let mut cluster = Cluster::new()?;
+    let bare_alias = NodeEntryDeployment::new(DeploymentKind::BareAlias, WorkDirAlias::new("~"));
+    let normal = NodeEntryDeployment::new(DeploymentKind::Normal, WorkDirAlias::new("~"));
+    cluster.set_node_deployment("vim", &bare_alias)?;
+    cluster.set_node_deployment("sh", &normal)?;
+    assert!(cluster.set_node_deployment("emacs", &normal).is_err());
+
+    let expect =
+        NodeEntryDeployment::new(DeploymentKind::BareAlias, WorkDirAlias::new(home_dir()?));
+    pretty_assert_eq!(cluster.nodes["vim"].settings.deployment, expect);
+    pretty_assert_eq!(Cluster::new()?, cluster);
+
+    let data = std::fs::read_to_string(".config/ocd/nodes/vim.toml")?;
+    let expect =
+        "# Editor of choice.\n[settings]\ndeployment = \"bare_alias:~\"\nurl = \"https://some/url\"\n";
+    pretty_assert_eq!(data, expect);
+
+    let data = std::fs::read_to_string(".config/ocd/nodes/sh.toml")?;
+    assert!(
+        data.contains("{ kind = \"normal\", work_dir_alias = \"~\" } # Shell of choice."),
+        "{data}"
+    );
+
+    Ok(())
+}
+
+#[sealed_test]
+fn transient_dirs_follow_xdg() -> Result<()> {
+    let pwd = std::env::current_dir()?;
+    std::env::set_var("XDG_STATE_HOME", pwd.join(".local/state"));
+    std::env::set_var("XDG_CACHE_HOME", pwd.join(".cache"));
+
+    pretty_assert_eq!(state_dir()?, pwd.join(".local/state/ocd"));
+    pretty_assert_eq!(cache_dir()?, pwd.join(".cache/ocd"));
+
+    Ok(())
+}
+
+#[sealed_test]
+fn set_home_dir_moves_standard_dirs() -> Result<()> {
+    let pwd = std::env::current_dir()?;
+    std::env::set_var("HOME", pwd.join("root"));
+    for var in ["XDG_CONFIG_HOME", "XDG_DATA_HOME", "XDG_STATE_HOME"] {
+        std::env::remove_var(var);
+    }
+    std::env::set_var("XDG_CACHE_HOME", pwd.join("shared/cache"));
+    std::env::set_var("OCD_AGE_IDENTITY", "~/keys.txt");
+
+    let user = pwd.join("user");
+    set_home_dir(&user);
+    pretty_assert_eq!(home_dir()?, user);
+    pretty_assert_eq!(config_dir()?, user.join(".config/ocd"));
+    pretty_assert_eq!(data_dir()?, user.join(".local/share/ocd"));
+    pretty_assert_eq!(state_dir()?, user.join(".local/state/ocd"));
+    pretty_assert_eq!(cache_dir()?, pwd.join("shared/cache/ocd"));
+    pretty_assert_eq!(identity_path()?, user.join("keys.txt"));
+    pretty_assert_eq!(PathBuf::from(expand_path("$HOME/.vimrc")?), user.join(".vimrc"));
+    pretty_assert_eq!(PathBuf::from(expand_path("~/.vimrc")?), user.join(".vimrc"));
+    pretty_assert_eq!(home_env(), Some(("HOME", user)));
+    pretty_assert_eq!(std::env::var("HOME")?, pwd.join("root").to_string_lossy());
+
+    Ok(())
+}
@@ -4,16 +4,33 @@
 use crate::{GitFixture, GitKind};
 
 use ocd::{
-    model::cluster::{Cluster, DeploymentKind, NodeEntry, RootEntry, WorkDirAlias},
-    store::{DeployAction, DeployState, Node, Root},
+    model::cluster::{
+        ClonePolicy, Cluster, DeploymentKind, NodeEntry, NodePin, NodeSource, PullStrategy,
+        RootEntry, StoreLayout, WorkDirAlias,
+    },
+    store::{
+        begin_transaction, check_remote, has_git_binary, is_node_present, is_sync_daemon_running,
+        last_transaction, migrate_store_layout, origin_url, prompt_segment, prune_artifacts,
+        rename_in_store, require_git_binary, set_clone_policy, set_store_layout, stale_artifacts,
+        store_layout_changed, undo_transaction, write_deploy_report, CollisionPolicy, DeployAction,
+        DeployOutcome, DeployPreflight, DeployState, DeploymentState, MultiEntryFetch, Node,
+        PullOutcome, RemoteHealth, Root, SparseCheckoutState, SyncStatus, SyncTrigger,
+        TablizeCluster,
+    },
 };
 
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use run_script::run_script;
 use sealed_test::prelude::*;
 use simple_test_case::dir_cases;
 use simple_txtar::Archive;
-use std::fs::write;
+use std::{
+    collections::BTreeMap,
+    fs::write,
+    os::unix::fs::MetadataExt,
+    path::{Path, PathBuf},
+    time::Duration,
+};
 
 #[sealed_test(env = [("XDG_DATA_HOME", ".local/share/ocd")])]
 fn root_new_init() -> Result<()> {
@@ -77,6 +94,31 @@ fn root_new_clone(_: &str, contents: &str) -> Result<()> {
     Ok(())
 }
 
+#[sealed_test(env = [
+    ("XDG_CONFIG_HOME", ".config/ocd"),
+    ("XDG_DATA_HOME", ".local/share/ocd"),
+])]
+fn root_is_cloned_from() -> Result<()> {
+    let pwd = std::env::current_dir()?;
+    std::fs::create_dir_all(".config/ocd")?;
+    std::env::set_var("HOME", &pwd);
+
+    let git = GitFixture::new("forge/remote_root.git", GitKind::Bare)?;
+    git.stage_and_commit("root.toml", "[settings]\nwork_dir_alias = \"config_dir\"\n")?;
+    assert!(!Root::is_cloned_from("forge/remote_root.git")?);
+
+    let root = Root::new_clone("forge/remote_root.git")?;
+    assert!(Root::is_cloned_from("forge/remote_root.git")?);
+    assert!(Root::is_cloned_from("forge/other_root.git").is_err());
+
+    // Partial clone of root gets removed, such that it can be cloned again.
+    std::fs::remove_file(".config/ocd/root.toml")?;
+    assert!(!Root::is_cloned_from("forge/remote_root.git")?);
+    assert!(!root.path().exists());
+
+    Ok(())
+}
+
 #[dir_cases("tests/integration/fixtures/root_deploy")]
 #[sealed_test(env = [
     ("XDG_CONFIG_HOME", ".config/ocd"),
@@ -186,16 +228,36 @@ fn node_new_init() -> Result<()> {
     std::env::set_var("HOME", std::env::current_dir()?);
 
     let entry = NodeEntry::builder()?.build();
-    let node = Node::new_init("dwm", &entry)?;
+    let node = Node::new_init("dwm", &entry, None)?;
     assert!(node.path().exists());
     assert!(!node.is_bare_alias());
 
     let entry = NodeEntry::builder()?
         .deployment(DeploymentKind::BareAlias, WorkDirAlias::try_default()?)
         .build();
-    let node = Node::new_init("vim", &entry)?;
+    let node = Node::new_init("vim", &entry, Some("trunk"))?;
     assert!(node.path().exists());
     assert!(node.is_bare_alias());
+    let (_, head, _) = run_script!(format!("git --git-dir {:?} symbolic-ref HEAD", node.path()))?;
+    assert_eq!(head.trim(), "refs/heads/trunk");
+
+    Ok(())
+}
+
+#[sealed_test(env = [("XDG_DATA_HOME", ".local/share/ocd")])]
+fn root_new_init_default_branch() -> Result<()> {
+    std::env::set_var("HOME", std::env::current_dir()?);
+    write(".gitconfig", "[init]\n    defaultBranch = develop\n")?;
+
+    let root = Root::new_init(&RootEntry::try_default()?)?;
+    let (_, head, _) = run_script!(format!("git --git-dir {:?} symbolic-ref HEAD", root.path()))?;
+    assert_eq!(head.trim(), "refs/heads/develop");
+
+    // Default branch setting of root wins over Git configuration.
+    let entry = NodeEntry::builder()?.build();
+    let node = Node::new_init("dwm", &entry, Some("trunk"))?;
+    let (_, head, _) = run_script!(format!("git --git-dir {:?} symbolic-ref HEAD", node.path()))?;
+    assert_eq!(head.trim(), "refs/heads/trunk");
 
     Ok(())
 }
@@ -232,6 +294,180 @@ fn node_new_open(_: &str, content: &str) -> Result<()> {
     Ok(())
 }
 
+#[sealed_test(env = [("XDG_DATA_HOME", ".local/share/ocd")])]
+fn node_new_open_clone_policy() -> Result<()> {
+    std::env::set_var("HOME", std::env::current_dir()?);
+    let git = GitFixture::new("forge/bash.git", GitKind::Normal)?;
+    git.stage_and_commit("bashrc", "set -o vi\n")?;
+    let entry = NodeEntry::builder()?.url("forge/bash.git").build();
+
+    // Missing node must never be cloned on demand.
+    set_clone_policy(ClonePolicy::Never);
+    assert!(Node::new_open("bash", &entry).is_err());
+    assert!(!std::path::Path::new(".local/share/ocd/bash").exists());
+
+    set_clone_policy(ClonePolicy::Always);
+    let node = Node::new_open("bash", &entry)?;
+    assert!(node.path().exists());
+
+    // Nodes already in repository store are opened no matter the policy.
+    set_clone_policy(ClonePolicy::Never);
+    assert!(Node::new_open("bash", &entry).is_ok());
+
+    Ok(())
+}
+
+#[sealed_test(env = [
+    ("XDG_CONFIG_HOME", ".config/ocd"),
+    ("XDG_DATA_HOME", ".local/share/ocd"),
+])]
+fn node_new_open_empty_remote() -> Result<()> {
+    let pwd = std::env::current_dir()?;
+    std::env::set_var("HOME", &pwd);
+
+    let _ = GitFixture::new("forge/vim.git", GitKind::Bare)?;
+    let url = format!("{}/forge/vim.git", pwd.display());
+    for kind in [DeploymentKind::Normal, DeploymentKind::BareAlias] {
+        let name = kind.to_string();
+        let entry =
+            NodeEntry::builder()?.deployment(kind, WorkDirAlias::new(&pwd)).url(&url).build();
+        let node = Node::new_open(&name, &entry)?;
+        assert!(node.path().exists());
+        assert_eq!(node.remote_health(), RemoteHealth::Matches);
+
+        let output = run_script::run_script!(format!(
+            "git --git-dir {:?} config --get-regexp '^branch\\.'",
+            node.path()
+        ))?;
+        assert!(output.1.contains(".remote origin"), "{name}: {output:?}");
+        assert!(output.1.contains(".merge refs/heads/"), "{name}: {output:?}");
+    }
+
+    Ok(())
+}
+
+#[sealed_test(env = [
+    ("XDG_CONFIG_HOME", ".config/ocd"),
+    ("XDG_DATA_HOME", ".local/share/ocd"),
+])]
+fn node_new_open_archive_source() -> Result<()> {
+    let pwd = std::env::current_dir()?;
+    std::env::set_var("HOME", &pwd);
+
+    run_script!(
+        r#"
+        mkdir -p release/zsh-theme-1.0/functions release/home
+        echo 'PROMPT="> "' > release/zsh-theme-1.0/.zshrc
+        echo 'echo hi' > release/zsh-theme-1.0/functions/greet
+        chmod +x release/zsh-theme-1.0/functions/greet
+        tar -czf release.tar.gz -C release zsh-theme-1.0
+        "#
+    )?;
+    let (_, digest, _) = run_script!("sha256sum release.tar.gz | cut -d ' ' -f 1")?;
+    let source = |sha256: &str| NodeSource::Archive {
+        url: format!("{}/release.tar.gz", pwd.display()),
+        sha256: sha256.trim().into(),
+    };
+
+    let home = pwd.join("release/home");
+    let entry = NodeEntry::builder()?
+        .deployment(DeploymentKind::BareAlias, WorkDirAlias::new(&home))
+        .source(source(&"0".repeat(64)))
+        .build();
+    assert!(Node::new_open("zsh", &entry).is_err());
+    assert!(!pwd.join(".local/share/ocd/zsh").exists());
+
+    let entry = NodeEntry::builder()?
+        .deployment(DeploymentKind::BareAlias, WorkDirAlias::new(&home))
+        .source(source(&digest))
+        .excluded(["functions/"])
+        .build();
+    let node = Node::new_open("zsh", &entry)?;
+    assert_eq!(node.remote_health(), RemoteHealth::Unchecked);
+    node.deploy(DeployAction::Deploy)?;
+    assert!(home.join(".zshrc").exists());
+    assert!(!home.join("functions/greet").exists());
+
+    node.deploy(DeployAction::DeployAll)?;
+    assert!(home.join("functions/greet").metadata()?.mode() & 0o111 != 0);
+
+    // Installed files of archive go away along with the node, leaving nothing behind.
+    node.deploy(DeployAction::Undeploy)?;
+    assert!(!home.join(".zshrc").exists());
+    assert!(!home.join("functions/greet").exists());
+    node.nuke()?;
+    assert!(!pwd.join(".local/share/ocd/zsh").exists());
+    assert!(is_node_present("zsh", &entry).is_ok_and(|present| !present));
+
+    Ok(())
+}
+
+#[sealed_test(env = [
+    ("XDG_CONFIG_HOME", ".config/ocd"),
+    ("XDG_DATA_HOME", ".local/share/ocd"),
+])]
+fn node_new_open_external() -> Result<()> {
+    let pwd = std::env::current_dir()?;
+    std::env::set_var("HOME", &pwd);
+
+    let entry = NodeEntry::builder()?.path(pwd.join("projects/dots")).build();
+    assert!(Node::new_open("dots", &entry).is_err());
+
+    let git = GitFixture::new("projects/dots", GitKind::Normal)?;
+    git.stage_and_commit(".vimrc", "set number\n")?;
+    let node = Node::new_open("dots", &entry)?;
+    assert!(node.is_external());
+    assert!(!pwd.join(".local/share/ocd/dots").exists());
+
+    // External repositories belong to the user, so nuking the node leaves them alone.
+    node.nuke()?;
+    assert!(node.path().exists());
+
+    Ok(())
+}
+
+#[sealed_test(env = [
+    ("XDG_CONFIG_HOME", ".config/ocd"),
+    ("XDG_DATA_HOME", ".local/share/ocd"),
+])]
+fn store_layout_migrate() -> Result<()> {
+    let pwd = std::env::current_dir()?;
+    std::env::set_var("HOME", &pwd);
+    std::fs::create_dir_all(".config/ocd/nodes")?;
+    write(
+        ".config/ocd/root.toml",
+        "[settings]\nwork_dir_alias = \"home_dir\"\nstore_layout = \"forge\"\n",
+    )?;
+    write(
+        ".config/ocd/nodes/vim.toml",
+        "[settings]\ndeployment = \"bare_alias\"\nurl = \"https://host/user/vim.git\"\n",
+    )?;
+    let _root = GitFixture::new(".local/share/ocd/root", GitKind::Bare)?;
+    let git = GitFixture::new(".local/share/ocd/vim", GitKind::Bare)?;
+    git.stage_and_commit(".vimrc", "set number\n")?;
+
+    let store = pwd.join(".local/share/ocd");
+    let cluster = Cluster::new()?;
+    assert!(!store_layout_changed());
+
+    set_store_layout(StoreLayout::Forge);
+    assert!(store_layout_changed());
+    assert_eq!(migrate_store_layout(&cluster)?, vec!["vim".to_string()]);
+    assert!(!store_layout_changed());
+    assert!(!store.join("vim").exists());
+    let node = Node::new_open("vim", &cluster.nodes["vim"])?;
+    assert_eq!(node.path(), store.join("host/user/vim"));
+
+    // Moving nodes back leaves no empty forge directories behind.
+    set_store_layout(StoreLayout::Name);
+    assert_eq!(migrate_store_layout(&cluster)?, vec!["vim".to_string()]);
+    assert!(!store.join("host").exists());
+    let node = Node::new_open("vim", &cluster.nodes["vim"])?;
+    assert_eq!(node.path(), store.join("vim"));
+
+    Ok(())
+}
+
 #[dir_cases("tests/integration/fixtures/node_deploy")]
 #[sealed_test(env = [
     ("XDG_CONFIG_HOME", ".config/ocd"),
@@ -261,6 +497,105 @@ fn node_deploy_action(_: &str, content: &str) -> Result<()> {
     Ok(())
 }
 
+#[sealed_test(env = [
+    ("XDG_CONFIG_HOME", ".config/ocd"),
+    ("XDG_DATA_HOME", ".local/share/ocd"),
+])]
+fn node_drifted_files() -> Result<()> {
+    let pwd = std::env::current_dir()?;
+    std::fs::create_dir_all(".config/ocd")?;
+    std::env::set_var("HOME", &pwd);
+
+    let git = GitFixture::new(".local/share/ocd/node", GitKind::Bare)?;
+    git.stage_and_commit(".vimrc", "set number\n")?;
+    git.stage_and_commit(".shrc", "set -o vi\n")?;
+    git.stage_and_commit("README.md", "Introduce newcomers.\n")?;
+    let entry = NodeEntry::builder()?
+        .deployment(DeploymentKind::BareAlias, WorkDirAlias::new(&pwd))
+        .excluded(["README*"])
+        .build();
+    let node = Node::new_open("node", &entry)?;
+
+    // Nothing drifts from nodes that were never deployed.
+    assert!(node.drifted_files()?.is_empty());
+
+    node.deploy(DeployAction::Deploy)?;
+    assert!(node.drifted_files()?.is_empty());
+
+    write(".vimrc", "set relativenumber\n")?;
+    std::fs::remove_file(".shrc")?;
+    assert_eq!(node.drifted_files()?, [PathBuf::from(".shrc"), PathBuf::from(".vimrc")]);
+
+    // Excluded files only drift once they were deployed.
+    node.deploy(DeployAction::DeployAll)?;
+    write("README.md", "Welcome newcomers.\n")?;
+    assert_eq!(
+        node.drifted_files()?,
+        [PathBuf::from(".shrc"), PathBuf::from(".vimrc"), PathBuf::from("README.md")]
+    );
+
+    Ok(())
+}
+
+#[sealed_test(env = [
+    ("XDG_CONFIG_HOME", ".config/ocd"),
+    ("XDG_DATA_HOME", ".local/share/ocd"),
+])]
+fn node_read_head_file() -> Result<()> {
+    let pwd = std::env::current_dir()?;
+    std::fs::create_dir_all(".config/ocd")?;
+    std::env::set_var("HOME", &pwd);
+
+    let git = GitFixture::new(".local/share/ocd/node", GitKind::Bare)?;
+    git.stage_and_commit("hooks/pre.sh", "echo pre\n")?;
+    let entry = NodeEntry::builder()?
+        .deployment(DeploymentKind::BareAlias, WorkDirAlias::new(pwd.join("alias")))
+        .build();
+    let node = Node::new_open("node", &entry)?;
+
+    // Hook scripts are read from HEAD, even before node is deployed.
+    assert_eq!(node.read_head_file(Path::new("hooks/pre.sh"))?, "echo pre\n");
+    assert!(node.read_head_file(Path::new("hooks/post.sh")).is_err());
+    assert_eq!(node.work_tree(), pwd.join("alias"));
+
+    Ok(())
+}
+
+#[sealed_test(env = [
+    ("XDG_CONFIG_HOME", ".config/ocd"),
+    ("XDG_DATA_HOME", ".local/share/ocd"),
+])]
+fn node_deploy_outcome() -> Result<()> {
+    let pwd = std::env::current_dir()?;
+    std::fs::create_dir_all(".config/ocd")?;
+    std::env::set_var("HOME", &pwd);
+
+    let git = GitFixture::new(".local/share/ocd/node", GitKind::Bare)?;
+    git.stage_and_commit("vimrc", "set number\n")?;
+    let entry = NodeEntry::builder()?
+        .deployment(DeploymentKind::BareAlias, WorkDirAlias::new(&pwd))
+        .build();
+    let node = Node::new_open("node", &entry)?;
+
+    // Planning never deploys anything.
+    assert_eq!(node.plan(DeployAction::Deploy)?, DeployOutcome::Changed);
+    assert!(!node.is_deployed(DeployState::WithoutExcluded)?);
+
+    assert_eq!(node.deploy(DeployAction::Deploy)?, DeployOutcome::Changed);
+    assert_eq!(node.plan(DeployAction::Deploy)?, DeployOutcome::AlreadyInDesiredState);
+    assert_eq!(node.deploy(DeployAction::Deploy)?, DeployOutcome::AlreadyInDesiredState);
+    assert_eq!(node.plan(DeployAction::Undeploy)?, DeployOutcome::Changed);
+    assert_eq!(node.deploy(DeployAction::Undeploy)?, DeployOutcome::Changed);
+
+    let git = GitFixture::new(".local/share/ocd/normal", GitKind::Normal)?;
+    git.stage_and_commit("dwm.c", "int main(void) { return 0; }\n")?;
+    let node = Node::new_open("normal", &NodeEntry::builder()?.build())?;
+    assert_eq!(node.plan(DeployAction::Deploy)?, DeployOutcome::Skipped);
+    assert_eq!(node.deploy(DeployAction::Deploy)?, DeployOutcome::Skipped);
+
+    Ok(())
+}
+
 #[dir_cases("tests/integration/fixtures/node_deploy")]
 #[sealed_test(env = [
     ("XDG_CONFIG_HOME", ".config/ocd"),
@@ -348,3 +683,1813 @@ fn node_undeploy_excluded_action(_: &str, content: &str) -> Result<()> {
 
     Ok(())
 }
+
+#[dir_cases("tests/integration/fixtures/node_inline_deploy")]
+#[sealed_test(env = [
+    ("XDG_CONFIG_HOME", ".config/ocd"),
+    ("XDG_DATA_HOME", ".local/share/ocd"),
+])]
+fn node_inline_deploy_action(_: &str, content: &str) -> Result<()> {
+    let pwd = std::env::current_dir()?;
+    std::fs::create_dir_all(".config/ocd/nodes")?;
+    std::env::set_var("HOME", &pwd);
+
+    let txtar = Archive::from(content);
+    let git = GitFixture::new(".local/share/ocd/root", GitKind::Bare)?;
+    for file in txtar.iter() {
+        git.stage_and_commit(&file.name, &file.content)?;
+        if file.name.ends_with(".toml") {
+            write(pwd.join(".config/ocd").join(&file.name), file.content.as_bytes())?;
+        }
+    }
+
+    let cluster = Cluster::new()?;
+    let root = Root::new_open(&cluster.root)?;
+    assert!(root.is_deployed(DeployState::WithoutExcluded)?);
+    assert!(!pwd.join(".config/ocd/git/config").exists());
+
+    let node = Node::new_open("git", &cluster.nodes["git"])?;
+    node.deploy(DeployAction::Deploy)?;
+    assert!(node.is_deployed(DeployState::WithoutExcluded)?);
+    assert!(!node.is_deployed(DeployState::WithExcluded)?);
+
+    // Root rewriting its own rules must not clobber inline node rules.
+    root.refresh()?;
+    assert!(node.is_deployed(DeployState::WithoutExcluded)?);
+
+    node.deploy(DeployAction::Undeploy)?;
+    assert!(!node.is_deployed(DeployState::WithoutExcluded)?);
+    assert!(root.is_deployed(DeployState::WithoutExcluded)?);
+
+    Ok(())
+}
+
+#[dir_cases("tests/integration/fixtures/node_inline_deploy")]
+#[sealed_test(env = [
+    ("XDG_CONFIG_HOME", ".config/ocd"),
+    ("XDG_DATA_HOME", ".local/share/ocd"),
+])]
+fn root_deploy_all_keeps_inline_excluded(_: &str, content: &str) -> Result<()> {
+    let pwd = std::env::current_dir()?;
+    std::fs::create_dir_all(".config/ocd/nodes")?;
+    std::env::set_var("HOME", &pwd);
+
+    let txtar = Archive::from(content);
+    let git = GitFixture::new(".local/share/ocd/root", GitKind::Bare)?;
+    for file in txtar.iter() {
+        git.stage_and_commit(&file.name, &file.content)?;
+        if file.name.ends_with(".toml") {
+            write(pwd.join(".config/ocd").join(&file.name), file.content.as_bytes())?;
+        }
+    }
+
+    // Root deployed with excluded files leaves inline nodes that were never deployed alone.
+    let cluster = Cluster::new()?;
+    let root = Root::new_open(&cluster.root)?;
+    root.deploy(DeployAction::DeployAll)?;
+    assert!(root.is_deployed(DeployState::WithExcluded)?);
+    assert!(!pwd.join(".config/ocd/git/config").exists());
+
+    let node = Node::new_open("git", &cluster.nodes["git"])?;
+    node.deploy(DeployAction::Deploy)?;
+    assert!(node.is_deployed(DeployState::WithoutExcluded)?);
+    root.deploy(DeployAction::DeployAll)?;
+    assert!(node.is_deployed(DeployState::WithoutExcluded)?);
+    assert!(!pwd.join(".config/ocd/git/README.md").exists());
+
+    Ok(())
+}
+
+#[dir_cases("tests/integration/fixtures/node_hard_link_deploy")]
+#[sealed_test(env = [
+    ("XDG_CONFIG_HOME", ".config/ocd"),
+    ("XDG_DATA_HOME", ".local/share/ocd"),
+])]
+fn node_hard_link_deploy_action(_: &str, content: &str) -> Result<()> {
+    let pwd = std::env::current_dir()?;
+    std::fs::create_dir_all("home")?;
+    std::env::set_var("HOME", &pwd);
+
+    let txtar = Archive::from(content);
+    let git = GitFixture::new("forge/node.git", GitKind::Bare)?;
+    for file in txtar.iter() {
+        git.stage_and_commit(&file.name, &file.content)?;
+    }
+
+    let home = pwd.join("home");
+    let entry = NodeEntry::builder()?
+        .deployment(DeploymentKind::HardLink, WorkDirAlias::new(&home))
+        .url(format!("{}/forge/node.git", pwd.display()))
+        .excluded(["README*"])
+        .build();
+    let node = Node::new_open("node", &entry)?;
+    let checkout = pwd.join(".local/share/ocd/node");
+
+    node.deploy(DeployAction::Deploy)?;
+    assert!(node.is_deployed(DeployState::WithoutExcluded)?);
+    assert!(!home.join("README.md").exists());
+    for file in [".vimrc", ".vim/colors/theme.vim"] {
+        let (src, dest) = (checkout.join(file).metadata()?, home.join(file).metadata()?);
+        assert_eq!((src.dev(), src.ino()), (dest.dev(), dest.ino()));
+    }
+
+    node.deploy(DeployAction::DeployAll)?;
+    assert!(node.is_deployed(DeployState::WithExcluded)?);
+
+    node.deploy(DeployAction::UndeployExcludes)?;
+    assert!(!home.join("README.md").exists());
+    assert!(node.is_deployed(DeployState::WithoutExcluded)?);
+
+    node.deploy(DeployAction::Undeploy)?;
+    assert!(!node.is_deployed(DeployState::WithoutExcluded)?);
+    assert!(!home.join(".vim").exists());
+    assert!(!pwd.join(".local/state/ocd/manifests/node.toml").exists());
+    assert!(checkout.join(".vimrc").exists());
+
+    Ok(())
+}
+
+#[dir_cases("tests/integration/fixtures/node_hard_link_deploy")]
+#[sealed_test(env = [
+    ("XDG_CONFIG_HOME", ".config/ocd"),
+    ("XDG_DATA_HOME", ".local/share/ocd"),
+])]
+fn node_symlink_deploy_action(_: &str, content: &str) -> Result<()> {
+    let pwd = std::env::current_dir()?;
+    std::fs::create_dir_all("home")?;
+    std::env::set_var("HOME", &pwd);
+
+    let txtar = Archive::from(content);
+    let git = GitFixture::new("forge/node.git", GitKind::Bare)?;
+    for file in txtar.iter() {
+        git.stage_and_commit(&file.name, &file.content)?;
+    }
+    write("home/.bashrc", "set -o vi")?;
+
+    let home = pwd.join("home");
+    let entry = NodeEntry::builder()?
+        .deployment(DeploymentKind::Symlink, WorkDirAlias::new(&home))
+        .url(format!("{}/forge/node.git", pwd.display()))
+        .excluded(["README*"])
+        .build();
+    let node = Node::new_open("node", &entry)?;
+    let checkout = pwd.join(".local/share/ocd/node");
+
+    node.deploy(DeployAction::Deploy)?;
+    assert!(node.is_deployed(DeployState::WithoutExcluded)?);
+    assert!(node.deployment_status(DeployState::WithoutExcluded)?.modified.is_empty());
+    assert!(!home.join("README.md").exists());
+    for file in [".vimrc", ".vim/colors/theme.vim"] {
+        assert_eq!(std::fs::read_link(home.join(file))?, checkout.join(file));
+    }
+
+    node.deploy(DeployAction::DeployAll)?;
+    assert!(node.is_deployed(DeployState::WithExcluded)?);
+
+    // Links replaced by the user are no longer OCD's to remove.
+    std::fs::remove_file(home.join(".vimrc"))?;
+    write(home.join(".vimrc"), "set relativenumber")?;
+    node.deploy(DeployAction::Undeploy)?;
+    assert!(!home.join("README.md").exists());
+    assert!(!home.join(".vim").exists());
+    assert_eq!(std::fs::read_to_string(home.join(".vimrc"))?, "set relativenumber");
+    assert_eq!(std::fs::read_to_string(home.join(".bashrc"))?, "set -o vi");
+    assert!(!pwd.join(".local/state/ocd/manifests/node.toml").exists());
+
+    // Files in the way are never overwritten.
+    assert!(node.deploy(DeployAction::Deploy).is_err());
+    assert_eq!(std::fs::read_to_string(home.join(".vimrc"))?, "set relativenumber");
+
+    Ok(())
+}
+
+#[dir_cases("tests/integration/fixtures/node_hard_link_deploy")]
+#[sealed_test(env = [
+    ("XDG_CONFIG_HOME", ".config/ocd"),
+    ("XDG_DATA_HOME", ".local/share/ocd"),
+])]
+fn node_copy_deploy_action(_: &str, content: &str) -> Result<()> {
+    let pwd = std::env::current_dir()?;
+    std::fs::create_dir_all("home")?;
+    std::env::set_var("HOME", &pwd);
+
+    let txtar = Archive::from(content);
+    let git = GitFixture::new("forge/node.git", GitKind::Bare)?;
+    for file in txtar.iter() {
+        git.stage_and_commit(&file.name, &file.content)?;
+    }
+
+    let home = pwd.join("home");
+    let entry = NodeEntry::builder()?
+        .deployment(DeploymentKind::Copy, WorkDirAlias::new(&home))
+        .url(format!("{}/forge/node.git", pwd.display()))
+        .excluded(["README*"])
+        .build();
+    let node = Node::new_open("node", &entry)?;
+    let checkout = pwd.join(".local/share/ocd/node");
+
+    node.deploy(DeployAction::Deploy)?;
+    assert!(node.is_deployed(DeployState::WithoutExcluded)?);
+    assert!(node.locally_modified(DeployAction::Deploy)?.is_empty());
+    assert!(!home.join("README.md").exists());
+    for file in [".vimrc", ".vim/colors/theme.vim"] {
+        assert!(!home.join(file).symlink_metadata()?.file_type().is_symlink());
+        assert_eq!(
+            std::fs::read_to_string(home.join(file))?,
+            std::fs::read_to_string(checkout.join(file))?
+        );
+    }
+
+    // Edited copies are reported before deployment overwrites them, and never undeployed.
+    write(home.join(".vimrc"), "set relativenumber")?;
+    assert_eq!(node.locally_modified(DeployAction::DeployAll)?, [home.join(".vimrc")]);
+    node.deploy(DeployAction::Undeploy)?;
+    assert!(!home.join(".vim").exists());
+    assert_eq!(std::fs::read_to_string(home.join(".vimrc"))?, "set relativenumber");
+    assert!(!pwd.join(".local/state/ocd/manifests/node.toml").exists());
+
+    Ok(())
+}
+
+#[sealed_test(env = [
+    ("XDG_CONFIG_HOME", ".config/ocd"),
+    ("XDG_DATA_HOME", ".local/share/ocd"),
+])]
+fn node_hard_link_deploy_conflict() -> Result<()> {
+    let pwd = std::env::current_dir()?;
+    std::fs::create_dir_all("home")?;
+    std::env::set_var("HOME", &pwd);
+
+    let git = GitFixture::new("forge/node.git", GitKind::Bare)?;
+    git.stage_and_commit(".vimrc", "set number")?;
+    write("home/.vimrc", "set relativenumber")?;
+
+    let entry = NodeEntry::builder()?
+        .deployment(DeploymentKind::HardLink, WorkDirAlias::new(pwd.join("home")))
+        .url(format!("{}/forge/node.git", pwd.display()))
+        .build();
+    let node = Node::new_open("node", &entry)?;
+
+    assert!(node.deploy(DeployAction::Deploy).is_err());
+    assert_eq!(std::fs::read_to_string("home/.vimrc")?, "set relativenumber");
+
+    Ok(())
+}
+
+#[sealed_test(env = [
+    ("XDG_CONFIG_HOME", ".config/ocd"),
+    ("XDG_DATA_HOME", ".local/share/ocd"),
+])]
+fn node_bare_alias_deploy_collision() -> Result<()> {
+    let pwd = std::env::current_dir()?;
+    std::fs::create_dir_all(".config/ocd")?;
+    std::env::set_var("HOME", &pwd);
+
+    let git = GitFixture::new(".local/share/ocd/node", GitKind::Bare)?;
+    git.stage_and_commit(".vimrc", "set number\n")?;
+    git.stage_and_commit(".bashrc", "set -o vi\n")?;
+    write(".vimrc", "set number\n")?;
+    write(".bashrc", "set -o emacs\n")?;
+
+    let entry = NodeEntry::builder()?
+        .deployment(DeploymentKind::BareAlias, WorkDirAlias::new(&pwd))
+        .build();
+    let mut node = Node::new_open("node", &entry)?;
+
+    // Files with the content of the index are no collision.
+    let error = format!("{:#}", node.deploy(DeployAction::Deploy).unwrap_err());
+    assert!(error.contains(".bashrc") && !error.contains(".vimrc"), "{error}");
+    assert_eq!(std::fs::read_to_string(".bashrc")?, "set -o emacs\n");
+
+    node.set_collisions(CollisionPolicy::Backup);
+    assert_eq!(node.deploy(DeployAction::Deploy)?, DeployOutcome::Changed);
+    assert_eq!(std::fs::read_to_string(".bashrc")?, "set -o vi\n");
+    assert_eq!(std::fs::read_to_string(".bashrc.ocd-backup")?, "set -o emacs\n");
+
+    // Files that sparse checkout skipped do not belong to node either.
+    node.deploy(DeployAction::Undeploy)?;
+    write(".bashrc", "set -o emacs\n")?;
+    node.set_collisions(CollisionPolicy::Abort);
+    assert!(node.deploy(DeployAction::Deploy).is_err());
+
+    node.set_collisions(CollisionPolicy::Force);
+    assert_eq!(node.deploy(DeployAction::Deploy)?, DeployOutcome::Changed);
+    assert_eq!(std::fs::read_to_string(".bashrc")?, "set -o vi\n");
+    assert!(!pwd.join(".bashrc.ocd-backup.1").exists());
+
+    Ok(())
+}
+
+#[sealed_test(env = [
+    ("XDG_CONFIG_HOME", ".config/ocd"),
+    ("XDG_DATA_HOME", ".local/share/ocd"),
+])]
+fn node_encrypted_deploy() -> Result<()> {
+    // INVARIANT: Machines without GnuPG have nothing to decrypt with.
+    if std::process::Command::new("gpg").arg("--version").output().is_err() {
+        return Ok(());
+    }
+
+    let pwd = std::env::current_dir()?;
+    std::fs::create_dir_all(".config/ocd")?;
+    std::env::set_var("HOME", &pwd);
+    std::env::set_var("GNUPGHOME", pwd.join(".gnupg"));
+    run_script!(
+        r#"
+        mkdir -m 700 .gnupg
+        gpg --batch --quiet --passphrase '' --quick-gen-key ocd@ocd default default never 2>&1
+        echo hunter2 | gpg --batch --quiet --armor --encrypt --recipient ocd@ocd > token.asc
+        "#
+    )?;
+    let token = std::fs::read_to_string("token.asc")?;
+
+    let git = GitFixture::new(".local/share/ocd/node", GitKind::Bare)?;
+    git.stage_and_commit(".config/token.asc", &token)?;
+    let entry = NodeEntry::builder()?
+        .deployment(DeploymentKind::BareAlias, WorkDirAlias::new(&pwd))
+        .encrypted([".config/token.asc"])
+        .build();
+    let node = Node::new_open("node", &entry)?;
+
+    // Encrypted files are decrypted next to themselves, readable by the user only.
+    assert_eq!(node.deploy(DeployAction::Deploy)?, DeployOutcome::Changed);
+    assert_eq!(std::fs::read_to_string(".config/token")?, "hunter2\n");
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        assert_eq!(pwd.join(".config/token").metadata()?.permissions().mode() & 0o777, 0o600);
+    }
+    let exclude = std::fs::read_to_string(".local/share/ocd/node/info/exclude")?;
+    assert!(exclude.lines().any(|line| line == "/.config/token"), "{exclude}");
+    assert_eq!(node.deploy(DeployAction::Deploy)?, DeployOutcome::AlreadyInDesiredState);
+
+    // Decrypted files never outlive their deployment.
+    node.deploy(DeployAction::Undeploy)?;
+    assert!(!pwd.join(".config/token").exists());
+    run_script!("gpgconf --kill gpg-agent")?;
+
+    Ok(())
+}
+
+#[sealed_test(env = [
+    ("XDG_CONFIG_HOME", ".config/ocd"),
+    ("XDG_DATA_HOME", ".local/share/ocd"),
+])]
+fn node_template_deploy() -> Result<()> {
+    let pwd = std::env::current_dir()?;
+    std::fs::create_dir_all(".config/ocd")?;
+    std::env::set_var("HOME", &pwd);
+    let git = GitFixture::new(".local/share/ocd/node", GitKind::Bare)?;
+    git.stage_and_commit(".gitconfig.tmpl", "[user]\n\temail = {{ vars.email }}\n")?;
+    git.stage_and_commit(".bashrc", "alias ls='ls --color'\n")?;
+    let entry = NodeEntry::builder()?
+        .deployment(DeploymentKind::BareAlias, WorkDirAlias::new(&pwd))
+        .templates(["*.tmpl"])
+        .build();
+    let mut node = Node::new_open("node", &entry)?;
+    node.set_template_vars(BTreeMap::from([("email".into(), "jdoe@example.com".into())]));
+
+    // Templates are rendered next to themselves instead of being checked out verbatim.
+    assert_eq!(node.deploy(DeployAction::Deploy)?, DeployOutcome::Changed);
+    assert_eq!(std::fs::read_to_string(".gitconfig")?, "[user]\n\temail = jdoe@example.com\n");
+    assert!(!pwd.join(".gitconfig.tmpl").exists());
+    assert!(pwd.join(".bashrc").exists());
+    let exclude = std::fs::read_to_string(".local/share/ocd/node/info/exclude")?;
+    assert!(exclude.lines().any(|line| line == "/.gitconfig"), "{exclude}");
+    assert_eq!(node.deploy(DeployAction::Deploy)?, DeployOutcome::AlreadyInDesiredState);
+
+    node.deploy(DeployAction::Undeploy)?;
+    assert!(!pwd.join(".gitconfig").exists());
+
+    // Templates referring to undefined variables fail to render.
+    node.set_template_vars(BTreeMap::new());
+    let error = node.deploy(DeployAction::Deploy).unwrap_err();
+    assert!(format!("{error:#}").contains("\"email\" is not defined"), "{error:#}");
+
+    Ok(())
+}
+
+#[sealed_test(env = [
+    ("XDG_CONFIG_HOME", ".config/ocd"),
+    ("XDG_DATA_HOME", ".local/share/ocd"),
+])]
+fn node_hard_link_locally_modified() -> Result<()> {
+    let pwd = std::env::current_dir()?;
+    std::fs::create_dir_all("home")?;
+    std::env::set_var("HOME", &pwd);
+
+    let git = GitFixture::new("forge/node.git", GitKind::Bare)?;
+    git.stage_and_commit(".vimrc", "set number")?;
+
+    let entry = NodeEntry::builder()?
+        .deployment(DeploymentKind::HardLink, WorkDirAlias::new(pwd.join("home")))
+        .url(format!("{}/forge/node.git", pwd.display()))
+        .build();
+    let node = Node::new_open("node", &entry)?;
+    node.deploy(DeployAction::Deploy)?;
+    assert!(node.locally_modified(DeployAction::Deploy)?.is_empty());
+
+    // Editors that save by replacing files break hard-links, which would get clobbered.
+    std::fs::remove_file("home/.vimrc")?;
+    write("home/.vimrc", "set relativenumber")?;
+    let modified = std::fs::File::options().write(true).open("home/.vimrc")?;
+    modified.set_modified(std::time::SystemTime::now() + std::time::Duration::from_secs(60))?;
+    assert_eq!(node.locally_modified(DeployAction::Deploy)?, vec![pwd.join("home/.vimrc")]);
+    assert!(node.locally_modified(DeployAction::Undeploy)?.is_empty());
+
+    node.deploy(DeployAction::Deploy)?;
+    assert_eq!(std::fs::read_to_string("home/.vimrc")?, "set number");
+    assert!(node.locally_modified(DeployAction::Deploy)?.is_empty());
+
+    Ok(())
+}
+
+#[sealed_test(env = [
+    ("XDG_CONFIG_HOME", ".config/ocd"),
+    ("XDG_DATA_HOME", ".local/share/ocd"),
+])]
+fn node_excluded_git_parity() -> Result<()> {
+    let pwd = std::env::current_dir()?;
+    std::env::set_var("HOME", &pwd);
+
+    let files = [
+        "README.md",
+        "notes.txt",
+        "build/out.o",
+        "docs/README.md",
+        "docs/guide/intro.md",
+        "docs/guide.md",
+        "src/main.rs",
+        "src/sub/lib.rs",
+        "a/build/out.o",
+        "a/b/c/notes.txt",
+        ".config/nvim/init.lua",
+    ];
+    let forge = GitFixture::new("forge/node.git", GitKind::Bare)?;
+    for file in files {
+        forge.stage_and_commit(file, file)?;
+    }
+
+    // Git decides what bare-alias nodes deploy, and OCD decides what hard-link nodes deploy.
+    let rule_sets: [&[&str]; 8] = [
+        &["README*"],
+        &["/README*"],
+        &["build/"],
+        &["/build/"],
+        &["docs/*.md"],
+        &["**/notes.txt"],
+        &["src/**", ".config/"],
+        &["*.o", "guide"],
+    ];
+    for (index, rules) in rule_sets.iter().enumerate() {
+        let (git_home, ocd_home) =
+            (pwd.join(format!("git{index}")), pwd.join(format!("ocd{index}")));
+        std::fs::create_dir_all(&git_home)?;
+        std::fs::create_dir_all(&ocd_home)?;
+
+        let git = GitFixture::new(format!(".local/share/ocd/git{index}"), GitKind::Bare)?;
+        for file in files {
+            git.stage_and_commit(file, file)?;
+        }
+        let entry = NodeEntry::builder()?
+            .deployment(DeploymentKind::BareAlias, WorkDirAlias::new(&git_home))
+            .excluded(rules.iter().copied())
+            .build();
+        Node::new_open(format!("git{index}"), &entry)?.deploy(DeployAction::Deploy)?;
+
+        let entry = NodeEntry::builder()?
+            .deployment(DeploymentKind::HardLink, WorkDirAlias::new(&ocd_home))
+            .url(format!("{}/forge/node.git", pwd.display()))
+            .excluded(rules.iter().copied())
+            .build();
+        let node = Node::new_open(format!("ocd{index}"), &entry)?;
+        node.deploy(DeployAction::Deploy)?;
+        assert!(node.is_deployed(DeployState::WithoutExcluded)?, "{rules:?}");
+
+        assert_eq!(deployed_files(&ocd_home)?, deployed_files(&git_home)?, "{rules:?}");
+    }
+
+    Ok(())
+}
+
+#[sealed_test(env = [
+    ("XDG_CONFIG_HOME", ".config/ocd"),
+    ("XDG_DATA_HOME", ".local/share/ocd"),
+])]
+fn node_included_git_parity() -> Result<()> {
+    let pwd = std::env::current_dir()?;
+    std::env::set_var("HOME", &pwd);
+
+    let files = [
+        "README.md",
+        "docs/README.md",
+        "docs/guide.md",
+        "src/main.rs",
+        "src/sub/lib.rs",
+        ".config/nvim/init.lua",
+        ".config/nvim/README.md",
+    ];
+    let forge = GitFixture::new("forge/node.git", GitKind::Bare)?;
+    for file in files {
+        forge.stage_and_commit(file, file)?;
+    }
+
+    // Exclusion rules win over inclusion rules, for Git and OCD alike.
+    let rule_sets: [(&[&str], &[&str]); 4] = [
+        (&["src/"], &[]),
+        (&[".config/nvim/", "/README*"], &[]),
+        (&["docs/", ".config/"], &["README*"]),
+        (&["*.md"], &["docs/"]),
+    ];
+    for (index, (included, excluded)) in rule_sets.iter().enumerate() {
+        let (git_home, ocd_home) =
+            (pwd.join(format!("git{index}")), pwd.join(format!("ocd{index}")));
+        std::fs::create_dir_all(&git_home)?;
+        std::fs::create_dir_all(&ocd_home)?;
+
+        let git = GitFixture::new(format!(".local/share/ocd/git{index}"), GitKind::Bare)?;
+        for file in files {
+            git.stage_and_commit(file, file)?;
+        }
+        let entry = NodeEntry::builder()?
+            .deployment(DeploymentKind::BareAlias, WorkDirAlias::new(&git_home))
+            .included(included.iter().copied())
+            .excluded(excluded.iter().copied())
+            .build();
+        Node::new_open(format!("git{index}"), &entry)?.deploy(DeployAction::Deploy)?;
+
+        let entry = NodeEntry::builder()?
+            .deployment(DeploymentKind::HardLink, WorkDirAlias::new(&ocd_home))
+            .url(format!("{}/forge/node.git", pwd.display()))
+            .included(included.iter().copied())
+            .excluded(excluded.iter().copied())
+            .build();
+        let node = Node::new_open(format!("ocd{index}"), &entry)?;
+        node.deploy(DeployAction::Deploy)?;
+        assert!(node.is_deployed(DeployState::WithoutExcluded)?, "{included:?}");
+
+        let deployed = deployed_files(&ocd_home)?;
+        assert!(!deployed.is_empty() && deployed.len() < files.len(), "{included:?}");
+        assert_eq!(deployed, deployed_files(&git_home)?, "{included:?} {excluded:?}");
+    }
+
+    Ok(())
+}
+
+fn deployed_files(dir: &std::path::Path) -> Result<Vec<String>> {
+    let mut files = Vec::new();
+    let mut dirs = vec![dir.to_path_buf()];
+    while let Some(current) = dirs.pop() {
+        for entry in std::fs::read_dir(current)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                dirs.push(path);
+            } else {
+                files.push(path.strip_prefix(dir)?.to_string_lossy().into_owned());
+            }
+        }
+    }
+    files.sort();
+
+    Ok(files)
+}
+
+#[sealed_test(env = [
+    ("XDG_CONFIG_HOME", ".config/ocd"),
+    ("XDG_DATA_HOME", ".local/share/ocd"),
+])]
+fn node_needs_redeploy() -> Result<()> {
+    let pwd = std::env::current_dir()?;
+    std::env::set_var("HOME", &pwd);
+
+    let git = GitFixture::new(".local/share/ocd/node", GitKind::Bare)?;
+    git.stage_and_commit(".shrc", "set -o vi")?;
+
+    let entry = NodeEntry::builder()?
+        .deployment(DeploymentKind::BareAlias, WorkDirAlias::new(&pwd))
+        .build();
+    let node = Node::new_open("node", &entry)?;
+    node.deploy(DeployAction::Deploy)?;
+    assert!(!node.needs_redeploy(true)?);
+
+    // Move HEAD without touching working directory alias, like a passthrough pull would.
+    let before = node.head_id();
+    git.stage_and_commit(".profile", ". .shrc")?;
+    assert_ne!(node.head_id(), before);
+    assert!(node.needs_redeploy(true)?);
+    assert!(!node.needs_redeploy(false)?);
+
+    Ok(())
+}
+
+#[sealed_test(env = [
+    ("XDG_CONFIG_HOME", ".config/ocd"),
+    ("XDG_DATA_HOME", ".local/share/ocd"),
+])]
+fn node_convert() -> Result<()> {
+    let pwd = std::env::current_dir()?;
+    std::env::set_var("HOME", &pwd);
+
+    let forge = GitFixture::new("forge/vim.git", GitKind::Normal)?;
+    forge.stage_and_commit(".vimrc", "set number")?;
+    run_script!("git -C forge/vim.git tag v1.0")?;
+    let url = format!("{}/forge/vim.git", pwd.display());
+    let normal = NodeEntry::builder()?.url(&url).build();
+    let node = Node::new_open("vim", &normal)?;
+    node.gitcall(["remote", "add", "upstream", "https://some/upstream.git"])?;
+
+    let bare_alias = NodeEntry::builder()?
+        .deployment(DeploymentKind::BareAlias, WorkDirAlias::new(&pwd))
+        .url(&url)
+        .build();
+    let node = node.convert(&bare_alias)?;
+    assert!(node.is_bare_alias());
+    assert!(!node.is_deployed(DeployState::WithoutExcluded)?);
+    node.deploy(DeployAction::Deploy)?;
+    assert!(pwd.join(".vimrc").exists());
+
+    let (_, refs, _) = run_script!("git --git-dir .local/share/ocd/vim show-ref")?;
+    assert!(refs.contains("refs/tags/v1.0"), "{refs}");
+    let (_, remotes, _) = run_script!("git --git-dir .local/share/ocd/vim remote -v")?;
+    assert!(remotes.contains(&url) && remotes.contains("https://some/upstream.git"), "{remotes}");
+    let (_, upstream, _) =
+        run_script!("git --git-dir .local/share/ocd/vim rev-parse --abbrev-ref '@{u}'")?;
+    assert!(upstream.starts_with("origin/"), "{upstream}");
+
+    // Converting back undeploys the node, and checks out its files in the repository store.
+    let node = node.convert(&normal)?;
+    assert!(!node.is_bare_alias());
+    assert!(!pwd.join(".vimrc").exists());
+    assert!(pwd.join(".local/share/ocd/vim/.vimrc").exists());
+    assert!(!pwd.join(".local/share/ocd/vim.convert").exists());
+
+    let hard_link =
+        NodeEntry::builder()?.deployment(DeploymentKind::HardLink, WorkDirAlias::new(&pwd)).build();
+    assert!(node.convert(&hard_link).is_err());
+
+    Ok(())
+}
+
+#[sealed_test(env = [
+    ("XDG_CONFIG_HOME", ".config/ocd"),
+    ("XDG_DATA_HOME", ".local/share/ocd"),
+])]
+fn node_new_adopt() -> Result<()> {
+    let pwd = std::env::current_dir()?;
+    std::env::set_var("HOME", &pwd);
+
+    let git = GitFixture::new("src/nvim", GitKind::Normal)?;
+    git.stage_and_commit("init.lua", "vim.o.number = true")?;
+    run_script!("git -C src/nvim remote add origin https://some/nvim.git")?;
+    write("src/nvim/wip.lua", "-- not committed yet")?;
+    assert_eq!(origin_url(&pwd.join("src/nvim"))?.as_deref(), Some("https://some/nvim.git"));
+
+    // Normal nodes move repository into repository store, uncommitted work included.
+    let normal = NodeEntry::builder()?.url("https://some/nvim.git").build();
+    let node = Node::new_adopt("nvim", &normal, &pwd.join("src/nvim"))?;
+    assert!(!node.is_bare_alias());
+    assert!(!pwd.join("src/nvim").exists());
+    assert!(pwd.join(".local/share/ocd/nvim/wip.lua").exists());
+    assert!(Node::new_adopt("nvim", &normal, &pwd.join(".local/share/ocd/nvim")).is_err());
+
+    // Bare-alias nodes copy repository, leaving it where it is.
+    let git = GitFixture::new("src/bash", GitKind::Normal)?;
+    git.stage_and_commit(".bashrc", "set -o vi")?;
+    assert_eq!(origin_url(&pwd.join("src/bash"))?, None);
+    let bare_alias = NodeEntry::builder()?
+        .deployment(DeploymentKind::BareAlias, WorkDirAlias::new(&pwd))
+        .build();
+    let node = Node::new_adopt("bash", &bare_alias, &pwd.join("src/bash"))?;
+    assert!(node.is_bare_alias());
+    assert!(pwd.join("src/bash/.git").exists());
+    node.deploy(DeployAction::Deploy)?;
+    assert_eq!(std::fs::read_to_string(".bashrc")?, "set -o vi");
+
+    // Nothing is left in repository store for repositories that cannot be adopted.
+    std::fs::create_dir_all("src/empty")?;
+    assert!(Node::new_adopt("empty", &bare_alias, &pwd.join("src/empty")).is_err());
+    assert!(!pwd.join(".local/share/ocd/empty").exists());
+
+    Ok(())
+}
+
+#[sealed_test(env = [
+    ("XDG_CONFIG_HOME", ".config/ocd"),
+    ("XDG_DATA_HOME", ".local/share/ocd"),
+])]
+fn node_prune_history() -> Result<()> {
+    let pwd = std::env::current_dir()?;
+    std::env::set_var("HOME", &pwd);
+
+    let forge = GitFixture::new("forge/vim.git", GitKind::Normal)?;
+    forge.stage_and_commit(".vimrc", "set number")?;
+    run_script!(
+        r#"
+        cd forge/vim.git
+        for year in 2001 2002 2003; do
+            echo "$year" > year
+            git add year
+            GIT_AUTHOR_DATE="$year-01-01T00:00:00" GIT_COMMITTER_DATE="$year-01-01T00:00:00" \
+                git -c user.name=ocd -c user.email=ocd@ocd commit -q -m "$year"
+        done
+        "#
+    )?;
+    let url = format!("{}/forge/vim.git", pwd.display());
+    let entry = NodeEntry::builder()?.url(&url).shallow_since("2002-06-01").build();
+    let node = Node::new_open("vim", &entry)?;
+    assert!(!node.is_shallow());
+
+    node.prune_history(entry.settings.shallow_since.as_deref().unwrap())?;
+    assert!(node.is_shallow());
+    let (_, log, _) = run_script!("git --git-dir .local/share/ocd/vim/.git log --format=%s")?;
+    assert_eq!(log.lines().collect::<Vec<_>>(), vec!["2003"]);
+    node.gc()?;
+
+    // Conversion copies full history, so shallow clones are refused.
+    let bare_alias = NodeEntry::builder()?
+        .deployment(DeploymentKind::BareAlias, WorkDirAlias::new(&pwd))
+        .url(&url)
+        .build();
+    let error = node.convert(&bare_alias).unwrap_err().to_string();
+    assert!(error.contains("--unshallow"), "{error}");
+
+    Ok(())
+}
+
+#[sealed_test(env = [
+    ("XDG_CONFIG_HOME", ".config/ocd"),
+    ("XDG_DATA_HOME", ".local/share/ocd"),
+])]
+fn cluster_multi_entry_fetch() -> Result<()> {
+    let pwd = std::env::current_dir()?;
+    std::env::set_var("HOME", &pwd);
+    write(".gitconfig", "[user]\n    name = ocd\n    email = ocd@ocd\n")?;
+
+    let mut nodes = Vec::new();
+    for name in ["vim", "sh"] {
+        let _forge = GitFixture::new(format!("forge/{name}.git"), GitKind::Normal)?;
+        run_script!(&format!(
+            "cd forge/{name}.git && echo a > a && git add a && git commit -q -m a"
+        ))?;
+        let entry =
+            NodeEntry::builder()?.url(format!("{}/forge/{name}.git", pwd.display())).build();
+        let _ = Node::new_open(name, &entry)?;
+        nodes.push((name, entry));
+    }
+    run_script!(
+        "cd forge/vim.git && echo b > b && git add b && git commit -q -m b && \
+         rm -rf ../sh.git"
+    )?;
+
+    // Failing fetches never keep other entries from being fetched.
+    let mut fetch = MultiEntryFetch::new(Some(2));
+    for (name, entry) in &nodes {
+        fetch = fetch.add_node(*name, entry);
+    }
+    let results = tokio::runtime::Runtime::new()?.block_on(fetch.fetch_all());
+    let results: BTreeMap<String, bool> =
+        results.into_iter().map(|(name, result)| (name, result.is_ok())).collect();
+    assert_eq!(results, BTreeMap::from([("sh".into(), false), ("vim".into(), true)]));
+
+    // Fetched upstreams are integrated without fetching again.
+    let vim = Node::new_open("vim", &nodes[0].1)?;
+    let never = || -> Result<PullStrategy> { panic!("node did not diverge") };
+    assert_eq!(vim.pull_fetched(never)?, PullOutcome::FastForwarded);
+    assert!(pwd.join(".local/share/ocd/vim/b").exists());
+
+    Ok(())
+}
+
+#[sealed_test(env = [
+    ("XDG_CONFIG_HOME", ".config/ocd"),
+    ("XDG_DATA_HOME", ".local/share/ocd"),
+])]
+fn node_pinned_pull() -> Result<()> {
+    let pwd = std::env::current_dir()?;
+    std::env::set_var("HOME", &pwd);
+    write(".gitconfig", "[user]\n    name = ocd\n    email = ocd@ocd\n")?;
+
+    let _forge = GitFixture::new("forge/vim.git", GitKind::Normal)?;
+    run_script!(
+        r#"
+        cd forge/vim.git && echo a > a && git add a && git commit -q -m a && git tag v1
+        git checkout -q -b dev && echo c > c && git add c && git commit -q -m c
+        git checkout -q main && echo b > b && git add b && git commit -q -m b
+        "#
+    )?;
+    let url = format!("{}/forge/vim.git", pwd.display());
+    let never = || -> Result<PullStrategy> { panic!("node did not diverge") };
+
+    // Nodes pinned to a tag are cloned detached at it, and pulls keep them there.
+    let entry = NodeEntry::builder()?.url(&url).pin(NodePin::Tag("v1".into())).build();
+    let node = Node::new_open("vim", &entry)?;
+    assert!(pwd.join(".local/share/ocd/vim/a").exists());
+    assert!(!pwd.join(".local/share/ocd/vim/b").exists());
+    assert_eq!(node.pull(never)?, PullOutcome::UpToDate);
+    run_script!("cd .local/share/ocd/vim && git checkout -q main")?;
+    assert_eq!(node.pull(never)?, PullOutcome::Pinned);
+    assert!(!pwd.join(".local/share/ocd/vim/b").exists());
+
+    // Pinned references that vanished upstream are reported.
+    run_script!("cd forge/vim.git && git tag -d v1 > /dev/null")?;
+    let error = format!("{:#}", node.pull(never).unwrap_err());
+    assert!(error.contains("no longer exists upstream"), "{error}");
+
+    // Nodes pinned to a branch follow it, instead of the default branch.
+    let entry = NodeEntry::builder()?.url(&url).pin(NodePin::Branch("dev".into())).build();
+    let node = Node::new_open("sh", &entry)?;
+    assert!(pwd.join(".local/share/ocd/sh/c").exists());
+    run_script!(
+        "cd forge/vim.git && git checkout -q dev && echo d > d && git add d && git commit -q -m d"
+    )?;
+    assert_eq!(node.pull(never)?, PullOutcome::FastForwarded);
+    assert!(pwd.join(".local/share/ocd/sh/d").exists());
+
+    // Pinned references that never existed fail clones, leaving nothing behind.
+    let entry = NodeEntry::builder()?.url(&url).pin(NodePin::Rev("deadbeef".into())).build();
+    assert!(Node::new_open("bash", &entry).is_err());
+    assert!(!pwd.join(".local/share/ocd/bash").exists());
+
+    Ok(())
+}
+
+#[sealed_test(env = [
+    ("XDG_CONFIG_HOME", ".config/ocd"),
+    ("XDG_DATA_HOME", ".local/share/ocd"),
+])]
+fn node_pull() -> Result<()> {
+    let pwd = std::env::current_dir()?;
+    std::env::set_var("HOME", &pwd);
+    write(".gitconfig", "[user]\n    name = ocd\n    email = ocd@ocd\n")?;
+
+    let _forge = GitFixture::new("forge/vim.git", GitKind::Normal)?;
+    run_script!("cd forge/vim.git && echo a > .vimrc && git add .vimrc && git commit -q -m a")?;
+    let url = format!("{}/forge/vim.git", pwd.display());
+    let entry = NodeEntry::builder()?.url(&url).build();
+    let node = Node::new_open("vim", &entry)?;
+    let never = || -> Result<PullStrategy> { panic!("node did not diverge") };
+    assert_eq!(node.pull(never)?, PullOutcome::UpToDate);
+
+    // Fast-forwards happen in the work tree of the node, never in the current directory.
+    run_script!("cd forge/vim.git && echo b > b && git add b && git commit -q -m b")?;
+    assert_eq!(node.pull(never)?, PullOutcome::FastForwarded);
+    assert!(pwd.join(".local/share/ocd/vim/b").exists());
+    assert!(!pwd.join("b").exists());
+
+    let diverge = |name: &str| {
+        run_script!(&format!(
+            "cd forge/vim.git && echo {name} > up-{name} && git add up-{name} && \
+             git commit -q -m up-{name} && cd ../../.local/share/ocd/vim && echo {name} > \
+             local-{name} && git add local-{name} && git commit -q -m local-{name}"
+        ))
+    };
+    diverge("c")?;
+    assert_eq!(node.pull(|| Ok(PullStrategy::Skip))?, PullOutcome::Diverged);
+    assert!(!pwd.join(".local/share/ocd/vim/up-c").exists());
+    assert_eq!(node.pull(|| Ok(PullStrategy::Rebase))?, PullOutcome::Rebased);
+    assert!(pwd.join(".local/share/ocd/vim/up-c").exists());
+
+    diverge("d")?;
+    assert_eq!(node.pull(|| Ok(PullStrategy::Merge))?, PullOutcome::Merged);
+    assert!(pwd.join(".local/share/ocd/vim/up-d").exists());
+
+    // Conflicts abort the pull, leaving node as it was.
+    run_script!(
+        r#"
+        cd forge/vim.git && echo up > .vimrc && git commit -q -am up
+        cd ../../.local/share/ocd/vim && echo local > .vimrc && git commit -q -am local
+        "#
+    )?;
+    let head = node.head_id();
+    assert!(node.pull(|| Ok(PullStrategy::Merge)).is_err());
+    assert!(node.pull(|| Ok(PullStrategy::Rebase)).is_err());
+    assert_eq!(node.head_id(), head);
+    assert!(!pwd.join(".local/share/ocd/vim/.git/MERGE_HEAD").exists());
+    assert!(!pwd.join(".local/share/ocd/vim/.git/rebase-merge").exists());
+
+    Ok(())
+}
+
+#[sealed_test(env = [
+    ("XDG_CONFIG_HOME", ".config/ocd"),
+    ("XDG_DATA_HOME", ".local/share/ocd"),
+])]
+fn deploy_preflight() -> Result<()> {
+    let pwd = std::env::current_dir()?;
+    std::env::set_var("HOME", &pwd);
+
+    let git = GitFixture::new(".local/share/ocd/vim", GitKind::Bare)?;
+    git.stage_and_commit(".vimrc", "set number\n")?;
+    git.stage_and_commit("README.md", "Introduce newcomers.\n")?;
+    let entry = NodeEntry::builder()?
+        .deployment(DeploymentKind::BareAlias, WorkDirAlias::new(&pwd))
+        .excluded(["README*"])
+        .build();
+    let node = Node::new_open("vim", &entry)?;
+
+    let mut preflight = DeployPreflight::new();
+    preflight.add_node(&node, DeployAction::Deploy)?;
+    assert_eq!(preflight.needed_bytes(), 11);
+    preflight.check()?;
+
+    let mut preflight = DeployPreflight::new();
+    preflight.add_node(&node, DeployAction::DeployAll)?;
+    assert_eq!(preflight.needed_bytes(), 32);
+
+    // Files already deployed need nothing.
+    node.deploy(DeployAction::Deploy)?;
+    let mut preflight = DeployPreflight::new();
+    preflight.add_node(&node, DeployAction::Deploy)?;
+    preflight.add_node(&node, DeployAction::Undeploy)?;
+    assert_eq!(preflight.needed_bytes(), 0);
+    preflight.check()?;
+
+    Ok(())
+}
+
+#[sealed_test(env = [
+    ("XDG_CONFIG_HOME", ".config/ocd"),
+    ("XDG_DATA_HOME", ".local/share/ocd"),
+])]
+fn node_prune_artifacts() -> Result<()> {
+    let pwd = std::env::current_dir()?;
+    std::env::set_var("HOME", &pwd);
+
+    let git = GitFixture::new(".local/share/ocd/vim", GitKind::Bare)?;
+    git.stage_and_commit(".vim/colors/theme.vim", "colorscheme default\n")?;
+    git.stage_and_commit(".vimrc", "set number\n")?;
+    let vim = NodeEntry::builder()?
+        .deployment(DeploymentKind::BareAlias, WorkDirAlias::new(&pwd))
+        .build();
+    Node::new_open("vim", &vim)?.deploy(DeployAction::Deploy)?;
+
+    let git = GitFixture::new(".local/share/ocd/sh", GitKind::Bare)?;
+    git.stage_and_commit(".vimrc", "set number\n")?;
+    git.stage_and_commit(".bashrc", "set -o vi\n")?;
+    let sh = NodeEntry::builder()?
+        .deployment(DeploymentKind::BareAlias, WorkDirAlias::new(&pwd))
+        .build();
+    Node::new_open("sh", &sh)?.deploy(DeployAction::Deploy)?;
+
+    let mut cluster = Cluster {
+        root: RootEntry::builder()?.build(),
+        nodes: BTreeMap::from([("vim".into(), vim)]),
+    };
+    cluster.nodes.insert("sh".into(), sh);
+    assert!(stale_artifacts(&cluster)?.is_empty());
+
+    // Files that nodes still defined deployed too are never pruned.
+    cluster.nodes.remove("vim");
+    let expect = BTreeMap::from([("vim".to_string(), vec![pwd.join(".vim/colors/theme.vim")])]);
+    assert_eq!(stale_artifacts(&cluster)?, expect);
+    assert_eq!(prune_artifacts(&cluster)?, 1);
+    assert!(!pwd.join(".vim").exists());
+    assert!(pwd.join(".vimrc").exists());
+    assert!(stale_artifacts(&cluster)?.is_empty());
+
+    // Undeployed nodes leave nothing behind.
+    Node::new_open("sh", &cluster.nodes["sh"])?.deploy(DeployAction::Undeploy)?;
+    cluster.nodes.remove("sh");
+    assert!(stale_artifacts(&cluster)?.is_empty());
+
+    Ok(())
+}
+
+#[sealed_test(env = [
+    ("XDG_CONFIG_HOME", ".config"),
+    ("XDG_DATA_HOME", ".local/share"),
+])]
+fn root_staged_config_files() -> Result<()> {
+    let pwd = std::env::current_dir()?;
+    std::env::set_var("HOME", &pwd);
+
+    let _ = GitFixture::new(".local/share/ocd/root", GitKind::Bare)?;
+    let root = Root::new_open(&RootEntry::builder()?.deploy_to_home_dir()?.build())?;
+    std::fs::create_dir_all(".config/ocd/nodes")?;
+    write(".config/ocd/root.toml", "[settings]\nwork_dir_alias = \"home_dir\"\n")?;
+    write(".config/ocd/nodes/vim.toml", "[settings]\ndeployment = \"bare_alias\"\n")?;
+    write(".config/ocd/README.md", "Dotfiles.\n")?;
+    run_script!(
+        "git --git-dir=.local/share/ocd/root --work-tree=. add .config/ocd && \
+         echo 'broken' > .config/ocd/nodes/vim.toml"
+    )?;
+
+    // Staged content counts, not what the working directory alias holds now.
+    let files = root.staged_config_files()?;
+    let paths: Vec<&str> = files.keys().filter_map(|path| path.to_str()).collect();
+    assert_eq!(paths, vec!["nodes/vim.toml", "root.toml"]);
+    assert_eq!(files[Path::new("nodes/vim.toml")], "[settings]\ndeployment = \"bare_alias\"\n");
+
+    Ok(())
+}
+
+#[sealed_test(env = [
+    ("XDG_CONFIG_HOME", ".config/ocd"),
+    ("XDG_DATA_HOME", ".local/share/ocd"),
+])]
+fn node_deployed_files() -> Result<()> {
+    let pwd = std::env::current_dir()?;
+    std::fs::create_dir_all(".config/ocd")?;
+    std::env::set_var("HOME", &pwd);
+
+    let root = GitFixture::new(".local/share/ocd/root", GitKind::Bare)?;
+    root.stage_and_commit("root.toml", "[settings]\nwork_dir_alias = \"config_dir\"\n")?;
+    write(".config/ocd/root.toml", "[settings]\nwork_dir_alias = \"config_dir\"\n")?;
+    let root = Root::new_open(&RootEntry::builder()?.build())?;
+    assert_eq!(root.deployed_files()?, vec![pwd.join(".config/ocd/root.toml")]);
+
+    let git = GitFixture::new(".local/share/ocd/vim", GitKind::Bare)?;
+    git.stage_and_commit(".vimrc", "set number")?;
+    git.stage_and_commit("README.md", "Introduce newcomers.")?;
+    let entry = NodeEntry::builder()?
+        .deployment(DeploymentKind::BareAlias, WorkDirAlias::new(&pwd))
+        .excluded(["README*"])
+        .build();
+    let node = Node::new_open("vim", &entry)?;
+    assert!(node.deployed_files()?.is_empty());
+    node.deploy(DeployAction::Deploy)?;
+    assert_eq!(node.deployed_files()?, vec![pwd.join(".vimrc")]);
+
+    let _ = GitFixture::new(".local/share/ocd/sh", GitKind::Normal)?;
+    let node = Node::new_open("sh", &NodeEntry::builder()?.build())?;
+    assert!(node.deployed_files()?.is_empty());
+
+    // Root only ever gets undeployed when forced to.
+    root.deploy(DeployAction::Undeploy)?;
+    assert!(pwd.join(".config/ocd/root.toml").exists());
+    root.force_undeploy()?;
+    assert!(!pwd.join(".config/ocd/root.toml").exists());
+    assert!(root.deployed_files()?.is_empty());
+
+    Ok(())
+}
+
+#[sealed_test(env = [
+    ("XDG_CONFIG_HOME", ".config/ocd"),
+    ("XDG_DATA_HOME", ".local/share/ocd"),
+])]
+fn node_checkout_commit() -> Result<()> {
+    let pwd = std::env::current_dir()?;
+    std::fs::create_dir_all(".config/ocd")?;
+    std::env::set_var("HOME", &pwd);
+
+    let forge = GitFixture::new("forge/vim.git", GitKind::Normal)?;
+    forge.stage_and_commit(".vimrc", "set number")?;
+    let (_, frozen, _) = run_script!("git -C forge/vim.git rev-parse HEAD")?;
+    let frozen = frozen.trim();
+    let url = format!("{}/forge/vim.git", pwd.display());
+    let entry = NodeEntry::builder()?
+        .deployment(DeploymentKind::BareAlias, WorkDirAlias::new(&pwd))
+        .url(&url)
+        .build();
+    let node = Node::new_open("vim", &entry)?;
+    node.deploy(DeployAction::Deploy)?;
+    let branch = node.current_branch()?;
+
+    // Commits missing from node are fetched from origin.
+    forge.stage_and_commit(".vimrc", "set relativenumber")?;
+    node.gitcall(["pull", "--quiet", "origin", branch.as_str()])?;
+    assert_eq!(std::fs::read_to_string(".vimrc")?, "set relativenumber");
+    assert!(node.checkout_commit(frozen)?);
+    assert!(!node.checkout_commit(frozen)?);
+    assert!(node.is_detached());
+    assert_eq!(std::fs::read_to_string(".vimrc")?, "set number");
+    assert!(node.checkout_commit("0000000000000000000000000000000000000000").is_err());
+
+    node.checkout_branch(&branch)?;
+    assert!(!node.is_detached());
+    assert_eq!(std::fs::read_to_string(".vimrc")?, "set relativenumber");
+
+    // Root only commits files that actually changed.
+    let root = GitFixture::new(".local/share/ocd/root", GitKind::Bare)?;
+    root.stage_and_commit("root.toml", "[settings]\nwork_dir_alias = \"config_dir\"\n")?;
+    write(".config/ocd/root.toml", "[settings]\nwork_dir_alias = \"config_dir\"\n")?;
+    let root = Root::new_open(&RootEntry::builder()?.build())?;
+    let lockfile = pwd.join(".config/ocd/cluster.lock");
+    let before = root.head_id();
+    write(&lockfile, format!("[nodes.vim]\ncommit = \"{frozen}\"\n"))?;
+    assert!(root.commit_file(&lockfile, "Freeze vim")?);
+    assert!(!root.commit_file(&lockfile, "Freeze vim")?);
+    std::fs::remove_file(&lockfile)?;
+    assert!(root.commit_file(&lockfile, "Thaw vim")?);
+    let (_, log, _) = run_script!("git --git-dir .local/share/ocd/root log --format=%s")?;
+    assert_eq!(log.lines().take(2).collect::<Vec<_>>(), vec!["Thaw vim", "Freeze vim"]);
+    assert_ne!(root.head_id(), before);
+
+    Ok(())
+}
+
+#[sealed_test(env = [
+    ("XDG_CONFIG_HOME", ".config/ocd"),
+    ("XDG_DATA_HOME", ".local/share/ocd"),
+])]
+fn node_rollback() -> Result<()> {
+    let pwd = std::env::current_dir()?;
+    std::env::set_var("HOME", &pwd);
+
+    let forge = GitFixture::new("forge/vim.git", GitKind::Normal)?;
+    forge.stage_and_commit(".vimrc", "set number")?;
+    let url = format!("{}/forge/vim.git", pwd.display());
+    let entry = NodeEntry::builder()?
+        .deployment(DeploymentKind::BareAlias, WorkDirAlias::new(&pwd))
+        .url(&url)
+        .build();
+    let node = Node::new_open("vim", &entry)?;
+    let branch = node.current_branch()?;
+    assert!(node.rollback().is_err());
+    node.deploy(DeployAction::Deploy)?;
+    let first = node.head_id();
+
+    forge.stage_and_commit(".vimrc", "set relativenumber")?;
+    node.gitcall(["pull", "--quiet", "origin", branch.as_str()])?;
+    node.deploy(DeployAction::Deploy)?;
+    let second = node.head_id();
+
+    // Pulls that were never deployed go back to the last deployment.
+    forge.stage_and_commit(".vimrc", "set nonumber")?;
+    node.gitcall(["pull", "--quiet", "origin", branch.as_str()])?;
+    assert_eq!(std::fs::read_to_string(".vimrc")?, "set nonumber");
+    assert_eq!(Some(node.rollback()?), second);
+    assert_eq!(std::fs::read_to_string(".vimrc")?, "set relativenumber");
+    assert_eq!(node.deployment_history()?, vec![second.clone().unwrap(), first.clone().unwrap()]);
+
+    // Rolling back again goes further back, until nothing is left.
+    assert_eq!(Some(node.rollback()?), first);
+    assert!(node.is_detached());
+    assert_eq!(std::fs::read_to_string(".vimrc")?, "set number");
+    assert!(node.rollback().is_err());
+
+    Ok(())
+}
+
+#[sealed_test(env = [
+    ("XDG_CONFIG_HOME", ".config/ocd"),
+    ("XDG_DATA_HOME", ".local/share/ocd"),
+])]
+fn node_sparse_checkout_state() -> Result<()> {
+    let pwd = std::env::current_dir()?;
+    std::env::set_var("HOME", &pwd);
+
+    let git = GitFixture::new(".local/share/ocd/node", GitKind::Bare)?;
+    git.stage_and_commit(".shrc", "set -o vi")?;
+    let entry = NodeEntry::builder()?
+        .deployment(DeploymentKind::BareAlias, WorkDirAlias::new(&pwd))
+        .excluded(["README*"])
+        .build();
+    let mut node = Node::new_open("node", &entry)?;
+    assert_eq!(node.sparse_checkout_state()?, SparseCheckoutState::Missing);
+    node.deploy(DeployAction::Deploy)?;
+    assert_eq!(node.sparse_checkout_state()?, SparseCheckoutState::Managed);
+
+    let path = ".local/share/ocd/node/info/sparse-checkout";
+    let data = std::fs::read_to_string(path)?;
+    assert!(data.starts_with("# managed by ocd (hash: "), "{data}");
+    assert!(data.ends_with(")\n/*\n!README*\n"), "{data}");
+
+    // Hand edits are drifted, but still managed by OCD.
+    write(path, data.replace("!README*", "!LICENSE"))?;
+    assert_eq!(node.sparse_checkout_state()?, SparseCheckoutState::Drifted);
+    node.deploy(DeployAction::Undeploy)?;
+    assert_eq!(node.sparse_checkout_state()?, SparseCheckoutState::Managed);
+
+    // Files of older versions are recognized by their shape.
+    write(path, "/*\n!README*\n")?;
+    assert_eq!(node.sparse_checkout_state()?, SparseCheckoutState::Managed);
+
+    write(path, "/.shrc\n")?;
+    assert_eq!(node.sparse_checkout_state()?, SparseCheckoutState::Foreign);
+    assert!(node.deploy(DeployAction::Deploy).is_err());
+    assert_eq!(std::fs::read_to_string(path)?, "/.shrc\n");
+    node.set_force(true);
+    node.deploy(DeployAction::Deploy)?;
+    assert_eq!(node.sparse_checkout_state()?, SparseCheckoutState::Managed);
+
+    Ok(())
+}
+
+#[sealed_test(env = [
+    ("XDG_CONFIG_HOME", ".config/ocd"),
+    ("XDG_DATA_HOME", ".local/share/ocd"),
+])]
+fn node_deployment_status() -> Result<()> {
+    let pwd = std::env::current_dir()?;
+    std::env::set_var("HOME", &pwd);
+
+    let git = GitFixture::new(".local/share/ocd/node", GitKind::Bare)?;
+    git.stage_and_commit(".shrc", "set -o vi")?;
+    git.stage_and_commit(".profile", ". .shrc")?;
+    git.stage_and_commit("README.md", "Introduce newcomers.")?;
+
+    let entry = NodeEntry::builder()?
+        .deployment(DeploymentKind::BareAlias, WorkDirAlias::new(&pwd))
+        .excluded(["README*"])
+        .build();
+    let node = Node::new_open("node", &entry)?;
+    let status = node.deployment_status(DeployState::WithoutExcluded)?;
+    assert_eq!(status.state, DeploymentState::Undeployed);
+    assert_eq!(status.to_string(), "undeployed: 2 files missing (.profile, .shrc)");
+
+    node.deploy(DeployAction::Deploy)?;
+    let status = node.deployment_status(DeployState::WithoutExcluded)?;
+    assert_eq!(status.state, DeploymentState::Deployed);
+    assert_eq!(status.to_string(), "deployed");
+    let status = node.deployment_status(DeployState::WithExcluded)?;
+    assert_eq!(status.state, DeploymentState::PartiallyDeployed);
+    assert_eq!(status.missing, vec![std::path::PathBuf::from("README.md")]);
+
+    std::fs::remove_file(".profile")?;
+    write(".shrc", "set -o emacs")?;
+    let status = node.deployment_status(DeployState::WithoutExcluded)?;
+    assert_eq!(status.state, DeploymentState::PartiallyDeployed);
+    assert_eq!(
+        status.to_string(),
+        "partially deployed: 1 file missing (.profile), 1 file modified (.shrc)"
+    );
+    assert!(!node.is_deployed(DeployState::WithoutExcluded)?);
+
+    Ok(())
+}
+
+#[sealed_test(env = [
+    ("XDG_CONFIG_HOME", ".config/ocd"),
+    ("XDG_DATA_HOME", ".local/share/ocd"),
+])]
+fn node_refresh() -> Result<()> {
+    let pwd = std::env::current_dir()?;
+    std::env::set_var("HOME", &pwd);
+
+    let git = GitFixture::new(".local/share/ocd/node", GitKind::Bare)?;
+    git.stage_and_commit(".shrc", "set -o vi")?;
+    git.stage_and_commit("README.md", "Introduce newcomers.")?;
+
+    let entry = NodeEntry::builder()?
+        .deployment(DeploymentKind::BareAlias, WorkDirAlias::new(&pwd))
+        .excluded(["README*"])
+        .build();
+    Node::new_open("node", &entry)?.deploy(DeployAction::Deploy)?;
+    assert!(pwd.join(".shrc").exists());
+    assert!(!pwd.join("README.md").exists());
+
+    let entry = NodeEntry::builder()?
+        .deployment(DeploymentKind::BareAlias, WorkDirAlias::new(&pwd))
+        .excluded([".shrc"])
+        .build();
+    let node = Node::new_open("node", &entry)?;
+    node.refresh()?;
+    assert!(!pwd.join(".shrc").exists());
+    assert!(pwd.join("README.md").exists());
+    assert!(node.is_deployed(DeployState::WithoutExcluded)?);
+
+    Ok(())
+}
+
+#[sealed_test(env = [
+    ("XDG_CONFIG_HOME", ".config/ocd"),
+    ("XDG_DATA_HOME", ".local/share/ocd"),
+])]
+fn node_remote_health() -> Result<()> {
+    let pwd = std::env::current_dir()?;
+    std::env::set_var("HOME", &pwd);
+
+    let forge = GitFixture::new("forge/vim.git", GitKind::Bare)?;
+    forge.stage_and_commit(".vimrc", "set number")?;
+    let url = format!("{}/forge/vim.git", pwd.display());
+    let entry = NodeEntry::builder()?.url(&url).build();
+    let node = Node::new_open("vim", &entry)?;
+    assert_eq!(node.remote_health(), RemoteHealth::Matches);
+
+    run_script!(r#"git -C .local/share/ocd/vim remote set-url origin https://some/fork.git"#)?;
+    assert_eq!(node.remote_health(), RemoteHealth::Diverged("https://some/fork.git".into()));
+    node.repair_origin()?;
+    assert_eq!(node.remote_health(), RemoteHealth::Matches);
+
+    let spelling = format!("{}/forge/vim/", pwd.display());
+    run_script!(&format!("git -C .local/share/ocd/vim remote set-url origin {spelling}"))?;
+    assert_eq!(node.remote_health(), RemoteHealth::Matches);
+
+    run_script!(r#"git -C .local/share/ocd/vim remote remove origin"#)?;
+    assert_eq!(node.remote_health(), RemoteHealth::Missing);
+    node.repair_origin()?;
+    assert_eq!(node.remote_health(), RemoteHealth::Matches);
+
+    Ok(())
+}
+
+#[sealed_test(env = [
+    ("XDG_CONFIG_HOME", ".config/ocd"),
+    ("XDG_DATA_HOME", ".local/share/ocd"),
+])]
+fn root_set_origin() -> Result<()> {
+    let pwd = std::env::current_dir()?;
+    std::env::set_var("HOME", &pwd);
+
+    let forge = GitFixture::new("forge/dots.git", GitKind::Bare)?;
+    forge.stage_and_commit(".shrc", "set -o vi")?;
+    let url = format!("{}/forge/dots.git", pwd.display());
+    check_remote(&url)?;
+    assert!(check_remote(&format!("{}/missing/dots.git", pwd.display())).is_err());
+
+    let root = Root::new_init(&RootEntry::try_default()?)?;
+    assert_eq!(root.origin_url(), None);
+    root.set_origin(&url)?;
+    assert_eq!(root.origin_url(), Some(url.clone()));
+    root.set_origin("https://new/dots.git")?;
+    assert_eq!(root.origin_url(), Some("https://new/dots.git".into()));
+
+    Ok(())
+}
+
+#[sealed_test(env = [
+    ("XDG_CONFIG_HOME", ".config/ocd"),
+    ("XDG_DATA_HOME", ".local/share/ocd"),
+])]
+fn tablize_cluster_fancy_fetch() -> Result<()> {
+    let pwd = std::env::current_dir()?;
+    std::fs::create_dir_all(".config/ocd/nodes")?;
+    std::env::set_var("HOME", &pwd);
+
+    let root = GitFixture::new(".local/share/ocd/root", GitKind::Bare)?;
+    root.stage_and_commit("root.toml", "[settings]\nwork_dir_alias = \"config_dir\"\n")?;
+    write(".config/ocd/root.toml", "[settings]\nwork_dir_alias = \"config_dir\"\n")?;
+    for name in ["vim", "bash", "sh"] {
+        let forge = GitFixture::new(format!("forge/{name}.git"), GitKind::Normal)?;
+        forge.stage_and_commit("README.md", "Introduce newcomers.")?;
+        write(
+            format!(".config/ocd/nodes/{name}.toml"),
+            format!(
+                "[settings]\ndeployment = \"normal\"\nurl = \"{}/forge/{name}.git\"\n",
+                pwd.display()
+            ),
+        )?;
+    }
+
+    let cluster = Cluster::new()?;
+    let root = Root::new_open(&cluster.root)?;
+    let tablize = TablizeCluster::new(&root, &cluster);
+    tokio::runtime::Runtime::new()?.block_on(tablize.fancy(Some(2), true))?;
+    for name in ["vim", "bash", "sh"] {
+        assert!(pwd.join(".local/share/ocd").join(name).exists());
+    }
+
+    Ok(())
+}
+
+#[sealed_test(env = [
+    ("XDG_CONFIG_HOME", ".config/ocd"),
+    ("XDG_DATA_HOME", ".local/share/ocd"),
+])]
+fn tablize_cluster_never_clones() -> Result<()> {
+    let pwd = std::env::current_dir()?;
+    std::fs::create_dir_all(".config/ocd/nodes")?;
+    std::env::set_var("HOME", &pwd);
+
+    let root = GitFixture::new(".local/share/ocd/root", GitKind::Bare)?;
+    root.stage_and_commit("root.toml", "[settings]\nwork_dir_alias = \"config_dir\"\n")?;
+    write(".config/ocd/root.toml", "[settings]\nwork_dir_alias = \"config_dir\"\n")?;
+    for name in ["vim", "bash"] {
+        let forge = GitFixture::new(format!("forge/{name}.git"), GitKind::Normal)?;
+        forge.stage_and_commit("README.md", "Introduce newcomers.")?;
+        write(
+            format!(".config/ocd/nodes/{name}.toml"),
+            format!(
+                "[settings]\ndeployment = \"normal\"\nurl = \"{}/forge/{name}.git\"\n",
+                pwd.display()
+            ),
+        )?;
+    }
+
+    let cluster = Cluster::new()?;
+    let root = Root::new_open(&cluster.root)?;
+    let tablize = TablizeCluster::new(&root, &cluster);
+    tablize.names_only()?;
+    tokio::runtime::Runtime::new()?.block_on(tablize.fancy(Some(2), false))?;
+    for name in ["vim", "bash"] {
+        assert!(!pwd.join(".local/share/ocd").join(name).exists());
+    }
+
+    Ok(())
+}
+
+#[sealed_test(env = [
+    ("XDG_CONFIG_HOME", ".config/ocd"),
+    ("XDG_DATA_HOME", ".local/share/ocd"),
+])]
+fn node_untracked_near_tracked() -> Result<()> {
+    let pwd = std::env::current_dir()?;
+    std::fs::create_dir_all(".config/ocd/nodes")?;
+    std::env::set_var("HOME", &pwd);
+
+    write(".config/ocd/root.toml", "[settings]\nwork_dir_alias = \"config_dir\"\n")?;
+    let git = GitFixture::new(".local/share/ocd/vim", GitKind::Bare)?;
+    git.stage_and_commit(".vimrc", "set nocompatible")?;
+    git.stage_and_commit(".vim/plugins.vim", "call plug#begin()")?;
+    write(
+        ".config/ocd/nodes/vim.toml",
+        "[settings]\ndeployment = { kind = \"bare_alias\", work_dir_alias = \"$HOME\" }\n",
+    )?;
+
+    let cluster = Cluster::new()?;
+    let node = Node::new_open("vim", &cluster.nodes["vim"])?;
+    node.deploy(DeployAction::Deploy)?;
+
+    std::fs::create_dir_all(".vim/undo")?;
+    std::fs::create_dir_all("elsewhere")?;
+    write(".viminfo", "# viminfo")?;
+    write(".vim/colors.vim", "colorscheme default")?;
+    write(".vim/plugins.vim.swp", "swap")?;
+    write(".vim/undo/plugins.vim", "undo")?;
+    write("elsewhere/notes.vim", "notes")?;
+    write(".local/share/ocd/vim/info/exclude", "*.swp\n")?;
+
+    // Only files next to tracked files that are not ignored get suggested.
+    let expect = vec![std::path::PathBuf::from(".vim/colors.vim"), ".viminfo".into()];
+    assert_eq!(node.untracked_near_tracked()?, expect);
+
+    Ok(())
+}
+
+#[sealed_test(env = [
+    ("XDG_CONFIG_HOME", ".config/ocd"),
+    ("XDG_DATA_HOME", ".local/share/ocd"),
+])]
+fn node_worktree_status() -> Result<()> {
+    let pwd = std::env::current_dir()?;
+    std::fs::create_dir_all(".config/ocd/nodes")?;
+    std::env::set_var("HOME", &pwd);
+
+    write(".config/ocd/root.toml", "[settings]\nwork_dir_alias = \"config_dir\"\n")?;
+    let git = GitFixture::new(".local/share/ocd/vim", GitKind::Bare)?;
+    git.stage_and_commit(".vimrc", "set nocompatible")?;
+    git.stage_and_commit(".vim/plugins.vim", "call plug#begin()")?;
+    write(
+        ".config/ocd/nodes/vim.toml",
+        "[settings]\ndeployment = { kind = \"bare_alias\", work_dir_alias = \"$HOME\" }\n",
+    )?;
+
+    let cluster = Cluster::new()?;
+    let node = Node::new_open("vim", &cluster.nodes["vim"])?;
+    node.deploy(DeployAction::Deploy)?;
+    let status = node.worktree_status()?;
+    assert!(status.is_clean(), "{status:?}");
+    assert_eq!(status.short(), "---");
+
+    write(".vimrc", "set compatible")?;
+    write(".vim/colors.vim", "colorscheme default")?;
+    let status = node.worktree_status()?;
+    assert!(!status.is_clean());
+    assert_eq!(status.short(), "-M?");
+
+    node.gitcall(["add", ".vimrc"])?;
+    assert_eq!(node.worktree_status()?.short(), "S-?");
+
+    Ok(())
+}
+
+#[sealed_test(env = [
+    ("XDG_CONFIG_HOME", ".config/ocd"),
+    ("XDG_DATA_HOME", ".local/share/ocd"),
+])]
+fn node_clean_untracked() -> Result<()> {
+    let pwd = std::env::current_dir()?;
+    std::fs::create_dir_all(".config/ocd/nodes")?;
+    std::env::set_var("HOME", &pwd);
+
+    write(".config/ocd/root.toml", "[settings]\nwork_dir_alias = \"config_dir\"\n")?;
+    let git = GitFixture::new(".local/share/ocd/vim", GitKind::Bare)?;
+    git.stage_and_commit(".vimrc", "set nocompatible")?;
+    git.stage_and_commit(".vim/plugins.vim", "call plug#begin()")?;
+    write(
+        ".config/ocd/nodes/vim.toml",
+        "[settings]\ndeployment = { kind = \"bare_alias\", work_dir_alias = \"$HOME\" }\n",
+    )?;
+
+    let cluster = Cluster::new()?;
+    let node = Node::new_open("vim", &cluster.nodes["vim"])?;
+    node.deploy(DeployAction::Deploy)?;
+
+    std::fs::create_dir_all(".vim/undo")?;
+    write(".viminfo", "# viminfo")?;
+    write(".vim/colors.vim", "colorscheme default")?;
+    write(".vim/undo/plugins.vim", "undo")?;
+
+    // Files directly in home directory are never cleaned.
+    let expect = vec![std::path::PathBuf::from(".vim/colors.vim")];
+    assert_eq!(node.cleanable_untracked()?, expect);
+    assert_eq!(node.clean_untracked()?, expect);
+    assert!(!pwd.join(".vim/colors.vim").exists());
+    assert!(pwd.join(".viminfo").exists());
+    assert!(pwd.join(".vim/undo/plugins.vim").exists());
+    assert!(pwd.join(".vim/plugins.vim").exists());
+
+    Ok(())
+}
+
+#[sealed_test(env = [
+    ("XDG_CONFIG_HOME", ".config/ocd"),
+    ("XDG_DATA_HOME", ".local/share/ocd"),
+])]
+fn node_gitcall_missing_git_binary() -> Result<()> {
+    let pwd = std::env::current_dir()?;
+    std::fs::create_dir_all(".config/ocd/nodes")?;
+    std::env::set_var("HOME", &pwd);
+    write(".config/ocd/root.toml", "[settings]\nwork_dir_alias = \"config_dir\"\n")?;
+    write(".config/ocd/nodes/vim.toml", "[settings]\ndeployment = \"normal\"\n")?;
+    let _ = GitFixture::new(".local/share/ocd/vim", GitKind::Normal)?;
+    let cluster = Cluster::new()?;
+    let node = Node::new_open("vim", &cluster.nodes["vim"])?;
+
+    assert!(has_git_binary());
+    std::fs::create_dir_all("empty")?;
+    std::env::set_var("PATH", pwd.join("empty"));
+    assert!(!has_git_binary());
+
+    // Missing Git binary comes with installation guidance, both up front and on first use.
+    let error = require_git_binary().unwrap_err().to_string();
+    assert!(error.contains("install git"));
+    let error = node.gitcall(["status"]).unwrap_err().to_string();
+    assert!(error.contains("install git"));
+
+    Ok(())
+}
+
+#[sealed_test(env = [
+    ("XDG_CONFIG_HOME", ".config/ocd"),
+    ("XDG_DATA_HOME", ".local/share/ocd"),
+])]
+fn cluster_write_deploy_report() -> Result<()> {
+    let pwd = std::env::current_dir()?;
+    std::fs::create_dir_all(".config/ocd/nodes")?;
+    std::env::set_var("HOME", &pwd);
+
+    let root = GitFixture::new(".local/share/ocd/root", GitKind::Bare)?;
+    root.stage_and_commit("root.toml", "[settings]\nwork_dir_alias = \"config_dir\"\n")?;
+    write(".config/ocd/root.toml", "[settings]\nwork_dir_alias = \"config_dir\"\n")?;
+    for name in ["vim", "sh"] {
+        let git = GitFixture::new(format!(".local/share/ocd/{name}"), GitKind::Bare)?;
+        git.stage_and_commit(format!(".{name}rc"), "set -o vi")?;
+        write(
+            format!(".config/ocd/nodes/{name}.toml"),
+            "[settings]\ndeployment = { kind = \"bare_alias\", work_dir_alias = \"$HOME\" }\n",
+        )?;
+    }
+
+    let cluster = Cluster::new()?;
+    Node::new_open("vim", &cluster.nodes["vim"])?.deploy(DeployAction::Deploy)?;
+    write_deploy_report(&cluster, "$HOME/.config/ocd/deployed.toml")?;
+
+    let data = std::fs::read_to_string(".config/ocd/deployed.toml")?;
+    assert!(data.starts_with("# Generated by ocd"), "{data}");
+    assert!(data.contains("[entries.root]"), "{data}");
+    assert!(data.contains("[entries.vim]\ndeployment = \"bare-alias\""), "{data}");
+    assert!(!data.contains("[entries.sh]"), "{data}");
+
+    // Report is never picked up by entries whose working directory alias houses it.
+    let exclude = std::fs::read_to_string(".local/share/ocd/root/info/exclude")?;
+    assert!(exclude.lines().any(|line| line == "/deployed.toml"), "{exclude}");
+    let exclude = std::fs::read_to_string(".local/share/ocd/vim/info/exclude")?;
+    assert!(exclude.lines().any(|line| line == "/.config/ocd/deployed.toml"), "{exclude}");
+    write_deploy_report(&cluster, "$HOME/.config/ocd/deployed.toml")?;
+    let exclude = std::fs::read_to_string(".local/share/ocd/root/info/exclude")?;
+    assert_eq!(exclude.matches("/deployed.toml").count(), 1, "{exclude}");
+
+    Ok(())
+}
+
+#[sealed_test(env = [
+    ("XDG_CONFIG_HOME", ".config/ocd"),
+    ("XDG_DATA_HOME", ".local/share/ocd"),
+])]
+fn node_mirror() -> Result<()> {
+    let pwd = std::env::current_dir()?;
+    std::fs::create_dir_all(".config/ocd/nodes")?;
+    std::env::set_var("HOME", &pwd);
+
+    let root = GitFixture::new(".local/share/ocd/root", GitKind::Bare)?;
+    root.stage_and_commit("root.toml", "[settings]\nwork_dir_alias = \"config_dir\"\n")?;
+    write(".config/ocd/root.toml", "[settings]\nwork_dir_alias = \"config_dir\"\n")?;
+    let forge = GitFixture::new("forge/plugin.git", GitKind::Normal)?;
+    forge.stage_and_commit("plugin.vim", "set number")?;
+    run_script!("git -C forge/plugin.git tag v1.0")?;
+    write(
+        ".config/ocd/nodes/plugin.toml",
+        format!(
+            "[settings]\ndeployment = \"mirror\"\nurl = \"{}/forge/plugin.git\"\n",
+            pwd.display()
+        ),
+    )?;
+
+    let cluster = Cluster::new()?;
+    let node = Node::new_open("plugin", &cluster.nodes["plugin"])?;
+    node.deploy(DeployAction::Deploy)?;
+    assert!(!node.is_deployed(DeployState::WithoutExcluded)?);
+    assert!(!pwd.join("plugin.vim").exists());
+
+    let (_, refs, _) = run_script!("git --git-dir .local/share/ocd/plugin show-ref")?;
+    assert!(refs.contains("refs/tags/v1.0"), "{refs}");
+    assert!(!refs.contains("refs/remotes/"), "{refs}");
+
+    // Mirrors pick up new references when fetched.
+    run_script!("git -C forge/plugin.git tag v2.0")?;
+    let root = Root::new_open(&cluster.root)?;
+    let tablize = TablizeCluster::new(&root, &cluster);
+    tokio::runtime::Runtime::new()?.block_on(tablize.fancy(Some(1), true))?;
+    let (_, refs, _) = run_script!("git --git-dir .local/share/ocd/plugin show-ref")?;
+    assert!(refs.contains("refs/tags/v2.0"), "{refs}");
+
+    Ok(())
+}
+
+#[sealed_test(env = [
+    ("XDG_CONFIG_HOME", ".config/ocd"),
+    ("XDG_DATA_HOME", ".local/share/ocd"),
+])]
+fn node_nested_cluster() -> Result<()> {
+    let pwd = std::env::current_dir()?;
+    std::fs::create_dir_all(".config/ocd/nodes")?;
+    std::env::set_var("HOME", &pwd);
+
+    write(".config/ocd/root.toml", "[settings]\nwork_dir_alias = \"home_dir\"\n")?;
+    let forge = GitFixture::new("forge/nvim.git", GitKind::Normal)?;
+    forge.stage_and_commit("init.lua", "vim.o.number = true")?;
+    let forge = GitFixture::new("forge/work.git", GitKind::Normal)?;
+    forge.stage_and_commit("root.toml", "[settings]\nwork_dir_alias = \"home_dir\"\n")?;
+    forge.stage_and_commit(
+        "nodes/nvim.toml",
+        format!(
+            "[settings]\ndeployment = \"normal\"\nurl = \"{}/forge/nvim.git\"\n",
+            pwd.display()
+        ),
+    )?;
+    write(
+        ".config/ocd/nodes/work.toml",
+        format!(
+            "[settings]\ndeployment = \"cluster\"\nurl = \"{}/forge/work.git\"\n",
+            pwd.display()
+        ),
+    )?;
+
+    // Should clone root of nested cluster, and deploy its definition right away.
+    let cluster = Cluster::new()?;
+    assert_eq!(cluster.nodes.len(), 1);
+    let node = Node::new_open("work", &cluster.nodes["work"])?;
+    assert!(pwd.join(".local/share/ocd/work/root").exists());
+    assert!(pwd.join(".config/ocd/clusters/work/nodes/nvim.toml").exists());
+    assert!(node.is_deployed(DeployState::WithExcluded)?);
+
+    // Should namespace node entries of nested cluster once cluster is reloaded.
+    let cluster = Cluster::new()?;
+    let _ = Node::new_open("work/nvim", &cluster.nodes["work/nvim"])?;
+    assert!(pwd.join(".local/share/ocd/work/nvim/init.lua").exists());
+
+    Ok(())
+}
+
+#[sealed_test(env = [
+    ("XDG_CONFIG_HOME", ".config/ocd"),
+    ("XDG_DATA_HOME", ".local/share/ocd"),
+    ("XDG_STATE_HOME", ".local/state/ocd"),
+])]
+fn cluster_prompt_segment() -> Result<()> {
+    let pwd = std::env::current_dir()?;
+    std::fs::create_dir_all(".config/ocd/nodes")?;
+    std::env::set_var("HOME", &pwd);
+
+    let root = GitFixture::new(".local/share/ocd/root", GitKind::Bare)?;
+    root.stage_and_commit("root.toml", "[settings]\nwork_dir_alias = \"config_dir\"\n")?;
+    write(".config/ocd/root.toml", "[settings]\nwork_dir_alias = \"config_dir\"\n")?;
+    let git = GitFixture::new(".local/share/ocd/sh", GitKind::Bare)?;
+    git.stage_and_commit(".shrc", "set -o vi")?;
+    write(".config/ocd/nodes/sh.toml", "[settings]\ndeployment = \"bare_alias\"\n")?;
+    write(".config/ocd/nodes/vim.toml", "[settings]\ndeployment = \"bare_alias\"\n")?;
+
+    let cluster = Cluster::new()?;
+    Node::new_open("sh", &cluster.nodes["sh"])?.deploy(DeployAction::Deploy)?;
+    assert_eq!(prompt_segment(&cluster, false)?, "");
+    assert!(!pwd.join(".local/share/ocd/vim").exists());
+
+    // Modifications show up once cached status is refreshed.
+    write(".shrc", "set -o emacs")?;
+    assert_eq!(prompt_segment(&cluster, false)?, "");
+    assert_eq!(prompt_segment(&cluster, true)?, "ocd:1✗");
+
+    Ok(())
+}
+
+#[sealed_test(env = [("XDG_STATE_HOME", ".local/state/ocd")])]
+fn sync_status_round_trip() -> Result<()> {
+    std::env::set_var("HOME", std::env::current_dir()?);
+    assert_eq!(SyncStatus::load()?, None);
+    assert!(!is_sync_daemon_running()?);
+
+    let mut status = SyncStatus::new(Duration::from_secs(600));
+    status.record(SyncTrigger::Config, &Err(anyhow!("Root is missing")));
+    status.save()?;
+
+    let loaded = SyncStatus::load()?.expect("status was saved");
+    assert_eq!(loaded.pid, std::process::id());
+    assert_eq!(loaded.interval, 600);
+    let last = loaded.last.expect("reconcile was recorded");
+    assert_eq!(last.trigger, SyncTrigger::Config);
+    assert_eq!(last.error.as_deref(), Some("Root is missing"));
+    assert!(last.seconds_ago() < 60);
+
+    Ok(())
+}
+
+#[sealed_test(env = [
+    ("XDG_CONFIG_HOME", ".config/ocd"),
+    ("XDG_DATA_HOME", ".local/share/ocd"),
+    ("XDG_STATE_HOME", ".local/state"),
+])]
+fn undo_transaction_reverts_last() -> Result<()> {
+    let pwd = std::env::current_dir()?;
+    std::fs::create_dir_all(".config/ocd/nodes")?;
+    std::fs::create_dir_all("home")?;
+    std::env::set_var("HOME", &pwd);
+
+    let root = GitFixture::new(".local/share/ocd/root", GitKind::Bare)?;
+    root.stage_and_commit("root.toml", "[settings]\nwork_dir_alias = \"config_dir\"\n")?;
+    write(".config/ocd/root.toml", "[settings]\nwork_dir_alias = \"config_dir\"\n")?;
+    let forge = GitFixture::new("forge/vim.git", GitKind::Normal)?;
+    forge.stage_and_commit(".vimrc", "set number")?;
+    write(
+        ".config/ocd/nodes/vim.toml",
+        format!(
+            "[settings]\ndeployment = \"bare_alias:~/home\"\nurl = \"{}/forge/vim.git\"\n",
+            pwd.display()
+        ),
+    )?;
+    let cluster = Cluster::new()?;
+    let node = Node::new_open("vim", &cluster.nodes["vim"])?;
+    assert!(undo_transaction(&cluster)?.is_none());
+
+    // Deployment actions outside of transactions are never recorded.
+    node.deploy(DeployAction::Deploy)?;
+    node.deploy(DeployAction::Undeploy)?;
+    assert!(last_transaction()?.is_none());
+
+    let transaction = begin_transaction("deploy");
+    node.deploy(DeployAction::Deploy)?;
+    node.deploy(DeployAction::Deploy)?;
+    drop(transaction);
+    let last = last_transaction()?.ok_or(anyhow!("Transaction was not recorded"))?;
+    assert_eq!(last.steps.len(), 1);
+    assert_eq!(last.steps[0].placed, vec![pwd.join("home/.vimrc")]);
+
+    let transaction = begin_transaction("undeploy");
+    node.deploy(DeployAction::Undeploy)?;
+    drop(transaction);
+    assert!(!pwd.join("home/.vimrc").exists());
+
+    // Undoing goes back one transaction at a time.
+    assert_eq!(
+        undo_transaction(&cluster)?.map(|undone| undone.command).as_deref(),
+        Some("undeploy")
+    );
+    assert!(node.is_deployed(DeployState::WithoutExcluded)?);
+    assert_eq!(undo_transaction(&cluster)?.map(|undone| undone.command).as_deref(), Some("deploy"));
+    assert!(!pwd.join("home/.vimrc").exists());
+    assert!(undo_transaction(&cluster)?.is_none());
+
+    Ok(())
+}
+
+#[sealed_test(env = [
+    ("XDG_CONFIG_HOME", ".config/ocd"),
+    ("XDG_DATA_HOME", ".local/share/ocd"),
+    ("XDG_STATE_HOME", ".local/state"),
+])]
+fn rename_in_store_keeps_deployment() -> Result<()> {
+    let pwd = std::env::current_dir()?;
+    std::fs::create_dir_all(".config/ocd/nodes")?;
+    std::fs::create_dir_all("home")?;
+    std::env::set_var("HOME", &pwd);
+
+    let root = GitFixture::new(".local/share/ocd/root", GitKind::Bare)?;
+    root.stage_and_commit("root.toml", "[settings]\nwork_dir_alias = \"config_dir\"\n")?;
+    write(".config/ocd/root.toml", "[settings]\nwork_dir_alias = \"config_dir\"\n")?;
+    let forge = GitFixture::new("forge/vim.git", GitKind::Normal)?;
+    forge.stage_and_commit(".vimrc", "set number")?;
+    write(
+        ".config/ocd/nodes/vim.toml",
+        format!(
+            "[settings]\ndeployment = \"bare_alias:~/home\"\nurl = \"{}/forge/vim.git\"\n",
+            pwd.display()
+        ),
+    )?;
+    let mut cluster = Cluster::new()?;
+    Node::new_open("vim", &cluster.nodes["vim"])?.deploy(DeployAction::Deploy)?;
+
+    rename_in_store(&cluster.nodes["vim"], "vim", "nvim")?;
+    cluster.rename_node("vim", "nvim")?;
+    assert!(!pwd.join(".local/share/ocd/vim").exists());
+    assert!(is_node_present("nvim", &cluster.nodes["nvim"])?);
+
+    // Files deployed under the old name belong to the new name.
+    let node = Node::new_open("nvim", &cluster.nodes["nvim"])?;
+    assert!(node.is_deployed(DeployState::WithoutExcluded)?);
+    assert!(stale_artifacts(&cluster)?.is_empty());
+    assert_eq!(node.deployment_history()?.len(), 1);
+
+    Ok(())
+}